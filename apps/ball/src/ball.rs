@@ -41,10 +41,15 @@ impl Ball {
             gotinput_id: None,
             audioframe_id: None,
             focuschange_id: Some(AppOp::FocusChange.to_u32().unwrap()),
+            lifecycle_id: None,
             rawkeys_id: Some(AppOp::Rawkeys.to_u32().unwrap()),
         }).expect("couldn't register Ux context for shellchat");
 
         let gid = gam.request_content_canvas(token.unwrap()).expect("couldn't get content canvas");
+        gam.register_animation(token.unwrap(), AppOp::Pump.to_u32().unwrap(), BALL_UPDATE_RATE_MS)
+            .expect("couldn't register ball animation");
+        // the walls are worked out once, for the upright screen
+        gam.opt_out_of_rotation(token.unwrap()).expect("couldn't opt out of screen rotation");
         let screensize = gam.get_canvas_bounds(gid).expect("couldn't get dimensions of content canvas");
 
         gam.draw_rectangle(gid,
@@ -152,6 +157,15 @@ impl Ball {
                 DrawStyle::new(PixelColor::Light, PixelColor::Dark, BORDER_WIDTH))
         ).expect("couldn't draw our rectangle");
     }
+    /// Sets the mode from its name as typed into the shell. Returns false if there's no such mode.
+    pub(crate) fn set_mode(&mut self, name: &str) -> bool {
+        match name {
+            "random" => self.mode = BallMode::Random,
+            "tilt" => self.mode = BallMode::Tilt,
+            _ => return false,
+        }
+        true
+    }
     pub(crate) fn rawkeys(&mut self, keys: [char; 4]) {
         log::debug!("got rawkey {:?}", keys); // you could use the raw keypresses, but modals are easier...
         let mut note = String::new();
@@ -174,48 +188,3 @@ impl Ball {
         }
     }
 }
-
-pub(crate) fn ball_pump_thread(cid_to_main: xous::CID, pump_sid: xous::SID) {
-    let _ = std::thread::spawn({
-        let cid_to_main = cid_to_main; // kind of redundant but I like making the closure captures explicit
-        let sid = pump_sid;
-        move || {
-            let tt = ticktimer_server::Ticktimer::new().unwrap();
-            let cid_to_self = xous::connect(sid).unwrap();
-            let mut run = true;
-            loop {
-                // this blocks the process until a message is received, descheduling it from the run queue
-                let msg = xous::receive_message(sid).unwrap();
-                match FromPrimitive::from_usize(msg.body.id()) {
-                    Some(PumpOp::Run) => {
-                        run = true;
-                        xous::send_message(
-                            cid_to_self,
-                            Message::new_scalar(PumpOp::Pump.to_usize().unwrap(), 0, 0, 0, 0)
-                        ).expect("couldn't pump the main loop event thread");
-                    },
-                    Some(PumpOp::Stop) => run = false,
-                    Some(PumpOp::Pump) => {
-                        xous::send_message(
-                            cid_to_main,
-                            Message::new_blocking_scalar(AppOp::Pump.to_usize().unwrap(), 0, 0, 0, 0)
-                        ).expect("couldn't pump the main loop event thread");
-                        if run {
-                            tt.sleep_ms(BALL_UPDATE_RATE_MS).unwrap();
-                            xous::send_message(
-                                cid_to_self,
-                                Message::new_scalar(PumpOp::Pump.to_usize().unwrap(), 0, 0, 0, 0)
-                            ).expect("couldn't pump the main loop event thread");
-                        }
-                    }
-                    Some(PumpOp::Quit) => {
-                        xous::return_scalar(msg.sender, 1).expect("couldn't ack the quit message");
-                        break;
-                    }
-                    _ => log::error!("Got unrecognized message: {:?}", msg),
-                }
-            }
-            xous::destroy_server(sid).ok();
-        }
-    });
-}
\ No newline at end of file
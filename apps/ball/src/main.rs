@@ -4,7 +4,6 @@
 mod ball;
 use ball::*;
 use num_traits::*;
-use xous::Message;
 
 // This name should be (1) unique (2) under 64 characters long and (3) ideally descriptive.
 const BALL_SERVER_NAME: &'static str = "User app 'ball'";
@@ -12,7 +11,7 @@ const BALL_SERVER_NAME: &'static str = "User app 'ball'";
 /// Opcodes for the application main loop
 #[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive)]
 pub(crate) enum AppOp {
-    /// pumps the state of the ball
+    /// animation frame from the GAM; pumps the state of the ball
     Pump,
     /// redraw our screen
     Redraw,
@@ -22,18 +21,11 @@ pub(crate) enum AppOp {
     FocusChange,
     /// exit the application
     Quit,
+    /// the `ball` verb typed into the shell, sent as a `shellchat::VerbInvocation`
+    Verb,
 }
 
-const BALL_UPDATE_RATE_MS: usize = 50;
-
-/// Opcodes from the Pump thread loop
-#[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive)]
-pub(crate) enum PumpOp {
-    Run,
-    Stop,
-    Pump,
-    Quit,
-}
+const BALL_UPDATE_RATE_MS: u32 = 50;
 
 #[xous::xous_main]
 fn xmain() -> ! {
@@ -45,14 +37,19 @@ fn xmain() -> ! {
     let sid = xns.register_name(BALL_SERVER_NAME, None).expect("can't register server");
     log::trace!("registered with NS -- {:?}", sid);
 
-    // create the ball object
+    // create the ball object; the GAM sends it Pump frames while it has focus
     let mut ball = Ball::new(sid);
 
-    // build a thread that will send periodic pumps to the main thread when the system is told to run,
-    // but also not busy-wait when it is told to stop
-    let pump_sid = xous::create_server().unwrap();
-    let cid_to_pump = xous::connect(pump_sid).unwrap();
-    ball::ball_pump_thread(xous::connect(sid).unwrap(), pump_sid);
+    // the shell may start after us, so the verb is added from a thread of its own
+    std::thread::spawn(move || {
+        let xns = xous_names::XousNames::new().unwrap();
+        let shell = shellchat::Shellchat::new(&xns).unwrap();
+        if !shell.register_verb("ball", "ball [random] [tilt]: how the ball moves", sid, AppOp::Verb.to_u32().unwrap()).unwrap_or(false) {
+            log::warn!("couldn't add the ball verb to the shell");
+        }
+    });
+    // connected to when the verb is first used, by which time the shell is up
+    let mut shell: Option<shellchat::Shellchat> = None;
 
     // this is the main event loop for the app.
     let mut allow_redraw = true;
@@ -65,11 +62,10 @@ fn xmain() -> ! {
                     ball.update();
                 }
             }
-            Some(AppOp::Pump) => { // this is a blocking scalar so that pump calls don't initiate faster than the app can draw
+            Some(AppOp::Pump) => {
                 if allow_redraw {
                     ball.update();
                 }
-                xous::return_scalar(msg.sender, 1).expect("couldn't ack pump");
             }
             Some(AppOp::Rawkeys) => xous::msg_scalar_unpack!(msg, k1, k2, k3, k4, {
                 let keys = [
@@ -85,26 +81,25 @@ fn xmain() -> ! {
                 match new_state {
                     gam::FocusState::Background => {
                         allow_redraw = false; // this instantly terminates future updates, even if Pump messages are in our input queue
-                        xous::send_message(
-                            cid_to_pump,
-                            Message::new_scalar(PumpOp::Stop.to_usize().unwrap(), 0, 0, 0, 0)
-                        ).expect("couldn't send stop message to the pump thread");
                     }
                     gam::FocusState::Foreground => {
                         allow_redraw = true;
-                        xous::send_message(
-                            cid_to_pump,
-                            Message::new_scalar(PumpOp::Run.to_usize().unwrap(), 0, 0, 0, 0)
-                        ).expect("couldn't send run message to the pump thread");
                     }
                 }
             }),
+            Some(AppOp::Verb) => {
+                let buffer = unsafe { xous_ipc::Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let invocation = buffer.to_original::<shellchat::VerbInvocation, _>().unwrap();
+                let mode = invocation.args.as_str().unwrap_or("").trim();
+                let text = if ball.set_mode(mode) {
+                    format!("Ball mode is now {}", mode)
+                } else {
+                    format!("No ball mode called {}; try random or tilt", mode)
+                };
+                shell.get_or_insert_with(|| shellchat::Shellchat::new(&xns).unwrap())
+                    .respond(invocation.token, &text).expect("couldn't respond to the shell");
+            }
             Some(AppOp::Quit) => xous::msg_blocking_scalar_unpack!(msg, _, _, _, _, {
-                xous::send_message(
-                    cid_to_pump,
-                    Message::new_blocking_scalar(PumpOp::Quit.to_usize().unwrap(), 0, 0, 0, 0)
-                ).expect("couldn't send run message to the pump thread");
-                unsafe{xous::disconnect(cid_to_pump).ok()};
                 xous::return_scalar(msg.sender, 1).expect("couldn't acknowledge quit message");
                 break;
             }),
@@ -56,6 +56,7 @@ impl Repl{
             audioframe_id: None,
             rawkeys_id: None,
             focuschange_id: Some(ReplOp::ChangeFocus.to_u32().unwrap()),
+            lifecycle_id: None,
         }).expect("couldn't register Ux context for repl");
 
         let content = gam.request_content_canvas(token.unwrap()).expect("couldn't get content canvas");
@@ -162,6 +163,9 @@ impl Repl{
         )).expect("can't clear content area");
     }
     pub(crate) fn redraw(&mut self) -> Result<(), xous::Error> {
+        // the canvas changes shape if the screen is turned on its side, so re-read it on every redraw
+        self.screensize = self.gam.get_canvas_bounds(self.content).expect("couldn't get dimensions of content canvas");
+        self.bubble_width = ((self.screensize.x / 5) * 4) as u16;
         self.clear_area();
 
         // this defines the bottom border of the text bubbles as they stack up wards
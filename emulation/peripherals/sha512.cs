@@ -49,9 +49,10 @@ namespace Antmicro.Renode.Peripherals.Miscellaneous
             return 0;
         }
 
+        // the driver writes the last bytes of a message that isn't a whole number of words one at a time
         public void WriteByte(long offset, byte value)
         {
-            this.Log(LogLevel.Error, "Writing byte value 0x{0:X} to 0x{0:X}", value, offset);
+            this.Log(LogLevel.Noisy, "Adding byte 0x{0:X} (at address 0x{1:X}) to hash", value, offset);
             this.sha512.add8ToHash(value, (uint)offset);
         }
 
@@ -96,6 +97,10 @@ namespace Antmicro.Renode.Peripherals.Miscellaneous
             }
             if (this.usingSha256.Value)
             {
+                if (this.Sha512_256Backing == null)
+                {
+                    this.Sha512_256Backing = new List<byte>();
+                }
                 foreach (byte b in inputBuffer)
                 {
                     this.Sha512_256Backing.Add(b);
@@ -114,6 +119,10 @@ namespace Antmicro.Renode.Peripherals.Miscellaneous
             inputBuffer[0] = encounteredValue;
             if (this.usingSha256.Value)
             {
+                if (this.Sha512_256Backing == null)
+                {
+                    this.Sha512_256Backing = new List<byte>();
+                }
                 this.Sha512_256Backing.Add(inputBuffer[0]);
             }
             else
@@ -161,7 +170,8 @@ namespace Antmicro.Renode.Peripherals.Miscellaneous
             ;
 
             Registers.CONFIG.Define(this)
-                .WithFlag(0, out shaIsEnabled, FieldMode.Read | FieldMode.Write, name: "SHA_EN")
+                // clearing SHA_EN resets the unit, which the driver relies on to put it back to idle
+                .WithFlag(0, out shaIsEnabled, FieldMode.Read | FieldMode.Write, name: "SHA_EN", changeCallback: (oldValue, newValue) => { if (oldValue && !newValue) Reset(); })
                 .WithFlag(1, out inputIsSwapped, FieldMode.Read | FieldMode.Write, name: "ENDIAN_SWAP")
                 .WithFlag(2, out outputIsSwapped, FieldMode.Read | FieldMode.Write, name: "DIGEST_SWAP")
                 .WithFlag(3, out usingSha256, FieldMode.Read | FieldMode.Write, name: "SELECT_256")
@@ -193,7 +203,9 @@ namespace Antmicro.Renode.Peripherals.Miscellaneous
                     {
                         if (this.usingSha256.Value)
                         {
-                            this.Sha512_256Result = new SHA512_256Managed(this.Sha512_256Backing.ToArray());
+                            // processing without a HASH_START first hashes an empty message, rather than faulting the emulator
+                            var message = this.Sha512_256Backing != null ? this.Sha512_256Backing.ToArray() : new byte[0];
+                            this.Sha512_256Result = new SHA512_256Managed(message);
                             this.Sha512_256Backing = null;
                         }
                         else
@@ -322,6 +334,8 @@ namespace Antmicro.Renode.Peripherals.Miscellaneous
         public void Reset()
         {
             this.sha512.Initialize();
+            this.Sha512_256Backing = null;
+            this.Sha512_256Result = null;
             this.digestedLength = 0;
         }
 
@@ -85,6 +85,7 @@ pub fn lend_page_inner(
     _dest_space: &MemoryMapping,
     _dest_addr: *mut u8,
     _mutable: bool,
+    _shared: bool,
 ) -> Result<usize, Error> {
     unimplemented!()
 }
@@ -16,6 +16,9 @@ extern "Rust" {
 
 // use RAM-based backing so this variable is automatically saved on suspend
 static mut SIM_BACKING: usize = 0;
+
+/// The cycle count the last time the kernel was entered
+static mut LAST_TRAP_CYCLES: u64 = 0;
 /// Disable external interrupts
 pub fn disable_all_irqs() {
     unsafe { SIM_BACKING = sim::read() };
@@ -136,6 +139,15 @@ pub extern "C" fn trap_handler(
 
     let pid = current_pid();
 
+    // Whoever was running gets charged for the cycles since the last trap,
+    // including the kernel's own time spent getting them going.
+    let now = crate::arch::cycles();
+    let running = ArchProcess::with_current(|p| p.current_tid());
+    SystemServices::with_mut(|ss| {
+        ss.charge_cpu_time(pid, running, now.wrapping_sub(unsafe { LAST_TRAP_CYCLES }))
+    });
+    unsafe { LAST_TRAP_CYCLES = now };
+
     if (sc.bits() == 9) || (sc.bits() == 8) {
         // We got here because of an `ecall` instruction.  When we return, skip
         // past this instruction.  If this is a call such as `SwitchTo`, then we
@@ -326,6 +338,27 @@ pub extern "C" fn trap_handler(
             loop {}
         }
 
+        // Keep what the process looked like, so a crash report can be written
+        // once it's gone.
+        let record = ArchProcess::with_current(|process| {
+            let thread = process.current_thread();
+            let mut record = xous_kernel::CrashRecord {
+                pid: pid.get() as usize,
+                cause: sc.bits(),
+                pc: thread.sepc,
+                detail: stval::read(),
+                registers: thread.registers,
+                stack: [0; xous_kernel::CRASH_STACK_WORDS],
+            };
+            let sp = thread.registers[1];
+            for (i, word) in record.stack.iter_mut().enumerate() {
+                let addr = sp.wrapping_add(i * core::mem::size_of::<usize>());
+                *word = crate::arch::mem::peek_memory(addr as *mut usize).unwrap_or(0);
+            }
+            record
+        });
+        SystemServices::with_mut(|ss| ss.record_crash(&record));
+
         // If it's not a failure in the kernel, terminate or debug the current process.
         SystemServices::with_mut(|ss| {
             if cfg!(feature = "gdbserver") {
@@ -344,6 +377,12 @@ pub extern "C" fn trap_handler(
             crate::arch::syscall::resume(current_pid().get() == 1, process.current_thread())
         })
     } else {
+        #[cfg(feature = "profile")]
+        ArchProcess::with_current(|process| {
+            let thread = process.current_thread();
+            crate::profile::sample(pid, thread.sepc, thread.registers[0]);
+        });
+
         let irqs_pending = sip::read();
         // println!("irqs: {:x}", irqs_pending);
 
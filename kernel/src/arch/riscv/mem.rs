@@ -6,7 +6,7 @@ use core::fmt;
 use riscv::register::satp;
 use xous_kernel::{MemoryFlags, PID};
 
-// pub const DEFAULT_STACK_TOP: usize = 0x8000_0000;
+pub const DEFAULT_STACK_TOP: usize = 0x8000_0000;
 pub const DEFAULT_HEAP_BASE: usize = 0x2000_0000;
 pub const DEFAULT_MESSAGE_BASE: usize = 0x4000_0000;
 pub const DEFAULT_BASE: usize = 0x6000_0000;
@@ -17,6 +17,45 @@ pub const PAGE_SIZE: usize = 4096;
 const PAGE_TABLE_OFFSET: usize = 0xff40_0000;
 const PAGE_TABLE_ROOT_OFFSET: usize = 0xff80_0000;
 
+// The riscv64 values here are preparation for an RV64 port, not support for
+// one: the page tables are still walked and built as Sv32, so an RV64 kernel
+// can't boot yet.
+//
+// Layout of `satp`, which holds the paging mode, the address space ID (the
+// kernel uses the PID) and the physical page number of the root page table.
+//
+// Only this much of riscv64 is in place. The page table walks below, the
+// loader and the syscall ABI are all still Sv32 and 32-bit, so a riscv64
+// kernel can't boot yet.
+#[cfg(target_arch = "riscv32")]
+const SATP_MODE: usize = 1 << 31; // Sv32
+#[cfg(target_arch = "riscv32")]
+const SATP_ASID_SHIFT: usize = 22;
+#[cfg(target_arch = "riscv32")]
+const SATP_ASID_BITS: usize = 9;
+#[cfg(target_arch = "riscv64")]
+const SATP_MODE: usize = 8 << 60; // Sv39
+#[cfg(target_arch = "riscv64")]
+const SATP_ASID_SHIFT: usize = 44;
+#[cfg(target_arch = "riscv64")]
+const SATP_ASID_BITS: usize = 16;
+
+/// The `satp` value for the address space of `pid`, whose root page table is
+/// at physical address `root`
+fn satp_for(pid: PID, root: usize) -> usize {
+    SATP_MODE | ((pid.get() as usize) << SATP_ASID_SHIFT) | (root >> 12)
+}
+
+/// The PID (address space ID) a `satp` value belongs to
+pub fn satp_pid(satp: usize) -> usize {
+    (satp >> SATP_ASID_SHIFT) & ((1 << SATP_ASID_BITS) - 1)
+}
+
+/// The physical address of the root page table a `satp` value points to
+fn satp_root(satp: usize) -> usize {
+    (satp & ((1 << SATP_ASID_SHIFT) - 1)) << 12
+}
+
 extern "C" {
     fn flush_mmu();
 }
@@ -57,9 +96,9 @@ impl core::fmt::Debug for MemoryMapping {
             fmt,
             "(satp: 0x{:08x}, mode: {}, ASID: {}, PPN: {:08x})",
             self.satp,
-            self.satp >> 31,
-            self.satp >> 22 & ((1 << 9) - 1),
-            (self.satp & ((1 << 22) - 1)) << 12,
+            (self.satp & SATP_MODE != 0) as usize,
+            satp_pid(self.satp),
+            satp_root(self.satp),
         )
     }
 }
@@ -115,7 +154,7 @@ impl MemoryMapping {
 
     /// Get the "PID" (actually, ASID) from the current mapping
     pub fn get_pid(&self) -> PID {
-        PID::new((self.satp >> 22 & ((1 << 9) - 1)) as _).unwrap()
+        PID::new(satp_pid(self.satp) as _).unwrap()
     }
 
     /// Set this mapping as the systemwide mapping.
@@ -492,6 +531,100 @@ pub fn map_page_inner(
     Ok(())
 }
 
+/// Zero out a page that isn't mapped anywhere yet and fill in the given
+/// pagetable entries, by mapping it into the current process for a moment.
+fn fill_pagetable(
+    mm: &mut MemoryManager,
+    phys: usize,
+    entries: &[(usize, usize)],
+) -> Result<(), xous_kernel::Error> {
+    let virt = mm.find_virtual_address(
+        core::ptr::null_mut(),
+        PAGE_SIZE,
+        xous_kernel::MemoryType::Messages,
+    )? as usize;
+    map_page_inner(
+        mm,
+        crate::arch::process::current_pid(),
+        phys,
+        virt,
+        MemoryFlags::R | MemoryFlags::W,
+        false,
+    )?;
+    let table = virt as *mut usize;
+    unsafe {
+        memset(table as *mut u8, 0, PAGE_SIZE);
+        for &(index, entry) in entries {
+            table.add(index).write_volatile(entry);
+        }
+    }
+    unmap_page_inner(mm, virt)?;
+    Ok(())
+}
+
+/// Build an empty address space for a new process. It has a root pagetable
+/// mapped at `PAGE_TABLE_ROOT_OFFSET`, the pagetables that map it, a blank
+/// context page and the kernel's megapage, which is everything needed to
+/// switch to it. All of the pages belong to `pid`, so they are freed along
+/// with the process.
+///
+/// # Errors
+///
+/// * **OutOfMemory**: There weren't enough free pages. Any pages that were
+///                    allocated still belong to `pid`.
+pub fn create_address_space(
+    mm: &mut MemoryManager,
+    pid: PID,
+) -> Result<MemoryMapping, xous_kernel::Error> {
+    let root = mm.alloc_page(pid)?;
+    let context = mm.alloc_page(pid)?;
+    // The pagetables that map 0xff40_0000 and 0xff80_0000 respectively
+    let l0_tables = mm.alloc_page(pid)?;
+    let l0_root = mm.alloc_page(pid)?;
+
+    let table = |phys: usize| ((phys >> 12) << 10) | MMUFlags::VALID.bits();
+    let page = |phys: usize| {
+        ((phys >> 12) << 10)
+            | (MMUFlags::VALID | MMUFlags::R | MMUFlags::W | MMUFlags::D | MMUFlags::A).bits()
+    };
+    let root_vpn1 = PAGE_TABLE_ROOT_OFFSET >> 22;
+    let tables_vpn1 = (PAGE_TABLE_OFFSET + root_vpn1 * PAGE_SIZE) >> 22;
+    let vpn0 = |virt: usize| (virt >> 12) & ((1 << 10) - 1);
+
+    // Every process shares the kernel's megapage with PID1
+    let kernel = unsafe { (PAGE_TABLE_ROOT_OFFSET as *const usize).add(1023).read_volatile() };
+    fill_pagetable(
+        mm,
+        root,
+        &[
+            (tables_vpn1, table(l0_tables)),
+            (root_vpn1, table(l0_root)),
+            (1023, kernel),
+        ],
+    )?;
+    fill_pagetable(
+        mm,
+        l0_root,
+        &[
+            (vpn0(PAGE_TABLE_ROOT_OFFSET), page(root)),
+            (vpn0(PAGE_TABLE_ROOT_OFFSET + PAGE_SIZE), page(context)),
+        ],
+    )?;
+    fill_pagetable(
+        mm,
+        l0_tables,
+        &[
+            (vpn0(PAGE_TABLE_OFFSET + tables_vpn1 * PAGE_SIZE), page(l0_tables)),
+            (vpn0(PAGE_TABLE_OFFSET + root_vpn1 * PAGE_SIZE), page(l0_root)),
+        ],
+    )?;
+    fill_pagetable(mm, context, &[])?;
+
+    Ok(MemoryMapping {
+        satp: satp_for(pid, root),
+    })
+}
+
 /// Get the pagetable entry for a given address, or `Err()` if the address is invalid
 pub fn pagetable_entry(addr: usize) -> Result<&'static mut usize, xous_kernel::Error> {
     if addr & 3 != 0 {
@@ -601,6 +734,7 @@ pub fn lend_page_inner(
     dest_space: &MemoryMapping,
     dest_addr: *mut u8,
     mutable: bool,
+    shared: bool,
 ) -> Result<usize, xous_kernel::Error> {
     //klog!("***lend - src: {:08x} dest: {:08x}***", src_addr as u32, dest_addr as u32);
     let entry = pagetable_entry(src_addr as usize)?;
@@ -619,8 +753,13 @@ pub fn lend_page_inner(
         return Err(xous_kernel::Error::ShareViolation);
     }
 
-    // Strip the `VALID` flag, and set the `SHARED` flag.
-    *entry = (*entry & !MMUFlags::VALID.bits()) | MMUFlags::S.bits();
+    // Strip the `VALID` flag, and set the `SHARED` flag. A shared lend keeps
+    // the page valid here, and `SHARED` still stops it being lent twice.
+    if shared {
+        *entry |= MMUFlags::S.bits();
+    } else {
+        *entry = (*entry & !MMUFlags::VALID.bits()) | MMUFlags::S.bits();
+    }
 
     // Ensure the change takes effect.
     unsafe { flush_mmu() };
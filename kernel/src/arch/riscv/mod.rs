@@ -52,6 +52,26 @@ pub fn init() {
     rand::init();
 }
 
+extern "C" {
+    // These come from the assembly library of the `riscv` crate
+    fn __read_cycle() -> usize;
+    fn __read_cycleh() -> usize;
+}
+
+/// The number of cycles the CPU has run since reset. Supervisor mode may
+/// only read the counter because the loader has set `mcounteren.CY`, and
+/// checked that it took, before starting the kernel.
+pub fn cycles() -> u64 {
+    loop {
+        let hi = unsafe { __read_cycleh() };
+        let lo = unsafe { __read_cycle() };
+        // Try again if the low half wrapped between the two reads
+        if hi == unsafe { __read_cycleh() } {
+            return ((hi as u64) << 32) | lo as u64;
+        }
+    }
+}
+
 /// Put the core to sleep until an interrupt hits. Returns `true`
 /// to indicate the kernel should not exit.
 pub fn idle() -> bool {
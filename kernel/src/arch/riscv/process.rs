@@ -130,7 +130,7 @@ pub struct Thread {
 impl Process {
     pub fn current() -> Process {
         let pid = unsafe { PROCESS_TABLE.current };
-        let hardware_pid = (riscv::register::satp::read().bits() >> 22) & ((1 << 9) - 1);
+        let hardware_pid = crate::arch::mem::satp_pid(riscv::register::satp::read().bits());
         assert!((pid.get() as usize) == hardware_pid);
         Process { pid }
     }
@@ -497,8 +497,11 @@ impl Process {
         );
     }
 
-    pub fn create(_pid: PID, _init_data: ProcessInit) -> PID {
-        todo!();
+    /// The address space of a new process is built by
+    /// `mem::create_address_space()`, and its context is set up when it's
+    /// first switched to, so there is nothing left to do here.
+    pub fn create(pid: PID, _init_data: ProcessInit) -> PID {
+        pid
     }
 
     pub fn destroy(pid: PID) -> Result<(), xous_kernel::Error> {
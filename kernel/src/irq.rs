@@ -80,6 +80,11 @@ pub fn interrupt_claim(
     }
 }
 
+/// Returns `true` if the given PID has claimed at least one interrupt.
+pub fn pid_has_interrupts(pid: PID) -> bool {
+    unsafe { IRQ_HANDLERS.iter().flatten().any(|handler| handler.0 == pid) }
+}
+
 /// Iterate through the IRQ handlers and remove any handler that exists
 /// for the given PID.
 pub fn release_interrupts_for_pid(pid: PID) {
@@ -0,0 +1,53 @@
+// SPDX-FileCopyrightText: 2020 Sean Cross <sean@xobs.io>
+// SPDX-License-Identifier: Apache-2.0
+
+//! Histograms of how long a process waits for the CPU after it's woken, kept
+//! per priority class when the kernel is built with `sched-latency`. A process
+//! is stamped with the cycle counter when it goes from sleeping to ready, and
+//! the time until it's next switched to is counted in a bucket; see
+//! `xous_kernel::SCHED_LATENCY_BUCKETS`.
+
+use crate::arch::process::MAX_PROCESS_COUNT;
+use xous_kernel::{Priority, PID, SCHED_LATENCY_BASE_LOG2, SCHED_LATENCY_BUCKETS};
+
+/// One for each `Priority`
+const PRIORITY_CLASSES: usize = 3;
+
+/// When each process was woken, or 0 if it isn't waiting to run
+static mut WOKEN_AT: [u64; MAX_PROCESS_COUNT] = [0; MAX_PROCESS_COUNT];
+static mut HISTOGRAM: [[usize; SCHED_LATENCY_BUCKETS]; PRIORITY_CLASSES] =
+    [[0; SCHED_LATENCY_BUCKETS]; PRIORITY_CLASSES];
+
+fn bucket(cycles: u64) -> usize {
+    let log2 = (64 - cycles.leading_zeros()) as usize;
+    log2.saturating_sub(SCHED_LATENCY_BASE_LOG2)
+        .min(SCHED_LATENCY_BUCKETS - 1)
+}
+
+/// `pid` has become ready after sleeping
+pub fn woken(pid: PID) {
+    let slot = unsafe { &mut WOKEN_AT[pid.get() as usize - 1] };
+    if *slot == 0 {
+        *slot = crate::arch::cycles().max(1);
+    }
+}
+
+/// `pid`, of class `priority`, is being switched to
+pub fn ran(pid: PID, priority: Priority) {
+    let slot = unsafe { &mut WOKEN_AT[pid.get() as usize - 1] };
+    if *slot != 0 {
+        let waited = crate::arch::cycles().wrapping_sub(*slot);
+        *slot = 0;
+        unsafe { HISTOGRAM[priority as usize][bucket(waited)] += 1 };
+    }
+}
+
+/// `pid` is starting over as a new process
+pub fn forget(pid: PID) {
+    unsafe { WOKEN_AT[pid.get() as usize - 1] = 0 };
+}
+
+/// The count in one bucket of the histogram of `priority`
+pub fn count(priority: Priority, bucket: usize) -> Option<usize> {
+    unsafe { HISTOGRAM[priority as usize].get(bucket).copied() }
+}
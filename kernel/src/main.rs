@@ -19,8 +19,12 @@ mod arch;
 #[macro_use]
 mod args;
 mod irq;
+#[cfg(all(baremetal, feature = "sched-latency"))]
+mod latency;
 mod macros;
 mod mem;
+#[cfg(all(baremetal, feature = "profile"))]
+mod profile;
 mod server;
 mod services;
 mod syscall;
@@ -105,7 +109,8 @@ pub unsafe extern "C" fn init(arg_offset: *const u32, init_offset: *const u32, r
 }
 
 /// Loop through the SystemServices list to determine the next PID to be run.
-/// If no process is ready, return `None`.
+/// Processes of a higher priority go first, and processes of the same
+/// priority take turns. If no process is ready, return `None`.
 fn next_pid_to_run(last_pid: Option<PID>) -> Option<PID> {
     // PIDs are 1-indexed but arrays are 0-indexed.  By not subtracting
     // 1 from the PID when we use it as an array index, we automatically
@@ -113,33 +118,21 @@ fn next_pid_to_run(last_pid: Option<PID>) -> Option<PID> {
     let current_pid = last_pid.unwrap_or(unsafe { PID::new_unchecked(1) }).get() as usize;
 
     SystemServices::with(|system_services| {
-        for test_idx in current_pid..system_services.processes.len() {
-            if system_services.processes[test_idx].ppid.get() == 1 {
-                // print!("PID {} is owned by PID1... ", test_idx + 1);
-                if system_services.processes[test_idx].runnable() {
-                    // println!(" and is runnable");
-                    return match pid_from_usize(test_idx + 1) {
-                        Ok(x) => Some(x),
-                        Err(_) => None,
-                    };
-                }
-                // println!(" and is NOT RUNNABLE");
+        let process_count = system_services.processes.len();
+        let mut best: Option<(usize, Priority)> = None;
+        for offset in 0..process_count {
+            let test_idx = (current_pid + offset) % process_count;
+            let process = &system_services.processes[test_idx];
+            if process.ppid.get() != 1 || !process.runnable() {
+                continue;
             }
-        }
-        for test_idx in 0..current_pid {
-            if system_services.processes[test_idx].ppid.get() == 1 {
-                // print!("PID {} is owned by PID1... ", test_idx + 1);
-                if system_services.processes[test_idx].runnable() {
-                    // println!(" and is runnable");
-                    return match pid_from_usize(test_idx + 1) {
-                        Ok(x) => Some(x),
-                        Err(_) => None,
-                    };
-                }
-                // println!(" and is NOT RUNNABLE");
+            // Only a strictly higher priority displaces an earlier pick, so
+            // the next in line among equals wins.
+            if best.map_or(true, |(_, priority)| process.priority() > priority) {
+                best = Some((test_idx, process.priority()));
             }
         }
-        None
+        best.and_then(|(test_idx, _)| pid_from_usize(test_idx + 1).ok())
     })
 }
 
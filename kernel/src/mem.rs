@@ -4,7 +4,7 @@
 use core::fmt;
 
 pub use crate::arch::mem::{MemoryMapping, PAGE_SIZE};
-use crate::arch::process::Process;
+use crate::arch::process::{Process, MAX_PROCESS_COUNT};
 
 use xous_kernel::{MemoryFlags, MemoryRange, PID};
 
@@ -40,6 +40,13 @@ impl fmt::Display for MemoryRangeExtra {
     }
 }
 
+/// How many pages a process owns, and the most it has owned
+#[derive(Copy, Clone, Default)]
+struct PageUsage {
+    pages: usize,
+    peak: usize,
+}
+
 pub struct MemoryManager {
     ram_start: usize,
     ram_size: usize,
@@ -47,6 +54,9 @@ pub struct MemoryManager {
     ram_name: u32,
     #[allow(dead_code)]
     last_ram_page: usize,
+    /// Pages owned by each process, indexed by PID - 1. Only kept on
+    /// hardware, where there's a table of page owners.
+    usage: [PageUsage; MAX_PROCESS_COUNT],
 }
 
 impl Default for MemoryManager {
@@ -77,6 +87,7 @@ impl MemoryManager {
             ram_size: 0,
             ram_name: 0,
             last_ram_page: 0,
+            usage: [PageUsage { pages: 0, peak: 0 }; MAX_PROCESS_COUNT],
         }
     }
 
@@ -173,9 +184,40 @@ impl MemoryManager {
         unsafe {
             MEMORY_ALLOCATIONS = slice::from_raw_parts_mut(base as *mut Option<PID>, mem_size)
         };
+
+        // The loader has already handed out pages to the processes it set up
+        unsafe {
+            for owner in MEMORY_ALLOCATIONS.iter() {
+                self.account(None, *owner);
+            }
+        }
         Ok(())
     }
 
+    /// Keep the per-process page counts up to date as a page changes hands
+    #[cfg(baremetal)]
+    fn account(&mut self, previous: Option<PID>, owner: Option<PID>) {
+        if previous == owner {
+            return;
+        }
+        if let Some(usage) = previous.and_then(|pid| self.usage.get_mut(pid.get() as usize - 1)) {
+            usage.pages = usage.pages.saturating_sub(1);
+        }
+        if let Some(usage) = owner.and_then(|pid| self.usage.get_mut(pid.get() as usize - 1)) {
+            usage.pages += 1;
+            usage.peak = usage.peak.max(usage.pages);
+        }
+    }
+
+    /// Return the number of pages the specified process owns now, and the
+    /// most it has owned at once. Both are 0 in hosted mode.
+    pub fn pages_owned_by(&self, pid: PID) -> (usize, usize) {
+        self.usage
+            .get(pid.get() as usize - 1)
+            .map(|usage| (usage.pages, usage.peak))
+            .unwrap_or((0, 0))
+    }
+
     /// Print the number of RAM bytes used by the specified process.
     /// This does not include memory such as peripherals and CSRs.
     #[cfg(baremetal)]
@@ -266,6 +308,7 @@ impl MemoryManager {
                 // );
                 if allocation.is_none() {
                     *allocation = Some(pid);
+                    self.account(None, Some(pid));
                     self.last_ram_page = index + 1;
                     // if self.last_ram_page >= end_point {
                     //     self.last_ram_page = 0;
@@ -462,6 +505,54 @@ impl MemoryManager {
         Ok(virt)
     }
 
+    /// Give `pid`, whose address space must be the active one, a stack of
+    /// `size` bytes that ends at `top`, as the loader does for the processes
+    /// started at boot: the topmost page is backed, and the rest is reserved
+    /// for the stack to grow into.
+    ///
+    /// # Errors
+    ///
+    /// * MemoryInUse - Some of the stack is already mapped
+    /// * OutOfMemory - There was no page left for the top of the stack
+    #[cfg(baremetal)]
+    pub fn map_stack(&mut self, pid: PID, top: usize, size: usize) -> Result<(), xous_kernel::Error> {
+        let bottom = top - size;
+        if !(bottom..top)
+            .step_by(PAGE_SIZE)
+            .all(crate::arch::mem::address_available)
+        {
+            return Err(xous_kernel::Error::MemoryInUse);
+        }
+
+        // Mapping the top page first also gives `pid` the pagetable the rest
+        // of the stack is reserved in
+        let top_page = top - PAGE_SIZE;
+        let phys = self.alloc_page(pid)?;
+        if let Err(e) = crate::arch::mem::map_page_inner(
+            self,
+            pid,
+            phys,
+            top_page,
+            xous_kernel::MemoryFlags::R | xous_kernel::MemoryFlags::W,
+            false,
+        ) {
+            self.release_page(phys as *mut usize, pid).ok();
+            return Err(e);
+        }
+        unsafe { (top_page as *mut usize).write_bytes(0, PAGE_SIZE / core::mem::size_of::<usize>()) };
+        crate::arch::mem::hand_page_to_user(top_page as *mut u8)?;
+
+        let mut mapping = MemoryMapping::current();
+        for virt in (bottom..top_page).step_by(PAGE_SIZE) {
+            mapping.reserve_address(
+                self,
+                virt,
+                xous_kernel::MemoryFlags::R | xous_kernel::MemoryFlags::W,
+            )?;
+        }
+        Ok(())
+    }
+
     pub fn is_main_memory(&self, phys: *mut u8) -> bool {
         (phys as usize) >= self.ram_start && (phys as usize) < self.ram_start + self.ram_size
     }
@@ -571,7 +662,7 @@ impl MemoryManager {
     /// Mark the page in the current process as being lent.  If the borrow is
     /// read-only, then additionally remove the "write" bit on it.  If the page
     /// is writable, then remove it from the current process until the borrow is
-    /// returned.
+    /// returned, unless it's `shared`.
     #[allow(dead_code)]
     pub fn lend_page(
         &mut self,
@@ -581,6 +672,7 @@ impl MemoryManager {
         dest_mapping: &MemoryMapping,
         dest_addr: *mut u8,
         mutable: bool,
+        shared: bool,
     ) -> Result<usize, xous_kernel::Error> {
         // If this page is to be writable, detach it from this process.
         // Otherwise, mark it as read-only to prevent a process from modifying
@@ -593,6 +685,7 @@ impl MemoryManager {
             dest_mapping,
             dest_addr as _,
             mutable,
+            shared,
         )
     }
 
@@ -690,7 +783,11 @@ impl MemoryManager {
         // Happy path: The address is in main RAM
         if addr >= self.ram_start && addr < self.ram_start + self.ram_size {
             offset += (addr - self.ram_start) / PAGE_SIZE;
-            return unsafe { action_inner(&mut MEMORY_ALLOCATIONS[offset], pid, action) };
+            let owner = unsafe { &mut MEMORY_ALLOCATIONS[offset] };
+            let previous = *owner;
+            action_inner(owner, pid, action)?;
+            self.account(previous, *owner);
+            return Ok(());
         }
 
         offset += self.ram_size / PAGE_SIZE;
@@ -702,7 +799,11 @@ impl MemoryManager {
                     && addr < (region.mem_start + region.mem_size) as usize
                 {
                     offset += (addr - (region.mem_start as usize)) / PAGE_SIZE;
-                    return action_inner(&mut MEMORY_ALLOCATIONS[offset], pid, action);
+                    let owner = &mut MEMORY_ALLOCATIONS[offset];
+                    let previous = *owner;
+                    action_inner(owner, pid, action)?;
+                    self.account(previous, *owner);
+                    return Ok(());
                 }
                 offset += region.mem_size as usize / PAGE_SIZE;
             }
@@ -761,6 +862,7 @@ impl MemoryManager {
     pub unsafe fn release_all_memory_for_process(&mut self, _pid: PID) {
         #[cfg(baremetal)]
         for (idx, owner) in MEMORY_ALLOCATIONS.iter_mut().enumerate() {
+            let previous = *owner;
             // If this address has been allocated to this process, consider
             // freeing it or reparenting it.
             if owner == &mut Some(_pid) {
@@ -773,8 +875,13 @@ impl MemoryManager {
                     // Mark this page as free, which allows it to be re-allocated.
                     *owner = None;
                 }
+                self.account(previous, *owner);
             }
         }
+        // The PID will be given to a new process, which starts counting afresh
+        if let Some(usage) = self.usage.get_mut(_pid.get() as usize - 1) {
+            *usage = PageUsage::default();
+        }
     }
 
     /// Adjust the flags on the given memory range. This allows for stripping flags from a memory
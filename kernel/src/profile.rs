@@ -0,0 +1,74 @@
+// SPDX-FileCopyrightText: 2020 Sean Cross <sean@xobs.io>
+// SPDX-License-Identifier: Apache-2.0
+
+//! A sampling profiler, kept when the kernel is built with `profile`. While
+//! it's on, every interrupt records the process that was running and where it
+//! was, so the preemption tick gives an even sample of where the CPU spends
+//! its time. The samples are laid out as in `xous_kernel::ProfileSample`.
+
+use xous_kernel::{ProfileSample, PID, PROFILE_SAMPLES, PROFILE_SAMPLE_WORDS};
+
+static mut ON: bool = false;
+/// The first process to touch the profiler, which becomes the only one that
+/// may, as with the IPC trace
+static mut OWNER: Option<PID> = None;
+/// Samples taken since the profiler was turned on
+static mut COUNT: usize = 0;
+static mut SAMPLES: [[usize; PROFILE_SAMPLE_WORDS]; PROFILE_SAMPLES] =
+    [[0; PROFILE_SAMPLE_WORDS]; PROFILE_SAMPLES];
+
+/// An interrupt came in while `pid` was at `pc`
+pub fn sample(pid: PID, pc: usize, ra: usize) {
+    unsafe {
+        if !ON {
+            return;
+        }
+        let sample = ProfileSample {
+            pid: pid.get(),
+            pc,
+            ra,
+        };
+        SAMPLES[COUNT % PROFILE_SAMPLES] = sample.to_words();
+        COUNT += 1;
+    }
+}
+
+fn claim(caller: PID) -> Result<(), xous_kernel::Error> {
+    unsafe {
+        if *OWNER.get_or_insert(caller) != caller {
+            return Err(xous_kernel::Error::AccessDenied);
+        }
+    }
+    Ok(())
+}
+
+/// Turn the profiler on or off on behalf of `caller`
+pub fn set(caller: PID, enable: bool) -> Result<(), xous_kernel::Error> {
+    claim(caller)?;
+    unsafe {
+        if enable && !ON {
+            COUNT = 0;
+        }
+        ON = enable;
+    }
+    Ok(())
+}
+
+/// Word `index` of the samples kept, counting from the oldest, along with
+/// the number taken
+pub fn word(caller: PID, index: usize) -> Result<(usize, usize), xous_kernel::Error> {
+    claim(caller)?;
+    let sample = index / PROFILE_SAMPLE_WORDS;
+    if sample >= PROFILE_SAMPLES {
+        return Err(xous_kernel::Error::InvalidSyscall);
+    }
+    unsafe {
+        let oldest = if COUNT > PROFILE_SAMPLES {
+            COUNT % PROFILE_SAMPLES
+        } else {
+            0
+        };
+        let words = &SAMPLES[(oldest + sample) % PROFILE_SAMPLES];
+        Ok((COUNT, words[index % PROFILE_SAMPLE_WORDS]))
+    }
+}
@@ -37,6 +37,11 @@ pub struct Server {
     /// this message. If there are no available contexts, then messages will
     /// need to be queued.
     ready_threads: usize,
+
+    /// A bitfield of threads in the owning process that are blocked in
+    /// `WaitMessage` until a message is queued for this server or for one
+    /// of the other servers they are waiting on.
+    selecting_threads: usize,
 }
 
 pub struct SenderID {
@@ -301,6 +306,7 @@ impl Server {
             tail_generation: 0,
             queue,
             ready_threads: 0,
+            selecting_threads: 0,
         });
         Ok(())
     }
@@ -1202,4 +1208,43 @@ impl Server {
         self.ready_threads |= 1 << tid;
         klog!("ready threads now: {:08b}", self.ready_threads);
     }
+
+    /// Returns `true` if a message has been queued that hasn't yet been
+    /// received.
+    pub fn has_queued_message(&self) -> bool {
+        self.tail_generation != self.head_generation
+    }
+
+    /// Return the number of messages that have been queued but not yet
+    /// received, and the number of slots in the queue. Messages that have
+    /// been received but not yet answered still take up a slot, so the queue
+    /// can be full with fewer messages waiting than it has slots.
+    pub fn queue_depth(&self) -> (usize, usize) {
+        (
+            self.tail_generation.wrapping_sub(self.head_generation) as usize,
+            self.queue.len(),
+        )
+    }
+
+    /// Add the given context to the list of threads waiting for a message
+    /// to be queued.
+    pub fn park_selecting_thread(&mut self, tid: TID) {
+        self.selecting_threads |= 1 << tid;
+    }
+
+    /// Remove the given context from the list of threads waiting for a
+    /// message to be queued. It's fine if it wasn't on the list.
+    pub fn unpark_selecting_thread(&mut self, tid: TID) {
+        self.selecting_threads &= !(1 << tid);
+    }
+
+    /// Return the lowest numbered context that is waiting for a message to
+    /// be queued, without removing it.
+    pub fn selecting_thread(&self) -> Option<TID> {
+        if self.selecting_threads == 0 {
+            None
+        } else {
+            Some(self.selecting_threads.trailing_zeros() as TID)
+        }
+    }
 }
@@ -13,11 +13,19 @@ use crate::filled_array;
 use crate::server::Server;
 // use core::mem;
 use xous_kernel::{
-    pid_from_usize, Error, MemoryAddress, Message, ProcessInit, ThreadInit, CID, PID, SID, TID,
+    pid_from_usize, Error, IpcTraceEvent, IpcTraceKind, MemoryAddress, Message, MessageEnvelope,
+    Priority, ProcessInit, ScalarMessage, ThreadInit, CID, CRASH_RECORD_WORDS, IPC_TRACE_EVENTS,
+    IPC_TRACE_EVENT_WORDS, MAX_LEND_SEGMENTS, PID, SID, TID,
 };
 
 const MAX_SERVER_COUNT: usize = 128;
 
+/// How many requests to hear about servers going away the kernel keeps
+const MAX_SERVER_WATCHES: usize = 32;
+
+/// How many messages lent in pieces may be out with servers at once
+const MAX_VECTORED_LENDS: usize = 16;
+
 pub use crate::arch::process::{INITIAL_TID, MAX_PROCESS_COUNT};
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -43,6 +51,67 @@ pub struct SystemServices {
 
     /// A table of all servers in the system
     servers: [Option<Server>; MAX_SERVER_COUNT],
+
+    /// The last process fault, kept for whoever writes crash reports
+    crash: Crash,
+
+    /// Recent IPC, kept while tracing is on
+    trace: Trace,
+
+    /// Clients waiting to hear that a server has gone away
+    watches: [Option<Watch>; MAX_SERVER_WATCHES],
+
+    /// Memory lent in pieces that servers have yet to return
+    vectored: [Option<Vectored>; MAX_VECTORED_LENDS],
+
+    /// Threads parked in `wait_address()`, with the address each waits on.
+    /// On hardware this is read back out of the parked thread's registers.
+    #[cfg(all(not(baremetal), test))]
+    address_waits: Vec<(PID, TID, usize)>,
+}
+
+struct Crash {
+    /// How many faults have been recorded since boot
+    sequence: usize,
+    record: [usize; CRASH_RECORD_WORDS],
+    /// The one process allowed to read the record, once it has asked
+    reader: Option<PID>,
+}
+
+struct Trace {
+    enabled: bool,
+    /// How many events have been recorded since tracing was turned on
+    count: usize,
+    events: [[usize; IPC_TRACE_EVENT_WORDS]; IPC_TRACE_EVENTS],
+    /// The one process allowed to control and read the trace, once it has
+    /// asked
+    owner: Option<PID>,
+}
+
+/// A request to be told when a server is destroyed
+#[derive(Copy, Clone)]
+struct Watch {
+    /// The process that asked
+    pid: PID,
+    /// The asking process' connection to the server, which is passed back
+    cid: CID,
+    /// The server being watched
+    server: usize,
+    /// The asking process' own server that the notice is sent to
+    notify: usize,
+    /// The message id of the notice
+    opcode: usize,
+}
+
+/// A lend made up of several ranges of the client, lined up in the server
+#[derive(Copy, Clone)]
+struct Vectored {
+    /// The server the memory is lent to
+    server: usize,
+    /// Where the ranges start in the server
+    server_addr: usize,
+    /// The ranges in the client, in the order they were lined up
+    segments: [Option<MemoryRange>; MAX_LEND_SEGMENTS],
 }
 
 #[derive(Copy, Clone, PartialEq)]
@@ -121,10 +190,14 @@ pub struct Process {
     /// This process' PID. This should match up with the index in the process table.
     pub pid: PID,
 
-    /// The process that created this process, which tells who is allowed to
-    /// manipulate this process.
+    /// The process that created this process. The scheduler only runs
+    /// children of PID 1, so programs the loader starts are handed over to it.
     pub ppid: PID,
 
+    /// Who is allowed to manipulate this process. That's its parent, unless
+    /// it was handed over to PID 1, in which case the loader keeps this.
+    owner: PID,
+
     /// The current thread ID
     pub current_thread: TID,
 
@@ -134,12 +207,33 @@ pub struct Process {
 
     /// When an exception is hit, the kernel will switch to this Thread.
     exception_handler: Option<ExceptionHandler>,
+
+    /// CPU cycles this process has spent running. Only counted on baremetal.
+    cpu_time: u64,
+
+    /// The same, split up by thread. A thread's count starts over when its
+    /// ID is handed to a new thread.
+    thread_cpu_time: [u64; arch::process::MAX_THREAD + 1],
+
+    /// Bytes this process' heap has been grown by. The process keeps its own
+    /// count, which only it can see, so it's copied here for other processes
+    /// to look at.
+    heap_size: usize,
+
+    /// Which class of processes this one is scheduled with
+    priority: Priority,
+
+    /// Whether the name the loader gave this PID at boot still belongs to
+    /// the process using it. Once that process terminates, whatever takes
+    /// the PID over has no name.
+    boot_name: bool,
 }
 
 impl Default for Process {
     fn default() -> Self {
         Process {
             ppid: unsafe { PID::new_unchecked(1) },
+            owner: unsafe { PID::new_unchecked(1) },
             ..Default::default()
         }
     }
@@ -209,6 +303,10 @@ impl Process {
         )
     }
 
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
     /// This process slot is unallocated and may be turn into a process
     pub fn free(&self) -> bool {
         matches!(self.state, ProcessState::Free)
@@ -237,6 +335,7 @@ impl Process {
         // Remove this PID from the process table
         ArchProcess::destroy(self.pid)?;
         self.state = ProcessState::Free;
+        self.boot_name = false;
         Ok(())
     }
 }
@@ -246,15 +345,36 @@ std::thread_local!(static SYSTEM_SERVICES: core::cell::RefCell<SystemServices> =
     processes: [Process {
         state: ProcessState::Free,
         ppid: unsafe { PID::new_unchecked(1) },
+        owner: unsafe { PID::new_unchecked(1) },
         pid: unsafe { PID::new_unchecked(1) },
         mapping: arch::mem::DEFAULT_MEMORY_MAPPING,
         current_thread: 0_usize,
         previous_thread: INITIAL_TID as TID,
         exception_handler: None,
+        cpu_time: 0,
+        thread_cpu_time: [0; arch::process::MAX_THREAD + 1],
+        heap_size: 0,
+        priority: Priority::Normal,
+        boot_name: true,
     }; MAX_PROCESS_COUNT],
     // Note we can't use MAX_SERVER_COUNT here because of how Rust's
     // macro tokenization works
     servers: filled_array![None; 128],
+    crash: Crash {
+        sequence: 0,
+        record: [0; CRASH_RECORD_WORDS],
+        reader: None,
+    },
+    trace: Trace {
+        enabled: false,
+        count: 0,
+        events: [[0; IPC_TRACE_EVENT_WORDS]; IPC_TRACE_EVENTS],
+        owner: None,
+    },
+    watches: [None; MAX_SERVER_WATCHES],
+    vectored: [None; MAX_VECTORED_LENDS],
+    #[cfg(test)]
+    address_waits: Vec::new(),
 }));
 
 #[cfg(baremetal)]
@@ -262,15 +382,34 @@ static mut SYSTEM_SERVICES: SystemServices = SystemServices {
     processes: [Process {
         state: ProcessState::Free,
         ppid: unsafe { PID::new_unchecked(1) },
+        owner: unsafe { PID::new_unchecked(1) },
         pid: unsafe { PID::new_unchecked(1) },
         mapping: arch::mem::DEFAULT_MEMORY_MAPPING,
         current_thread: 0_usize,
         previous_thread: INITIAL_TID as TID,
         exception_handler: None,
+        cpu_time: 0,
+        thread_cpu_time: [0; arch::process::MAX_THREAD + 1],
+        heap_size: 0,
+        priority: Priority::Normal,
+        boot_name: true,
     }; MAX_PROCESS_COUNT],
     // Note we can't use MAX_SERVER_COUNT here because of how Rust's
     // macro tokenization works
     servers: filled_array![None; 128],
+    crash: Crash {
+        sequence: 0,
+        record: [0; CRASH_RECORD_WORDS],
+        reader: None,
+    },
+    trace: Trace {
+        enabled: false,
+        count: 0,
+        events: [[0; IPC_TRACE_EVENT_WORDS]; IPC_TRACE_EVENTS],
+        owner: None,
+    },
+    watches: [None; MAX_SERVER_WATCHES],
+    vectored: [None; MAX_VECTORED_LENDS],
 };
 
 impl core::fmt::Debug for Process {
@@ -337,7 +476,7 @@ impl SystemServices {
         // value from the bootloader.  For each process, translate it from a raw
         // KernelArguments value to a SystemServices Process value.
         for init in init_offsets.iter() {
-            let pid = (init.satp >> 22) & ((1 << 9) - 1);
+            let pid = crate::arch::mem::satp_pid(init.satp);
             let process = &mut self.processes[(pid - 1) as usize];
             // println!(
             //     "Process: SATP: {:08x}  PID: {}  Memory: {:08x}  PC: {:08x}  SP: {:08x}  Index: {}",
@@ -351,6 +490,7 @@ impl SystemServices {
             unsafe {
                 process.mapping.from_raw(init.satp);
                 process.ppid = PID::new_unchecked(1);
+                process.owner = PID::new_unchecked(1);
                 process.pid = PID::new(pid as _).unwrap();
             };
             // let old_state = process.state;
@@ -390,17 +530,359 @@ impl SystemServices {
                 continue;
             }
             let new_pid = pid_from_usize(idx + 1)?;
+            #[cfg(baremetal)]
+            {
+                entry.mapping = crate::mem::MemoryManager::with_mut(|mm| {
+                    arch::mem::create_address_space(mm, new_pid).map_err(|e| {
+                        unsafe { mm.release_all_memory_for_process(new_pid) };
+                        e
+                    })
+                })?;
+            }
             arch::process::Process::create(new_pid, init_process);
             let ppid = crate::arch::process::current_pid();
             // println!("Creating new process for PID {} with PPID {}", new_pid, ppid);
             entry.state = ProcessState::Allocated;
             entry.ppid = ppid;
+            entry.owner = ppid;
             entry.pid = new_pid;
+            entry.cpu_time = 0;
+            entry.thread_cpu_time = [0; arch::process::MAX_THREAD + 1];
+            entry.heap_size = 0;
+            entry.priority = Priority::Normal;
+            #[cfg(all(baremetal, feature = "sched-latency"))]
+            crate::latency::forget(new_pid);
             return Ok(new_pid);
         }
         Err(xous_kernel::Error::ProcessNotFound)
     }
 
+    /// Whether `pid` may create processes and fill them in. Only the app
+    /// loader, which checks the signatures of what it loads, is allowed to.
+    /// It's known by the name it was built with, which nothing created
+    /// afterwards can take.
+    #[cfg(baremetal)]
+    pub fn may_create_processes(&self, pid: PID) -> bool {
+        self.process_name(pid) == Some("app-loader")
+    }
+
+    /// Processes in hosted mode are threads of the test harness, or separate
+    /// programs the kernel knows nothing about, and may all create processes.
+    #[cfg(not(baremetal))]
+    pub fn may_create_processes(&self, _pid: PID) -> bool {
+        true
+    }
+
+    /// Look up a child of `caller` that was created but hasn't been started.
+    fn unstarted_child(&self, caller: PID, child: PID) -> Result<&Process, xous_kernel::Error> {
+        let process = self
+            .processes
+            .get(child.get() as usize - 1)
+            .filter(|process| !process.free())
+            .ok_or(xous_kernel::Error::ProcessNotFound)?;
+        if child == caller || process.owner != caller {
+            return Err(xous_kernel::Error::ProcessNotChild);
+        }
+        if process.state != ProcessState::Allocated {
+            return Err(xous_kernel::Error::AccessDenied);
+        }
+        Ok(process)
+    }
+
+    /// Move pages of the current process into `child`, which it created and
+    /// hasn't started yet, such that they appear at `dest` there. This is
+    /// how a loader fills in a new program. The pages keep their flags.
+    #[cfg(baremetal)]
+    pub fn move_memory_to_child(
+        &mut self,
+        child: PID,
+        src: MemoryRange,
+        dest: usize,
+    ) -> Result<(), xous_kernel::Error> {
+        let caller = self.current_pid();
+        let dest_mapping = self.unstarted_child(caller, child)?.mapping;
+        let src_mapping = self.get_process(caller)?.mapping;
+        let len = src.len();
+        if dest & 0xfff != 0 || len & 0xfff != 0 {
+            return Err(xous_kernel::Error::BadAlignment);
+        }
+        match dest.checked_add(len) {
+            Some(end) if dest != 0 && end <= arch::mem::USER_AREA_END => (),
+            _ => return Err(xous_kernel::Error::BadAddress),
+        }
+        for page in (0..len).step_by(arch::mem::PAGE_SIZE) {
+            if arch::mem::page_is_lent(src.as_mut_ptr().wrapping_add(page)) {
+                return Err(xous_kernel::Error::ShareViolation);
+            }
+        }
+
+        // Mapping a page twice is fatal, so make sure the pages are free first
+        dest_mapping.activate()?;
+        let free = (dest..dest + len)
+            .step_by(arch::mem::PAGE_SIZE)
+            .all(arch::mem::address_available);
+        src_mapping.activate()?;
+        if !free {
+            return Err(xous_kernel::Error::MemoryInUse);
+        }
+        self.send_memory(src.as_mut_ptr() as *mut usize, child, dest as *mut usize, len)
+            .map(|_| ())
+    }
+
+    #[cfg(not(baremetal))]
+    pub fn move_memory_to_child(
+        &mut self,
+        _child: PID,
+        _src: MemoryRange,
+        _dest: usize,
+    ) -> Result<(), xous_kernel::Error> {
+        Err(xous_kernel::Error::UnhandledSyscall)
+    }
+
+    /// Start the first thread of `child`, which the current process created,
+    /// at `entrypoint`. It gets a stack at the same place as the processes
+    /// started at boot, and its PID as its first argument.
+    #[cfg(baremetal)]
+    pub fn start_process(&mut self, child: PID, entrypoint: usize) -> Result<(), xous_kernel::Error> {
+        let caller = self.current_pid();
+        let child_mapping = self.unstarted_child(caller, child)?.mapping;
+        let caller_mapping = self.get_process(caller)?.mapping;
+        let top = arch::mem::DEFAULT_STACK_TOP;
+        let size = crate::arch::process::DEFAULT_STACK_SIZE;
+
+        child_mapping.activate()?;
+        let stack =
+            crate::mem::MemoryManager::with_mut(|mm| mm.map_stack(child, top, size));
+        caller_mapping.activate()?;
+        stack?;
+
+        let init = ThreadInit::new(
+            entrypoint,
+            unsafe { MemoryRange::new(top - size, size)? },
+            child.get() as usize,
+            0,
+            0,
+            0,
+        );
+        self.hand_over_child(caller, child, init)
+    }
+
+    #[cfg(not(baremetal))]
+    pub fn start_process(&mut self, _child: PID, _entrypoint: usize) -> Result<(), xous_kernel::Error> {
+        Err(xous_kernel::Error::UnhandledSyscall)
+    }
+
+    /// Make `child`, which `caller` created, ready to run its first thread
+    /// from `init`. It becomes a child of PID 1 so that it gets scheduled and
+    /// may start servers like the processes started at boot, but only
+    /// `caller` may still stop it or change its priority.
+    pub fn hand_over_child(
+        &mut self,
+        caller: PID,
+        child: PID,
+        init: ThreadInit,
+    ) -> Result<(), xous_kernel::Error> {
+        self.unstarted_child(caller, child)?;
+        let process = self.get_process_mut(child)?;
+        process.state = ProcessState::Setup(init);
+        process.ppid = unsafe { PID::new_unchecked(1) };
+        process.owner = caller;
+        Ok(())
+    }
+
+    /// Add to the CPU time of the given thread, and of its process. Processes
+    /// that have gone away in the meantime are ignored.
+    #[cfg(baremetal)]
+    pub fn charge_cpu_time(&mut self, pid: PID, tid: TID, cycles: u64) {
+        if let Ok(process) = self.get_process_mut(pid) {
+            process.cpu_time = process.cpu_time.wrapping_add(cycles);
+            if let Some(thread) = process.thread_cpu_time.get_mut(tid) {
+                *thread = thread.wrapping_add(cycles);
+            }
+        }
+    }
+
+    /// Keep the record of a fault that is about to take down a process,
+    /// replacing the one before it.
+    #[cfg(baremetal)]
+    pub fn record_crash(&mut self, record: &xous_kernel::CrashRecord) {
+        self.crash.record = record.to_words();
+        self.crash.sequence = self.crash.sequence.wrapping_add(1).max(1);
+    }
+
+    /// Return the number of faults recorded so far along with one word of
+    /// the last one. The first process to ask becomes the only one that may.
+    pub fn crash_record_word(
+        &mut self,
+        caller: PID,
+        index: usize,
+    ) -> Result<(usize, usize), xous_kernel::Error> {
+        if *self.crash.reader.get_or_insert(caller) != caller {
+            return Err(xous_kernel::Error::AccessDenied);
+        }
+        let word = self
+            .crash
+            .record
+            .get(index)
+            .ok_or(xous_kernel::Error::InvalidSyscall)?;
+        Ok((self.crash.sequence, *word))
+    }
+
+    /// Add a step of a message to the IPC trace, if tracing is on. `size` is
+    /// the length of any memory that goes with it.
+    pub fn trace_ipc(
+        &mut self,
+        kind: IpcTraceKind,
+        client: PID,
+        sidx: usize,
+        message: Option<&Message>,
+        size: usize,
+    ) {
+        if !self.trace.enabled {
+            return;
+        }
+        let server = match self.server_from_sidx(sidx) {
+            Some(server) => server.pid,
+            None => return,
+        };
+        #[cfg(baremetal)]
+        let timestamp = crate::arch::cycles();
+        #[cfg(not(baremetal))]
+        let timestamp = std::time::UNIX_EPOCH
+            .elapsed()
+            .map(|t| t.as_nanos() as u64)
+            .unwrap_or(0);
+        let event = IpcTraceEvent {
+            timestamp,
+            kind,
+            blocking: message.map(|m| m.is_blocking()).unwrap_or(true),
+            client: client.get(),
+            server: server.get(),
+            server_index: sidx as u8,
+            id: message.map(|m| m.id()).unwrap_or(0),
+            size,
+        };
+        self.trace.events[self.trace.count % IPC_TRACE_EVENTS] = event.to_words();
+        self.trace.count = self.trace.count.wrapping_add(1);
+    }
+
+    /// Turn the IPC trace on or off on behalf of `caller`. The first process
+    /// to touch the trace becomes the only one that may.
+    pub fn set_ipc_trace(&mut self, caller: PID, enable: bool) -> Result<(), xous_kernel::Error> {
+        if *self.trace.owner.get_or_insert(caller) != caller {
+            return Err(xous_kernel::Error::AccessDenied);
+        }
+        if enable && !self.trace.enabled {
+            self.trace.count = 0;
+        }
+        self.trace.enabled = enable;
+        Ok(())
+    }
+
+    /// Return the number of events traced so far, along with one word of the
+    /// trace, counting from the oldest event kept.
+    pub fn ipc_trace_word(
+        &mut self,
+        caller: PID,
+        index: usize,
+    ) -> Result<(usize, usize), xous_kernel::Error> {
+        if *self.trace.owner.get_or_insert(caller) != caller {
+            return Err(xous_kernel::Error::AccessDenied);
+        }
+        let event = index / IPC_TRACE_EVENT_WORDS;
+        if event >= IPC_TRACE_EVENTS {
+            return Err(xous_kernel::Error::InvalidSyscall);
+        }
+        let oldest = if self.trace.count > IPC_TRACE_EVENTS {
+            self.trace.count % IPC_TRACE_EVENTS
+        } else {
+            0
+        };
+        let words = &self.trace.events[(oldest + event) % IPC_TRACE_EVENTS];
+        Ok((self.trace.count, words[index % IPC_TRACE_EVENT_WORDS]))
+    }
+
+    /// Return the CPU cycles the given process has spent running, or with
+    /// `None`, the cycles the CPU has run since reset. The latter keeps
+    /// counting when processes exit, so it can be used to work out shares.
+    pub fn cpu_time(&self, pid: Option<PID>) -> Result<u64, xous_kernel::Error> {
+        match pid {
+            Some(pid) => self
+                .processes
+                .get(pid.get() as usize - 1)
+                .filter(|process| process.state != ProcessState::Free)
+                .map(|process| process.cpu_time)
+                .ok_or(xous_kernel::Error::ProcessNotFound),
+            #[cfg(baremetal)]
+            None => Ok(crate::arch::cycles()),
+            #[cfg(not(baremetal))]
+            None => Ok(0),
+        }
+    }
+
+    /// Return the CPU cycles the given thread has spent running
+    pub fn thread_cpu_time(&self, pid: PID, tid: TID) -> Result<u64, xous_kernel::Error> {
+        let process = self
+            .processes
+            .get(pid.get() as usize - 1)
+            .filter(|process| process.state != ProcessState::Free)
+            .ok_or(xous_kernel::Error::ProcessNotFound)?;
+        process
+            .thread_cpu_time
+            .get(tid)
+            .copied()
+            .ok_or(xous_kernel::Error::ThreadNotAvailable)
+    }
+
+    /// Record the new size of a process' heap, after it has grown or shrunk
+    pub fn set_heap_size(&mut self, pid: PID, size: usize) {
+        if let Ok(process) = self.get_process_mut(pid) {
+            process.heap_size = size;
+        }
+    }
+
+    /// Get the size of the heap of `pid`, and the number of messages waiting
+    /// in the queues of all of the servers it owns.
+    pub fn process_stats(&self, pid: PID) -> Result<(usize, usize), xous_kernel::Error> {
+        let process = self
+            .processes
+            .get(pid.get() as usize - 1)
+            .filter(|process| process.state != ProcessState::Free)
+            .ok_or(xous_kernel::Error::ProcessNotFound)?;
+        let queued = self
+            .servers
+            .iter()
+            .flatten()
+            .filter(|server| server.pid == pid)
+            .map(|server| server.queue_depth().0)
+            .sum();
+        Ok((process.heap_size, queued))
+    }
+
+    /// Set the scheduling priority of `pid` on behalf of `caller`, which must
+    /// be the process itself or its owner. Only processes that own an
+    /// interrupt may be made `Realtime`.
+    pub fn set_priority(
+        &mut self,
+        caller: PID,
+        pid: PID,
+        priority: Priority,
+    ) -> Result<(), xous_kernel::Error> {
+        let process = self
+            .processes
+            .get_mut(pid.get() as usize - 1)
+            .filter(|process| !process.free())
+            .ok_or(xous_kernel::Error::ProcessNotFound)?;
+        if process.pid != caller && process.owner != caller {
+            return Err(xous_kernel::Error::ProcessNotChild);
+        }
+        if priority == Priority::Realtime && !crate::irq::pid_has_interrupts(pid) {
+            return Err(xous_kernel::Error::AccessDenied);
+        }
+        process.priority = priority;
+        Ok(())
+    }
+
     pub fn get_process(&self, pid: PID) -> Result<&Process, xous_kernel::Error> {
         // PID0 doesn't exist -- process IDs are offset by 1.
         let pid_idx = pid.get() as usize - 1;
@@ -643,7 +1125,11 @@ impl SystemServices {
                 ProcessState::Running(x | (1 << tid))
             }
             ProcessState::Ready(x) if x & (1 << tid) == 0 => ProcessState::Ready(x | (1 << tid)),
-            ProcessState::Sleeping => ProcessState::Ready(1 << tid),
+            ProcessState::Sleeping => {
+                #[cfg(all(baremetal, feature = "sched-latency"))]
+                crate::latency::woken(pid);
+                ProcessState::Ready(1 << tid)
+            }
             ProcessState::Debug(x) if x & (1 << tid) == 0 => ProcessState::Debug(x | (1 << tid)),
             ProcessState::Exception(ready_threads)
             | ProcessState::BlockedException(ready_threads) => {
@@ -977,6 +1463,8 @@ impl SystemServices {
             // point onward, we will need to activate the previous memory space
             // if we encounter an error.
             new.mapping.activate()?;
+            #[cfg(all(baremetal, feature = "sched-latency"))]
+            crate::latency::ran(new_pid, new.priority);
 
             // Set up the new process, if necessary.  Remove the new thread from
             // the list of ready threads.
@@ -1250,7 +1738,8 @@ impl SystemServices {
     ///
     /// During this process, memory is marked as `Shared` in the source process.
     /// If the share is Mutable, then this memory is unmapped from the source
-    /// process.  If the share is immutable, then memory is marked as
+    /// process, unless it is also `shared`, in which case both processes
+    /// keep it mapped.  If the share is immutable, then memory is marked as
     /// not-writable in the source process.
     ///
     /// If no free slot can be found, memory is re-attached to the first
@@ -1279,6 +1768,7 @@ impl SystemServices {
         dest_virt: *mut usize,
         len: usize,
         mutable: bool,
+        shared: bool,
     ) -> Result<*mut usize, xous_kernel::Error> {
         if len == 0 {
             return Err(xous_kernel::Error::BadAddress);
@@ -1339,6 +1829,7 @@ impl SystemServices {
                     &dest_mapping,
                     dest_virt.wrapping_add(offset) as *mut u8,
                     mutable,
+                    shared,
                 )
                 .unwrap_or_else(|e| {
                     error = Some(e);
@@ -1371,10 +1862,133 @@ impl SystemServices {
         _dest_virt: *mut usize,
         _len: usize,
         _mutable: bool,
+        _shared: bool,
     ) -> Result<*mut usize, xous_kernel::Error> {
         Ok(src_virt)
     }
 
+    /// Lend several ranges of the current process to the server in slot
+    /// `sidx`, side by side, so the server sees them as a single buffer,
+    /// which is returned. The ranges are remembered until the server gives
+    /// the buffer back, see `take_vectored()`.
+    pub fn lend_vectored(
+        &mut self,
+        sidx: usize,
+        segments: &[Option<MemoryRange>; MAX_LEND_SEGMENTS],
+        dest_pid: PID,
+        mutable: bool,
+    ) -> Result<MemoryRange, xous_kernel::Error> {
+        let slot = self
+            .vectored
+            .iter()
+            .position(|v| v.is_none())
+            .ok_or(xous_kernel::Error::OutOfMemory)?;
+        let len: usize = segments.iter().flatten().map(|segment| segment.len()).sum();
+
+        // Find room for all of the ranges in a row
+        let src_mapping = self.get_process(self.current_pid())?.mapping;
+        let dest_mapping = self.get_process(dest_pid)?.mapping;
+        dest_mapping.activate()?;
+        let dest_virt = crate::mem::MemoryManager::with_mut(|mm| {
+            mm.find_virtual_address(
+                core::ptr::null_mut(),
+                len,
+                xous_kernel::MemoryType::Messages,
+            )
+        });
+        src_mapping.activate()?;
+        let dest_virt = dest_virt? as usize;
+
+        let mut offset = 0;
+        for (index, segment) in segments.iter().flatten().enumerate() {
+            if let Err(e) = self.lend_memory(
+                segment.as_mut_ptr() as *mut usize,
+                dest_pid,
+                (dest_virt + offset) as *mut usize,
+                segment.len(),
+                mutable,
+                false,
+            ) {
+                // Give back the segments that did make it across, so a
+                // failed call leaves the client's memory as it found it.
+                self.unlend_segments(segments, index, dest_pid, dest_virt);
+                return Err(e);
+            }
+            offset += segment.len();
+        }
+        self.vectored[slot] = Some(Vectored {
+            server: sidx,
+            server_addr: dest_virt,
+            segments: *segments,
+        });
+        unsafe { MemoryRange::new(dest_virt, len) }
+    }
+
+    /// Undo the first `count` segments of a `lend_vectored()` that failed
+    /// partway, moving them from `dest_virt` in `dest_pid` back to the
+    /// current process.
+    #[cfg(baremetal)]
+    fn unlend_segments(
+        &mut self,
+        segments: &[Option<MemoryRange>; MAX_LEND_SEGMENTS],
+        count: usize,
+        dest_pid: PID,
+        dest_virt: usize,
+    ) {
+        let current_pid = self.current_pid();
+        let (src_mapping, dest_mapping) = match (
+            self.get_process(current_pid).map(|p| p.mapping),
+            self.get_process(dest_pid).map(|p| p.mapping),
+        ) {
+            (Ok(src), Ok(dest)) => (src, dest),
+            _ => return,
+        };
+        crate::mem::MemoryManager::with_mut(|mm| {
+            let mut offset = 0;
+            for segment in segments.iter().flatten().take(count) {
+                for page in (0..segment.len()).step_by(crate::mem::PAGE_SIZE) {
+                    // `unlend_page()` runs from the borrower's address space
+                    // and leaves it active.
+                    dest_mapping.activate().unwrap();
+                    mm.unlend_page(
+                        &dest_mapping,
+                        (dest_virt + offset + page) as *mut u8,
+                        current_pid,
+                        &src_mapping,
+                        (segment.as_ptr() as usize + page) as *mut u8,
+                    )
+                    .expect("couldn't undo a partial vectored lend");
+                }
+                offset += segment.len();
+            }
+            src_mapping.activate().unwrap();
+        });
+    }
+
+    /// Hosted lends are copies, so there is nothing to undo.
+    #[cfg(not(baremetal))]
+    fn unlend_segments(
+        &mut self,
+        _segments: &[Option<MemoryRange>; MAX_LEND_SEGMENTS],
+        _count: usize,
+        _dest_pid: PID,
+        _dest_virt: usize,
+    ) {
+    }
+
+    /// If the memory at `server_addr` in the server in slot `sidx` was lent
+    /// in pieces, forget it and return the client's ranges it came from.
+    pub fn take_vectored(
+        &mut self,
+        sidx: usize,
+        server_addr: usize,
+    ) -> Option<[Option<MemoryRange>; MAX_LEND_SEGMENTS]> {
+        let slot = self.vectored.iter().position(
+            |v| matches!(v, Some(v) if v.server == sidx && v.server_addr == server_addr),
+        )?;
+        self.vectored[slot].take().map(|v| v.segments)
+    }
+
     /// Return memory from one process back to another
     ///
     /// During this process, memory is unmapped from the source process.
@@ -1516,6 +2130,9 @@ impl SystemServices {
             .ok_or(xous_kernel::Error::ThreadNotAvailable)?;
 
         arch_process.setup_thread(new_tid, thread_init)?;
+        if let Some(time) = process.thread_cpu_time.get_mut(new_tid) {
+            *time = 0;
+        }
 
         // println!("KERNEL({}): Created new thread {}", pid, new_tid);
 
@@ -1622,6 +2239,96 @@ impl SystemServices {
         }
     }
 
+    /// Park this thread until another thread of the same process wakes
+    /// `address`, unless the word there no longer holds `expected`. The kernel
+    /// runs with interrupts off, so the word can't change between the check
+    /// and the thread being parked.
+    ///
+    /// # Errors
+    ///
+    /// * **BadAlignment**: `address` isn't aligned to a word
+    /// * **BadAddress**: `address` isn't mapped in this process
+    ///
+    /// Hosted processes only share the kernel's address space when they're
+    /// threads of a test, so that's the only hosted build that has this.
+    #[cfg(any(baremetal, test))]
+    pub fn wait_address(
+        &mut self,
+        pid: PID,
+        tid: TID,
+        address: usize,
+        expected: usize,
+    ) -> Result<xous_kernel::Result, xous_kernel::Error> {
+        let current_pid = self.current_pid();
+        assert_eq!(pid, current_pid);
+
+        if address & (core::mem::size_of::<usize>() - 1) != 0 {
+            return Err(xous_kernel::Error::BadAlignment);
+        }
+        #[cfg(baremetal)]
+        if address >= crate::arch::mem::USER_AREA_END {
+            return Err(xous_kernel::Error::BadAddress);
+        }
+        // A page that was reserved but never touched gets backed now, and reads as zero
+        #[cfg(baremetal)]
+        crate::mem::MemoryManager::with_mut(|mm| mm.ensure_page_exists(address))?;
+        if unsafe { (address as *const usize).read_volatile() } != expected {
+            return Ok(xous_kernel::Result::Ok);
+        }
+
+        #[cfg(not(baremetal))]
+        self.address_waits.push((pid, tid, address));
+
+        let ppid = self.get_process(pid)?.ppid;
+        self.activate_process_thread(tid, ppid, 0, false)
+            .map(|_| Ok(xous_kernel::Result::ResumeProcess))
+            .unwrap_or(Err(xous_kernel::Error::ProcessNotFound))
+    }
+
+    /// Wake up to `count` threads of this process that were parked by
+    /// `wait_address()` on `address`, and return how many there were.
+    #[cfg(any(baremetal, test))]
+    pub fn wake_address(
+        &mut self,
+        pid: PID,
+        address: usize,
+        count: usize,
+    ) -> Result<usize, xous_kernel::Error> {
+        let current_pid = self.current_pid();
+        assert_eq!(pid, current_pid);
+
+        let mut woken = 0;
+        while woken < count {
+            let ready_threads = match self.get_process(pid)?.state {
+                ProcessState::Running(x) => x,
+                state => panic!("Process was in an invalid state: {:?}", state),
+            };
+            #[cfg(baremetal)]
+            let waiting_tid = match ArchProcess::current().find_thread(|waiting_tid, thr| {
+                (ready_threads & (1 << waiting_tid)) == 0 // Thread is waiting (i.e. not ready to run)
+                    && thr.a0() == (xous_kernel::SysCallNumber::WaitAddress as usize) // Thread called `WaitAddress`
+                    && thr.a1() == address // It is waiting on this address
+            }) {
+                Some((waiting_tid, _thread)) => waiting_tid,
+                None => break,
+            };
+            #[cfg(not(baremetal))]
+            let parked = self
+                .address_waits
+                .iter()
+                .position(|&(p, t, a)| p == pid && a == address && (ready_threads & (1 << t)) == 0);
+            #[cfg(not(baremetal))]
+            let waiting_tid = match parked {
+                Some(index) => self.address_waits.remove(index).1,
+                None => break,
+            };
+            self.ready_thread(pid, waiting_tid)?;
+            self.set_thread_result(pid, waiting_tid, xous_kernel::Result::Ok)?;
+            woken += 1;
+        }
+        Ok(woken)
+    }
+
     /// Allocate a new server ID for this process and return the address. If the
     /// server table is full, or if there is not enough memory to map the server queue,
     /// return an error.
@@ -1729,6 +2436,17 @@ impl SystemServices {
         }
 
         let server_idx = idx_to_destroy.ok_or(xous_kernel::Error::ServerNotFound)?;
+
+        // Threads waiting on this server among others are woken up, and will
+        // find out it's gone when they try to receive from it.
+        while self.servers[server_idx]
+            .as_ref()
+            .and_then(|server| server.selecting_thread())
+            .is_some()
+        {
+            self.wake_selecting_thread(server_idx)?;
+        }
+
         let server = self.servers[server_idx].take().unwrap();
         // Try to destroy the server. This will fail if the server
         // has any outstanding memory requests.
@@ -1761,9 +2479,107 @@ impl SystemServices {
 
         // Switch back to the primary process.
         self.get_process(pid).unwrap().activate().unwrap();
+        self.server_gone(server_idx);
         Ok(())
     }
 
+    /// Ask for a scalar message with id `opcode` to be sent to `notify`, a
+    /// server of `pid`, once the server behind `cid` is destroyed. Asking
+    /// again for the same connection replaces the earlier request.
+    pub fn notify_server_exit(
+        &mut self,
+        pid: PID,
+        cid: CID,
+        notify: SID,
+        opcode: usize,
+    ) -> Result<(), xous_kernel::Error> {
+        let server = self
+            .sidx_from_cid(cid)
+            .ok_or(xous_kernel::Error::ServerNotFound)?;
+        let notify = self
+            .sidx_from_sid(notify, pid)
+            .ok_or(xous_kernel::Error::ServerNotFound)?;
+        let watch = Watch {
+            pid,
+            cid,
+            server,
+            notify,
+            opcode,
+        };
+        let slot = match self
+            .watches
+            .iter()
+            .position(|w| matches!(w, Some(w) if w.pid == pid && w.cid == cid))
+        {
+            Some(idx) => idx,
+            None => self
+                .watches
+                .iter()
+                .position(|w| w.is_none())
+                .ok_or(xous_kernel::Error::OutOfMemory)?,
+        };
+        self.watches[slot] = Some(watch);
+        Ok(())
+    }
+
+    /// Tell whoever asked that the server in slot `sidx` is gone, and drop
+    /// the requests that were leaning on it.
+    fn server_gone(&mut self, sidx: usize) {
+        // Nothing lent to it will come back through a return
+        for vectored in self.vectored.iter_mut() {
+            if matches!(vectored, Some(v) if v.server == sidx) {
+                *vectored = None;
+            }
+        }
+        for idx in 0..self.watches.len() {
+            let watch = match self.watches[idx] {
+                Some(watch) if watch.server == sidx || watch.notify == sidx => watch,
+                _ => continue,
+            };
+            self.watches[idx] = None;
+            if watch.server != sidx {
+                continue;
+            }
+            let notice = Message::Scalar(ScalarMessage {
+                id: watch.opcode,
+                arg1: watch.cid as usize,
+                arg2: 0,
+                arg3: 0,
+                arg4: 0,
+            });
+            // The watcher may be gone too, or its queue may be full, and
+            // there's nobody to tell about that
+            self.send_notice(watch.notify, notice).ok();
+        }
+    }
+
+    /// Hand a non-blocking message from the kernel to the server in slot
+    /// `sidx`, the same way `SendMessage` would.
+    fn send_notice(&mut self, sidx: usize, notice: Message) -> Result<(), xous_kernel::Error> {
+        let kernel_pid = PID::new(1).unwrap();
+        let server = self
+            .server_from_sidx_mut(sidx)
+            .ok_or(xous_kernel::Error::ServerNotFound)?;
+        let server_pid = server.pid;
+        if let Some(server_tid) = server.take_available_thread() {
+            let envelope = MessageEnvelope {
+                sender: crate::server::SenderID::new(sidx, 0, Some(kernel_pid)).into(),
+                body: notice,
+            };
+            self.ready_thread(server_pid, server_tid)?;
+            if !cfg!(baremetal) {
+                self.switch_to_thread(server_pid, Some(server_tid))?;
+            }
+            return self.set_thread_result(
+                server_pid,
+                server_tid,
+                xous_kernel::Result::Message(envelope),
+            );
+        }
+        self.queue_server_message(sidx, kernel_pid, 0, notice, None)?;
+        self.wake_selecting_thread(sidx)
+    }
+
     /// Connect to a server on behalf of another process.
     pub fn connect_process_to_server(
         &mut self,
@@ -1874,7 +2690,25 @@ impl SystemServices {
             *idx = None;
             klog!("Removing server from table");
             Ok(())
-        })
+        })?;
+        // Once the connection is given up, its server is nothing to us
+        for watch in self.watches.iter_mut() {
+            if matches!(watch, Some(w) if w.pid == pid && w.cid == cid) {
+                *watch = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the depth of the queue of the server with the given SID,
+    /// whichever process owns it; see `Server::queue_depth()`.
+    pub fn queue_depth(&self, sid: SID) -> Result<(usize, usize), xous_kernel::Error> {
+        self.servers
+            .iter()
+            .flatten()
+            .find(|server| server.sid == sid)
+            .map(|server| server.queue_depth())
+            .ok_or(xous_kernel::Error::ServerNotFound)
     }
 
     /// Retrieve the server ID index from the specified SID.
@@ -1892,6 +2726,31 @@ impl SystemServices {
         None
     }
 
+    /// If a thread is waiting for a message to be queued on the given server,
+    /// stop it waiting on all of the servers in its set and let it run again.
+    /// Its `WaitMessage` call returns `Ok`, and it goes on to pick up the
+    /// message itself.
+    pub fn wake_selecting_thread(&mut self, sidx: usize) -> Result<(), xous_kernel::Error> {
+        let server = self
+            .server_from_sidx(sidx)
+            .ok_or(xous_kernel::Error::ServerNotFound)?;
+        let server_pid = server.pid;
+        let tid = match server.selecting_thread() {
+            Some(tid) => tid,
+            None => return Ok(()),
+        };
+        for server in self.servers.iter_mut().flatten() {
+            if server.pid == server_pid {
+                server.unpark_selecting_thread(tid);
+            }
+        }
+        self.set_thread_result(server_pid, tid, xous_kernel::Result::Ok)?;
+        if cfg!(baremetal) {
+            self.ready_thread(server_pid, tid)?;
+        }
+        Ok(())
+    }
+
     /// Return a server based on the connection id and the current process
     pub fn server_from_sidx(&self, sidx: usize) -> Option<&Server> {
         if sidx > self.servers.len() {
@@ -2025,6 +2884,38 @@ impl SystemServices {
 
     /// Terminate the given process. Returns the process' parent PID.
     pub fn terminate_process(&mut self, target_pid: PID) -> Result<PID, xous_kernel::Error> {
+        let parent_pid = self.release_process(target_pid)?;
+        self.switch_to_thread(parent_pid, None).unwrap();
+        Ok(parent_pid)
+    }
+
+    /// Stop a child of the current process and free everything it had, as
+    /// though it had terminated itself. The current process keeps running.
+    #[cfg(baremetal)]
+    pub fn destroy_process(&mut self, child: PID) -> Result<(), xous_kernel::Error> {
+        let caller = self.current_pid();
+        let process = self
+            .processes
+            .get(child.get() as usize - 1)
+            .filter(|process| !process.free())
+            .ok_or(xous_kernel::Error::ProcessNotFound)?;
+        if child == caller || process.owner != caller {
+            return Err(xous_kernel::Error::ProcessNotChild);
+        }
+        let released = self.release_process(child);
+        // Tearing down the child leaves some other address space active
+        self.get_process(caller)?.activate()?;
+        released.map(|_| ())
+    }
+
+    #[cfg(not(baremetal))]
+    pub fn destroy_process(&mut self, _child: PID) -> Result<(), xous_kernel::Error> {
+        Err(xous_kernel::Error::UnhandledSyscall)
+    }
+
+    /// Disconnect `target_pid` from everything and free it, returning the PID
+    /// of its parent. Whichever process was looked at last is left active.
+    fn release_process(&mut self, target_pid: PID) -> Result<PID, xous_kernel::Error> {
         // To terminate a process, we must perform the following:
         //
         // 1. If we have any client connections, remove them.
@@ -2067,12 +2958,10 @@ impl SystemServices {
         }
 
         // Now that the server has been "Disconnected", free the server entry.
-        #[allow(clippy::manual_flatten)]
-        for server in self.servers.iter_mut() {
-            if let Some(server_inner) = server {
-                if server_inner.pid == target_pid {
-                    *server = None;
-                }
+        for sidx in 0..self.servers.len() {
+            if matches!(&self.servers[sidx], Some(server) if server.pid == target_pid) {
+                self.servers[sidx] = None;
+                self.server_gone(sidx);
             }
         }
 
@@ -2081,8 +2970,6 @@ impl SystemServices {
         let parent_pid = process.ppid;
         process.terminate()?;
 
-        self.switch_to_thread(parent_pid, None).unwrap();
-
         Ok(parent_pid)
     }
 
@@ -2239,9 +3126,35 @@ impl SystemServices {
         Ok(())
     }
 
+    /// Returns the length of the name of `pid`, along with the four bytes of
+    /// it at `index * 4`. A process without a name has a length of 0.
+    #[cfg(baremetal)]
+    pub fn process_name_word(
+        &self,
+        pid: PID,
+        index: usize,
+    ) -> Result<(usize, usize), xous_kernel::Error> {
+        self.processes
+            .get(pid.get() as usize - 1)
+            .filter(|process| process.state != ProcessState::Free)
+            .ok_or(xous_kernel::Error::ProcessNotFound)?;
+        let name = self.process_name(pid).unwrap_or("").as_bytes();
+        if index > 0 && index * 4 >= name.len() {
+            return Err(xous_kernel::Error::InvalidSyscall);
+        }
+        let mut word = [0u8; 4];
+        for (dest, src) in word.iter_mut().zip(name.iter().skip(index * 4)) {
+            *dest = *src;
+        }
+        Ok((name.len(), u32::from_le_bytes(word) as usize))
+    }
+
     /// Returns the process name, if any, of a given PID
     #[cfg(baremetal)]
     pub fn process_name(&self, pid: PID) -> Option<&str> {
+        if !self.processes.get(pid.get() as usize - 1)?.boot_name {
+            return None;
+        }
         let args = crate::args::KernelArguments::get();
         for arg in args.iter() {
             if arg.name != u32::from_le_bytes(*b"PNam") {
@@ -64,7 +64,9 @@ fn do_yield(_pid: PID, tid: TID) -> SysCallResult {
     })
 }
 
-fn send_message(pid: PID, thread: TID, cid: CID, message: Message) -> SysCallResult {
+/// Send `message` to the server behind `cid`. A `shared` `MutableBorrow` stays
+/// mapped in the sender while it's lent, see `SysCall::ShareMemory`.
+fn send_message(pid: PID, thread: TID, cid: CID, message: Message, shared: bool) -> SysCallResult {
     SystemServices::with_mut(|ss| {
         let sidx = ss
             .sidx_from_cid(cid)
@@ -85,9 +87,7 @@ fn send_message(pid: PID, thread: TID, cid: CID, message: Message) -> SysCallRes
         };
 
         // Translate memory messages from the client process to the server
-        // process. Additionally, determine whether the call is blocking. If
-        // so, switch to the server context right away.
-        let blocking = message.is_blocking();
+        // process.
         let message = match message {
             Message::Scalar(_) | Message::BlockingScalar(_) => message,
             Message::Move(msg) => {
@@ -111,9 +111,17 @@ fn send_message(pid: PID, thread: TID, cid: CID, message: Message) -> SysCallRes
                     core::ptr::null_mut(),
                     msg.buf.len(),
                     true,
+                    shared,
                 )?;
+                // The flag tells the server that we can still write to the memory, so
+                // it's only ever there because the kernel put it there
+                let id = if shared {
+                    msg.id | SHARED_MESSAGE_FLAG
+                } else {
+                    msg.id & !SHARED_MESSAGE_FLAG
+                };
                 Message::MutableBorrow(MemoryMessage {
-                    id: msg.id,
+                    id,
                     buf: unsafe { MemoryRange::new(new_virt as usize, msg.buf.len()) }?,
                     offset: msg.offset,
                     valid: msg.valid,
@@ -126,6 +134,7 @@ fn send_message(pid: PID, thread: TID, cid: CID, message: Message) -> SysCallRes
                     core::ptr::null_mut(),
                     msg.buf.len(),
                     false,
+                    false,
                 )?;
                 // println!(
                 //     "Lending {} bytes from {:08x} in PID {} to {:08x} in PID {}",
@@ -143,139 +152,211 @@ fn send_message(pid: PID, thread: TID, cid: CID, message: Message) -> SysCallRes
                 })
             }
         };
+        deliver_message(ss, pid, thread, sidx, server_pid, message, client_address)
+    })
+}
 
-        // If the server has an available thread to receive the message,
-        // transfer it right away.
-        let server = ss
-            .server_from_sidx_mut(sidx)
-            .expect("server couldn't be located");
-        if let Some(server_tid) = server.take_available_thread() {
-            // klog!(
-            //     "there are threads available in PID {} to handle this message -- marking as Ready",
-            //     server_pid
-            // );
-            let sender_idx = if message.is_blocking() {
-                ss.remember_server_message(sidx, pid, thread, &message, client_address)
-                    .map_err(|e| {
-                        klog!("error remembering server message: {:?}", e);
-                        ss.server_from_sidx_mut(sidx)
-                            .expect("server couldn't be located")
-                            .return_available_thread(server_tid);
-                        e
-                    })?
-            } else {
-                0
-            };
-            let sender = SenderID::new(sidx, sender_idx, Some(pid));
-            klog!(
-                "server connection data: sidx: {}, idx: {}, server pid: {}",
-                sidx,
-                sender_idx,
-                server_pid
-            );
-            let envelope = MessageEnvelope {
-                sender: sender.into(),
-                body: message,
-            };
+/// Lend `segments` to the server behind `cid` side by side, as one buffer,
+/// see `SysCall::LendVectored`.
+fn send_vectored(
+    pid: PID,
+    thread: TID,
+    cid: CID,
+    id: usize,
+    mutable: bool,
+    segments: [Option<MemoryRange>; MAX_LEND_SEGMENTS],
+) -> SysCallResult {
+    SystemServices::with_mut(|ss| {
+        let sidx = ss
+            .sidx_from_cid(cid)
+            .ok_or(xous_kernel::Error::ServerNotFound)?;
+        let server_pid = ss
+            .server_from_sidx(sidx)
+            .expect("server couldn't be located")
+            .pid;
+        // Within one process there's nothing to lend, so the pieces would
+        // stay where they are instead of lining up
+        let first = segments[0].ok_or(xous_kernel::Error::BadAddress)?;
+        if server_pid == pid {
+            return Err(xous_kernel::Error::BadAddress);
+        }
+        let buf = ss.lend_vectored(sidx, &segments, server_pid, mutable)?;
+        let message = MemoryMessage {
+            id,
+            buf,
+            offset: None,
+            valid: None,
+        };
+        let message = if mutable {
+            Message::MutableBorrow(message)
+        } else {
+            Message::Borrow(message)
+        };
+        deliver_message(
+            ss,
+            pid,
+            thread,
+            sidx,
+            server_pid,
+            message,
+            MemoryAddress::new(first.as_ptr() as usize),
+        )
+    })
+}
 
-            // Mark the server's context as "Ready". If this fails, return the context
-            // to the blocking list.
-            ss.ready_thread(server_pid, server_tid).map_err(|e| {
-                ss.server_from_sidx_mut(sidx)
-                    .expect("server couldn't be located")
-                    .return_available_thread(server_tid);
-                e
-            })?;
+/// Hand a message that has already been moved into the server's address
+/// space to the server in slot `sidx`, either straight to a waiting thread or
+/// into the server's queue.
+fn deliver_message(
+    ss: &mut SystemServices,
+    pid: PID,
+    thread: TID,
+    sidx: usize,
+    server_pid: PID,
+    message: Message,
+    client_address: Option<MemoryAddress>,
+) -> SysCallResult {
+    // A blocking call switches to the server context right away
+    let blocking = message.is_blocking();
+    let size = message.memory_message().map(|m| m.buf.len()).unwrap_or(0);
+    ss.trace_ipc(IpcTraceKind::Send, pid, sidx, Some(&message), size);
 
-            let runnable = ss
-                .runnable(server_pid, Some(server_tid))
-                .expect("server doesn't exist");
-            // --- NOTE: Returning this value //
-            return if blocking && cfg!(baremetal) {
-                if !runnable {
-                    // If it's not runnable (e.g. it's being debugged), switch to the parent.
-                    let (ppid, ptid) = unsafe { SWITCHTO_CALLER.take().unwrap() };
-                    klog!("Activating Server parent process (server is blocked) and switching away from Client");
-                    ss.set_thread_result(
-                        server_pid,
-                        server_tid,
-                        xous_kernel::Result::Message(envelope),
-                    )
-                    .expect("couldn't set result for server thread");
-                    ss.activate_process_thread(thread, ppid, ptid, false)
-                        .map(|_| Ok(xous_kernel::Result::ResumeProcess))
-                        .unwrap_or(Err(xous_kernel::Error::ProcessNotFound))
-                } else {
-                    // Switch to the server, since it's in a state to be run.
-                    klog!("Activating Server context and switching away from Client");
-                    ss.activate_process_thread(thread, server_pid, server_tid, false)
-                        .map(|_| Ok(xous_kernel::Result::Message(envelope)))
-                        .unwrap_or(Err(xous_kernel::Error::ProcessNotFound))
-                }
-            } else if blocking && !cfg!(baremetal) {
-                klog!("Blocking client, since it sent a blocking message");
-                ss.unschedule_thread(pid, thread)?;
-                ss.switch_to_thread(server_pid, Some(server_tid))?;
-                ss.set_thread_result(
-                    server_pid,
-                    server_tid,
-                    xous_kernel::Result::Message(envelope),
-                )
-                .map(|_| xous_kernel::Result::BlockedProcess)
-            } else if cfg!(baremetal) {
-                klog!("Setting the return value of the Server ({}:{}) to {:?} and returning to Client",
-                    server_pid, server_tid, envelope);
-                ss.set_thread_result(
-                    server_pid,
-                    server_tid,
-                    xous_kernel::Result::Message(envelope),
-                )
-                .map(|_| xous_kernel::Result::Ok)
-            } else {
-                klog!(
-                    "setting the return value of the Server to {:?} and returning to Client",
-                    envelope
-                );
-                // "Switch to" the server PID when not running on bare metal. This ensures
-                // that it's "Running".
-                ss.switch_to_thread(server_pid, Some(server_tid))?;
+    // If the server has an available thread to receive the message,
+    // transfer it right away.
+    let server = ss
+        .server_from_sidx_mut(sidx)
+        .expect("server couldn't be located");
+    if let Some(server_tid) = server.take_available_thread() {
+        // klog!(
+        //     "there are threads available in PID {} to handle this message -- marking as Ready",
+        //     server_pid
+        // );
+        let sender_idx = if message.is_blocking() {
+            ss.remember_server_message(sidx, pid, thread, &message, client_address)
+                .map_err(|e| {
+                    klog!("error remembering server message: {:?}", e);
+                    ss.server_from_sidx_mut(sidx)
+                        .expect("server couldn't be located")
+                        .return_available_thread(server_tid);
+                    e
+                })?
+        } else {
+            0
+        };
+        let sender = SenderID::new(sidx, sender_idx, Some(pid));
+        ss.trace_ipc(IpcTraceKind::Receive, pid, sidx, Some(&message), size);
+        klog!(
+            "server connection data: sidx: {}, idx: {}, server pid: {}",
+            sidx,
+            sender_idx,
+            server_pid
+        );
+        let envelope = MessageEnvelope {
+            sender: sender.into(),
+            body: message,
+        };
+
+        // Mark the server's context as "Ready". If this fails, return the context
+        // to the blocking list.
+        ss.ready_thread(server_pid, server_tid).map_err(|e| {
+            ss.server_from_sidx_mut(sidx)
+                .expect("server couldn't be located")
+                .return_available_thread(server_tid);
+            e
+        })?;
+
+        let runnable = ss
+            .runnable(server_pid, Some(server_tid))
+            .expect("server doesn't exist");
+        // --- NOTE: Returning this value //
+        return if blocking && cfg!(baremetal) {
+            if !runnable {
+                // If it's not runnable (e.g. it's being debugged), switch to the parent.
+                let (ppid, ptid) = unsafe { SWITCHTO_CALLER.take().unwrap() };
+                klog!("Activating Server parent process (server is blocked) and switching away from Client");
                 ss.set_thread_result(
                     server_pid,
                     server_tid,
                     xous_kernel::Result::Message(envelope),
                 )
-                .map(|_| xous_kernel::Result::Ok)
-            };
-        }
-        klog!(
-            "no threads available in PID {} to handle this message, so queueing",
-            server_pid
-        );
-        // Add this message to the queue.  If the queue is full, this
-        // returns an error.
-        let _queue_idx = ss.queue_server_message(sidx, pid, thread, message, client_address)?;
-        klog!("queued into index {:x}", _queue_idx);
-
-        // Park this context if it's blocking.  This is roughly
-        // equivalent to a "Yield".
-        if blocking {
-            if cfg!(baremetal) {
-                // println!("Returning to parent");
-                let process = ss.get_process(pid).expect("Can't get current process");
-                let ppid = process.ppid;
-                unsafe { SWITCHTO_CALLER = None };
-                ss.activate_process_thread(thread, ppid, 0, false)
+                .expect("couldn't set result for server thread");
+                ss.activate_process_thread(thread, ppid, ptid, false)
                     .map(|_| Ok(xous_kernel::Result::ResumeProcess))
                     .unwrap_or(Err(xous_kernel::Error::ProcessNotFound))
             } else {
-                ss.unschedule_thread(pid, thread)?;
-                Ok(xous_kernel::Result::BlockedProcess)
+                // Switch to the server, since it's in a state to be run.
+                klog!("Activating Server context and switching away from Client");
+                ss.activate_process_thread(thread, server_pid, server_tid, false)
+                    .map(|_| Ok(xous_kernel::Result::Message(envelope)))
+                    .unwrap_or(Err(xous_kernel::Error::ProcessNotFound))
             }
+        } else if blocking && !cfg!(baremetal) {
+            klog!("Blocking client, since it sent a blocking message");
+            ss.unschedule_thread(pid, thread)?;
+            ss.switch_to_thread(server_pid, Some(server_tid))?;
+            ss.set_thread_result(
+                server_pid,
+                server_tid,
+                xous_kernel::Result::Message(envelope),
+            )
+            .map(|_| xous_kernel::Result::BlockedProcess)
+        } else if cfg!(baremetal) {
+            klog!("Setting the return value of the Server ({}:{}) to {:?} and returning to Client",
+                server_pid, server_tid, envelope);
+            ss.set_thread_result(
+                server_pid,
+                server_tid,
+                xous_kernel::Result::Message(envelope),
+            )
+            .map(|_| xous_kernel::Result::Ok)
         } else {
-            // println!("Returning to Client with Ok result");
-            Ok(xous_kernel::Result::Ok)
+            klog!(
+                "setting the return value of the Server to {:?} and returning to Client",
+                envelope
+            );
+            // "Switch to" the server PID when not running on bare metal. This ensures
+            // that it's "Running".
+            ss.switch_to_thread(server_pid, Some(server_tid))?;
+            ss.set_thread_result(
+                server_pid,
+                server_tid,
+                xous_kernel::Result::Message(envelope),
+            )
+            .map(|_| xous_kernel::Result::Ok)
+        };
+    }
+    klog!(
+        "no threads available in PID {} to handle this message, so queueing",
+        server_pid
+    );
+    // Add this message to the queue.  If the queue is full, this
+    // returns an error.
+    let _queue_idx = ss.queue_server_message(sidx, pid, thread, message, client_address)?;
+    klog!("queued into index {:x}", _queue_idx);
+
+    // If a thread is waiting on this server along with others, let it
+    // know there's something to receive.
+    ss.wake_selecting_thread(sidx)?;
+
+    // Park this context if it's blocking.  This is roughly
+    // equivalent to a "Yield".
+    if blocking {
+        if cfg!(baremetal) {
+            // println!("Returning to parent");
+            let process = ss.get_process(pid).expect("Can't get current process");
+            let ppid = process.ppid;
+            unsafe { SWITCHTO_CALLER = None };
+            ss.activate_process_thread(thread, ppid, 0, false)
+                .map(|_| Ok(xous_kernel::Result::ResumeProcess))
+                .unwrap_or(Err(xous_kernel::Error::ProcessNotFound))
+        } else {
+            ss.unschedule_thread(pid, thread)?;
+            Ok(xous_kernel::Result::BlockedProcess)
         }
-    })
+    } else {
+        // println!("Returning to Client with Ok result");
+        Ok(xous_kernel::Result::Ok)
+    }
 }
 
 fn return_memory(
@@ -337,6 +418,7 @@ fn return_memory(
                 return Err(xous_kernel::Error::ProcessNotFound);
             }
         };
+        ss.trace_ipc(IpcTraceKind::Return, client_pid, sender.sidx, None, len.get());
         // println!(
         //     "KERNEL({}): Returning {} bytes from {:08x} in PID {} to {:08x} in PID {} in context {}",
         //     pid,
@@ -352,14 +434,32 @@ fn return_memory(
         #[cfg(not(baremetal))]
         let src_virt = buf.as_ptr() as _;
 
-        // Return the memory to the calling process
-        ss.return_memory(
-            src_virt,
-            client_pid,
-            client_tid,
-            client_addr.get() as _,
-            len.get(),
-        )?;
+        // Return the memory to the calling process. Memory that was lent in
+        // pieces goes back piece by piece.
+        match ss.take_vectored(sender.sidx, src_virt as usize) {
+            Some(segments) => {
+                let mut offset = 0;
+                for segment in segments.iter().flatten() {
+                    ss.return_memory(
+                        (src_virt as usize + offset) as _,
+                        client_pid,
+                        client_tid,
+                        segment.as_ptr() as _,
+                        segment.len(),
+                    )?;
+                    offset += segment.len();
+                }
+            }
+            None => {
+                ss.return_memory(
+                    src_virt,
+                    client_pid,
+                    client_tid,
+                    client_addr.get() as _,
+                    len.get(),
+                )?;
+            }
+        }
 
         let client_is_runnable = ss.runnable(client_pid, Some(client_tid))?;
 
@@ -434,6 +534,7 @@ fn return_scalar(
                 return Err(xous_kernel::Error::ProcessNotFound);
             }
         };
+        ss.trace_ipc(IpcTraceKind::Return, client_pid, sender.sidx, None, 0);
 
         let client_is_runnable = ss.runnable(client_pid, Some(client_tid))?;
 
@@ -505,6 +606,7 @@ fn return_scalar2(
                 return Err(xous_kernel::Error::ProcessNotFound);
             }
         };
+        ss.trace_ipc(IpcTraceKind::Return, client_pid, sender.sidx, None, 0);
 
         let client_is_runnable = ss.runnable(client_pid, Some(client_tid))?;
 
@@ -561,6 +663,10 @@ fn receive_message(pid: PID, tid: TID, sid: SID, blocking: ExecutionType) -> Sys
         // If there is a pending message, return it immediately.
         if let Some(msg) = server.take_next_message(sidx) {
             klog!("waiting messages found -- returning {:x?}", msg);
+            if let Some(client) = msg.sender.pid() {
+                let size = msg.body.memory_message().map(|m| m.buf.len()).unwrap_or(0);
+                ss.trace_ipc(IpcTraceKind::Receive, client, sidx, Some(&msg.body), size);
+            }
             return Ok(xous_kernel::Result::Message(msg));
         }
 
@@ -597,6 +703,50 @@ fn receive_message(pid: PID, tid: TID, sid: SID, blocking: ExecutionType) -> Sys
     })
 }
 
+fn wait_message(pid: PID, tid: TID, cids: &[CID]) -> SysCallResult {
+    SystemServices::with_mut(|ss| {
+        if cids.is_empty() {
+            return Err(xous_kernel::Error::InvalidSyscall);
+        }
+        let mut sidxs = [0usize; xous_kernel::MAX_WAIT_SERVERS];
+        for (sidx, &cid) in sidxs.iter_mut().zip(cids.iter()) {
+            *sidx = ss
+                .sidx_from_cid(cid)
+                .ok_or(xous_kernel::Error::ServerNotFound)?;
+            let server = ss
+                .server_from_sidx(*sidx)
+                .ok_or(xous_kernel::Error::ServerNotFound)?;
+            // Only the process that owns a server may wait on it
+            if server.pid != pid {
+                return Err(xous_kernel::Error::ServerNotFound);
+            }
+            if server.has_queued_message() {
+                return Ok(xous_kernel::Result::Ok);
+            }
+        }
+
+        // Nothing is queued on any of the servers, so wait until something is.
+        // Whichever server gets a message first takes us off all the others.
+        klog!("no messages on {} servers -- parking thread {}", cids.len(), tid);
+        for &sidx in sidxs[..cids.len()].iter() {
+            ss.server_from_sidx_mut(sidx)
+                .expect("server couldn't be located")
+                .park_selecting_thread(tid);
+        }
+
+        if cfg!(baremetal) {
+            unsafe { SWITCHTO_CALLER = None };
+            let ppid = ss.get_process(pid).expect("Can't get current process").ppid;
+            ss.activate_process_thread(tid, ppid, 0, false)
+                .map(|_| Ok(xous_kernel::Result::ResumeProcess))
+                .unwrap_or(Err(xous_kernel::Error::ProcessNotFound))
+        } else {
+            ss.unschedule_thread(pid, tid)
+                .map(|_| xous_kernel::Result::BlockedProcess)
+        }
+    })
+}
+
 pub fn handle(pid: PID, tid: TID, in_irq: bool, call: SysCall) -> SysCallResult {
     #[cfg(feature = "debug-print")]
     print!("KERNEL({}:{}): Syscall {:x?}", pid, tid, call);
@@ -708,7 +858,7 @@ pub fn handle_inner(pid: PID, tid: TID, in_irq: bool, call: SysCall) -> SysCallR
             if delta & 0xfff != 0 {
                 return Err(xous_kernel::Error::BadAlignment);
             }
-            let start = {
+            let (start, heap_size) = {
                 ArchProcess::with_inner_mut(|process_inner| {
                     if process_inner.mem_heap_size + delta > process_inner.mem_heap_max {
                         return Err(xous_kernel::Error::OutOfMemory);
@@ -716,9 +866,10 @@ pub fn handle_inner(pid: PID, tid: TID, in_irq: bool, call: SysCall) -> SysCallR
 
                     let start = process_inner.mem_heap_base + process_inner.mem_heap_size;
                     process_inner.mem_heap_size += delta;
-                    Ok(start as *mut u8)
+                    Ok((start as *mut u8, process_inner.mem_heap_size))
                 })?
             };
+            SystemServices::with_mut(|ss| ss.set_heap_size(pid, heap_size));
             MemoryManager::with_mut(|mm| {
                 Ok(xous_kernel::Result::MemoryRange(
                     mm.reserve_range(start, delta, flags)?,
@@ -729,15 +880,16 @@ pub fn handle_inner(pid: PID, tid: TID, in_irq: bool, call: SysCall) -> SysCallR
             if delta & 0xfff != 0 {
                 return Err(xous_kernel::Error::BadAlignment);
             }
-            let start = ArchProcess::with_inner_mut(|process_inner| {
+            let (start, heap_size) = ArchProcess::with_inner_mut(|process_inner| {
                 if process_inner.mem_heap_size + delta > process_inner.mem_heap_max {
                     return Err(xous_kernel::Error::OutOfMemory);
                 }
 
                 let start = process_inner.mem_heap_base + process_inner.mem_heap_size;
                 process_inner.mem_heap_size -= delta;
-                Ok(start)
+                Ok((start, process_inner.mem_heap_size))
             })?;
+            SystemServices::with_mut(|ss| ss.set_heap_size(pid, heap_size));
             MemoryManager::with_mut(|mm| {
                 for page in ((start - delta)..start).step_by(crate::arch::mem::PAGE_SIZE) {
                     mm.unmap_page(page as *mut usize)
@@ -776,9 +928,52 @@ pub fn handle_inner(pid: PID, tid: TID, in_irq: bool, call: SysCall) -> SysCallR
             Ok(xous_kernel::Result::ResumeProcess)
         }
         SysCall::ReceiveMessage(sid) => receive_message(pid, tid, sid, ExecutionType::Blocking),
+        SysCall::SetPriority(target, priority) => SystemServices::with_mut(|ss| {
+            ss.set_priority(pid, target, priority)
+                .map(|_| xous_kernel::Result::Ok)
+        }),
+        SysCall::GetCrashRecord(index) => SystemServices::with_mut(|ss| {
+            let (sequence, word) = ss.crash_record_word(pid, index)?;
+            Ok(xous_kernel::Result::Scalar2(sequence, word))
+        }),
+        SysCall::GetThreadCpuTime(target, tid) => SystemServices::with(|ss| {
+            let cycles = ss.thread_cpu_time(target, tid)?;
+            Ok(xous_kernel::Result::Scalar2(
+                cycles as u32 as usize,
+                (cycles >> 32) as u32 as usize,
+            ))
+        }),
+        SysCall::GetCpuTime(pid) => SystemServices::with(|ss| {
+            let cycles = ss.cpu_time(pid)?;
+            Ok(xous_kernel::Result::Scalar2(
+                cycles as u32 as usize,
+                (cycles >> 32) as u32 as usize,
+            ))
+        }),
+        SysCall::WaitMessage(count, cids) => {
+            if count > cids.len() {
+                return Err(xous_kernel::Error::InvalidSyscall);
+            }
+            wait_message(pid, tid, &cids[..count])
+        }
         SysCall::TryReceiveMessage(sid) => {
             receive_message(pid, tid, sid, ExecutionType::NonBlocking)
         }
+        SysCall::GetProcessStats(target, index) => {
+            let (heap_size, queued) = SystemServices::with(|ss| ss.process_stats(target))?;
+            let (pages, _) = MemoryManager::with_mut(|mm| mm.pages_owned_by(target));
+            let value = match index {
+                0 => heap_size,
+                1 => pages,
+                2 => queued,
+                _ => return Err(xous_kernel::Error::InvalidSyscall),
+            };
+            Ok(xous_kernel::Result::Scalar1(value))
+        }
+        SysCall::GetQueueDepth(sid) => SystemServices::with(|ss| {
+            ss.queue_depth(sid)
+                .map(|(pending, capacity)| xous_kernel::Result::Scalar2(pending, capacity))
+        }),
         SysCall::WaitEvent => SystemServices::with_mut(|ss| {
             let process = ss.get_process(pid).expect("Can't get current process");
             let ppid = process.ppid;
@@ -802,9 +997,67 @@ pub fn handle_inner(pid: PID, tid: TID, in_irq: bool, call: SysCall) -> SysCallR
             })
         }),
         SysCall::CreateProcess(process_init) => SystemServices::with_mut(|ss| {
+            if !ss.may_create_processes(pid) {
+                return Err(xous_kernel::Error::AccessDenied);
+            }
             ss.create_process(process_init)
                 .map(xous_kernel::Result::ProcessID)
         }),
+        SysCall::MoveMemoryToProcess(child, range, dest) => SystemServices::with_mut(|ss| {
+            if !ss.may_create_processes(pid) {
+                return Err(xous_kernel::Error::AccessDenied);
+            }
+            ss.move_memory_to_child(child, range, dest.get())
+                .map(|_| xous_kernel::Result::Ok)
+        }),
+        SysCall::StartProcess(child, entrypoint) => SystemServices::with_mut(|ss| {
+            if !ss.may_create_processes(pid) {
+                return Err(xous_kernel::Error::AccessDenied);
+            }
+            ss.start_process(child, entrypoint)
+                .map(|_| xous_kernel::Result::Ok)
+        }),
+        SysCall::DestroyProcess(child) => SystemServices::with_mut(|ss| {
+            ss.destroy_process(child).map(|_| xous_kernel::Result::Ok)
+        }),
+        #[cfg(baremetal)]
+        SysCall::GetProcessName(target, index) => SystemServices::with(|ss| {
+            let (len, word) = ss.process_name_word(target, index)?;
+            Ok(xous_kernel::Result::Scalar2(len, word))
+        }),
+        SysCall::SetIpcTrace(enable) => SystemServices::with_mut(|ss| {
+            ss.set_ipc_trace(pid, enable).map(|_| xous_kernel::Result::Ok)
+        }),
+        SysCall::GetIpcTrace(index) => SystemServices::with_mut(|ss| {
+            let (count, word) = ss.ipc_trace_word(pid, index)?;
+            Ok(xous_kernel::Result::Scalar2(count, word))
+        }),
+        SysCall::NotifyServerExit(cid, notify, opcode) => SystemServices::with_mut(|ss| {
+            ss.notify_server_exit(pid, cid, notify, opcode)
+                .map(|_| xous_kernel::Result::Ok)
+        }),
+        SysCall::GetMemoryStats(index) => {
+            let (pages, peak) = MemoryManager::with_mut(|mm| mm.pages_owned_by(pid));
+            let value = match index {
+                0 => ArchProcess::with_inner(|process_inner| process_inner.mem_heap_size),
+                1 => ArchProcess::with_inner(|process_inner| process_inner.mem_heap_max),
+                2 => pages,
+                3 => peak,
+                _ => return Err(xous_kernel::Error::InvalidSyscall),
+            };
+            Ok(xous_kernel::Result::Scalar1(value))
+        }
+        #[cfg(all(baremetal, feature = "sched-latency"))]
+        SysCall::GetSchedLatency(priority, bucket) => crate::latency::count(priority, bucket)
+            .map(xous_kernel::Result::Scalar1)
+            .ok_or(xous_kernel::Error::InvalidSyscall),
+        #[cfg(all(baremetal, feature = "profile"))]
+        SysCall::SetProfile(enable) => {
+            crate::profile::set(pid, enable).map(|_| xous_kernel::Result::Ok)
+        }
+        #[cfg(all(baremetal, feature = "profile"))]
+        SysCall::GetProfile(index) => crate::profile::word(pid, index)
+            .map(|(count, word)| xous_kernel::Result::Scalar2(count, word)),
         SysCall::CreateServerWithAddress(name) => SystemServices::with_mut(|ss| {
             ss.create_server_with_address(pid, name)
                 .map(|(sid, cid)| xous_kernel::Result::NewServerID(sid, cid))
@@ -827,7 +1080,7 @@ pub fn handle_inner(pid: PID, tid: TID, in_irq: bool, call: SysCall) -> SysCallR
         SysCall::ReturnScalar2(sender, arg1, arg2) => {
             return_scalar2(pid, tid, in_irq, sender, arg1, arg2)
         }
-        SysCall::TrySendMessage(cid, message) => send_message(pid, tid, cid, message),
+        SysCall::TrySendMessage(cid, message) => send_message(pid, tid, cid, message, false),
         SysCall::TerminateProcess(_ret) => SystemServices::with_mut(|ss| {
             ss.unschedule_thread(pid, tid)?;
             ss.terminate_process(pid)?;
@@ -864,7 +1117,31 @@ pub fn handle_inner(pid: PID, tid: TID, in_irq: bool, call: SysCall) -> SysCallR
             }
         }
         SysCall::SendMessage(cid, message) => {
-            let result = send_message(pid, tid, cid, message);
+            let result = send_message(pid, tid, cid, message, false);
+            match result {
+                Ok(o) => Ok(o),
+                Err(xous_kernel::Error::ServerQueueFull) => retry_syscall(pid, tid),
+                Err(e) => Err(e),
+            }
+        }
+        SysCall::ShareMemory(cid, message) => {
+            // In hosted mode every process has memory of its own
+            if !cfg!(baremetal) {
+                return Err(xous_kernel::Error::UnhandledSyscall);
+            }
+            let result = send_message(pid, tid, cid, Message::MutableBorrow(message), true);
+            match result {
+                Ok(o) => Ok(o),
+                Err(xous_kernel::Error::ServerQueueFull) => retry_syscall(pid, tid),
+                Err(e) => Err(e),
+            }
+        }
+        SysCall::LendVectored(cid, id, mutable, segments) => {
+            // In hosted mode memory is copied, so there's nothing to line up
+            if !cfg!(baremetal) {
+                return Err(xous_kernel::Error::UnhandledSyscall);
+            }
+            let result = send_vectored(pid, tid, cid, id, mutable, segments);
             match result {
                 Ok(o) => Ok(o),
                 Err(xous_kernel::Error::ServerQueueFull) => retry_syscall(pid, tid),
@@ -884,6 +1161,20 @@ pub fn handle_inner(pid: PID, tid: TID, in_irq: bool, call: SysCall) -> SysCallR
                 ret
             })
         }
+        #[cfg(any(baremetal, test))]
+        SysCall::WaitAddress(address, expected) => {
+            SystemServices::with_mut(|ss| ss.wait_address(pid, tid, address, expected)).map(
+                |ret| {
+                    unsafe { SWITCHTO_CALLER = None };
+                    ret
+                },
+            )
+        }
+        #[cfg(any(baremetal, test))]
+        SysCall::WakeAddress(address, count) => SystemServices::with_mut(|ss| {
+            ss.wake_address(pid, address, count)
+                .map(xous_kernel::Result::Scalar1)
+        }),
         SysCall::UpdateMemoryFlags(range, flags, pid) => {
             // We do not yet support modifying flags for other processes.
             if pid.is_some() {
@@ -16,7 +16,7 @@ static GLOBAL: &StatsAlloc<std::alloc::System> = &INSTRUMENTED_SYSTEM;
 
 const SERVER_SPEC: &str = "127.0.0.1:0";
 
-use core::sync::atomic::{AtomicU64, Ordering};
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 static RNG_LOCAL_STATE: AtomicU64 = AtomicU64::new(1);
 
 fn start_kernel(server_spec: &str) -> JoinHandle<()> {
@@ -126,6 +126,290 @@ fn shutdown() {
     main_thread.join().expect("couldn't join main thread");
 }
 
+#[test]
+fn cpu_time() {
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let xous_process = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "cpu_time",
+        || {
+            // Time is only counted on hardware, but the process must exist
+            let pid = xous_kernel::current_pid().expect("couldn't get pid");
+            assert_eq!(xous_kernel::cpu_time(Some(pid)), Ok(0));
+            assert_eq!(xous_kernel::cpu_time(None), Ok(0));
+            assert_eq!(
+                xous_kernel::cpu_time(xous_kernel::PID::new(200)),
+                Err(xous_kernel::Error::ProcessNotFound)
+            );
+            let tid = xous_kernel::current_tid().expect("couldn't get tid");
+            assert_eq!(xous_kernel::thread_cpu_time(pid, tid), Ok(0));
+            assert_eq!(
+                xous_kernel::thread_cpu_time(pid, 200),
+                Err(xous_kernel::Error::ThreadNotAvailable)
+            );
+            assert_eq!(
+                xous_kernel::thread_cpu_time(xous_kernel::PID::new(200).unwrap(), tid),
+                Err(xous_kernel::Error::ProcessNotFound)
+            );
+        },
+    ))
+    .expect("couldn't spawn process");
+    crate::wait_process_as_thread(xous_process).expect("couldn't join process");
+
+    shutdown_kernel();
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+#[test]
+fn memory_stats() {
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let xous_process = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "memory_stats",
+        || {
+            // Pages are only counted on hardware, and a hosted process never grows its heap
+            let stats = xous_kernel::memory_stats().expect("couldn't get memory stats");
+            assert_eq!(stats.heap_used, 0);
+            assert!(stats.heap_limit > 0);
+            assert_eq!(stats.pages, 0);
+            assert_eq!(stats.peak_pages, 0);
+        },
+    ))
+    .expect("couldn't spawn process");
+    crate::wait_process_as_thread(xous_process).expect("couldn't join process");
+
+    shutdown_kernel();
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+#[test]
+fn set_priority() {
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let xous_process = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "set_priority",
+        || {
+            use xous_kernel::Priority;
+            let pid = xous_kernel::current_pid().expect("couldn't get pid");
+            xous_kernel::set_priority(pid, Priority::Background).expect("couldn't lower priority");
+            xous_kernel::set_priority(pid, Priority::Normal).expect("couldn't restore priority");
+            // This process owns no interrupts
+            assert_eq!(
+                xous_kernel::set_priority(pid, Priority::Realtime),
+                Err(xous_kernel::Error::AccessDenied)
+            );
+            assert_eq!(
+                xous_kernel::set_priority(xous_kernel::PID::new(200).unwrap(), Priority::Background),
+                Err(xous_kernel::Error::ProcessNotFound)
+            );
+        },
+    ))
+    .expect("couldn't spawn process");
+    crate::wait_process_as_thread(xous_process).expect("couldn't join process");
+
+    shutdown_kernel();
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+#[test]
+fn started_child_is_scheduled() {
+    use crate::arch::process::{set_current_pid, Process as ArchProcess};
+    use crate::services::SystemServices;
+    use xous_kernel::{Priority, ProcessInit, ProcessKey, ThreadInit, SID};
+
+    // This runs on the test's own thread, so it gets empty tables of its own
+    // rather than those of a running kernel.
+    let (pid1, loader, child) = SystemServices::with_mut(|ss| {
+        let mut create = |n| {
+            ss.create_process(ProcessInit { key: ProcessKey::new([n; 16]) })
+                .unwrap()
+        };
+        let pid1 = create(1);
+        let loader = create(2);
+        set_current_pid(loader);
+        let child = create(3);
+        (pid1, loader, child)
+    });
+    assert_eq!(crate::next_pid_to_run(None), None);
+
+    SystemServices::with_mut(|ss| {
+        assert_eq!(
+            ss.hand_over_child(pid1, child, ThreadInit {}),
+            Err(xous_kernel::Error::ProcessNotChild)
+        );
+        ss.hand_over_child(loader, child, ThreadInit {})
+            .expect("couldn't start child");
+        assert_eq!(ss.get_process(child).unwrap().ppid, pid1);
+        // Starting it twice isn't allowed
+        assert_eq!(
+            ss.hand_over_child(loader, child, ThreadInit {}),
+            Err(xous_kernel::Error::AccessDenied)
+        );
+
+        // The loader still owns it, even though PID 1 is its parent now
+        ss.set_priority(loader, child, Priority::Background)
+            .expect("loader couldn't set the priority");
+        assert_eq!(
+            ss.set_priority(pid1, child, Priority::Normal),
+            Err(xous_kernel::Error::ProcessNotChild)
+        );
+    });
+    assert_eq!(crate::next_pid_to_run(None), Some(child));
+
+    SystemServices::with_mut(|ss| {
+        set_current_pid(child);
+        ArchProcess::with_inner_mut(|process_inner| process_inner.pid = child);
+        ss.create_server_with_address(child, SID::from_bytes(b"started-child-sv").unwrap())
+            .expect("child couldn't create a server");
+    });
+}
+
+#[test]
+fn higher_priority_runs_first() {
+    use crate::arch::process::set_current_pid;
+    use crate::services::SystemServices;
+    use xous_kernel::{Priority, ProcessInit, ProcessKey, ThreadInit};
+
+    let (loader, first, second) = SystemServices::with_mut(|ss| {
+        let mut create = |n| {
+            ss.create_process(ProcessInit { key: ProcessKey::new([n; 16]) })
+                .unwrap()
+        };
+        create(1);
+        let loader = create(2);
+        set_current_pid(loader);
+        let first = create(3);
+        let second = create(4);
+        ss.hand_over_child(loader, first, ThreadInit {})
+            .expect("couldn't start first child");
+        ss.hand_over_child(loader, second, ThreadInit {})
+            .expect("couldn't start second child");
+        (loader, first, second)
+    });
+    // Equals take turns
+    assert_eq!(crate::next_pid_to_run(Some(first)), Some(second));
+    assert_eq!(crate::next_pid_to_run(Some(second)), Some(first));
+
+    // Once the first drops back, the second runs even when it's the one
+    // that ran last, and the first comes ahead of it in line
+    SystemServices::with_mut(|ss| {
+        ss.set_priority(loader, first, Priority::Background)
+            .expect("couldn't lower the first child");
+    });
+    assert_eq!(crate::next_pid_to_run(None), Some(second));
+    assert_eq!(crate::next_pid_to_run(Some(second)), Some(second));
+
+    SystemServices::with_mut(|ss| {
+        // Realtime is kept to processes that own an interrupt
+        assert_eq!(
+            ss.set_priority(loader, second, Priority::Realtime),
+            Err(xous_kernel::Error::AccessDenied)
+        );
+        ss.set_priority(loader, first, Priority::Normal)
+            .expect("couldn't restore the first child");
+    });
+    assert_eq!(crate::next_pid_to_run(Some(second)), Some(first));
+}
+
+#[test]
+fn crash_record() {
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let (read_send, read_recv) = unbounded();
+    let (done_send, done_recv) = unbounded();
+
+    // The reader stays alive until the other process has tried, so that one
+    // can't be handed the reader's PID
+    let reader = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "crash_record reader",
+        move || {
+            // Nothing has faulted, so there's nothing to read
+            assert_eq!(xous_kernel::crash_record(), Ok(None));
+            read_send.send(()).unwrap();
+            done_recv.recv().unwrap();
+        },
+    ))
+    .expect("couldn't spawn process");
+    read_recv.recv().unwrap();
+
+    let other = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "crash_record other",
+        || {
+            // The first process to ask is the only one that may
+            assert_eq!(
+                xous_kernel::crash_record(),
+                Err(xous_kernel::Error::AccessDenied)
+            );
+        },
+    ))
+    .expect("couldn't spawn process");
+    crate::wait_process_as_thread(other).expect("couldn't join process");
+    done_send.send(()).unwrap();
+    crate::wait_process_as_thread(reader).expect("couldn't join process");
+
+    shutdown_kernel();
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+#[test]
+fn ipc_trace() {
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let (traced_send, traced_recv) = unbounded();
+    let (done_send, done_recv) = unbounded();
+
+    let tracer = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "ipc_trace tracer",
+        move || {
+            xous_kernel::set_ipc_trace(true).expect("couldn't turn on tracing");
+            let server = xous_kernel::create_server().expect("couldn't create server");
+            let connection =
+                xous_kernel::try_connect(server).expect("couldn't connect to our own server");
+            xous_kernel::try_send_message(
+                connection,
+                xous_kernel::Message::new_scalar(42, 0, 0, 0, 0),
+            )
+            .expect("couldn't send message");
+            let msg = xous_kernel::receive_message(server).expect("couldn't receive message");
+            assert_eq!(msg.body.id(), 42);
+
+            let mut events = vec![];
+            let count = xous_kernel::read_ipc_trace(|event| events.push(event))
+                .expect("couldn't read the trace");
+            assert_eq!(count, 2);
+            let me = xous_kernel::current_pid().unwrap().get();
+            assert_eq!(events[0].kind, xous_kernel::IpcTraceKind::Send);
+            assert_eq!(events[1].kind, xous_kernel::IpcTraceKind::Receive);
+            for event in events.iter() {
+                assert_eq!((event.id, event.size, event.blocking), (42, 0, false));
+                assert_eq!((event.client, event.server), (me, me));
+            }
+            traced_send.send(()).unwrap();
+            done_recv.recv().unwrap();
+        },
+    ))
+    .expect("couldn't spawn process");
+    traced_recv.recv().unwrap();
+
+    let other = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "ipc_trace other",
+        || {
+            // Only the first process to touch the trace may control it
+            assert_eq!(
+                xous_kernel::set_ipc_trace(true),
+                Err(xous_kernel::Error::AccessDenied)
+            );
+        },
+    ))
+    .expect("couldn't spawn process");
+    crate::wait_process_as_thread(other).expect("couldn't join process");
+    done_send.send(()).unwrap();
+    crate::wait_process_as_thread(tracer).expect("couldn't join process");
+
+    shutdown_kernel();
+    main_thread.join().expect("couldn't join kernel process");
+}
+
 #[test]
 fn connect_for_process() {
     use xous_kernel::SID;
@@ -406,6 +690,156 @@ fn try_receive_message() {
     main_thread.join().expect("couldn't join kernel process");
 }
 
+#[test]
+fn queue_depth() {
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let (server_addr_send, server_addr_recv) = unbounded();
+    let (client_sent_send, client_sent_recv) = unbounded();
+
+    let xous_server = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "queue_depth server",
+        move || {
+            let sid = xous_kernel::create_server().expect("couldn't create test server");
+            let (pending, capacity) =
+                xous_kernel::queue_depth(sid).expect("couldn't get queue depth");
+            assert_eq!(pending, 0);
+            assert!(capacity >= 3, "queue only holds {} messages", capacity);
+            server_addr_send.send(sid).unwrap();
+            client_sent_recv.recv().unwrap();
+
+            assert_eq!(xous_kernel::queue_depth(sid), Ok((3, capacity)));
+            xous_kernel::try_receive_message(sid)
+                .expect("couldn't receive messages")
+                .expect("got None as an envelope");
+            assert_eq!(xous_kernel::queue_depth(sid), Ok((2, capacity)));
+
+            let unknown = xous_kernel::SID::from_u32(1, 2, 3, 4);
+            assert_eq!(
+                xous_kernel::queue_depth(unknown),
+                Err(xous_kernel::Error::ServerNotFound)
+            );
+        },
+    ))
+    .expect("couldn't spawn server process");
+
+    let xous_client = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "queue_depth client",
+        move || {
+            let sid = server_addr_recv.recv().unwrap();
+            let conn = xous_kernel::try_connect(sid).expect("couldn't connect to server");
+            for id in 0..3 {
+                xous_kernel::try_send_message(
+                    conn,
+                    xous_kernel::Message::new_scalar(id, 0, 0, 0, 0),
+                )
+                .expect("couldn't send message");
+            }
+            // Anyone who knows the SID can look at the queue, not just the server
+            assert_eq!(
+                xous_kernel::queue_depth(sid).map(|(pending, _)| pending),
+                Ok(3)
+            );
+            client_sent_send
+                .send(())
+                .expect("couldn't notify them we sent the messages");
+        },
+    ))
+    .expect("couldn't spawn client process");
+
+    crate::wait_process_as_thread(xous_server).expect("couldn't join server process");
+    crate::wait_process_as_thread(xous_client).expect("couldn't join client process");
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+#[test]
+fn process_stats() {
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let xous_process = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "process_stats",
+        || {
+            let pid = xous_kernel::current_pid().expect("couldn't get pid");
+            let sid = xous_kernel::create_server().expect("couldn't create test server");
+            let conn = xous_kernel::try_connect(sid).expect("couldn't connect to server");
+            for id in 0..2 {
+                xous_kernel::try_send_message(conn, xous_kernel::Message::new_scalar(id, 0, 0, 0, 0))
+                    .expect("couldn't send message");
+            }
+            // Pages are only counted on hardware, and a hosted process never grows its heap
+            let stats = xous_kernel::process_stats(pid).expect("couldn't get process stats");
+            assert_eq!(stats.heap_used, 0);
+            assert_eq!(stats.pages, 0);
+            assert_eq!(stats.queued, 2);
+            assert_eq!(
+                xous_kernel::process_stats(xous_kernel::PID::new(200).unwrap()),
+                Err(xous_kernel::Error::ProcessNotFound)
+            );
+        },
+    ))
+    .expect("couldn't spawn process");
+    crate::wait_process_as_thread(xous_process).expect("couldn't join process");
+
+    shutdown_kernel();
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+#[test]
+fn server_set_receive_message() {
+    // Start the server in another thread
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let (server_addr_send, server_addr_recv) = unbounded();
+    let (server_rcvd_send, server_rcvd_recv) = unbounded();
+
+    // The server "process" has two servers, and a single thread that waits
+    // on both of them.
+    let xous_server = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "server_set_receive_message server",
+        move || {
+            let sid_a = xous_kernel::create_server().expect("couldn't create test server");
+            let sid_b = xous_kernel::create_server().expect("couldn't create test server");
+            let mut set =
+                xous_kernel::ServerSet::new(&[sid_a, sid_b]).expect("couldn't make server set");
+            server_addr_send.send((sid_a, sid_b)).unwrap();
+            for &(sid, id) in [(sid_b, 21), (sid_a, 22)].iter() {
+                let (from, envelope) = set.receive_message().expect("couldn't receive messages");
+                assert_eq!(from, sid);
+                assert_eq!(envelope.body.id(), id);
+                server_rcvd_send.send(()).unwrap();
+            }
+        },
+    ))
+    .expect("couldn't spawn server process");
+
+    let xous_client = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "server_set_receive_message client",
+        move || {
+            let (sid_a, sid_b) = server_addr_recv.recv().unwrap();
+            let conn_a = xous_kernel::connect(sid_a).expect("couldn't connect to server");
+            let conn_b = xous_kernel::connect(sid_b).expect("couldn't connect to server");
+            for &(conn, id) in [(conn_b, 21), (conn_a, 22)].iter() {
+                xous_kernel::send_message(
+                    conn,
+                    xous_kernel::Message::new_scalar(id, 0, 0, 0, 0),
+                )
+                .expect("couldn't send message");
+                server_rcvd_recv.recv().unwrap();
+            }
+        },
+    ))
+    .expect("couldn't spawn client process");
+
+    // Wait for both processes to finish
+    crate::wait_process_as_thread(xous_server).expect("couldn't join server process");
+    crate::wait_process_as_thread(xous_client).expect("couldn't join client process");
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
+
 #[test]
 fn send_blocking_scalar_message() {
     // Start the server in another thread
@@ -1050,6 +1484,47 @@ fn server_client_same_process() {
     main_thread.join().expect("couldn't join kernel process");
 }
 
+#[test]
+fn notify_server_exit() {
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let xous_process = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "notify_server_exit",
+        || {
+            let watched = xous_kernel::create_server().expect("couldn't create server");
+            let connection = xous_kernel::try_connect(watched).expect("couldn't connect to server");
+            let notify = xous_kernel::create_server().expect("couldn't create notify server");
+            xous_kernel::notify_server_exit(connection, notify, 7)
+                .expect("couldn't ask for notification");
+
+            xous_kernel::destroy_server(watched).expect("couldn't destroy server");
+            let msg = xous_kernel::receive_message(notify).expect("couldn't receive notice");
+            assert_eq!(msg.sender.pid(), xous_kernel::PID::new(1));
+            assert_eq!(
+                msg.body,
+                xous_kernel::Message::Scalar(xous_kernel::ScalarMessage {
+                    id: 7,
+                    arg1: connection as usize,
+                    arg2: 0,
+                    arg3: 0,
+                    arg4: 0,
+                })
+            );
+
+            // The connection went with the server, so there's nothing left to watch
+            assert_eq!(
+                xous_kernel::notify_server_exit(connection, notify, 7),
+                Err(xous_kernel::Error::ServerNotFound)
+            );
+        },
+    ))
+    .expect("couldn't spawn process");
+    crate::wait_process_as_thread(xous_process).expect("couldn't join process");
+
+    shutdown_kernel();
+    main_thread.join().expect("couldn't join kernel process");
+}
+
 /// Test that one process can have multiple contexts
 #[test]
 fn multiple_contexts() {
@@ -1177,3 +1652,308 @@ fn process_restart_server() {
 
     main_thread.join().expect("couldn't join kernel process");
 }
+
+/// Start `count` threads that each sleep on `word` while it holds 0, then
+/// bump `woken`. Returns once they've had time to park.
+fn park_address_waiters(
+    word: &std::sync::Arc<AtomicUsize>,
+    woken: &std::sync::Arc<AtomicUsize>,
+    count: usize,
+) -> Vec<xous_kernel::arch::WaitHandle<()>> {
+    use std::time::Duration;
+    let parking = std::sync::Arc::new(AtomicUsize::new(0));
+    let waiters = (0..count)
+        .map(|_| {
+            let (word, woken, parking) = (word.clone(), woken.clone(), parking.clone());
+            xous_kernel::create_thread(move || {
+                parking.fetch_add(1, Ordering::SeqCst);
+                xous_kernel::wait_address(&word, 0).expect("couldn't wait on the word");
+                woken.fetch_add(1, Ordering::SeqCst);
+            })
+            .expect("couldn't spawn waiter thread")
+        })
+        .collect();
+    while parking.load(Ordering::SeqCst) < count {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    // They've counted themselves in, but may not have made the call yet
+    std::thread::sleep(Duration::from_millis(100));
+    waiters
+}
+
+#[test]
+fn wait_address_wrong_value() {
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let waiter = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "wait_address_wrong_value",
+        || {
+            let word = AtomicUsize::new(1);
+            // The word has already moved on, so there's nothing to wait for
+            xous_kernel::wait_address(&word, 0).expect("couldn't wait on the word");
+            assert_eq!(xous_kernel::wake_address(&word, usize::MAX), Ok(0));
+        },
+    ))
+    .expect("couldn't start waiter process");
+    xous_kernel::wait_process_as_thread(waiter).expect("couldn't join waiter process");
+
+    shutdown_kernel();
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+#[test]
+fn wake_address_one() {
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let waker = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "wake_address_one",
+        || {
+            let word = std::sync::Arc::new(AtomicUsize::new(0));
+            let woken = std::sync::Arc::new(AtomicUsize::new(0));
+            let waiters = park_address_waiters(&word, &woken, 2);
+
+            assert_eq!(xous_kernel::wake_address(&word, 1), Ok(1));
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            assert_eq!(woken.load(Ordering::SeqCst), 1);
+
+            assert_eq!(xous_kernel::wake_address(&word, 1), Ok(1));
+            for waiter in waiters {
+                xous_kernel::wait_thread(waiter).expect("couldn't join waiter thread");
+            }
+            assert_eq!(woken.load(Ordering::SeqCst), 2);
+            assert_eq!(xous_kernel::wake_address(&word, 1), Ok(0));
+        },
+    ))
+    .expect("couldn't start waker process");
+    xous_kernel::wait_process_as_thread(waker).expect("couldn't join waker process");
+
+    shutdown_kernel();
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+#[test]
+fn wake_address_all() {
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let waker = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "wake_address_all",
+        || {
+            let word = std::sync::Arc::new(AtomicUsize::new(0));
+            let other = std::sync::Arc::new(AtomicUsize::new(0));
+            let woken = std::sync::Arc::new(AtomicUsize::new(0));
+            let waiters = park_address_waiters(&word, &woken, 3);
+            let bystander = park_address_waiters(&other, &woken, 1);
+
+            assert_eq!(xous_kernel::wake_address(&word, usize::MAX), Ok(3));
+            for waiter in waiters {
+                xous_kernel::wait_thread(waiter).expect("couldn't join waiter thread");
+            }
+            // Only the threads on that word were woken
+            assert_eq!(woken.load(Ordering::SeqCst), 3);
+
+            assert_eq!(xous_kernel::wake_address(&other, usize::MAX), Ok(1));
+            for waiter in bystander {
+                xous_kernel::wait_thread(waiter).expect("couldn't join bystander thread");
+            }
+        },
+    ))
+    .expect("couldn't start waker process");
+    xous_kernel::wait_process_as_thread(waker).expect("couldn't join waker process");
+
+    shutdown_kernel();
+    main_thread.join().expect("couldn't join kernel process");
+}
+
+/// Stands in for the ticktimer server, which calls with a timeout keep time
+/// with. It serves `ElapsedMs`, `WaitForCondition` and `NotifyCondition`
+/// until it gets any other opcode.
+fn fake_ticktimer() {
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+
+    let sid = xous_kernel::create_server_with_address(b"ticktimer-server")
+        .expect("couldn't create ticktimer server");
+    let start = Instant::now();
+    let mut waiting: Vec<(usize, Instant, xous_kernel::MessageSender)> = Vec::new();
+    let mut excess: HashMap<usize, usize> = HashMap::new();
+    loop {
+        let now = Instant::now();
+        waiting.retain(|&(_, at, sender)| {
+            if now < at {
+                return true;
+            }
+            xous_kernel::return_scalar(sender, 1).expect("couldn't end a wait");
+            false
+        });
+        let envelope = match xous_kernel::try_receive_message(sid).expect("couldn't receive") {
+            Some(envelope) => envelope,
+            None => {
+                std::thread::sleep(Duration::from_millis(1));
+                continue;
+            }
+        };
+        let scalar = *envelope.body.scalar_message().expect("not a scalar");
+        match scalar.id {
+            0 => xous_kernel::return_scalar2(
+                envelope.sender,
+                start.elapsed().as_millis() as usize,
+                0,
+            )
+            .expect("couldn't return the time"),
+            8 => match excess.get_mut(&scalar.arg1) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    xous_kernel::return_scalar(envelope.sender, 0).expect("couldn't end a wait");
+                }
+                _ => waiting.push((
+                    scalar.arg1,
+                    now + Duration::from_millis(scalar.arg2 as u64),
+                    envelope.sender,
+                )),
+            },
+            9 => match waiting.iter().position(|&(condvar, _, _)| condvar == scalar.arg1) {
+                Some(index) => xous_kernel::return_scalar(waiting.remove(index).2, 0)
+                    .expect("couldn't notify"),
+                None => *excess.entry(scalar.arg1).or_default() += 1,
+            },
+            _ => return,
+        }
+    }
+}
+
+#[test]
+fn send_receive_timeout() {
+    use std::time::{Duration, Instant};
+    use xous_kernel::{Error, Message};
+
+    let main_thread = start_kernel(SERVER_SPEC);
+
+    let (server_addr_send, server_addr_recv) = unbounded();
+
+    let ticktimer = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "send_receive_timeout ticktimer",
+        fake_ticktimer,
+    ))
+    .expect("couldn't spawn ticktimer process");
+
+    // Answers opcode 1 with its argument plus one, writes to mutable lends,
+    // sits on opcode 2 without answering, and leaves on opcode 3.
+    let xous_server = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "send_receive_timeout server",
+        move || {
+            let sid = xous_kernel::create_server().expect("couldn't create test server");
+            server_addr_send.send(sid).unwrap();
+            let mut unanswered = Vec::new();
+            loop {
+                let mut envelope =
+                    xous_kernel::receive_message(sid).expect("couldn't receive message");
+                if let Some(memory) = envelope.body.memory_message_mut() {
+                    unsafe { *memory.buf.as_mut_ptr() = 42 };
+                    xous_kernel::return_memory(envelope.sender, memory.buf)
+                        .expect("couldn't return memory");
+                    continue;
+                }
+                let scalar = *envelope.body.scalar_message().expect("not a scalar");
+                match scalar.id {
+                    1 => xous_kernel::return_scalar(envelope.sender, scalar.arg1 + 1)
+                        .expect("couldn't answer"),
+                    2 => unanswered.push(envelope),
+                    _ => break,
+                }
+            }
+        },
+    ))
+    .expect("couldn't spawn server process");
+
+    let xous_client = xous_kernel::create_process_as_thread(xous_kernel::ProcessArgsAsThread::new(
+        "send_receive_timeout client",
+        move || {
+            let timeout = Duration::from_millis(50);
+
+            // Nothing arrives, so the receive gives up
+            let own = xous_kernel::create_server().expect("couldn't create own server");
+            let started = Instant::now();
+            assert!(matches!(
+                xous_kernel::receive_message_timeout(own, 50),
+                Err(Error::Timeout)
+            ));
+            assert!(started.elapsed() >= timeout);
+
+            // A message that comes in time is received
+            let own_conn = xous_kernel::try_connect(own).expect("couldn't connect to own server");
+            xous_kernel::try_send_message(own_conn, Message::new_scalar(5, 6, 7, 8, 9))
+                .expect("couldn't send to own server");
+            let envelope =
+                xous_kernel::receive_message_timeout(own, 1000).expect("message didn't arrive");
+            assert_eq!(envelope.body, Message::new_scalar(5, 6, 7, 8, 9));
+
+            let sid = server_addr_recv.recv().unwrap();
+            let conn = xous_kernel::connect(sid).expect("couldn't connect to server");
+
+            // A server that answers
+            assert_eq!(
+                xous_kernel::send_message_timeout(
+                    conn,
+                    Message::new_blocking_scalar(1, 41, 0, 0, 0),
+                    1000
+                ),
+                Ok(xous_kernel::Result::Scalar1(42))
+            );
+
+            // A mutable lend sees what the server wrote
+            let page = xous_kernel::map_memory(
+                None,
+                None,
+                4096,
+                xous_kernel::MemoryFlags::R | xous_kernel::MemoryFlags::W,
+            )
+            .expect("couldn't map a page");
+            xous_kernel::send_message_timeout(
+                conn,
+                Message::new_lend_mut(4, page, None, None),
+                1000,
+            )
+            .expect("couldn't lend");
+            assert_eq!(unsafe { *page.as_mut_ptr() }, 42);
+
+            // A server that never answers
+            let started = Instant::now();
+            assert_eq!(
+                xous_kernel::send_message_timeout(
+                    conn,
+                    Message::new_blocking_scalar(2, 0, 0, 0, 0),
+                    50
+                ),
+                Err(Error::Timeout)
+            );
+            assert!(started.elapsed() >= timeout);
+
+            // The worker from before is used for the next call
+            assert_eq!(
+                xous_kernel::send_message_timeout(
+                    conn,
+                    Message::new_blocking_scalar(1, 1, 0, 0, 0),
+                    1000
+                ),
+                Ok(xous_kernel::Result::Scalar1(2))
+            );
+
+            xous_kernel::try_send_message(conn, Message::new_scalar(3, 0, 0, 0, 0))
+                .expect("couldn't stop server");
+            let ticktimer = xous_kernel::connect(
+                xous_kernel::SID::from_bytes(b"ticktimer-server").unwrap(),
+            )
+            .expect("couldn't connect to ticktimer");
+            xous_kernel::try_send_message(ticktimer, Message::new_scalar(0xff, 0, 0, 0, 0))
+                .expect("couldn't stop ticktimer");
+        },
+    ))
+    .expect("couldn't spawn client process");
+
+    crate::wait_process_as_thread(xous_client).expect("couldn't join client process");
+    crate::wait_process_as_thread(xous_server).expect("couldn't join server process");
+    crate::wait_process_as_thread(ticktimer).expect("couldn't join ticktimer process");
+    shutdown_kernel();
+
+    main_thread.join().expect("couldn't join kernel process");
+}
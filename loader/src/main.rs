@@ -412,6 +412,22 @@ extern "C" {
     ) -> !;
 }
 
+/// Lets supervisor mode read the cycle, time and instret counters. The kernel reads the cycle counter on
+/// every trap to keep each thread's CPU time, so it's read back to make sure that took: otherwise the
+/// kernel would fault on its very first trap, with nothing to say why.
+#[cfg(target_arch = "riscv32")]
+fn enable_counters() {
+    let enabled: usize;
+    unsafe {
+        core::arch::asm!("csrw mcounteren, {0}", "csrr {1}, mcounteren", in(reg) 0b111usize, out(reg) enabled);
+    }
+    if enabled & 1 == 0 {
+        panic!("the cycle counter can't be opened up to the kernel (mcounteren is {:x})", enabled);
+    }
+}
+#[cfg(not(target_arch = "riscv32"))]
+fn enable_counters() {}
+
 
 impl ProgramDescription {
     /// Map this ProgramDescription into RAM.
@@ -1274,6 +1290,9 @@ fn boot_sequence(args: KernelArguments, _signature: u32) -> ! {
     println!("  Small @ {:08x}", fonts::small::SMALL_GLYPHS.as_ptr() as u32);
     println!("  Bold @ {:08x}", fonts::bold::BOLD_GLYPHS.as_ptr() as u32);
 
+    // mcounteren is cleared by the reset that comes before a resume as well
+    enable_counters();
+
     if !clean {
         // The MMU should be set up now, and memory pages assigned to their
         // respective processes.
@@ -0,0 +1,79 @@
+pub(crate) const SERVER_NAME_APP_LOADER: &str = "_App loader_";
+
+/// PDDB dictionary the installed apps are kept in, one key per app holding its signed image
+pub const APP_DICT: &str = "sys.apps";
+/// Longest name an app can be installed under
+pub const APP_NAME_LEN: usize = 64;
+/// Largest signed image that can be installed
+pub const MAX_APP_LEN: usize = 1024 * 1024;
+/// Most apps a listing can hold
+pub const MAX_LISTED_APPS: usize = 16;
+/// Size of the signature record at the start of a signed image, and of the page holding the name in an
+/// `Install` lend
+pub const PAGE_LEN: usize = 4096;
+
+#[derive(num_derive::FromPrimitive, num_derive::ToPrimitive, Debug)]
+pub(crate) enum Opcode {
+    /// Checks a signed image and stores it in the PDDB. The caller lends a buffer with the name in its first page
+    /// and the image from the second page on; `offset` is the length of the name, and `valid` the length of the
+    /// image. The outcome comes back in `offset`: `None` on success, or a `LoadError`.
+    Install,
+    /// Removes an installed app. Takes a `Request`.
+    Uninstall,
+    /// Starts an installed app. Takes a `Request`, and fills in the PID it runs under.
+    Launch,
+    /// Stops an app that was launched, by the PID in arg1. Returns 0, or a `LoadError`.
+    Kill,
+    /// Fills in an `AppList`
+    List,
+    /// Exits the server
+    Quit,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, num_derive::FromPrimitive, num_derive::ToPrimitive,
+    rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub enum LoadError {
+    /// The name is empty, too long, or not UTF-8
+    BadName = 1,
+    /// No app is installed under that name, or running under that PID
+    NotFound,
+    /// The image isn't signed by any key this device knows
+    BadSignature,
+    /// The image isn't a RISC-V executable the loader can load
+    BadElf,
+    /// The image is too large, or there isn't enough memory to load it
+    TooLarge,
+    /// The PDDB couldn't be read or written; it may not be mounted yet
+    Storage,
+    /// The kernel wouldn't create, fill in, start or stop the process
+    Kernel,
+    /// Apps can't be loaded in hosted mode, though they can be installed
+    Unsupported,
+    /// The request couldn't be made
+    Internal,
+    /// The image is signed with the developer key, which is only accepted until this device has keys of its own
+    DevKey,
+    /// Only the shell and the status bar may install, remove, start or stop apps
+    NotAllowed,
+}
+
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub(crate) struct Request {
+    pub name: xous_ipc::String<APP_NAME_LEN>,
+    /// filled in by `Launch`
+    pub pid: Option<u8>,
+    /// filled in by the server; `None` if the request went through
+    pub error: Option<LoadError>,
+}
+
+#[derive(Debug, Copy, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct AppInfo {
+    pub name: xous_ipc::String<APP_NAME_LEN>,
+    /// the PID the app is running under, if it was launched and is still running
+    pub pid: Option<u8>,
+}
+
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub(crate) struct AppList {
+    pub apps: [Option<AppInfo>; MAX_LISTED_APPS],
+}
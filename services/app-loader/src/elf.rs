@@ -0,0 +1,86 @@
+//! Just enough of an ELF reader to load the statically linked RISC-V executables apps are built as: the
+//! entry point, and the `PT_LOAD` segments with their placement and permissions. Anything else in the file
+//! (sections, symbols, relocations) is ignored.
+
+const EI_CLASS_32: u8 = 1;
+const EI_DATA_LE: u8 = 1;
+const ET_EXEC: u16 = 2;
+const EM_RISCV: u16 = 0xf3;
+const PT_LOAD: u32 = 1;
+const ELF_HEADER_LEN: usize = 52;
+const PROGRAM_HEADER_LEN: usize = 32;
+
+/// Segment permission bits, as in `p_flags`
+pub const PF_X: u32 = 1;
+pub const PF_W: u32 = 2;
+pub const PF_R: u32 = 4;
+
+#[derive(Debug)]
+pub struct Segment {
+    /// where the segment's data starts in the file
+    pub offset: usize,
+    /// where the segment goes in the app's address space
+    pub vaddr: usize,
+    /// how much of the segment comes from the file; the rest is zeroed
+    pub filesz: usize,
+    pub memsz: usize,
+    pub flags: u32,
+}
+
+#[derive(Debug)]
+pub struct Elf {
+    pub entry: usize,
+    pub segments: Vec<Segment>,
+}
+
+fn u16_at(data: &[u8], at: usize) -> Option<u16> {
+    data.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+fn u32_at(data: &[u8], at: usize) -> Option<u32> {
+    data.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Reads the headers of `data`. Returns `None` if it isn't a 32-bit little-endian RISC-V executable, or if a
+/// segment reaches past the end of the file or of the address space.
+pub fn parse(data: &[u8]) -> Option<Elf> {
+    if data.len() < ELF_HEADER_LEN || &data[..4] != b"\x7fELF" {
+        return None;
+    }
+    if data[4] != EI_CLASS_32 || data[5] != EI_DATA_LE {
+        return None;
+    }
+    if u16_at(data, 16)? != ET_EXEC || u16_at(data, 18)? != EM_RISCV {
+        return None;
+    }
+    let entry = u32_at(data, 24)? as usize;
+    let phoff = u32_at(data, 28)? as usize;
+    let phentsize = u16_at(data, 42)? as usize;
+    let phnum = u16_at(data, 44)? as usize;
+    if phentsize < PROGRAM_HEADER_LEN {
+        return None;
+    }
+
+    let mut segments = Vec::new();
+    for i in 0..phnum {
+        let ph = phoff.checked_add(i.checked_mul(phentsize)?)?;
+        if u32_at(data, ph)? != PT_LOAD {
+            continue;
+        }
+        let segment = Segment {
+            offset: u32_at(data, ph + 4)? as usize,
+            vaddr: u32_at(data, ph + 8)? as usize,
+            filesz: u32_at(data, ph + 16)? as usize,
+            memsz: u32_at(data, ph + 20)? as usize,
+            flags: u32_at(data, ph + 24)?,
+        };
+        if segment.filesz > segment.memsz || segment.offset.checked_add(segment.filesz)? > data.len() {
+            return None;
+        }
+        segment.vaddr.checked_add(segment.memsz)?;
+        segments.push(segment);
+    }
+    if segments.is_empty() {
+        return None;
+    }
+    Some(Elf { entry, segments })
+}
@@ -0,0 +1,107 @@
+#![cfg_attr(target_os = "none", no_std)]
+
+pub mod api;
+use api::*;
+use num_traits::{FromPrimitive, ToPrimitive};
+use xous::{send_message, Message, CID};
+use xous_ipc::Buffer;
+
+/// Installs, starts and stops apps. An app is a RISC-V executable, signed with `tools/sign_image.py`, that is
+/// kept in the PDDB under a name of its own and loaded into a new process when it's launched.
+pub struct AppLoader {
+    conn: CID,
+}
+impl AppLoader {
+    pub fn new(xns: &xous_names::XousNames) -> Result<Self, xous::Error> {
+        REFCOUNT.fetch_add(1, Ordering::Relaxed);
+        let conn = xns.request_connection_blocking(api::SERVER_NAME_APP_LOADER).expect("Can't connect to App loader server");
+        Ok(AppLoader { conn })
+    }
+    /// Checks the signature on `image` and stores it under `name`, replacing any app already installed there.
+    pub fn install(&self, name: &str, image: &[u8]) -> Result<(), LoadError> {
+        if name.is_empty() || name.len() > APP_NAME_LEN {
+            return Err(LoadError::BadName);
+        }
+        if image.len() > MAX_APP_LEN {
+            return Err(LoadError::TooLarge);
+        }
+        // the name takes the first page, and the image starts on the next one
+        let len = PAGE_LEN + (image.len() + PAGE_LEN - 1) / PAGE_LEN * PAGE_LEN;
+        let mut buf = xous::syscall::map_memory(None, None, len, xous::MemoryFlags::R | xous::MemoryFlags::W)
+            .or(Err(LoadError::TooLarge))?;
+        let bytes = buf.as_slice_mut::<u8>();
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+        bytes[PAGE_LEN..PAGE_LEN + image.len()].copy_from_slice(image);
+        let request = xous::MemoryMessage {
+            id: Opcode::Install.to_usize().unwrap(),
+            buf,
+            offset: xous::MemoryAddress::new(name.len()),
+            valid: xous::MemorySize::new(image.len()),
+        };
+        let result = send_message(self.conn, Message::MutableBorrow(request));
+        xous::syscall::unmap_memory(buf).unwrap();
+        match result {
+            Ok(xous::Result::MemoryReturned(None, _)) => Ok(()),
+            Ok(xous::Result::MemoryReturned(Some(code), _)) => Err(FromPrimitive::from_usize(code.get()).unwrap_or(LoadError::Internal)),
+            _ => Err(LoadError::Internal),
+        }
+    }
+    pub fn uninstall(&self, name: &str) -> Result<(), LoadError> {
+        self.request(Opcode::Uninstall, name).map(|_| ())
+    }
+    /// Starts the app installed under `name`, and returns the PID it runs under. An app can be launched more
+    /// than once.
+    pub fn launch(&self, name: &str) -> Result<xous::PID, LoadError> {
+        self.request(Opcode::Launch, name)?
+            .and_then(xous::PID::new)
+            .ok_or(LoadError::Internal)
+    }
+    /// Stops an app that was launched. An app that has already exited is not an error.
+    pub fn kill(&self, pid: xous::PID) -> Result<(), LoadError> {
+        match send_message(self.conn,
+            Message::new_blocking_scalar(Opcode::Kill.to_usize().unwrap(), pid.get() as usize, 0, 0, 0)
+        ) {
+            Ok(xous::Result::Scalar1(0)) => Ok(()),
+            Ok(xous::Result::Scalar1(code)) => Err(FromPrimitive::from_usize(code).unwrap_or(LoadError::Internal)),
+            _ => Err(LoadError::Internal),
+        }
+    }
+    /// The installed apps, with the PID of each one that is running
+    pub fn list(&self) -> Result<Vec<AppInfo>, LoadError> {
+        let list = AppList { apps: [None; MAX_LISTED_APPS] };
+        let mut buf = Buffer::into_buf(list).or(Err(LoadError::Internal))?;
+        buf.lend_mut(self.conn, Opcode::List.to_u32().unwrap()).or(Err(LoadError::Internal))?;
+        let list = buf.to_original::<AppList, _>().or(Err(LoadError::Internal))?;
+        Ok(list.apps.iter().filter_map(|a| *a).collect())
+    }
+    fn request(&self, op: Opcode, name: &str) -> Result<Option<u8>, LoadError> {
+        if name.is_empty() || name.len() > APP_NAME_LEN {
+            return Err(LoadError::BadName);
+        }
+        let request = Request {
+            name: xous_ipc::String::<APP_NAME_LEN>::from_str(name),
+            pid: None,
+            error: None,
+        };
+        let mut buf = Buffer::into_buf(request).or(Err(LoadError::Internal))?;
+        buf.lend_mut(self.conn, op.to_u32().unwrap()).or(Err(LoadError::Internal))?;
+        let request = buf.to_original::<Request, _>().or(Err(LoadError::Internal))?;
+        match request.error {
+            Some(e) => Err(e),
+            None => Ok(request.pid),
+        }
+    }
+}
+
+use core::sync::atomic::{AtomicU32, Ordering};
+static REFCOUNT: AtomicU32 = AtomicU32::new(0);
+impl Drop for AppLoader {
+    fn drop(&mut self) {
+        // the connection to the server side must be reference counted, so that multiple instances of this object within
+        // a single process do not end up de-allocating the CID on other threads before they go out of scope.
+        // Note to future me: you want this. Don't get rid of it because you think, "nah, nobody will ever make more than one copy of this object".
+        if REFCOUNT.fetch_sub(1, Ordering::Relaxed) == 1 {
+            unsafe{xous::disconnect(self.conn).unwrap();}
+        }
+    }
+}
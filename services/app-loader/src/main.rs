@@ -0,0 +1,379 @@
+#![cfg_attr(target_os = "none", no_std)]
+#![cfg_attr(target_os = "none", no_main)]
+
+mod api;
+use api::*;
+mod elf;
+
+use num_traits::{FromPrimitive, ToPrimitive};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use xous_ipc::Buffer;
+
+use log::info;
+
+/// Version of the trailer `sign_image.py` puts after the ELF, inside the signed region
+const TRAILER_VERSION: u32 = 1;
+const TRAILER_LEN: usize = 8;
+/// Apps are loaded below the heap, which the kernel places at 0x2000_0000
+#[cfg(any(target_os = "none", target_os = "xous"))]
+const LOAD_AREA_END: usize = 0x2000_0000;
+/// Processes, by the name they were built with, that may install, remove, start and stop apps
+#[cfg(any(target_os = "none", target_os = "xous"))]
+const TRUSTED_CALLERS: [&str; 2] = ["shellchat", "status"];
+
+/// A signed image, kept in a page-aligned buffer of its own so it can be lent to root-keys for checking
+struct Image {
+    buf: xous::MemoryRange,
+    len: usize,
+}
+impl Image {
+    fn new(len: usize) -> Result<Image, LoadError> {
+        if len > MAX_APP_LEN {
+            return Err(LoadError::TooLarge);
+        }
+        let buf = xous::syscall::map_memory(
+            None,
+            None,
+            ((len + PAGE_LEN - 1) / PAGE_LEN * PAGE_LEN).max(PAGE_LEN),
+            xous::MemoryFlags::R | xous::MemoryFlags::W,
+        ).or(Err(LoadError::TooLarge))?;
+        Ok(Image { buf, len })
+    }
+    fn data(&self) -> &[u8] { &self.buf.as_slice::<u8>()[..self.len] }
+    fn data_mut(&mut self) -> &mut [u8] {
+        let len = self.len;
+        &mut self.buf.as_slice_mut::<u8>()[..len]
+    }
+    /// Checks the signature. Anyone can sign with the developer key, so images signed with it are only taken
+    /// while the device hasn't made keys of its own, and is still running the developer-signed images it came with.
+    fn check(&self, rootkeys: &root_keys::RootKeys) -> Result<(), LoadError> {
+        match rootkeys.check_app_signature(self.buf, self.len) {
+            Ok(Some(root_keys::api::AppSigner::DevKey)) => match rootkeys.is_initialized() {
+                Ok(false) => {
+                    log::warn!("app is signed with the developer key; anyone could have made it");
+                    Ok(())
+                }
+                Ok(true) => {
+                    log::warn!("refusing an app signed with the developer key on a device with its own keys");
+                    Err(LoadError::DevKey)
+                }
+                Err(_) => Err(LoadError::Internal),
+            },
+            Ok(Some(_)) => Ok(()),
+            Ok(None) => Err(LoadError::BadSignature),
+            Err(_) => Err(LoadError::Internal),
+        }
+    }
+    /// The ELF inside a signed image. Only call this once the signature checks out, as it trusts the signed
+    /// length in the header.
+    fn elf(&self) -> Result<&[u8], LoadError> {
+        let data = self.data();
+        let field = |at: usize| data.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize);
+        let signed_len = field(4).ok_or(LoadError::BadElf)?;
+        if signed_len < TRAILER_LEN || PAGE_LEN + signed_len > data.len() {
+            return Err(LoadError::BadElf);
+        }
+        let elf_len = signed_len - TRAILER_LEN;
+        let trailer = PAGE_LEN + elf_len;
+        if field(trailer) != Some(TRAILER_VERSION as usize) || field(trailer + 4) != Some(signed_len - 4) {
+            log::warn!("app image has a malformed trailer");
+            return Err(LoadError::BadElf);
+        }
+        Ok(&data[PAGE_LEN..trailer])
+    }
+}
+impl Drop for Image {
+    fn drop(&mut self) {
+        xous::syscall::unmap_memory(self.buf).unwrap();
+    }
+}
+
+/// Whether `sender` may install, remove, start or stop apps
+fn may_manage_apps(sender: xous::MessageSender) -> bool {
+    #[cfg(any(target_os = "none", target_os = "xous"))]
+    {
+        let mut name = [0u8; 64];
+        match sender.pid().map(|pid| xous::process_name(pid, &mut name)) {
+            Some(Ok(len)) if len > 0 && len < name.len() => core::str::from_utf8(&name[..len])
+                .map(|n| TRUSTED_CALLERS.contains(&n))
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+    #[cfg(not(any(target_os = "none", target_os = "xous")))]
+    {
+        // processes don't have names in hosted mode
+        let _ = sender;
+        true
+    }
+}
+
+fn valid_name(name: &str) -> Result<(), LoadError> {
+    if name.is_empty() || name.len() > APP_NAME_LEN {
+        Err(LoadError::BadName)
+    } else {
+        Ok(())
+    }
+}
+
+fn read_app(pddb: &mut pddb::Pddb, name: &str) -> Result<Image, LoadError> {
+    let mut key = match pddb.get(APP_DICT, name, None, false, false, None, None::<fn()>) {
+        Ok(key) => key,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(LoadError::NotFound),
+        Err(_) => return Err(LoadError::Storage),
+    };
+    let len = key.attributes().or(Err(LoadError::Storage))?.len;
+    let mut image = Image::new(len)?;
+    key.read_exact(image.data_mut()).or(Err(LoadError::Storage))?;
+    Ok(image)
+}
+
+fn write_app(pddb: &mut pddb::Pddb, name: &str, image: &Image) -> Result<(), LoadError> {
+    // a shorter image would leave the tail of the old one behind
+    pddb.delete_key(APP_DICT, name, None).ok();
+    let mut key = pddb.get(APP_DICT, name, None, true, true, Some(image.len), None::<fn()>)
+        .or(Err(LoadError::Storage))?;
+    key.write_all(image.data()).and_then(|_| key.flush()).or(Err(LoadError::Storage))
+}
+
+/// Creates a process for `elf`, fills in its segments and starts it. A process that can't be started is
+/// destroyed again, along with whatever was moved into it.
+#[cfg(any(target_os = "none", target_os = "xous"))]
+fn spawn(elf: &elf::Elf, data: &[u8]) -> Result<xous::PID, LoadError> {
+    let pid = xous::create_empty_process().or(Err(LoadError::Kernel))?;
+    match load(pid, elf, data).and_then(|_| xous::start_process(pid, elf.entry).or(Err(LoadError::Kernel))) {
+        Ok(()) => Ok(pid),
+        Err(e) => {
+            xous::destroy_process(pid).ok();
+            Err(e)
+        }
+    }
+}
+
+/// Lays the segments out in memory of our own, sets each page's permissions to those of the segments on it,
+/// and moves the pages into `pid`.
+#[cfg(any(target_os = "none", target_os = "xous"))]
+fn load(pid: xous::PID, elf: &elf::Elf, data: &[u8]) -> Result<(), LoadError> {
+    use xous::MemoryFlags;
+    let lo = elf.segments.iter().map(|s| s.vaddr).min().unwrap() & !(PAGE_LEN - 1);
+    let hi = elf.segments.iter().map(|s| s.vaddr + s.memsz).max().unwrap();
+    let hi = (hi + PAGE_LEN - 1) & !(PAGE_LEN - 1);
+    if lo == 0 || hi > LOAD_AREA_END {
+        log::warn!("app wants to load at {:08x}-{:08x}, outside of the load area", lo, hi);
+        return Err(LoadError::BadElf);
+    }
+
+    let mut area = xous::syscall::map_memory(None, None, hi - lo, MemoryFlags::R | MemoryFlags::W | MemoryFlags::X)
+        .or(Err(LoadError::TooLarge))?;
+    let pages = (hi - lo) / PAGE_LEN;
+    let mut flags = vec![MemoryFlags::empty(); pages];
+    {
+        let bytes = area.as_slice_mut::<u8>();
+        // this also brings every page in, which they have to be before they can be moved
+        bytes.fill(0);
+        for s in elf.segments.iter() {
+            let start = s.vaddr - lo;
+            bytes[start..start + s.filesz].copy_from_slice(&data[s.offset..s.offset + s.filesz]);
+            let mut f = MemoryFlags::empty();
+            if s.flags & (elf::PF_R | elf::PF_W) != 0 {
+                f |= MemoryFlags::R;
+            }
+            if s.flags & elf::PF_W != 0 {
+                f |= MemoryFlags::W;
+            }
+            if s.flags & elf::PF_X != 0 {
+                f |= MemoryFlags::X;
+            }
+            if s.memsz != 0 {
+                for page in flags[start / PAGE_LEN..=(start + s.memsz - 1) / PAGE_LEN].iter_mut() {
+                    *page |= f;
+                }
+            }
+        }
+    }
+
+    // hand the pages over in runs that share the same permissions
+    let base = area.as_mut_ptr() as usize;
+    let mut run = 0;
+    while run < pages {
+        let mut end = run + 1;
+        while end < pages && flags[end] == flags[run] {
+            end += 1;
+        }
+        let range = unsafe { xous::MemoryRange::new(base + run * PAGE_LEN, (end - run) * PAGE_LEN).unwrap() };
+        let result = if flags[run].is_empty() {
+            xous::syscall::unmap_memory(range).or(Err(LoadError::Kernel))
+        } else {
+            xous::syscall::update_memory_flags(range, flags[run])
+                .and_then(|_| xous::move_memory_to_process(pid, range, xous::MemoryAddress::new(lo + run * PAGE_LEN).unwrap()))
+                .or(Err(LoadError::Kernel))
+        };
+        if let Err(e) = result {
+            // what's left is still ours
+            let rest = unsafe { xous::MemoryRange::new(base + run * PAGE_LEN, (pages - run) * PAGE_LEN).unwrap() };
+            xous::syscall::unmap_memory(rest).ok();
+            return Err(e);
+        }
+        run = end;
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "none", target_os = "xous")))]
+fn spawn(_elf: &elf::Elf, _data: &[u8]) -> Result<xous::PID, LoadError> {
+    log::warn!("apps can't be started in hosted mode");
+    Err(LoadError::Unsupported)
+}
+
+#[xous::xous_main]
+fn xmain() -> ! {
+    log_server::init_wait().unwrap();
+    log::set_max_level(log::LevelFilter::Info);
+    info!("my PID is {}", xous::process::id());
+
+    let xns = xous_names::XousNames::new().unwrap();
+    let loader_sid = xns.register_name(api::SERVER_NAME_APP_LOADER, None).expect("can't register server");
+    log::trace!("registered with NS -- {:?}", loader_sid);
+
+    let rootkeys = root_keys::RootKeys::new(&xns, None).expect("couldn't connect to root keys");
+    let mut pddb = pddb::Pddb::new();
+
+    // apps we started, by PID, with the name they were installed under
+    let mut running: HashMap<u8, String> = HashMap::new();
+
+    log::trace!("ready to accept requests");
+    loop {
+        let mut msg = xous::receive_message(loader_sid).unwrap();
+        let allowed = if may_manage_apps(msg.sender) { Ok(()) } else { Err(LoadError::NotAllowed) };
+        match FromPrimitive::from_usize(msg.body.id()) {
+            Some(Opcode::Install) => {
+                let mem = msg.body.memory_message_mut().unwrap();
+                let name_len = mem.offset.map(|o| o.get()).unwrap_or(0);
+                let image_len = mem.valid.map(|v| v.get()).unwrap_or(0);
+                let result = if allowed.is_err() {
+                    allowed
+                } else if name_len > APP_NAME_LEN || PAGE_LEN + image_len > mem.buf.len() {
+                    Err(LoadError::BadName)
+                } else {
+                    let lent = mem.buf.as_slice::<u8>();
+                    match std::str::from_utf8(&lent[..name_len]) {
+                        Ok(name) => valid_name(name).and_then(|_| Image::new(image_len)).and_then(|mut image| {
+                            image.data_mut().copy_from_slice(&lent[PAGE_LEN..PAGE_LEN + image_len]);
+                            image.check(&rootkeys)?;
+                            elf::parse(image.elf()?).ok_or(LoadError::BadElf)?;
+                            if !pddb.try_mount() {
+                                return Err(LoadError::Storage);
+                            }
+                            write_app(&mut pddb, name, &image)?;
+                            log::info!("installed {} ({} bytes)", name, image_len);
+                            Ok(())
+                        }),
+                        Err(_) => Err(LoadError::BadName),
+                    }
+                };
+                mem.offset = result.err().and_then(|e| xous::MemoryAddress::new(e.to_usize().unwrap()));
+            }
+            Some(Opcode::Uninstall) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let mut request = buffer.to_original::<Request, _>().unwrap();
+                let name = request.name.as_str().unwrap_or("");
+                request.error = allowed.and_then(|_| valid_name(name)).and_then(|_| {
+                    if !pddb.try_mount() {
+                        return Err(LoadError::Storage);
+                    }
+                    match pddb.delete_key(APP_DICT, name, None) {
+                        Ok(_) => Ok(()),
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(LoadError::NotFound),
+                        Err(_) => Err(LoadError::Storage),
+                    }
+                }).err();
+                if request.error.is_none() {
+                    log::info!("uninstalled {}", name);
+                }
+                buffer.replace(request).unwrap();
+            }
+            Some(Opcode::Launch) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let mut request = buffer.to_original::<Request, _>().unwrap();
+                let name = String::from(request.name.as_str().unwrap_or(""));
+                let result = allowed.and_then(|_| valid_name(&name)).and_then(|_| {
+                    if !pddb.try_mount() {
+                        return Err(LoadError::Storage);
+                    }
+                    // the image is checked again, in case the key was changed behind the loader's back
+                    let image = read_app(&mut pddb, &name)?;
+                    image.check(&rootkeys)?;
+                    let data = image.elf()?;
+                    let elf = elf::parse(data).ok_or(LoadError::BadElf)?;
+                    spawn(&elf, data)
+                });
+                match result {
+                    Ok(pid) => {
+                        log::info!("launched {} as PID {}", name, pid.get());
+                        running.insert(pid.get(), name);
+                        request.pid = Some(pid.get());
+                        request.error = None;
+                    }
+                    Err(e) => {
+                        log::warn!("couldn't launch {}: {:?}", name, e);
+                        request.pid = None;
+                        request.error = Some(e);
+                    }
+                }
+                buffer.replace(request).unwrap();
+            }
+            Some(Opcode::Kill) => xous::msg_blocking_scalar_unpack!(msg, pid, _, _, _, {
+                let result = match allowed.map(|_| running.remove(&(pid as u8))) {
+                    Err(e) => e.to_usize().unwrap(),
+                    Ok(Some(name)) => match xous::destroy_process(xous::PID::new(pid as u8).unwrap()) {
+                        // it got there first
+                        Ok(_) | Err(xous::Error::ProcessNotFound) => {
+                            log::info!("stopped {} (PID {})", name, pid);
+                            0
+                        }
+                        Err(e) => {
+                            log::warn!("couldn't stop {} (PID {}): {:?}", name, pid, e);
+                            running.insert(pid as u8, name);
+                            LoadError::Kernel.to_usize().unwrap()
+                        }
+                    },
+                    Ok(None) => LoadError::NotFound.to_usize().unwrap(),
+                };
+                xous::return_scalar(msg.sender, result).unwrap();
+            }),
+            Some(Opcode::List) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let mut list = buffer.to_original::<AppList, _>().unwrap();
+                // forget apps that have exited on their own
+                running.retain(|&pid, _| xous::cpu_time(xous::PID::new(pid)) != Err(xous::Error::ProcessNotFound));
+                let mut names = if pddb.try_mount() {
+                    pddb.list_keys(APP_DICT, None).unwrap_or_default()
+                } else {
+                    Vec::new()
+                };
+                names.sort();
+                for (slot, name) in list.apps.iter_mut().zip(names.iter()) {
+                    *slot = Some(AppInfo {
+                        name: xous_ipc::String::<APP_NAME_LEN>::from_str(name),
+                        pid: running.iter().filter(|(_, n)| *n == name).map(|(p, _)| *p).min(),
+                    });
+                }
+                buffer.replace(list).unwrap();
+            }
+            Some(Opcode::Quit) => {
+                log::warn!("Quit received, goodbye world!");
+                break;
+            },
+            None => {
+                log::error!("couldn't convert opcode: {:?}", msg);
+            }
+        }
+    }
+    // clean up our program
+    log::trace!("main loop exit, destroying servers");
+    xns.unregister_server(loader_sid).unwrap();
+    xous::destroy_server(loader_sid).unwrap();
+    log::trace!("quitting");
+    xous::terminate_process(0)
+}
@@ -44,6 +44,12 @@ pub(crate) enum Opcode {
     /// set headphone volume -- L&R channels are ganged together in this API, but codec can do separately
     SetHeadphoneVolume,
 
+    /// play a tone, powering up the codec for it if no stream is running
+    PlayTone,
+
+    /// play one of the canned `Clip`s, the same way as a tone
+    PlayClip,
+
     /// Suspend/resume callback
     SuspendResume,
 }
@@ -59,6 +65,25 @@ pub enum VolumeOps {
 }
 
 
+/// Short sounds for system events, played with `Codec::play_clip()`
+#[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive, Copy, Clone)]
+pub enum Clip {
+    /// the battery is running low
+    LowBattery,
+    /// a backup has been written
+    BackupDone,
+    /// a security key wants a touch to confirm, e.g. for FIDO
+    TouchRequest,
+    /// something needs the user's attention
+    Notification,
+    /// an operation failed
+    Failure,
+}
+/// how loud the clips play, as a percent of full scale
+pub const CLIP_VOLUME: u8 = 50;
+/// the longest tone that can be asked for
+pub const MAX_TONE_MS: u32 = 5000;
+
 #[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive)]
 pub(crate) enum EventCallback {
     Event,
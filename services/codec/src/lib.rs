@@ -128,6 +128,26 @@ impl Codec {
             )
         ).map(|_| ())
     }
+    /// Plays a tone of `freq_hz` for `duration_ms`, up to `MAX_TONE_MS`, at `volume` percent of full scale. The
+    /// speaker and headphone volumes apply on top of that. The codec is powered up for the tone and back down
+    /// once it's done, so there's no stream to set up. A tone or clip cuts off one that's still playing, but
+    /// nothing is played while an app has a stream running, as the app owns the audio then.
+    pub fn play_tone(&self, freq_hz: u32, duration_ms: u32, volume: u8) -> Result<(), xous::Error> {
+        send_message(self.conn,
+            Message::new_scalar(Opcode::PlayTone.to_usize().unwrap(),
+                freq_hz as usize,
+                duration_ms.min(MAX_TONE_MS) as usize,
+                volume as usize,
+                0
+            )
+        ).map(|_| ())
+    }
+    /// Plays one of the canned clips for system events, at `CLIP_VOLUME`; see `play_tone()`.
+    pub fn play_clip(&self, clip: Clip) -> Result<(), xous::Error> {
+        send_message(self.conn,
+            Message::new_scalar(Opcode::PlayClip.to_usize().unwrap(), clip.to_usize().unwrap(), 0, 0, 0)
+        ).map(|_| ())
+    }
     pub fn is_running(&self) -> Result<bool, xous::Error> {
         match send_message(self.conn,
             Message::new_blocking_scalar(Opcode::IsLive.to_usize().unwrap(), 0, 0, 0, 0)
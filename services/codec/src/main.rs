@@ -4,6 +4,8 @@
 mod api;
 mod backend;
 use backend::Codec;
+mod tone;
+use tone::{Note, ToneGen};
 
 use num_traits::{ToPrimitive, FromPrimitive};
 use xous_ipc::Buffer;
@@ -20,6 +22,13 @@ struct ScalarCallback {
     cb_to_client_id: u32,
 }
 
+/// A tone or clip that the server is playing itself, rather than an app's stream
+struct Playing {
+    tone: ToneGen,
+    /// if the codec was powered up for it, it's powered back down once it's done
+    powered_up: bool,
+}
+
 #[xous::xous_main]
 fn xmain() -> ! {
     log_server::init_wait().unwrap();
@@ -33,6 +42,12 @@ fn xmain() -> ! {
 
     let codec_conn = xous::connect(codec_sid).expect("couldn't make connection for the codec implementation");
     let mut codec = Codec::new(codec_conn, &xns);
+    // the stream is fed from the audio interrupt through the loop below, so it has to get in ahead of
+    // ordinary work to keep the FIFOs from running dry. Realtime is only for processes that own an
+    // interrupt, which `Codec::new()` has claimed by now.
+    #[cfg(any(target_os = "none", target_os = "xous"))]
+    xous::set_priority(xous::current_pid().unwrap(), xous::Priority::Realtime)
+        .expect("couldn't raise the codec to realtime");
 
     let ticktimer = ticktimer_server::Ticktimer::new().unwrap();
     log::trace!("ready to accept requests");
@@ -51,6 +66,7 @@ fn xmain() -> ! {
     let mut speaker_analog_gain_db: f32 = -6.0;
     let mut headphone_analog_gain_db: f32 = -15.0;
     let mut audio_cb_conns: [Option<ScalarCallback>; 32] = [None; 32];
+    let mut playing: Option<Playing> = None;
     loop {
         let mut msg = xous::receive_message(codec_sid).unwrap();
         //log::trace!("got message {:?}", msg);
@@ -61,9 +77,11 @@ fn xmain() -> ! {
                 codec.resume();
             }),
             Some(api::Opcode::PowerOff) => xous::msg_scalar_unpack!(msg, _, _, _, _, {
+                playing = None;
                 codec.power(false);
             }),
             Some(api::Opcode::Setup8kStereo) => xous::msg_scalar_unpack!(msg, _, _, _, _, {
+                cancel_tone(&mut codec, &mut playing);
                 log::trace!("turning on codec power");
                 codec.power(true);
                 log::trace!("waiting for power up");
@@ -72,6 +90,7 @@ fn xmain() -> ! {
                 codec.init();
             }),
             Some(api::Opcode::ResumeStream) => xous::msg_scalar_unpack!(msg, _, _, _, _, {
+                cancel_tone(&mut codec, &mut playing);
                 if codec.is_on() && codec.is_init() {
                     codec.audio_i2s_start();
                 } else {
@@ -163,7 +182,21 @@ fn xmain() -> ! {
             }
             Some(api::Opcode::AnotherFrame) => xous::msg_scalar_unpack!(msg, _rdcount, _wrcount, _, _, {
                 //log::trace!("A rd {} wr {}", rdcount, wrcount);
-                send_event(&audio_cb_conns, codec.free_play_frames(), codec.available_rec_frames());
+                if playing.is_some() {
+                    continue_tone(&mut codec, &mut playing);
+                } else {
+                    send_event(&audio_cb_conns, codec.free_play_frames(), codec.available_rec_frames());
+                }
+            }),
+            Some(api::Opcode::PlayTone) => xous::msg_scalar_unpack!(msg, freq_hz, duration_ms, volume, _, {
+                let note = Note { freq_hz: freq_hz as u32, ms: (duration_ms as u32).min(MAX_TONE_MS) };
+                start_tone(&mut codec, &ticktimer, &mut playing, ToneGen::new(&[note], volume.min(100) as u8));
+            }),
+            Some(api::Opcode::PlayClip) => xous::msg_scalar_unpack!(msg, clip, _, _, _, {
+                match FromPrimitive::from_usize(clip) {
+                    Some(clip) => start_tone(&mut codec, &ticktimer, &mut playing, ToneGen::new(tone::clip_notes(clip), CLIP_VOLUME)),
+                    None => log::error!("got a clip that we don't recognize, ignoring!"),
+                }
             }),
             Some(api::Opcode::SetSpeakerVolume) => xous::msg_scalar_unpack!(msg, op, gain_code, _, _, {
                 match FromPrimitive::from_usize(op) {
@@ -235,6 +268,59 @@ fn xmain() -> ! {
 }
 
 
+/// Starts playing `tone`, cutting off any that's already playing, unless an app has a stream running
+fn start_tone(codec: &mut Codec, ticktimer: &ticktimer_server::Ticktimer, playing: &mut Option<Playing>, tone: ToneGen) {
+    let powered_up = match playing.take() {
+        Some(previous) => {
+            codec.audio_i2s_stop();
+            previous.powered_up
+        }
+        None if codec.is_live() => {
+            log::info!("an app's stream is running, so the tone isn't played");
+            return;
+        }
+        None if codec.is_on() && codec.is_init() => false,
+        None => {
+            codec.power(true);
+            ticktimer.sleep_ms(2).unwrap();
+            codec.init();
+            true
+        }
+    };
+    let mut now_playing = Playing { tone, powered_up };
+    top_up_tone(codec, &mut now_playing);
+    codec.audio_i2s_start();
+    *playing = Some(now_playing);
+}
+fn top_up_tone(codec: &mut Codec, tone: &mut Playing) {
+    while codec.free_play_frames() > 0 {
+        match tone.tone.next_frame() {
+            Some(frame) => codec.nq_play_frame(frame).unwrap(), // can't fail, as there's room
+            None => break,
+        }
+    }
+}
+/// Called as frames are used up: tops up the frames of the tone, and once they've all been played, stops the
+/// codec, and powers it down if it was powered up for the tone
+fn continue_tone(codec: &mut Codec, playing: &mut Option<Playing>) {
+    if let Some(tone) = playing.as_mut() {
+        top_up_tone(codec, tone);
+        if !codec.can_play() {
+            codec.audio_i2s_stop();
+            if tone.powered_up {
+                codec.power(false);
+            }
+            *playing = None;
+        }
+    }
+}
+/// An app is starting its stream, which takes over from a tone. The codec is left on for the stream.
+fn cancel_tone(codec: &mut Codec, playing: &mut Option<Playing>) {
+    if playing.take().is_some() {
+        codec.audio_i2s_stop();
+    }
+}
+
 fn do_hook(hookdata: ScalarHook, cb_conns: &mut [Option<ScalarCallback>; 32]) {
     let (s0, s1, s2, s3) = hookdata.sid;
     let sid = xous::SID::from_u32(s0, s1, s2, s3);
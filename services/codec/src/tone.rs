@@ -0,0 +1,200 @@
+use crate::api::Clip;
+use codec::{FIFO_DEPTH, ZERO_PCM};
+
+/// the stream is set up by `Setup8kStereo`
+const SAMPLE_RATE_HZ: u32 = 8000;
+/// each note ramps up and down over this long, so it starts and stops without a click
+const RAMP_MS: u32 = 4;
+
+/// One note of a tone or clip; a frequency of 0 is a rest
+#[derive(Copy, Clone)]
+pub(crate) struct Note {
+    pub freq_hz: u32,
+    pub ms: u32,
+}
+
+const LOW_BATTERY: [Note; 3] = [
+    Note { freq_hz: 880, ms: 120 },
+    Note { freq_hz: 0, ms: 60 },
+    Note { freq_hz: 440, ms: 240 },
+];
+const BACKUP_DONE: [Note; 3] = [
+    Note { freq_hz: 523, ms: 100 },
+    Note { freq_hz: 659, ms: 100 },
+    Note { freq_hz: 784, ms: 200 },
+];
+const TOUCH_REQUEST: [Note; 4] = [
+    Note { freq_hz: 988, ms: 80 },
+    Note { freq_hz: 0, ms: 80 },
+    Note { freq_hz: 988, ms: 80 },
+    Note { freq_hz: 0, ms: 80 },
+];
+const NOTIFICATION: [Note; 2] = [
+    Note { freq_hz: 784, ms: 80 },
+    Note { freq_hz: 1047, ms: 160 },
+];
+const FAILURE: [Note; 2] = [
+    Note { freq_hz: 330, ms: 150 },
+    Note { freq_hz: 262, ms: 300 },
+];
+
+pub(crate) fn clip_notes(clip: Clip) -> &'static [Note] {
+    match clip {
+        Clip::LowBattery => &LOW_BATTERY,
+        Clip::BackupDone => &BACKUP_DONE,
+        Clip::TouchRequest => &TOUCH_REQUEST,
+        Clip::Notification => &NOTIFICATION,
+        Clip::Failure => &FAILURE,
+    }
+}
+
+/// Synthesizes a sequence of notes into play frames, one frame at a time as the codec asks for them
+pub(crate) struct ToneGen {
+    notes: Vec<Note>,
+    /// full scale times the volume
+    amplitude: f32,
+    /// the note being played, and how many of its samples are done
+    note: usize,
+    sample: u32,
+    /// set once the frame of silence after the last note is out
+    done: bool,
+}
+impl ToneGen {
+    pub fn new(notes: &[Note], volume: u8) -> ToneGen {
+        ToneGen {
+            notes: notes.to_vec(),
+            amplitude: i16::MAX as f32 * volume.min(100) as f32 / 100.0,
+            note: 0,
+            sample: 0,
+            done: false,
+        }
+    }
+
+    fn next_sample(&mut self) -> Option<i16> {
+        loop {
+            let note = *self.notes.get(self.note)?;
+            let len = note.ms * SAMPLE_RATE_HZ / 1000;
+            if self.sample >= len {
+                self.note += 1;
+                self.sample = 0;
+                continue;
+            }
+            let t = self.sample;
+            self.sample += 1;
+            // past half the sample rate it would alias, so it's left out
+            if note.freq_hz == 0 || note.freq_hz >= SAMPLE_RATE_HZ / 2 {
+                return Some(0);
+            }
+            let ramp_len = RAMP_MS * SAMPLE_RATE_HZ / 1000;
+            let envelope = (t.min(len - 1 - t) as f32 / ramp_len as f32).min(1.0);
+            let phase = 2.0 * core::f32::consts::PI * note.freq_hz as f32 * t as f32 / SAMPLE_RATE_HZ as f32;
+            return Some((self.amplitude * envelope * phase.sin()) as i16);
+        }
+    }
+
+    /// The next frame to play, or None once the notes are all out. The last frame is silence, so the end of the
+    /// last note has left the FIFO by the time the codec runs out of frames.
+    pub fn next_frame(&mut self) -> Option<[u32; FIFO_DEPTH]> {
+        if self.done {
+            return None;
+        }
+        let silence = ZERO_PCM as u32 | (ZERO_PCM as u32) << 16;
+        let mut frame = [silence; FIFO_DEPTH];
+        for (i, stereo_sample) in frame.iter_mut().enumerate() {
+            match self.next_sample() {
+                // the same on both channels: |31 right 16|15 left 0|
+                Some(s) => *stereo_sample = (s as u16 as u32) << 16 | s as u16 as u32,
+                None => {
+                    // nothing was left for this frame, so it's the silence that ends the notes
+                    if i == 0 {
+                        self.done = true;
+                    }
+                    break;
+                }
+            }
+        }
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every frame of `notes` at `volume`, as the left channel's samples
+    fn play(notes: &[Note], volume: u8) -> Vec<Vec<i16>> {
+        let mut tone = ToneGen::new(notes, volume);
+        core::iter::from_fn(|| tone.next_frame())
+            .map(|frame| {
+                frame.iter().map(|&stereo| {
+                    assert_eq!(stereo >> 16, stereo & 0xFFFF, "both channels carry the same sample");
+                    stereo as u16 as i16
+                }).collect()
+            })
+            .collect()
+    }
+    fn peak(frames: &[Vec<i16>]) -> i32 {
+        frames.iter().flatten().map(|&s| (s as i32).abs()).max().unwrap_or(0)
+    }
+
+    #[test]
+    fn frame_count() {
+        let samples = |notes: &[Note]| notes.iter().map(|note| note.ms * SAMPLE_RATE_HZ / 1000).sum::<u32>() as usize;
+        for notes in [&LOW_BATTERY[..], &BACKUP_DONE, &TOUCH_REQUEST, &NOTIFICATION, &FAILURE] {
+            // whole frames for the notes, then one of silence
+            let expected = (samples(notes) + FIFO_DEPTH - 1) / FIFO_DEPTH + 1;
+            assert_eq!(play(notes, 50).len(), expected);
+        }
+        // notes that fill their last frame exactly still get just the one of silence
+        let exact = [Note { freq_hz: 440, ms: (FIFO_DEPTH as u32 * 1000 / SAMPLE_RATE_HZ) * 2 }];
+        assert_eq!(samples(&exact) % FIFO_DEPTH, 0);
+        assert_eq!(play(&exact, 50).len(), samples(&exact) / FIFO_DEPTH + 1);
+        assert_eq!(play(&[], 50).len(), 1);
+    }
+
+    #[test]
+    fn ends_in_silence() {
+        let frames = play(&NOTIFICATION, 100);
+        assert!(frames.last().unwrap().iter().all(|&s| s == ZERO_PCM as i16));
+        // what's left of the last frame of notes is silence too
+        let samples = NOTIFICATION.iter().map(|note| note.ms * SAMPLE_RATE_HZ / 1000).sum::<u32>() as usize;
+        let flat: Vec<i16> = frames.iter().flatten().copied().collect();
+        assert!(flat[samples..].iter().all(|&s| s == 0));
+    }
+
+    #[test]
+    fn envelope_ramps_each_note() {
+        let notes = [Note { freq_hz: 1000, ms: 50 }, Note { freq_hz: 0, ms: 10 }, Note { freq_hz: 2000, ms: 50 }];
+        let flat: Vec<i16> = play(&notes, 100).iter().flatten().copied().collect();
+        let amplitude = i16::MAX as f32;
+        let ramp_len = RAMP_MS * SAMPLE_RATE_HZ / 1000;
+        let mut start = 0;
+        for note in notes.iter() {
+            let len = note.ms * SAMPLE_RATE_HZ / 1000;
+            for t in 0..len {
+                let s = flat[start + t as usize] as f32;
+                // within the ramp at either end, no louder than the envelope lets it be
+                let bound = amplitude * (t.min(len - 1 - t) as f32 / ramp_len as f32).min(1.0);
+                assert!(s.abs() <= bound + 1.0, "sample {} of a {} Hz note is {}, over {}", t, note.freq_hz, s, bound);
+            }
+            // each note starts and stops at zero, so there's no click
+            assert_eq!(flat[start], 0);
+            assert_eq!(flat[start + len as usize - 1], 0);
+            start += len as usize;
+        }
+    }
+
+    #[test]
+    fn volume_scales_the_amplitude() {
+        let notes = [Note { freq_hz: 1000, ms: 100 }];
+        let full = peak(&play(&notes, 100));
+        assert!(full as f32 >= i16::MAX as f32 * 0.99, "peak at full volume is {}", full);
+        let half = peak(&play(&notes, 50));
+        assert!((half - full / 2).abs() <= 1, "peak at half volume is {}, full is {}", half, full);
+        assert_eq!(peak(&play(&notes, 0)), 0);
+        // past full scale it's held at full
+        assert_eq!(peak(&play(&notes, 200)), full);
+        // a note past half the sample rate would alias, so it's silent
+        assert_eq!(peak(&play(&[Note { freq_hz: SAMPLE_RATE_HZ / 2, ms: 50 }], 100)), 0);
+    }
+}
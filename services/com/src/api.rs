@@ -90,6 +90,39 @@ impl Default for SsidReturn {
         }
     }
 }
+/// The most bytes of information elements the EC passes on from each beacon
+pub const SSID_IE_LEN: usize = 256;
+/// What's needed from an access point's beacon to tell how it's secured
+#[derive(Debug, Copy, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SsidIes {
+    pub name: xous_ipc::String<32>,
+    /// the beacon's capability field
+    pub capability: u16,
+    /// how many bytes of `ies` are filled in
+    pub len: u16,
+    pub ies: [u8; SSID_IE_LEN],
+}
+impl Default for SsidIes {
+    fn default() -> Self {
+        SsidIes {
+            name: xous_ipc::String::<32>::new(),
+            capability: 0,
+            len: 0,
+            ies: [0; SSID_IE_LEN],
+        }
+    }
+}
+#[derive(Debug, Copy, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub(crate) struct SsidIesReturn {
+    pub list: [SsidIes; 8],
+}
+impl Default for SsidIesReturn {
+    fn default() -> Self {
+        SsidIesReturn {
+            list: [SsidIes::default(); 8],
+        }
+    }
+}
 #[derive(Debug, Copy, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
 pub struct WlanStatusIpc {
     pub ssid: Option<SsidRecord>,
@@ -294,6 +327,9 @@ pub(crate) enum Opcode {
 
     /// gets more details on the latest interrupt
     IntFetchVector = 49,
+
+    /// Return the capability field and information elements of the latest SSID list
+    SsidFetchIes = 50,
 }
 
 /// These enums indicate what kind of callback type we're sending.
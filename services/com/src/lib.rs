@@ -287,6 +287,16 @@ impl Com {
         Ok(ret)
     }
 
+    /// returns the capability field and information elements of each AP in the most recent incremental scan
+    /// results, for working out how it's secured. ECs too old to pass these on return an empty list.
+    pub fn ssid_fetch_ies(&self) -> Result<Vec<SsidIes>, xous::Error> {
+        let ies_alloc = SsidIesReturn::default();
+        let mut buf = Buffer::into_buf(ies_alloc).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.conn, Opcode::SsidFetchIes.to_u32().unwrap()).or(Err(xous::Error::InternalError))?;
+        let response = buf.to_original::<SsidIesReturn, _>().unwrap();
+        Ok(response.list.iter().filter(|ap| !ap.name.as_str().unwrap_or("").is_empty()).copied().collect())
+    }
+
     pub fn get_standby_current(&self) -> Result<Option<i16>, xous::Error> {
         if let xous::Result::Scalar2(valid, current) =
             send_message(self.conn, Message::new_blocking_scalar(Opcode::StandbyCurrent.to_usize().unwrap(), 0, 0, 0, 0)).unwrap() {
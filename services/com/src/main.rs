@@ -21,6 +21,10 @@ const LEGACY_REV: u32 = 0x8b5b_8e50; // this is the git rev shipped before we we
 const LEGACY_TAG: u32 = 0x00_09_05_00; // this is corresponding tag
 const STD_TIMEOUT: u32 = 100;
 const EC_BOOT_WAIT_MS: usize = 3500;
+/// EC verb that reads back the beacon details of the latest SSID list. It's newer than the com_rs we build
+/// against, so it's spelled out here; ECs before `SSID_IES_TAG` don't know it.
+const SSID_FETCH_IES_VERB: u16 = 0x2103;
+const SSID_IES_TAG: u32 = 0x00_09_0a_00;
 #[derive(Debug, Copy, Clone)]
 pub struct WorkRequest {
     work: ComSpec,
@@ -851,6 +855,31 @@ fn xmain() -> ! {
                 }
                 buffer.replace(ssid_ret).unwrap();
             }
+            Some(Opcode::SsidFetchIes) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let mut ies_ret = buffer.to_original::<SsidIesReturn, _>().expect("couldn't convert incoming storage");
+                if ec_tag >= SSID_IES_TAG {
+                    com.txrx(SSID_FETCH_IES_VERB);
+                    // Same order as SSID_FETCH_STR. Each record is the capability field, then the SSID as a length
+                    // word and 16 words of name, then the IEs as a length word and SSID_IE_LEN / 2 words.
+                    for ap in ies_ret.list.iter_mut() {
+                        let mut read = || com.wait_txrx(ComState::LINK_READ.verb, Some(STD_TIMEOUT));
+                        ap.capability = read();
+                        let mut name = [0u8; 32];
+                        let name_len = (read() as usize).min(name.len());
+                        for word in name.chunks_mut(2) {
+                            word.copy_from_slice(&read().to_le_bytes());
+                        }
+                        ap.len = read().min(SSID_IE_LEN as u16);
+                        for word in ap.ies.chunks_mut(2) {
+                            word.copy_from_slice(&read().to_le_bytes());
+                        }
+                        ap.name.clear();
+                        ap.name.append(core::str::from_utf8(&name[..name_len]).unwrap_or("")).ok();
+                    }
+                }
+                buffer.replace(ies_ret).unwrap();
+            }
             Some(Opcode::WlanOn) => {
                 info!("TODO: implement WlanOn");
                 com.txrx(ComState::WLAN_ON.verb);
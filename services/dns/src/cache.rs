@@ -0,0 +1,87 @@
+//! The resolver's cache. An address is kept for as long as the TTL it came with, counted from when it was
+//! looked up, and is never handed out after that. A TTL of 0 means the address isn't to be cached at all;
+//! TTLs over `MAX_TTL_SECS` are cut down to it, so an address that has since changed doesn't linger for weeks.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+const MAX_TTL_SECS: u32 = 24 * 60 * 60;
+
+pub(crate) struct DnsCache {
+    /// each name's addresses, with the `Ticktimer::elapsed_ms()` at which each one expires
+    entries: HashMap<String, HashMap<IpAddr, u64>>,
+    ticktimer: ticktimer_server::Ticktimer,
+    /// a frozen cache doesn't expire anything
+    frozen: bool,
+}
+
+impl DnsCache {
+    pub(crate) fn new() -> DnsCache {
+        DnsCache {
+            entries: HashMap::new(),
+            ticktimer: ticktimer_server::Ticktimer::new().unwrap(),
+            frozen: false,
+        }
+    }
+
+    /// Caches the addresses found for `name`, each with its TTL in seconds, in place of any cached before
+    pub(crate) fn insert(&mut self, name: &str, records: &HashMap<IpAddr, u32>) {
+        let now = self.ticktimer.elapsed_ms();
+        let entry: HashMap<IpAddr, u64> = records
+            .iter()
+            .filter(|(_, &ttl)| ttl > 0)
+            .map(|(&addr, &ttl)| (addr, now + ttl.min(MAX_TTL_SECS) as u64 * 1000))
+            .collect();
+        if entry.is_empty() {
+            self.entries.remove(name);
+        } else {
+            self.entries.insert(name.to_string(), entry);
+        }
+    }
+
+    /// The addresses for `name` that haven't expired, each with the seconds it has left, or `None` if there
+    /// aren't any
+    pub(crate) fn get(&mut self, name: &str) -> Option<HashMap<IpAddr, u32>> {
+        let now = self.ticktimer.elapsed_ms();
+        let frozen = self.frozen;
+        let entry = self.entries.get_mut(name)?;
+        if !frozen {
+            entry.retain(|_, &mut expiry| expiry > now);
+        }
+        if entry.is_empty() {
+            self.entries.remove(name);
+            return None;
+        }
+        Some(
+            entry
+                .iter()
+                .map(|(&addr, &expiry)| (addr, (expiry.saturating_sub(now) / 1000) as u32))
+                .collect(),
+        )
+    }
+
+    /// Drops everything that has expired, so names that aren't looked up again don't take up memory forever
+    pub(crate) fn expire(&mut self) {
+        if self.frozen {
+            return;
+        }
+        let now = self.ticktimer.elapsed_ms();
+        for (name, entry) in self.entries.iter_mut() {
+            entry.retain(|addr, &mut expiry| {
+                if expiry <= now {
+                    log::debug!("DNS cache expiring {}->{:?}", name, addr);
+                }
+                expiry > now
+            });
+        }
+        self.entries.retain(|_, entry| !entry.is_empty());
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub(crate) fn set_frozen(&mut self, frozen: bool) {
+        self.frozen = frozen;
+    }
+}
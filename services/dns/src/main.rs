@@ -3,6 +3,9 @@
 
 mod api;
 use api::*;
+mod cache;
+use cache::DnsCache;
+mod mdns;
 
 use net::{Duration, NetIpAddr};
 use num_traits::*;
@@ -303,20 +306,26 @@ impl Message {
     }
 }
 
+/// The resolver's socket is bound to this address. In hosted mode it's a host socket, which can't reach a DNS
+/// server from the loopback address.
+#[cfg(any(target_os = "none", target_os = "xous"))]
+const RESOLVER_BIND_ADDR: &str = "127.0.0.1";
+#[cfg(not(any(target_os = "none", target_os = "xous")))]
+const RESOLVER_BIND_ADDR: &str = "0.0.0.0";
+
 pub struct Resolver {
     /// DnsServerManager is a service of the Net crate that automatically updates the DNS server list
     mgr: net::DnsServerManager,
     socket: net::UdpSocket,
     buf: [u8; DNS_PKT_MAX_LEN],
     trng: trng::Trng,
-    freeze: bool,
 }
 impl Resolver {
     pub fn new(xns: &xous_names::XousNames) -> Resolver {
         let trng = trng::Trng::new(&xns).unwrap();
         let local_port = (49152 + trng.get_u32().unwrap() % 16384) as u16;
         let mut socket = net::UdpSocket::bind_xous(
-            format!("127.0.0.1:{}", local_port),
+            format!("{}:{}", RESOLVER_BIND_ADDR, local_port),
             Some(DNS_PKT_MAX_LEN as u16),
         )
         .expect("couldn't create socket for DNS resolver");
@@ -332,7 +341,6 @@ impl Resolver {
             socket,
             buf: [0; DNS_PKT_MAX_LEN],
             trng,
-            freeze: false,
         }
     }
     pub fn add_server(&mut self, addr: IpAddr) {
@@ -345,12 +353,8 @@ impl Resolver {
         self.mgr.clear();
     }
     pub fn set_freeze_config(&mut self, freeze: bool) {
-        self.freeze = freeze;
         self.mgr.set_freeze(freeze);
     }
-    pub fn get_freeze(&self) -> bool {
-        self.freeze
-    }
     /// this allows us to re-use the TRNG object
     pub fn trng_u32(&self) -> u32 {
         self.trng.get_u32().unwrap()
@@ -496,9 +500,14 @@ fn xmain() -> ! {
     // if you wanted to force a server into the initial config, you can do it here, for example:
     // resolver.add_server(IpAddr::V4(Ipv4Addr::new(1,1,1,1)));
 
-    let mut dns_cache = HashMap::<std::string::String, HashMap<IpAddr, u32>>::new();
+    let mut dns_cache = DnsCache::new();
+
+    // answer for our own name on the local network
+    thread::spawn(move || {
+        mdns::responder();
+    });
 
-    // build a thread that pings the UpdateTtl function once every few minutes to expire the DNS cache
+    // build a thread that pings the UpdateTtl function once every few minutes to clear expired entries out of the cache
     thread::spawn({
         let local_cid = xous::connect(dns_sid).unwrap();
         move || {
@@ -531,7 +540,7 @@ fn xmain() -> ! {
                         log::trace!("performing a lookup of {}", owned_name);
                         // Try to get the result out of the DNS cache
                         if let Some(entries) = dns_cache.get(&owned_name) {
-                            fill_response(msg, entries);
+                            fill_response(msg, &entries);
                             continue;
                         }
 
@@ -539,7 +548,7 @@ fn xmain() -> ! {
                         match resolver.resolve(&owned_name) {
                             Ok(cache_entry) => {
                                 fill_response(msg, &cache_entry);
-                                dns_cache.insert(owned_name, cache_entry);
+                                dns_cache.insert(&owned_name, &cache_entry);
                                 continue;
                             }
                             Err(e) => {
@@ -581,11 +590,7 @@ fn xmain() -> ! {
                     match resolver.resolve(name.as_str().unwrap()) {
                         Ok(cache_entry) => {
                             if cache_entry.len() > 0 {
-                                dns_cache.insert(name_std, cache_entry);
-
-                                // now pick the entry back out again, as it was consumed...
-                                let name_std = std::string::String::from(name.as_str().unwrap());
-                                let cache_entry = dns_cache.get(&name_std).unwrap();
+                                dns_cache.insert(&name_std, &cache_entry);
 
                                 // pick a random entry from the query response
                                 let rand = resolver.trng_u32() as usize % cache_entry.len();
@@ -619,53 +624,20 @@ fn xmain() -> ! {
                     }
                 }
             }
-            Some(Opcode::UpdateTtl) => msg_scalar_unpack!(msg, incr_secs, _, _, _, {
-                let increment = if incr_secs < u32::MAX as usize {
-                    incr_secs as u32
-                } else {
-                    u32::MAX
-                };
-                if !resolver.get_freeze() {
-                    let mut expired_names = Vec::<std::string::String>::new();
-                    for (name, cache_map) in dns_cache.iter_mut() {
-                        // each entry can have multiple names with a different TTL
-                        // decrement the TTL, and note which go to zero
-                        let mut expired_entries = Vec::<IpAddr>::new();
-                        for (entry, ttl) in cache_map.iter_mut() {
-                            log::debug!("entry: {:?}, ttl: {}, incr: {}", entry, ttl, increment);
-                            if *ttl < increment {
-                                *ttl = 0;
-                                expired_entries.push(*entry);
-                            } else {
-                                *ttl = *ttl - increment as u32;
-                            }
-                        }
-                        // remove the entries that are 0
-                        for entry in expired_entries {
-                            log::debug!("DNS cache expiring {:?}", entry);
-                            cache_map.remove(&entry);
-                        }
-                        // if all the entries are removed, mark for removal from the cache entirely
-                        if cache_map.len() == 0 {
-                            // have to copy the name to a new object to track it
-                            let name = std::string::String::from(name.as_str());
-                            expired_names.push(name);
-                        }
-                    }
-                    for name in expired_names {
-                        log::debug!("DNS cache removing {}", &name);
-                        dns_cache.remove(&name);
-                    }
-                }
+            Some(Opcode::UpdateTtl) => msg_scalar_unpack!(msg, _, _, _, _, {
+                // entries expire by the clock, not by this count, so all that's left to do is tidy up
+                dns_cache.expire();
             }),
             Some(Opcode::Flush) => {
                 dns_cache.clear();
             }
             Some(Opcode::FreezeConfig) => {
                 resolver.set_freeze_config(true);
+                dns_cache.set_frozen(true);
             }
             Some(Opcode::ThawConfig) => {
                 resolver.set_freeze_config(false);
+                dns_cache.set_frozen(false);
             }
             Some(Opcode::Quit) => {
                 log::warn!("got quit!");
@@ -0,0 +1,192 @@
+//! A minimal mDNS (RFC 6762) responder, so the device can be found on the local network as `MDNS_HOSTNAME`
+//! without knowing its address. It answers A queries for that one name, and announces the name whenever the
+//! device's address changes. It doesn't probe for other devices using the name, or resolve `.local` names.
+
+use std::convert::TryInto;
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, SocketAddr};
+
+use net::Duration;
+
+pub(crate) const MDNS_HOSTNAME: &str = "precursor.local";
+const MDNS_ADDR: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+/// RFC 6762 suggests 120 seconds for a host's address
+const MDNS_TTL_SECS: u32 = 120;
+/// and no more than 10 seconds in a reply to a plain DNS resolver
+const LEGACY_TTL_SECS: u32 = 10;
+/// how often to check whether our address has changed
+const POLL_INTERVAL_MS: u64 = 10_000;
+const MDNS_PKT_MAX_LEN: usize = 1500;
+
+const TYPE_A: u16 = 1;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// On a question's class, asks for the answer to be sent back unicast; on an answer's, tells the receiver
+/// to replace what it has cached for the name
+const CLASS_TOP_BIT: u16 = 0x8000;
+
+pub(crate) fn responder() {
+    let mut socket = match net::UdpSocket::bind_xous(
+        format!("0.0.0.0:{}", MDNS_PORT),
+        Some(MDNS_PKT_MAX_LEN as u16),
+    ) {
+        Ok(socket) => socket,
+        Err(e) => {
+            log::error!(
+                "couldn't bind the mDNS port, {} won't be answered for: {:?}",
+                MDNS_HOSTNAME,
+                e
+            );
+            return;
+        }
+    };
+    socket
+        .set_read_timeout(Some(Duration::from_millis(POLL_INTERVAL_MS)))
+        .unwrap();
+    socket.set_nonblocking(false).unwrap();
+    // the Net server keeps us in the group, and reports it again, as the network comes and goes
+    if let Err(e) = socket.join_multicast_v4(&MDNS_ADDR, &Ipv4Addr::UNSPECIFIED) {
+        log::error!("couldn't join the mDNS group: {:?}", e);
+    }
+    let group = SocketAddr::new(MDNS_ADDR.into(), MDNS_PORT);
+    let netmgr = net::NetManager::new();
+    let mut my_addr: Option<Ipv4Addr> = None;
+    let mut pkt = [0u8; MDNS_PKT_MAX_LEN];
+    loop {
+        let addr = netmgr
+            .get_ipv4_config()
+            .map(|config| Ipv4Addr::from(config.addr));
+        if addr != my_addr {
+            my_addr = addr;
+            if let Some(addr) = my_addr {
+                log::info!("announcing {} at {}", MDNS_HOSTNAME, addr);
+                socket.send_to(&response(0, false, addr), &group).ok();
+            }
+        }
+        match socket.recv_from(&mut pkt) {
+            Ok((len, src)) => {
+                if let Some(addr) = my_addr {
+                    if let Some((reply, dest)) =
+                        answer(&pkt[..len.min(pkt.len())], src, group, addr)
+                    {
+                        log::debug!("answering mDNS query from {:?}", src);
+                        socket.send_to(&reply, &dest).ok();
+                    }
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(e) => log::warn!("mDNS receive failed: {:?}", e),
+        }
+    }
+}
+
+fn read_u16(pkt: &[u8], index: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        pkt.get(index..index + 2)?.try_into().unwrap(),
+    ))
+}
+
+/// Reads the name at `index`, following compression pointers, and returns it along with the index just past it
+fn read_name(pkt: &[u8], mut index: usize) -> Option<(String, usize)> {
+    let mut name = String::new();
+    let mut end = None;
+    let mut jumps = 0;
+    loop {
+        let len = *pkt.get(index)? as usize;
+        if len == 0 {
+            index += 1;
+            break;
+        }
+        if len & 0xc0 == 0xc0 {
+            end.get_or_insert(index + 2);
+            // a name can only point back so many times before it has to be a loop
+            jumps += 1;
+            if jumps > 16 {
+                return None;
+            }
+            index = (len & 0x3f) << 8 | *pkt.get(index + 1)? as usize;
+            continue;
+        }
+        if !name.is_empty() {
+            name.push('.');
+        }
+        name.push_str(std::str::from_utf8(pkt.get(index + 1..index + 1 + len)?).ok()?);
+        index += 1 + len;
+    }
+    Some((name, end.unwrap_or(index)))
+}
+
+/// The reply to `query`, and where to send it, if it asks for our address
+fn answer(
+    query: &[u8],
+    src: SocketAddr,
+    group: SocketAddr,
+    addr: Ipv4Addr,
+) -> Option<(Vec<u8>, SocketAddr)> {
+    let id = read_u16(query, 0)?;
+    let flags = read_u16(query, 2)?;
+    // only standard queries are answered; bit 15 marks a response, bits 11-14 the opcode
+    if flags & 0xf800 != 0 {
+        return None;
+    }
+    let mut index = 12;
+    let mut asked = false;
+    let mut unicast = false;
+    for _ in 0..read_u16(query, 4)? {
+        let (name, next) = read_name(query, index)?;
+        let qtype = read_u16(query, next)?;
+        let qclass = read_u16(query, next + 2)?;
+        index = next + 4;
+        if name.eq_ignore_ascii_case(MDNS_HOSTNAME)
+            && (qtype == TYPE_A || qtype == TYPE_ANY)
+            && qclass & !CLASS_TOP_BIT == CLASS_IN
+        {
+            asked = true;
+            unicast |= qclass & CLASS_TOP_BIT != 0;
+        }
+    }
+    if !asked {
+        None
+    } else if src.port() != MDNS_PORT {
+        // a query from any other port is from a plain DNS resolver, which wants a plain DNS reply
+        Some((response(id, true, addr), src))
+    } else if unicast {
+        Some((response(0, false, addr), src))
+    } else {
+        Some((response(0, false, addr), group))
+    }
+}
+
+/// An answer giving `addr` for `MDNS_HOSTNAME`. A `legacy` answer, for a plain DNS resolver, repeats the
+/// question and has a shorter TTL.
+fn response(id: u16, legacy: bool, addr: Ipv4Addr) -> Vec<u8> {
+    let mut name = Vec::new();
+    for label in MDNS_HOSTNAME.split('.') {
+        name.push(label.len() as u8);
+        name.extend_from_slice(label.as_bytes());
+    }
+    name.push(0);
+
+    let mut pkt = Vec::new();
+    pkt.extend_from_slice(&id.to_be_bytes());
+    pkt.extend_from_slice(&0x8400u16.to_be_bytes()); // an authoritative response
+    pkt.extend_from_slice(&(if legacy { 1u16 } else { 0 }).to_be_bytes()); // questions
+    pkt.extend_from_slice(&1u16.to_be_bytes()); // answers
+    pkt.extend_from_slice(&[0; 4]); // authority + additional
+    let (class, ttl) = if legacy {
+        pkt.extend_from_slice(&name);
+        pkt.extend_from_slice(&TYPE_A.to_be_bytes());
+        pkt.extend_from_slice(&CLASS_IN.to_be_bytes());
+        (CLASS_IN, LEGACY_TTL_SECS)
+    } else {
+        (CLASS_IN | CLASS_TOP_BIT, MDNS_TTL_SECS)
+    };
+    pkt.extend_from_slice(&name);
+    pkt.extend_from_slice(&TYPE_A.to_be_bytes());
+    pkt.extend_from_slice(&class.to_be_bytes());
+    pkt.extend_from_slice(&ttl.to_be_bytes());
+    pkt.extend_from_slice(&4u16.to_be_bytes());
+    pkt.extend_from_slice(&addr.octets());
+    pkt
+}
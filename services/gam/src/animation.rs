@@ -0,0 +1,97 @@
+use crate::api::*;
+use num_traits::*;
+
+#[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive)]
+enum AnimatorOp {
+    /// start ticking at the given interval; the generation tags the ticks so stale ones can be dropped
+    Run, //(interval_ms, generation)
+    Stop,
+    Tick, //(generation)
+}
+
+/// The frame timing of an app's animation, as registered with the GAM
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Animation {
+    pub frame_id: u32,
+    pub interval_ms: u32,
+    /// number of frames sent to the app since the animation was registered
+    pub frame: usize,
+}
+
+/// Drives the animation of the focused app. Only one context has focus at a time, so a single pump
+/// thread is enough: it's (re)started with the focused app's interval on every focus change, and sends
+/// `AnimationFrame` to the GAM main loop, which passes the frame on to the app.
+pub(crate) struct Animator {
+    conn: xous::CID,
+    generation: usize,
+    running: bool,
+}
+impl Animator {
+    pub(crate) fn new(main_conn: xous::CID) -> Animator {
+        let sid = xous::create_server().unwrap();
+        let conn = xous::connect(sid).unwrap();
+        std::thread::spawn(move || {
+            let tt = ticktimer_server::Ticktimer::new().unwrap();
+            let cid_to_self = xous::connect(sid).unwrap();
+            let mut interval_ms = 0;
+            let mut current = 0;
+            loop {
+                let msg = xous::receive_message(sid).unwrap();
+                match FromPrimitive::from_usize(msg.body.id()) {
+                    Some(AnimatorOp::Run) => xous::msg_scalar_unpack!(msg, interval, generation, _, _, {
+                        interval_ms = interval;
+                        current = generation;
+                        xous::send_message(cid_to_self,
+                            xous::Message::new_scalar(AnimatorOp::Tick.to_usize().unwrap(), generation, 0, 0, 0)
+                        ).expect("couldn't start animation");
+                    }),
+                    Some(AnimatorOp::Stop) => current = 0,
+                    Some(AnimatorOp::Tick) => xous::msg_scalar_unpack!(msg, generation, _, _, _, {
+                        // a restart leaves the previous chain of ticks behind; it dies out here
+                        if generation == current {
+                            // blocking, so frames aren't queued up faster than the GAM can hand them out
+                            xous::send_message(main_conn,
+                                xous::Message::new_blocking_scalar(Opcode::AnimationFrame.to_usize().unwrap(), generation, 0, 0, 0)
+                            ).expect("couldn't send animation frame");
+                            tt.sleep_ms(interval_ms).unwrap();
+                            xous::send_message(cid_to_self,
+                                xous::Message::new_scalar(AnimatorOp::Tick.to_usize().unwrap(), generation, 0, 0, 0)
+                            ).expect("couldn't schedule animation frame");
+                        }
+                    }),
+                    None => log::error!("unhandled animator message {:?}", msg),
+                }
+            }
+        });
+        Animator {
+            conn,
+            generation: 0,
+            running: false,
+        }
+    }
+    /// Starts sending frames for `animation`, or stops the frames if there's none
+    pub(crate) fn follow(&mut self, animation: Option<Animation>) {
+        match animation {
+            Some(a) => {
+                // never 0, so a stopped animator can't mistake a tick for its own
+                self.generation = self.generation.wrapping_add(1).max(1);
+                self.running = true;
+                xous::send_message(self.conn,
+                    xous::Message::new_scalar(AnimatorOp::Run.to_usize().unwrap(), a.interval_ms as usize, self.generation, 0, 0)
+                ).expect("couldn't start animator");
+            }
+            None => {
+                if self.running {
+                    self.running = false;
+                    xous::send_message(self.conn,
+                        xous::Message::new_scalar(AnimatorOp::Stop.to_usize().unwrap(), 0, 0, 0, 0)
+                    ).expect("couldn't stop animator");
+                }
+            }
+        }
+    }
+    /// Checks that a frame comes from the animation that's currently running
+    pub(crate) fn is_current(&self, generation: usize) -> bool {
+        self.running && generation == self.generation
+    }
+}
@@ -1,4 +1,4 @@
-use graphics_server::api::{Rectangle, TextView, Line, RoundedRectangle, Circle, Point, Gid};
+use graphics_server::api::{Rectangle, TextView, Line, RoundedRectangle, Circle, Arc, Point, Gid};
 use xous_ipc::String;
 
 pub(crate) const SERVER_NAME_GAM: &str      = "_Graphical Abstraction Manager_";
@@ -9,6 +9,7 @@ pub enum GamObjectType {
     Circ(Circle),
     Rect(Rectangle),
     RoundRect(RoundedRectangle),
+    Arc(Arc),
 }
 
 #[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
@@ -58,6 +59,124 @@ pub struct SetCanvasBoundsRequest {
     pub granted: Option<Point>,
 }
 
+/// Maximum number of tabs a single app can register, including its original content canvas
+pub const MAX_TABS: usize = 4;
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct TabRegistration {
+    /// app token of the registering context
+    pub token: [u32; 4],
+    pub names: [String::<32>; MAX_TABS],
+    pub count: usize,
+    /// opcode sent to the app's listener, with the index of the newly visible tab as the first argument
+    pub tabchange_id: u32,
+    /// filled in by the GAM with the canvas of each tab; `None` if the registration was refused
+    pub gids: Option<[Gid; MAX_TABS]>,
+}
+/// How long a toast stays up if the caller doesn't care
+pub const TOAST_DEFAULT_MS: u32 = 2500;
+pub const TOAST_MIN_MS: u32 = 500;
+pub const TOAST_MAX_MS: u32 = 10_000;
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct ToastAction {
+    /// the app token of the context asking for the key; only boot contexts, which hold a token, get it
+    pub token: [u32; 4],
+    /// the key that triggers the action, as a `char`
+    pub key: u32,
+    /// server that receives `opcode` as a scalar message when the key is pressed; arg1 identifies the toast
+    pub sid: [u32; 4],
+    pub opcode: u32,
+}
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct ToastRequest {
+    pub text: String::<128>,
+    /// clamped to `TOAST_MIN_MS`..=`TOAST_MAX_MS`
+    pub duration_ms: u32,
+    pub action: Option<ToastAction>,
+}
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct TabSwitch {
+    pub token: [u32; 4],
+    pub index: usize,
+}
+
+/// Shortest frame interval the GAM will schedule; faster animations just smear on the memory LCD
+pub const ANIMATION_MIN_INTERVAL_MS: u32 = 20;
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct AnimationRegistration {
+    /// app token of the animated context
+    pub token: [u32; 4],
+    /// opcode sent to the app's listener for every frame, with the frame count as the first argument
+    pub frame_id: u32,
+    /// time between frames; 0 cancels the animation
+    pub interval_ms: u32,
+}
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct KeyEventRegistration {
+    /// app token of the context that wants the key events
+    pub token: [u32; 4],
+    /// opcode sent to the app's listener for every keydown and keyup, with the `KeyEvent` as the first three
+    /// arguments; `None` stops them
+    pub keyevent_id: Option<u32>,
+}
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct HotkeyRegistration {
+    /// app token of the context registering the key
+    pub token: [u32; 4],
+    /// the key that's pressed with select held, as it's sent without shift
+    pub key: u32,
+    /// the server the key is sent to, as a scalar message with the key as the first argument
+    pub sid: [u32; 4],
+    pub opcode: u32,
+    /// set by the GAM: whether the key is now the registrant's
+    pub accepted: bool,
+}
+/// Number of slots shown at the right end of the status bar's lower line
+pub const STATUS_SLOT_COUNT: usize = 3;
+/// Number of claims the GAM keeps track of; claims beyond the visible slots wait their turn by priority
+pub const MAX_STATUS_CLAIMS: usize = 8;
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct StatusSlotClaim {
+    /// identifies the claimant in logs; a name can only hold one claim
+    pub name: String::<32>,
+    /// higher numbers win a slot over lower ones; ties go to the earlier claim
+    pub priority: u8,
+    /// filled in by the GAM with the token used to update the slot; `None` if the claim was refused
+    pub token: Option<[u32; 4]>,
+}
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct StatusSlotUpdate {
+    pub token: [u32; 4],
+    /// an icon glyph and/or a few characters; an empty string hides the slot without giving up the claim
+    pub text: String::<16>,
+}
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct StatusSlotList {
+    /// token of the status bar; the list is only handed out to the status bar
+    pub token: [u32; 4],
+    /// the winning slots, highest priority first
+    pub slots: [String::<16>; STATUS_SLOT_COUNT],
+    pub count: usize,
+    /// bumped by the GAM every time the visible slots change
+    pub generation: u32,
+}
+
+/// Most clip regions that can be pushed onto a canvas at once, see `Gam::push_clip()`
+pub const MAX_CLIP_DEPTH: usize = 8;
+/// `region` is in canvas-local coordinates; `granted` is filled in by the GAM.
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct PushClipRequest {
+    pub canvas: Gid,
+    pub region: Rectangle,
+    pub granted: bool,
+}
+
+/// `region` is in canvas-local coordinates; `None` flushes everything drawn on the canvas since the last flush.
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct FlushRegionRequest {
+    pub canvas: Gid,
+    pub region: Option<Rectangle>,
+}
+
 #[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
 pub struct SetAudioOpcode {
     pub token: [u32; 4],
@@ -82,7 +201,7 @@ pub struct UxRegistration {
     // request specification
     pub app_name: String::<128>,  // the putative name of our application - GAM may modify this if a spoof attempt is detected
     pub ux_type: UxType,
-    pub predictor: Option<String::<64>>, // optional specification for an IME prediction engine to use. This can be updated later on, or None and a default engine will be provided.
+    pub predictor: Option<String::<64>>, // optional specification for an IME prediction engine to use. This can be updated later on, or None and a default engine will be provided: the dictionary predictor, `ime_plugin_dict::SERVER_NAME_IME_PLUGIN_DICT`.
 
     // Callbacks:
     /// SID ofserver for callbacks from the GAM. Note this is a disclosure of the SID, which is normally a secret in the kernel services.
@@ -98,6 +217,9 @@ pub struct UxRegistration {
     pub rawkeys_id: Option<u32>,
     /// optional opcode ID code for focus change notifications. Most applications will want to provide this to stop hogging resources when backgrounded
     pub focuschange_id: Option<u32>,
+    /// optional opcode ID for lifecycle notifications, with a `LifecycleEvent` as the first argument. Apps with state
+    /// worth keeping should provide this, so they can save it to the PDDB before a suspend or power-off.
+    pub lifecycle_id: Option<u32>,
 }
 
 
@@ -113,19 +235,50 @@ pub(crate) enum Opcode {
     // This normally will be denied, unless the requested Gid corresponds to a special canvas that allows resizing.
     SetCanvasBounds, //(SetCanvasBoundsRequest),
 
+    // narrows drawing on a canvas to a region, until the matching pop
+    PushClip, //(PushClipRequest),
+    PopClip, //(Gid),
+
     // draws an object
     RenderObject, //(GamObject),
     RenderObjectList,
 
+    // blits a bitmap
+    RenderBitmap, //(Bitmap),
+
+    // draws a polyline or polygon
+    RenderPolygon, //(Polygon),
+
     // renders a TextView
     RenderTextView, //(TextView),
 
     // forces a redraw (which also does defacement, etc.)
     Redraw,
 
+    // pushes just the damaged part of a canvas to the screen, bypassing the redraw rate limiter
+    FlushRegion, //(FlushRegionRequest),
+
+    // redirects drawing on a canvas to an offscreen buffer, seeded with the canvas' current contents
+    BeginOffscreen, //(Gid),
+    // copies the offscreen buffer onto the canvas in one step, and resumes drawing directly to the screen
+    SwapOffscreen, //(Gid),
+
+    // hands a glyph set blob to the graphics server; the first word is the slot, and is replaced with the result code
+    LoadGlyphSet, //(slot, blob),
+    UnloadGlyphSet, //(slot),
+    // hands a soft-hyphenation dictionary to the graphics server. Only honored for the status bar's token; the
+    // first word of the buffer is replaced with the result code
+    LoadHyphenation, //(token, len, dictionary),
+    UnloadHyphenation, //(token)
+
     // returns a GID to the "content" Canvas of the token holder
     RequestContentCanvas,
 
+    // splits the token holder's content area into named tabs, returning a GID for each one
+    RegisterTabs,
+    // brings one of the token holder's tabs to the front
+    SwitchTab,
+
     // registers a Ux of a requested type
     // takes in the LayoutType, default PredictorType, a SID for UxEvents, a human-readable identifier token; returns a content canvas GID
     // also takes a bunch of optional ID codes for the various callbacks
@@ -153,6 +306,16 @@ pub(crate) enum Opcode {
 
     /// passed to the keyboard server to notify me of a keyboard event
     KeyboardEvent,
+    /// passed to the keyboard server to notify me of a hot key, pressed while select is held
+    HotkeyEvent,
+    /// Has a hot key sent to a server whatever has focus
+    RegisterHotkey, //(HotkeyRegistration)
+    /// Gives up a hot key
+    UnregisterHotkey, //(HotkeyRegistration)
+    /// passed to the keyboard server to notify me of a keydown or keyup, with its timestamp
+    KeyEventNotification,
+    /// Has the keydowns and keyups sent to an app while it has focus
+    RegisterKeyEvents, //(KeyEventRegistration)
 
     /// used to turn keyboard vibrate on and off
     Vibe,
@@ -163,6 +326,8 @@ pub(crate) enum Opcode {
 
     /// pass-through to get glyph heights to assist with layout planning, without having to create a gfx connection
     QueryGlyphProps,
+    /// pass-through to size up a string before laying it out
+    MeasureText, //(TextMeasurement),
 
     /// request redraw of IME area
     RedrawIme,
@@ -176,9 +341,57 @@ pub(crate) enum Opcode {
     /// Turn on Devboot Flag
     Devboot,
 
+    /// Switch the display between normal and light-on-dark rendering
+    SetInverted, //(memory: status bar token, bool)
+    /// Draws text at about twice its size everywhere but the status bar, or back at its usual size
+    SetLargeText, //(memory: status bar token, bool)
+    /// Turns the screen for every app that hasn't opted out of rotation
+    SetRotation, //(memory: status bar token, Rotation)
+    /// Keeps the screen upright while the app is in the foreground
+    OptOutOfRotation, //(scalar: token)
+
+    /// Shows a short notification over the bottom of the screen, without taking focus
+    ShowToast, //(ToastRequest)
+    /// Internal: the display time of the toast with the given id is up
+    ToastExpired,
+
+    /// Registers or cancels the frame timer of an app
+    RegisterAnimation, //(AnimationRegistration)
+    /// Internal: the animator's timer for the focused app has fired
+    AnimationFrame,
+
+    /// Claims a status bar slot at a given priority
+    ClaimStatusSlot, //(StatusSlotClaim)
+    /// Sets the text of a claimed status bar slot
+    SetStatusSlot, //(StatusSlotUpdate)
+    /// Gives up a status bar slot
+    ReleaseStatusSlot, //(scalar: token)
+    /// Used by the status bar to fetch the slots it should display
+    GetStatusSlots, //(StatusSlotList)
+
     /// Show a test pattern. Can only call this once (to prevent abuse)
     TestPattern,
 
+    /// Internal: suspend/resume callback, passed on to the apps as lifecycle events
+    SuspendResume,
+    /// An app is done saving its state after `AboutToSuspend` (scalar: app token)
+    LifecycleAck,
+    /// Internal: the apps took too long to acknowledge `AboutToSuspend` (scalar: timer handle)
+    LifecycleTimeout,
+
+    /// Captures the screen. The buffer starts with the caller's app token, which must be that of the app with
+    /// focus; the first word is replaced with the result code and the second with the bytes per row, and the
+    /// raster follows the token. The GAM asks the user first, and only answers once they have.
+    /// Refused while a modal or menu has focus, so a password being typed in is never captured.
+    Screenshot,
+    /// Internal: the user pressed the key on the toast asking whether to take a screenshot (scalar: toast id)
+    ScreenshotConfirmed,
+    /// Internal: what the toast covered has been redrawn, so the screenshot can be taken (scalar: toast id)
+    ScreenshotCapture,
+
+    /// Sent by our own watchdog thread; answered straight away, to show the main loop is still running
+    WatchdogPing,
+
     Quit,
 }
 
@@ -201,6 +414,8 @@ pub(crate) enum MenuMgrOp {
     // incoming is one of these ops
     AddItem,
     DeleteItem,
+    /// adds the item to the named submenu, creating the submenu if needed
+    AddSubmenuItem(String::<64>),
     SetIndex(usize),
     Quit,
     // response must be one of these
@@ -224,6 +439,44 @@ pub(crate) struct MenuManagement {
     pub(crate) op: MenuMgrOp,
 }
 
+/// Number of entries a launcher can hold
+pub const MAX_LAUNCHER_ENTRIES: usize = 16;
+/// Number of recently used apps shown in the top row of a launcher
+pub const LAUNCHER_MRU_LEN: usize = 3;
+/// Launcher icons are square, this many pixels on a side
+pub const LAUNCHER_ICON_SIZE: u16 = 32;
+/// A launcher icon is a `BitmapFormat::Mono` image of `LAUNCHER_ICON_SIZE` pixels on a side
+pub type LauncherIcon = [u8; (LAUNCHER_ICON_SIZE as usize / 8) * LAUNCHER_ICON_SIZE as usize];
+
+/// The parts of a launcher that the user can change: the order of the entries, and the most recently
+/// used ones, most recent first. Entries are referred to by name, so a saved layout still
+/// applies when apps are added or removed from a build.
+#[derive(Debug, Copy, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct LauncherLayout {
+    pub order: [Option<String::<64>>; MAX_LAUNCHER_ENTRIES],
+    pub mru: [Option<String::<64>>; LAUNCHER_MRU_LEN],
+}
+impl LauncherLayout {
+    pub fn new() -> Self {
+        LauncherLayout {
+            order: [None; MAX_LAUNCHER_ENTRIES],
+            mru: [None; LAUNCHER_MRU_LEN],
+        }
+    }
+}
+#[derive(Debug, Eq, PartialEq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub(crate) enum LauncherMgrOp {
+    GetLayout,
+    SetLayout,
+    Quit,
+    Ok,
+}
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub(crate) struct LauncherManagement {
+    pub(crate) layout: LauncherLayout,
+    pub(crate) op: LauncherMgrOp,
+}
+
 #[allow(dead_code)] // here until Memory types are implemented
 #[derive(Debug, Copy, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
 pub enum MenuPayload {
@@ -232,6 +485,9 @@ pub enum MenuPayload {
     /// this a nebulous-but-TBD maybe way of bodging in a more complicated record, which would involve
     /// casting this memorized, static payload into a Buffer and passing it on. Let's not worry too much about it for now, it's mostly apirational...
     Memory(([u8; 256], usize)),
+    /// selecting the item opens the submenu of this name, which belongs to the same menu; `action_conn` is
+    /// ignored. Left arrow goes back to the parent menu.
+    Submenu(String::<64>),
 }
 #[derive(Debug, Copy, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
 pub struct MenuItem {
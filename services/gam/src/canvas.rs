@@ -40,6 +40,13 @@ pub struct Canvas {
     pan_offset: Point,
     // track the drawing state of the canvas
     state: Rc<RefCell<CanvasState>>,
+    // union of everything drawn since the last flush, in screen coordinates
+    damage: RefCell<Option<Rectangle>>,
+    // when set, draws to this canvas go to the graphics server's offscreen buffer until the next swap
+    composing: RefCell<bool>,
+    // regions pushed by the canvas' owner to narrow where it draws, innermost last, in screen coordinates.
+    // each is already intersected with the ones under it; None means nothing of the region is visible.
+    clip_stack: Vec<Option<Rectangle>>,
     // The type of canvas. Useful for debugging, don't remove it.
     #[allow(dead_code)]
     canvas_type: crate::api::CanvasType,
@@ -61,12 +68,12 @@ impl Canvas {
         Ok(if pan_offset.is_some() {
             Canvas {
                 clip_rect, trust_level, state: Rc::new(RefCell::new(CanvasState::OffScreenDrawable)), gid: Gid::new(gid), pan_offset: pan_offset.unwrap(),
-                canvas_type,
+                damage: RefCell::new(None), composing: RefCell::new(false), clip_stack: Vec::new(), canvas_type,
             }
         } else {
             Canvas {
                 clip_rect, trust_level, state: Rc::new(RefCell::new(CanvasState::OffScreenDrawable)), gid: Gid::new(gid), pan_offset: Point::new(0, 0),
-                canvas_type,
+                damage: RefCell::new(None), composing: RefCell::new(false), clip_stack: Vec::new(), canvas_type,
             }
         })
     }
@@ -78,8 +85,37 @@ impl Canvas {
     }
     pub fn pan_offset(&self) -> Point { self.pan_offset }
     pub fn clip_rect(&self) -> Rectangle { self.clip_rect }
-    pub fn set_clip(&mut self, cr: Rectangle) { self.clip_rect = cr; *self.state.borrow_mut() = CanvasState::OffScreenDrawable }
+    pub fn set_clip(&mut self, cr: Rectangle) {
+        self.clip_rect = cr; *self.state.borrow_mut() = CanvasState::OffScreenDrawable; self.damage.replace(None); self.composing.replace(false);
+        // pushed regions were worked out against the old layout; the owner redraws from scratch after a move anyway
+        self.clip_stack.clear();
+    }
+    /// Narrows drawing to `region`, given in canvas coordinates like the objects drawn on it. Returns false if
+    /// `MAX_CLIP_DEPTH` regions are already pushed.
+    pub fn push_clip(&mut self, mut region: Rectangle) -> bool {
+        if self.clip_stack.len() >= crate::api::MAX_CLIP_DEPTH {
+            return false;
+        }
+        region.translate(self.clip_rect.tl);
+        region.translate(self.pan_offset);
+        let clip = self.draw_clip().and_then(|outer| region.clip_with(outer));
+        self.clip_stack.push(clip);
+        true
+    }
+    /// Drops the innermost pushed region. Returns false if there wasn't one.
+    pub fn pop_clip(&mut self) -> bool {
+        self.clip_stack.pop().is_some()
+    }
+    /// Where draws on the canvas may land, in screen coordinates: the innermost pushed region, or the whole
+    /// canvas if none is pushed. None if a pushed region lies entirely outside the canvas.
+    pub fn draw_clip(&self) -> Option<Rectangle> {
+        match self.clip_stack.last() {
+            Some(clip) => *clip,
+            None => Some(self.clip_rect),
+        }
+    }
     pub fn gid(&self) -> Gid { self.gid }
+    pub fn canvas_type(&self) -> crate::api::CanvasType { self.canvas_type }
     pub fn trust_level(&self) -> u8 { self.trust_level }
     pub fn set_trust_level(&mut self, level: u8) {self.trust_level = level;}
     pub fn state(&self) -> CanvasState { *self.state.borrow() }
@@ -140,8 +176,25 @@ impl Canvas {
             // other states are already offscreen
         }
     }
+    /// Grows the damaged area to include `region` (screen coordinates), clipped to the canvas.
+    pub fn add_damage(&self, region: Rectangle) {
+        if let Some(clipped) = region.clip_with(self.clip_rect) {
+            let updated = match *self.damage.borrow() {
+                Some(d) => d.union(clipped),
+                None => clipped,
+            };
+            self.damage.replace(Some(updated));
+        }
+    }
+    /// The area that has been drawn since the last flush, if any, in screen coordinates
+    pub fn damage(&self) -> Option<Rectangle> { *self.damage.borrow() }
+    pub fn clear_damage(&self) { self.damage.replace(None); }
+    /// True if the canvas is being composed offscreen, see `Gam::begin_offscreen()`
+    pub fn is_composing(&self) -> bool { *self.composing.borrow() }
+    pub fn set_composing(&self, composing: bool) { self.composing.replace(composing); }
     // call this after the screen has been flushed
     pub fn do_flushed(&self) -> Result<(), xous::Error> {
+        self.damage.replace(None);
         if *self.state.borrow() == CanvasState::DrawableDirty || *self.state.borrow() == CanvasState::DrawableDrawn {
             *self.state.borrow_mut() = CanvasState::DrawableDrawn;
             Ok(())
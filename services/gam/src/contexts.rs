@@ -7,6 +7,7 @@ use gam::MAIN_MENU_NAME;
 
 use log::info;
 use std::collections::HashMap;
+use core::sync::atomic::Ordering;
 use enum_dispatch::enum_dispatch;
 
 // todo:
@@ -28,6 +29,10 @@ pub(crate) trait LayoutApi {
     // for Chats, this resizes the height of the input area; for menus, it resizes the overall height
     fn resize_height(&mut self, gfx: &graphics_server::Gfx, new_height: i16, status_cliprect: &Rectangle, canvases: &mut HashMap<Gid, Canvas>) -> Result<Point, xous::Error>;
     fn get_gids(&self) -> Vec<GidRecord>;
+    // fits the layout to a screen that has changed shape, e.g. because it was turned on its side, or to a new
+    // text size. Like a resize,
+    // this leaves the canvases offscreen, so the visibility has to be set again afterwards.
+    fn relayout(&mut self, gfx: &graphics_server::Gfx, status_cliprect: &Rectangle, canvases: &mut HashMap<Gid, Canvas>) -> Result<(), xous::Error>;
     //fn get_input_canvas(&self) -> Option<Gid> { None }
     //fn get_prediction_canvas(&self) -> Option<Gid> { None }
     //fn get_content_canvas(&self) -> Gid; // layouts always have a content canvas
@@ -73,7 +78,50 @@ pub(crate) struct UxContext {
     pub audioframe_id: Option<u32>,
     /// opcode ID for focus change
     pub focuschange_id: Option<u32>,
+    /// opcode ID for suspend, resume and shutdown notifications
+    pub lifecycle_id: Option<u32>,
+    /// extra content canvases, if the app has registered tabs
+    pub tabs: Option<TabSet>,
+    /// frame timing, if the app has registered an animation
+    pub animation: Option<Animation>,
+    /// set if the app always wants the screen upright, whatever rotation the user has picked
+    pub rotation_opt_out: bool,
+    /// opcode ID for timestamped keydowns and keyups, if the app has asked for them
+    pub keyevent_id: Option<u32>,
 }
+impl UxContext {
+    /// Brings the tab canvases in line with the visibility of the layout: only the active tab is shown, and
+    /// the other tabs follow any changes to the size of the layout's content canvas.
+    pub(crate) fn sync_tabs(&self, onscreen: bool, canvases: &mut HashMap<Gid, Canvas>) {
+        if let Some(tabs) = self.tabs {
+            let content_clip = match canvases.get(&tabs.gids[0]) {
+                Some(c) => c.clip_rect(),
+                None => return,
+            };
+            for (index, gid) in tabs.gids[..tabs.count].iter().enumerate() {
+                if let Some(canvas) = canvases.get_mut(gid) {
+                    let clip = canvas.clip_rect();
+                    if index != 0 && (clip.tl != content_clip.tl || clip.br != content_clip.br) {
+                        canvas.set_clip(content_clip);
+                    }
+                    canvas.set_onscreen(onscreen && index == tabs.active);
+                }
+            }
+        }
+    }
+}
+
+/// The tabs of an app. Tab 0 is the content canvas of the app's layout; the others are canvases of the same
+/// size that the GAM only puts onscreen while their tab is selected.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct TabSet {
+    pub names: [String::<32>; MAX_TABS],
+    pub gids: [Gid; MAX_TABS],
+    pub count: usize,
+    pub active: usize,
+    pub tabchange_id: u32,
+}
+
 pub(crate) const BOOT_CONTEXT_TRUSTLEVEL: u8 = 254;
 
 /*
@@ -94,12 +142,22 @@ pub(crate) struct ContextManager {
     main_menu_app_token: Option<[u32; 4]>, // app_token of the main menu, if it has been registered
     /// for internal generation of deface states
     pub trng: trng::Trng,
+    /// sends animation frames to the focused app
+    animator: Animator,
+    /// the rotation the user picked, for apps that haven't opted out of it
+    rotation: Rotation,
+    /// the rotation the screen is in right now
+    applied_rotation: Rotation,
+    /// the status bar canvas, which is widened or narrowed when the screen changes shape
+    status_gid: Option<Gid>,
 }
 impl ContextManager {
     pub fn new(xns: &xous_names::XousNames) -> Self {
         // hook the keyboard event server and have it forward keys to our local main loop
         let kbd = keyboard::Keyboard::new(&xns).expect("can't connect to KBD");
         kbd.register_listener(crate::api::SERVER_NAME_GAM, Opcode::KeyboardEvent as usize);
+        kbd.register_hotkey_listener(crate::api::SERVER_NAME_GAM, Opcode::HotkeyEvent as usize);
+        kbd.register_key_event_listener(crate::api::SERVER_NAME_GAM, Opcode::KeyEventNotification as usize);
 
         info!("acquiring connection to IMEF...");
         let mut imef = ime_plugin_api::ImeFrontEnd::new(&xns).expect("Couldn't connect to IME front end");
@@ -114,8 +172,15 @@ impl ContextManager {
             kbd,
             main_menu_app_token: None,
             trng: trng::Trng::new(&xns).expect("couldn't connect to trng"),
+            animator: Animator::new(CB_TO_MAIN_CONN.load(Ordering::Relaxed)),
+            rotation: Rotation::Upright,
+            applied_rotation: Rotation::Upright,
+            status_gid: None,
         }
     }
+    pub(crate) fn set_status_canvas(&mut self, gid: Gid) {
+        self.status_gid = Some(gid);
+    }
     pub(crate) fn claim_token(&mut self, name: &str) -> Option<[u32; 4]> {
         self.tm.claim_token(name)
     }
@@ -142,7 +207,10 @@ impl ContextManager {
                     chatlayout.set_visibility_state(false, canvases);
                         let ux_context = UxContext {
                         layout: UxLayout::ChatLayout(chatlayout),
-                        predictor: registration.predictor,
+                        // a chat has to have a predictor for the IME to hook up its input line at all
+                        predictor: registration.predictor.or_else(||
+                            Some(String::<64>::from_str(ime_plugin_dict::SERVER_NAME_IME_PLUGIN_DICT))
+                        ),
                         app_token: token,
                         gam_token: [trng.get_u32().unwrap(), trng.get_u32().unwrap(), trng.get_u32().unwrap(), trng.get_u32().unwrap(), ],
                         listener: xous::connect(xous::SID::from_array(registration.listener)).unwrap(),
@@ -150,8 +218,13 @@ impl ContextManager {
                         gotinput_id: registration.gotinput_id,
                         audioframe_id: registration.audioframe_id,
                         focuschange_id: registration.focuschange_id,
+                        lifecycle_id: registration.lifecycle_id,
                         rawkeys_id: None,
                         vibe: false,
+                        tabs: None,
+                        animation: None,
+                        rotation_opt_out: false,
+                        keyevent_id: None,
                     };
                     self.contexts.insert(token, ux_context);
                 },
@@ -171,8 +244,13 @@ impl ContextManager {
                         gotinput_id: None,
                         audioframe_id: None,
                         focuschange_id: registration.focuschange_id,
+                        lifecycle_id: registration.lifecycle_id,
                         rawkeys_id: registration.rawkeys_id,
                         vibe: false,
+                        tabs: None,
+                        animation: None,
+                        rotation_opt_out: false,
+                        keyevent_id: None,
                     };
 
                     if registration.app_name.as_str().unwrap() == MAIN_MENU_NAME {
@@ -198,8 +276,13 @@ impl ContextManager {
                         gotinput_id: None,
                         audioframe_id: None,
                         focuschange_id: registration.focuschange_id,
+                        lifecycle_id: registration.lifecycle_id,
                         rawkeys_id: registration.rawkeys_id,
                         vibe: false,
+                        tabs: None,
+                        animation: None,
+                        rotation_opt_out: false,
+                        keyevent_id: None,
                     };
                     self.contexts.insert(token, ux_context);
                     // this check gives permissions to password boxes to render inverted text
@@ -225,8 +308,13 @@ impl ContextManager {
                         gotinput_id: None,
                         audioframe_id: None,
                         focuschange_id: registration.focuschange_id,
+                        lifecycle_id: registration.lifecycle_id,
                         rawkeys_id: registration.rawkeys_id,
                         vibe: false,
+                        tabs: None,
+                        animation: None,
+                        rotation_opt_out: false,
+                        keyevent_id: None,
                     };
                     self.contexts.insert(token, ux_context);
                 }
@@ -268,6 +356,131 @@ impl ContextManager {
             None
         }
     }
+    /// Creates a canvas for every tab after the first, which reuses the app's content canvas. An app can only
+    /// register its tabs once.
+    pub(crate) fn register_tabs(&mut self,
+        trng: &trng::Trng,
+        canvases: &mut HashMap<Gid, Canvas>,
+        registration: &TabRegistration,
+    ) -> Option<[Gid; MAX_TABS]> {
+        if registration.count == 0 || registration.count > MAX_TABS {
+            return None;
+        }
+        let content = self.get_content_canvas(registration.token)?;
+        let onscreen = self.focused_context == Some(registration.token);
+        let context = self.contexts.get_mut(&registration.token)?;
+        if context.tabs.is_some() {
+            log::warn!("attempt to register tabs twice, ignoring");
+            return None;
+        }
+        let (clip, trust_level, canvas_type) = {
+            let c = canvases.get(&content)?;
+            (c.clip_rect(), c.trust_level(), c.canvas_type())
+        };
+        let mut gids = [content; MAX_TABS];
+        for gid in gids[1..registration.count].iter_mut() {
+            let canvas = Canvas::new(clip, trust_level, trng, None, canvas_type).expect("couldn't create tab canvas");
+            *gid = canvas.gid();
+            canvases.insert(canvas.gid(), canvas);
+        }
+        context.tabs = Some(TabSet {
+            names: registration.names,
+            gids,
+            count: registration.count,
+            active: 0,
+            tabchange_id: registration.tabchange_id,
+        });
+        context.sync_tabs(onscreen, canvases);
+        Some(gids)
+    }
+    /// Makes `index` the visible tab of the app holding `token`. If the app is in the foreground, the content
+    /// area is blanked and the app is told to draw the new tab.
+    pub(crate) fn switch_tab(&mut self,
+        gfx: &graphics_server::Gfx,
+        canvases: &mut HashMap<Gid, Canvas>,
+        token: [u32; 4],
+        index: usize,
+    ) -> Result<(), xous::Error> {
+        let onscreen = self.focused_context == Some(token);
+        let context = self.contexts.get_mut(&token).ok_or(xous::Error::ProcessNotFound)?;
+        let tabs = context.tabs.as_mut().ok_or(xous::Error::UseBeforeInit)?;
+        if index >= tabs.count {
+            return Err(xous::Error::BadAddress);
+        }
+        if index == tabs.active {
+            return Ok(());
+        }
+        tabs.active = index;
+        let (gid, tabchange_id) = (tabs.gids[index], tabs.tabchange_id);
+        log::debug!("switching to tab {}", tabs.names[index]);
+        context.sync_tabs(onscreen, canvases);
+        recompute_canvases(canvases);
+        if onscreen {
+            if let Some(canvas) = canvases.get(&gid) {
+                let mut rect = canvas.clip_rect();
+                rect.style = DrawStyle {fill_color: Some(PixelColor::Light), stroke_color: None, stroke_width: 0,};
+                gfx.draw_rectangle(rect).expect("can't clear canvas");
+            }
+        }
+        xous::send_message(context.listener,
+            xous::Message::new_scalar(tabchange_id as usize, index, 0, 0, 0)
+        ).map(|_| ())
+    }
+    /// Sets up, replaces or (with an interval of 0) cancels the animation of the app holding the token.
+    /// Frames only flow while the app has focus.
+    pub(crate) fn register_animation(&mut self, registration: &AnimationRegistration) -> Result<(), xous::Error> {
+        let focused = self.focused_context == Some(registration.token);
+        let context = self.contexts.get_mut(&registration.token).ok_or(xous::Error::ProcessNotFound)?;
+        context.animation = if registration.interval_ms == 0 {
+            None
+        } else {
+            Some(Animation {
+                frame_id: registration.frame_id,
+                interval_ms: registration.interval_ms.max(ANIMATION_MIN_INTERVAL_MS),
+                frame: 0,
+            })
+        };
+        if focused {
+            self.animator.follow(context.animation);
+        }
+        Ok(())
+    }
+    /// Passes a frame from the animator on to the focused app, with the frame count as the first argument
+    pub(crate) fn animation_frame(&mut self, generation: usize) {
+        if !self.animator.is_current(generation) {
+            return;
+        }
+        let focused = match self.focused_context {
+            Some(token) => token,
+            None => return,
+        };
+        if let Some(context) = self.contexts.get_mut(&focused) {
+            if let Some(animation) = context.animation.as_mut() {
+                animation.frame = animation.frame.wrapping_add(1);
+                // a frame the app can't take right now is simply dropped; the next one carries the frame count
+                xous::send_message(context.listener,
+                    xous::Message::new_scalar(animation.frame_id as usize, animation.frame, 0, 0, 0)
+                ).map(|_| ()).unwrap_or_else(|e| log::debug!("dropped animation frame: {:?}", e));
+            }
+        }
+    }
+    /// Starts or (with `None`) stops the key events of the app holding the token
+    pub(crate) fn register_key_events(&mut self, registration: &KeyEventRegistration) -> Result<(), xous::Error> {
+        let context = self.contexts.get_mut(&registration.token).ok_or(xous::Error::ProcessNotFound)?;
+        context.keyevent_id = registration.keyevent_id;
+        Ok(())
+    }
+    /// Passes a key event on to the focused context, if it asked for them. Only the focused context gets them,
+    /// so an app can't watch what's typed into a password modal popped up over it.
+    pub(crate) fn key_event_notification(&self, args: [usize; 3]) {
+        if let Some(context) = self.focused_context() {
+            if let Some(keyevent_id) = context.keyevent_id {
+                xous::send_message(context.listener,
+                    xous::Message::new_scalar(keyevent_id as usize, args[0], args[1], args[2], 0)
+                ).map(|_| ()).unwrap_or_else(|e| log::warn!("couldn't send key event to an app: {:?}", e));
+            }
+        }
+    }
     pub(crate) fn set_canvas_height(&mut self,
         gfx: &graphics_server::Gfx,
         gam_token: [u32; 4],
@@ -278,6 +491,7 @@ impl ContextManager {
         for context in self.contexts.values_mut() {
             if context.gam_token == gam_token {
                 let result = context.layout.resize_height(gfx, new_height, status_cliprect, canvases).expect("couldn't adjust height of active Ux context");
+                context.sync_tabs(self.focused_context == Some(context.app_token), canvases);
                 return Some(result)
             }
         }
@@ -293,6 +507,7 @@ impl ContextManager {
 
         if let Some(context) = self.contexts.get_mut(&app_token) {
             let result = context.layout.resize_height(gfx, new_height, status_cliprect, canvases).expect("couldn't adjust height of active Ux context");
+            context.sync_tabs(self.focused_context == Some(app_token), canvases);
             Some(result)
         } else {
             None
@@ -339,16 +554,19 @@ impl ContextManager {
                         (context.layout.behavior()                 == LayoutBehavior::App) &&
                         (leaving_focused_context.layout.behavior() == LayoutBehavior::App) {
                             context.layout.set_visibility_state(true, canvases);
+                            context.sync_tabs(true, canvases);
                             leaving_visibility = false;
                         } else if // alert covering an app
                         (context.layout.behavior()                 == LayoutBehavior::Alert) &&
                         (leaving_focused_context.layout.behavior() == LayoutBehavior::App) {
                             context.layout.set_visibility_state(true, canvases);
+                            context.sync_tabs(true, canvases);
                             leaving_visibility = true;
                         } else if // app covering an alert
                         (context.layout.behavior()                 == LayoutBehavior::App) &&
                         (leaving_focused_context.layout.behavior() == LayoutBehavior::Alert) {
                             context.layout.set_visibility_state(true, canvases);
+                            context.sync_tabs(true, canvases);
                             leaving_visibility = false;
                         }
                     }
@@ -356,6 +574,7 @@ impl ContextManager {
                     // there was no current focus, just make the activation visible
                     log::debug!("setting first-time visibility to context {:?}", token);
                     context.layout.set_visibility_state(true, canvases);
+                    context.sync_tabs(true, canvases);
                 }
             }
         }
@@ -370,6 +589,7 @@ impl ContextManager {
                 }
                 if let Some(old_context) = self.get_context_by_token_mut(self.focused_context.unwrap()) {
                     old_context.layout.set_visibility_state(leaving_visibility, canvases);
+                    old_context.sync_tabs(leaving_visibility, canvases);
                 }
             }
         }
@@ -410,6 +630,10 @@ impl ContextManager {
                 recompute_canvases(canvases);
             }
         }
+        // an app may want the screen turned differently from whatever it's taking over from
+        if let Some(LayoutBehavior::App) = self.get_context_by_token(token).map(|c| c.layout.behavior()) {
+            self.apply_rotation(gfx, canvases, token);
+        }
         log::trace!("foregrounding new context");
         {
             // now re-check-out the new context and finalize things
@@ -431,8 +655,10 @@ impl ContextManager {
                 self.notify_focus_change_to(gam::FocusState::Foreground, context).unwrap();
                 log::trace!("raised focus to: {:?}", context);
                 let last_token = context.app_token;
+                let animation = context.animation;
                 self.last_context = self.focused_context;
                 self.focused_context = Some(last_token);
+                self.animator.follow(animation);
             }
             // run the defacement before we redraw all the canvases
             if deface(gfx, &self.trng, canvases) {
@@ -443,6 +669,105 @@ impl ContextManager {
         }
         Ok(())
     }
+    /// Records the rotation picked by the user. It's applied right away if an app is in the foreground, and
+    /// otherwise the next time one is brought forward.
+    pub(crate) fn set_rotation(&mut self,
+        gfx: &graphics_server::Gfx,
+        canvases: &mut HashMap<Gid, Canvas>,
+        rotation: Rotation,
+    ) {
+        self.rotation = rotation;
+        self.refresh_rotation(gfx, canvases);
+    }
+    /// Keeps the screen upright whenever the app holding `token` is in the foreground
+    pub(crate) fn opt_out_of_rotation(&mut self,
+        gfx: &graphics_server::Gfx,
+        canvases: &mut HashMap<Gid, Canvas>,
+        token: [u32; 4],
+    ) -> Result<(), xous::Error> {
+        let context = self.contexts.get_mut(&token).ok_or(xous::Error::ProcessNotFound)?;
+        context.rotation_opt_out = true;
+        self.refresh_rotation(gfx, canvases);
+        Ok(())
+    }
+    /// Re-applies the rotation if an app is in the foreground, and has it redraw if the screen was turned
+    fn refresh_rotation(&mut self, gfx: &graphics_server::Gfx, canvases: &mut HashMap<Gid, Canvas>) {
+        let token = match self.focused_context() {
+            Some(context) if context.layout.behavior() == LayoutBehavior::App => context.app_token,
+            _ => return,
+        };
+        if self.apply_rotation(gfx, canvases, token) {
+            self.redraw().expect("couldn't redraw after rotating the screen");
+        }
+    }
+    /// Turns the screen the way the app holding `token` wants it: upright if it opted out, or else the way
+    /// the user picked. Returns true if the screen was turned, in which case it has been cleared and
+    /// everything on it has to be drawn again.
+    fn apply_rotation(&mut self,
+        gfx: &graphics_server::Gfx,
+        canvases: &mut HashMap<Gid, Canvas>,
+        token: [u32; 4],
+    ) -> bool {
+        let wanted = match self.contexts.get(&token) {
+            Some(context) if context.rotation_opt_out => Rotation::Upright,
+            Some(_) => self.rotation,
+            None => return false,
+        };
+        if wanted == self.applied_rotation {
+            return false;
+        }
+        let reshaped = wanted.is_landscape() != self.applied_rotation.is_landscape();
+        log::info!("rotating screen to {:?}", wanted);
+        gfx.set_rotation(wanted).expect("couldn't rotate the screen");
+        self.applied_rotation = wanted;
+        if reshaped {
+            self.relayout(gfx, canvases);
+        }
+        // the frame buffer isn't turned with the coordinates, so what's on it is now the wrong way around
+        clear_screen(gfx);
+        true
+    }
+    /// Turns large text on or off, and fits every layout to the new text heights
+    pub(crate) fn set_large_text(&mut self,
+        gfx: &graphics_server::Gfx,
+        canvases: &mut HashMap<Gid, Canvas>,
+        large: bool,
+    ) {
+        if crate::LARGE_TEXT.swap(large, Ordering::Relaxed) == large {
+            return;
+        }
+        self.relayout(gfx, canvases);
+        clear_screen(gfx);
+        if self.focused_context.is_some() {
+            self.redraw().expect("couldn't redraw after changing the text size");
+        }
+    }
+    /// Fits the status bar and every layout to a screen that has changed shape or text size
+    fn relayout(&mut self, gfx: &graphics_server::Gfx, canvases: &mut HashMap<Gid, Canvas>) {
+        let screensize = gfx.screen_size().expect("couldn't get screen size");
+        let status_cliprect = match self.status_gid.and_then(|gid| canvases.get_mut(&gid)) {
+            Some(status) => {
+                let mut cr = status.clip_rect();
+                cr.br.x = screensize.x;
+                status.set_clip(cr);
+                status.set_onscreen(true);
+                status.set_drawable(true);
+                cr
+            }
+            None => {
+                log::error!("no status canvas to lay out the screen around");
+                return;
+            }
+        };
+        for context in self.contexts.values_mut() {
+            let onscreen = context.layout.get_gids().iter()
+                .any(|gr| canvases.get(&gr.gid).map(|c| c.is_onscreen()).unwrap_or(false));
+            context.layout.relayout(gfx, &status_cliprect, canvases).expect("couldn't fit layout to the screen");
+            context.layout.set_visibility_state(onscreen, canvases);
+            context.sync_tabs(onscreen, canvases);
+        }
+        recompute_canvases(canvases);
+    }
     pub(crate) fn revert_focus(&mut self,
         gfx: &graphics_server::Gfx,
         canvases: &mut HashMap<Gid, Canvas>,
@@ -462,6 +787,23 @@ impl ContextManager {
         }
         Ok(())
     }
+    /// Tells every app that asked for lifecycle events about `event`. An app that can't take the message
+    /// right now misses it, rather than holding up the rest. Returns the tokens of the apps that got it.
+    pub(crate) fn notify_lifecycle(&self, event: gam::LifecycleEvent) -> Vec<[u32; 4]> {
+        let mut notified = Vec::new();
+        for context in self.contexts.values() {
+            if let Some(lifecycle_id) = context.lifecycle_id {
+                log::debug!("lifecycle {:?} msg to {}, id {}", event, context.listener, lifecycle_id);
+                match xous::try_send_message(context.listener,
+                    xous::Message::new_scalar(lifecycle_id as usize, event as usize, 0, 0, 0)
+                ) {
+                    Ok(_) => notified.push(context.app_token),
+                    Err(e) => log::warn!("couldn't send {:?} to an app: {:?}", event, e),
+                }
+            }
+        }
+        notified
+    }
     pub(crate) fn redraw(&self) -> Result<(), xous::Error> { // redraws the currently focused context
         if let Some(token) = self.focused_app() {
             if let Some(context) = self.contexts.get(&token) {
@@ -490,6 +832,10 @@ impl ContextManager {
     pub(crate) fn focused_app(&self) -> Option<[u32; 4]> {
         self.focused_context
     }
+    /// Whether a modal or menu has focus, which nothing else may draw over or take keys from
+    pub(crate) fn alert_has_focus(&self) -> bool {
+        self.focused_context().map_or(false, |c| c.layout.behavior() == LayoutBehavior::Alert)
+    }
     pub(crate) fn forward_input(&self, input: String::<4000>) -> Result<(), xous::Error> {
         if let Some(token) = self.focused_app() {
             if let Some(context) = self.contexts.get(&token) {
@@ -507,6 +853,21 @@ impl ContextManager {
         gfx: &graphics_server::Gfx,
         canvases: &mut HashMap<Gid, Canvas>,
     ) {
+        // pressing left and right together cycles through the tabs of the focused app. The F keys are taken by
+        // the IME, and the menu key acts as soon as it's pressed, so neither can start a chord.
+        if keys.contains(&'←') && keys.contains(&'→') {
+            if let Some(focus) = self.focused_context {
+                if let Some(tabs) = self.get_context_by_token(focus).and_then(|c| c.tabs) {
+                    let index = (tabs.active + 1) % tabs.count;
+                    if let Err(e) = self.switch_tab(gfx, canvases, focus, index) {
+                        log::warn!("couldn't switch tabs: {:?}", e);
+                    }
+                    // the chord is consumed by the GAM
+                    return;
+                }
+            }
+        }
+
         // only pop up the menu if the primary key hit is the menu key (search just the first entry of keys); reject multi-key hits
         // only pop up the menu if it isn't already popped up
         if keys[0] == '∴' {
@@ -593,3 +954,9 @@ impl ContextManager {
         Err(xous::Error::ProcessNotFound)
     }
 }
+
+fn clear_screen(gfx: &graphics_server::Gfx) {
+    let screensize = gfx.screen_size().expect("couldn't get screen size");
+    gfx.draw_rectangle(Rectangle::new_with_style(Point::new(0, 0), screensize,
+        DrawStyle::new(PixelColor::Light, PixelColor::Light, 0))).expect("couldn't clear screen");
+}
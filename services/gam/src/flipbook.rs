@@ -0,0 +1,135 @@
+//! A flipbook is a short sequence of 1-bpp frames played back on a canvas at a fixed rate, for things like
+//! onboarding tutorials and progress indicators. The frames are timed by the GAM's animation facility, so
+//! playback pauses by itself while the app is in the background.
+//!
+//! A flipbook blob, typically read out of a key in the PDDB, starts with four little-endian `u16`s: the frame
+//! width, the frame height, the number of frames and the time between frames in milliseconds. The frames
+//! follow back to back, each laid out as a `BitmapFormat::Mono` bitmap.
+
+use crate::Gam;
+use graphics_server::api::{Bitmap, BitmapFormat, Gid, Point, Rectangle, BITMAP_DATA_LEN};
+
+/// Size of the header at the start of a flipbook blob
+pub const FLIPBOOK_HEADER_LEN: usize = 8;
+/// Most frames a flipbook can hold; at 30 frames per second that's a bit over two seconds
+pub const FLIPBOOK_MAX_FRAMES: usize = 64;
+
+pub struct Flipbook {
+    width: u16,
+    height: u16,
+    interval_ms: u32,
+    frames: Vec<u8>,
+    count: usize,
+    /// start over after the last frame, instead of holding it
+    pub looping: bool,
+}
+impl Flipbook {
+    /// Checks and takes in a flipbook blob. Each frame has to fit in a single `Bitmap`.
+    pub fn new(blob: &[u8], looping: bool) -> Result<Flipbook, xous::Error> {
+        if blob.len() < FLIPBOOK_HEADER_LEN {
+            return Err(xous::Error::InvalidString);
+        }
+        let field = |i: usize| u16::from_le_bytes([blob[i * 2], blob[i * 2 + 1]]);
+        let (width, height, count, interval_ms) = (field(0), field(1), field(2) as usize, field(3) as u32);
+        let frame_len = Bitmap::data_len(width, height, BitmapFormat::Mono);
+        if width == 0 || height == 0 || count == 0 || interval_ms == 0 {
+            return Err(xous::Error::InvalidString);
+        }
+        if frame_len > BITMAP_DATA_LEN || count > FLIPBOOK_MAX_FRAMES {
+            return Err(xous::Error::OutOfMemory);
+        }
+        if blob.len() != FLIPBOOK_HEADER_LEN + frame_len * count {
+            return Err(xous::Error::InvalidString);
+        }
+        Ok(Flipbook {
+            width,
+            height,
+            interval_ms,
+            frames: blob[FLIPBOOK_HEADER_LEN..].to_vec(),
+            count,
+            looping,
+        })
+    }
+    pub fn len(&self) -> usize { self.count }
+    pub fn size(&self) -> Point { Point::new(self.width as i16, self.height as i16) }
+    pub fn interval_ms(&self) -> u32 { self.interval_ms }
+    /// Starts the GAM sending `frame_id` to the app's listener at the flipbook's rate. The frame count that comes
+    /// with each message is what `draw()` takes. Calling this again restarts from the first frame.
+    pub fn play(&self, gam: &Gam, token: [u32; 4], frame_id: u32) -> Result<(), xous::Error> {
+        gam.register_animation(token, frame_id, self.interval_ms)
+    }
+    /// Stops the frames; whatever was drawn last stays on the canvas.
+    pub fn stop(&self, gam: &Gam, token: [u32; 4]) -> Result<(), xous::Error> {
+        gam.register_animation(token, 0, 0)
+    }
+    /// The bitmap for `frame`, as counted by the GAM from 1, with its top left at `tl` on `canvas`
+    pub fn frame(&self, canvas: Gid, tl: Point, frame: usize) -> Bitmap {
+        let index = frame.saturating_sub(1);
+        let index = if self.looping { index % self.count } else { index.min(self.count - 1) };
+        let len = Bitmap::data_len(self.width, self.height, BitmapFormat::Mono);
+        // the blob was checked when it was taken in, so every frame fits
+        Bitmap::new(canvas, tl, self.width, self.height, BitmapFormat::Mono, &self.frames[index * len..(index + 1) * len])
+            .expect("flipbook frame doesn't fit in a bitmap")
+    }
+    /// Draws `frame` and pushes it to the screen. Returns false once a flipbook that doesn't loop has shown its
+    /// last frame, so the caller knows to `stop()`.
+    pub fn draw(&self, gam: &Gam, canvas: Gid, tl: Point, frame: usize) -> Result<bool, xous::Error> {
+        gam.draw_bitmap(&self.frame(canvas, tl, frame))?;
+        let br = Point::new(tl.x + self.width as i16 - 1, tl.y + self.height as i16 - 1);
+        gam.flush_region(canvas, Some(Rectangle::new(tl, br)))?;
+        Ok(self.looping || frame < self.count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics_server::api::PixelColor;
+    /// An 8x1 flipbook with `count` frames, where frame `n` has only pixel `n` set
+    fn blob(count: u16) -> Vec<u8> {
+        let mut blob = Vec::new();
+        for field in [8u16, 1, count, 33] {
+            blob.extend_from_slice(&field.to_le_bytes());
+        }
+        for n in 0..count {
+            blob.push(0x80 >> n);
+        }
+        blob
+    }
+    /// Which frame of `blob()` a bitmap shows
+    fn shown(bm: &Bitmap) -> Option<u16> {
+        (0..8).find(|&x| bm.pixel(x, 0) == Some(PixelColor::Dark))
+    }
+    #[test]
+    fn frame_advance() {
+        let book = Flipbook::new(&blob(3), false).unwrap();
+        assert_eq!(book.len(), 3);
+        let at = |frame| shown(&book.frame(Gid::new([0; 4]), Point::new(0, 0), frame));
+        // the GAM counts frames from 1
+        assert_eq!(at(1), Some(0));
+        assert_eq!(at(2), Some(1));
+        assert_eq!(at(3), Some(2));
+        // a book that doesn't loop holds its last frame
+        assert_eq!(at(4), Some(2));
+        assert_eq!(at(100), Some(2));
+    }
+    #[test]
+    fn frame_wrap_around() {
+        let book = Flipbook::new(&blob(3), true).unwrap();
+        let at = |frame| shown(&book.frame(Gid::new([0; 4]), Point::new(0, 0), frame));
+        assert_eq!(at(3), Some(2));
+        assert_eq!(at(4), Some(0));
+        assert_eq!(at(5), Some(1));
+        assert_eq!(at(3 * 1000 + 3), Some(2));
+    }
+    #[test]
+    fn empty_book() {
+        assert_eq!(Flipbook::new(&blob(0), false).err(), Some(xous::Error::InvalidString));
+        assert_eq!(Flipbook::new(&blob(0)[..FLIPBOOK_HEADER_LEN - 1], true).err(), Some(xous::Error::InvalidString));
+        assert_eq!(Flipbook::new(&[], true).err(), Some(xous::Error::InvalidString));
+        // a header that promises more frames than follow it
+        let mut short = blob(2);
+        short.pop();
+        assert_eq!(Flipbook::new(&short, false).err(), Some(xous::Error::InvalidString));
+    }
+}
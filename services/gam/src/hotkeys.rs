@@ -0,0 +1,90 @@
+use crate::api::*;
+
+struct Hotkey {
+    key: char,
+    /// app token of the context that registered the key, which alone can change or give it up
+    owner: [u32; 4],
+    sid: [u32; 4],
+    cid: xous::CID,
+    opcode: u32,
+}
+
+/// Routes hot keys, pressed while select is held, to whoever registered them, whatever has focus. The
+/// keyboard server picks the chords out; a key nobody has registered is handed on as an ordinary key press.
+/// A key belongs to the app that registered it until that app gives it up, or its server goes away.
+pub(crate) struct Hotkeys {
+    registered: Vec<Hotkey>,
+}
+impl Hotkeys {
+    pub(crate) fn new() -> Hotkeys {
+        Hotkeys {
+            registered: Vec::new(),
+        }
+    }
+    /// Returns whether `key` is now the registrant's; a key can only have one. The caller has already checked
+    /// the registration's token.
+    pub(crate) fn register(&mut self, registration: &HotkeyRegistration) -> bool {
+        let key = match core::char::from_u32(registration.key) {
+            Some(key) => key,
+            None => return false,
+        };
+        if let Some(index) = self.registered.iter().position(|h| h.key == key) {
+            if self.registered[index].owner != registration.token {
+                log::warn!("hot key {:?} is already registered", key);
+                return false;
+            }
+            // the owner is moving it to another server or opcode
+            self.forget(index);
+        }
+        let cid = match self.registered.iter().find(|h| h.sid == registration.sid) {
+            Some(h) => h.cid,
+            None => match xous::connect(xous::SID::from_array(registration.sid)) {
+                Ok(cid) => cid,
+                Err(e) => {
+                    log::warn!("couldn't connect to hot key registrant: {:?}", e);
+                    return false;
+                }
+            },
+        };
+        self.registered.push(Hotkey {
+            key,
+            owner: registration.token,
+            sid: registration.sid,
+            cid,
+            opcode: registration.opcode,
+        });
+        true
+    }
+    pub(crate) fn unregister(&mut self, key: char, token: [u32; 4]) -> Result<(), xous::Error> {
+        let index = self.registered.iter().position(|h| h.key == key && h.owner == token).ok_or(xous::Error::AccessDenied)?;
+        self.forget(index);
+        Ok(())
+    }
+    fn forget(&mut self, index: usize) {
+        let hotkey = self.registered.remove(index);
+        if !self.registered.iter().any(|h| h.cid == hotkey.cid) {
+            unsafe { xous::disconnect(hotkey.cid).ok() };
+        }
+    }
+    /// Sends `key` to its registrant, returning `false` if it doesn't have one
+    pub(crate) fn dispatch(&mut self, key: char) -> bool {
+        let index = match self.registered.iter().position(|h| h.key == key) {
+            Some(index) => index,
+            None => return false,
+        };
+        let hotkey = &self.registered[index];
+        match xous::try_send_message(hotkey.cid, xous::Message::new_scalar(hotkey.opcode as usize, key as u32 as usize, 0, 0, 0)) {
+            Err(xous::Error::ServerNotFound) => {
+                log::info!("registrant of hot key {:?} has gone away", key);
+                self.forget(index);
+                false
+            }
+            Err(e) => {
+                // still theirs, so the key isn't passed on as an ordinary press while they're busy
+                log::warn!("couldn't send hot key {:?}: {:?}", key, e);
+                true
+            }
+            Ok(_) => true,
+        }
+    }
+}
@@ -0,0 +1,536 @@
+//! A launcher is a menu for starting apps. Each entry can carry an icon, the user can rearrange the
+//! entries, and the apps launched most recently are offered in a row of their own at the top.
+//! Like a `Menu`, it runs in the process that creates it and draws on a menu canvas owned by the GAM.
+
+use crate::api::*;
+use crate::Gam;
+use crate::MenuOpcode;
+
+use graphics_server::api::*;
+
+use xous_ipc::{String, Buffer};
+use num_traits::*;
+
+#[cfg(feature = "tts")]
+use tts_frontend::*;
+
+/// room kept at the right of each row for the arrows that show the list scrolls
+const SCROLL_MARK_WIDTH: i16 = 16;
+
+#[derive(Debug, Copy, Clone)]
+pub struct LauncherEntry {
+    pub name: String::<64>,
+    pub icon: Option<LauncherIcon>,
+    /// selecting the entry sends `action_opcode` as a scalar with `action_payload` as its arguments
+    pub action_conn: xous::CID,
+    pub action_opcode: u32,
+    pub action_payload: [u32; 4],
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Selection {
+    /// an entry in the most recently used row, by position in the row
+    Recent(usize),
+    /// an entry in the list, by position in the user's order
+    Entry(usize),
+    Close,
+}
+
+pub struct Launcher {
+    pub sid: xous::SID,
+    pub gam: Gam,
+    pub entries: Vec<LauncherEntry>,
+    /// indices into `entries`, in the order the user wants them
+    order: Vec<usize>,
+    /// indices into `entries`, most recently launched first
+    mru: Vec<usize>,
+    selection: Selection,
+    /// position in `order` of the first entry on screen, when there are more than fit on the canvas
+    scroll: usize,
+    /// set while the selected entry is picked up to be moved
+    moving: bool,
+    close_label: String::<64>,
+    pub canvas: Gid,
+    pub authtoken: [u32; 4],
+    pub margin: i16,
+    pub line_height: i16,
+    pub name: std::string::String,
+    /// told with a scalar message whenever the layout changes, so it can be saved
+    changed: Option<(xous::CID, u32)>,
+    #[cfg(feature = "tts")]
+    pub tts: TtsFrontend,
+}
+
+impl Launcher {
+    pub fn new(name: &str, close_label: &str) -> Launcher {
+        let xns = xous_names::XousNames::new().unwrap();
+        let sid = xous::create_server().expect("can't create private launcher message server");
+        let gam = Gam::new(&xns).expect("can't connect to GAM");
+        let authtoken = gam.register_ux(
+            UxRegistration {
+                app_name: String::<128>::from_str(name),
+                ux_type: UxType::Menu,
+                predictor: None,
+                listener: sid.to_array(),
+                redraw_id: MenuOpcode::Redraw.to_u32().unwrap(),
+                gotinput_id: None,
+                audioframe_id: None,
+                focuschange_id: None,
+                lifecycle_id: None,
+                rawkeys_id: Some(MenuOpcode::Rawkeys.to_u32().unwrap()),
+            }
+        ).expect("couldn't register my Ux element with GAM");
+        assert!(authtoken.is_some(), "Couldn't register launcher. Did you remember to add the app_name to the tokens.rs expected boot contexts list?");
+        let canvas = gam.request_content_canvas(authtoken.unwrap()).expect("couldn't get my content canvas from GAM");
+        let line_height = gam.glyph_height_hint(GlyphStyle::Cjk).expect("couldn't get glyph height hint") as i16 + 2;
+        #[cfg(feature="tts")]
+        let tts = TtsFrontend::new(&xns).unwrap();
+        Launcher {
+            sid,
+            gam,
+            entries: Vec::new(),
+            order: Vec::new(),
+            mru: Vec::new(),
+            selection: Selection::Entry(0),
+            scroll: 0,
+            moving: false,
+            close_label: String::from_str(close_label),
+            canvas,
+            authtoken: authtoken.unwrap(),
+            margin: 8,
+            line_height,
+            name: std::string::String::from(name),
+            changed: None,
+            #[cfg(feature="tts")]
+            tts,
+        }
+    }
+    /// Adds an entry at the end of the list. Returns false if the launcher is full.
+    pub fn add_entry(&mut self, entry: LauncherEntry) -> bool {
+        if entry.name.as_str().unwrap() == "🔇" { // suppress entries that are not applicable for a given locale
+            return true;
+        }
+        if self.entries.len() >= MAX_LAUNCHER_ENTRIES {
+            log::warn!("launcher {} is full, dropping {}", self.name, entry.name);
+            return false;
+        }
+        self.order.push(self.entries.len());
+        self.entries.push(entry);
+        self.fit_to_entries();
+        true
+    }
+    /// Sends a scalar with `opcode` to `conn` whenever the user reorders the entries or launches an app
+    pub fn set_change_listener(&mut self, conn: xous::CID, opcode: u32) {
+        self.changed = Some((conn, opcode));
+    }
+    pub fn layout(&self) -> LauncherLayout {
+        let mut layout = LauncherLayout::new();
+        for (dst, &index) in layout.order.iter_mut().zip(self.order.iter()) {
+            *dst = Some(self.entries[index].name);
+        }
+        for (dst, &index) in layout.mru.iter_mut().zip(self.mru.iter()) {
+            *dst = Some(self.entries[index].name);
+        }
+        layout
+    }
+    /// Applies a saved layout. Entries the layout doesn't know about keep their place after the ones it
+    /// does, and names that no longer match an entry are skipped.
+    pub fn set_layout(&mut self, layout: &LauncherLayout) {
+        let mut order = Vec::new();
+        for name in layout.order.iter().flatten() {
+            if let Some(index) = self.find(name) {
+                if !order.contains(&index) {
+                    order.push(index);
+                }
+            }
+        }
+        for &index in self.order.iter() {
+            if !order.contains(&index) {
+                order.push(index);
+            }
+        }
+        self.order = order;
+        self.mru.clear();
+        for name in layout.mru.iter().flatten() {
+            if let Some(index) = self.find(name) {
+                if !self.mru.contains(&index) {
+                    self.mru.push(index);
+                }
+            }
+        }
+        self.selection = Selection::Entry(0);
+        self.scroll = 0;
+        self.moving = false;
+        self.fit_to_entries();
+    }
+    fn find(&self, name: &String::<64>) -> Option<usize> {
+        self.entries.iter().position(|e| e.name.as_str() == name.as_str())
+    }
+
+    fn row_height(&self) -> i16 {
+        self.line_height.max(LAUNCHER_ICON_SIZE as i16 + 4)
+    }
+    /// height of the most recently used row, including the gap that sets it apart from the list
+    fn recent_height(&self) -> i16 {
+        if self.mru.is_empty() {
+            0
+        } else {
+            LAUNCHER_ICON_SIZE as i16 + self.line_height + self.margin * 2
+        }
+    }
+    /// top of the given row of the list, counting from the first row on screen
+    fn entry_top(&self, row: usize) -> i16 {
+        self.margin + self.recent_height() + row as i16 * self.row_height()
+    }
+    /// how many entries fit on a canvas of the given height, leaving room for the close entry
+    fn visible_rows(&self, height: i16) -> usize {
+        let room = height - self.entry_top(0) - self.line_height - self.margin;
+        (room / self.row_height()).max(1) as usize
+    }
+    /// Scrolls the list just far enough to bring the selection on screen
+    fn scroll_to_selection(&mut self, rows: usize) {
+        let max_scroll = self.order.len().saturating_sub(rows);
+        match self.selection {
+            Selection::Entry(position) if position < self.scroll => self.scroll = position,
+            Selection::Entry(position) if position >= self.scroll + rows => self.scroll = position + 1 - rows,
+            Selection::Entry(_) => (),
+            Selection::Recent(_) => self.scroll = 0,
+            Selection::Close => self.scroll = max_scroll,
+        }
+        self.scroll = self.scroll.min(max_scroll);
+    }
+    /// Asks the GAM to size the launcher's canvas to fit everything it shows. The GAM may grant less
+    /// than that, in which case the list scrolls.
+    fn fit_to_entries(&mut self) {
+        let current_bounds = self.gam.get_canvas_bounds(self.canvas).expect("couldn't get current bounds");
+        let mut new_bounds = SetCanvasBoundsRequest {
+            requested: Point::new(current_bounds.x, self.entry_top(self.order.len()) + self.line_height + self.margin),
+            granted: None,
+            token_type: TokenType::App,
+            token: self.authtoken,
+        };
+        self.gam.set_canvas_bounds_request(&mut new_bounds).expect("couldn't call set bounds");
+    }
+
+    fn draw_icon(&self, icon: &LauncherIcon, tl: Point) {
+        if let Some(mut bm) = Bitmap::new(self.canvas, tl, LAUNCHER_ICON_SIZE, LAUNCHER_ICON_SIZE, BitmapFormat::Mono, icon) {
+            bm.transparent = Some(PixelColor::Light);
+            self.gam.draw_bitmap(&bm).expect("couldn't draw launcher icon");
+        }
+    }
+    fn draw_text(&self, text: &str, tl: Point, br: Point, marker: bool) {
+        use core::fmt::Write;
+        let mut tv = TextView::new(self.canvas, TextBounds::BoundingBox(Rectangle::new(tl, br)));
+        if marker {
+            write!(tv.text, "\u{25B6}").unwrap();
+        }
+        write!(tv.text, "{}", text).unwrap();
+        tv.draw_border = false;
+        tv.style = GlyphStyle::Regular;
+        tv.margin = Point::new(0, 0);
+        tv.ellipsis = true;
+        tv.max_lines = Some(1);
+        self.gam.post_textview(&mut tv).expect("couldn't render launcher text");
+    }
+    fn draw_recent(&self, width: i16) {
+        let cell_width = (width - self.margin * 2) / LAUNCHER_MRU_LEN as i16;
+        for (column, &index) in self.mru.iter().enumerate() {
+            let entry = &self.entries[index];
+            let left = self.margin + column as i16 * cell_width;
+            let selected = self.selection == Selection::Recent(column);
+            if let Some(icon) = entry.icon.as_ref() {
+                self.draw_icon(icon, Point::new(left + (cell_width - LAUNCHER_ICON_SIZE as i16) / 2, self.margin));
+            }
+            let top = self.margin + LAUNCHER_ICON_SIZE as i16;
+            self.draw_text(entry.name.as_str().unwrap(),
+                Point::new(left, top), Point::new(left + cell_width - 1, top + self.line_height), selected);
+        }
+        let y = self.recent_height();
+        self.gam.draw_line(self.canvas, Line::new_with_style(
+            Point::new(self.margin, y), Point::new(width - self.margin, y),
+            DrawStyle::new(PixelColor::Dark, PixelColor::Dark, 1))
+        ).expect("couldn't draw launcher divider");
+    }
+    fn draw_entry(&self, position: usize, row: usize, width: i16) {
+        let entry = &self.entries[self.order[position]];
+        let top = self.entry_top(row);
+        let selected = self.selection == Selection::Entry(position);
+        let icon_x = self.margin + 20;
+        if let Some(icon) = entry.icon.as_ref() {
+            self.draw_icon(icon, Point::new(icon_x, top + 2));
+        }
+        let text_top = top + (self.row_height() - self.line_height) / 2;
+        if selected {
+            // a picked up entry is marked with up/down arrows until it's dropped
+            let marker = if self.moving { "\u{2195}" } else { "\u{25B6}" };
+            self.draw_text(marker, Point::new(self.margin, text_top), Point::new(icon_x - 1, text_top + self.line_height), false);
+        }
+        let text_x = icon_x + LAUNCHER_ICON_SIZE as i16 + self.margin;
+        self.draw_text(entry.name.as_str().unwrap(),
+            Point::new(text_x, text_top), Point::new(width - self.margin - SCROLL_MARK_WIDTH, text_top + self.line_height), false);
+    }
+    /// Marks the right end of a row with an arrow, to show that the list continues past it
+    fn draw_scroll_mark(&self, row: usize, mark: &str, width: i16) {
+        let top = self.entry_top(row) + (self.row_height() - self.line_height) / 2;
+        self.draw_text(mark, Point::new(width - self.margin - SCROLL_MARK_WIDTH, top),
+            Point::new(width - self.margin, top + self.line_height), false);
+    }
+    pub fn redraw(&mut self) {
+        let canvas_size = self.gam.get_canvas_bounds(self.canvas).unwrap();
+        self.gam.draw_rounded_rectangle(self.canvas,
+            RoundedRectangle::new(
+                Rectangle::new_with_style(Point::new(0, 0), canvas_size,
+                    DrawStyle::new(PixelColor::Light, PixelColor::Dark, 3)
+                ), 5
+            )).unwrap();
+        if !self.mru.is_empty() {
+            self.draw_recent(canvas_size.x);
+        }
+        let rows = self.visible_rows(canvas_size.y);
+        self.scroll_to_selection(rows);
+        let shown = self.scroll..self.order.len().min(self.scroll + rows);
+        for (row, position) in shown.clone().enumerate() {
+            self.draw_entry(position, row, canvas_size.x);
+        }
+        if shown.start > 0 {
+            self.draw_scroll_mark(0, "\u{25B2}", canvas_size.x);
+        }
+        if shown.end < self.order.len() {
+            self.draw_scroll_mark(shown.len() - 1, "\u{25BC}", canvas_size.x);
+        }
+        let top = self.entry_top(shown.len());
+        self.draw_text(self.close_label.as_str().unwrap(),
+            Point::new(self.margin, top), Point::new(canvas_size.x - self.margin, top + self.line_height),
+            self.selection == Selection::Close);
+        #[cfg(feature="tts")]
+        {
+            if let Some(index) = self.selected_entry() {
+                self.tts.tts_simple(self.entries[index].name.as_str().unwrap()).unwrap();
+            }
+        }
+        self.gam.redraw().unwrap();
+    }
+
+    fn selected_entry(&self) -> Option<usize> {
+        match self.selection {
+            Selection::Recent(column) => self.mru.get(column).copied(),
+            Selection::Entry(position) => self.order.get(position).copied(),
+            Selection::Close => None,
+        }
+    }
+    fn select_up(&mut self) {
+        self.selection = match self.selection {
+            Selection::Recent(_) => Selection::Close,
+            Selection::Entry(0) if !self.mru.is_empty() => Selection::Recent(0),
+            Selection::Entry(0) => Selection::Close,
+            Selection::Entry(position) => Selection::Entry(position - 1),
+            Selection::Close if self.order.is_empty() => Selection::Close,
+            Selection::Close => Selection::Entry(self.order.len() - 1),
+        };
+    }
+    fn select_down(&mut self) {
+        self.selection = match self.selection {
+            Selection::Entry(position) if position + 1 < self.order.len() => Selection::Entry(position + 1),
+            Selection::Entry(_) => Selection::Close,
+            Selection::Recent(_) if !self.order.is_empty() => Selection::Entry(0),
+            Selection::Recent(_) => Selection::Close,
+            Selection::Close if !self.mru.is_empty() => Selection::Recent(0),
+            Selection::Close => Selection::Entry(0),
+        };
+    }
+    /// Moves the picked up entry one place up or down the list
+    fn move_entry(&mut self, up: bool) {
+        if let Selection::Entry(position) = self.selection {
+            let target = if up { position.checked_sub(1) } else { Some(position + 1) };
+            if let Some(target) = target.filter(|&t| t < self.order.len()) {
+                self.order.swap(position, target);
+                self.selection = Selection::Entry(target);
+            }
+        }
+    }
+    fn notify_changed(&self) {
+        if let Some((conn, opcode)) = self.changed {
+            xous::send_message(conn, xous::Message::new_scalar(opcode as usize, 0, 0, 0, 0))
+                .expect("couldn't report launcher change");
+        }
+    }
+    fn launch(&mut self, index: usize) {
+        // give up focus before issuing the command, as switching apps conflicts with the launcher being up
+        self.gam.relinquish_focus().unwrap();
+        let entry = self.entries[index];
+        #[cfg(feature="tts")]
+        {
+            let mut phrase = "select ".to_string();
+            phrase.push_str(entry.name.as_str().unwrap());
+            self.tts.tts_blocking(&phrase).unwrap();
+        }
+        xous::send_message(entry.action_conn,
+            xous::Message::new_scalar(entry.action_opcode as usize,
+                entry.action_payload[0] as usize, entry.action_payload[1] as usize,
+                entry.action_payload[2] as usize, entry.action_payload[3] as usize)
+        ).expect("couldn't send launcher action");
+        self.mru.retain(|&i| i != index);
+        self.mru.insert(0, index);
+        self.mru.truncate(LAUNCHER_MRU_LEN);
+        self.fit_to_entries();
+        self.notify_changed();
+    }
+    pub fn key_event(&mut self, keys: [char; 4]) {
+        for &k in keys.iter() {
+            if self.moving {
+                match k {
+                    '↑' => self.move_entry(true),
+                    '↓' => self.move_entry(false),
+                    '∴' | '→' | '←' => {
+                        self.moving = false;
+                        self.notify_changed();
+                    }
+                    _ => continue,
+                }
+                self.redraw();
+                continue;
+            }
+            match k {
+                '∴' => {
+                    match self.selected_entry() {
+                        Some(index) => self.launch(index),
+                        None => self.gam.relinquish_focus().unwrap(),
+                    }
+                    // the next time the launcher comes up, it starts on the most recent app
+                    self.selection = if self.mru.is_empty() { Selection::Entry(0) } else { Selection::Recent(0) };
+                    self.gam.redraw().unwrap();
+                    break; // drop any characters that happened to trail the select key, it's probably a fat-finger error.
+                }
+                '↑' => self.select_up(),
+                '↓' => self.select_down(),
+                '←' => if let Selection::Recent(column) = self.selection {
+                    self.selection = Selection::Recent(column.checked_sub(1).unwrap_or(self.mru.len() - 1));
+                },
+                '→' => match self.selection {
+                    Selection::Recent(column) => self.selection = Selection::Recent((column + 1) % self.mru.len()),
+                    // pick the entry up; it follows the up/down keys until any of select, left or right drops it
+                    Selection::Entry(_) => self.moving = true,
+                    Selection::Close => (),
+                },
+                _ => continue,
+            }
+            self.redraw();
+        }
+    }
+}
+
+pub struct LauncherMatic {
+    cid: xous::CID,
+}
+impl LauncherMatic {
+    pub fn layout(&self) -> LauncherLayout {
+        let mm = LauncherManagement {
+            layout: LauncherLayout::new(),
+            op: LauncherMgrOp::GetLayout,
+        };
+        let mut buf = Buffer::into_buf(mm).expect("Couldn't convert to memory structure");
+        buf.lend_mut(self.cid, 0).expect("Couldn't issue management opcode");
+        buf.to_original::<LauncherManagement, _>().unwrap().layout
+    }
+    pub fn set_layout(&self, layout: LauncherLayout) {
+        let mm = LauncherManagement {
+            layout,
+            op: LauncherMgrOp::SetLayout,
+        };
+        let mut buf = Buffer::into_buf(mm).expect("Couldn't convert to memory structure");
+        buf.lend_mut(self.cid, 0).expect("Couldn't issue management opcode");
+    }
+    pub fn quit(&self) {
+        let mm = LauncherManagement {
+            layout: LauncherLayout::new(),
+            op: LauncherMgrOp::Quit,
+        };
+        let mut buf = Buffer::into_buf(mm).expect("Couldn't convert to memory structure");
+        buf.lend_mut(self.cid, 0).expect("Couldn't issue management opcode");
+    }
+}
+use std::thread;
+use std::sync::{Arc, Mutex};
+/// Builds a launcher from a list of entries, and then manages it. The launcher's layout can be read
+/// back and restored through the returned `LauncherMatic`, which listens on the private server `manager`.
+/// `changed` is where to send a scalar message with the given opcode whenever the user changes the layout.
+pub fn launcher_matic(
+    entries: Vec::<LauncherEntry>,
+    close_label: &str,
+    launcher_name: &'static str,
+    manager: xous::SID,
+    changed: Option<(xous::CID, u32)>,
+) -> LauncherMatic {
+    let launcher = Arc::new(Mutex::new(Launcher::new(launcher_name, close_label)));
+    for entry in entries {
+        launcher.lock().unwrap().add_entry(entry);
+    }
+    if let Some((conn, opcode)) = changed {
+        launcher.lock().unwrap().set_change_listener(conn, opcode);
+    }
+    let _ = thread::spawn({
+        let launcher = launcher.clone();
+        let sid = launcher.lock().unwrap().sid.clone();
+        move || {
+            loop {
+                let msg = xous::receive_message(sid).unwrap();
+                match FromPrimitive::from_usize(msg.body.id()) {
+                    Some(MenuOpcode::Redraw) => {
+                        launcher.lock().unwrap().redraw();
+                    },
+                    Some(MenuOpcode::Rawkeys) => xous::msg_scalar_unpack!(msg, k1, k2, k3, k4, {
+                        let keys = [
+                            core::char::from_u32(k1 as u32).unwrap_or('\u{0000}'),
+                            core::char::from_u32(k2 as u32).unwrap_or('\u{0000}'),
+                            core::char::from_u32(k3 as u32).unwrap_or('\u{0000}'),
+                            core::char::from_u32(k4 as u32).unwrap_or('\u{0000}'),
+                        ];
+                        launcher.lock().unwrap().key_event(keys);
+                    }),
+                    Some(MenuOpcode::Quit) => {
+                        xous::return_scalar(msg.sender, 1).unwrap();
+                        break;
+                    },
+                    None => {
+                        log::error!("unknown opcode {:?}", msg.body.id());
+                    }
+                }
+            }
+            xous::destroy_server(launcher.lock().unwrap().sid).unwrap();
+        }
+    });
+    let _ = thread::spawn({
+        let launcher = launcher.clone();
+        move || {
+            loop {
+                let mut msg = xous::receive_message(manager).unwrap();
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let mut mgmt = buffer.to_original::<LauncherManagement, _>().expect("launcher manager received unexpected message type");
+                match mgmt.op {
+                    LauncherMgrOp::GetLayout => {
+                        mgmt.layout = launcher.lock().unwrap().layout();
+                        mgmt.op = LauncherMgrOp::Ok;
+                        buffer.replace(mgmt).unwrap();
+                    }
+                    LauncherMgrOp::SetLayout => {
+                        launcher.lock().unwrap().set_layout(&mgmt.layout);
+                        mgmt.op = LauncherMgrOp::Ok;
+                        buffer.replace(mgmt).unwrap();
+                    }
+                    LauncherMgrOp::Quit => {
+                        mgmt.op = LauncherMgrOp::Ok;
+                        buffer.replace(mgmt).unwrap();
+                        break;
+                    }
+                    _ => {
+                        log::error!("Unhandled opcode: {:?}", mgmt.op);
+                    }
+                }
+            }
+            xous::destroy_server(manager).unwrap();
+        }
+    });
+    LauncherMatic {
+        cid: xous::connect(manager).unwrap(),
+    }
+}
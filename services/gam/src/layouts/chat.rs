@@ -8,6 +8,7 @@ use crate::{LayoutApi, LayoutBehavior};
 
 use crate::contexts::MISC_CONTEXT_DEFAULT_TRUST;
 const TRUST_OFFSET: u8 = 2;
+const MARGIN: i16 = 4;
 
 #[derive(Debug, Copy, Clone)]
 pub(crate) struct ChatLayout {
@@ -21,7 +22,7 @@ pub(crate) struct ChatLayout {
     min_input_height: i16,
     _screensize: Point,
     _small_height: i16,
-    _regular_height: i16,
+    regular_height: i16,
 }
 impl ChatLayout {
     // pass in the status canvas so we can size around it, but we can't draw on it
@@ -29,9 +30,9 @@ impl ChatLayout {
         status_cliprect: &Rectangle, canvases: &mut HashMap<Gid, Canvas>) -> Result<ChatLayout, xous::Error> {
         let screensize = gfx.screen_size().expect("Couldn't get screen size");
         // get the height of various text regions to compute the layout
-        let small_height: i16 = gfx.glyph_height_hint(GlyphStyle::Small).expect("couldn't get glyph height") as i16;
-        let regular_height: i16 = gfx.glyph_height_hint(GlyphStyle::Regular).expect("couldn't get glyph height") as i16;
-        let margin = 4;
+        let small_height: i16 = crate::glyph_height_hint(gfx, GlyphStyle::Small) as i16;
+        let regular_height: i16 = crate::glyph_height_hint(gfx, GlyphStyle::Regular) as i16;
+        let margin = MARGIN;
 
         // allocate canvases in structures, and record their GID for future reference
         // base trust - 2 so that main menu + status bar always ride on top
@@ -68,7 +69,7 @@ impl ChatLayout {
             min_input_height,
             _screensize: screensize,
             _small_height: small_height,
-            _regular_height: regular_height,
+            regular_height,
         })
     }
 }
@@ -125,6 +126,26 @@ impl LayoutApi for ChatLayout {
             Ok(input_canvas.clip_rect().br)
         }
     }
+    fn relayout(&mut self, gfx: &graphics_server::Gfx, status_cliprect: &Rectangle, canvases: &mut HashMap<Gid, Canvas>) -> Result<(), xous::Error> {
+        let screensize = gfx.screen_size()?;
+        // the text size may have changed too
+        self.regular_height = crate::glyph_height_hint(gfx, GlyphStyle::Regular) as i16;
+        let min_input_height = self.regular_height + MARGIN*2;
+        // the input area keeps the height it has grown to, unless it was at its minimum
+        let input_rect = canvases.get(&self.input).expect("couldn't find input canvas").clip_rect();
+        let input_height = input_rect.br.y - input_rect.tl.y + 1;
+        let input_height = if input_height == self.min_input_height { min_input_height } else { input_height.max(min_input_height) };
+        self.min_input_height = min_input_height;
+
+        let predictive_rect = Rectangle::new_coords(0, screensize.y - self.regular_height - MARGIN*2, screensize.x, screensize.y);
+        let input_rect = Rectangle::new_v_stack(predictive_rect, -input_height);
+        let content_rect = Rectangle::new_v_span(*status_cliprect, input_rect);
+        canvases.get_mut(&self.predictive).expect("couldn't find predictive canvas").set_clip(predictive_rect);
+        canvases.get_mut(&self.input).expect("couldn't find input canvas").set_clip(input_rect);
+        canvases.get_mut(&self.content).expect("couldn't find content canvas").set_clip(content_rect);
+        self._screensize = screensize;
+        Ok(())
+    }
     fn get_gids(&self) ->Vec<crate::api::GidRecord> {
         vec![
             crate::api::GidRecord {
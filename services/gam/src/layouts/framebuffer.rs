@@ -53,6 +53,13 @@ impl LayoutApi for Framebuffer {
         fb_canvas.set_clip(fb_clip_rect);
         Ok(fb_clip_rect.br)
     }
+    fn relayout(&mut self, gfx: &graphics_server::Gfx, status_cliprect: &Rectangle, canvases: &mut HashMap<Gid, Canvas>) -> Result<(), xous::Error> {
+        let screensize = gfx.screen_size()?;
+        let fb_canvas = canvases.get_mut(&self.gid).expect("couldn't find my canvas");
+        fb_canvas.set_clip(Rectangle::new(Point::new(0, status_cliprect.br().y + 1), screensize));
+        self._screensize = screensize;
+        Ok(())
+    }
     fn get_gids(&self) ->Vec<crate::api::GidRecord> {
         vec![
             crate::api::GidRecord {
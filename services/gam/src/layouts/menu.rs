@@ -13,7 +13,7 @@ const TRUST_OFFSET: u8 = 0;
 pub(crate) struct MenuLayout {
     pub menu: Gid,
     menu_y_pad: i16,
-    _menu_x_pad: i16,
+    menu_x_pad: i16,
     menu_min_height: i16,
     screensize: Point,
     _height: i16,
@@ -22,7 +22,7 @@ impl MenuLayout {
     pub fn init(gfx: &graphics_server::Gfx, trng: &trng::Trng, canvases: &mut HashMap<Gid, Canvas>) -> Result<MenuLayout, xous::Error> {
         let screensize = gfx.screen_size().expect("Couldn't get screen size");
         // get the height of various text regions to compute the layout
-        let height: i16 = gfx.glyph_height_hint(GlyphStyle::Regular).expect("couldn't get glyph height") as i16;
+        let height: i16 = crate::glyph_height_hint(gfx, GlyphStyle::Regular) as i16;
 
         const MENU_Y_PAD: i16 = 100;
         const MENU_X_PAD: i16 = 35;
@@ -37,7 +37,7 @@ impl MenuLayout {
         Ok(MenuLayout {
             menu: gid,
             menu_y_pad: MENU_Y_PAD,
-            _menu_x_pad: MENU_X_PAD,
+            menu_x_pad: MENU_X_PAD,
             menu_min_height: height,
             screensize,
             _height: height, // start with "minimum" size and grow up as items are added
@@ -73,6 +73,18 @@ impl LayoutApi for MenuLayout {
         // gfx.draw_rectangle(menu_clip_rect).expect("can't clear menu");
         Ok(menu_clip_rect.br)
     }
+    fn relayout(&mut self, gfx: &graphics_server::Gfx, _status_cliprect: &Rectangle, canvases: &mut HashMap<Gid, Canvas>) -> Result<(), xous::Error> {
+        self.screensize = gfx.screen_size()?;
+        self.menu_min_height = crate::glyph_height_hint(gfx, GlyphStyle::Regular) as i16;
+        let menu_canvas = canvases.get_mut(&self.menu).expect("couldn't find menu canvas");
+        let orig_rect = menu_canvas.clip_rect();
+
+        let height = orig_rect.br().y.min(self.screensize.y - self.menu_y_pad);
+        let mut menu_clip_rect = Rectangle::new_coords(self.menu_x_pad, self.menu_y_pad, self.screensize.x - self.menu_x_pad, height);
+        menu_clip_rect.style = DrawStyle {fill_color: Some(PixelColor::Dark), stroke_color: None, stroke_width: 0,};
+        menu_canvas.set_clip(menu_clip_rect);
+        Ok(())
+    }
     fn get_gids(&self) ->Vec<crate::api::GidRecord> {
         vec![
             crate::api::GidRecord {
@@ -14,7 +14,7 @@ const TRUST_OFFSET: u8 = 1;
 pub(crate) struct ModalLayout {
     pub modal: Gid,
     modal_y_pad: i16,
-    _modal_x_pad: i16,
+    modal_x_pad: i16,
     modal_min_height: i16,
     screensize: Point,
     _height: i16,
@@ -24,7 +24,7 @@ impl ModalLayout {
     pub fn init(gfx: &graphics_server::Gfx, trng: &trng::Trng, canvases: &mut HashMap<Gid, Canvas>) -> Result<ModalLayout, xous::Error> {
         let screensize = gfx.screen_size().expect("Couldn't get screen size");
         // get the height of various text regions to compute the layout
-        let height: i16 = gfx.glyph_height_hint(GlyphStyle::Regular).expect("couldn't get glyph height") as i16;
+        let height: i16 = crate::glyph_height_hint(gfx, GlyphStyle::Regular) as i16;
 
         const MODAL_Y_PAD: i16 = 80;
         const MODAL_X_PAD: i16 = 20;
@@ -39,7 +39,7 @@ impl ModalLayout {
         Ok(ModalLayout {
             modal: modal_gid,
             modal_y_pad: MODAL_Y_PAD,
-            _modal_x_pad: MODAL_X_PAD,
+            modal_x_pad: MODAL_X_PAD,
             modal_min_height: height,
             screensize,
             _height: screensize.y - MODAL_Y_PAD, // start with the "maximum" size, and shrink down once items are known
@@ -76,6 +76,18 @@ impl LayoutApi for ModalLayout {
         // gfx.draw_rectangle(menu_clip_rect).expect("can't clear menu");
         Ok(modal_clip_rect.br)
     }
+    fn relayout(&mut self, gfx: &graphics_server::Gfx, _status_cliprect: &Rectangle, canvases: &mut HashMap<Gid, Canvas>) -> Result<(), xous::Error> {
+        self.screensize = gfx.screen_size()?;
+        self.modal_min_height = crate::glyph_height_hint(gfx, GlyphStyle::Regular) as i16;
+        let modal_canvas = canvases.get_mut(&self.modal).expect("couldn't find modal canvas");
+        let orig_rect = modal_canvas.clip_rect();
+
+        let height = orig_rect.br().y.min(self.screensize.y - self.modal_y_pad);
+        let mut modal_clip_rect = Rectangle::new_coords(self.modal_x_pad, self.modal_y_pad, self.screensize.x - self.modal_x_pad, height);
+        modal_clip_rect.style = DrawStyle {fill_color: Some(PixelColor::Dark), stroke_color: None, stroke_width: 0,};
+        modal_canvas.set_clip(modal_clip_rect);
+        Ok(())
+    }
     fn get_gids(&self) ->Vec<crate::api::GidRecord> {
         vec![
             crate::api::GidRecord {
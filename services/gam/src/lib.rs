@@ -8,13 +8,23 @@ pub mod modal;
 pub use modal::*;
 pub mod menu;
 pub use menu::*;
+pub mod launcher;
+pub use launcher::*;
+pub mod picker;
+pub use picker::*;
+pub mod flipbook;
+pub use flipbook::*;
 pub mod apps;
 pub use apps::*;
 
-use graphics_server::api::{TextOp, TextView};
-use graphics_server::api::{Point, Gid, Line, Rectangle, Circle, RoundedRectangle, TokenClaim};
+use graphics_server::api::{TextOp, TextView, TextMeasurement};
+use graphics_server::api::{Point, Gid, Line, Rectangle, Circle, Arc, RoundedRectangle, TokenClaim};
+pub use graphics_server::api::{Bitmap, BitmapFormat, Polygon, MAX_POLYGON_POINTS};
 pub use graphics_server::api::GlyphStyle;
 pub use graphics_server::api::PixelColor;
+pub use graphics_server::api::{SCREENSHOT_LEN, SCREENSHOT_ROW_BYTES, SCREENSHOT_LANDSCAPE_ROW_BYTES};
+pub use graphics_server::api::Rotation;
+pub use keyboard::{KeyEvent, RowCol};
 use api::Opcode; // if you prefer to map the api into your local namespace
 use xous::{send_message, CID, Message};
 use xous_ipc::{String, Buffer};
@@ -29,19 +39,23 @@ pub const MAIN_MENU_NAME: &'static str = "main menu";
 pub const PDDB_MODAL_NAME: &'static str = "pddb modal";
 pub const PDDB_MENU_NAME: &'static str = "pddb menu";
 pub const ROOTKEY_MODAL_NAME: &'static str = "rootkeys modal";
-pub const EMOJI_MENU_NAME: &'static str = "emoji menu";
+pub const SYMBOL_PICKER_NAME: &'static str = "symbol picker";
 pub const SHARED_MODAL_NAME: &'static str = "shared modal";
 pub const STATUS_BAR_NAME: &'static str = "status";
 pub const APP_NAME_SHELLCHAT: &'static str = "shellchat";
 pub const APP_MENU_NAME: &'static str = "app menu";
 pub const KBD_MENU_NAME: &'static str = "keyboard menu";
 
+/// How long apps get to acknowledge `LifecycleEvent::AboutToSuspend` before the system suspends without them.
+/// It stays well inside the time susres gives the whole system to get ready.
+pub const LIFECYCLE_ACK_TIMEOUT_MS: u64 = 2000;
+
 /// UX context registry. Names here are authorized by the GAM to have Canvases.
 pub const EXPECTED_BOOT_CONTEXTS: &[&'static str] = &[
     APP_NAME_SHELLCHAT,
     MAIN_MENU_NAME,
     STATUS_BAR_NAME,
-    EMOJI_MENU_NAME,
+    SYMBOL_PICKER_NAME,
     ROOTKEY_MODAL_NAME,
     PDDB_MODAL_NAME,
     SHARED_MODAL_NAME,
@@ -50,6 +64,20 @@ pub const EXPECTED_BOOT_CONTEXTS: &[&'static str] = &[
     KBD_MENU_NAME,
 ];
 
+/// A capture of the screen, as returned by `Gam::screenshot()`
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ScreenCapture {
+    /// bytes per line of `raster`
+    pub row_bytes: usize,
+    pub raster: Vec<u8>,
+}
+impl ScreenCapture {
+    /// width and height in pixels
+    pub fn size(&self) -> (usize, usize) {
+        (self.row_bytes * 8, self.raster.len() / self.row_bytes)
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 #[repr(u32)]
 pub enum FocusState {
@@ -66,6 +94,19 @@ impl FocusState {
     }
 }
 
+/// Sent to every app that registered a `lifecycle_id`, whether or not it has focus. The messages don't wait
+/// for the app, so anything that has to be saved should be saved promptly.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, num_derive::FromPrimitive, num_derive::ToPrimitive)]
+pub enum LifecycleEvent {
+    /// the system is going to sleep: flush state to the PDDB and stop timers, then call `Gam::lifecycle_ack()`.
+    /// The suspend waits for every app to acknowledge, for up to `LIFECYCLE_ACK_TIMEOUT_MS`.
+    AboutToSuspend = 0,
+    /// the system woke up again after an `AboutToSuspend`
+    Resumed = 1,
+    /// the system is about to power off, after which there is no `Resumed`
+    ShuttingDown = 2,
+}
+
 
 #[derive(Debug)]
 pub struct Gam {
@@ -93,6 +134,134 @@ impl Gam {
         ).map(|_|())
     }
 
+    /// Pushes a part of a canvas to the screen immediately, instead of waiting for the next rate-limited `redraw()`.
+    /// Only the lines that were actually drawn since the last flush are sent to the LCD, so an app that updates
+    /// a single row of text can have it appear with minimal latency. `region` is in canvas coordinates; pass
+    /// `None` to flush everything drawn on the canvas.
+    pub fn flush_region(&self, gid: Gid, region: Option<Rectangle>) -> Result<(), xous::Error> {
+        let req = FlushRegionRequest {
+            canvas: gid,
+            region,
+        };
+        let buf = Buffer::into_buf(req).or(Err(xous::Error::InternalError))?;
+        buf.lend(self.conn, Opcode::FlushRegion.to_u32().unwrap()).map(|_|())
+    }
+
+    /// Narrows drawing on `gid` to `region`, in canvas coordinates, until the matching `pop_clip()`. Parts of objects,
+    /// bitmaps and TextViews outside of the region are left untouched, so a widget that is partly scrolled out
+    /// of view can be drawn whole. Regions nest: each one is intersected with the ones pushed before it. Fails
+    /// with `OutOfMemory` once `gam::MAX_CLIP_DEPTH` regions are pushed. Pushed regions are dropped when the
+    /// canvas is moved or resized, since the app redraws everything then anyway.
+    pub fn push_clip(&self, gid: Gid, region: Rectangle) -> Result<(), xous::Error> {
+        let req = PushClipRequest {
+            canvas: gid,
+            region,
+            granted: false,
+        };
+        let mut buf = Buffer::into_buf(req).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.conn, Opcode::PushClip.to_u32().unwrap()).or(Err(xous::Error::InternalError))?;
+        if buf.to_original::<PushClipRequest, _>().or(Err(xous::Error::InternalError))?.granted {
+            Ok(())
+        } else {
+            Err(xous::Error::OutOfMemory)
+        }
+    }
+    /// Undoes the most recent `push_clip()` on `gid`.
+    pub fn pop_clip(&self, gid: Gid) -> Result<(), xous::Error> {
+        let g = gid.gid();
+        send_message(self.conn,
+            Message::new_scalar(Opcode::PopClip.to_usize().unwrap(), g[0] as _, g[1] as _, g[2] as _, g[3] as _)
+        ).map(|_|())
+    }
+
+    /// Starts composing `gid` offscreen. Everything drawn on the canvas after this call lands in a back buffer
+    /// instead of on the screen, so a redraw made of many TextViews and objects never shows up half-finished.
+    /// The back buffer starts out as a copy of what the canvas currently shows. Call `swap_offscreen()` when done.
+    pub fn begin_offscreen(&self, gid: Gid) -> Result<(), xous::Error> {
+        let g = gid.gid();
+        send_message(self.conn,
+            Message::new_scalar(Opcode::BeginOffscreen.to_usize().unwrap(), g[0] as _, g[1] as _, g[2] as _, g[3] as _)
+        ).map(|_|())
+    }
+    /// Atomically copies the composed canvas onto the screen buffer and goes back to drawing directly on screen.
+    /// The result appears on the next `redraw()` or `flush_region()`.
+    pub fn swap_offscreen(&self, gid: Gid) -> Result<(), xous::Error> {
+        let g = gid.gid();
+        send_message(self.conn,
+            Message::new_scalar(Opcode::SwapOffscreen.to_usize().unwrap(), g[0] as _, g[1] as _, g[2] as _, g[3] as _)
+        ).map(|_|())
+    }
+
+    /// Loads a glyph set into one of the runtime slots, so text can be drawn with the matching `GlyphStyle::Loaded*`
+    /// style. Typically `blob` comes from a key in the PDDB; see `graphics_server::api::glyphset` for the format.
+    pub fn load_glyph_set(&self, slot: usize, blob: &[u8]) -> Result<(), xous::Error> {
+        if slot >= graphics_server::api::GLYPH_SET_SLOTS {
+            return Err(xous::Error::BadAddress);
+        }
+        if blob.len() > graphics_server::api::GLYPH_SET_MAX_LEN {
+            return Err(xous::Error::OutOfMemory);
+        }
+        let mut buf = Buffer::new(blob.len() + 4);
+        buf[0..4].copy_from_slice(&(slot as u32).to_le_bytes());
+        buf[4..4 + blob.len()].copy_from_slice(blob);
+        buf.lend_mut(self.conn, Opcode::LoadGlyphSet.to_u32().unwrap())?;
+        match xous::Error::from_usize(u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize) {
+            xous::Error::NoError => Ok(()),
+            e => Err(e),
+        }
+    }
+    pub fn unload_glyph_set(&self, slot: usize) -> Result<(), xous::Error> {
+        send_message(self.conn,
+            Message::new_scalar(Opcode::UnloadGlyphSet.to_usize().unwrap(), slot, 0, 0, 0)
+        ).map(|_|())
+    }
+    /// Sets the soft-hyphenation dictionary used to break long words across lines of text. Typically `dictionary`
+    /// comes from a key in the PDDB: UTF-8 text, one word per line with its break points marked by `-`.
+    /// The dictionary is system-wide, so only the status bar may set it: `token` is its claimed token,
+    /// and anything else fails with `AccessDenied`.
+    pub fn load_hyphenation(&self, token: [u32; 4], dictionary: &[u8]) -> Result<(), xous::Error> {
+        if dictionary.len() > graphics_server::api::HYPHENATION_MAX_LEN {
+            return Err(xous::Error::OutOfMemory);
+        }
+        let mut buf = Buffer::new(dictionary.len() + 20);
+        for (dst, word) in buf[..16].chunks_exact_mut(4).zip(token.iter()) {
+            dst.copy_from_slice(&word.to_le_bytes());
+        }
+        buf[16..20].copy_from_slice(&(dictionary.len() as u32).to_le_bytes());
+        buf[20..20 + dictionary.len()].copy_from_slice(dictionary);
+        buf.lend_mut(self.conn, Opcode::LoadHyphenation.to_u32().unwrap())?;
+        match xous::Error::from_usize(u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize) {
+            xous::Error::NoError => Ok(()),
+            e => Err(e),
+        }
+    }
+    pub fn unload_hyphenation(&self, token: [u32; 4]) -> Result<(), xous::Error> {
+        send_message(self.conn,
+            Message::new_scalar(Opcode::UnloadHyphenation.to_usize().unwrap(),
+                token[0] as usize, token[1] as usize, token[2] as usize, token[3] as usize)
+        ).map(|_|())
+    }
+    /// Captures the screen as a raster of `SCREENSHOT_LEN` bytes, in the format described by `SCREENSHOT_ROW_BYTES`,
+    /// and the way the user is holding the screen: while it's on its side, rows are `SCREENSHOT_LANDSCAPE_ROW_BYTES` long.
+    /// `token` is the caller's app token, as returned by `register_ux()`; the capture is only taken while
+    /// that app has focus, so a background process can't watch the screen. The GAM asks the user with a toast
+    /// of its own first, and this blocks until they've answered, or the toast has timed out.
+    /// Fails with `AccessDenied` if the caller doesn't have focus, if a modal or menu has focus, or if the user
+    /// didn't allow it; and with `ServerQueueFull` while another screenshot is waiting on the user.
+    pub fn screenshot(&self, token: [u32; 4]) -> Result<ScreenCapture, xous::Error> {
+        let mut buf = Buffer::new(SCREENSHOT_LEN + 16);
+        for (dst, word) in buf[..16].chunks_exact_mut(4).zip(token.iter()) {
+            dst.copy_from_slice(&word.to_le_bytes());
+        }
+        buf.lend_mut(self.conn, Opcode::Screenshot.to_u32().unwrap())?;
+        match xous::Error::from_usize(u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize) {
+            xous::Error::NoError => Ok(ScreenCapture {
+                row_bytes: u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]) as usize,
+                raster: buf[16..16 + SCREENSHOT_LEN].to_vec(),
+            }),
+            e => Err(e),
+        }
+    }
     pub fn powerdown_request(&self) -> Result<bool, xous::Error> {
         let response = send_message(self.conn,
             Message::new_blocking_scalar(Opcode::PowerDownRequest.to_usize().unwrap(), 0, 0, 0, 0))?;
@@ -196,12 +365,32 @@ impl Gam {
         let buf = Buffer::into_buf(go).or(Err(xous::Error::InternalError))?;
         buf.lend(self.conn, Opcode::RenderObject.to_u32().unwrap()).map(|_|())
     }
+    /// Draws an arc or pie slice; see `graphics_server::api::Arc` for how the angles are measured.
+    pub fn draw_arc(&self, gid: Gid, arc: Arc) -> Result<(), xous::Error> {
+        let go = GamObject {
+            canvas: gid,
+            obj: GamObjectType::Arc(arc),
+        };
+        let buf = Buffer::into_buf(go).or(Err(xous::Error::InternalError))?;
+        buf.lend(self.conn, Opcode::RenderObject.to_u32().unwrap()).map(|_|())
+    }
     pub fn draw_list(&self, list: GamObjectList) -> Result<(), xous::Error> {
         let buf = Buffer::into_buf(list).or(Err(xous::Error::InternalError))?;
         buf.lend(self.conn, Opcode::RenderObjectList.to_u32().unwrap())
             .map(|_| ())
     }
 
+    /// Blits a bitmap onto the canvas named in `bm`. Coordinates are local to the canvas.
+    pub fn draw_bitmap(&self, bm: &Bitmap) -> Result<(), xous::Error> {
+        let buf = Buffer::into_buf(*bm).or(Err(xous::Error::InternalError))?;
+        buf.lend(self.conn, Opcode::RenderBitmap.to_u32().unwrap()).map(|_|())
+    }
+    /// Draws a polyline, or a filled and/or outlined polygon, in one message. Handy for arrows, graphs and gauges.
+    pub fn draw_polygon(&self, poly: &Polygon) -> Result<(), xous::Error> {
+        let buf = Buffer::into_buf(*poly).or(Err(xous::Error::InternalError))?;
+        buf.lend(self.conn, Opcode::RenderPolygon.to_u32().unwrap()).map(|_|())
+    }
+
     pub fn get_canvas_bounds(&self, gid: Gid) -> Result<Point, xous::Error> {
         log::trace!("GAM_API: get_canvas_bounds");
         let response = send_message(self.conn,
@@ -249,6 +438,145 @@ impl Gam {
         }
     }
 
+    /// Splits the app's content area into up to `MAX_TABS` named tabs, and returns the canvas of each tab in
+    /// order. The first tab is the app's existing content canvas. Only the selected tab is onscreen, so drawing
+    /// on the canvas of a hidden tab is ignored. The user cycles through the tabs by pressing left and right
+    /// together; when that happens the content area is cleared and `tabchange_id` is sent to the app's listener
+    /// with the index of the new tab, which the app is expected to draw.
+    pub fn register_tabs(&self, token: [u32; 4], names: &[&str], tabchange_id: u32) -> Result<Vec<Gid>, xous::Error> {
+        if names.len() == 0 || names.len() > MAX_TABS {
+            return Err(xous::Error::OutOfMemory);
+        }
+        let mut registration = TabRegistration {
+            token,
+            names: [String::<32>::new(); MAX_TABS],
+            count: names.len(),
+            tabchange_id,
+            gids: None,
+        };
+        for (dst, &name) in registration.names.iter_mut().zip(names.iter()) {
+            *dst = String::<32>::from_str(name);
+        }
+        let mut buf = Buffer::into_buf(registration).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.conn, Opcode::RegisterTabs.to_u32().unwrap()).or(Err(xous::Error::InternalError))?;
+        let returned = buf.to_original::<TabRegistration, _>().or(Err(xous::Error::InternalError))?;
+        match returned.gids {
+            Some(gids) => Ok(gids[..names.len()].to_vec()),
+            None => Err(xous::Error::AccessDenied),
+        }
+    }
+    /// Brings the tab at `index` to the front, as if the user had switched to it
+    pub fn switch_tab(&self, token: [u32; 4], index: usize) -> Result<(), xous::Error> {
+        let switch = TabSwitch { token, index };
+        let buf = Buffer::into_buf(switch).or(Err(xous::Error::InternalError))?;
+        buf.send(self.conn, Opcode::SwitchTab.to_u32().unwrap()).map(|_| ())
+    }
+    /// Briefly shows `text` over the bottom of the screen, e.g. "Code copied". The toast doesn't take focus
+    /// and goes away on its own after `duration_ms` (see `TOAST_DEFAULT_MS`). Only one toast is shown at a
+    /// time; a new one replaces the old one.
+    pub fn toast(&self, text: &str, duration_ms: u32) -> Result<(), xous::Error> {
+        self.send_toast(ToastRequest {
+            text: String::<128>::from_str(text),
+            duration_ms,
+            action: None,
+        })
+    }
+    /// Like `toast()`, but while the toast is up, pressing `key` sends `opcode` to the server at `sid` as a
+    /// scalar message and takes the toast down. The key doesn't reach the focused app. `token` is the app
+    /// token of the caller's context: without a valid one, the toast is shown without its action. The key is
+    /// never taken while a modal or menu has focus.
+    pub fn toast_with_action(&self, token: [u32; 4], text: &str, duration_ms: u32, key: char, sid: xous::SID, opcode: u32) -> Result<(), xous::Error> {
+        self.send_toast(ToastRequest {
+            text: String::<128>::from_str(text),
+            duration_ms,
+            action: Some(ToastAction {
+                token,
+                key: key as u32,
+                sid: sid.to_array(),
+                opcode,
+            }),
+        })
+    }
+    fn send_toast(&self, request: ToastRequest) -> Result<(), xous::Error> {
+        let buf = Buffer::into_buf(request).or(Err(xous::Error::InternalError))?;
+        buf.send(self.conn, Opcode::ShowToast.to_u32().unwrap()).map(|_| ())
+    }
+    /// Asks the GAM to send `frame_id` to the app's listener every `interval_ms` (at least
+    /// `ANIMATION_MIN_INTERVAL_MS`), with a running frame count as the first argument. Frames are only sent
+    /// while the app has focus, so the app doesn't need a pump thread of its own or to stop it when it's
+    /// backgrounded. Calling this again replaces the timing; an interval of 0 stops the animation.
+    pub fn register_animation(&self, token: [u32; 4], frame_id: u32, interval_ms: u32) -> Result<(), xous::Error> {
+        let registration = AnimationRegistration { token, frame_id, interval_ms };
+        let buf = Buffer::into_buf(registration).or(Err(xous::Error::InternalError))?;
+        buf.send(self.conn, Opcode::RegisterAnimation.to_u32().unwrap()).map(|_| ())
+    }
+    /// Has every keydown and keyup sent to the app holding `token` while it has focus, as a scalar message with
+    /// `keyevent_id`; `KeyEvent::from_args()` reads the key, whether it went down, and when, in ticktimer ms.
+    /// They come as well as the keys themselves, for things like long presses; `None` stops them.
+    pub fn register_key_events(&self, token: [u32; 4], keyevent_id: Option<u32>) -> Result<(), xous::Error> {
+        let registration = KeyEventRegistration { token, keyevent_id };
+        let buf = Buffer::into_buf(registration).or(Err(xous::Error::InternalError))?;
+        buf.send(self.conn, Opcode::RegisterKeyEvents.to_u32().unwrap()).map(|_| ())
+    }
+    /// Has `key`, pressed while select is held, sent to the server `sid` as a scalar message with `opcode`,
+    /// whatever app has focus; the key is the first argument. The key belongs to the app holding `token` until
+    /// it gives it up, so this returns `false` if another app has it. The key is the one it is without shift,
+    /// after any remapping.
+    pub fn register_hotkey(&self, token: [u32; 4], key: char, sid: xous::SID, opcode: u32) -> Result<bool, xous::Error> {
+        let registration = HotkeyRegistration { token, key: key as u32, sid: sid.to_array(), opcode, accepted: false };
+        let mut buf = Buffer::into_buf(registration).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.conn, Opcode::RegisterHotkey.to_u32().unwrap()).or(Err(xous::Error::InternalError))?;
+        let returned = buf.to_original::<HotkeyRegistration, _>().or(Err(xous::Error::InternalError))?;
+        Ok(returned.accepted)
+    }
+    pub fn unregister_hotkey(&self, token: [u32; 4], key: char) -> Result<(), xous::Error> {
+        let registration = HotkeyRegistration { token, key: key as u32, sid: [0; 4], opcode: 0, accepted: false };
+        let buf = Buffer::into_buf(registration).or(Err(xous::Error::InternalError))?;
+        buf.send(self.conn, Opcode::UnregisterHotkey.to_u32().unwrap()).map(|_| ())
+    }
+    /// Claims one of the indicator slots on the status bar, e.g. for a "USB HID active" or "backup running"
+    /// icon. Only `STATUS_SLOT_COUNT` slots are visible at once; when more claims have something to show, the
+    /// ones with the highest `priority` are displayed. Returns the token to pass to `set_status_slot()`.
+    /// The claim lasts until `release_status_slot()`, or until the claiming process exits.
+    pub fn claim_status_slot(&self, name: &str, priority: u8) -> Result<[u32; 4], xous::Error> {
+        let claim = StatusSlotClaim {
+            name: String::<32>::from_str(name),
+            priority,
+            token: None,
+        };
+        let mut buf = Buffer::into_buf(claim).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.conn, Opcode::ClaimStatusSlot.to_u32().unwrap()).or(Err(xous::Error::InternalError))?;
+        let returned = buf.to_original::<StatusSlotClaim, _>().or(Err(xous::Error::InternalError))?;
+        returned.token.ok_or(xous::Error::AccessDenied)
+    }
+    /// Sets what a claimed slot shows: an icon glyph and/or a few characters. The status bar picks up the
+    /// change on its next refresh. An empty string hides the slot, letting a lower-priority claim show through.
+    pub fn set_status_slot(&self, slot: [u32; 4], text: &str) -> Result<(), xous::Error> {
+        let update = StatusSlotUpdate {
+            token: slot,
+            text: String::<16>::from_str(text),
+        };
+        let buf = Buffer::into_buf(update).or(Err(xous::Error::InternalError))?;
+        buf.send(self.conn, Opcode::SetStatusSlot.to_u32().unwrap()).map(|_| ())
+    }
+    pub fn release_status_slot(&self, slot: [u32; 4]) -> Result<(), xous::Error> {
+        send_message(self.conn,
+            Message::new_scalar(Opcode::ReleaseStatusSlot.to_usize().unwrap(),
+            slot[0] as usize, slot[1] as usize, slot[2] as usize, slot[3] as usize)
+        ).map(|_| ())
+    }
+    /// Used by the status bar to fetch the slots that won arbitration. `token` is the status bar's app token.
+    pub fn status_slots(&self, token: [u32; 4]) -> Result<StatusSlotList, xous::Error> {
+        let list = StatusSlotList {
+            token,
+            slots: [String::<16>::new(); STATUS_SLOT_COUNT],
+            count: 0,
+            generation: 0,
+        };
+        let mut buf = Buffer::into_buf(list).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.conn, Opcode::GetStatusSlots.to_u32().unwrap()).or(Err(xous::Error::InternalError))?;
+        buf.to_original::<StatusSlotList, _>().or(Err(xous::Error::InternalError))
+    }
     pub fn claim_token(&self, name: &str) -> Result<Option<[u32; 4]>, xous::Error> {
         let tokenclaim = TokenClaim {
             token: None,
@@ -307,6 +635,13 @@ impl Gam {
             ena, 0, 0, 0,)
         ).map(|_| ())
     }
+    /// Tells the GAM that the app with `token` is ready for the suspend it was told about with `AboutToSuspend`
+    pub fn lifecycle_ack(&self, token: [u32; 4]) -> Result<(), xous::Error> {
+        send_message(self.conn,
+            Message::new_scalar(Opcode::LifecycleAck.to_usize().unwrap(),
+                token[0] as usize, token[1] as usize, token[2] as usize, token[3] as usize)
+        ).map(|_| ())
+    }
     /// this indicates to the GAM that the currently running app no longer wants to be the focus of attention
     /// we might respect that. or maybe not. depends on the GAM's policies.
     pub fn relinquish_focus(&self) -> Result<(), xous::Error> {
@@ -327,6 +662,18 @@ impl Gam {
             panic!("unexpected return value: {:#?}", response);
         }
     }
+    /// Returns the width and height `text` would take up if drawn in `style`, wrapping at `max_width` pixels,
+    /// or only at newlines if that's `None`. This is the size a `TextView` with no margin or border would have,
+    /// at whatever size text is currently drawn, so rows can be laid out and codes right-aligned up front.
+    pub fn measure_text(&self, text: &str, style: GlyphStyle, max_width: Option<u16>) -> Result<Point, xous::Error> {
+        if text.len() > 3072 {
+            return Err(xous::Error::OutOfMemory);
+        }
+        let mut buf = Buffer::into_buf(TextMeasurement::new(text, style, max_width)).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.conn, Opcode::MeasureText.to_u32().unwrap()).or(Err(xous::Error::InternalError))?;
+        buf.to_original::<TextMeasurement, _>().or(Err(xous::Error::InternalError))?
+            .bounds.ok_or(xous::Error::InternalError)
+    }
     pub fn request_ime_redraw(&self) -> Result<(), xous::Error> {
         send_message(self.conn,
             Message::new_scalar(Opcode::RedrawIme.to_usize().unwrap(),
@@ -378,6 +725,46 @@ impl Gam {
             ena, 0, 0, 0,)
         ).map(|_| ())
     }
+    /// Renders the whole screen light-on-dark. The flip happens as the frame is pushed to the LCD, so apps
+    /// keep drawing with their usual colors. The setting isn't remembered here; the status bar keeps it in the PDDB.
+    /// It's system-wide, so only the status bar may change it: `token` is its claimed token, and anything else
+    /// fails with `AccessDenied`.
+    pub fn set_inverted(&self, token: [u32; 4], inverted: bool) -> Result<(), xous::Error> {
+        self.send_display_setting(Opcode::SetInverted, token, if inverted { 1 } else { 0 })
+    }
+    fn send_display_setting(&self, op: Opcode, token: [u32; 4], value: u32) -> Result<(), xous::Error> {
+        let mut buf = Buffer::new(20);
+        for (dst, word) in buf[..16].chunks_exact_mut(4).zip(token.iter()) {
+            dst.copy_from_slice(&word.to_le_bytes());
+        }
+        buf[16..20].copy_from_slice(&value.to_le_bytes());
+        buf.lend_mut(self.conn, op.to_u32().unwrap())?;
+        match xous::Error::from_usize(u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize) {
+            xous::Error::NoError => Ok(()),
+            e => Err(e),
+        }
+    }
+    /// Turns large text on or off for the whole system. While it's on, text views are drawn in the
+    /// `GlyphStyle::enlarged()` version of their styles, and `glyph_height_hint()` reports the enlarged heights,
+    /// so apps that take their line heights from it when they redraw keep their layouts in step.
+    /// Only the status bar may change it: `token` is its claimed token, and anything else fails with `AccessDenied`.
+    pub fn set_large_text(&self, token: [u32; 4], large: bool) -> Result<(), xous::Error> {
+        self.send_display_setting(Opcode::SetLargeText, token, if large { 1 } else { 0 })
+    }
+    /// Turns the screen for every app that hasn't opted out with `opt_out_of_rotation()`. Like the inversion,
+    /// the setting is kept in the PDDB by the status bar, and only the status bar may change it: `token` is its
+    /// claimed token, and anything else fails with `AccessDenied`.
+    pub fn set_rotation(&self, token: [u32; 4], rotation: Rotation) -> Result<(), xous::Error> {
+        self.send_display_setting(Opcode::SetRotation, token, rotation.to_u32().unwrap())
+    }
+    /// Keeps the screen upright while the app holding `token` is in the foreground, for apps that only make
+    /// sense in the portrait shape. Apps that re-read their canvas bounds when they redraw follow the rotation without it.
+    pub fn opt_out_of_rotation(&self, token: [u32; 4]) -> Result<(), xous::Error> {
+        send_message(self.conn,
+            Message::new_scalar(Opcode::OptOutOfRotation.to_usize().unwrap(),
+            token[0] as usize, token[1] as usize, token[2] as usize, token[3] as usize)
+        ).map(|_| ())
+    }
     pub fn selftest(&self, duration_ms: usize) {
         send_message(
             self.conn,
@@ -11,22 +11,35 @@ mod layouts;
 use layouts::*;
 mod contexts;
 use contexts::*;
+mod toast;
+use toast::*;
+mod statusslots;
+use statusslots::*;
+mod animation;
+use animation::*;
+mod hotkeys;
+use hotkeys::*;
 
 use graphics_server::*;
 use xous_ipc::{Buffer, String};
 use api::Opcode;
 use xous::{msg_scalar_unpack, msg_blocking_scalar_unpack};
-use gam::{MAIN_MENU_NAME, ROOTKEY_MODAL_NAME};
+use gam::{MAIN_MENU_NAME, ROOTKEY_MODAL_NAME, PDDB_MODAL_NAME};
 
 use log::info;
+use locales::t;
 use std::collections::HashMap;
 use num_traits::*;
-use core::sync::atomic::{AtomicU32, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 
 /// This sets the initial app focus on boot
 const INITIAL_APP_FOCUS: &'static str = gam::APP_NAME_SHELLCHAT;
 
 static CB_TO_MAIN_CONN: AtomicU32 = AtomicU32::new(0);
+/// the key pressed on the GAM's toast to allow a screenshot
+const SCREENSHOT_CONFIRM_KEY: char = 'y';
+/// how long the app under the screenshot toast is given to redraw what it covered, before the capture
+const SCREENSHOT_SETTLE_MS: usize = 500;
 fn imef_cb(s: String::<4000>) {
     if CB_TO_MAIN_CONN.load(Ordering::Relaxed) != 0 {
         let cb_to_main_conn = CB_TO_MAIN_CONN.load(Ordering::Relaxed);
@@ -35,6 +48,95 @@ fn imef_cb(s: String::<4000>) {
     }
 }
 
+/// Set while the user has asked for large text. Text views on every canvas but the status bar are then drawn
+/// in the `GlyphStyle::enlarged()` version of their styles, and glyph heights are reported to match.
+pub(crate) static LARGE_TEXT: AtomicBool = AtomicBool::new(false);
+/// The height of `style` as it's drawn right now, for laying out canvases around text
+pub(crate) fn glyph_height_hint(gfx: &graphics_server::Gfx, style: GlyphStyle) -> usize {
+    let style = if LARGE_TEXT.load(Ordering::Relaxed) { style.enlarged() } else { style };
+    gfx.glyph_height_hint(style).expect("couldn't get glyph height")
+}
+
+/// Points the graphics server at its offscreen buffer if `canvas` is being composed offscreen.
+/// Every call must be paired with `end_canvas_draw()`.
+fn begin_canvas_draw(gfx: &graphics_server::Gfx, canvas: &Canvas) {
+    if canvas.is_composing() {
+        gfx.set_draw_target(DrawTarget::Offscreen).expect("couldn't select offscreen draw target");
+    }
+}
+fn end_canvas_draw(gfx: &graphics_server::Gfx, canvas: &Canvas) {
+    if canvas.is_composing() {
+        gfx.set_draw_target(DrawTarget::Screen).expect("couldn't restore screen draw target");
+    }
+}
+
+/// Suspends the system, and tells the apps once it has woken up again
+/// A screenshot waiting on the user. The request is held until it's answered, and the caller with it.
+struct PendingScreenshot {
+    msg: xous::MessageEnvelope,
+    /// the app token of the caller
+    token: [u32; 4],
+    /// the toast asking the user
+    toast: usize,
+    /// set once the user has pressed the key
+    confirmed: bool,
+}
+
+/// Why a screenshot for the app holding `token` isn't allowed right now, if it isn't: only the app the user
+/// is looking at may capture the screen, and never while a modal or menu has focus, as it may be taking a password.
+fn screenshot_refusal(context_mgr: &ContextManager, token: [u32; 4]) -> Option<&'static str> {
+    let focus = context_mgr.focused_app();
+    let entering_password = [ROOTKEY_MODAL_NAME, PDDB_MODAL_NAME].iter()
+        .any(|name| focus.is_some() && context_mgr.find_app_token_by_name(name) == focus);
+    if entering_password || context_mgr.alert_has_focus() {
+        Some("a modal has focus")
+    } else if focus != Some(token) || !context_mgr.is_token_valid(token) {
+        Some("the caller doesn't have focus")
+    } else {
+        None
+    }
+}
+
+/// Answers a `Screenshot` request with a capture of the screen, or with `refusal`. The answer goes back to the
+/// caller once `msg` is dropped.
+fn answer_screenshot(gfx: &graphics_server::Gfx, msg: &mut xous::MessageEnvelope, refusal: Option<xous::Error>) {
+    let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+    let code = match refusal {
+        Some(e) => e,
+        None => match gfx.screenshot(&mut buffer[16..]) {
+            Ok(row_bytes) => {
+                buffer[4..8].copy_from_slice(&(row_bytes as u32).to_le_bytes());
+                xous::Error::NoError
+            }
+            Err(e) => e,
+        },
+    };
+    buffer[0..4].copy_from_slice(&(code as u32).to_le_bytes());
+}
+
+/// Unpacks a display setting sent with `Gam::send_display_setting()`: the status bar's token, then the value.
+/// Display settings are system-wide, so the value is only handed back if the token is the status bar's; the
+/// result code for the caller goes into the first word of `buffer` either way.
+fn status_bar_setting(context_mgr: &ContextManager, buffer: &mut [u8]) -> Option<usize> {
+    let mut token = [0u32; 4];
+    for (word, src) in token.iter_mut().zip(buffer[..16].chunks_exact(4)) {
+        *word = u32::from_le_bytes([src[0], src[1], src[2], src[3]]);
+    }
+    let value = u32::from_le_bytes([buffer[16], buffer[17], buffer[18], buffer[19]]) as usize;
+    let (code, value) = if context_mgr.find_app_token_by_name(gam::STATUS_BAR_NAME) == Some(token) {
+        (xous::Error::NoError, Some(value))
+    } else {
+        (xous::Error::AccessDenied, None)
+    };
+    buffer[0..4].copy_from_slice(&(code as u32).to_le_bytes());
+    value
+}
+
+fn suspend_until_resume(susres: &mut susres::Susres, context_mgr: &ContextManager, token: usize) {
+    susres.suspend_until_resume(token).expect("couldn't execute suspend/resume");
+    context_mgr.notify_lifecycle(gam::LifecycleEvent::Resumed);
+}
+
 #[xous::xous_main]
 fn xmain() -> ! {
     log_server::init_wait().unwrap();
@@ -53,6 +155,16 @@ fn xmain() -> ! {
     let trng = trng::Trng::new(&xns).expect("can't connect to TRNG");
 
     let mut context_mgr = ContextManager::new(&xns);
+    // suspends are passed on to the apps; going early gives them a head start on saving their state
+    let mut susres = susres::Susres::new(Some(susres::SuspendOrder::Early), &xns,
+        Opcode::SuspendResume as u32, CB_TO_MAIN_CONN.load(Ordering::Relaxed)).expect("couldn't create suspend/resume object");
+    // a suspend that's waiting for the apps to acknowledge AboutToSuspend: the susres token, and the timeout
+    let mut pending_suspend: Option<(usize, u32)> = None;
+    let mut suspend_waiting: Vec<[u32; 4]> = Vec::new();
+    let mut toasts = Toasts::new(&trng);
+    let mut status_slots = StatusSlots::new();
+    let mut hotkeys = Hotkeys::new();
+    let mut pending_screenshot: Option<PendingScreenshot> = None;
 
     // a map of canvases accessable by Gid
     let mut canvases: HashMap<Gid, Canvas> = HashMap::new();
@@ -65,10 +177,10 @@ fn xmain() -> ! {
             gfx.glyph_height_hint(GlyphStyle::Cjk).expect("couldn't get glyph height") as i16 * 2),
         255, &trng, None, crate::api::CanvasType::Status
     ).expect("couldn't create status canvas");
-    let status_cliprect = status_canvas.clip_rect();
     status_canvas.set_onscreen(true);
     status_canvas.set_drawable(true);
     let status_gid = status_canvas.gid().gid();
+    context_mgr.set_status_canvas(status_canvas.gid());
     canvases.insert(status_canvas.gid(), status_canvas);
     recompute_canvases(&canvases);
 
@@ -103,6 +215,7 @@ fn xmain() -> ! {
         log::info!("   ^C in the console window (this window) to quit");
         log::info!("********************************************************************************");
     }
+    watchdog::watch_server("gam", 5000, gam_sid, Opcode::WatchdogPing as usize);
     loop {
         let mut msg = xous::receive_message(gam_sid).unwrap();
         log::trace!("Message: {:?}", msg);
@@ -114,7 +227,10 @@ fn xmain() -> ! {
                         Some(c) => {
                             let mut rect = c.clip_rect();
                             rect.style = DrawStyle {fill_color: Some(PixelColor::Light), stroke_color: None, stroke_width: 0,};
+                            begin_canvas_draw(&gfx, c);
                             gfx.draw_rectangle(rect).expect("can't clear canvas");
+                            end_canvas_draw(&gfx, c);
+                            c.add_damage(rect);
                         },
                         None => info!("attempt to clear bogus canvas, ignored."),
                     }
@@ -140,6 +256,7 @@ fn xmain() -> ! {
             Some(Opcode::PowerDownRequest) => {
                 msg_blocking_scalar_unpack!(msg, _, _, _, _, {
                     powerdown_requested = true;
+                    context_mgr.notify_lifecycle(gam::LifecycleEvent::ShuttingDown);
                     gfx.draw_sleepscreen().expect("couldn't draw sleep screen");
                     // a screen flush is part of the draw_sleepscreen abstraction
                     xous::return_scalar(msg.sender, 1).expect("couldn't confirm power down UI request");
@@ -148,6 +265,8 @@ fn xmain() -> ! {
             Some(Opcode::ShipModeBlankRequest) => {
                 msg_blocking_scalar_unpack!(msg, _, _, _, _, {
                     powerdown_requested = true;
+                    context_mgr.notify_lifecycle(gam::LifecycleEvent::ShuttingDown);
+                    let screensize = gfx.screen_size().expect("Couldn't get screen size");
                     gfx.draw_rectangle(Rectangle::new_with_style(Point::new(0,0), screensize, DrawStyle::new(PixelColor::Light, PixelColor::Light, 0))).expect("couldn't clear screen");
                     gfx.flush().expect("couldn't refresh blank screen");
                     xous::return_scalar(msg.sender, 1).expect("couldn't confirm power down UI request");
@@ -171,6 +290,7 @@ fn xmain() -> ! {
                             context_mgr.redraw().expect("couldn't redraw after defacement");
                         }
                         log::trace!("flushing...");
+                        toasts.redraw(&gfx, context_mgr.alert_has_focus());
                         gfx.flush().expect("couldn't flush buffer to screen");
 
                         for (_, c) in canvases.iter_mut() {
@@ -179,6 +299,192 @@ fn xmain() -> ! {
                     }
                 })
             }
+            Some(Opcode::FlushRegion) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let req = buffer.to_original::<FlushRegionRequest, _>().unwrap();
+                if powerdown_requested {
+                    continue;
+                }
+                if deface(&gfx, &trng, &mut canvases) {
+                    // a layout change is pending; a partial flush could leave stale content on screen, so do it all
+                    context_mgr.redraw().expect("couldn't redraw after defacement");
+                    toasts.redraw(&gfx, context_mgr.alert_has_focus());
+                    gfx.flush().expect("couldn't flush buffer to screen");
+                    for (_, c) in canvases.iter_mut() {
+                        c.do_flushed().expect("couldn't update flushed state");
+                    }
+                    continue;
+                }
+                if let Some(canvas) = canvases.get(&req.canvas) {
+                    if canvas.is_drawable() && canvas.is_onscreen() {
+                        let region = match req.region {
+                            Some(mut r) => {
+                                r.translate(canvas.clip_rect().tl);
+                                r.translate(canvas.pan_offset());
+                                // only the part of the request that was actually drawn needs to go out
+                                canvas.damage().and_then(|d| d.clip_with(r))
+                            }
+                            None => canvas.damage(),
+                        };
+                        if let Some(r) = region {
+                            log::trace!("flush region {:?}", r);
+                            let r = toasts.cover(&gfx, r, context_mgr.alert_has_focus());
+                            gfx.flush_region(r).expect("couldn't flush region");
+                            if req.region.is_none() {
+                                canvas.clear_damage();
+                            }
+                        }
+                    }
+                } else {
+                    info!("bogus GID in FlushRegion, ignoring.");
+                }
+            }
+            Some(Opcode::ShowToast) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let request = buffer.to_original::<ToastRequest, _>().unwrap();
+                if powerdown_requested {
+                    continue;
+                }
+                // the new toast takes the place of the one asking for a screenshot, which can't be answered any more
+                if pending_screenshot.as_ref().map_or(false, |p| !p.confirmed && toasts.is_showing(p.toast)) {
+                    let mut pending = pending_screenshot.take().unwrap();
+                    answer_screenshot(&gfx, &mut pending.msg, Some(xous::Error::AccessDenied));
+                }
+                // only a context holding a token can have a toast take a key from the focused app
+                let trusted = request.action.map_or(false, |a| context_mgr.is_token_valid(a.token));
+                if toasts.show(&gfx, request, trusted, context_mgr.alert_has_focus(), CB_TO_MAIN_CONN.load(Ordering::Relaxed)) {
+                    context_mgr.redraw().unwrap_or_else(|e| log::debug!("no app to redraw under toast: {:?}", e));
+                }
+            }
+            Some(Opcode::ToastExpired) => msg_scalar_unpack!(msg, id, _, _, _, {
+                if pending_screenshot.as_ref().map_or(false, |p| p.toast == id && !p.confirmed) {
+                    log::info!("screenshot not allowed in time");
+                    let mut pending = pending_screenshot.take().unwrap();
+                    answer_screenshot(&gfx, &mut pending.msg, Some(xous::Error::AccessDenied));
+                }
+                if toasts.expire(&gfx, id) {
+                    context_mgr.redraw().unwrap_or_else(|e| log::debug!("no app to redraw under toast: {:?}", e));
+                }
+            }),
+            Some(Opcode::RegisterAnimation) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let registration = buffer.to_original::<AnimationRegistration, _>().unwrap();
+                if let Err(e) = context_mgr.register_animation(&registration) {
+                    log::warn!("couldn't register animation: {:?}", e);
+                }
+            }
+            Some(Opcode::AnimationFrame) => msg_blocking_scalar_unpack!(msg, generation, _, _, _, {
+                if !powerdown_requested {
+                    context_mgr.animation_frame(generation);
+                }
+                xous::return_scalar(msg.sender, 1).expect("couldn't ack animation frame");
+            }),
+            Some(Opcode::ClaimStatusSlot) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let mut claim = buffer.to_original::<StatusSlotClaim, _>().unwrap();
+                claim.token = status_slots.claim(&trng, msg.sender.pid(), claim.name, claim.priority);
+                buffer.replace(claim).unwrap();
+            }
+            Some(Opcode::SetStatusSlot) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let update = buffer.to_original::<StatusSlotUpdate, _>().unwrap();
+                if let Err(e) = status_slots.set(update.token, update.text) {
+                    log::warn!("status slot update with an unknown token: {:?}", e);
+                }
+            }
+            Some(Opcode::ReleaseStatusSlot) => msg_scalar_unpack!(msg, t0, t1, t2, t3, {
+                if let Err(e) = status_slots.release([t0 as u32, t1 as u32, t2 as u32, t3 as u32]) {
+                    log::warn!("status slot release with an unknown token: {:?}", e);
+                }
+            }),
+            Some(Opcode::GetStatusSlots) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let mut list = buffer.to_original::<StatusSlotList, _>().unwrap();
+                if context_mgr.find_app_token_by_name(gam::STATUS_BAR_NAME) == Some(list.token) {
+                    status_slots.visible(&mut list);
+                } else {
+                    log::warn!("status slots requested by something other than the status bar");
+                    list.count = 0;
+                }
+                buffer.replace(list).unwrap();
+            }
+            Some(Opcode::BeginOffscreen) => msg_scalar_unpack!(msg, g0, g1, g2, g3, {
+                let gid = Gid::new([g0 as _, g1 as _, g2 as _, g3 as _]);
+                match canvases.get(&gid) {
+                    Some(c) => {
+                        if c.is_drawable() && c.is_onscreen() {
+                            gfx.offscreen_begin(c.clip_rect()).expect("couldn't start offscreen composition");
+                            c.set_composing(true);
+                        } else {
+                            log::debug!("attempt to compose a non-drawable canvas offscreen, ignored.");
+                        }
+                    }
+                    None => info!("attempt to compose bogus canvas offscreen, ignored."),
+                }
+            }),
+            Some(Opcode::SwapOffscreen) => msg_scalar_unpack!(msg, g0, g1, g2, g3, {
+                let gid = Gid::new([g0 as _, g1 as _, g2 as _, g3 as _]);
+                match canvases.get(&gid) {
+                    Some(c) => {
+                        if c.is_composing() {
+                            c.set_composing(false);
+                            // a layout change since the begin would have cleared the flag, so the clip_rect still matches the back buffer
+                            if c.is_drawable() && c.is_onscreen() {
+                                gfx.offscreen_swap(c.clip_rect()).expect("couldn't swap offscreen buffer");
+                                c.add_damage(c.clip_rect());
+                                c.do_drawn().expect("couldn't set canvas to drawn");
+                            }
+                        }
+                    }
+                    None => info!("attempt to swap bogus canvas, ignored."),
+                }
+            }),
+            Some(Opcode::LoadGlyphSet) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let slot = u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize;
+                // trim the page padding off before forwarding, so the copy to the graphics server is no bigger than it needs to be
+                let result = match graphics_server::api::GlyphSetLayout::parse(&buffer[4..]) {
+                    Ok(layout) => gfx.load_glyph_set(slot, &buffer[4..4 + layout.total_len]),
+                    Err(e) => Err(e),
+                };
+                let code = match result {
+                    Ok(_) => xous::Error::NoError,
+                    Err(e) => e,
+                };
+                buffer[0..4].copy_from_slice(&(code as u32).to_le_bytes());
+            }
+            Some(Opcode::UnloadGlyphSet) => msg_scalar_unpack!(msg, slot, _, _, _, {
+                gfx.unload_glyph_set(slot).expect("couldn't unload glyph set");
+            }),
+            Some(Opcode::LoadHyphenation) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let mut token = [0u32; 4];
+                for (word, src) in token.iter_mut().zip(buffer[..16].chunks_exact(4)) {
+                    *word = u32::from_le_bytes([src[0], src[1], src[2], src[3]]);
+                }
+                let len = u32::from_le_bytes([buffer[16], buffer[17], buffer[18], buffer[19]]) as usize;
+                let result = if context_mgr.find_app_token_by_name(gam::STATUS_BAR_NAME) != Some(token) {
+                    log::warn!("hyphenation dictionary offered by something other than the status bar");
+                    Err(xous::Error::AccessDenied)
+                } else if len > graphics_server::api::HYPHENATION_MAX_LEN || len + 20 > buffer.len() {
+                    Err(xous::Error::OutOfMemory)
+                } else {
+                    gfx.load_hyphenation(&buffer[20..20 + len])
+                };
+                let code = match result {
+                    Ok(_) => xous::Error::NoError,
+                    Err(e) => e,
+                };
+                buffer[0..4].copy_from_slice(&(code as u32).to_le_bytes());
+            }
+            Some(Opcode::UnloadHyphenation) => msg_scalar_unpack!(msg, t0, t1, t2, t3, {
+                let token = [t0 as u32, t1 as u32, t2 as u32, t3 as u32];
+                if context_mgr.find_app_token_by_name(gam::STATUS_BAR_NAME) == Some(token) {
+                    gfx.unload_hyphenation().expect("couldn't unload hyphenation dictionary");
+                } else {
+                    log::warn!("hyphenation unload requested by something other than the status bar");
+                }
+            }),
             Some(Opcode::RenderTextView) => {
                 let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
                 let mut tv = buffer.to_original::<TextView, _>().unwrap();
@@ -210,17 +516,30 @@ fn xmain() -> ! {
                                 // set the clip rectangle according to the canvas' location
                                 let base_clip_rect = canvas.clip_rect();
                                 tv.clip_rect = Some(base_clip_rect.into());
+                                // text that falls outside a pushed clip region is laid out as usual, but not painted
+                                tv.draw_clip = canvas.draw_clip();
+                                if tv.draw_clip.is_none() {
+                                    tv.set_dry_run(true);
+                                }
 
                                 // you have to clone the tv object, because if you don't the same block of
                                 // memory gets passed on to the graphics_server(). Which is efficient, but,
                                 // the call will automatically Drop() the memory, which causes a panic when
                                 // this routine returns.
                                 let mut tv_clone = tv.clone();
+                                if LARGE_TEXT.load(Ordering::Relaxed) && canvas.canvas_type() != CanvasType::Status {
+                                    tv_clone.enlarge();
+                                }
                                 // issue the draw command
+                                begin_canvas_draw(&gfx, canvas);
                                 gfx.draw_textview(&mut tv_clone).expect("text view draw could not complete.");
+                                end_canvas_draw(&gfx, canvas);
                                 // copy back the fields that we want to be mutable
                                 tv.cursor = tv_clone.cursor;
                                 tv.bounds_computed = tv_clone.bounds_computed;
+                                tv.overflow = tv_clone.overflow;
+                                // bounds come back from the graphics server in screen coordinates
+                                canvas.add_damage(tv_clone.bounds_computed.unwrap_or(base_clip_rect));
 
                                 let ret = api::Return::RenderReturn(tv);
                                 buffer.replace(ret).unwrap();
@@ -255,12 +574,17 @@ fn xmain() -> ! {
                             }
                         }
                         let mut tv_clone = tv.clone();
+                        if LARGE_TEXT.load(Ordering::Relaxed)
+                        && canvases.get(&tv.get_canvas_gid()).map(|c| c.canvas_type()) != Some(CanvasType::Status) {
+                            tv_clone.enlarge();
+                        }
                         // issue the draw command
                         gfx.draw_textview(&mut tv_clone).expect("text view draw could not complete.");
                         // copy back the fields that we want to be mutable
                         log::trace!("got computed cursor of {:?}, bounds {:?}", tv_clone.cursor, tv_clone.bounds_computed);
                         tv.cursor = tv_clone.cursor;
                         tv.bounds_computed = tv_clone.bounds_computed;
+                        tv.overflow = tv_clone.overflow;
 
                         let ret = api::Return::RenderReturn(tv);
                         buffer.replace(ret).unwrap();
@@ -272,6 +596,8 @@ fn xmain() -> ! {
                 let mut cb = buffer.to_original::<SetCanvasBoundsRequest, _>().unwrap();
                 log::trace!("SetCanvasBoundsRequest {:?}", cb);
 
+                // the status bar changes width with the screen, so always take its current bounds
+                let status_cliprect = canvases.get(&Gid::new(status_gid)).expect("status canvas is missing").clip_rect();
                 let granted = if cb.token_type == TokenType::Gam {
                     context_mgr.set_canvas_height(&gfx, cb.token, cb.requested.y, &status_cliprect, &mut canvases)
                 } else {
@@ -297,6 +623,42 @@ fn xmain() -> ! {
                 let ret = api::Return::ContentCanvasReturn(context_mgr.get_content_canvas(req));
                 buffer.replace(ret).unwrap();
             }
+            Some(Opcode::RegisterTabs) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let mut registration = buffer.to_original::<TabRegistration, _>().unwrap();
+                registration.gids = context_mgr.register_tabs(&trng, &mut canvases, &registration);
+                if registration.gids.is_some() {
+                    recompute_canvases(&canvases);
+                }
+                buffer.replace(registration).unwrap();
+            }
+            Some(Opcode::SwitchTab) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let switch = buffer.to_original::<TabSwitch, _>().unwrap();
+                if let Err(e) = context_mgr.switch_tab(&gfx, &mut canvases, switch.token, switch.index) {
+                    log::warn!("couldn't switch to tab {}: {:?}", switch.index, e);
+                }
+            }
+            Some(Opcode::PushClip) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let mut req = buffer.to_original::<PushClipRequest, _>().unwrap();
+                req.granted = if let Some(canvas) = canvases.get_mut(&req.canvas) {
+                    canvas.push_clip(req.region)
+                } else {
+                    info!("bogus GID in PushClip, ignoring.");
+                    false
+                };
+                buffer.replace(req).unwrap();
+            }
+            Some(Opcode::PopClip) => msg_scalar_unpack!(msg, g0, g1, g2, g3, {
+                if let Some(canvas) = canvases.get_mut(&Gid::new([g0 as _, g1 as _, g2 as _, g3 as _])) {
+                    if !canvas.pop_clip() {
+                        log::warn!("PopClip without a matching PushClip, ignoring.");
+                    }
+                } else {
+                    info!("bogus GID in PopClip, ignoring.");
+                }
+            }),
             Some(Opcode::RenderObject) => {
                 let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
                 let obj = buffer.to_original::<GamObject, _>().unwrap();
@@ -304,40 +666,60 @@ fn xmain() -> ! {
                 if let Some(canvas) = canvases.get_mut(&obj.canvas) {
                     // first, figure out if we should even be drawing to this canvas.
                     if canvas.is_drawable() && canvas.is_onscreen() {
+                        // nothing to draw if the app has pushed a clip region that's entirely off the canvas
+                        let clip = match canvas.draw_clip() {
+                            Some(clip) => clip,
+                            None => continue,
+                        };
+                        begin_canvas_draw(&gfx, canvas);
                         match obj.obj {
                             GamObjectType::Line(mut line) => {
                                 line.translate(canvas.clip_rect().tl);
                                 line.translate(canvas.pan_offset());
                                 gfx.draw_line_clipped(
                                     line,
-                                    canvas.clip_rect(),
+                                    clip,
                                 ).expect("couldn't draw line");
+                                canvas.add_damage(line.bounds());
                             },
                             GamObjectType::Circ(mut circ) => {
                                 circ.translate(canvas.clip_rect().tl);
                                 circ.translate(canvas.pan_offset());
                                 gfx.draw_circle_clipped(
                                     circ,
-                                    canvas.clip_rect(),
+                                    clip,
                                 ).expect("couldn't draw circle");
+                                canvas.add_damage(circ.bounds());
                             },
                             GamObjectType::Rect(mut rect) => {
                                 rect.translate(canvas.clip_rect().tl);
                                 rect.translate(canvas.pan_offset());
                                 gfx.draw_rectangle_clipped(
                                     rect,
-                                    canvas.clip_rect(),
+                                    clip,
                                 ).expect("couldn't draw rectangle");
+                                canvas.add_damage(rect);
                             },
                             GamObjectType::RoundRect(mut rr) => {
                                 rr.translate(canvas.clip_rect().tl);
                                 rr.translate(canvas.pan_offset());
                                 gfx.draw_rounded_rectangle_clipped(
                                     rr,
-                                    canvas.clip_rect(),
+                                    clip,
                                 ).expect("couldn't draw rounded rectangle");
+                                canvas.add_damage(rr.border);
+                            }
+                            GamObjectType::Arc(mut arc) => {
+                                arc.translate(canvas.clip_rect().tl);
+                                arc.translate(canvas.pan_offset());
+                                gfx.draw_arc_clipped(
+                                    arc,
+                                    clip,
+                                ).expect("couldn't draw arc");
+                                canvas.add_damage(arc.bounds());
                             }
                         }
+                        end_canvas_draw(&gfx, canvas);
                         canvas.do_drawn().expect("couldn't set canvas to drawn");
                     } else {
                         log::debug!("attempt to draw Object on non-drawable canvas. Not fatal, but request ignored: {:?}", obj);
@@ -347,12 +729,73 @@ fn xmain() -> ! {
                 }
                 log::trace!("leaving RenderObject");
             }
+            Some(Opcode::RenderBitmap) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let mut bm = buffer.to_original::<Bitmap, _>().unwrap();
+                if !bm.is_valid() {
+                    info!("malformed Bitmap, not doing anything in response to draw request.");
+                    continue;
+                }
+                if let Some(canvas) = canvases.get_mut(&bm.get_canvas_gid()) {
+                    if canvas.is_drawable() && canvas.is_onscreen() {
+                        // nothing to draw if the app has pushed a clip region that's entirely off the canvas
+                        let clip = match canvas.draw_clip() {
+                            Some(clip) => clip,
+                            None => continue,
+                        };
+                        bm.translate(canvas.clip_rect().tl);
+                        bm.translate(canvas.pan_offset());
+                        bm.clip_rect = Some(clip);
+                        begin_canvas_draw(&gfx, canvas);
+                        gfx.draw_bitmap(&bm).expect("couldn't draw bitmap");
+                        end_canvas_draw(&gfx, canvas);
+                        canvas.add_damage(bm.bounds());
+                        canvas.do_drawn().expect("couldn't set canvas to drawn");
+                    } else {
+                        log::debug!("attempt to draw Bitmap on non-drawable canvas. Not fatal, but request ignored.");
+                    }
+                } else {
+                    info!("bogus GID in Bitmap, not doing anything in response to draw request.");
+                }
+            }
+            Some(Opcode::RenderPolygon) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let mut poly = buffer.to_original::<Polygon, _>().unwrap();
+                if let Some(canvas) = canvases.get_mut(&poly.get_canvas_gid()) {
+                    if canvas.is_drawable() && canvas.is_onscreen() {
+                        // nothing to draw if the app has pushed a clip region that's entirely off the canvas
+                        let clip = match canvas.draw_clip() {
+                            Some(clip) => clip,
+                            None => continue,
+                        };
+                        poly.translate(canvas.clip_rect().tl);
+                        poly.translate(canvas.pan_offset());
+                        poly.clip_rect = Some(clip);
+                        begin_canvas_draw(&gfx, canvas);
+                        gfx.draw_polygon(&poly).expect("couldn't draw polygon");
+                        end_canvas_draw(&gfx, canvas);
+                        if let Some(bounds) = poly.bounds() {
+                            canvas.add_damage(bounds);
+                        }
+                        canvas.do_drawn().expect("couldn't set canvas to drawn");
+                    } else {
+                        log::debug!("attempt to draw Polygon on non-drawable canvas. Not fatal, but request ignored.");
+                    }
+                } else {
+                    info!("bogus GID in Polygon, not doing anything in response to draw request.");
+                }
+            }
             Some(Opcode::RenderObjectList) => {
                 let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
                 let obj_ipc = buffer.to_original::<GamObjectList, _>().unwrap();
                 if let Some(canvas) = canvases.get_mut(&obj_ipc.canvas) {
                     // first, figure out if we should even be drawing to this canvas.
                     if canvas.is_drawable() && canvas.is_onscreen() {
+                        // nothing to draw if the app has pushed a clip region that's entirely off the canvas
+                        let clip = match canvas.draw_clip() {
+                            Some(clip) => clip,
+                            None => continue,
+                        };
                         let mut obj_list = ClipObjectList::default();
                         for item in obj_ipc.list.iter() {
                             if let Some(obj) = item {
@@ -360,29 +803,41 @@ fn xmain() -> ! {
                                     GamObjectType::Line(mut line) => {
                                         line.translate(canvas.clip_rect().tl);
                                         line.translate(canvas.pan_offset());
-                                        obj_list.push(ClipObjectType::Line(line), canvas.clip_rect()).unwrap();
+                                        obj_list.push(ClipObjectType::Line(line), clip).unwrap();
+                                        canvas.add_damage(line.bounds());
                                     },
                                     GamObjectType::Circ(mut circ) => {
                                         circ.translate(canvas.clip_rect().tl);
                                         circ.translate(canvas.pan_offset());
-                                        obj_list.push(ClipObjectType::Circ(circ), canvas.clip_rect()).unwrap();
+                                        obj_list.push(ClipObjectType::Circ(circ), clip).unwrap();
+                                        canvas.add_damage(circ.bounds());
                                     },
                                     GamObjectType::Rect(mut rect) => {
                                         rect.translate(canvas.clip_rect().tl);
                                         rect.translate(canvas.pan_offset());
-                                        obj_list.push(ClipObjectType::Rect(rect), canvas.clip_rect()).unwrap();
+                                        obj_list.push(ClipObjectType::Rect(rect), clip).unwrap();
+                                        canvas.add_damage(rect);
                                     },
                                     GamObjectType::RoundRect(mut rr) => {
                                         rr.translate(canvas.clip_rect().tl);
                                         rr.translate(canvas.pan_offset());
-                                        obj_list.push(ClipObjectType::RoundRect(rr), canvas.clip_rect()).unwrap();
+                                        obj_list.push(ClipObjectType::RoundRect(rr), clip).unwrap();
+                                        canvas.add_damage(rr.border);
+                                    }
+                                    GamObjectType::Arc(mut arc) => {
+                                        arc.translate(canvas.clip_rect().tl);
+                                        arc.translate(canvas.pan_offset());
+                                        obj_list.push(ClipObjectType::Arc(arc), clip).unwrap();
+                                        canvas.add_damage(arc.bounds());
                                     }
                                 }
                             } else {
                                 break;
                             }
                         }
+                        begin_canvas_draw(&gfx, canvas);
                         gfx.draw_object_list_clipped(obj_list).expect("couldn't draw object list");
+                        end_canvas_draw(&gfx, canvas);
                         canvas.do_drawn().expect("couldn't set canvas to drawn");
                     } else {
                         log::debug!("attempt to draw Object on non-drawable canvas. Not fatal, but request ignored: {:?}", obj_ipc);
@@ -415,6 +870,7 @@ fn xmain() -> ! {
                 };
                 // note that we are currently assigning all Ux registrations a trust level consistent with a boot context (ultimately trusted)
                 // this needs to be modified later on once we allow post-boot apps to be created
+                let status_cliprect = canvases.get(&Gid::new(status_gid)).expect("status canvas is missing").clip_rect();
                 let token = context_mgr.register(&gfx, &trng, &status_cliprect, &mut canvases,
                     registration);
 
@@ -461,8 +917,52 @@ fn xmain() -> ! {
                     core::char::from_u32(k3 as u32).unwrap_or('\u{0000}'),
                     core::char::from_u32(k4 as u32).unwrap_or('\u{0000}'),
                 ];
+                if toasts.take_action(&gfx, &keys, context_mgr.alert_has_focus()) {
+                    context_mgr.redraw().unwrap_or_else(|e| log::debug!("no app to redraw under toast: {:?}", e));
+                    continue;
+                }
                 context_mgr.key_event(keys, &gfx, &mut canvases);
             }),
+            Some(Opcode::HotkeyEvent) => msg_scalar_unpack!(msg, k, _, _, _, {
+                let key = core::char::from_u32(k as u32).unwrap_or('\u{0000}');
+                if !hotkeys.dispatch(key) {
+                    let keys = [key, '\u{0000}', '\u{0000}', '\u{0000}'];
+                    if toasts.take_action(&gfx, &keys, context_mgr.alert_has_focus()) {
+                        context_mgr.redraw().unwrap_or_else(|e| log::debug!("no app to redraw under toast: {:?}", e));
+                        continue;
+                    }
+                    context_mgr.key_event(keys, &gfx, &mut canvases);
+                }
+            }),
+            Some(Opcode::KeyEventNotification) => msg_scalar_unpack!(msg, a1, a2, a3, _, {
+                context_mgr.key_event_notification([a1, a2, a3]);
+            }),
+            Some(Opcode::RegisterKeyEvents) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let registration = buffer.to_original::<KeyEventRegistration, _>().unwrap();
+                if let Err(e) = context_mgr.register_key_events(&registration) {
+                    log::warn!("couldn't register key events: {:?}", e);
+                }
+            }
+            Some(Opcode::RegisterHotkey) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let mut registration = buffer.to_original::<HotkeyRegistration, _>().unwrap();
+                registration.accepted = if context_mgr.is_token_valid(registration.token) {
+                    hotkeys.register(&registration)
+                } else {
+                    log::warn!("hot key registration with an invalid token");
+                    false
+                };
+                buffer.replace(registration).unwrap();
+            }
+            Some(Opcode::UnregisterHotkey) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let registration = buffer.to_original::<HotkeyRegistration, _>().unwrap();
+                let key = core::char::from_u32(registration.key).unwrap_or('\u{0000}');
+                if let Err(e) = hotkeys.unregister(key, registration.token) {
+                    log::warn!("hot key {:?} isn't registered to the app giving it up: {:?}", key, e);
+                }
+            }
             Some(Opcode::Vibe) => msg_scalar_unpack!(msg, ena, _,  _,  _, {
                 if ena != 0 { context_mgr.vibe(true) }
                 else { context_mgr.vibe(false) }
@@ -479,9 +979,21 @@ fn xmain() -> ! {
                 }
             },
             Some(Opcode::QueryGlyphProps) => msg_blocking_scalar_unpack!(msg, style, _, _, _, {
-                let height = gfx.glyph_height_hint(GlyphStyle::from(style)).expect("couldn't query glyph height from gfx");
+                let height = glyph_height_hint(&gfx, GlyphStyle::from(style));
                 xous::return_scalar(msg.sender, height).expect("could not return QueryGlyphProps request");
             }),
+            Some(Opcode::MeasureText) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let mut m = buffer.to_original::<TextMeasurement, _>().unwrap();
+                let style = m.style;
+                // measure text the way it will be drawn on a content canvas
+                if LARGE_TEXT.load(Ordering::Relaxed) {
+                    m.style = style.enlarged();
+                }
+                gfx.measure_text(&mut m).expect("couldn't measure text");
+                m.style = style;
+                buffer.replace(m).unwrap();
+            }
             Some(Opcode::RedrawIme) => {
                 context_mgr.redraw_imef().expect("couldn't redraw the IMEF");
             },
@@ -543,6 +1055,122 @@ fn xmain() -> ! {
                 if ena != 0 { gfx.set_devboot(true).expect("couldn't send devboot message"); }
                 else { gfx.set_devboot(false).expect("couldn't send devboot message"); }
             }),
+            Some(Opcode::SetInverted) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                match status_bar_setting(&context_mgr, &mut buffer) {
+                    Some(ena) => gfx.set_inverted(ena != 0).expect("couldn't set display inversion"),
+                    None => log::warn!("display inversion set by something other than the status bar"),
+                }
+            }
+            Some(Opcode::SetLargeText) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                match status_bar_setting(&context_mgr, &mut buffer) {
+                    Some(ena) => context_mgr.set_large_text(&gfx, &mut canvases, ena != 0),
+                    None => log::warn!("large text set by something other than the status bar"),
+                }
+            }
+            Some(Opcode::SetRotation) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                match status_bar_setting(&context_mgr, &mut buffer).map(|r| (r, FromPrimitive::from_usize(r))) {
+                    Some((_, Some(rotation))) => context_mgr.set_rotation(&gfx, &mut canvases, rotation),
+                    Some((r, None)) => log::error!("invalid rotation: {}", r),
+                    None => log::warn!("screen rotation set by something other than the status bar"),
+                }
+            }
+            Some(Opcode::OptOutOfRotation) => msg_scalar_unpack!(msg, t0, t1, t2, t3, {
+                if let Err(e) = context_mgr.opt_out_of_rotation(&gfx, &mut canvases, [t0 as u32, t1 as u32, t2 as u32, t3 as u32]) {
+                    log::warn!("rotation opt-out with an unknown token: {:?}", e);
+                }
+            }),
+            Some(Opcode::SuspendResume) => msg_scalar_unpack!(msg, token, _, _, _, {
+                suspend_waiting = context_mgr.notify_lifecycle(gam::LifecycleEvent::AboutToSuspend);
+                if suspend_waiting.is_empty() {
+                    suspend_until_resume(&mut susres, &context_mgr, token);
+                } else {
+                    // the apps get a moment to save their state: the suspend goes ahead when the last of them
+                    // acknowledges, or when the timer runs out
+                    match ticktimer.call_at(ticktimer.elapsed_ms() + gam::LIFECYCLE_ACK_TIMEOUT_MS,
+                        gam_sid, Opcode::LifecycleTimeout.to_usize().unwrap()) {
+                        Ok(timer) => pending_suspend = Some((token, timer)),
+                        Err(e) => {
+                            log::warn!("couldn't time the wait for the apps, suspending right away: {:?}", e);
+                            suspend_waiting.clear();
+                            suspend_until_resume(&mut susres, &context_mgr, token);
+                        }
+                    }
+                }
+            }),
+            Some(Opcode::LifecycleAck) => msg_scalar_unpack!(msg, t0, t1, t2, t3, {
+                let app_token = [t0 as u32, t1 as u32, t2 as u32, t3 as u32];
+                suspend_waiting.retain(|t| *t != app_token);
+                if suspend_waiting.is_empty() {
+                    if let Some((token, timer)) = pending_suspend.take() {
+                        ticktimer.cancel_call(timer);
+                        suspend_until_resume(&mut susres, &context_mgr, token);
+                    }
+                }
+            }),
+            Some(Opcode::LifecycleTimeout) => msg_scalar_unpack!(msg, handle, _, _, _, {
+                // a timeout that was cancelled may still arrive, so only act on the current one
+                if let Some((token, timer)) = pending_suspend {
+                    if timer as usize == handle {
+                        log::warn!("{} app(s) didn't acknowledge the suspend in time, suspending anyway", suspend_waiting.len());
+                        pending_suspend = None;
+                        suspend_waiting.clear();
+                        suspend_until_resume(&mut susres, &context_mgr, token);
+                    }
+                }
+            }),
+            Some(Opcode::Screenshot) => {
+                let mut token = [0u32; 4];
+                {
+                    let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                    for (word, src) in token.iter_mut().zip(buffer[..16].chunks_exact(4)) {
+                        *word = u32::from_le_bytes([src[0], src[1], src[2], src[3]]);
+                    }
+                }
+                if let Some(reason) = screenshot_refusal(&context_mgr, token) {
+                    log::warn!("screenshot refused: {}", reason);
+                    answer_screenshot(&gfx, &mut msg, Some(xous::Error::AccessDenied));
+                } else if pending_screenshot.is_some() || powerdown_requested {
+                    answer_screenshot(&gfx, &mut msg, Some(xous::Error::ServerQueueFull));
+                } else {
+                    // the user is asked by the GAM itself, so the app can't draw the question or answer it
+                    let (toast, replaced) = toasts.confirm(&gfx, t!("screenshot.confirm", xous::LANG),
+                        SCREENSHOT_CONFIRM_KEY, Opcode::ScreenshotConfirmed, false, CB_TO_MAIN_CONN.load(Ordering::Relaxed));
+                    if replaced {
+                        context_mgr.redraw().unwrap_or_else(|e| log::debug!("no app to redraw under toast: {:?}", e));
+                    }
+                    pending_screenshot = Some(PendingScreenshot { msg, token, toast, confirmed: false });
+                }
+            }
+            Some(Opcode::ScreenshotConfirmed) => msg_scalar_unpack!(msg, id, _, _, _, {
+                // the toast's action comes from the GAM itself, with the id of the toast that took the key
+                let ours = msg.sender.pid() == xous::current_pid().ok();
+                if let Some(pending) = pending_screenshot.as_mut().filter(|p| ours && p.toast == id && !p.confirmed) {
+                    pending.confirmed = true;
+                    let conn = CB_TO_MAIN_CONN.load(Ordering::Relaxed);
+                    std::thread::spawn(move || {
+                        let tt = ticktimer_server::Ticktimer::new().unwrap();
+                        tt.sleep_ms(SCREENSHOT_SETTLE_MS).unwrap();
+                        xous::send_message(conn,
+                            xous::Message::new_scalar(Opcode::ScreenshotCapture.to_usize().unwrap(), id, 0, 0, 0)
+                        ).expect("couldn't take the screenshot");
+                    });
+                }
+            }),
+            Some(Opcode::ScreenshotCapture) => msg_scalar_unpack!(msg, id, _, _, _, {
+                let ours = msg.sender.pid() == xous::current_pid().ok();
+                if pending_screenshot.as_ref().map_or(false, |p| ours && p.toast == id && p.confirmed) {
+                    let mut pending = pending_screenshot.take().unwrap();
+                    // focus may have moved while the user was being asked
+                    let refusal = screenshot_refusal(&context_mgr, pending.token).map(|reason| {
+                        log::warn!("screenshot refused: {}", reason);
+                        xous::Error::AccessDenied
+                    });
+                    answer_screenshot(&gfx, &mut pending.msg, refusal);
+                }
+            }),
             Some(Opcode::TestPattern) => msg_blocking_scalar_unpack!(msg, duration_ms, _, _, _, {
                 if !did_test {
                     did_test = true;
@@ -555,6 +1183,9 @@ fn xmain() -> ! {
                 }
                 xous::return_scalar(msg.sender, 1).expect("couldn't ack self test");
             }),
+            Some(Opcode::WatchdogPing) => {
+                xous::return_scalar(msg.sender, 1).expect("couldn't answer the watchdog");
+            }
             Some(Opcode::Quit) => break,
             None => {log::error!("unhandled message {:?}", msg);}
         }
@@ -8,6 +8,7 @@ pub use graphics_server::*;
 
 use xous_ipc::{String, Buffer};
 use num_traits::*;
+use std::collections::HashMap;
 
 use graphics_server::api::{PixelColor, TextBounds, DrawStyle, GlyphStyle};
 
@@ -28,6 +29,10 @@ pub struct Menu<'a> {
     pub canvas_width: Option<i16>,
     pub helper_data: Option<Buffer<'a>>,
     pub name: std::string::String,
+    /// item lists that can be opened from `MenuPayload::Submenu` items, by name
+    pub submenus: HashMap<std::string::String, Vec<MenuItem>>,
+    /// the items and selected index of each parent of the submenu being shown, outermost first
+    parents: Vec<(Vec<MenuItem>, usize)>,
     #[cfg(feature = "tts")]
     pub tts: TtsFrontend,
 }
@@ -39,6 +44,13 @@ pub enum MenuOpcode { // note: this should match ModalOpcode, for compatibility
     Quit,
 }
 
+/// Each item is one line of `Regular` text, with room for the emoji and CJK glyphs that menus also use
+fn line_height(gam: &Gam) -> i16 {
+    let cjk = gam.glyph_height_hint(GlyphStyle::Cjk).expect("couldn't get glyph height hint");
+    let regular = gam.glyph_height_hint(GlyphStyle::Regular).expect("couldn't get glyph height hint");
+    cjk.max(regular) as i16 + 2
+}
+
 impl<'a> Menu<'a> {
     pub fn new(name: &str) -> Menu {
         let xns = xous_names::XousNames::new().unwrap();
@@ -54,13 +66,14 @@ impl<'a> Menu<'a> {
                 gotinput_id: None,
                 audioframe_id: None,
                 focuschange_id: None,
+                lifecycle_id: None,
                 rawkeys_id: Some(MenuOpcode::Rawkeys.to_u32().unwrap()),
             }
         ).expect("couldn't register my Ux element with GAM");
         assert!(authtoken.is_some(), "Couldn't register menu. Did you remember to add the app_name to the tokens.rs expected boot contexts list?");
         log::debug!("requesting content canvas for menu");
         let canvas = gam.request_content_canvas(authtoken.unwrap()).expect("couldn't get my content canvas from GAM");
-        let line_height = gam.glyph_height_hint(GlyphStyle::Cjk).expect("couldn't get glyph height hint") as i16 + 2;
+        let line_height = line_height(&gam);
         #[cfg(feature="tts")]
         let tts = TtsFrontend::new(&xns).unwrap();
         Menu {
@@ -77,6 +90,8 @@ impl<'a> Menu<'a> {
             canvas_width: None,
             helper_data: None,
             name: std::string::String::from(name),
+            submenus: HashMap::new(),
+            parents: Vec::new(),
             #[cfg(feature="tts")]
             tts,
         }
@@ -115,29 +130,38 @@ impl<'a> Menu<'a> {
         // add the menu item to the first free slot
         // any modifications to the menu structure should guarantee that the list is compacted
         // and has no holes, in order for the "selected index" logic to work
+        self.return_to_root();
         self.items.push(new_item);
 
         // now, recompute the height
-        let mut total_items = self.num_items();
-        if total_items == 0 {
-            total_items = 1; // just so we see a blank menu at least, and have a clue how to debug
+        self.fit_to_items();
+    }
+    /// Adds an item to the submenu called `submenu`, creating the submenu if it doesn't exist yet. The submenu is
+    /// shown when a `MenuPayload::Submenu` item of the same name is selected; changes to a submenu that is
+    /// currently open show up the next time it's opened.
+    pub fn add_submenu_item(&mut self, submenu: &str, new_item: MenuItem) {
+        if new_item.name.as_str().unwrap() == "🔇" {
+            return;
         }
-        let current_bounds = self.gam.get_canvas_bounds(self.canvas).expect("couldn't get current bounds");
-        let mut new_bounds = SetCanvasBoundsRequest {
-            requested: Point::new(current_bounds.x, total_items as i16 * self.line_height + self.margin * 2),
-            granted: None,
-            token_type: TokenType::App,
-            token: self.authtoken,
-        };
-        log::debug!("add_item requesting bounds of {:?}", new_bounds);
-        self.gam.set_canvas_bounds_request(&mut new_bounds).expect("couldn't call set bounds");
+        self.submenus.entry(std::string::String::from(submenu)).or_insert_with(Vec::new).push(new_item);
     }
     // note: this routine has yet to be tested. (remove this comment once it has been actually used by something)
     pub fn delete_item(&mut self, item: &str) -> bool {
+        self.return_to_root();
         let len_before = self.items.len();
         self.items.retain(|&candidate| candidate.name.as_str().unwrap() != item);
 
         // now, recompute the height
+        self.fit_to_items();
+
+        if len_before > self.items.len() {
+            true
+        } else {
+            false
+        }
+    }
+    /// Asks the GAM to size the menu's canvas to the items currently shown
+    fn fit_to_items(&mut self) {
         let mut total_items = self.num_items();
         if total_items == 0 {
             total_items = 1; // just so we see a blank menu at least, and have a clue how to debug
@@ -149,13 +173,41 @@ impl<'a> Menu<'a> {
             token_type: TokenType::App,
             token: self.authtoken,
         };
-        log::debug!("add_item requesting bounds of {:?}", new_bounds);
+        log::debug!("menu requesting bounds of {:?}", new_bounds);
         self.gam.set_canvas_bounds_request(&mut new_bounds).expect("couldn't call set bounds");
-
-        if len_before > self.items.len() {
-            true
-        } else {
-            false
+    }
+    /// Shows the items of the submenu called `name` in place of the current ones
+    fn open_submenu(&mut self, name: &str) {
+        let items = match self.submenus.get(name) {
+            Some(items) if items.len() > 0 => items.clone(),
+            _ => {
+                log::error!("menu {} has no submenu called {}", self.name, name);
+                return;
+            }
+        };
+        let parent = std::mem::replace(&mut self.items, items);
+        self.parents.push((parent, self.index));
+        self.index = 0;
+        self.fit_to_items();
+        self.redraw();
+    }
+    /// Goes back to the parent of the submenu being shown, with the submenu's entry selected again.
+    /// Returns false if the top level menu is already showing.
+    fn close_submenu(&mut self) -> bool {
+        match self.parents.pop() {
+            Some((items, index)) => {
+                self.items = items;
+                self.index = index;
+                self.fit_to_items();
+                true
+            }
+            None => false,
+        }
+    }
+    fn return_to_root(&mut self) {
+        if self.parents.len() > 0 {
+            while self.close_submenu() {}
+            self.index = 0;
         }
     }
     pub fn draw_item(&self, index: i16, with_marker: bool) {
@@ -182,6 +234,7 @@ impl<'a> Menu<'a> {
         item_tv.style = GlyphStyle::Regular;
         item_tv.margin = Point::new(0, 0);
         item_tv.ellipsis = true;
+        item_tv.max_lines = Some(1);
 
         self.gam.post_textview(&mut item_tv).expect("couldn't render menu list item");
     }
@@ -254,6 +307,12 @@ impl<'a> Menu<'a> {
     pub fn redraw(&mut self) {
         // for now, just draw a black rectangle
         log::trace!("menu redraw");
+        // the text size can be changed while the menu exists, e.g. to large text
+        let line_height = line_height(&self.gam);
+        if line_height != self.line_height {
+            self.line_height = line_height;
+            self.fit_to_items();
+        }
         let canvas_size = self.gam.get_canvas_bounds(self.canvas).unwrap();
         self.canvas_width = Some(canvas_size.x);
 
@@ -289,6 +348,10 @@ impl<'a> Menu<'a> {
             match k {
                 '∴' => {
                     let mi = self.items[self.index];
+                    if let MenuPayload::Submenu(submenu) = mi.action_payload {
+                        self.open_submenu(submenu.as_str().unwrap());
+                        break;
+                    }
                     // give up focus before issuing the command, as some commands conflict with loss of focus...
                     if mi.close_on_select {
                         self.gam.relinquish_focus().unwrap();
@@ -311,16 +374,23 @@ impl<'a> Menu<'a> {
                             MenuPayload::Memory((_buf, _len)) => {
                                 unimplemented!("menu buffer targets are a future feature");
                             }
+                            MenuPayload::Submenu(_) => (), // handled above
                         }
                     }
+                    if mi.close_on_select {
+                        // the next time the menu comes up, it starts from the top level
+                        self.return_to_root();
+                    }
                     self.index = 0; // reset the index to 0
                     log::trace!("menu redraw## select key");
                     self.gam.redraw().unwrap();
                     break; // drop any characters that happened to trail the select key, it's probably a fat-finger error.
                 },
                 '←' => {
-                    // placeholder
                     log::trace!("got left arrow");
+                    if self.close_submenu() {
+                        self.redraw();
+                    }
                 }
                 '→' => {
                     // placeholder
@@ -381,6 +451,17 @@ impl MenuMatic {
             false
         }
     }
+    /// Adds an item to the named submenu; see `Menu::add_submenu_item()`
+    pub fn add_submenu_item(&self, submenu: &str, item: MenuItem) -> bool {
+        let mm = MenuManagement {
+            item,
+            op: MenuMgrOp::AddSubmenuItem(String::from_str(submenu)),
+        };
+        let mut buf = Buffer::into_buf(mm).expect("Couldn't convert to memory structure");
+        buf.lend_mut(self.cid, 0).expect("Couldn't issue management opcode");
+        let ret = buf.to_original::<MenuManagement, _>().unwrap();
+        ret.op == MenuMgrOp::Ok
+    }
     pub fn set_index(&self, index: usize) {
         let op = MenuManagement {
             item: MenuItem { // dummy item, not used
@@ -418,10 +499,25 @@ use std::sync::{Arc, Mutex};
 /// If you want to modify the menu, pass it a Some(xous::SID) which is the private server
 /// address of the management interface.
 pub fn menu_matic(items: Vec::<MenuItem>, menu_name: &'static str, maybe_manager: Option<xous::SID>) -> Option<MenuMatic> {
+    menu_matic_with_submenus(items, Vec::new(), menu_name, maybe_manager)
+}
+/// Like `menu_matic()`, but also sets up named submenus, which are opened by items that carry a
+/// `MenuPayload::Submenu` with the submenu's name.
+pub fn menu_matic_with_submenus(
+    items: Vec::<MenuItem>,
+    submenus: Vec::<(&str, Vec::<MenuItem>)>,
+    menu_name: &'static str,
+    maybe_manager: Option<xous::SID>
+) -> Option<MenuMatic> {
     let menu = Arc::new(Mutex::new(Menu::new(menu_name)));
     for item in items {
         menu.lock().unwrap().add_item(item);
     }
+    for (submenu, submenu_items) in submenus {
+        for item in submenu_items {
+            menu.lock().unwrap().add_submenu_item(submenu, item);
+        }
+    }
     let _ = thread::spawn({
         let menu = menu.clone();
         let sid = menu.lock().unwrap().sid.clone();
@@ -479,6 +575,11 @@ pub fn menu_matic(items: Vec::<MenuItem>, menu_name: &'static str, maybe_manager
                             }
                             buffer.replace(mgmt).unwrap();
                         }
+                        MenuMgrOp::AddSubmenuItem(submenu) => {
+                            menu.lock().unwrap().add_submenu_item(submenu.as_str().unwrap(), mgmt.item);
+                            mgmt.op = MenuMgrOp::Ok;
+                            buffer.replace(mgmt).unwrap();
+                        }
                         MenuMgrOp::SetIndex(index) => {
                             log::info!("setting menu index {}", index);
                             menu.lock().unwrap().set_index(index);
@@ -2,6 +2,8 @@
 
 mod textentry;
 pub use textentry::*;
+mod numericentry;
+pub use numericentry::*;
 mod radiobuttons;
 pub use radiobuttons::*;
 mod checkboxes;
@@ -29,10 +31,13 @@ use num_traits::*;
 use core::fmt::Write;
 
 pub const MAX_ITEMS: usize = 8;
+/// Number of items that can be reported by a check box list that returns a bitfield
+pub const MAX_CHECKBOX_BITS: usize = 32;
 
 #[enum_dispatch(ActionApi)]
 pub enum ActionType {
     TextEntry,
+    NumericEntry,
     RadioButtons,
     CheckBoxes,
     Slider,
@@ -142,6 +147,21 @@ impl CheckBoxPayload {
     }
 }
 
+/// Selection of a check box list, as a bitfield: bit `n` is set if the `n`th item added to the list is checked.
+/// Unlike `CheckBoxPayload`, any number of the items can be checked, and items with the same name are told apart.
+#[derive(Debug, Copy, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Eq, PartialEq)]
+pub struct CheckBoxBitfield(pub u32);
+impl CheckBoxBitfield {
+    pub fn is_set(&self, index: usize) -> bool {
+        index < MAX_CHECKBOX_BITS && (self.0 & (1 << index)) != 0
+    }
+    pub fn toggle(&mut self, index: usize) {
+        if index < MAX_CHECKBOX_BITS {
+            self.0 ^= 1 << index;
+        }
+    }
+}
+
 //#[derive(Debug)]
 pub struct Modal<'a> {
     pub sid: xous::SID,
@@ -169,7 +189,18 @@ pub struct Modal<'a> {
     bot_memoized_height: Option<i16>,
 }
 
+fn line_height(gam: &Gam, style: GlyphStyle) -> i16 {
+    if xous::LANG == "zh" {
+        // zh has no "small" style
+        gam.glyph_height_hint(GlyphStyle::Regular).expect("couldn't get glyph height hint") as i16
+    } else {
+        gam.glyph_height_hint(style).expect("couldn't get glyph height hint") as i16
+    }
+}
+
 fn recompute_canvas(modal: &mut Modal, top_text: Option<&str>, bot_text: Option<&str>, style: GlyphStyle) {
+    // the text size may have changed since the modal was made, e.g. to large text
+    modal.line_height = line_height(&modal.gam, style);
     // we need to set a "max" size to our modal box, so that the text computations don't fail later on
     let current_bounds = modal.gam.get_canvas_bounds(modal.canvas).expect("couldn't get current bounds");
 
@@ -280,18 +311,14 @@ impl<'a> Modal<'a> {
                 gotinput_id: None,
                 audioframe_id: None,
                 focuschange_id: None,
+                lifecycle_id: None,
                 rawkeys_id: Some(ModalOpcode::Rawkeys.to_u32().unwrap()),
             }
         ).expect("couldn't register my Ux element with GAM");
         assert!(authtoken.is_some(), "Couldn't register modal. Did you remember to add the app_name to the tokens.rs expected boot contexts list?");
         log::debug!("requesting content canvas for modal");
         let canvas = gam.request_content_canvas(authtoken.unwrap()).expect("couldn't get my content canvas from GAM");
-        let line_height = if xous::LANG == "zh" {
-            // zh has no "small" style
-            gam.glyph_height_hint(GlyphStyle::Regular).expect("couldn't get glyph height hint") as i16
-        } else {
-            gam.glyph_height_hint(style).expect("couldn't get glyph height hint") as i16
-        };
+        let line_height = line_height(&gam, style);
         let canvas_bounds = gam.get_canvas_bounds(canvas).expect("couldn't get starting canvas bounds");
 
         log::trace!("initializing Modal structure");
@@ -575,6 +602,7 @@ General form for modals:
  Action form can be exactly one of the following:
    - password text field - enter closes the form, has visibility options as left/right arrows; entered text wraps
    - regular text field - enter closes the form, visibility is always visible; entered text wraps
+   - numeric field - digits only, enter validates against min/max/length and closes the form; optionally masked
    - radio buttons - has an explicit "okay" button to close the modal; up/down arrows + select/enter pick the radio
    - check boxes - has an explicit "okay" button to close the modal; up/down arrows + select/enter checks boxes
    - slider - left/right moves the slider, enter/select closes the modal
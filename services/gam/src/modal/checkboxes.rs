@@ -15,6 +15,8 @@ pub struct CheckBoxes {
     pub action_conn: xous::CID,
     pub action_opcode: u32,
     pub action_payload: CheckBoxPayload,
+    /// when set, the selection is tracked and returned as a bitfield instead of `action_payload`
+    pub bitfield: Option<CheckBoxBitfield>,
     pub select_index: i16,
     #[cfg(feature = "tts")]
     pub tts: TtsFrontend,
@@ -28,17 +30,30 @@ impl CheckBoxes {
             action_conn,
             action_opcode,
             action_payload: CheckBoxPayload::new(),
+            bitfield: None,
             select_index: 0,
             #[cfg(feature="tts")]
             tts,
         }
     }
+    /// A check box list that returns its selection as a `CheckBoxBitfield`, starting with the `preselected` items checked
+    pub fn new_bitfield(action_conn: xous::CID, action_opcode: u32, preselected: u32) -> Self {
+        let mut checkboxes = CheckBoxes::new(action_conn, action_opcode);
+        checkboxes.bitfield = Some(CheckBoxBitfield(preselected));
+        checkboxes
+    }
     pub fn add_item(&mut self, new_item: ItemName) {
         self.items.push(new_item);
     }
     pub fn clear_items(&mut self) {
         self.items.clear();
     }
+    fn is_checked(&self, index: usize) -> bool {
+        match self.bitfield {
+            Some(bits) => bits.is_set(index),
+            None => self.action_payload.contains(self.items[index].as_str()),
+        }
+    }
 }
 impl ActionApi for CheckBoxes {
     fn set_action_opcode(&mut self, op: u32) {self.action_opcode = op}
@@ -53,6 +68,7 @@ impl ActionApi for CheckBoxes {
             TextBounds::BoundingBox(Rectangle::new_coords(0, 0, 1, 1))
         );
         tv.ellipsis = true;
+        tv.max_lines = Some(1); // each item owns exactly one row
         tv.style = modal.style;
         tv.invert = false;
         tv.draw_border= false;
@@ -67,7 +83,7 @@ impl ActionApi for CheckBoxes {
 
         let mut cur_line = 0;
         let mut do_okay = true;
-        for item in self.items.iter() {
+        for (index, item) in self.items.iter().enumerate() {
             let cur_y = at_height + cur_line * modal.line_height;
             if cur_line == self.select_index {
                 #[cfg(feature="tts")]
@@ -84,7 +100,7 @@ impl ActionApi for CheckBoxes {
                 modal.gam.post_textview(&mut tv).expect("couldn't post tv");
                 do_okay = false;
             }
-            if self.action_payload.contains(item.as_str()) {
+            if self.is_checked(index) {
                 // draw the check mark
                 tv.text.clear();
                 tv.bounds_computed = None;
@@ -118,9 +134,9 @@ impl ActionApi for CheckBoxes {
             #[cfg(feature="tts")]
             {
                 self.tts.tts_blocking(t!("checkbox.select_and_close_tts", xous::LANG)).unwrap();
-                for item in self.action_payload.payload().iter() {
-                    if let Some(name) = item {
-                        self.tts.tts_blocking(name.as_str()).unwrap();
+                for (index, item) in self.items.iter().enumerate() {
+                    if self.is_checked(index) {
+                        self.tts.tts_blocking(item.as_str()).unwrap();
                     }
                 }
             }
@@ -159,8 +175,24 @@ impl ActionApi for CheckBoxes {
             }
             '∴' | '\u{d}' => {
                 if (self.select_index as usize) < self.items.len() {
-                    let item_name = self.items[self.select_index as usize].as_str();
-                    if self.action_payload.contains(item_name) {
+                    let index = self.select_index as usize;
+                    let item_name = self.items[index].as_str();
+                    if let Some(bits) = self.bitfield.as_mut() {
+                        if index >= MAX_CHECKBOX_BITS {
+                            log::warn!("Only the first {} items of a bitfield check box list can be checked.", MAX_CHECKBOX_BITS);
+                            return (None, false)
+                        }
+                        bits.toggle(index);
+                        #[cfg(feature="tts")]
+                        {
+                            if bits.is_set(index) {
+                                self.tts.tts_blocking(t!("checkbox.check", xous::LANG)).unwrap();
+                            } else {
+                                self.tts.tts_blocking(t!("checkbox.uncheck", xous::LANG)).unwrap();
+                            }
+                            self.tts.tts_blocking(item_name).unwrap();
+                        }
+                    } else if self.action_payload.contains(item_name) {
                         self.action_payload.remove(item_name);
                         #[cfg(feature="tts")]
                         {
@@ -180,7 +212,10 @@ impl ActionApi for CheckBoxes {
                         }
                     }
                 } else {  // the OK button select
-                    let buf = Buffer::into_buf(self.action_payload).expect("couldn't convert message to payload");
+                    let buf = match self.bitfield {
+                        Some(bits) => Buffer::into_buf(bits),
+                        None => Buffer::into_buf(self.action_payload),
+                    }.expect("couldn't convert message to payload");
                     buf.send(self.action_conn, self.action_opcode).map(|_| ()).expect("couldn't send action message");
                     return (None, true)
                 }
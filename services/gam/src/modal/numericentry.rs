@@ -0,0 +1,209 @@
+use crate::*;
+use graphics_server::api::*;
+
+use xous_ipc::Buffer;
+use locales::t;
+
+use core::fmt::Write;
+
+/// Constraints applied to a `NumericEntry`. Lengths are in digits; a `max_len` of 0 means
+/// "as many digits as fit in the payload".
+#[derive(Debug, Copy, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Eq, PartialEq)]
+pub struct NumericLimits {
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+    pub min_len: u32,
+    pub max_len: u32,
+}
+impl NumericLimits {
+    pub fn new() -> Self {
+        NumericLimits { min: None, max: None, min_len: 0, max_len: 0 }
+    }
+    /// A fixed-length code, e.g. a 6-digit TOTP or an 8-digit PIN
+    pub fn fixed_len(len: u32) -> Self {
+        NumericLimits { min: None, max: None, min_len: len, max_len: len }
+    }
+    /// A bounded value, e.g. a port number in 1..=65535
+    pub fn range(min: u64, max: u64) -> Self {
+        NumericLimits { min: Some(min), max: Some(max), min_len: 1, max_len: 0 }
+    }
+    fn effective_max_len(&self) -> usize {
+        // 20 digits is the most a u64 can represent; also stay well under the payload capacity
+        const MAX_DIGITS: usize = 20;
+        if self.max_len == 0 || self.max_len as usize > MAX_DIGITS {
+            MAX_DIGITS
+        } else {
+            self.max_len as usize
+        }
+    }
+    /// Checks a string of digits against the limits. Returns an error message suitable for display
+    /// in the modal if the input is not acceptable.
+    pub fn check(&self, digits: &str) -> Option<ValidatorErr> {
+        let mut err = ValidatorErr::new();
+        let len = digits.chars().count();
+        if len == 0 || !digits.chars().all(|c| c.is_ascii_digit()) {
+            write!(err, "{}", t!("numeric.not_a_number", xous::LANG)).unwrap();
+            return Some(err);
+        }
+        if len < self.min_len as usize || len > self.effective_max_len() {
+            if self.min_len as usize == self.effective_max_len() {
+                write!(err, "{} {}", t!("numeric.exact_len", xous::LANG), self.min_len).unwrap();
+            } else {
+                write!(err, "{} {}-{}", t!("numeric.len_range", xous::LANG), self.min_len.max(1), self.effective_max_len()).unwrap();
+            }
+            return Some(err);
+        }
+        if self.min.is_some() || self.max.is_some() {
+            let value = match digits.parse::<u64>() {
+                Ok(v) => v,
+                Err(_) => {
+                    write!(err, "{}", t!("numeric.not_a_number", xous::LANG)).unwrap();
+                    return Some(err);
+                }
+            };
+            let too_small = self.min.map_or(false, |min| value < min);
+            let too_big = self.max.map_or(false, |max| value > max);
+            if too_small || too_big {
+                write!(err, "{} {}-{}",
+                    t!("numeric.value_range", xous::LANG),
+                    self.min.unwrap_or(0),
+                    self.max.unwrap_or(u64::MAX)
+                ).unwrap();
+                return Some(err);
+            }
+        }
+        None
+    }
+}
+
+/// A text entry variant that only accepts the digits 0-9. Non-digit keys are dropped at entry time,
+/// the length is capped at `limits.max_len`, and the remaining limits are checked when the user hits enter.
+/// The result is returned as a `TextEntryPayload` containing only digits.
+#[derive(Copy, Clone)]
+pub struct NumericEntry {
+    /// when true, entered digits are shown as `*`. Useful for PINs.
+    pub masked: bool,
+    pub limits: NumericLimits,
+    pub action_conn: xous::CID,
+    pub action_opcode: u32,
+    pub action_payload: TextEntryPayload,
+}
+impl NumericEntry {
+    pub fn new(masked: bool, limits: NumericLimits, action_conn: xous::CID, action_opcode: u32) -> Self {
+        NumericEntry {
+            masked,
+            limits,
+            action_conn,
+            action_opcode,
+            action_payload: TextEntryPayload::new(),
+        }
+    }
+}
+impl ActionApi for NumericEntry {
+    fn set_action_opcode(&mut self, op: u32) {self.action_opcode = op}
+    fn height(&self, glyph_height: i16, margin: i16) -> i16 {
+        glyph_height + 2*margin
+    }
+    fn redraw(&self, at_height: i16, modal: &Modal) {
+        let mut tv = TextView::new(
+            modal.canvas,
+            TextBounds::BoundingBox(Rectangle::new(
+                Point::new(modal.margin, at_height),
+                Point::new(modal.canvas_width - modal.margin, at_height + modal.line_height))
+        ));
+        tv.ellipsis = true;
+        tv.style = if self.masked {
+            GlyphStyle::Monospace
+        } else {
+            modal.style
+        };
+        tv.margin = Point::new(0, 0);
+        tv.draw_border = false;
+        tv.insertion = Some(self.action_payload.0.len() as i32);
+        tv.text.clear();
+        for ch in self.action_payload.as_str().chars() {
+            tv.text.push(if self.masked {'*'} else {ch}).expect("text field too long");
+        }
+        modal.gam.post_textview(&mut tv).expect("couldn't post textview");
+
+        modal.gam.draw_line(modal.canvas, Line::new_with_style(
+            Point::new(modal.margin, at_height + modal.line_height + 4),
+            Point::new(modal.canvas_width - modal.margin, at_height + modal.line_height + 4),
+            DrawStyle::new(PixelColor::Dark, PixelColor::Dark, 1))
+            ).expect("couldn't draw entry line");
+    }
+    fn key_action(&mut self, k: char) -> (Option<ValidatorErr>, bool) {
+        log::trace!("key_action: {}", k);
+        match k {
+            '∴' | '\u{d}' => {
+                if let Some(err_msg) = self.limits.check(self.action_payload.as_str()) {
+                    self.action_payload.volatile_clear(); // reset the input field
+                    return (Some(err_msg), false);
+                }
+                let buf = Buffer::into_buf(self.action_payload).expect("couldn't convert message to payload");
+                buf.send(self.action_conn, self.action_opcode).map(|_| ()).expect("couldn't send action message");
+                self.action_payload.volatile_clear(); // PINs are secrets, too
+                return (None, true)
+            }
+            '\u{8}' => { // backspace
+                self.action_payload.0.pop();
+            }
+            '0'..='9' => {
+                if self.action_payload.0.len() < self.limits.effective_max_len() {
+                    #[cfg(feature="tts")]
+                    {
+                        let xns = xous_names::XousNames::new().unwrap();
+                        let tts = tts_frontend::TtsFrontend::new(&xns).unwrap();
+                        tts.tts_blocking(&k.to_string()).unwrap();
+                    }
+                    self.action_payload.0.push(k).expect("ran out of space storing number");
+                }
+            }
+            _ => {
+                // everything else (navigation keys, letters, nulls) is ignored
+            }
+        }
+        (None, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn range_min_max() {
+        let limits = NumericLimits::range(1, 65535);
+        assert!(limits.check("1").is_none());
+        assert!(limits.check("65535").is_none());
+        assert!(limits.check("443").is_none());
+        // leading zeros are just more digits
+        assert!(limits.check("0080").is_none());
+    }
+    #[test]
+    fn range_out_of_range() {
+        let limits = NumericLimits::range(1, 65535);
+        let expected = format!("{} 1-65535", t!("numeric.value_range", xous::LANG));
+        assert_eq!(limits.check("0").unwrap().to_str(), expected);
+        assert_eq!(limits.check("65536").unwrap().to_str(), expected);
+        // more digits than a u64 holds
+        assert!(limits.check("99999999999999999999").is_some());
+        let above = NumericLimits { min: None, max: Some(9), min_len: 0, max_len: 0 };
+        assert!(above.check("0").is_none());
+        assert!(above.check("10").is_some());
+    }
+    #[test]
+    fn not_a_number() {
+        let expected = t!("numeric.not_a_number", xous::LANG);
+        for digits in ["", "12a", "-5", "3.5", " 7", "١٢"] {
+            assert_eq!(NumericLimits::new().check(digits).unwrap().to_str(), expected, "{:?}", digits);
+        }
+    }
+    #[test]
+    fn fixed_length() {
+        let limits = NumericLimits::fixed_len(6);
+        assert!(limits.check("482913").is_none());
+        let expected = format!("{} 6", t!("numeric.exact_len", xous::LANG));
+        assert_eq!(limits.check("48291").unwrap().to_str(), expected);
+        assert_eq!(limits.check("4829130").unwrap().to_str(), expected);
+    }
+}
@@ -66,6 +66,7 @@ impl ActionApi for RadioButtons {
             TextBounds::BoundingBox(Rectangle::new_coords(0, 0, 1, 1))
         );
         tv.ellipsis = true;
+        tv.max_lines = Some(1); // each item owns exactly one row
         tv.style = modal.style;
         tv.invert = self.is_password;
         tv.draw_border= false;
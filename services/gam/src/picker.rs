@@ -0,0 +1,276 @@
+//! A symbol picker pops up a grid of characters the keyboard has no key for, a page at a time, and sends
+//! the one picked on as if it had been typed. Like a `Menu`, it runs in the process that creates it and draws
+//! on a menu canvas owned by the GAM.
+//!
+//! The page's title sits above the grid: moving up off the top row selects it, and left and right then turn
+//! the page. Select on a symbol picks it, and on the title closes the picker without picking anything.
+
+use crate::api::*;
+use crate::Gam;
+use crate::MenuOpcode;
+
+use graphics_server::api::*;
+
+use xous_ipc::String;
+use num_traits::*;
+
+#[cfg(feature = "tts")]
+use tts_frontend::*;
+
+pub struct SymbolPage {
+    pub title: &'static str,
+    pub symbols: Vec<char>,
+}
+
+pub struct SymbolPicker {
+    pub sid: xous::SID,
+    pub gam: Gam,
+    pages: Vec<SymbolPage>,
+    page: usize,
+    /// the selected symbol, by position on the page, or `None` for the title
+    selection: Option<usize>,
+    /// where a picked symbol is sent, as a scalar with the symbol as its first argument
+    action_conn: xous::CID,
+    action_opcode: u32,
+    pub canvas: Gid,
+    pub authtoken: [u32; 4],
+    pub margin: i16,
+    pub cell_size: i16,
+    pub name: std::string::String,
+    #[cfg(feature = "tts")]
+    pub tts: TtsFrontend,
+}
+
+impl SymbolPicker {
+    pub fn new(name: &str, pages: Vec<SymbolPage>, action_conn: xous::CID, action_opcode: u32) -> SymbolPicker {
+        let xns = xous_names::XousNames::new().unwrap();
+        let sid = xous::create_server().expect("can't create private symbol picker message server");
+        let gam = Gam::new(&xns).expect("can't connect to GAM");
+        let authtoken = gam.register_ux(
+            UxRegistration {
+                app_name: String::<128>::from_str(name),
+                ux_type: UxType::Menu,
+                predictor: None,
+                listener: sid.to_array(),
+                redraw_id: MenuOpcode::Redraw.to_u32().unwrap(),
+                gotinput_id: None,
+                audioframe_id: None,
+                focuschange_id: None,
+                lifecycle_id: None,
+                rawkeys_id: Some(MenuOpcode::Rawkeys.to_u32().unwrap()),
+            }
+        ).expect("couldn't register my Ux element with GAM");
+        assert!(authtoken.is_some(), "Couldn't register symbol picker. Did you remember to add the app_name to the tokens.rs expected boot contexts list?");
+        let canvas = gam.request_content_canvas(authtoken.unwrap()).expect("couldn't get my content canvas from GAM");
+        #[cfg(feature="tts")]
+        let tts = TtsFrontend::new(&xns).unwrap();
+        let mut picker = SymbolPicker {
+            sid,
+            gam,
+            pages,
+            page: 0,
+            selection: Some(0),
+            action_conn,
+            action_opcode,
+            canvas,
+            authtoken: authtoken.unwrap(),
+            margin: 8,
+            cell_size: 0,
+            name: std::string::String::from(name),
+            #[cfg(feature="tts")]
+            tts,
+        };
+        picker.fit_to_page();
+        picker
+    }
+
+    fn columns(&self) -> usize {
+        let width = self.gam.get_canvas_bounds(self.canvas).expect("couldn't get current bounds").x;
+        ((width - self.margin * 2) / self.cell_size).max(1) as usize
+    }
+    fn grid_top(&self) -> i16 {
+        self.margin + self.cell_size
+    }
+    /// Asks the GAM to size the picker's canvas to fit the current page, as the text size may have changed
+    fn fit_to_page(&mut self) {
+        // cells are square, and big enough for the tallest glyphs: emoji and CJK
+        self.cell_size = self.gam.glyph_height_hint(GlyphStyle::Cjk).expect("couldn't get glyph height hint") as i16 + 4;
+        let rows = (self.pages[self.page].symbols.len() + self.columns() - 1) / self.columns();
+        let current_bounds = self.gam.get_canvas_bounds(self.canvas).expect("couldn't get current bounds");
+        let mut new_bounds = SetCanvasBoundsRequest {
+            requested: Point::new(current_bounds.x, self.grid_top() + rows as i16 * self.cell_size + self.margin),
+            granted: None,
+            token_type: TokenType::App,
+            token: self.authtoken,
+        };
+        self.gam.set_canvas_bounds_request(&mut new_bounds).expect("couldn't call set bounds");
+    }
+
+    fn draw_text(&self, text: &str, tl: Point, br: Point) {
+        use core::fmt::Write;
+        let mut tv = TextView::new(self.canvas, TextBounds::BoundingBox(Rectangle::new(tl, br)));
+        write!(tv.text, "{}", text).unwrap();
+        tv.draw_border = false;
+        tv.style = GlyphStyle::Regular;
+        tv.margin = Point::new(0, 0);
+        tv.ellipsis = true;
+        tv.max_lines = Some(1);
+        self.gam.post_textview(&mut tv).expect("couldn't render symbol picker text");
+    }
+    /// marks the selection with a box around it
+    fn draw_outline(&self, tl: Point, br: Point) {
+        let style = DrawStyle {
+            fill_color: None,
+            stroke_color: Some(PixelColor::Dark),
+            stroke_width: 1,
+        };
+        self.gam.draw_rectangle(self.canvas, Rectangle::new_with_style(tl, br, style))
+            .expect("couldn't draw symbol picker selection");
+    }
+    pub fn redraw(&mut self) {
+        self.fit_to_page();
+        let canvas_size = self.gam.get_canvas_bounds(self.canvas).unwrap();
+        self.gam.draw_rounded_rectangle(self.canvas,
+            RoundedRectangle::new(
+                Rectangle::new_with_style(Point::new(0, 0), canvas_size,
+                    DrawStyle::new(PixelColor::Light, PixelColor::Dark, 3)
+                ), 5
+            )).unwrap();
+
+        let title = format!("\u{25C0} {} {}/{} \u{25B6}", self.pages[self.page].title, self.page + 1, self.pages.len());
+        self.draw_text(&title, Point::new(self.margin + 2, self.margin + 2),
+            Point::new(canvas_size.x - self.margin - 2, self.margin + self.cell_size - 1));
+        if self.selection.is_none() {
+            self.draw_outline(Point::new(self.margin, self.margin), Point::new(canvas_size.x - self.margin, self.grid_top()));
+        }
+
+        let columns = self.columns();
+        for (position, symbol) in self.pages[self.page].symbols.iter().enumerate() {
+            let left = self.margin + (position % columns) as i16 * self.cell_size;
+            let top = self.grid_top() + (position / columns) as i16 * self.cell_size;
+            self.draw_text(&symbol.to_string(),
+                Point::new(left + 2, top + 2), Point::new(left + self.cell_size - 1, top + self.cell_size - 1));
+            if self.selection == Some(position) {
+                self.draw_outline(Point::new(left, top), Point::new(left + self.cell_size, top + self.cell_size));
+            }
+        }
+        #[cfg(feature="tts")]
+        {
+            match self.selection {
+                Some(position) => self.tts.tts_simple(&self.pages[self.page].symbols[position].to_string()).unwrap(),
+                None => self.tts.tts_simple(self.pages[self.page].title).unwrap(),
+            }
+        }
+        self.gam.redraw().unwrap();
+    }
+
+    fn turn_page(&mut self, forward: bool) {
+        let count = self.pages.len();
+        self.page = if forward { (self.page + 1) % count } else { (self.page + count - 1) % count };
+        self.fit_to_page();
+    }
+    fn select_up(&mut self) {
+        let columns = self.columns();
+        self.selection = match self.selection {
+            Some(position) if position >= columns => Some(position - columns),
+            _ => None,
+        };
+    }
+    fn select_down(&mut self) {
+        let columns = self.columns();
+        let count = self.pages[self.page].symbols.len();
+        self.selection = match self.selection {
+            None => Some(0),
+            Some(position) if position + columns < count => Some(position + columns),
+            Some(position) => Some(position),
+        };
+    }
+    fn pick(&mut self, symbol: char) {
+        // give up focus before sending the symbol on, so it lands in whatever the picker was raised over
+        self.gam.relinquish_focus().unwrap();
+        #[cfg(feature="tts")]
+        {
+            let mut phrase = "select ".to_string();
+            phrase.push(symbol);
+            self.tts.tts_blocking(&phrase).unwrap();
+        }
+        xous::send_message(self.action_conn,
+            xous::Message::new_scalar(self.action_opcode as usize, symbol as u32 as usize, 0, 0, 0)
+        ).expect("couldn't send picked symbol");
+    }
+    pub fn key_event(&mut self, keys: [char; 4]) {
+        for &k in keys.iter() {
+            let count = self.pages[self.page].symbols.len();
+            match k {
+                '∴' => {
+                    match self.selection {
+                        Some(position) => {
+                            let symbol = self.pages[self.page].symbols[position];
+                            self.pick(symbol);
+                        }
+                        None => self.gam.relinquish_focus().unwrap(),
+                    }
+                    // the page is kept for next time, as the next symbol wanted is likely to be on it too
+                    self.gam.redraw().unwrap();
+                    break; // drop any characters that happened to trail the select key, it's probably a fat-finger error.
+                }
+                '↑' => self.select_up(),
+                '↓' => self.select_down(),
+                '←' => match self.selection {
+                    None => self.turn_page(false),
+                    Some(position) => self.selection = Some(position.checked_sub(1).unwrap_or(count - 1)),
+                },
+                '→' => match self.selection {
+                    None => self.turn_page(true),
+                    Some(position) => self.selection = Some((position + 1) % count),
+                },
+                _ => continue,
+            }
+            self.redraw();
+        }
+    }
+}
+
+use std::thread;
+/// Builds a symbol picker from `pages` and runs it on a thread of its own.
+/// A picked symbol is sent to `action_conn` as a scalar message with `action_opcode`, the same way the
+/// keyboard sends keys.
+pub fn symbol_picker(
+    pages: Vec::<SymbolPage>,
+    picker_name: &'static str,
+    action_conn: xous::CID,
+    action_opcode: u32,
+) {
+    assert!(!pages.is_empty() && pages.iter().all(|p| !p.symbols.is_empty()),
+        "a symbol picker needs at least one page, with at least one symbol on each");
+    let mut picker = SymbolPicker::new(picker_name, pages, action_conn, action_opcode);
+    let _ = thread::spawn({
+        move || {
+            loop {
+                let msg = xous::receive_message(picker.sid).unwrap();
+                match FromPrimitive::from_usize(msg.body.id()) {
+                    Some(MenuOpcode::Redraw) => {
+                        picker.redraw();
+                    },
+                    Some(MenuOpcode::Rawkeys) => xous::msg_scalar_unpack!(msg, k1, k2, k3, k4, {
+                        let keys = [
+                            core::char::from_u32(k1 as u32).unwrap_or('\u{0000}'),
+                            core::char::from_u32(k2 as u32).unwrap_or('\u{0000}'),
+                            core::char::from_u32(k3 as u32).unwrap_or('\u{0000}'),
+                            core::char::from_u32(k4 as u32).unwrap_or('\u{0000}'),
+                        ];
+                        picker.key_event(keys);
+                    }),
+                    Some(MenuOpcode::Quit) => {
+                        xous::return_scalar(msg.sender, 1).unwrap();
+                        break;
+                    },
+                    None => {
+                        log::error!("unknown opcode {:?}", msg.body.id());
+                    }
+                }
+            }
+            xous::destroy_server(picker.sid).unwrap();
+        }
+    });
+}
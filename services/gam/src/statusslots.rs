@@ -0,0 +1,99 @@
+use crate::api::*;
+use xous_ipc::String;
+
+struct SlotClaim {
+    name: String::<32>,
+    priority: u8,
+    token: [u32; 4],
+    /// order of the claim, to break ties between equal priorities
+    serial: u32,
+    /// the process that made the claim, so it can be let go once that process has exited
+    pid: Option<xous::PID>,
+    text: String::<16>,
+}
+
+/// Arbitrates the small indicator slots on the status bar. Any service can claim a slot at a priority;
+/// the status bar periodically asks for the `STATUS_SLOT_COUNT` highest-priority claims that currently
+/// have something to show, and renders them.
+pub(crate) struct StatusSlots {
+    claims: Vec<SlotClaim>,
+    next_serial: u32,
+    generation: u32,
+}
+impl StatusSlots {
+    pub(crate) fn new() -> StatusSlots {
+        StatusSlots {
+            claims: Vec::new(),
+            next_serial: 0,
+            generation: 0,
+        }
+    }
+    /// Returns the token for the new claim, or `None` if the name is already taken or the table is full
+    pub(crate) fn claim(&mut self, trng: &trng::Trng, pid: Option<xous::PID>, name: String::<32>, priority: u8) -> Option<[u32; 4]> {
+        self.prune();
+        if self.claims.len() >= MAX_STATUS_CLAIMS {
+            log::warn!("status slot table is full, refusing claim by {}", name);
+            return None;
+        }
+        if self.claims.iter().any(|c| c.name.as_str() == name.as_str()) {
+            log::warn!("status slot {} is already claimed", name);
+            return None;
+        }
+        let token = [trng.get_u32().unwrap(), trng.get_u32().unwrap(), trng.get_u32().unwrap(), trng.get_u32().unwrap()];
+        self.claims.push(SlotClaim {
+            name,
+            priority,
+            token,
+            serial: self.next_serial,
+            pid,
+            text: String::<16>::new(),
+        });
+        self.next_serial = self.next_serial.wrapping_add(1);
+        // a new claim starts out empty, so nothing visible changes yet
+        Some(token)
+    }
+    pub(crate) fn set(&mut self, token: [u32; 4], text: String::<16>) -> Result<(), xous::Error> {
+        let claim = self.claims.iter_mut().find(|c| c.token == token).ok_or(xous::Error::AccessDenied)?;
+        if claim.text.as_str() != text.as_str() {
+            claim.text = text;
+            self.generation = self.generation.wrapping_add(1);
+        }
+        Ok(())
+    }
+    pub(crate) fn release(&mut self, token: [u32; 4]) -> Result<(), xous::Error> {
+        let index = self.claims.iter().position(|c| c.token == token).ok_or(xous::Error::AccessDenied)?;
+        let claim = self.claims.remove(index);
+        if !claim.text.is_empty() {
+            self.generation = self.generation.wrapping_add(1);
+        }
+        Ok(())
+    }
+    /// Drops the claims of processes that have exited without releasing them, so they neither hold on to
+    /// a place in the table nor keep showing. The kernel only knows processes that are still around.
+    fn prune(&mut self) {
+        let before = self.claims.len();
+        let shown = self.claims.iter().filter(|c| !c.text.is_empty()).count();
+        self.claims.retain(|c| match c.pid {
+            Some(pid) => xous::cpu_time(Some(pid)) != Err(xous::Error::ProcessNotFound),
+            None => true,
+        });
+        if self.claims.len() != before {
+            log::info!("released {} status slot(s) held by processes that have exited", before - self.claims.len());
+            if self.claims.iter().filter(|c| !c.text.is_empty()).count() != shown {
+                self.generation = self.generation.wrapping_add(1);
+            }
+        }
+    }
+    /// Fills in the slots that win arbitration, highest priority first
+    pub(crate) fn visible(&mut self, list: &mut StatusSlotList) {
+        self.prune();
+        let mut shown: Vec<&SlotClaim> = self.claims.iter().filter(|c| !c.text.is_empty()).collect();
+        shown.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.serial.cmp(&b.serial)));
+        list.count = 0;
+        for (dst, claim) in list.slots.iter_mut().zip(shown.iter()) {
+            *dst = claim.text;
+            list.count += 1;
+        }
+        list.generation = self.generation;
+    }
+}
@@ -0,0 +1,192 @@
+use crate::api::*;
+use graphics_server::*;
+use num_traits::*;
+
+use std::collections::HashMap;
+
+/// Distance between the toast and the edges of the screen
+const TOAST_MARGIN: i16 = 8;
+
+struct ActiveToast {
+    id: usize,
+    text: xous_ipc::String::<128>,
+    /// the key that triggers the action, and where to report it
+    action: Option<(char, xous::CID, u32)>,
+    /// area covered on screen, known once the toast has been drawn
+    bounds: Option<Rectangle>,
+}
+
+/// A single short-lived notification, drawn by the GAM over the bottom of the screen. It never takes focus:
+/// the focused app keeps its input, except for the toast's action key if it has one, and only a context
+/// holding a token can have one. While a modal or menu has focus, the toast is neither drawn nor takes keys,
+/// so it can't cover or listen in on a trusted prompt. Showing a toast replaces whatever toast was up before.
+pub(crate) struct Toasts {
+    /// the GAM's own Gid for drawing toasts
+    gid: Gid,
+    current: Option<ActiveToast>,
+    next_id: usize,
+    /// connections to the servers that asked for action keys, kept so repeated toasts don't use up connections
+    listeners: HashMap<[u32; 4], xous::CID>,
+}
+impl Toasts {
+    pub(crate) fn new(trng: &trng::Trng) -> Toasts {
+        let mut gid = [0u32; 4];
+        for word in gid.iter_mut() {
+            *word = trng.get_u32().expect("couldn't make toast gid");
+        }
+        Toasts {
+            gid: Gid::new(gid),
+            current: None,
+            next_id: 1,
+            listeners: HashMap::new(),
+        }
+    }
+    /// Puts up a toast and starts the timer that takes it down again by sending `ToastExpired` to `main_conn`.
+    /// `trusted` says whether the action's token is held by a context; without one the action is dropped.
+    /// `alert_focused` holds off drawing while a modal or menu has focus. Returns true if a previous toast was
+    /// taken down, in which case the screen under it needs to be redrawn.
+    pub(crate) fn show(&mut self, gfx: &graphics_server::Gfx, request: ToastRequest, trusted: bool,
+        alert_focused: bool, main_conn: xous::CID) -> bool {
+        let replaced = self.clear(gfx);
+        let action = match request.action {
+            Some(_) if !trusted => {
+                log::warn!("dropping the action of a toast without a valid token");
+                None
+            }
+            Some(a) => match (core::char::from_u32(a.key), self.connect(a.sid)) {
+                (Some(key), Some(cid)) => Some((key, cid, a.opcode)),
+                _ => {
+                    log::warn!("ignoring unusable toast action {:?}", a);
+                    None
+                }
+            },
+            None => None,
+        };
+        self.put_up(gfx, request.text, request.duration_ms, action, alert_focused, main_conn);
+        replaced
+    }
+    /// Puts up a toast of the GAM's own, showing `text` for as long as a toast can stay up. If the user presses
+    /// `key`, `opcode` is sent to `main_conn` with the toast's id. Returns the id, and whether a previous toast
+    /// was taken down.
+    pub(crate) fn confirm(&mut self, gfx: &graphics_server::Gfx, text: &str, key: char, opcode: Opcode,
+        alert_focused: bool, main_conn: xous::CID) -> (usize, bool) {
+        let replaced = self.clear(gfx);
+        let action = Some((key, main_conn, opcode.to_u32().unwrap()));
+        let id = self.put_up(gfx, xous_ipc::String::from_str(text), TOAST_MAX_MS, action, alert_focused, main_conn);
+        (id, replaced)
+    }
+    /// Whether the toast that `id` refers to is still up
+    pub(crate) fn is_showing(&self, id: usize) -> bool {
+        self.current.as_ref().map_or(false, |t| t.id == id)
+    }
+    fn put_up(&mut self, gfx: &graphics_server::Gfx, text: xous_ipc::String::<128>, duration_ms: u32,
+        action: Option<(char, xous::CID, u32)>, alert_focused: bool, main_conn: xous::CID) -> usize {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.current = Some(ActiveToast {
+            id,
+            text,
+            action,
+            bounds: None,
+        });
+        if !alert_focused {
+            if let Some(r) = self.draw(gfx) {
+                gfx.flush_region(r).expect("couldn't flush toast");
+            }
+        }
+
+        let duration = duration_ms.max(TOAST_MIN_MS).min(TOAST_MAX_MS);
+        std::thread::spawn(move || {
+            let tt = ticktimer_server::Ticktimer::new().unwrap();
+            tt.sleep_ms(duration as usize).unwrap();
+            xous::send_message(main_conn,
+                xous::Message::new_scalar(Opcode::ToastExpired.to_usize().unwrap(), id, 0, 0, 0)
+            ).expect("couldn't expire toast");
+        });
+        id
+    }
+    /// Takes the toast down if it's still the one that `id` refers to. Returns true if the screen under the
+    /// toast needs to be redrawn.
+    pub(crate) fn expire(&mut self, gfx: &graphics_server::Gfx, id: usize) -> bool {
+        if self.current.as_ref().map_or(false, |t| t.id == id) {
+            self.clear(gfx)
+        } else {
+            false
+        }
+    }
+    /// Checks a key event against the toast's action key. On a match, the action is reported to whoever raised
+    /// the toast, with the toast's id as the first argument, and the toast is taken down; the caller should then drop the key event, and redraw the screen.
+    /// Keys always go to a focused modal or menu, as they may be a password.
+    pub(crate) fn take_action(&mut self, gfx: &graphics_server::Gfx, keys: &[char; 4], alert_focused: bool) -> bool {
+        if alert_focused {
+            return false;
+        }
+        let (id, cid, opcode) = match self.current.as_ref().and_then(|t| t.action.map(|a| (t.id, a))) {
+            Some((id, (key, cid, opcode))) if keys.contains(&key) => (id, cid, opcode),
+            _ => return false,
+        };
+        xous::send_message(cid, xous::Message::new_scalar(opcode as usize, id, 0, 0, 0))
+            .expect("couldn't send toast action");
+        self.clear(gfx);
+        true
+    }
+    /// Draws the toast back on top of anything that was just rendered into `region`, and returns the region
+    /// that has to be flushed to keep the toast intact. Nothing is drawn over a focused modal or menu.
+    pub(crate) fn cover(&mut self, gfx: &graphics_server::Gfx, region: Rectangle, alert_focused: bool) -> Rectangle {
+        match self.current.as_ref().and_then(|t| t.bounds) {
+            Some(bounds) if !alert_focused && bounds.intersects(region) => {
+                self.draw(gfx);
+                region.union(bounds)
+            }
+            _ => region,
+        }
+    }
+    /// Redraws the toast without flushing, e.g. ahead of a full-screen flush, unless a modal or menu has focus
+    pub(crate) fn redraw(&mut self, gfx: &graphics_server::Gfx, alert_focused: bool) {
+        if self.current.is_some() && !alert_focused {
+            self.draw(gfx);
+        }
+    }
+
+    fn draw(&mut self, gfx: &graphics_server::Gfx) -> Option<Rectangle> {
+        let toast = self.current.as_mut()?;
+        let screen = gfx.screen_size().expect("couldn't get screen size");
+        let mut tv = TextView::new(
+            self.gid,
+            TextBounds::GrowableFromBl(
+                Point::new(TOAST_MARGIN, screen.y - TOAST_MARGIN),
+                (screen.x - TOAST_MARGIN * 2) as u16,
+            ),
+        );
+        tv.clip_rect = Some(Rectangle::new(Point::new(0, 0), screen).into());
+        tv.rounded_border = Some(6);
+        tv.max_lines = Some(3);
+        tv.ellipsis = true;
+        tv.text.append(toast.text.as_str().unwrap_or("")).ok();
+        tv.set_op(TextOp::Render);
+        gfx.draw_textview(&mut tv).expect("couldn't draw toast");
+        toast.bounds = tv.bounds_computed;
+        toast.bounds
+    }
+    /// Blanks the area under the toast and forgets it. Returns true if anything was on screen.
+    fn clear(&mut self, gfx: &graphics_server::Gfx) -> bool {
+        match self.current.take().and_then(|t| t.bounds) {
+            Some(mut r) => {
+                r.style = DrawStyle {fill_color: Some(PixelColor::Light), stroke_color: None, stroke_width: 0,};
+                gfx.draw_rectangle(r).expect("can't clear toast");
+                // the focused app is asked to redraw, but it may not touch this area, so don't leave the toast on the LCD
+                gfx.flush_region(r).expect("couldn't flush toast");
+                true
+            }
+            None => false,
+        }
+    }
+    fn connect(&mut self, sid: [u32; 4]) -> Option<xous::CID> {
+        if let Some(cid) = self.listeners.get(&sid) {
+            return Some(*cid);
+        }
+        let cid = xous::connect(xous::SID::from_array(sid)).ok()?;
+        self.listeners.insert(sid, cid);
+        Some(cid)
+    }
+}
@@ -24,13 +24,27 @@ pub mod text;
 pub use text::*;
 pub mod glyphstyle;
 pub use glyphstyle::*;
+pub mod glyphset;
+pub use glyphset::*;
+pub mod polygon;
+pub use polygon::*;
 pub mod blitstr2;
 pub use blitstr2::*;
+pub mod bitmap;
+pub use bitmap::*;
 
 use std::hash::{Hash, Hasher};
 
 pub const LINES: usize = 536;
 pub const WIDTH: usize = 336;
+/// bytes per row of a screenshot: one bit per pixel, most significant bit first, with 1 for a dark pixel (as in a binary PBM file)
+pub const SCREENSHOT_ROW_BYTES: usize = (WIDTH + 7) / 8;
+/// bytes per row of a screenshot taken while the screen is turned on its side
+pub const SCREENSHOT_LANDSCAPE_ROW_BYTES: usize = (LINES + 7) / 8;
+/// length of a full-screen screenshot raster, which is the same in every rotation
+pub const SCREENSHOT_LEN: usize = SCREENSHOT_ROW_BYTES * LINES;
+/// upper bound on the size of a soft-hyphenation dictionary; room for a few tens of thousands of words
+pub const HYPHENATION_MAX_LEN: usize = 512 * 1024;
 
 //////////////// IPC APIs
 #[derive(Debug, Copy, Clone, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
@@ -56,12 +70,19 @@ impl Hash for Gid {
 }
 
 pub const SERVER_NAME_GFX: &str = "_Graphics_";
+/// Address of the server that puts panics on the screen. It takes a single kind of message, a memory lend
+/// holding the length of the text as a little-endian `u32` followed by the UTF-8 text; the id is ignored.
+/// Only the log server sends it, and it can't look names up, so the server has a fixed address.
+pub const SERVER_NAME_GFX_PANIC: &[u8; 16] = b"panic-to-screen!";
 
 #[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive)]
 pub(crate) enum Opcode {
     /// Flush the buffer to the screen
     Flush,
 
+    /// Flush only the lines within a vertical span of the screen
+    FlushRegion,
+
     /// Clear the buffer to "light" colored pixels
     Clear,
 
@@ -86,6 +107,34 @@ pub(crate) enum Opcode {
     /// draws a textview
     DrawTextView, //(TextView),
 
+    /// computes the size of a string without drawing it
+    MeasureText, //(TextMeasurement),
+
+    /// blits a 1-bpp or grayscale bitmap, with optional transparency and integer scaling
+    DrawBitmap, //(Bitmap),
+
+    /// draws a stroked polyline, or a filled and/or stroked polygon
+    DrawPolygon, //(Polygon),
+
+    /// selects whether drawing operations go to the visible frame buffer or the offscreen buffer
+    SetDrawTarget, //(DrawTarget)
+    /// copies a region of the visible frame buffer into the offscreen buffer, so composition can start from the current contents
+    OffscreenBegin, //(Rectangle)
+    /// copies a region of the offscreen buffer onto the visible frame buffer in a single operation
+    OffscreenSwap, //(Rectangle)
+    /// moves the pixels of a region down (or up, for a negative offset), filling the lines that open up
+    ScrollRegion, //(Rectangle, dy, fill is dark)
+
+    /// loads a glyph set blob into one of the runtime glyph set slots
+    LoadGlyphSet, //(slot, blob)
+    /// frees a runtime glyph set slot
+    UnloadGlyphSet, //(slot)
+
+    /// replaces the soft-hyphenation dictionary used when typesetting
+    LoadHyphenation, //(len, dictionary)
+    /// drops the soft-hyphenation dictionary; words are then only broken at hyphens in the text
+    UnloadHyphenation,
+
     /// draws an object that requires clipping
     DrawClipObject, //(ClipObject),
     DrawClipObjectList,
@@ -99,6 +148,11 @@ pub(crate) enum Opcode {
     /// permanently turns on the Devboot mark
     Devboot,
 
+    /// flips the whole display to light-on-dark (1) or back to normal (0) at the time it's pushed to the LCD
+    SetInverted,
+    /// turns the image on the LCD by a multiple of 90 degrees; the screen size and drawing coordinates follow
+    SetRotation, //(Rotation)
+
     /// bulk read for signature verifications
     BulkReadFonts,
     RestartBulkRead,
@@ -106,12 +160,54 @@ pub(crate) enum Opcode {
     /// generates a test pattern
     TestPattern,
 
+    /// copies the visible frame buffer into the lent buffer as a PBM raster, in the orientation the user sees it.
+    /// The first word of the buffer is set to the bytes per row, and the raster follows. Assumes requests are vetted by the GAM
+    Screenshot,
+
     /// SuspendResume callback
     SuspendResume,
 
+    /// Sent by our own panic thread with the text of a panic to put up, see `panic::show()`
+    DrawPanic,
+
+    /// Sent by our own watchdog thread; answered straight away, to show the main loop is still running
+    WatchdogPing,
+
     Quit,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, num_derive::FromPrimitive, num_derive::ToPrimitive)]
+pub enum DrawTarget {
+    /// the frame buffer that gets flushed to the LCD
+    Screen = 0,
+    /// a full-screen buffer that is never flushed; regions are copied to the screen with `OffscreenSwap`
+    Offscreen = 1,
+}
+
+/// How far the image is turned clockwise on the LCD. The LCD is taller than it is wide, so in the
+/// `Clockwise` and `CounterClockwise` positions the screen is landscape, e.g. for use sideways in a stand.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, num_derive::FromPrimitive, num_derive::ToPrimitive)]
+pub enum Rotation {
+    Upright = 0,
+    Clockwise = 1,
+    UpsideDown = 2,
+    CounterClockwise = 3,
+}
+impl Rotation {
+    pub fn is_landscape(&self) -> bool {
+        *self == Rotation::Clockwise || *self == Rotation::CounterClockwise
+    }
+    /// The next position, a quarter turn further clockwise
+    pub fn next(&self) -> Rotation {
+        match self {
+            Rotation::Upright => Rotation::Clockwise,
+            Rotation::Clockwise => Rotation::UpsideDown,
+            Rotation::UpsideDown => Rotation::CounterClockwise,
+            Rotation::CounterClockwise => Rotation::Upright,
+        }
+    }
+}
+
 #[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
 pub enum ClipObjectType {
     Line(Line),
@@ -119,6 +215,7 @@ pub enum ClipObjectType {
     Rect(Rectangle),
     RoundRect(RoundedRectangle),
     XorLine(Line),
+    Arc(Arc),
 }
 
 #[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
@@ -0,0 +1,183 @@
+use crate::api::{Gid, PixelColor, Point, Rectangle};
+
+/// Storage available for pixel data. Sized so that a whole `Bitmap` fits inside a single 4k page
+/// once the rkyv header overhead is accounted for.
+pub const BITMAP_DATA_LEN: usize = 3840;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub enum BitmapFormat {
+    /// 1 bit per pixel, MSB is the leftmost pixel, rows padded to a byte boundary. A set bit is `PixelColor::Dark`.
+    Mono,
+    /// 8 bits per pixel, 0 is black and 255 is white. Rendered with a 4x4 ordered dither.
+    Gray,
+}
+
+/// 4x4 Bayer matrix, scaled to 0-255 thresholds
+const BAYER_4X4: [[u8; 4]; 4] = [
+    [  8, 136,  40, 168],
+    [200,  72, 232, 104],
+    [ 56, 184,  24, 152],
+    [248, 120, 216,  88],
+];
+
+/// A bitmap is blitted with its top left corner at `tl`. Coordinates are local to the canvas when
+/// handed to the GAM; the GAM translates them to screen space and fills in `clip_rect`.
+#[derive(Copy, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct Bitmap {
+    canvas: Gid,
+    pub clip_rect: Option<Rectangle>, // set by the GAM to the canvas' clip_rect, in screen coordinates
+    pub tl: Point,
+    pub width: u16,
+    pub height: u16,
+    pub format: BitmapFormat,
+    /// pixels that resolve to this color are not drawn
+    pub transparent: Option<PixelColor>,
+    /// integer magnification factor; 0 is treated as 1
+    pub scale: u8,
+    data: [u8; BITMAP_DATA_LEN],
+}
+impl Bitmap {
+    /// Creates a bitmap from raw pixel data. Returns `None` if the image is empty or the data
+    /// doesn't match the stated dimensions, or is too large to fit in a single message.
+    pub fn new(canvas: Gid, tl: Point, width: u16, height: u16, format: BitmapFormat, pixels: &[u8]) -> Option<Bitmap> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let len = Bitmap::data_len(width, height, format);
+        if len > BITMAP_DATA_LEN || pixels.len() < len {
+            return None;
+        }
+        let mut data = [0u8; BITMAP_DATA_LEN];
+        data[..len].copy_from_slice(&pixels[..len]);
+        Some(Bitmap {
+            canvas,
+            clip_rect: None,
+            tl,
+            width,
+            height,
+            format,
+            transparent: None,
+            scale: 1,
+            data,
+        })
+    }
+    /// number of bytes of pixel data required for an image of the given dimensions
+    pub fn data_len(width: u16, height: u16, format: BitmapFormat) -> usize {
+        match format {
+            BitmapFormat::Mono => ((width as usize + 7) / 8) * height as usize,
+            BitmapFormat::Gray => width as usize * height as usize,
+        }
+    }
+    pub fn get_canvas_gid(&self) -> Gid {
+        self.canvas
+    }
+    /// Bitmaps arrive over IPC with their fields set by the sender, so the dimensions have to be
+    /// checked against the backing store before any pixel is looked up.
+    pub fn is_valid(&self) -> bool {
+        self.width != 0 && self.height != 0
+            && Bitmap::data_len(self.width, self.height, self.format) <= BITMAP_DATA_LEN
+    }
+    pub fn scale_factor(&self) -> i16 {
+        if self.scale == 0 { 1 } else { self.scale as i16 }
+    }
+    /// The area covered by the bitmap after scaling, inclusive of the bottom right pixel. Saturates
+    /// at the edge of the coordinate space.
+    pub fn bounds(&self) -> Rectangle {
+        let s = self.scale_factor() as i32;
+        let edge = |origin: i16, len: u16| {
+            (origin as i32 + len as i32 * s - 1).clamp(i16::MIN as i32, i16::MAX as i32) as i16
+        };
+        Rectangle::new(self.tl, Point::new(edge(self.tl.x, self.width), edge(self.tl.y, self.height)))
+    }
+    pub fn translate(&mut self, offset: Point) {
+        self.tl.x = self.tl.x.saturating_add(offset.x);
+        self.tl.y = self.tl.y.saturating_add(offset.y);
+    }
+    /// Returns the color of the source pixel at (x, y), or `None` if it is out of range
+    /// or matches the transparent color.
+    pub fn pixel(&self, x: u16, y: u16) -> Option<PixelColor> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let color = match self.format {
+            BitmapFormat::Mono => {
+                let stride = (self.width as usize + 7) / 8;
+                let byte = *self.data.get(y as usize * stride + x as usize / 8)?;
+                PixelColor::from(byte & (0x80 >> (x % 8)) != 0)
+            }
+            BitmapFormat::Gray => {
+                let level = *self.data.get(y as usize * self.width as usize + x as usize)?;
+                PixelColor::from(level < BAYER_4X4[y as usize % 4][x as usize % 4])
+            }
+        };
+        if Some(color) == self.transparent {
+            None
+        } else {
+            Some(color)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn mono_pixels() {
+        // 10x2: row 0 is a checkerboard, row 1 only has the last pixel set
+        let data = [0b1010_1010, 0b1000_0000, 0b0000_0000, 0b0100_0000];
+        let bm = Bitmap::new(Gid::new([0; 4]), Point::new(0, 0), 10, 2, BitmapFormat::Mono, &data).unwrap();
+        assert_eq!(bm.pixel(0, 0), Some(PixelColor::Dark));
+        assert_eq!(bm.pixel(1, 0), Some(PixelColor::Light));
+        assert_eq!(bm.pixel(8, 0), Some(PixelColor::Dark));
+        assert_eq!(bm.pixel(9, 1), Some(PixelColor::Dark));
+        assert_eq!(bm.pixel(8, 1), Some(PixelColor::Light));
+        assert_eq!(bm.pixel(10, 0), None);
+    }
+    #[test]
+    fn transparency_and_bounds() {
+        let data = [0xFF; 8];
+        let mut bm = Bitmap::new(Gid::new([0; 4]), Point::new(10, 20), 8, 8, BitmapFormat::Mono, &data).unwrap();
+        bm.transparent = Some(PixelColor::Dark);
+        assert_eq!(bm.pixel(3, 3), None);
+        bm.scale = 3;
+        assert_eq!(bm.bounds().tl, Point::new(10, 20));
+        assert_eq!(bm.bounds().br, Point::new(33, 43));
+    }
+    #[test]
+    fn gray_dither() {
+        let black = [0u8; 16];
+        let white = [255u8; 16];
+        let b = Bitmap::new(Gid::new([0; 4]), Point::new(0, 0), 4, 4, BitmapFormat::Gray, &black).unwrap();
+        let w = Bitmap::new(Gid::new([0; 4]), Point::new(0, 0), 4, 4, BitmapFormat::Gray, &white).unwrap();
+        for y in 0..4 {
+            for x in 0..4 {
+                assert_eq!(b.pixel(x, y), Some(PixelColor::Dark));
+                assert_eq!(w.pixel(x, y), Some(PixelColor::Light));
+            }
+        }
+        let mid = [128u8; 16];
+        let m = Bitmap::new(Gid::new([0; 4]), Point::new(0, 0), 4, 4, BitmapFormat::Gray, &mid).unwrap();
+        let dark = (0..16).filter(|i| m.pixel(i % 4, i / 4) == Some(PixelColor::Dark)).count();
+        assert_eq!(dark, 8);
+    }
+    #[test]
+    fn rejects_bad_sizes() {
+        assert!(Bitmap::new(Gid::new([0; 4]), Point::new(0, 0), 0, 4, BitmapFormat::Mono, &[0; 4]).is_none());
+        assert!(Bitmap::new(Gid::new([0; 4]), Point::new(0, 0), 16, 4, BitmapFormat::Mono, &[0; 4]).is_none());
+        assert!(Bitmap::new(Gid::new([0; 4]), Point::new(0, 0), 100, 100, BitmapFormat::Gray, &[0; 10000]).is_none());
+    }
+    #[test]
+    fn forged_dimensions() {
+        // a sender can set the public fields after construction; nothing may index past the data
+        let mut bm = Bitmap::new(Gid::new([0; 4]), Point::new(0, 0), 8, 8, BitmapFormat::Gray, &[0; 64]).unwrap();
+        bm.width = 1000;
+        bm.height = 1000;
+        assert!(!bm.is_valid());
+        assert_eq!(bm.pixel(999, 999), None);
+        bm.scale = 255;
+        bm.tl = Point::new(i16::MAX - 10, i16::MIN);
+        assert_eq!(bm.bounds().br, Point::new(i16::MAX, i16::MAX));
+        bm.translate(Point::new(100, -100));
+        assert_eq!(bm.tl, Point::new(i16::MAX, i16::MIN));
+    }
+}
@@ -0,0 +1,130 @@
+//! Glyph sets can be loaded at runtime (e.g. from the PDDB) to cover scripts and symbols that aren't
+//! baked into the graphics server image. Each set occupies one of `GLYPH_SET_SLOTS` slots, and text
+//! is rendered with it by selecting the matching `GlyphStyle::Loaded*` style.
+//!
+//! A glyph set blob uses the same sprite format as the built-in fonts (see `blitstr2.rs`), with a
+//! small header in front. All fields are little-endian:
+//!
+//! | offset          | contents                                                          |
+//! |-----------------|-------------------------------------------------------------------|
+//! | 0               | magic, `GLYPH_SET_MAGIC` (the bytes "XGS1")                       |
+//! | 4               | `count`, number of glyphs in the set                              |
+//! | 8               | `max_height`, line height of the set in pixels (1-16)             |
+//! | 12              | reserved, must be 0                                               |
+//! | 16              | `count` codepoints as `u32`, sorted in strictly ascending order   |
+//! | 16 + 4*count    | `count` widths as `u8` (1-16), zero-padded to a multiple of 4     |
+//! | after widths    | `count` 16x16px sprites, eight `u32` words each                   |
+
+pub const GLYPH_SET_MAGIC: u32 = 0x3153_4758;
+pub const GLYPH_SET_SLOTS: usize = 4;
+pub const GLYPH_SET_HEADER_LEN: usize = 16;
+/// Upper bound on the size of a single glyph set blob; large enough for a full CJK extension block
+pub const GLYPH_SET_MAX_LEN: usize = 1024 * 1024;
+
+const SPRITE_WORDS: usize = 8;
+const SPRITE_PX: u32 = 16;
+
+/// Describes where the sections of a validated glyph set blob are located, in bytes from the start of the blob
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GlyphSetLayout {
+    pub count: usize,
+    pub max_height: u8,
+    pub widths_offset: usize,
+    pub glyphs_offset: usize,
+    pub total_len: usize,
+}
+impl GlyphSetLayout {
+    /// Computes the layout of a set of `count` glyphs
+    pub fn new(count: usize, max_height: u8) -> GlyphSetLayout {
+        let widths_offset = GLYPH_SET_HEADER_LEN + count * 4;
+        let glyphs_offset = widths_offset + ((count + 3) & !3);
+        GlyphSetLayout {
+            count,
+            max_height,
+            widths_offset,
+            glyphs_offset,
+            total_len: glyphs_offset + count * SPRITE_WORDS * 4,
+        }
+    }
+    /// Checks that `blob` is a well-formed glyph set and returns its layout. Trailing bytes
+    /// after the sprite data are allowed, so a blob can be read from storage in page-sized chunks.
+    pub fn parse(blob: &[u8]) -> Result<GlyphSetLayout, xous::Error> {
+        if blob.len() < GLYPH_SET_HEADER_LEN {
+            return Err(xous::Error::InvalidString);
+        }
+        if read_u32(blob, 0) != GLYPH_SET_MAGIC || read_u32(blob, 12) != 0 {
+            return Err(xous::Error::InvalidString);
+        }
+        let count = read_u32(blob, 4) as usize;
+        let max_height = read_u32(blob, 8);
+        if count == 0 || max_height == 0 || max_height > SPRITE_PX || count > GLYPH_SET_MAX_LEN / (SPRITE_WORDS * 4) {
+            return Err(xous::Error::InvalidString);
+        }
+        let layout = GlyphSetLayout::new(count, max_height as u8);
+        if layout.total_len > GLYPH_SET_MAX_LEN || blob.len() < layout.total_len {
+            return Err(xous::Error::InvalidString);
+        }
+        // the lookup is a binary search, so the codepoints have to be sorted
+        let mut prev: Option<u32> = None;
+        for i in 0..count {
+            let cp = read_u32(blob, GLYPH_SET_HEADER_LEN + i * 4);
+            if prev.map_or(false, |p| cp <= p) || core::char::from_u32(cp).is_none() {
+                return Err(xous::Error::InvalidString);
+            }
+            prev = Some(cp);
+            let wide = blob[layout.widths_offset + i] as u32;
+            if wide == 0 || wide > SPRITE_PX {
+                return Err(xous::Error::InvalidString);
+            }
+        }
+        Ok(layout)
+    }
+}
+
+fn read_u32(blob: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([blob[offset], blob[offset + 1], blob[offset + 2], blob[offset + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn make_blob(codepoints: &[u32], widths: &[u8], height: u32) -> Vec<u8> {
+        let layout = GlyphSetLayout::new(codepoints.len(), height as u8);
+        let mut blob = vec![0u8; layout.total_len];
+        blob[0..4].copy_from_slice(&GLYPH_SET_MAGIC.to_le_bytes());
+        blob[4..8].copy_from_slice(&(codepoints.len() as u32).to_le_bytes());
+        blob[8..12].copy_from_slice(&height.to_le_bytes());
+        for (i, cp) in codepoints.iter().enumerate() {
+            let o = GLYPH_SET_HEADER_LEN + i * 4;
+            blob[o..o + 4].copy_from_slice(&cp.to_le_bytes());
+        }
+        blob[layout.widths_offset..layout.widths_offset + widths.len()].copy_from_slice(widths);
+        blob
+    }
+    #[test]
+    fn parse_valid() {
+        let blob = make_blob(&[0x2200, 0x2201, 0x2202], &[8, 10, 12], 15);
+        let layout = GlyphSetLayout::parse(&blob).unwrap();
+        assert_eq!(layout.count, 3);
+        assert_eq!(layout.max_height, 15);
+        assert_eq!(layout.widths_offset, 28);
+        assert_eq!(layout.glyphs_offset, 32);
+        assert_eq!(layout.total_len, 32 + 3 * 32);
+        // trailing padding is fine
+        let mut padded = blob.clone();
+        padded.resize(4096, 0);
+        assert_eq!(GlyphSetLayout::parse(&padded).unwrap(), layout);
+    }
+    #[test]
+    fn parse_rejects_malformed() {
+        let good = make_blob(&[0x2200, 0x2201], &[8, 8], 15);
+        let mut bad_magic = good.clone();
+        bad_magic[0] = 0;
+        assert!(GlyphSetLayout::parse(&bad_magic).is_err());
+        assert!(GlyphSetLayout::parse(&good[..good.len() - 1]).is_err());
+        assert!(GlyphSetLayout::parse(&make_blob(&[0x2201, 0x2200], &[8, 8], 15)).is_err());
+        assert!(GlyphSetLayout::parse(&make_blob(&[0x2200, 0x2201], &[8, 17], 15)).is_err());
+        assert!(GlyphSetLayout::parse(&make_blob(&[0x2200, 0x2201], &[8, 8], 17)).is_err());
+        assert!(GlyphSetLayout::parse(&make_blob(&[0xD800], &[8], 15)).is_err());
+    }
+}
@@ -8,6 +8,35 @@ pub enum GlyphStyle {
     Cjk = 4,
     Large = 5,
     ExtraLarge = 6,
+    /// Glyph sets loaded at runtime with `Gfx::load_glyph_set()`. Characters missing from the set, or all
+    /// characters if the slot is empty, fall back to `Regular`.
+    Loaded0 = 7,
+    Loaded1 = 8,
+    Loaded2 = 9,
+    Loaded3 = 10,
+}
+impl GlyphStyle {
+    /// The glyph set slot used by this style, if it is one of the `Loaded*` styles
+    pub fn glyph_set_slot(&self) -> Option<usize> {
+        match self {
+            GlyphStyle::Loaded0 => Some(0),
+            GlyphStyle::Loaded1 => Some(1),
+            GlyphStyle::Loaded2 => Some(2),
+            GlyphStyle::Loaded3 => Some(3),
+            _ => None,
+        }
+    }
+    /// The style drawn in place of this one when the user has asked for large text, for low-vision use.
+    /// Latin text comes out at about twice its usual size; styles with no doubled font are left alone.
+    /// That includes `Monospace`: the doubled fonts are proportional, and code or tables drawn in them would
+    /// lose their columns.
+    pub fn enlarged(&self) -> GlyphStyle {
+        match self {
+            GlyphStyle::Small => GlyphStyle::Large,
+            GlyphStyle::Regular | GlyphStyle::Bold => GlyphStyle::ExtraLarge,
+            other => *other,
+        }
+    }
 }
 
 /// Convert number to style for use with register-based message passing sytems
@@ -22,6 +51,10 @@ impl From<usize> for GlyphStyle {
             4 => GlyphStyle::Cjk,
             5 => GlyphStyle::Large,
             6 => GlyphStyle::ExtraLarge,
+            7 => GlyphStyle::Loaded0,
+            8 => GlyphStyle::Loaded1,
+            9 => GlyphStyle::Loaded2,
+            10 => GlyphStyle::Loaded3,
             _ => GlyphStyle::Regular,
         }
     }
@@ -39,6 +72,10 @@ impl Into<usize> for GlyphStyle {
             GlyphStyle::Cjk => 4,
             GlyphStyle::Large => 5,
             GlyphStyle::ExtraLarge => 6,
+            GlyphStyle::Loaded0 => 7,
+            GlyphStyle::Loaded1 => 8,
+            GlyphStyle::Loaded2 => 9,
+            GlyphStyle::Loaded3 => 10,
         }
     }
 }
@@ -57,5 +94,7 @@ pub fn glyph_to_height_hint(g: GlyphStyle) -> usize {
         GlyphStyle::Cjk => 16, // crate::blistr2::fonts::emoji::MAX_HEIGHT as usize,
         GlyphStyle::Large => 24, // 2x of small
         GlyphStyle::ExtraLarge => 30, // 2x of regular
+        // the real height depends on what's loaded; the server substitutes it when answering QueryGlyphProps
+        GlyphStyle::Loaded0 | GlyphStyle::Loaded1 | GlyphStyle::Loaded2 | GlyphStyle::Loaded3 => 16,
     }
 }
\ No newline at end of file
@@ -0,0 +1,167 @@
+use crate::api::{DrawStyle, Gid, Point, Rectangle};
+
+/// Maximum number of vertices in a single polygon or polyline. Keeps a `Polygon` well inside one page.
+pub const MAX_POLYGON_POINTS: usize = 32;
+
+/// A polyline, or a closed polygon when `closed` is set. Open polylines are only ever stroked; a closed
+/// polygon is filled with the style's `fill_color` using the even-odd rule, then outlined with its
+/// `stroke_color`. Coordinates are local to the canvas when handed to the GAM; the GAM translates them to
+/// screen space and fills in `clip_rect`.
+#[derive(Debug, Copy, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct Polygon {
+    canvas: Gid,
+    pub clip_rect: Option<Rectangle>, // set by the GAM to the canvas' clip_rect, in screen coordinates
+    points: [Point; MAX_POLYGON_POINTS],
+    count: usize,
+    pub closed: bool,
+    pub style: DrawStyle,
+}
+impl Polygon {
+    pub fn new(canvas: Gid, closed: bool, style: DrawStyle) -> Polygon {
+        Polygon {
+            canvas,
+            clip_rect: None,
+            points: [Point::new(0, 0); MAX_POLYGON_POINTS],
+            count: 0,
+            closed,
+            style,
+        }
+    }
+    /// Appends a vertex. Hands the point back if the polygon is full.
+    pub fn push(&mut self, p: Point) -> Result<(), Point> {
+        if self.count < MAX_POLYGON_POINTS {
+            self.points[self.count] = p;
+            self.count += 1;
+            Ok(())
+        } else {
+            Err(p)
+        }
+    }
+    /// The vertices pushed so far. `count` comes over IPC as-is, so it's clamped to the capacity here.
+    pub fn points(&self) -> &[Point] {
+        &self.points[..self.count.min(MAX_POLYGON_POINTS)]
+    }
+    pub fn get_canvas_gid(&self) -> Gid {
+        self.canvas
+    }
+    pub fn translate(&mut self, offset: Point) {
+        let n = self.count.min(MAX_POLYGON_POINTS);
+        for p in self.points[..n].iter_mut() {
+            *p = *p + offset;
+        }
+    }
+    /// Bounding box of all the vertices, or `None` for an empty polygon
+    pub fn bounds(&self) -> Option<Rectangle> {
+        let pts = self.points();
+        let first = pts.first()?;
+        let mut r = Rectangle::new(*first, *first);
+        for p in pts.iter() {
+            r.tl.x = r.tl.x.min(p.x);
+            r.tl.y = r.tl.y.min(p.y);
+            r.br.x = r.br.x.max(p.x);
+            r.br.y = r.br.y.max(p.y);
+        }
+        Some(r)
+    }
+    /// The edges to stroke, as pairs of end points. A closed polygon includes the edge from its last vertex back to the first.
+    pub fn edges(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+        let pts = self.points();
+        let n = pts.len();
+        let edge_count = if self.closed && n > 2 { n } else { n.saturating_sub(1) };
+        (0..edge_count).map(move |i| (pts[i], pts[(i + 1) % n]))
+    }
+    /// Finds where the pixel row `y` crosses the outline, for scanline filling. The x coordinates are written
+    /// to `xs` in ascending order, and the number of crossings is returned. Even-odd pairs of crossings
+    /// bound the spans to fill. Each edge is treated as half-open in y, so a vertex that the outline passes
+    /// straight through is only counted once, while a peak is counted twice and yields an empty span.
+    pub fn crossings(&self, y: i16, xs: &mut [i16; MAX_POLYGON_POINTS]) -> usize {
+        let pts = self.points();
+        let n = pts.len();
+        if n < 3 {
+            return 0;
+        }
+        let mut count = 0;
+        for i in 0..n {
+            let (a, b) = (pts[i], pts[(i + 1) % n]);
+            if a.y == b.y {
+                continue; // horizontal edges are covered by the spans of the edges around them
+            }
+            let (top, bot) = if a.y < b.y { (a, b) } else { (b, a) };
+            if y >= top.y && y < bot.y {
+                // widened before subtracting, as vertices far apart overflow i16, and their product overflows i32
+                let (dy, dx, height) = (y as i64 - top.y as i64, bot.x as i64 - top.x as i64, bot.y as i64 - top.y as i64);
+                // the crossing lies between the edge's ends, so it fits back in an i16
+                xs[count] = (top.x as i64 + dy * dx / height) as i16;
+                count += 1;
+            }
+        }
+        xs[..count].sort_unstable();
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn triangle() -> Polygon {
+        let mut p = Polygon::new(Gid::new([0; 4]), true, DrawStyle::default());
+        p.push(Point::new(10, 0)).unwrap();
+        p.push(Point::new(20, 20)).unwrap();
+        p.push(Point::new(0, 20)).unwrap();
+        p
+    }
+    #[test]
+    fn polygon_bounds_and_edges() {
+        let mut p = triangle();
+        let b = p.bounds().unwrap();
+        assert_eq!(b.tl, Point::new(0, 0));
+        assert_eq!(b.br, Point::new(20, 20));
+        assert_eq!(p.edges().count(), 3);
+        p.closed = false;
+        assert_eq!(p.edges().count(), 2);
+        p.translate(Point::new(5, 5));
+        assert_eq!(p.points()[0], Point::new(15, 5));
+    }
+    #[test]
+    fn polygon_crossings() {
+        let p = triangle();
+        let mut xs = [0i16; MAX_POLYGON_POINTS];
+        assert_eq!(p.crossings(10, &mut xs), 2);
+        assert_eq!(&xs[..2], &[5, 15]);
+        // the apex is shared by two edges, but counted by both since it's the top of each
+        assert_eq!(p.crossings(0, &mut xs), 2);
+        assert_eq!(&xs[..2], &[10, 10]);
+        // the bottom edge is horizontal and the half-open rule excludes the last row
+        assert_eq!(p.crossings(20, &mut xs), 0);
+        assert_eq!(p.crossings(-1, &mut xs), 0);
+    }
+    #[test]
+    fn polygon_crossings_far_apart() {
+        let mut p = Polygon::new(Gid::new([0; 4]), true, DrawStyle::default());
+        p.push(Point::new(i16::MIN, i16::MIN)).unwrap();
+        p.push(Point::new(i16::MAX, i16::MAX)).unwrap();
+        p.push(Point::new(i16::MIN, i16::MAX)).unwrap();
+        let mut xs = [0i16; MAX_POLYGON_POINTS];
+        assert_eq!(p.crossings(0, &mut xs), 2);
+        assert_eq!(&xs[..2], &[i16::MIN, 0]);
+        assert_eq!(p.crossings(i16::MAX - 1, &mut xs), 2);
+        assert_eq!(&xs[..2], &[i16::MIN, i16::MAX - 1]);
+    }
+    #[test]
+    fn polygon_capacity() {
+        let mut p = Polygon::new(Gid::new([0; 4]), false, DrawStyle::default());
+        for i in 0..MAX_POLYGON_POINTS {
+            p.push(Point::new(i as i16, 0)).unwrap();
+        }
+        assert!(p.push(Point::new(0, 0)).is_err());
+    }
+    #[test]
+    fn polygon_forged_count() {
+        let mut p = triangle();
+        p.count = usize::MAX;
+        assert_eq!(p.points().len(), MAX_POLYGON_POINTS);
+        p.translate(Point::new(1, 1));
+        assert!(p.bounds().is_some());
+        assert!(p.push(Point::new(0, 0)).is_err());
+    }
+}
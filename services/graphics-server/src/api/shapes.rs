@@ -110,6 +110,14 @@ impl Rectangle {
         ((point.x >= self.tl.x) && (point.x <= self.br.x))
             && ((point.y >= self.tl.y) && (point.y <= self.br.y))
     }
+    /// returns the smallest Rectangle that contains both this and the other Rectangle. The style of `self` is kept.
+    pub fn union(&self, other: Rectangle) -> Rectangle {
+        Rectangle {
+            tl: Point::new(min(self.tl.x, other.tl.x), min(self.tl.y, other.tl.y)),
+            br: Point::new(max(self.br.x, other.br.x), max(self.br.y, other.br.y)),
+            style: self.style,
+        }
+    }
     /// takes the current Rectangle, and clips it with a clipping Rectangle; returns a new rectangle as the result
     pub fn clip_with(&self, clip: Rectangle) -> Option<Rectangle> {
         // check to see if we even overlap; if not, don't do any computation
@@ -288,6 +296,13 @@ impl Line {
         self.start = self.start + offset;
         self.end = self.end + offset;
     }
+    /// bounding box of the pixels touched by the line
+    pub fn bounds(&self) -> Rectangle {
+        Rectangle::new(
+            Point::new(min(self.start.x, self.end.x), min(self.start.y, self.end.y)),
+            Point::new(max(self.start.x, self.end.x), max(self.start.y, self.end.y)),
+        )
+    }
 }
 
 //////////////////////////// CIRCLE
@@ -319,6 +334,143 @@ impl Circle {
     pub fn translate(&mut self, offset: Point) {
         self.center = self.center + offset;
     }
+    /// bounding box of the pixels touched by the circle
+    pub fn bounds(&self) -> Rectangle {
+        Rectangle::new(
+            Point::new(self.center.x - self.radius, self.center.y - self.radius),
+            Point::new(self.center.x + self.radius, self.center.y + self.radius),
+        )
+    }
+}
+
+//////////////////////////// ARC
+
+/// sin(degrees) * 1024, for 0 through 90 degrees
+const SIN_TABLE: [i32; 91] = [
+    0, 18, 36, 54, 71, 89, 107, 125, 143, 160,
+    178, 195, 213, 230, 248, 265, 282, 299, 316, 333,
+    350, 367, 384, 400, 416, 433, 449, 465, 481, 496,
+    512, 527, 543, 558, 573, 587, 602, 616, 630, 644,
+    658, 672, 685, 698, 711, 724, 737, 749, 761, 773,
+    784, 796, 807, 818, 828, 839, 849, 859, 868, 878,
+    887, 896, 904, 912, 920, 928, 935, 943, 949, 956,
+    962, 968, 974, 979, 984, 989, 994, 998, 1002, 1005,
+    1008, 1011, 1014, 1016, 1018, 1020, 1022, 1023, 1023, 1024,
+    1024,
+];
+
+fn sin_deg(degrees: i32) -> i32 {
+    let d = degrees.rem_euclid(360);
+    match d {
+        0..=90 => SIN_TABLE[d as usize],
+        91..=180 => SIN_TABLE[(180 - d) as usize],
+        181..=270 => -SIN_TABLE[(d - 180) as usize],
+        _ => -SIN_TABLE[(360 - d) as usize],
+    }
+}
+
+/// Direction of `degrees` clockwise from 12 o'clock as a vector scaled by 1024, in screen coordinates (y grows down)
+fn angle_vector(degrees: i16) -> (i32, i32) {
+    (sin_deg(degrees as i32), -sin_deg(degrees as i32 + 90))
+}
+
+/// An arc of a circle, or a pie slice if `pie` is set. Angles are in degrees, measured clockwise from
+/// 12 o'clock, and the arc runs clockwise from `start_angle` to `end_angle`; `end_angle = start_angle + 360`
+/// draws the whole circle. The arc itself is `style.stroke_width` pixels thick and drawn inwards from
+/// `radius` with `style.stroke_color`. A pie slice is also filled with `style.fill_color`, and its two
+/// radii are stroked.
+#[derive(Debug, Clone, Copy, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct Arc {
+    pub center: Point,
+    pub radius: i16,
+    pub start_angle: i16,
+    pub end_angle: i16,
+    pub pie: bool,
+
+    /// Drawing style
+    pub style: DrawStyle,
+}
+
+impl Arc {
+    pub fn new(c: Point, r: i16, start_angle: i16, end_angle: i16) -> Arc {
+        Arc {
+            center: c,
+            radius: r,
+            start_angle,
+            end_angle,
+            pie: false,
+            style: DrawStyle::default(),
+        }
+    }
+    pub fn new_with_style(c: Point, r: i16, start_angle: i16, end_angle: i16, style: DrawStyle) -> Arc {
+        Arc {
+            center: c,
+            radius: r,
+            start_angle,
+            end_angle,
+            pie: false,
+            style,
+        }
+    }
+    /// A filled pie slice, e.g. for a countdown indicator
+    pub fn new_pie(c: Point, r: i16, start_angle: i16, end_angle: i16, style: DrawStyle) -> Arc {
+        Arc {
+            center: c,
+            radius: r,
+            start_angle,
+            end_angle,
+            pie: true,
+            style,
+        }
+    }
+    pub fn translate(&mut self, offset: Point) {
+        self.center = self.center + offset;
+    }
+    /// bounding box of the full circle the arc is part of
+    pub fn bounds(&self) -> Rectangle {
+        Rectangle::new(
+            Point::new(self.center.x - self.radius, self.center.y - self.radius),
+            Point::new(self.center.x + self.radius, self.center.y + self.radius),
+        )
+    }
+    /// The number of degrees covered, from 0 to 360
+    pub fn sweep(&self) -> i32 {
+        let sweep = (self.end_angle as i32 - self.start_angle as i32).rem_euclid(360);
+        if sweep == 0 && self.end_angle != self.start_angle {
+            360
+        } else {
+            sweep
+        }
+    }
+    /// The point on the circle at `degrees`
+    pub fn point_at(&self, degrees: i16) -> Point {
+        let (vx, vy) = angle_vector(degrees);
+        Point::new(
+            self.center.x + ((self.radius as i32 * vx) / 1024) as i16,
+            self.center.y + ((self.radius as i32 * vy) / 1024) as i16,
+        )
+    }
+    /// True if the direction of the offset (`dx`, `dy`) from the center lies between the start and end angles
+    pub fn contains_angle(&self, dx: i16, dy: i16) -> bool {
+        let sweep = self.sweep();
+        if sweep == 0 {
+            return false;
+        }
+        if sweep >= 360 || (dx == 0 && dy == 0) {
+            return true;
+        }
+        let cross = |a: (i32, i32), b: (i32, i32)| a.0 * b.1 - a.1 * b.0;
+        let p = (dx as i32, dy as i32);
+        let a = angle_vector(self.start_angle);
+        let b = angle_vector(self.end_angle);
+        // in screen coordinates, going clockwise makes the cross product positive
+        if sweep <= 180 {
+            cross(a, p) >= 0 && cross(p, b) >= 0
+        } else {
+            // reflex sectors are the complement of the sector from the end back to the start
+            !(cross(b, p) > 0 && cross(p, a) > 0)
+        }
+    }
 }
 
 //////////////////////// Rounded Rectangle
@@ -391,4 +543,45 @@ mod tests {
         // to the right of
         assert!(!a.intersects(Rectangle::new(Point::new(101, 0), Point::new(150, 150),)));
     }
+    #[test]
+    fn union_test() {
+        let a = Rectangle::new(Point::new(10, 10), Point::new(20, 20));
+        let b = Rectangle::new(Point::new(5, 15), Point::new(30, 18));
+        let u = a.union(b);
+        assert_eq!(u.tl, Point::new(5, 10));
+        assert_eq!(u.br, Point::new(30, 20));
+        // enclosed rectangles don't grow the union
+        let u = a.union(Rectangle::new(Point::new(12, 12), Point::new(14, 14)));
+        assert_eq!(u.tl, a.tl);
+        assert_eq!(u.br, a.br);
+    }
+    #[test]
+    fn arc_sweep_test() {
+        let c = Point::new(0, 0);
+        assert_eq!(Arc::new(c, 10, 0, 90).sweep(), 90);
+        assert_eq!(Arc::new(c, 10, 270, 90).sweep(), 180);
+        assert_eq!(Arc::new(c, 10, 0, 360).sweep(), 360);
+        assert_eq!(Arc::new(c, 10, 45, 45).sweep(), 0);
+        assert_eq!(Arc::new(c, 10, 0, 0).point_at(90), Point::new(10, 0));
+        assert_eq!(Arc::new(c, 10, 0, 0).point_at(180), Point::new(0, 10));
+    }
+    #[test]
+    fn arc_contains_test() {
+        let c = Point::new(0, 0);
+        // 12 o'clock to 3 o'clock covers the top right quadrant
+        let a = Arc::new(c, 10, 0, 90);
+        assert!(a.contains_angle(5, -5));
+        assert!(a.contains_angle(0, -5));
+        assert!(a.contains_angle(5, 0));
+        assert!(!a.contains_angle(-5, -5));
+        assert!(!a.contains_angle(5, 5));
+        // the reflex complement covers everything but the top right quadrant
+        let b = Arc::new(c, 10, 90, 360);
+        assert!(!b.contains_angle(5, -5));
+        assert!(b.contains_angle(-5, -5));
+        assert!(b.contains_angle(5, 5));
+        assert!(b.contains_angle(-5, 5));
+        assert!(!Arc::new(c, 10, 30, 30).contains_angle(5, 5));
+        assert!(Arc::new(c, 10, 30, 390).contains_angle(5, 5));
+    }
 }
@@ -1,5 +1,6 @@
 use crate::api::{Gid, Point, Rectangle, GlyphStyle, Cursor};
 use xous_ipc::String;
+use core::convert::TryFrom;
 use core::ops::Add;
 
 /// coordinates are local to the canvas, not absolute to the screen
@@ -68,6 +69,27 @@ impl From<usize> for TextOp {
 // roughly 168 bytes to represent the rest of the struct, and we want to fill out the 4096 byte page with text
 const TEXTVIEW_LEN: usize = 3072;
 
+/// Maximum number of style runs in a single TextView
+pub const MAX_STYLE_SPANS: usize = 8;
+
+/// A run of text that is drawn in a different style from the rest of its `TextView`, e.g. a bold name
+/// followed by a code in monospace. Offsets count characters, not bytes, and `end` is exclusive.
+#[derive(Debug, Copy, Clone, PartialEq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct StyleSpan {
+    pub start: u32,
+    pub end: u32,
+    pub style: GlyphStyle,
+}
+impl StyleSpan {
+    /// Style of the character at `pos`. Where spans overlap, the one added last wins; characters outside
+    /// of any span are drawn in `base`.
+    pub fn style_at(spans: &[StyleSpan], pos: usize, base: GlyphStyle) -> GlyphStyle {
+        spans.iter().rev()
+            .find(|s| pos >= s.start as usize && pos < s.end as usize)
+            .map_or(base, |s| s.style)
+    }
+}
+
 #[derive(Copy, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
 pub struct TextView {
     // this is the operation as specified for the GAM. Note this is different from the "op" when sent to graphics-server
@@ -75,6 +97,7 @@ pub struct TextView {
     operation: TextOp,
     canvas: Gid,                      // GID of the canvas to draw on
     pub clip_rect: Option<Rectangle>, // this is set by the GAM to the canvas' clip_rect; needed by gfx for drawing. Note this is in screen coordinates.
+    pub draw_clip: Option<Rectangle>, // also set by the GAM, to the part of the canvas the app lets the text land on; layout still follows clip_rect. Screen coordinates.
 
     pub untrusted: bool, // render content with random stipples to indicate the strings within are untrusted
     pub token: Option<[u32; 4]>, // optional 128-bit token which is presented to prove a field's trustability
@@ -90,7 +113,8 @@ pub struct TextView {
     pub style: GlyphStyle,
     pub cursor: Cursor,
     pub insertion: Option<i32>, // this is the insertion point offset, if it's to be drawn, on the string
-    pub ellipsis: bool,
+    pub ellipsis: bool, // on overflow, end the text with an ellipsis. Otherwise the text is just truncated.
+    pub max_lines: Option<u16>, // text that needs more lines than this overflows, even if the bounds could fit more; None is no limit
 
     pub draw_border: bool,
     pub clear_area: bool, // you almost always want this to be true
@@ -101,6 +125,9 @@ pub struct TextView {
     // this field specifies the beginning and end of a "selected" region of text
     pub selected: Option<[u32; 2]>,
 
+    /// runs of text that don't use `style`; see `append_styled()`
+    spans: [Option<StyleSpan>; MAX_STYLE_SPANS],
+
     pub text: String<3072>,
 }
 impl TextView {
@@ -112,6 +139,7 @@ impl TextView {
             token: None,
             invert: false,
             clip_rect: None,
+            draw_clip: None,
             bounds_hint,
             bounds_computed: None,
             style: GlyphStyle::Regular,
@@ -119,11 +147,13 @@ impl TextView {
             cursor: Cursor::new(0, 0, 0),
             insertion: None,
             ellipsis: false,
+            max_lines: None,
             draw_border: true,
             border_width: 1,
             rounded_border: None,
             margin: Point { x: 4, y: 4 },
             selected: None,
+            spans: [None; MAX_STYLE_SPANS],
             clear_area: true,
             overflow: None,
             dry_run: false,
@@ -149,8 +179,38 @@ impl TextView {
         self.text.as_str().unwrap()
     }
 
+    /// Clears the text, along with any style runs that were set on it
     pub fn clear_str(&mut self) {
-        self.text.clear()
+        self.text.clear();
+        self.spans = [None; MAX_STYLE_SPANS];
+    }
+
+    /// Appends `s`, drawn in `style` instead of the view's own `style`. Text added with `write!()` keeps
+    /// using the view's style. Fails without changing anything if the text or the style runs are full.
+    pub fn append_styled(&mut self, s: &str, style: GlyphStyle) -> Result<(), xous::Error> {
+        if self.text.len() + s.len() > TEXTVIEW_LEN {
+            return Err(xous::Error::OutOfMemory);
+        }
+        let slot = self.spans.iter_mut().find(|span| span.is_none()).ok_or(xous::Error::OutOfMemory)?;
+        let start = self.to_str().chars().count() as u32;
+        *slot = Some(StyleSpan {
+            start,
+            end: start + s.chars().count() as u32,
+            style,
+        });
+        self.text.append(s).or(Err(xous::Error::OutOfMemory)).map(|_| ())
+    }
+
+    pub fn style_spans(&self) -> impl Iterator<Item = &StyleSpan> {
+        self.spans.iter().filter_map(|s| s.as_ref())
+    }
+
+    /// Swaps the view's style, and the styles of its runs, for their `GlyphStyle::enlarged()` versions
+    pub fn enlarge(&mut self) {
+        self.style = self.style.enlarged();
+        for span in self.spans.iter_mut().flatten() {
+            span.style = span.style.enlarged();
+        }
     }
 
     pub fn populate_from(&mut self, t: &TextView) {
@@ -170,13 +230,41 @@ impl TextView {
         self.rounded_border = t.rounded_border;
         self.margin = t.margin;
         self.selected = t.selected;
+        self.spans = t.spans;
         self.overflow = t.overflow;
         self.clip_rect = t.clip_rect;
+        self.draw_clip = t.draw_clip;
         self.dry_run = t.dry_run;
         self.insertion = t.insertion;
     }
 }
 
+/// A string to be sized up without drawing it, so it can be placed before its `TextView` is posted.
+/// The text is laid out just as a `TextView` of `max_width` with no margin, border or style spans would be.
+#[derive(Debug, Copy, Clone, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct TextMeasurement {
+    pub text: String<3072>,
+    pub style: GlyphStyle,
+    pub max_width: Option<u16>, // lines wrap at this many pixels; None breaks lines only at newlines
+    pub bounds: Option<Point>, // set by the graphics server to the width and height the text takes up
+}
+impl TextMeasurement {
+    pub fn new(text: &str, style: GlyphStyle, max_width: Option<u16>) -> Self {
+        TextMeasurement {
+            text: String::<3072>::from_str(text),
+            style,
+            max_width,
+            bounds: None,
+        }
+    }
+    /// Records the size the text came out at. A few thousand short lines can be taller than a `Point`
+    /// reaches, so the size is held at `i16::MAX` rather than wrapping around to a negative one.
+    pub fn set_bounds(&mut self, width: usize, height: usize) {
+        let clamp = |v: usize| i16::try_from(v).unwrap_or(i16::MAX);
+        self.bounds = Some(Point::new(clamp(width), clamp(height)));
+    }
+}
+
 // Allow a `&TextView` to be used anywhere that expects a `&str`
 impl AsRef<str> for TextView {
     fn as_ref(&self) -> &str {
@@ -213,3 +301,63 @@ impl core::fmt::Write for TextView {
         write!(self.text, "{}", s)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+    #[test]
+    fn styled_runs() {
+        let mut tv = TextView::new(Gid::new([0; 4]), TextBounds::GrowableFromTl(Point::new(0, 0), 100));
+        tv.append_styled("Bank", GlyphStyle::Bold).unwrap();
+        write!(tv, ": ").unwrap();
+        tv.append_styled("123 456", GlyphStyle::Monospace).unwrap();
+        let spans: Vec<StyleSpan> = tv.style_spans().copied().collect();
+        assert_eq!(spans.len(), 2);
+        assert_eq!((spans[1].start, spans[1].end), (6, 13));
+        assert_eq!(StyleSpan::style_at(&spans, 0, GlyphStyle::Regular), GlyphStyle::Bold);
+        assert_eq!(StyleSpan::style_at(&spans, 4, GlyphStyle::Regular), GlyphStyle::Regular);
+        assert_eq!(StyleSpan::style_at(&spans, 12, GlyphStyle::Regular), GlyphStyle::Monospace);
+        assert_eq!(StyleSpan::style_at(&spans, 13, GlyphStyle::Regular), GlyphStyle::Regular);
+        // offsets are in characters, so multi-byte text doesn't shift later runs
+        tv.clear_str();
+        assert_eq!(tv.style_spans().count(), 0);
+        tv.append_styled("é", GlyphStyle::Small).unwrap();
+        tv.append_styled("x", GlyphStyle::Bold).unwrap();
+        assert_eq!(tv.style_spans().nth(1).unwrap().start, 1);
+    }
+    #[test]
+    fn styled_runs_capacity() {
+        let mut tv = TextView::new(Gid::new([0; 4]), TextBounds::GrowableFromTl(Point::new(0, 0), 100));
+        for _ in 0..MAX_STYLE_SPANS {
+            tv.append_styled("a", GlyphStyle::Bold).unwrap();
+        }
+        assert!(tv.append_styled("b", GlyphStyle::Bold).is_err());
+        assert_eq!(tv.to_str(), "aaaaaaaa");
+    }
+    #[test]
+    fn measured_bounds() {
+        let mut m = TextMeasurement::new("hello", GlyphStyle::Regular, None);
+        m.set_bounds(31, 15);
+        assert_eq!(m.bounds, Some(Point::new(31, 15)));
+        m.set_bounds(i16::MAX as usize, 0);
+        assert_eq!(m.bounds, Some(Point::new(i16::MAX, 0)));
+        // taller than a Point can say, which used to come out negative
+        m.set_bounds(200, 49_152);
+        assert_eq!(m.bounds, Some(Point::new(200, i16::MAX)));
+    }
+    #[test]
+    fn enlarged_styles() {
+        let mut tv = TextView::new(Gid::new([0; 4]), TextBounds::GrowableFromTl(Point::new(0, 0), 100));
+        tv.append_styled("code", GlyphStyle::Monospace).unwrap();
+        tv.append_styled("note", GlyphStyle::Small).unwrap();
+        tv.append_styled("漢字", GlyphStyle::Cjk).unwrap();
+        tv.enlarge();
+        assert_eq!(tv.style, GlyphStyle::ExtraLarge);
+        let styles: Vec<GlyphStyle> = tv.style_spans().map(|s| s.style).collect();
+        assert_eq!(styles, vec![GlyphStyle::Monospace, GlyphStyle::Large, GlyphStyle::Cjk]);
+        // enlarging twice changes nothing more
+        tv.enlarge();
+        assert_eq!(tv.style, GlyphStyle::ExtraLarge);
+    }
+}
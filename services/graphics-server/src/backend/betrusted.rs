@@ -17,6 +17,9 @@ pub struct XousDisplay {
     srfb: ManagedMem<{ utralib::generated::HW_MEMLCD_MEM_LEN / core::mem::size_of::<u32>() }>,
     csr: utralib::CSR<u32>,
     susres: RegManager<{ utra::memlcd::MEMLCD_NUMREGS }>,
+    /// when set, every pixel is flipped on its way to the hardware frame buffer, so the frame buffer
+    /// that everything is composed into keeps its normal sense
+    inverted: bool,
 }
 
 impl XousDisplay {
@@ -63,6 +66,7 @@ impl XousDisplay {
             csr: CSR::new(control.as_mut_ptr() as *mut u32),
             susres: RegManager::new(control.as_mut_ptr() as *mut u32),
             srfb: ManagedMem::new(hwfb),
+            inverted: false,
         };
 
         display.set_clock(CONFIG_CLOCK_FREQUENCY);
@@ -134,37 +138,77 @@ impl XousDisplay {
     }
 
     pub fn redraw(&mut self) {
+        self.redraw_lines(0, FB_LINES - 1);
+    }
+
+    /// Pushes the dirty lines between `start` and `end` (inclusive) to the LCD. Only lines that have
+    /// their dirty bit set are copied to the hardware frame buffer; clean lines are already identical
+    /// to what the LCD is showing, so there is no point in touching them.
+    pub fn redraw_lines(&mut self, start: usize, end: usize) {
         let mut busy_count = 0;
         let mut dirty_count = 0;
+        let end = if end >= FB_LINES { FB_LINES - 1 } else { end };
+        if start > end {
+            return;
+        }
         while self.busy() {
             xous::yield_slice();
             busy_count += 1;
         }
         let fb: *mut [u32; FB_SIZE] = self.fb.as_mut_ptr() as *mut [u32; FB_SIZE];
         let hwfb: *mut [u32; FB_SIZE] = self.hwfb.as_mut_ptr() as *mut [u32; FB_SIZE];
-        for words in 0..FB_SIZE {
-            unsafe {
-                (*hwfb)[words] = (*fb)[words];
-            }
-        }
-        self.update_dirty();
-        // clear all the dirty bits, under the theory that it's time-wise cheaper on average
-        // to visit every line and clear the dirty bits than it is to do an update_all()
-        for lines in 0..FB_LINES {
-            if unsafe { (*fb)[lines * FB_WIDTH_WORDS + (FB_WIDTH_WORDS - 1)] & 0xFFFF_0000 } != 0x0
-            {
+        for lines in start..=end {
+            let dirty_word = lines * FB_WIDTH_WORDS + (FB_WIDTH_WORDS - 1);
+            if unsafe { (*fb)[dirty_word] & 0xFFFF_0000 } != 0x0 {
                 dirty_count += 1;
-            }
-            unsafe {
-                (*fb)[lines * FB_WIDTH_WORDS + (FB_WIDTH_WORDS - 1)] &= 0x0000_FFFF;
+                for words in lines * FB_WIDTH_WORDS..(lines + 1) * FB_WIDTH_WORDS {
+                    // the last word of a line only carries pixels in its lower half; the rest is the dirty bit
+                    let mask = if !self.inverted {
+                        0
+                    } else if words == dirty_word {
+                        0x0000_FFFF
+                    } else {
+                        0xFFFF_FFFF
+                    };
+                    unsafe {
+                        (*hwfb)[words] = (*fb)[words] ^ mask;
+                    }
+                }
+                // the hardware copy keeps the dirty bit so update_dirty() picks it up; clear ours
+                unsafe {
+                    (*fb)[dirty_word] &= 0x0000_FFFF;
+                }
+            } else {
+                // a clean line may still carry a dirty bit in the hardware buffer from a previous push
+                unsafe {
+                    (*hwfb)[dirty_word] &= 0x0000_FFFF;
+                }
             }
         }
-        log::trace!("redraw {}/{}", busy_count, dirty_count);
+        if dirty_count > 0 {
+            self.update_dirty();
+        }
+        log::trace!("redraw {}-{}: {}/{}", start, end, busy_count, dirty_count);
     }
 
     // note: this API is used by emulation, don't remove calls to it
     pub fn update(&mut self) {}
 
+    /// Switches between dark-on-light (normal) and light-on-dark output, and repaints the whole screen
+    pub fn set_inverted(&mut self, inverted: bool) {
+        if self.inverted == inverted {
+            return;
+        }
+        self.inverted = inverted;
+        let fb: *mut [u32; FB_SIZE] = self.fb.as_mut_ptr() as *mut [u32; FB_SIZE];
+        for lines in 0..FB_LINES {
+            unsafe {
+                (*fb)[lines * FB_WIDTH_WORDS + (FB_WIDTH_WORDS - 1)] |= 0x1_0000;
+            }
+        }
+        self.redraw();
+    }
+
     pub fn native_buffer(&mut self) -> &mut [u32; FB_SIZE] {
         unsafe { &mut *(self.fb.as_mut_ptr() as *mut [u32; FB_SIZE]) }
     }
@@ -20,8 +20,10 @@ const LIGHT_COLOUR: u32 = 0x1B1B19;
 pub struct XousDisplay {
     native_buffer: Vec<u32>, //[u32; WIDTH * HEIGHT],
     emulated_buffer: [u32; FB_SIZE],
-    window: Window,
+    /// None when built `headless`: the frame buffer is then only ever read back with a screenshot
+    window: Option<Window>,
     devboot: bool,
+    inverted: bool,
 }
 
 struct XousKeyboardHandler {
@@ -32,6 +34,24 @@ struct XousKeyboardHandler {
 
 impl XousDisplay {
     pub fn new() -> XousDisplay {
+        let native_buffer = vec![DARK_COLOUR; WIDTH * HEIGHT];
+        // headless runs don't connect to the keyboard either, as there's no window to type into; keys are
+        // injected by whatever is driving the run, using the connection this would have taken
+        let window = if cfg!(feature = "headless") {
+            None
+        } else {
+            Some(XousDisplay::open_window(&native_buffer))
+        };
+
+        XousDisplay {
+            native_buffer,
+            window,
+            emulated_buffer: [0u32; FB_SIZE],
+            devboot: true,
+            inverted: false,
+        }
+    }
+    fn open_window(native_buffer: &[u32]) -> Window {
         let mut window = Window::new(
             "Precursor",
             WIDTH,
@@ -51,9 +71,8 @@ impl XousDisplay {
         //     1000 * 1000 / MAX_FPS,
         // )));
 
-        let native_buffer = vec![DARK_COLOUR; WIDTH * HEIGHT];
         window
-            .update_with_buffer(&native_buffer, WIDTH, HEIGHT)
+            .update_with_buffer(native_buffer, WIDTH, HEIGHT)
             .unwrap();
 
         let xns = xous_names::XousNames::new().unwrap();
@@ -65,13 +84,7 @@ impl XousDisplay {
             right_shift: false,
         });
         window.set_input_callback(keyboard_handler);
-
-        XousDisplay {
-            native_buffer,
-            window,
-            emulated_buffer: [0u32; FB_SIZE],
-            devboot: true,
-        }
+        window
     }
     pub fn set_devboot(&mut self, ena: bool) {
         if ena {
@@ -79,6 +92,10 @@ impl XousDisplay {
         }
         // ignore attempts to turn off devboot
     }
+    pub fn set_inverted(&mut self, inverted: bool) {
+        self.inverted = inverted;
+        self.redraw();
+    }
     pub fn suspend(&self, _flag: bool) {}
     pub fn resume(&self, _flag: bool) {}
 
@@ -100,17 +117,32 @@ impl XousDisplay {
     }
 
     pub fn redraw(&mut self) {
+        if self.window.is_none() {
+            return;
+        }
         self.emulated_to_native();
-        self.window
-            .update_with_buffer(&self.native_buffer, WIDTH, HEIGHT)
-            .unwrap();
+        if let Some(window) = self.window.as_mut() {
+            window
+                .update_with_buffer(&self.native_buffer, WIDTH, HEIGHT)
+                .unwrap();
+        }
+    }
+
+    /// the emulated display has no notion of partial updates, so just redraw everything
+    pub fn redraw_lines(&mut self, _start: usize, _end: usize) {
+        self.redraw();
     }
 
     pub fn update(&mut self) {
+        if self.window.is_none() {
+            return;
+        }
         self.emulated_to_native();
-        self.window.update();
-        if !self.window.is_open() || self.window.is_key_down(Key::Escape) {
-            std::process::exit(0);
+        if let Some(window) = self.window.as_mut() {
+            window.update();
+            if !window.is_open() || window.is_key_down(Key::Escape) {
+                std::process::exit(0);
+            }
         }
     }
 
@@ -128,7 +160,7 @@ impl XousDisplay {
                         // try to render the devboot defile somewhat accurately
                         *dest = LIGHT_COLOUR
                     } else {
-                        *dest = if src_cell & (1 << bit) != 0 {
+                        *dest = if (src_cell & (1 << bit) != 0) != self.inverted {
                             DARK_COLOUR
                         } else {
                             LIGHT_COLOUR
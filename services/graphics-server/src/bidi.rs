@@ -0,0 +1,192 @@
+//! A minimal take on the Unicode bidirectional algorithm, good enough for short strings such as account
+//! names and SSIDs that mix Hebrew or Arabic with Latin text and digits.
+//!
+//! The typesetter lays text out in logical order. Once a composition is done, `reorder_line()` is run over
+//! each line to put its words into visual order:
+//!   - the base direction is taken from the first strongly-directional character of the text (rule P2)
+//!   - digits are treated as left-to-right, so numbers embedded in right-to-left text read correctly
+//!   - neutral words (spaces, punctuation) take the direction of their neighbors if both agree, otherwise the base direction
+//!   - runs of words that go against the base direction are reversed as a unit, and the glyphs within
+//!     right-to-left words are reversed
+//!
+//! Explicit embedding controls, mirroring of brackets and Arabic contextual shaping are not handled.
+
+use crate::api::TypesetWord;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    Ltr,
+    Rtl,
+    Neutral,
+}
+
+/// Classifies a character by its bidi strength
+pub fn char_direction(ch: char) -> Direction {
+    match ch as u32 {
+        0x0590..=0x08FF // Hebrew, Arabic, Syriac, Thaana, NKo, Samaritan, Mandaic and Arabic extensions
+        | 0xFB1D..=0xFDFF // Hebrew and Arabic presentation forms A
+        | 0xFE70..=0xFEFF // Arabic presentation forms B
+        | 0x10800..=0x10FFF // historic right-to-left scripts
+        | 0x1E800..=0x1EFFF // Adlam, Mende Kikakui, Arabic mathematical symbols
+        => Direction::Rtl,
+        _ => {
+            if ch.is_alphanumeric() {
+                Direction::Ltr
+            } else {
+                Direction::Neutral
+            }
+        }
+    }
+}
+
+/// Direction of a word, as set by its first strong character
+pub(crate) fn word_direction(word: &TypesetWord) -> Direction {
+    word.gs.iter()
+        .map(|gs| char_direction(gs.ch))
+        .find(|d| *d != Direction::Neutral)
+        .unwrap_or(Direction::Neutral)
+}
+
+/// Returns the base direction of the composition, or `None` if it doesn't need reordering at all
+pub(crate) fn base_direction(words: &[TypesetWord]) -> Option<Direction> {
+    let base = words.iter().map(word_direction).find(|d| *d != Direction::Neutral)?;
+    if base == Direction::Ltr && !words.iter().any(|w| word_direction(w) == Direction::Rtl) {
+        None
+    } else {
+        Some(base)
+    }
+}
+
+/// Puts the words of a single typeset line into visual order, keeping the line within the horizontal span
+/// that it occupied in logical order. Non-drawable words (the placeholder space at the start of a wrapped
+/// line) stay where they are.
+pub(crate) fn reorder_line(line: &mut [TypesetWord], base: Direction) {
+    let first = line.iter().position(|w| !w.non_drawable).unwrap_or(line.len());
+    let line = &mut line[first..];
+    if line.is_empty() {
+        return;
+    }
+    let x0 = line.iter().map(|w| w.origin.x).min().unwrap();
+
+    let logical: Vec<Direction> = line.iter().map(word_direction).collect();
+    let mut dirs = logical.clone();
+    for i in 0..dirs.len() {
+        if logical[i] == Direction::Neutral {
+            let prev = logical[..i].iter().rev().find(|d| **d != Direction::Neutral);
+            let next = logical[i + 1..].iter().find(|d| **d != Direction::Neutral);
+            dirs[i] = match (prev, next) {
+                (Some(p), Some(n)) if p == n => *p,
+                _ => base,
+            };
+        }
+    }
+
+    for (word, dir) in line.iter_mut().zip(dirs.iter()) {
+        if *dir == Direction::Rtl {
+            reverse_glyphs(word);
+        }
+    }
+
+    let n = line.len();
+    let mut order: Vec<usize> = (0..n).collect();
+    if base == Direction::Rtl {
+        order.reverse();
+    }
+    // runs against the base direction read in the opposite order of their surroundings
+    let mut i = 0;
+    while i < n {
+        if dirs[order[i]] != base {
+            let mut j = i;
+            while j < n && dirs[order[j]] != base {
+                j += 1;
+            }
+            order[i..j].reverse();
+            i = j;
+        } else {
+            i += 1;
+        }
+    }
+
+    let mut x = x0;
+    for &index in order.iter() {
+        line[index].origin.x = x;
+        x += line[index].width;
+    }
+}
+
+/// Reverses the glyphs of a right-to-left word. The insertion point is drawn on the left edge of the glyph
+/// that carries it, so it moves over by one to stay on the logical "before" side of its character.
+fn reverse_glyphs(word: &mut TypesetWord) {
+    let n = word.gs.len();
+    let inserts: Vec<bool> = word.gs.iter().map(|gs| gs.insert).collect();
+    word.gs.reverse();
+    for gs in word.gs.iter_mut() {
+        gs.insert = false;
+    }
+    for (logical, insert) in inserts.iter().enumerate() {
+        if *insert {
+            let visual = n - 1 - logical;
+            word.gs[(visual + 1).min(n - 1)].insert = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{GlyphSprite, Pt};
+    use crate::blitstr2::NULL_GLYPH_SPRITE;
+
+    fn word(s: &str, x: usize) -> TypesetWord {
+        let mut w = TypesetWord::new(Pt::new(x, 0), 0);
+        for ch in s.chars() {
+            w.push(GlyphSprite { ch, ..NULL_GLYPH_SPRITE });
+        }
+        w
+    }
+    fn layout(words: &[TypesetWord]) -> Vec<(String, usize)> {
+        let mut v: Vec<(String, usize)> = words.iter().map(|w| (w.gs.iter().map(|g| g.ch).collect(), w.origin.x)).collect();
+        v.sort_by_key(|(_, x)| *x);
+        v
+    }
+    fn set_line(text: &[&str]) -> Vec<TypesetWord> {
+        let mut x = 0;
+        let mut words = Vec::new();
+        for t in text {
+            let w = word(t, x);
+            x += w.width;
+            words.push(w);
+        }
+        words
+    }
+    #[test]
+    fn classify() {
+        assert_eq!(char_direction('a'), Direction::Ltr);
+        assert_eq!(char_direction('7'), Direction::Ltr);
+        assert_eq!(char_direction('ש'), Direction::Rtl);
+        assert_eq!(char_direction('ب'), Direction::Rtl);
+        assert_eq!(char_direction(' '), Direction::Neutral);
+        assert_eq!(base_direction(&set_line(&["hello", " ", "world"])), None);
+        assert_eq!(base_direction(&set_line(&["wifi", " ", "שלום"])), Some(Direction::Ltr));
+    }
+    #[test]
+    fn rtl_in_ltr() {
+        // logical: "wifi אב גד" -> visual: "wifi דג בא"
+        let mut words = set_line(&["wifi", " ", "אב", " ", "גד"]);
+        reorder_line(&mut words, Direction::Ltr);
+        let visual: Vec<String> = layout(&words).into_iter().map(|(s, _)| s).collect();
+        assert_eq!(visual, vec!["wifi", " ", "דג", " ", "בא"]);
+    }
+    #[test]
+    fn ltr_in_rtl() {
+        // logical: "אב 123" with an RTL base -> visual: "123 בא"
+        let mut words = set_line(&["אב", " ", "123"]);
+        let width = words.iter().map(|w| w.width).sum::<usize>();
+        reorder_line(&mut words, Direction::Rtl);
+        let visual = layout(&words);
+        assert_eq!(visual.iter().map(|(s, _)| s.as_str()).collect::<Vec<_>>(), vec!["123", " ", "בא"]);
+        // the line still covers the same span
+        assert_eq!(visual[0].1, 0);
+        assert_eq!(words.iter().map(|w| w.origin.x + w.width).max().unwrap(), width);
+    }
+}
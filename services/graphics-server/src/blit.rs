@@ -0,0 +1,250 @@
+//! Bulk frame buffer operations. These work a word (32 pixels) at a time instead of a pixel at a time,
+//! and hand runs of whole lines to `copy_from_slice`/`copy_within`, which compile down to the
+//! platform memcpy/memmove. The SoC has no memory-to-memory DMA engine, so this is the fastest copy the
+//! hardware offers today; if one is added, it slots in behind `copy_lines()` and `move_lines()`.
+//!
+//! All operations set the dirty bit of every line they touch, so the next flush picks them up.
+//!
+//! Rectangles are given in logical coordinates, and are mapped onto the LCD according to the rotation
+//! (see `rotation.rs`) before any words are touched.
+
+use crate::api::{PixelColor, Rectangle, Rotation};
+use crate::op::{self, LcdFB, LCD_WORDS_PER_LINE, WIDTH};
+use crate::rotation;
+
+/// the dirty bit lives in the upper half of the last word of each line, which carries no pixels
+const DIRTY_BIT: u32 = 0x1_0000;
+/// pixels carried by the last word of each line
+const LAST_WORD_PIXELS: u32 = 0x0000_FFFF;
+
+/// Clips `r` to `clip` and to the screen, or returns `None` if nothing is left
+pub fn clip_to_screen(r: Rectangle, clip: Option<Rectangle>) -> Option<Rectangle> {
+    let r = r.clip_with(rotation::screen())?;
+    match clip {
+        Some(c) => r.clip_with(c),
+        None => Some(r),
+    }
+}
+fn is_full_width(r: &Rectangle) -> bool {
+    r.tl.x == 0 && r.br.x == WIDTH - 1
+}
+/// The bits of word `w` that hold pixels `x0..=x1`
+fn word_mask(x0: usize, x1: usize, w: usize) -> u32 {
+    let lo = if x0 > w * 32 { x0 - w * 32 } else { 0 };
+    let hi = if x1 < w * 32 + 31 { x1 - w * 32 } else { 31 };
+    (((1u64 << (hi + 1)) - 1) as u32) & !((1u32 << lo) - 1)
+}
+fn mark_dirty(fb: &mut LcdFB, y0: usize, y1: usize) {
+    for y in y0..=y1 {
+        fb[y * LCD_WORDS_PER_LINE + (LCD_WORDS_PER_LINE - 1)] |= DIRTY_BIT;
+    }
+}
+
+/// Fills `r`, which must already be clipped to the screen, with `color`
+pub fn fill(fb: &mut LcdFB, r: Rectangle, color: PixelColor) {
+    fill_physical(fb, rotation::rect_to_physical(r), color);
+}
+fn fill_physical(fb: &mut LcdFB, r: Rectangle, color: PixelColor) {
+    let (x0, x1) = (r.tl.x as usize, r.br.x as usize);
+    let (y0, y1) = (r.tl.y as usize, r.br.y as usize);
+    // light pixels are stored as 1
+    let pattern = if color == PixelColor::Light { 0xFFFF_FFFF } else { 0 };
+    if is_full_width(&r) {
+        let mut line = [pattern; LCD_WORDS_PER_LINE];
+        line[LCD_WORDS_PER_LINE - 1] = (pattern & LAST_WORD_PIXELS) | DIRTY_BIT;
+        for chunk in fb[y0 * LCD_WORDS_PER_LINE..(y1 + 1) * LCD_WORDS_PER_LINE].chunks_exact_mut(LCD_WORDS_PER_LINE) {
+            chunk.copy_from_slice(&line);
+        }
+        return;
+    }
+    for y in y0..=y1 {
+        for w in x0 / 32..=x1 / 32 {
+            let mask = word_mask(x0, x1, w);
+            let index = y * LCD_WORDS_PER_LINE + w;
+            fb[index] = (fb[index] & !mask) | (pattern & mask);
+        }
+    }
+    mark_dirty(fb, y0, y1);
+}
+
+/// Copies whole lines `y0..=y1` from `src` to `dst` in one go
+fn copy_lines(src: &LcdFB, dst: &mut LcdFB, y0: usize, y1: usize) {
+    let range = y0 * LCD_WORDS_PER_LINE..(y1 + 1) * LCD_WORDS_PER_LINE;
+    dst[range.clone()].copy_from_slice(&src[range]);
+}
+/// Copies the pixels inside `r`, which must already be clipped to the screen, from `src` to `dst`
+pub fn copy(src: &LcdFB, dst: &mut LcdFB, r: Rectangle) {
+    let r = rotation::rect_to_physical(r);
+    let (x0, x1) = (r.tl.x as usize, r.br.x as usize);
+    let (y0, y1) = (r.tl.y as usize, r.br.y as usize);
+    if is_full_width(&r) {
+        copy_lines(src, dst, y0, y1);
+    } else {
+        for y in y0..=y1 {
+            for w in x0 / 32..=x1 / 32 {
+                let mask = word_mask(x0, x1, w);
+                let index = y * LCD_WORDS_PER_LINE + w;
+                dst[index] = (dst[index] & !mask) | (src[index] & mask);
+            }
+        }
+    }
+    mark_dirty(dst, y0, y1);
+}
+
+/// Moves whole lines `y0..=y1` of `fb` so the first one lands on line `to`; the ranges may overlap
+fn move_lines(fb: &mut LcdFB, y0: usize, y1: usize, to: usize) {
+    fb.copy_within(y0 * LCD_WORDS_PER_LINE..(y1 + 1) * LCD_WORDS_PER_LINE, to * LCD_WORDS_PER_LINE);
+}
+/// Scrolls the contents of `r`, which must already be clipped to the screen, down by `dy` lines (up if
+/// `dy` is negative). Lines that scroll out of `r` are lost, and the lines that open up are filled with `fill_color`.
+pub fn scroll(fb: &mut LcdFB, r: Rectangle, dy: i16, fill_color: PixelColor) {
+    // scrolling by more than the height clears `r` all the same; clamping keeps `-dy` and `abs()` from overflowing
+    let height = r.br.y - r.tl.y + 1;
+    let dy = dy.clamp(-height, height);
+    match rotation::get() {
+        Rotation::Upright => scroll_lines(fb, r, dy, fill_color),
+        // turned upside down, the lines of the screen are still lines of the LCD, just in the opposite order
+        Rotation::UpsideDown => scroll_lines(fb, rotation::rect_to_physical(r), -dy, fill_color),
+        // on its side, the lines of the screen are columns of the LCD
+        _ => scroll_pixels(fb, r, dy, fill_color),
+    }
+}
+/// Scrolls `r`, given in LCD coordinates, a line at a time
+fn scroll_lines(fb: &mut LcdFB, r: Rectangle, dy: i16, fill_color: PixelColor) {
+    let height = r.br.y - r.tl.y + 1;
+    if dy == 0 {
+        return;
+    }
+    if dy.abs() >= height {
+        fill_physical(fb, r, fill_color);
+        return;
+    }
+    let (y0, y1) = (r.tl.y as usize, r.br.y as usize);
+    let n = dy.unsigned_abs() as usize;
+    // the lines that keep content, given as (first source line, last source line, first destination line)
+    let (src0, src1, dst0) = if dy > 0 { (y0, y1 - n, y0 + n) } else { (y0 + n, y1, y0) };
+    if is_full_width(&r) {
+        move_lines(fb, src0, src1, dst0);
+    } else {
+        let (x0, x1) = (r.tl.x as usize, r.br.x as usize);
+        let count = src1 - src0;
+        for k in 0..=count {
+            // moving down: start at the bottom, so no source line is overwritten before it's copied
+            let i = if dy > 0 { count - k } else { k };
+            for w in x0 / 32..=x1 / 32 {
+                let mask = word_mask(x0, x1, w);
+                let from = (src0 + i) * LCD_WORDS_PER_LINE + w;
+                let to = (dst0 + i) * LCD_WORDS_PER_LINE + w;
+                fb[to] = (fb[to] & !mask) | (fb[from] & mask);
+            }
+        }
+    }
+    let mut opened = r;
+    if dy > 0 {
+        opened.br.y = r.tl.y + dy - 1;
+    } else {
+        opened.tl.y = r.br.y + dy + 1;
+    }
+    fill_physical(fb, opened, fill_color);
+    mark_dirty(fb, y0, y1);
+}
+/// Scrolls `r`, given in logical coordinates, a pixel at a time
+fn scroll_pixels(fb: &mut LcdFB, r: Rectangle, dy: i16, fill_color: PixelColor) {
+    let height = r.br.y - r.tl.y + 1;
+    if dy == 0 {
+        return;
+    }
+    if dy.abs() >= height {
+        fill(fb, r, fill_color);
+        return;
+    }
+    let count = height - dy.abs();
+    for k in 0..count {
+        // moving down: start at the bottom, so no source line is overwritten before it's copied
+        let y = if dy > 0 { r.br.y - k } else { r.tl.y + k };
+        for x in r.tl.x..=r.br.x {
+            let color = op::get_pixel(fb, x, y - dy);
+            op::put_pixel(fb, x, y, color);
+        }
+    }
+    let mut opened = r;
+    if dy > 0 {
+        opened.br.y = r.tl.y + dy - 1;
+    } else {
+        opened.tl.y = r.br.y + dy + 1;
+    }
+    fill(fb, opened, fill_color);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::op::{LCD_FRAME_BUF_SIZE, LCD_LINES};
+
+    fn pixel(fb: &LcdFB, x: usize, y: usize) -> bool {
+        fb[y * LCD_WORDS_PER_LINE + x / 32] & (1 << (x % 32)) != 0
+    }
+    fn dirty(fb: &LcdFB, y: usize) -> bool {
+        fb[y * LCD_WORDS_PER_LINE + LCD_WORDS_PER_LINE - 1] & DIRTY_BIT != 0
+    }
+    #[test]
+    fn fill_keeps_dirty_bits_and_neighbours() {
+        let mut fb = [0u32; LCD_FRAME_BUF_SIZE];
+        fill(&mut fb, Rectangle::new_coords(30, 2, 70, 3), PixelColor::Light);
+        assert!(!pixel(&fb, 29, 2) && pixel(&fb, 30, 2) && pixel(&fb, 70, 3) && !pixel(&fb, 71, 3));
+        assert!(!pixel(&fb, 50, 1) && !pixel(&fb, 50, 4));
+        assert!(dirty(&fb, 2) && dirty(&fb, 3) && !dirty(&fb, 4));
+        // a full-width fill must not spill pixels into the dirty bit half of the last word
+        let full = clip_to_screen(Rectangle::new_coords(-5, 10, WIDTH + 5, 10), None).unwrap();
+        fill(&mut fb, full, PixelColor::Light);
+        assert_eq!(fb[10 * LCD_WORDS_PER_LINE + LCD_WORDS_PER_LINE - 1], LAST_WORD_PIXELS | DIRTY_BIT);
+        fill(&mut fb, full, PixelColor::Dark);
+        assert!(!pixel(&fb, 0, 10) && !pixel(&fb, WIDTH as usize - 1, 10) && dirty(&fb, 10));
+    }
+    #[test]
+    fn copy_matches_masked_copy() {
+        let mut src = [0u32; LCD_FRAME_BUF_SIZE];
+        for (i, word) in src.iter_mut().enumerate() {
+            *word = (i as u32).wrapping_mul(0x9E37_79B9) & if i % LCD_WORDS_PER_LINE == LCD_WORDS_PER_LINE - 1 { LAST_WORD_PIXELS } else { !0 };
+        }
+        let mut full = [0u32; LCD_FRAME_BUF_SIZE];
+        copy(&src, &mut full, clip_to_screen(Rectangle::full_screen(), None).unwrap());
+        for y in 0..LCD_LINES {
+            for x in 0..WIDTH as usize {
+                assert_eq!(pixel(&full, x, y), pixel(&src, x, y));
+            }
+            assert!(dirty(&full, y));
+        }
+        let mut part = [0u32; LCD_FRAME_BUF_SIZE];
+        copy(&src, &mut part, Rectangle::new_coords(5, 5, 40, 6));
+        assert_eq!(pixel(&part, 5, 5), pixel(&src, 5, 5));
+        assert!(!pixel(&part, 4, 5) && !pixel(&part, 41, 6) && !pixel(&part, 20, 7));
+    }
+    #[test]
+    fn scroll_moves_and_fills() {
+        for &r in [Rectangle::new_coords(0, 0, WIDTH - 1, 9), Rectangle::new_coords(3, 0, 60, 9)].iter() {
+            let mut fb = [0u32; LCD_FRAME_BUF_SIZE];
+            // mark line 2 and line 7 with a pixel each
+            fill(&mut fb, Rectangle::new_coords(10, 2, 10, 2), PixelColor::Light);
+            fill(&mut fb, Rectangle::new_coords(20, 7, 20, 7), PixelColor::Light);
+            scroll(&mut fb, r, 3, PixelColor::Dark);
+            assert!(pixel(&fb, 10, 5) && !pixel(&fb, 10, 2));
+            // line 7 moved to 10, which is outside of the region, so it's gone
+            assert!(!pixel(&fb, 20, 7) && !pixel(&fb, 20, 10));
+            scroll(&mut fb, r, -5, PixelColor::Light);
+            assert!(pixel(&fb, 10, 0) && !pixel(&fb, 10, 3));
+            // the lines that opened up at the bottom are light
+            assert!(pixel(&fb, 30, 5) && pixel(&fb, 30, 9) && !pixel(&fb, 30, 4));
+        }
+    }
+    #[test]
+    fn scroll_extremes_clear() {
+        let r = Rectangle::new_coords(0, 0, WIDTH - 1, 9);
+        for &dy in [i16::MIN, i16::MAX].iter() {
+            let mut fb = [0u32; LCD_FRAME_BUF_SIZE];
+            fill(&mut fb, Rectangle::new_coords(10, 2, 10, 2), PixelColor::Light);
+            scroll(&mut fb, r, dy, PixelColor::Dark);
+            assert!(!pixel(&fb, 10, 2));
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::api::{GlyphSetLayout, GlyphSprite, GLYPH_SET_HEADER_LEN, GLYPH_SET_SLOTS};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use xous::MemoryRange;
+
+const DEFAULT_KERN: u8 = 1;
+
+// Each slot records the base address and mapped length of its copy of the blob, plus the glyph count
+// and height from the validated header. An address of 0 means the slot is empty.
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: AtomicUsize = AtomicUsize::new(0);
+static SET_ADDR: [AtomicUsize; GLYPH_SET_SLOTS] = [EMPTY; GLYPH_SET_SLOTS];
+static SET_LEN: [AtomicUsize; GLYPH_SET_SLOTS] = [EMPTY; GLYPH_SET_SLOTS];
+static SET_COUNT: [AtomicUsize; GLYPH_SET_SLOTS] = [EMPTY; GLYPH_SET_SLOTS];
+static SET_HEIGHT: [AtomicUsize; GLYPH_SET_SLOTS] = [EMPTY; GLYPH_SET_SLOTS];
+
+/// Validates `blob` and copies it into memory owned by the graphics server, replacing whatever
+/// was previously loaded in `slot`.
+pub fn install(slot: usize, blob: &[u8]) -> Result<(), xous::Error> {
+    if slot >= GLYPH_SET_SLOTS {
+        return Err(xous::Error::BadAddress);
+    }
+    let layout = GlyphSetLayout::parse(blob)?;
+    let mut mem = xous::syscall::map_memory(
+        None,
+        None,
+        (layout.total_len + 4095) & !4095,
+        xous::MemoryFlags::R | xous::MemoryFlags::W,
+    )?;
+    mem.as_slice_mut::<u8>()[..layout.total_len].copy_from_slice(&blob[..layout.total_len]);
+    remove(slot);
+    SET_COUNT[slot].store(layout.count, Ordering::SeqCst);
+    SET_HEIGHT[slot].store(layout.max_height as usize, Ordering::SeqCst);
+    SET_LEN[slot].store(mem.len(), Ordering::SeqCst);
+    SET_ADDR[slot].store(mem.as_ptr() as usize, Ordering::SeqCst);
+    log::info!("loaded glyph set of {} glyphs into slot {}", layout.count, slot);
+    Ok(())
+}
+
+/// Frees the glyph set in `slot`. Text in the matching style falls back to the built-in fonts.
+pub fn remove(slot: usize) {
+    if slot >= GLYPH_SET_SLOTS {
+        return;
+    }
+    let addr = SET_ADDR[slot].swap(0, Ordering::SeqCst);
+    if addr != 0 {
+        let len = SET_LEN[slot].load(Ordering::SeqCst);
+        if let Some(range) = unsafe { MemoryRange::new(addr, len).ok() } {
+            xous::syscall::unmap_memory(range).expect("couldn't free glyph set");
+        }
+    }
+}
+
+/// Line height of the glyph set in `slot`, if one is loaded
+pub fn height(slot: usize) -> Option<usize> {
+    if slot < GLYPH_SET_SLOTS && SET_ADDR[slot].load(Ordering::SeqCst) != 0 {
+        Some(SET_HEIGHT[slot].load(Ordering::SeqCst))
+    } else {
+        None
+    }
+}
+
+/// Looks up `ch` in the glyph set loaded in `slot`. Follows the conventions of the built-in font lookups.
+pub fn loaded_glyph(slot: usize, ch: char) -> Result<GlyphSprite, usize> {
+    if slot >= GLYPH_SET_SLOTS {
+        return Err(1);
+    }
+    let addr = SET_ADDR[slot].load(Ordering::SeqCst);
+    if addr == 0 {
+        return Err(1);
+    }
+    let layout = GlyphSetLayout::new(SET_COUNT[slot].load(Ordering::SeqCst), SET_HEIGHT[slot].load(Ordering::SeqCst) as u8);
+    // safety: the blob was validated by install(), and is only unmapped by install() or remove(). Both of
+    // those run from the graphics server's message loop, so they can't race with a render that holds a sprite.
+    let (codepoints, widths, glyphs) = unsafe {
+        (
+            core::slice::from_raw_parts((addr + GLYPH_SET_HEADER_LEN) as *const u32, layout.count),
+            core::slice::from_raw_parts((addr + layout.widths_offset) as *const u8, layout.count),
+            core::slice::from_raw_parts((addr + layout.glyphs_offset) as *const u32, layout.count * 8),
+        )
+    };
+    match codepoints.binary_search(&(ch as u32)) {
+        Ok(n) => {
+            let offset = n << 3;
+            let end = offset + 8;
+            match end <= glyphs.len() {
+                true => Ok(GlyphSprite {
+                    glyph: &glyphs[offset..end],
+                    wide: widths[n],
+                    high: layout.max_height,
+                    kern: DEFAULT_KERN,
+                    ch,
+                    invert: false,
+                    insert: false,
+                    double: false,
+                }),
+                false => Err(0),
+            }
+        }
+        _ => Err(1),
+    }
+}
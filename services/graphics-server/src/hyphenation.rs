@@ -0,0 +1,113 @@
+//! Soft-hyphenation dictionary, consulted by the typesetter when a word has to be broken across lines.
+//!
+//! A dictionary is a UTF-8 text blob with one word per line, with its hyphenation points marked by `-`,
+//! e.g. `hy-phen-ation`. Blank lines and lines starting with `#` are ignored, and words are matched without
+//! regard to case. Only one dictionary is loaded at a time, normally the one for `xous::LANG`, which the
+//! status bar reads out of the PDDB once it's mounted.
+
+use std::collections::HashMap;
+
+/// Fewest characters to leave in front of a dictionary break
+const LEFT_MIN: usize = 2;
+/// Fewest characters to carry onto the next line after a dictionary break
+const RIGHT_MIN: usize = 3;
+
+pub(crate) struct Hyphenation {
+    /// lower-cased word, mapped to the character offsets where it may be broken
+    words: HashMap<String, Vec<usize>>,
+}
+impl Hyphenation {
+    pub fn new() -> Self {
+        Hyphenation { words: HashMap::new() }
+    }
+    /// Replaces the dictionary with the words in `blob`, and returns how many there are
+    pub fn load(&mut self, blob: &[u8]) -> Result<usize, xous::Error> {
+        let text = core::str::from_utf8(blob).or(Err(xous::Error::InvalidString))?;
+        let mut words = HashMap::new();
+        for line in text.lines().map(|l| l.trim()) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut word = String::new();
+            let mut points = Vec::new();
+            let mut offset = 0;
+            for ch in line.chars() {
+                if ch == '-' {
+                    points.push(offset);
+                } else {
+                    word.extend(ch.to_lowercase());
+                    offset += 1;
+                }
+            }
+            let len = offset;
+            points.retain(|&p| p >= LEFT_MIN && p + RIGHT_MIN <= len);
+            points.dedup();
+            if !points.is_empty() {
+                words.insert(word, points);
+            }
+        }
+        if words.is_empty() {
+            return Err(xous::Error::InvalidString);
+        }
+        self.words = words;
+        Ok(self.words.len())
+    }
+    pub fn clear(&mut self) {
+        self.words.clear();
+    }
+    /// Where `word` may be broken, as character offsets into it. Punctuation around the word, such as
+    /// quotes or a trailing comma, is skipped over when looking it up.
+    pub fn points(&self, word: &[char]) -> Vec<usize> {
+        if self.words.is_empty() {
+            return Vec::new();
+        }
+        let start = match word.iter().position(|c| c.is_alphabetic()) {
+            Some(s) => s,
+            None => return Vec::new(),
+        };
+        let end = word.iter().rposition(|c| c.is_alphabetic()).unwrap() + 1;
+        let mut key = String::new();
+        for ch in word[start..end].iter() {
+            // a character that lower-cases into several would throw the offsets off, so leave the word alone
+            let mut lower = ch.to_lowercase();
+            match (lower.next(), lower.next()) {
+                (Some(l), None) => key.push(l),
+                _ => return Vec::new(),
+            }
+        }
+        match self.words.get(&key) {
+            Some(points) => points.iter().map(|p| p + start).collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+    #[test]
+    fn parses_and_finds_points() {
+        let mut h = Hyphenation::new();
+        assert_eq!(h.load(b"# test dictionary\n\nhy-phen-ation\r\na-gain\nTy-pe-set-ter\n").unwrap(), 2);
+        assert_eq!(h.points(&chars("hyphenation")), vec![2, 6]);
+        // case and surrounding punctuation don't matter
+        assert_eq!(h.points(&chars("\"Typesetter,")), vec![3, 5, 8]);
+        // "a-gain" only had a break too close to the front of the word, so it was dropped
+        assert!(h.points(&chars("again")).is_empty());
+        assert!(h.points(&chars("unknown")).is_empty());
+    }
+    #[test]
+    fn rejects_bad_blobs() {
+        let mut h = Hyphenation::new();
+        assert!(h.load(&[0xff, 0xfe]).is_err());
+        assert!(h.load(b"# nothing but comments\n").is_err());
+        h.load(b"dic-tion-ary\n").unwrap();
+        // a failed load keeps the dictionary that was there
+        assert!(h.load(b"\n").is_err());
+        assert_eq!(h.points(&chars("dictionary")), vec![3, 7]);
+    }
+}
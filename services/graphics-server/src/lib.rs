@@ -3,8 +3,10 @@
 // pub mod size;
 pub mod api;
 pub use api::{
-    Circle, ClipObject, ClipObjectType, DrawStyle, Gid, Line, PixelColor, Point, Rectangle,
-    RoundedRectangle, TextBounds, TextOp, TextView, TokenClaim, ClipRect, Cursor, GlyphStyle, ClipObjectList
+    Arc, Circle, ClipObject, ClipObjectType, DrawStyle, Gid, Line, PixelColor, Point, Rectangle,
+    RoundedRectangle, TextBounds, TextOp, TextView, TextMeasurement, TokenClaim, ClipRect, Cursor, GlyphStyle, ClipObjectList,
+    Bitmap, BitmapFormat, DrawTarget, Polygon, MAX_POLYGON_POINTS, GlyphSetLayout, GLYPH_SET_SLOTS, GLYPH_SET_MAX_LEN,
+    SCREENSHOT_LEN, SCREENSHOT_ROW_BYTES, SCREENSHOT_LANDSCAPE_ROW_BYTES, HYPHENATION_MAX_LEN, Rotation,
 };
 pub mod op;
 
@@ -98,6 +100,23 @@ impl Gfx {
         .map(|_| ())
     }
 
+    /// Pushes only the lines spanned by `region` to the screen. The memory LCD updates on line granularity, so
+    /// the horizontal extent of the region is ignored. Lines outside the region keep their dirty state until the
+    /// next full `flush()`.
+    pub fn flush_region(&self, region: Rectangle) -> Result<(), xous::Error> {
+        send_message(
+            self.conn,
+            Message::new_scalar(
+                Opcode::FlushRegion.to_usize().unwrap(),
+                region.tl.y as usize,
+                region.br.y as usize,
+                0,
+                0,
+            ),
+        )
+        .map(|_| ())
+    }
+
     pub fn draw_sleepscreen(&self) -> Result<(), xous::Error> {
         send_message(
             self.conn,
@@ -149,6 +168,16 @@ impl Gfx {
         Ok(())
     }
 
+    /// Fills in `m.bounds` with the size the text would take up on screen
+    pub fn measure_text(&self, m: &mut TextMeasurement) -> Result<(), xous::Error> {
+        let mut buf = Buffer::into_buf(*m).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.conn, Opcode::MeasureText.to_u32().unwrap())
+            .or(Err(xous::Error::InternalError))?;
+
+        m.bounds = buf.to_original::<TextMeasurement, _>().unwrap().bounds;
+        Ok(())
+    }
+
     pub fn draw_line_clipped(&self, line: Line, clip: Rectangle) -> Result<(), xous::Error> {
         let co = ClipObject {
             clip,
@@ -194,6 +223,16 @@ impl Gfx {
             .map(|_| ())
     }
 
+    pub fn draw_arc_clipped(&self, arc: Arc, clip: Rectangle) -> Result<(), xous::Error> {
+        let co = ClipObject {
+            clip,
+            obj: ClipObjectType::Arc(arc),
+        };
+        let buf = Buffer::into_buf(co).or(Err(xous::Error::InternalError))?;
+        buf.lend(self.conn, Opcode::DrawClipObject.to_u32().unwrap())
+            .map(|_| ())
+    }
+
     pub fn draw_rounded_rectangle_clipped(
         &self,
         rr: RoundedRectangle,
@@ -217,6 +256,130 @@ impl Gfx {
             .map(|_| ())
     }
 
+    /// `bm.clip_rect` must be set, and coordinates must be in screen space; normally only the GAM calls this.
+    pub fn draw_bitmap(&self, bm: &Bitmap) -> Result<(), xous::Error> {
+        let buf = Buffer::into_buf(*bm).or(Err(xous::Error::InternalError))?;
+        buf.lend(self.conn, Opcode::DrawBitmap.to_u32().unwrap())
+            .map(|_| ())
+    }
+
+    pub fn draw_polygon(&self, poly: &Polygon) -> Result<(), xous::Error> {
+        let buf = Buffer::into_buf(*poly).or(Err(xous::Error::InternalError))?;
+        buf.lend(self.conn, Opcode::DrawPolygon.to_u32().unwrap())
+            .map(|_| ())
+    }
+
+    /// Loads a glyph set blob (see `api::glyphset` for the format) into `slot`, replacing any set that
+    /// was there before. Text drawn with the matching `GlyphStyle::Loaded*` style uses it from then on.
+    /// The blob is copied, so the caller's memory can be released as soon as this returns.
+    pub fn load_glyph_set(&self, slot: usize, blob: &[u8]) -> Result<(), xous::Error> {
+        if slot >= GLYPH_SET_SLOTS {
+            return Err(xous::Error::BadAddress);
+        }
+        if blob.len() > GLYPH_SET_MAX_LEN {
+            return Err(xous::Error::OutOfMemory);
+        }
+        let mut buf = Buffer::new(blob.len() + 4);
+        buf[0..4].copy_from_slice(&(slot as u32).to_le_bytes());
+        buf[4..4 + blob.len()].copy_from_slice(blob);
+        buf.lend_mut(self.conn, Opcode::LoadGlyphSet.to_u32().unwrap())?;
+        match xous::Error::from_usize(u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize) {
+            xous::Error::NoError => Ok(()),
+            e => Err(e),
+        }
+    }
+
+    /// Frees the glyph set in `slot`; its `GlyphStyle::Loaded*` style falls back to the built-in fonts.
+    pub fn unload_glyph_set(&self, slot: usize) -> Result<(), xous::Error> {
+        send_message(
+            self.conn,
+            Message::new_scalar(Opcode::UnloadGlyphSet.to_usize().unwrap(), slot, 0, 0, 0),
+        )
+        .map(|_| ())
+    }
+
+    /// Replaces the soft-hyphenation dictionary the typesetter uses to break long words across lines. The
+    /// dictionary is UTF-8 text, one word per line with its break points marked by `-` (e.g. `hy-phen-ation`).
+    pub fn load_hyphenation(&self, dictionary: &[u8]) -> Result<(), xous::Error> {
+        if dictionary.len() > HYPHENATION_MAX_LEN {
+            return Err(xous::Error::OutOfMemory);
+        }
+        let mut buf = Buffer::new(dictionary.len() + 4);
+        buf[0..4].copy_from_slice(&(dictionary.len() as u32).to_le_bytes());
+        buf[4..4 + dictionary.len()].copy_from_slice(dictionary);
+        buf.lend_mut(self.conn, Opcode::LoadHyphenation.to_u32().unwrap())?;
+        match xous::Error::from_usize(u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize) {
+            xous::Error::NoError => Ok(()),
+            e => Err(e),
+        }
+    }
+
+    /// Drops the soft-hyphenation dictionary; words are then only broken at hyphens in their text.
+    pub fn unload_hyphenation(&self) -> Result<(), xous::Error> {
+        send_message(
+            self.conn,
+            Message::new_scalar(Opcode::UnloadHyphenation.to_usize().unwrap(), 0, 0, 0, 0),
+        )
+        .map(|_| ())
+    }
+
+    /// Routes subsequent drawing operations to the screen or the offscreen buffer. Only the GAM should call this.
+    pub fn set_draw_target(&self, target: DrawTarget) -> Result<(), xous::Error> {
+        send_message(
+            self.conn,
+            Message::new_scalar(Opcode::SetDrawTarget.to_usize().unwrap(), target.to_usize().unwrap(), 0, 0, 0),
+        )
+        .map(|_| ())
+    }
+
+    /// Seeds `region` of the offscreen buffer with what is currently on the screen.
+    pub fn offscreen_begin(&self, region: Rectangle) -> Result<(), xous::Error> {
+        send_message(
+            self.conn,
+            Message::new_scalar(
+                Opcode::OffscreenBegin.to_usize().unwrap(),
+                region.tl.into(),
+                region.br.into(),
+                0,
+                0,
+            ),
+        )
+        .map(|_| ())
+    }
+
+    /// Copies `region` of the offscreen buffer onto the screen buffer. The copy is done within a single
+    /// message, so a concurrent flush can never show a half-composed region.
+    pub fn offscreen_swap(&self, region: Rectangle) -> Result<(), xous::Error> {
+        send_message(
+            self.conn,
+            Message::new_scalar(
+                Opcode::OffscreenSwap.to_usize().unwrap(),
+                region.tl.into(),
+                region.br.into(),
+                0,
+                0,
+            ),
+        )
+        .map(|_| ())
+    }
+
+    /// Scrolls the pixels inside `region` down by `dy` lines, or up if `dy` is negative. Pixels that move
+    /// out of `region` are dropped, and the lines that open up are filled with `fill`. Whole-width regions
+    /// are moved as a single block copy, so this is much cheaper than redrawing the region.
+    pub fn scroll_region(&self, region: Rectangle, dy: i16, fill: PixelColor) -> Result<(), xous::Error> {
+        send_message(
+            self.conn,
+            Message::new_scalar(
+                Opcode::ScrollRegion.to_usize().unwrap(),
+                region.tl.into(),
+                region.br.into(),
+                dy as usize,
+                if fill == PixelColor::Dark { 1 } else { 0 },
+            ),
+        )
+        .map(|_| ())
+    }
+
     pub fn draw_sleep_note(&self, flag: bool) -> Result<(), xous::Error> {
         let arg = if flag { 1 } else { 0 };
         send_message(
@@ -236,6 +399,39 @@ impl Gfx {
         .map(|_| ())
     }
 
+    /// Renders the whole display light-on-dark. Only the output to the LCD is flipped, so drawing
+    /// and screenshots are unaffected.
+    pub fn set_inverted(&self, inverted: bool) -> Result<(), xous::Error> {
+        send_message(
+            self.conn,
+            Message::new_scalar(Opcode::SetInverted.to_usize().unwrap(), if inverted { 1 } else { 0 }, 0, 0, 0),
+        )
+        .map(|_| ())
+    }
+
+    /// Turns the display to `rotation`. `screen_size()` and all drawing coordinates follow it from then on;
+    /// what's already in the frame buffer is not moved, so the caller clears and redraws the screen.
+    pub fn set_rotation(&self, rotation: Rotation) -> Result<(), xous::Error> {
+        send_message(
+            self.conn,
+            Message::new_scalar(Opcode::SetRotation.to_usize().unwrap(), rotation.to_usize().unwrap(), 0, 0, 0),
+        )
+        .map(|_| ())
+    }
+
+    /// Captures the visible screen into `raster`, which must hold at least `SCREENSHOT_LEN` bytes, as the user sees
+    /// it. Returns the number of bytes per row, which is `SCREENSHOT_ROW_BYTES` unless the screen is turned on its
+    /// side. See `SCREENSHOT_ROW_BYTES` for the pixel format.
+    pub fn screenshot(&self, raster: &mut [u8]) -> Result<usize, xous::Error> {
+        if raster.len() < SCREENSHOT_LEN {
+            return Err(xous::Error::OutOfMemory);
+        }
+        let mut buf = Buffer::new(SCREENSHOT_LEN + 4);
+        buf.lend_mut(self.conn, Opcode::Screenshot.to_u32().unwrap())?;
+        raster[..SCREENSHOT_LEN].copy_from_slice(&buf[4..4 + SCREENSHOT_LEN]);
+        Ok(u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize)
+    }
+
     /// instead of implementing the read in the library, we had the raw opcode to the caller
     /// this allows the caller to re-use the bulk read data structure across multiple reads
     /// instead of it being re-allocated and re-init'd every single call
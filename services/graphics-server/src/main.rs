@@ -7,8 +7,12 @@ mod backend;
 use backend::XousDisplay;
 
 mod op;
+mod blit;
 
 mod logo;
+// only put on the screen by the device; hosted builds just log panics
+#[cfg_attr(not(any(target_os = "none", target_os = "xous")), allow(dead_code, unused_imports))]
+mod panic;
 mod poweron;
 mod sleep_note;
 
@@ -16,6 +20,7 @@ use api::*;
 
 mod blitstr2;
 mod wordwrap;
+mod bidi;
 #[macro_use]
 mod style_macros;
 
@@ -26,6 +31,9 @@ use xous_ipc::Buffer;
 
 mod fontmap;
 use api::BulkRead;
+mod glyphset;
+mod hyphenation;
+mod rotation;
 
 use crate::wordwrap::*;
 use core::ops::Add;
@@ -33,6 +41,15 @@ use core::ops::Add;
 #[cfg(feature = "testing")]
 mod testing;
 
+/// Returns the frame buffer that drawing operations should currently go to. The offscreen buffer
+/// is only mapped once someone asks for it, so fall back to the screen if it doesn't exist.
+fn target_fb<'a>(display: &'a mut XousDisplay, offscreen: &'a mut Option<MemoryRange>, target: DrawTarget) -> &'a mut op::LcdFB {
+    match (target, offscreen) {
+        (DrawTarget::Offscreen, Some(mem)) => unsafe { &mut *(mem.as_mut_ptr() as *mut op::LcdFB) },
+        _ => display.native_buffer(),
+    }
+}
+
 fn draw_boot_logo(display: &mut XousDisplay) {
     display.blit_screen(&poweron::LOGO_MAP);
 }
@@ -98,8 +115,9 @@ fn xmain() -> ! {
     // these connections should be established:
     // - GAM
     // - keyrom (for verifying font maps)
+    // - the golden-image harness, which reads the screen back, when running headless
     let sid = xns
-        .register_name(api::SERVER_NAME_GFX, Some(2))
+        .register_name(api::SERVER_NAME_GFX, Some(if cfg!(feature = "headless") { 3 } else { 2 }))
         .expect("can't register server");
 
     // Create a new monochrome simulator display.
@@ -112,6 +130,20 @@ fn xmain() -> ! {
 
     let fontregion = map_fonts();
 
+    // the panic screen draws with the fonts, so it can't start before they're mapped
+    #[cfg(any(target_os = "none", target_os = "xous"))]
+    {
+        let sid = sid.to_u32();
+        xous::create_thread_4(
+            panic::panic_thread,
+            sid.0 as usize,
+            sid.1 as usize,
+            sid.2 as usize,
+            sid.3 as usize,
+        )
+        .expect("couldn't start panic screen thread");
+    }
+
     let mut use_sleep_note = true;
     if false {
         // leave this test case around
@@ -130,7 +162,8 @@ fn xmain() -> ! {
         op::rounded_rectangle(display.native_buffer(), rr, None);
     }
 
-    let screen_clip = Rectangle::new(Point::new(0, 0), display.screen_size());
+    // in logical coordinates, so it changes shape when the screen is turned on its side
+    let mut screen_clip = Rectangle::new(Point::new(0, 0), rotation::screen_size());
 
     display.redraw();
 
@@ -139,6 +172,14 @@ fn xmain() -> ! {
     let mut susres = susres::Susres::new(None, &xns, Opcode::SuspendResume as u32, sr_cid)
         .expect("couldn't create suspend/resume object");
 
+    watchdog::watch_server("graphics-server", 2000, sid, Opcode::WatchdogPing as usize);
+
+    // offscreen composition buffer, mapped on first use
+    let mut offscreen: Option<MemoryRange> = None;
+    let mut draw_target = DrawTarget::Screen;
+    // soft-hyphenation dictionary for the typesetter; empty until one is loaded
+    let mut hyphenation = hyphenation::Hyphenation::new();
+
     let mut bulkread = BulkRead::default(); // holding buffer for bulk reads; wastes ~8k when not in use, but saves a lot of copy/init for each iteration of the read
 
     #[cfg(feature = "testing")]
@@ -168,19 +209,22 @@ fn xmain() -> ! {
                 log::trace!("DrawClipObject {:?}", obj);
                 match obj.obj {
                     ClipObjectType::Line(line) => {
-                        op::line(display.native_buffer(), line, Some(obj.clip), false);
+                        op::line(target_fb(&mut display, &mut offscreen, draw_target), line, Some(obj.clip), false);
                     }
                     ClipObjectType::XorLine(line) => {
-                        op::line(display.native_buffer(), line, Some(obj.clip), true);
+                        op::line(target_fb(&mut display, &mut offscreen, draw_target), line, Some(obj.clip), true);
                     }
                     ClipObjectType::Circ(circ) => {
-                        op::circle(display.native_buffer(), circ, Some(obj.clip));
+                        op::circle(target_fb(&mut display, &mut offscreen, draw_target), circ, Some(obj.clip));
                     }
                     ClipObjectType::Rect(rect) => {
-                        op::rectangle(display.native_buffer(), rect, Some(obj.clip));
+                        op::rectangle(target_fb(&mut display, &mut offscreen, draw_target), rect, Some(obj.clip));
                     }
                     ClipObjectType::RoundRect(rr) => {
-                        op::rounded_rectangle(display.native_buffer(), rr, Some(obj.clip));
+                        op::rounded_rectangle(target_fb(&mut display, &mut offscreen, draw_target), rr, Some(obj.clip));
+                    }
+                    ClipObjectType::Arc(arc) => {
+                        op::arc(target_fb(&mut display, &mut offscreen, draw_target), arc, Some(obj.clip));
                     }
                 }
             }
@@ -192,19 +236,22 @@ fn xmain() -> ! {
                     if let Some(obj) = maybe_item {
                         match obj.obj {
                             ClipObjectType::Line(line) => {
-                                op::line(display.native_buffer(), line, Some(obj.clip), false);
+                                op::line(target_fb(&mut display, &mut offscreen, draw_target), line, Some(obj.clip), false);
                             }
                             ClipObjectType::XorLine(line) => {
-                                op::line(display.native_buffer(), line, Some(obj.clip), true);
+                                op::line(target_fb(&mut display, &mut offscreen, draw_target), line, Some(obj.clip), true);
                             }
                             ClipObjectType::Circ(circ) => {
-                                op::circle(display.native_buffer(), circ, Some(obj.clip));
+                                op::circle(target_fb(&mut display, &mut offscreen, draw_target), circ, Some(obj.clip));
                             }
                             ClipObjectType::Rect(rect) => {
-                                op::rectangle(display.native_buffer(), rect, Some(obj.clip));
+                                op::rectangle(target_fb(&mut display, &mut offscreen, draw_target), rect, Some(obj.clip));
                             }
                             ClipObjectType::RoundRect(rr) => {
-                                op::rounded_rectangle(display.native_buffer(), rr, Some(obj.clip));
+                                op::rounded_rectangle(target_fb(&mut display, &mut offscreen, draw_target), rr, Some(obj.clip));
+                            }
+                            ClipObjectType::Arc(arc) => {
+                                op::arc(target_fb(&mut display, &mut offscreen, draw_target), arc, Some(obj.clip));
                             }
                         }
                     } else {
@@ -227,6 +274,11 @@ fn xmain() -> ! {
                 let clip_rect = tv.clip_rect.unwrap();
                 // this is the translation vector to and from screen space
                 let screen_offset: Point = tv.clip_rect.unwrap().tl;
+                // and this is where the text may actually land, if the app has narrowed it down
+                let paint_clip = match tv.draw_clip {
+                    Some(dc) => dc.clip_with(clip_rect),
+                    None => Some(clip_rect),
+                };
 
                 let typeset_extent = match tv.bounds_hint {
                     TextBounds::BoundingBox(r) =>
@@ -246,6 +298,9 @@ fn xmain() -> ! {
                     &tv.style,
                     if let Some(i) = tv.insertion { Some(i as usize) } else { None }
                 );
+                typesetter.set_max_lines(tv.max_lines.map(|m| m as usize));
+                typesetter.set_style_spans(&tv.style_spans().copied().collect::<Vec<StyleSpan>>());
+                typesetter.set_hyphenation(&hyphenation);
                 let composition = typesetter.typeset(
                     if tv.ellipsis {
                         OverflowStrategy::Ellipsis
@@ -315,15 +370,15 @@ fn xmain() -> ! {
                     stroke_color: bordercolor,
                     stroke_width: borderwidth,
                 };
-                if !tv.dry_run() {
+                if !tv.dry_run() && paint_clip.is_some() {
                     if tv.rounded_border.is_some() {
                         op::rounded_rectangle(
-                            display.native_buffer(),
+                            target_fb(&mut display, &mut offscreen, draw_target),
                             RoundedRectangle::new(clear_rect, tv.rounded_border.unwrap() as _),
-                            tv.clip_rect,
+                            paint_clip,
                         );
                     } else {
-                        op::rectangle(display.native_buffer(), clear_rect, tv.clip_rect);
+                        op::rectangle(target_fb(&mut display, &mut offscreen, draw_target), clear_rect, paint_clip);
                     }
                 }
                 // for now, if we're in braille mode, emit all text to the debug log so we can see it
@@ -331,11 +386,11 @@ fn xmain() -> ! {
                 //   log::info!("{}", tv);
                 //}
 
-                if !tv.dry_run() {
-                    // note: make the clip rect `tv.clip_rect.unwrap()` if you want to debug wordwrapping artifacts; otherwise smallest_rect masks some problems
-                    let smallest_rect = clear_rect.clip_with(tv.clip_rect.unwrap())
+                if let (false, Some(paint_clip)) = (tv.dry_run(), paint_clip) {
+                    // note: make the clip rect `paint_clip` if you want to debug wordwrapping artifacts; otherwise smallest_rect masks some problems
+                    let smallest_rect = clear_rect.clip_with(paint_clip)
                         .unwrap_or(Rectangle::new(Point::new(0, 0), Point::new(0, 0,)));
-                    composition.render(display.native_buffer(), composition_top_left, tv.invert, smallest_rect);
+                    composition.render(target_fb(&mut display, &mut offscreen, draw_target), composition_top_left, tv.invert, smallest_rect);
                 }
                 // type mismatch for now, replace this with a simple equals once we sort that out
                 tv.cursor.pt.x = composition.final_cursor().pt.x;
@@ -345,24 +400,144 @@ fn xmain() -> ! {
                 tv.bounds_computed = Some(
                     clear_rect
                 );
+                tv.overflow = Some(typesetter.overflowed());
                 log::trace!("cursor ret {:?}, bounds ret {:?}", tv.cursor, tv.bounds_computed);
                 // pack our data back into the buffer to return
                 buffer.replace(tv).unwrap();
             }
+            Some(Opcode::MeasureText) => {
+                let mut buffer = unsafe {
+                    Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap())
+                };
+                let mut m = buffer.to_original::<TextMeasurement, _>().unwrap();
+                // without a width, the extent is just big enough that nothing ever wraps or overflows
+                let extent = Pt::new(m.max_width.map_or(i16::MAX as usize, |w| w as usize), i16::MAX as usize);
+                let mut typesetter = Typesetter::setup(m.text.to_str(), &extent, &m.style, None);
+                typesetter.set_hyphenation(&hyphenation);
+                let composition = typesetter.typeset(OverflowStrategy::Abort);
+                m.set_bounds(composition.bb_width(), composition.bb_height());
+                buffer.replace(m).unwrap();
+            }
+            Some(Opcode::DrawBitmap) => {
+                let buffer =
+                    unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let bm = buffer.to_original::<Bitmap, _>().unwrap();
+                if bm.clip_rect.is_none() {
+                    continue;
+                } // same policy as textviews: no clipping rectangle, nothing to draw
+                if !bm.is_valid() {
+                    log::warn!("bitmap dimensions don't fit its data, ignoring draw request");
+                    continue;
+                }
+                op::bitmap(target_fb(&mut display, &mut offscreen, draw_target), &bm, bm.clip_rect);
+            }
+            Some(Opcode::DrawPolygon) => {
+                let buffer =
+                    unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let poly = buffer.to_original::<Polygon, _>().unwrap();
+                if poly.clip_rect.is_none() {
+                    continue;
+                }
+                op::polygon(target_fb(&mut display, &mut offscreen, draw_target), &poly, poly.clip_rect);
+            }
+            Some(Opcode::SetDrawTarget) => msg_scalar_unpack!(msg, target, _, _, _, {
+                draw_target = FromPrimitive::from_usize(target).unwrap_or(DrawTarget::Screen);
+            }),
+            Some(Opcode::OffscreenBegin) => msg_scalar_unpack!(msg, tl, br, _, _, {
+                if offscreen.is_none() {
+                    offscreen = Some(xous::syscall::map_memory(
+                        None,
+                        None,
+                        ((backend::FB_SIZE * 4) + 4096) & !4095,
+                        xous::MemoryFlags::R | xous::MemoryFlags::W,
+                    ).expect("couldn't map offscreen frame buffer"));
+                }
+                if let Some(mem) = offscreen.as_mut() {
+                    let region = Rectangle::new(Point::from(tl), Point::from(br));
+                    let back = unsafe { &mut *(mem.as_mut_ptr() as *mut op::LcdFB) };
+                    op::copy_region(display.native_buffer(), back, region);
+                }
+            }),
+            Some(Opcode::OffscreenSwap) => msg_scalar_unpack!(msg, tl, br, _, _, {
+                if let Some(mem) = offscreen.as_mut() {
+                    let region = Rectangle::new(Point::from(tl), Point::from(br));
+                    let back = unsafe { &*(mem.as_ptr() as *const op::LcdFB) };
+                    op::copy_region(back, display.native_buffer(), region);
+                } else {
+                    log::warn!("OffscreenSwap without an OffscreenBegin; ignoring");
+                }
+            }),
+            Some(Opcode::ScrollRegion) => msg_scalar_unpack!(msg, tl, br, dy, fill, {
+                let region = Rectangle::new(Point::from(tl), Point::from(br));
+                op::scroll_region(target_fb(&mut display, &mut offscreen, draw_target), region,
+                    dy as i16, PixelColor::from(fill != 0), screen_clip.into());
+            }),
+            Some(Opcode::LoadGlyphSet) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                // the first word selects the slot on the way in, and carries the result code on the way out
+                let slot = u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize;
+                let result = match glyphset::install(slot, &buffer[4..]) {
+                    Ok(_) => xous::Error::NoError,
+                    Err(e) => {
+                        log::warn!("couldn't load glyph set into slot {}: {:?}", slot, e);
+                        e
+                    }
+                };
+                buffer[0..4].copy_from_slice(&(result as u32).to_le_bytes());
+            }
+            Some(Opcode::UnloadGlyphSet) => msg_scalar_unpack!(msg, slot, _, _, _, {
+                glyphset::remove(slot);
+            }),
+            Some(Opcode::LoadHyphenation) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                // the first word is the length of the dictionary on the way in, and the result code on the way out
+                let len = u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize;
+                let result = if len > HYPHENATION_MAX_LEN || len + 4 > buffer.len() {
+                    xous::Error::OutOfMemory
+                } else {
+                    match hyphenation.load(&buffer[4..4 + len]) {
+                        Ok(count) => {
+                            log::info!("loaded hyphenation dictionary of {} words", count);
+                            xous::Error::NoError
+                        }
+                        Err(e) => {
+                            log::warn!("couldn't load hyphenation dictionary: {:?}", e);
+                            e
+                        }
+                    }
+                };
+                buffer[0..4].copy_from_slice(&(result as u32).to_le_bytes());
+            }
+            Some(Opcode::UnloadHyphenation) => {
+                hyphenation.clear();
+            }
             Some(Opcode::Flush) => {
                 log::trace!("***gfx flush*** redraw##");
+                if panic::PANIC_SHOWN.load(core::sync::atomic::Ordering::SeqCst) {
+                    continue;
+                }
                 display.update();
                 display.redraw();
             }
+            Some(Opcode::FlushRegion) => msg_scalar_unpack!(msg, y0, y1, _, _, {
+                log::trace!("***gfx flush region {}-{}***", y0 as i16, y1 as i16);
+                // the coordinates are i16, sent as usize; negative values clamp to the top of the screen
+                let start = if (y0 as i16) < 0 { 0 } else { y0 as i16 as usize };
+                let end = if (y1 as i16) < 0 { 0 } else { y1 as i16 as usize };
+                if !panic::PANIC_SHOWN.load(core::sync::atomic::Ordering::SeqCst) {
+                    display.update();
+                    display.redraw_lines(start, end);
+                }
+            }),
             Some(Opcode::Clear) => {
                 let mut r = Rectangle::full_screen();
                 r.style = DrawStyle::new(PixelColor::Light, PixelColor::Light, 0);
-                op::rectangle(display.native_buffer(), r, screen_clip.into())
+                op::rectangle(target_fb(&mut display, &mut offscreen, draw_target), r, screen_clip.into())
             }
             Some(Opcode::Line) => msg_scalar_unpack!(msg, p1, p2, style, _, {
                 let l =
                     Line::new_with_style(Point::from(p1), Point::from(p2), DrawStyle::from(style));
-                op::line(display.native_buffer(), l, screen_clip.into(), false);
+                op::line(target_fb(&mut display, &mut offscreen, draw_target), l, screen_clip.into(), false);
             }),
             Some(Opcode::Rectangle) => msg_scalar_unpack!(msg, tl, br, style, _, {
                 let r = Rectangle::new_with_style(
@@ -370,7 +545,7 @@ fn xmain() -> ! {
                     Point::from(br),
                     DrawStyle::from(style),
                 );
-                op::rectangle(display.native_buffer(), r, screen_clip.into());
+                op::rectangle(target_fb(&mut display, &mut offscreen, draw_target), r, screen_clip.into());
             }),
             Some(Opcode::RoundedRectangle) => msg_scalar_unpack!(msg, tl, br, style, r, {
                 let rr = RoundedRectangle::new(
@@ -381,7 +556,7 @@ fn xmain() -> ! {
                     ),
                     r as _,
                 );
-                op::rounded_rectangle(display.native_buffer(), rr, screen_clip.into());
+                op::rounded_rectangle(target_fb(&mut display, &mut offscreen, draw_target), rr, screen_clip.into());
             }),
             Some(Opcode::Circle) => msg_scalar_unpack!(msg, center, radius, style, _, {
                 let c = Circle::new_with_style(
@@ -389,10 +564,10 @@ fn xmain() -> ! {
                     radius as _,
                     DrawStyle::from(style),
                 );
-                op::circle(display.native_buffer(), c, screen_clip.into());
+                op::circle(target_fb(&mut display, &mut offscreen, draw_target), c, screen_clip.into());
             }),
             Some(Opcode::ScreenSize) => msg_blocking_scalar_unpack!(msg, _, _, _, _, {
-                let pt = display.screen_size();
+                let pt = rotation::screen_size();
                 xous::return_scalar2(msg.sender, pt.x as usize, pt.y as usize)
                     .expect("couldn't return ScreenSize request");
             }),
@@ -401,7 +576,7 @@ fn xmain() -> ! {
                 xous::return_scalar2(
                     msg.sender,
                     glyph.into(),
-                    glyph_to_height_hint(glyph),
+                    glyph.glyph_set_slot().and_then(glyphset::height).unwrap_or(glyph_to_height_hint(glyph)),
                 )
                 .expect("could not return QueryGlyphProps request");
             }),
@@ -417,6 +592,18 @@ fn xmain() -> ! {
                     display.set_devboot(false);
                 }
             }),
+            Some(Opcode::SetInverted) => msg_scalar_unpack!(msg, ena, _, _, _, {
+                display.set_inverted(ena != 0);
+            }),
+            Some(Opcode::SetRotation) => msg_scalar_unpack!(msg, r, _, _, _, {
+                match FromPrimitive::from_usize(r) {
+                    Some(turn) => {
+                        rotation::set(turn);
+                        screen_clip = Rectangle::new(Point::new(0, 0), rotation::screen_size());
+                    }
+                    None => log::error!("invalid rotation: {}", r),
+                }
+            }),
             Some(Opcode::RestartBulkRead) => msg_blocking_scalar_unpack!(msg, _, _, _, _, {
                 bulkread.from_offset = 0;
                 xous::return_scalar(msg.sender, 0)
@@ -453,6 +640,12 @@ fn xmain() -> ! {
                 bulkread.from_offset += readlen as u32;
                 buf.replace(bulkread).unwrap();
             }
+            Some(Opcode::Screenshot) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                // always the visible buffer, so a capture taken mid-composition shows what the user sees
+                let row_bytes = op::screenshot_raster(display.as_slice(), &mut buffer[4..4 + SCREENSHOT_LEN]);
+                buffer[0..4].copy_from_slice(&(row_bytes as u32).to_le_bytes());
+            }
             Some(Opcode::TestPattern) => msg_blocking_scalar_unpack!(msg, duration, _, _, _, {
                 let mut stashmem = xous::syscall::map_memory(
                     None,
@@ -558,6 +751,17 @@ fn xmain() -> ! {
 
                 xous::return_scalar(msg.sender, duration).expect("couldn't ack test pattern");
             }),
+            Some(Opcode::DrawPanic) => {
+                if msg.sender.pid().map(|p| p.get() as u32) == Some(xous::process::id()) {
+                    if let Some(mem) = msg.body.memory_message() {
+                        let bytes = unsafe { core::slice::from_raw_parts(mem.buf.as_ptr(), mem.buf.len()) };
+                        panic::show(&mut display, bytes);
+                    }
+                }
+            }
+            Some(Opcode::WatchdogPing) => {
+                xous::return_scalar(msg.sender, 1).expect("couldn't answer the watchdog");
+            }
             Some(Opcode::Quit) => break,
             None => {
                 log::error!("received opcode scalar that is not handled");
@@ -1,4 +1,6 @@
-use crate::api::{Circle, DrawStyle, Line, Pixel, PixelColor, Point, Rectangle, RoundedRectangle};
+use crate::blit;
+use crate::rotation;
+use crate::api::{Arc, Bitmap, Circle, DrawStyle, Line, Pixel, PixelColor, Point, Polygon, Rectangle, RoundedRectangle, MAX_POLYGON_POINTS, SCREENSHOT_ROW_BYTES, SCREENSHOT_LANDSCAPE_ROW_BYTES};
 
 /// LCD Frame buffer bounds
 pub const LCD_WORDS_PER_LINE: usize = 11;
@@ -6,13 +8,16 @@ pub const LCD_PX_PER_LINE: usize = 336;
 pub const LCD_LINES: usize = 536;
 pub const LCD_FRAME_BUF_SIZE: usize = LCD_WORDS_PER_LINE * LCD_LINES;
 
+/// size of the LCD; drawing coordinates are bounded by `rotation::screen_size()` instead, which depends on the rotation
 pub const WIDTH: i16 = 336;
 pub const HEIGHT: i16 = 536;
 
 /// For passing frame buffer references
 pub type LcdFB = [u32; LCD_FRAME_BUF_SIZE];
 
-fn put_pixel(fb: &mut LcdFB, x: i16, y: i16, color: PixelColor) {
+/// Sets the pixel at logical coordinates `x`, `y`
+pub(crate) fn put_pixel(fb: &mut LcdFB, x: i16, y: i16, color: PixelColor) {
+    let (x, y) = rotation::to_physical(x, y);
     let mut clip_y: usize = y as usize;
     if clip_y >= LCD_LINES {
         clip_y = LCD_LINES - 1;
@@ -32,7 +37,8 @@ fn put_pixel(fb: &mut LcdFB, x: i16, y: i16, color: PixelColor) {
     fb[clip_y * LCD_WORDS_PER_LINE + (LCD_WORDS_PER_LINE - 1)] |= 0x1_0000;
 }
 
-fn xor_pixel(fb: &mut LcdFB, x: i16, y: i16) {
+pub(crate) fn xor_pixel(fb: &mut LcdFB, x: i16, y: i16) {
+    let (x, y) = rotation::to_physical(x, y);
     let mut clip_y: usize = y as usize;
     if clip_y >= LCD_LINES {
         clip_y = LCD_LINES - 1;
@@ -48,6 +54,13 @@ fn xor_pixel(fb: &mut LcdFB, x: i16, y: i16) {
     fb[clip_y * LCD_WORDS_PER_LINE + (LCD_WORDS_PER_LINE - 1)] |= 0x1_0000;
 }
 
+/// Reads the pixel at logical coordinates `x`, `y`, which must be on the screen
+pub(crate) fn get_pixel(fb: &LcdFB, x: i16, y: i16) -> PixelColor {
+    let (x, y) = rotation::to_physical(x, y);
+    // light pixels are stored as 1
+    PixelColor::from(fb[y as usize * LCD_WORDS_PER_LINE + x as usize / 32] & (1 << (x as usize % 32)) == 0)
+}
+
 pub fn line(fb: &mut LcdFB, l: Line, clip: Option<Rectangle>, xor: bool) {
     let color: PixelColor;
     if l.style.stroke_color.is_some() {
@@ -65,9 +78,10 @@ pub fn line(fb: &mut LcdFB, l: Line, clip: Option<Rectangle>, xor: bool) {
     let dy = -((y1 - y0).abs());
     let sy = if y0 < y1 { 1 } else { -1 };
     let mut err = dx + dy; /* error value e_xy */
+    let screen = rotation::screen_size();
     loop {
         /* loop */
-        if x0 >= 0 && y0 >= 0 && x0 < (WIDTH as _) && y0 < (HEIGHT as _) {
+        if x0 >= 0 && y0 >= 0 && x0 < screen.x && y0 < screen.y {
             if clip.is_none() || (clip.unwrap().intersects_point(Point::new(x0, y0))) {
                 if !xor {
                     put_pixel(fb, x0 as _, y0 as _, color);
@@ -181,93 +195,35 @@ pub fn circle(fb: &mut LcdFB, circle: Circle, clip: Option<Rectangle>) {
     }
 }
 
-/// Pixel iterator for each pixel in the rect border
-/// lifted from embedded-graphics crate
-#[derive(Debug, Clone, Copy)]
-pub struct RectangleIterator {
-    top_left: Point,
-    bottom_right: Point,
-    style: DrawStyle,
-    p: Point,
-    clip: Option<Rectangle>,
-}
-
-impl Iterator for RectangleIterator {
-    type Item = Pixel;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        // Don't render anything if the rectangle has no border or fill color.
-        if self.style.stroke_color.is_none() && self.style.fill_color.is_none() {
-            return None;
+/// Rectangles are drawn as up to five solid fills: the interior, then the border strips over it.
+/// Each fill goes through `blit::fill()`, a word at a time, which matters for the full-screen
+/// clears that happen on every app switch.
+pub fn rectangle(fb: &mut LcdFB, rect: Rectangle, clip: Option<Rectangle>) {
+    if let Some(fill) = rect.style.fill_color {
+        if let Some(r) = blit::clip_to_screen(rect, clip) {
+            blit::fill(fb, r, fill);
         }
-
-        loop {
-            let mut out = None;
-
-            // Finished, i.e. we're below the rect
-            if self.p.y > self.bottom_right.y {
-                break None;
-            }
-
-            if self.clip.is_none() || // short-circuit evaluation makes this safe
-               (self.clip.unwrap().intersects_point(self.p))
-            {
-                let border_width = self.style.stroke_width;
-                let tl = self.top_left;
-                let br = self.bottom_right;
-
-                // Border
-                if (
-                    // Top border
-                    (self.p.y >= tl.y && self.p.y < tl.y + border_width)
-                // Bottom border
-                || (self.p.y <= br.y && self.p.y > br.y - border_width)
-                // Left border
-                || (self.p.x >= tl.x && self.p.x < tl.x + border_width)
-                // Right border
-                || (self.p.x <= br.x && self.p.x > br.x - border_width)
-                ) && self.style.stroke_color.is_some()
-                {
-                    out = Some(Pixel(
-                        self.p,
-                        self.style.stroke_color.expect("Expected stroke"),
-                    ));
-                }
-                // Fill
-                else if let Some(fill) = self.style.fill_color {
-                    out = Some(Pixel(self.p, fill));
-                }
-            }
-
-            self.p.x += 1;
-
-            // Reached end of row? Jump down one line
-            if self.p.x > self.bottom_right.x {
-                self.p.x = self.top_left.x;
-                self.p.y += 1;
-            }
-
-            if out.is_some() {
-                break out;
+    }
+    let bw = rect.style.stroke_width;
+    if let Some(stroke) = rect.style.stroke_color {
+        if bw <= 0 {
+            return;
+        }
+        let (tl, br) = (rect.tl, rect.br);
+        let strips = [
+            Rectangle::new(tl, Point::new(br.x, (tl.y + bw - 1).min(br.y))),
+            Rectangle::new(Point::new(tl.x, (br.y - bw + 1).max(tl.y)), br),
+            Rectangle::new(tl, Point::new((tl.x + bw - 1).min(br.x), br.y)),
+            Rectangle::new(Point::new((br.x - bw + 1).max(tl.x), tl.y), br),
+        ];
+        for strip in strips.iter() {
+            if let Some(r) = blit::clip_to_screen(*strip, clip) {
+                blit::fill(fb, r, stroke);
             }
         }
     }
 }
 
-pub fn rectangle(fb: &mut LcdFB, rect: Rectangle, clip: Option<Rectangle>) {
-    let r = RectangleIterator {
-        top_left: rect.tl,
-        bottom_right: rect.br,
-        style: rect.style,
-        p: rect.tl,
-        clip: clip,
-    };
-
-    for pixel in r {
-        put_pixel(fb, pixel.0.x, pixel.0.y, pixel.1);
-    }
-}
-
 /////////////////////////////////////////////////// rounded rectangle
 
 #[derive(Debug, Clone, Copy)]
@@ -564,3 +520,156 @@ pub fn rounded_rectangle(fb: &mut LcdFB, rr: RoundedRectangle, clip: Option<Rect
         clip,
     );
 }
+
+/////////////////////////////////////////////////// bitmaps
+
+/// Blits a bitmap whose coordinates are already in screen space. Transparent pixels are skipped;
+/// every source pixel is replicated `scale` times in each direction.
+pub fn bitmap(fb: &mut LcdFB, bm: &Bitmap, clip: Option<Rectangle>) {
+    let scale = bm.scale_factor() as i32;
+    let bounds = bm.bounds();
+    // restrict the iteration to the intersection of the bitmap, the clip, and the screen
+    let mut area = rotation::screen();
+    if let Some(c) = clip {
+        area = match area.clip_with(c) {
+            Some(r) => r,
+            None => return,
+        };
+    }
+    let area = match bounds.clip_with(area) {
+        Some(r) => r,
+        None => return,
+    };
+    for y in area.tl.y..=area.br.y {
+        let src_y = ((y as i32 - bm.tl.y as i32) / scale) as u16;
+        for x in area.tl.x..=area.br.x {
+            let src_x = ((x as i32 - bm.tl.x as i32) / scale) as u16;
+            if let Some(color) = bm.pixel(src_x, src_y) {
+                put_pixel(fb, x, y, color);
+            }
+        }
+    }
+}
+
+/////////////////////////////////////////////////// polygons
+
+/// Draws a polygon whose coordinates are already in screen space. Closed polygons are filled by scanning
+/// rows over the bounding box; the outline is then stroked on top with 1-pixel lines.
+pub fn polygon(fb: &mut LcdFB, poly: &Polygon, clip: Option<Rectangle>) {
+    let bounds = match poly.bounds() {
+        Some(b) => b,
+        None => return,
+    };
+    let mut area = rotation::screen();
+    if let Some(c) = clip {
+        area = match area.clip_with(c) {
+            Some(r) => r,
+            None => return,
+        };
+    }
+    if let (true, Some(color)) = (poly.closed, poly.style.fill_color) {
+        if let Some(fill_area) = bounds.clip_with(area) {
+            let mut xs = [0i16; MAX_POLYGON_POINTS];
+            for y in fill_area.tl.y..=fill_area.br.y {
+                let count = poly.crossings(y, &mut xs);
+                for span in xs[..count].chunks_exact(2) {
+                    let x0 = span[0].max(fill_area.tl.x);
+                    let x1 = span[1].min(fill_area.br.x);
+                    for x in x0..=x1 {
+                        put_pixel(fb, x, y, color);
+                    }
+                }
+            }
+        }
+    }
+    if poly.style.stroke_color.is_some() {
+        for (start, end) in poly.edges() {
+            line(fb, Line::new_with_style(start, end, poly.style), Some(area), false);
+        }
+    }
+}
+
+/// Draws an arc, or a pie slice when `arc.pie` is set. Each pixel of the bounding box is tested against the
+/// radius and the start/end angles, which is plenty fast for the small indicators this is meant for.
+pub fn arc(fb: &mut LcdFB, arc: Arc, clip: Option<Rectangle>) {
+    let mut area = rotation::screen();
+    if let Some(c) = clip {
+        area = match area.clip_with(c) {
+            Some(r) => r,
+            None => return,
+        };
+    }
+    let area = match arc.bounds().clip_with(area) {
+        Some(r) => r,
+        None => return,
+    };
+    let radius = arc.radius.abs() as i32;
+    let outer = radius * radius;
+    let inner_radius = (radius - arc.style.stroke_width as i32).max(0);
+    let inner = inner_radius * inner_radius;
+    for y in area.tl.y..=area.br.y {
+        for x in area.tl.x..=area.br.x {
+            let (dx, dy) = (x - arc.center.x, y - arc.center.y);
+            let d = dx as i32 * dx as i32 + dy as i32 * dy as i32;
+            if d > outer || !arc.contains_angle(dx, dy) {
+                continue;
+            }
+            if d > inner {
+                if let Some(color) = arc.style.stroke_color {
+                    put_pixel(fb, x, y, color);
+                }
+            } else if arc.pie {
+                if let Some(color) = arc.style.fill_color {
+                    put_pixel(fb, x, y, color);
+                }
+            }
+        }
+    }
+    if arc.pie && arc.style.stroke_color.is_some() && arc.sweep() < 360 {
+        for angle in [arc.start_angle, arc.end_angle].iter() {
+            line(fb, Line::new_with_style(arc.center, arc.point_at(*angle), arc.style), Some(area), false);
+        }
+    }
+}
+
+/// Copies the pixels inside `region` from `src` to `dst`, leaving everything outside of it untouched.
+/// Lines that are written in `dst` get their dirty bit set so the next flush picks them up.
+pub fn copy_region(src: &LcdFB, dst: &mut LcdFB, region: Rectangle) {
+    if let Some(r) = blit::clip_to_screen(region, None) {
+        blit::copy(src, dst, r);
+    }
+}
+
+/// Scrolls the pixels inside `region` down by `dy` lines, or up if `dy` is negative, filling the lines that open up with `fill`
+pub fn scroll_region(fb: &mut LcdFB, region: Rectangle, dy: i16, fill: PixelColor, clip: Option<Rectangle>) {
+    if let Some(r) = blit::clip_to_screen(region, clip) {
+        blit::scroll(fb, r, dy, fill);
+    }
+}
+
+/// Packs the frame buffer into a screenshot raster in logical coordinates, so that it reads the way the screen
+/// is being held, and returns the bytes per line. The frame buffer stores light pixels as 1 with the leftmost
+/// pixel in the least significant bit, so every byte is flipped and inverted.
+pub fn screenshot_raster(fb: &[u32], raster: &mut [u8]) -> usize {
+    let size = rotation::screen_size();
+    let (width, lines) = (size.x as usize, size.y as usize);
+    let row_bytes = if rotation::get().is_landscape() { SCREENSHOT_LANDSCAPE_ROW_BYTES } else { SCREENSHOT_ROW_BYTES };
+    for (y, row) in raster.chunks_exact_mut(row_bytes).take(lines).enumerate() {
+        for (i, byte) in row.iter_mut().enumerate() {
+            let mut packed = 0u8;
+            for bit in 0..8 {
+                let x = i * 8 + bit;
+                if x >= width {
+                    break;
+                }
+                let (px, py) = rotation::to_physical(x as i16, y as i16);
+                let (px, py) = (px as usize, py as usize);
+                if fb[py * LCD_WORDS_PER_LINE + px / 32] & (1 << (px % 32)) == 0 {
+                    packed |= 0x80 >> bit;
+                }
+            }
+            *byte = packed;
+        }
+    }
+    row_bytes
+}
@@ -0,0 +1,100 @@
+//! Draws panics on the screen. The log server lends the text of each panic that finishes to a server of its own
+//! here, which passes it on to the main loop. That paints it over whatever was showing and stops anything else
+//! from reaching the screen, so the report stays up until the device is rebooted.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::api::{DrawStyle, PixelColor, Point, Rectangle};
+use crate::backend::XousDisplay;
+use crate::blitstr2::{self, ClipRect};
+use crate::{op, rotation};
+
+/// Set once a panic is on the screen; after that the main loop doesn't push anything to the screen
+pub(crate) static PANIC_SHOWN: AtomicBool = AtomicBool::new(false);
+
+const MARGIN: i16 = 4;
+
+/// Waits for panics from the log server and hands them to the main loop, whose server is `sid0`-`sid3`, as
+/// `Opcode::DrawPanic`. The main loop owns the display, so this thread never touches it.
+pub(crate) fn panic_thread(sid0: usize, sid1: usize, sid2: usize, sid3: usize) {
+    let main_cid = xous::connect(xous::SID::from_u32(sid0 as u32, sid1 as u32, sid2 as u32, sid3 as u32))
+        .expect("couldn't connect to the main loop");
+    let sid = xous::create_server_with_address(crate::api::SERVER_NAME_GFX_PANIC)
+        .expect("couldn't create panic server");
+    loop {
+        let msg = xous::receive_message(sid).unwrap();
+        // The server's address is well known, so anyone could ask for a panic screen and lock up the display
+        if !from_log_server(msg.sender) {
+            log::warn!("ignoring a panic screen request from PID {:?}", msg.sender.pid());
+            continue;
+        }
+        if let Some(mem) = msg.body.memory_message() {
+            if mem.buf.len() < 4 {
+                continue;
+            }
+            let bytes = unsafe { core::slice::from_raw_parts(mem.buf.as_ptr(), mem.buf.len()) };
+            let mut copy = xous_ipc::Buffer::new(bytes.len());
+            copy[..bytes.len()].copy_from_slice(bytes);
+            copy.lend(main_cid, crate::api::Opcode::DrawPanic as u32).ok();
+        }
+    }
+}
+
+fn from_log_server(sender: xous::MessageSender) -> bool {
+    let mut name = [0u8; 64];
+    match sender.pid().map(|pid| xous::process_name(pid, &mut name)) {
+        Some(Ok(len)) if len > 0 && len < name.len() => &name[..len] == b"log-server",
+        _ => false,
+    }
+}
+
+/// Puts up the panic lent with `Opcode::DrawPanic`: a little-endian `u32` length followed by that much text.
+/// Called from the main loop.
+pub(crate) fn show(display: &mut XousDisplay, bytes: &[u8]) {
+    if bytes.len() < 4 {
+        return;
+    }
+    let len = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    let text = &bytes[4..(4 + len).min(bytes.len())];
+    PANIC_SHOWN.store(true, Ordering::SeqCst);
+    // the log server keeps only so much of a panic, which can end partway through a character
+    let text = core::str::from_utf8(text)
+        .unwrap_or_else(|e| core::str::from_utf8(&text[..e.valid_up_to()]).unwrap());
+    draw(display, text);
+}
+
+fn draw(display: &mut XousDisplay, text: &str) {
+    let size = rotation::screen_size();
+    let fb = display.native_buffer();
+    let mut r = Rectangle::new(Point::new(0, 0), size);
+    r.style = DrawStyle::new(PixelColor::Light, PixelColor::Light, 0);
+    op::rectangle(fb, r, rotation::screen().into());
+    let cr = ClipRect::new(0, 0, size.x as usize, size.y as usize);
+
+    let line_height = blitstr2::mono_glyph(' ').map(|g| g.high as i16).unwrap_or(15) + 1;
+    let mut p = Point::new(MARGIN, MARGIN);
+    for ch in text.chars() {
+        if ch == '\n' {
+            p = Point::new(MARGIN, p.y + line_height);
+            continue;
+        }
+        let glyph = match blitstr2::mono_glyph(ch).or_else(|_| blitstr2::mono_glyph('?')) {
+            Ok(glyph) => glyph,
+            Err(_) => continue,
+        };
+        if p.x + glyph.wide as i16 > size.x - MARGIN {
+            p = Point::new(MARGIN, p.y + line_height);
+        }
+        if p.y + line_height > size.y - MARGIN {
+            break;
+        }
+        if rotation::is_upright() {
+            blitstr2::xor_glyph(fb, &p, glyph, false, cr);
+        } else {
+            rotation::xor_glyph(fb, &p, glyph, false, cr, 1);
+        }
+        p.x += (glyph.wide + glyph.kern) as i16;
+    }
+    display.update();
+    display.redraw();
+}
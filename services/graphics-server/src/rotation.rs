@@ -0,0 +1,138 @@
+//! Screen rotation. Drawing operations work in logical coordinates, with (0, 0) at the top left of the
+//! screen as the user holds it, and are turned into LCD coordinates as their pixels are composed into the
+//! frame buffer. Upright, the transform is the identity and costs nothing.
+//!
+//! Rectangles stay rectangles under a quarter turn, so fills and copies keep their word-wide paths in every
+//! position. Glyphs, and scrolling while the screen is landscape, go a pixel at a time instead.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use num_traits::FromPrimitive;
+
+use crate::api::{GlyphSprite, PixelColor, Point, Rectangle, Rotation};
+use crate::blitstr2::ClipRect;
+use crate::op::{self, LcdFB, HEIGHT, WIDTH};
+
+static ROTATION: AtomicUsize = AtomicUsize::new(Rotation::Upright as usize);
+
+pub fn set(rotation: Rotation) {
+    ROTATION.store(rotation as usize, Ordering::SeqCst);
+}
+pub fn get() -> Rotation {
+    FromPrimitive::from_usize(ROTATION.load(Ordering::SeqCst)).unwrap_or(Rotation::Upright)
+}
+pub fn is_upright() -> bool {
+    ROTATION.load(Ordering::SeqCst) == Rotation::Upright as usize
+}
+
+/// Size of the screen in logical coordinates
+pub fn screen_size() -> Point {
+    if get().is_landscape() {
+        Point::new(HEIGHT, WIDTH)
+    } else {
+        Point::new(WIDTH, HEIGHT)
+    }
+}
+/// Every pixel of the screen, in logical coordinates
+pub fn screen() -> Rectangle {
+    let size = screen_size();
+    Rectangle::new(Point::new(0, 0), Point::new(size.x - 1, size.y - 1))
+}
+
+fn map(rotation: Rotation, x: i16, y: i16) -> (i16, i16) {
+    match rotation {
+        Rotation::Upright => (x, y),
+        Rotation::Clockwise => (WIDTH - 1 - y, x),
+        Rotation::UpsideDown => (WIDTH - 1 - x, HEIGHT - 1 - y),
+        Rotation::CounterClockwise => (y, HEIGHT - 1 - x),
+    }
+}
+fn map_rect(rotation: Rotation, r: Rectangle) -> Rectangle {
+    let (x0, y0) = map(rotation, r.tl.x, r.tl.y);
+    let (x1, y1) = map(rotation, r.br.x, r.br.y);
+    let mut mapped = r;
+    mapped.tl = Point::new(x0.min(x1), y0.min(y1));
+    mapped.br = Point::new(x0.max(x1), y0.max(y1));
+    mapped
+}
+/// Maps a logical pixel onto the LCD
+pub fn to_physical(x: i16, y: i16) -> (i16, i16) {
+    map(get(), x, y)
+}
+/// Maps a logical rectangle, given by its corner pixels, onto the LCD
+pub fn rect_to_physical(r: Rectangle) -> Rectangle {
+    map_rect(get(), r)
+}
+
+/// Draws a glyph at logical point `p` one pixel at a time, for when the screen isn't upright. `scale` is 2
+/// for double-size glyphs. Follows the clipping and color conventions of `blitstr2::xor_glyph()`.
+pub fn xor_glyph(fb: &mut LcdFB, p: &Point, gs: GlyphSprite, xor: bool, cr: ClipRect, scale: i16) {
+    if gs.glyph.len() < 8 {
+        return;
+    }
+    let high = gs.high as i16 / scale;
+    let wide = gs.wide as i16 / scale;
+    if high > 16 || wide > 16 {
+        return;
+    }
+    let size = screen_size();
+    for y in 0..high {
+        // each word of the sprite holds two rows of 16 pixels, with the leftmost pixel in the lowest bit
+        let pattern = (gs.glyph[(y >> 1) as usize] >> ((y & 1) << 4)) & 0xffff;
+        for x in 0..wide {
+            if pattern & (1 << x) == 0 {
+                continue;
+            }
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    let (lx, ly) = (p.x + x * scale + dx, p.y + y * scale + dy);
+                    if lx < cr.min.x as i16 || lx >= cr.max.x as i16 || ly < cr.min.y as i16 || ly >= cr.max.y as i16
+                    || lx < 0 || ly < 0 || lx >= size.x || ly >= size.y {
+                        continue;
+                    }
+                    if xor {
+                        op::xor_pixel(fb, lx, ly);
+                    } else {
+                        op::put_pixel(fb, lx, ly, PixelColor::Dark);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Rotation; 4] = [Rotation::Upright, Rotation::Clockwise, Rotation::UpsideDown, Rotation::CounterClockwise];
+
+    #[test]
+    fn corners_land_on_the_lcd() {
+        for &rotation in ALL.iter() {
+            let (w, h) = if rotation.is_landscape() { (HEIGHT, WIDTH) } else { (WIDTH, HEIGHT) };
+            for &(x, y) in [(0, 0), (w - 1, 0), (0, h - 1), (w - 1, h - 1)].iter() {
+                let (px, py) = map(rotation, x, y);
+                assert!(px >= 0 && px < WIDTH && py >= 0 && py < HEIGHT, "{:?} maps {},{} off the LCD", rotation, x, y);
+            }
+        }
+        // a quarter turn clockwise takes the logical top left to the top right of the LCD
+        assert_eq!(map(Rotation::Clockwise, 0, 0), (WIDTH - 1, 0));
+        assert_eq!(map(Rotation::CounterClockwise, 0, 0), (0, HEIGHT - 1));
+        assert_eq!(map(Rotation::UpsideDown, 0, 0), (WIDTH - 1, HEIGHT - 1));
+    }
+    #[test]
+    fn rectangles_stay_rectangles() {
+        let r = Rectangle::new_coords(10, 20, 30, 25);
+        for &rotation in ALL.iter() {
+            let m = map_rect(rotation, r);
+            assert!(m.tl.x <= m.br.x && m.tl.y <= m.br.y);
+            // the area is the same, and every corner of the original lands on a corner of the mapped rectangle
+            assert_eq!((m.br.x - m.tl.x + 1) * (m.br.y - m.tl.y + 1), 21 * 6);
+            for &(x, y) in [(10, 20), (30, 20), (10, 25), (30, 25)].iter() {
+                let (px, py) = map(rotation, x, y);
+                assert!((px == m.tl.x || px == m.br.x) && (py == m.tl.y || py == m.br.y));
+            }
+        }
+        assert_eq!(map_rect(Rotation::Upright, r).tl, r.tl);
+    }
+}
@@ -200,6 +200,34 @@ pub fn tests() {
                     _ => {}
                 }
             }
+
+            // time the bulk frame buffer paths. The draw calls are non-blocking, so each batch ends with a
+            // blocking request that's only answered once the server has worked through the whole queue.
+            const BLIT_RUNS: u64 = 50;
+            let screensize = gfx.screen_size().expect("Couldn't get screen size");
+            let start = ticktimer.elapsed_ms();
+            for i in 0..BLIT_RUNS {
+                let color = if i % 2 == 0 { PixelColor::Dark } else { PixelColor::Light };
+                gfx.draw_rectangle(Rectangle::new_with_style(Point::new(0, 0), screensize, DrawStyle::new(color, color, 1))).unwrap();
+            }
+            gfx.flush().unwrap();
+            gfx.screen_size().unwrap();
+            log::info!("full screen fill: {}ms for {} runs", ticktimer.elapsed_ms() - start, BLIT_RUNS);
+            let start = ticktimer.elapsed_ms();
+            for _ in 0..BLIT_RUNS {
+                gfx.scroll_region(Rectangle::new(Point::new(0, 0), screensize), -8, PixelColor::Light).unwrap();
+            }
+            gfx.flush().unwrap();
+            gfx.screen_size().unwrap();
+            log::info!("full screen scroll: {}ms for {} runs", ticktimer.elapsed_ms() - start, BLIT_RUNS);
+            let start = ticktimer.elapsed_ms();
+            for _ in 0..BLIT_RUNS {
+                gfx.offscreen_begin(Rectangle::new(Point::new(0, 0), screensize)).unwrap();
+                gfx.offscreen_swap(Rectangle::new(Point::new(0, 0), screensize)).unwrap();
+            }
+            gfx.flush().unwrap();
+            gfx.screen_size().unwrap();
+            log::info!("full screen offscreen round trip: {}ms for {} runs", ticktimer.elapsed_ms() - start, BLIT_RUNS);
         }
     });
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use crate::api::{Point, Rectangle, GlyphStyle, glyph_to_height_hint, GlyphSprite, TypesetWord, Pt, Cursor};
+use crate::api::{Point, Rectangle, GlyphStyle, glyph_to_height_hint, GlyphSprite, TypesetWord, Pt, Cursor, StyleSpan};
 
 #[allow(unused_imports)]
 use crate::backend::{FB_SIZE, FB_WIDTH_PIXELS, FB_LINES};
@@ -17,19 +17,40 @@ use crate::backend::{FB_SIZE, FB_WIDTH_PIXELS, FB_LINES};
 ///
 /// The exact GlyphSprite chosen is picked based on a hierarchy that starts with a hint based on
 /// `xous::LANG`, then rules based on the `base_style: GlyphStyle` field, which allows for all the text within
-/// a given string to be eg. small, regular, monospace, bold (mixing of different styles is not yet supported,
-/// but could be in the future if we add some sort of markup parsing to the text stream).
+/// a given string to be eg. small, regular, monospace, bold. Runs of characters can be given a different
+/// style with a list of `StyleSpan`s; spaces between words always use the base style.
 ///
 /// The location of the GlyphSprites do a "Best effort" to fit the words within the `bounds` based on the
-/// designated rule without word-wrapping. If a single word overflows one line width, it will be broken
-/// into two words at the closest boundary that does not overflow the text box, otherwise, it will be moved
-/// to a new line on its own (e.g. no hyphenation rules are applied).
+/// designated rule. A word that doesn't fit on the current line is moved to a new line on its own, unless
+/// that would leave more than `1/LOOSE_LINE_DIVISOR` of the line empty: then it's hyphenated instead, if it
+/// has a place to break. Words can be broken after a hyphen, at a soft hyphen (U+00AD, which is only drawn
+/// when the line is broken there), or at the points listed in the loaded hyphenation dictionary (see
+/// `hyphenation.rs`). If a single word overflows one line width, it's broken at the last of those points
+/// that fits, or failing that, at the closest boundary that does not overflow the text box.
 ///
 /// If the overall string cannot fit within the absolute bounds defined by the `max` area and/or the `bounds`,
 /// the rendering is halted, and ellipses are inserted at the end.
 
 use crate::blitstr2::{self, *};
 use crate::style_macros::*;
+use crate::hyphenation::Hyphenation;
+
+/// drawn as a hyphen if a line is broken on it, and not drawn at all otherwise
+const SOFT_HYPHEN: char = '\u{ad}';
+/// a word is hyphenated rather than moved whole onto the next line if that would leave more
+/// than this fraction of the line empty
+const LOOSE_LINE_DIVISOR: usize = 5;
+
+/// The kinds of places a word can be broken across lines
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Break {
+    /// after a hyphen in the text
+    Explicit,
+    /// at a soft hyphen in the text, which turns into a visible hyphen
+    Soft,
+    /// at a point from the hyphenation dictionary; a hyphen is added to the end of the line
+    Dictionary,
+}
 
 impl TypesetWord {
     pub fn new(origin: Pt, strpos: usize) -> Self {
@@ -133,6 +154,10 @@ impl ComposedType {
                     renderable = false;
                 }
                 point.x += (glyph.wide + glyph.kern) as usize; // keep scorekeeping on this, because it could eventually become renderable
+                // zero-width glyphs (e.g. soft hyphens) have nothing to draw, but may still carry the insertion point
+                if glyph.wide == 0 && !glyph.insert {
+                    renderable = false;
+                }
                 if !renderable {
                     // quickly short circuit over any text that is definitely outside of our clipping rectangle
                     continue;
@@ -141,7 +166,18 @@ impl ComposedType {
                         clip_rect.tl().x as usize, clip_rect.tl().y as usize,
                         clip_rect.br().x as usize, clip_rect.br().y as usize
                     );
-                    if !glyph.double {
+                    if glyph.wide == 0 {
+                        // only the insertion point below
+                    } else if !crate::rotation::is_upright() {
+                        crate::rotation::xor_glyph(
+                            frbuf,
+                            &Point::new(maybe_x, maybe_y),
+                            *glyph,
+                            glyph.invert ^ invert,
+                            cr,
+                            if glyph.double { 2 } else { 1 }
+                        );
+                    } else if !glyph.double {
                         blitstr2::xor_glyph(
                             frbuf,
                             &Point::new(maybe_x, maybe_y),
@@ -199,7 +235,7 @@ impl ComposedType {
 ///
 /// An insertion point cursor will be injected into the TypesetWord stream at the character offset in
 /// the input `string` if it is specified as `Some(usize)`.
-pub(crate) struct Typesetter {
+pub(crate) struct Typesetter<'a> {
     charpos: usize,
     cursor: Cursor, // indicates the current insertion point for a candidate. it is not updated as the candidates are formed.
     candidate: TypesetWord,
@@ -213,8 +249,12 @@ pub(crate) struct Typesetter {
     overflow: bool,
     max_width: usize,
     last_line_height: usize, // scorecarding for the very last line on the loop exit
+    max_lines: Option<usize>,
+    line_count: usize, // number of lines started so far, including the current one
+    spans: Vec<StyleSpan>,
+    hyphenation: Option<&'a Hyphenation>,
 }
-impl Typesetter {
+impl<'a> Typesetter<'a> {
     pub fn setup(
         s: &str,
         extent: &Pt,
@@ -242,8 +282,28 @@ impl Typesetter {
             overflow: false,
             max_width: 0,
             last_line_height: 0,
+            max_lines: None,
+            line_count: 1,
+            spans: Vec::new(),
+            hyphenation: None,
         }
     }
+    /// Limits the composition to `max_lines` lines; text beyond that is handled by the overflow strategy.
+    pub fn set_max_lines(&mut self, max_lines: Option<usize>) {
+        self.max_lines = max_lines;
+    }
+    /// Sets the runs of characters that are drawn in a style other than the base style
+    pub fn set_style_spans(&mut self, spans: &[StyleSpan]) {
+        self.spans = spans.to_vec();
+    }
+    /// Sets the dictionary used to find places to hyphenate words
+    pub fn set_hyphenation(&mut self, hyphenation: &'a Hyphenation) {
+        self.hyphenation = Some(hyphenation);
+    }
+    /// True if the last call to `typeset()` could not fit all of the text
+    pub fn overflowed(&self) -> bool {
+        self.overflow
+    }
 
     /// Wrap the words in the string until the space overflows, leaving ellipsis at the end.
     /// Any prior result in self.words is overwritten.
@@ -343,8 +403,14 @@ impl Typesetter {
                 // 3. The evolving word is longer than a single line, and there are no more lines available.
                 // 4. The evolving word fits a line but doesn't fit this line, and there is space on a new line for it.
                 // 5. The evolving word fits a line but doesn't fit this line, and there is no more space at all.
-                let mut gs = if ch != '\t' {
-                    style_glyph(ch, &self.base_style)
+                let mut gs = if ch == SOFT_HYPHEN {
+                    let mut soft = self.hyphen_at(self.charpos);
+                    soft.ch = SOFT_HYPHEN;
+                    soft.wide = 0;
+                    soft.kern = 0;
+                    soft
+                } else if ch != '\t' {
+                    style_glyph(ch, &StyleSpan::style_at(&self.spans, self.charpos, self.base_style))
                 } else {
                     self.large_space.clone()
                 };
@@ -373,13 +439,18 @@ impl Typesetter {
                             //  012345|678
                             //  abcdef|hij
                             if self.is_newline_available() { // case 2
-                                // commit the fragment of the word to the current line
-                                let gs_pop = self.candidate.pop();
-                                self.commit_candidate_word(&mut composition);
-                                // set the cursor to the next line
-                                self.move_candidate_to_newline();
-                                // now set the overflowed character on the new line so our state is synched up
-                                self.candidate.push(gs_pop);
+                                if let Some((keep, kind)) = self.find_break() {
+                                    self.break_candidate(&mut composition, keep, kind);
+                                } else {
+                                    // no place to hyphenate: commit the fragment of the word to the current line
+                                    let gs_pop = self.candidate.pop();
+                                    self.commit_candidate_word(&mut composition);
+                                    // set the cursor to the next line
+                                    self.move_candidate_to_newline();
+                                    // now set the overflowed character on the new line so our state is synched up
+                                    self.candidate.push(gs_pop);
+                                }
+                                self.commit_candidate_glyph(&gs);
                             } else { // case 3
                                 // similar to the one-line iterator exit, but with a call to overflow at the end.
                                 let _gs_pop = self.candidate.pop();
@@ -400,7 +471,10 @@ impl Typesetter {
                         }
                         _ => {
                             if self.is_newline_available() { // case 4
-                                self.move_candidate_to_newline();
+                                match self.find_break() {
+                                    Some((keep, kind)) if self.is_line_loose() => self.break_candidate(&mut composition, keep, kind),
+                                    _ => self.move_candidate_to_newline(),
+                                }
                                 self.commit_candidate_glyph(&gs);
                             } else { // case 5
                                 self.reject_candidate_word();
@@ -417,6 +491,19 @@ impl Typesetter {
         if self.candidate.gs.len() > 0 {
             self.commit_candidate_word(&mut composition);
         }
+        // words were laid out in logical order; put any right-to-left text into visual order
+        if let Some(base) = crate::bidi::base_direction(&composition) {
+            let mut start = 0;
+            while start < composition.len() {
+                let y = composition[start].origin.y;
+                let mut end = start;
+                while end < composition.len() && composition[end].origin.y == y {
+                    end += 1;
+                }
+                crate::bidi::reorder_line(&mut composition[start..end], base);
+                start = end;
+            }
+        }
         let ret = ComposedType::new(composition,
             ClipRect::new(
                 self.bb.min.x, self.bb.min.y,
@@ -437,6 +524,11 @@ impl Typesetter {
         ret
     }
     fn is_newline_available(&self) -> bool {
+        if let Some(max_lines) = self.max_lines {
+            if self.line_count >= max_lines {
+                return false;
+            }
+        }
         // repeated, bare newlines will have a candidate height of 0, as it contains no glyphs. correct for that.
         let corrected_height = if self.candidate.height == 0 {
             self.cursor.line_height
@@ -452,6 +544,93 @@ impl Typesetter {
     fn is_word_longer_than_line(&self) -> bool {
         self.candidate.width >= (self.bb.max.x - self.bb.min.x)
     }
+    /// True if moving the candidate onto the next line would leave too much of this one empty
+    fn is_line_loose(&self) -> bool {
+        self.bb.max.x.saturating_sub(self.cursor.pt.x) * LOOSE_LINE_DIVISOR > self.bb.max.x - self.bb.min.x
+    }
+    fn hyphen_at(&self, charpos: usize) -> GlyphSprite {
+        style_glyph('-', &StyleSpan::style_at(&self.spans, charpos, self.base_style))
+    }
+    /// Finds the last place the candidate can be broken so that its front part, hyphen included, still
+    /// fits on the current line. Returns how many of its glyphs stay on this line, and the kind of break.
+    fn find_break(&self) -> Option<(usize, Break)> {
+        let chars: Vec<char> = self.s.chars().collect();
+        let start = self.candidate.strpos;
+        let len = self.candidate.gs.len();
+        // the candidate can be the tail of a word that was already broken, so look for the whole word
+        let mut word_start = start.min(chars.len());
+        while word_start > 0 && !chars[word_start - 1].is_whitespace() {
+            word_start -= 1;
+        }
+        let mut word_end = start.min(chars.len());
+        while word_end < chars.len() && !chars[word_end].is_whitespace() {
+            word_end += 1;
+        }
+        let word = &chars[word_start..word_end];
+        // each break is given as the position in the string of the first character after it
+        let mut breaks = Vec::<(usize, Break)>::new();
+        for (i, &ch) in word.iter().enumerate().take(word.len().saturating_sub(1)).skip(1) {
+            if ch == '-' {
+                breaks.push((word_start + i + 1, Break::Explicit));
+            } else if ch == SOFT_HYPHEN {
+                breaks.push((word_start + i + 1, Break::Soft));
+            }
+        }
+        if let Some(hyphenation) = self.hyphenation {
+            for p in hyphenation.points(word) {
+                // the text's own hyphens take precedence over the dictionary
+                if word[p - 1] != '-' && word[p - 1] != SOFT_HYPHEN && word[p] != '-' && word[p] != SOFT_HYPHEN {
+                    breaks.push((word_start + p, Break::Dictionary));
+                }
+            }
+        }
+        let mut best: Option<(usize, Break)> = None;
+        for (pos, kind) in breaks {
+            if pos <= start || pos - start >= len {
+                continue;
+            }
+            let keep = pos - start;
+            let mut width: usize = self.candidate.gs[..keep].iter().map(|gs| (gs.wide + gs.kern) as usize).sum();
+            if kind != Break::Explicit {
+                let hyphen = self.hyphen_at(pos - 1);
+                width += (hyphen.wide + hyphen.kern) as usize;
+            }
+            if self.cursor.pt.x + width < self.bb.max.x && best.map_or(true, |(k, _)| keep > k) {
+                best = Some((keep, kind));
+            }
+        }
+        best
+    }
+    /// Breaks the candidate after its first `keep` glyphs: the front part is committed to the current line,
+    /// and the rest becomes the candidate at the start of the next line.
+    fn break_candidate(&mut self, composition: &mut Vec::<TypesetWord>, keep: usize, kind: Break) {
+        let mut rest = Vec::<GlyphSprite>::new();
+        while self.candidate.gs.len() > keep {
+            rest.push(self.candidate.pop());
+        }
+        let pos = self.candidate.strpos + keep;
+        match kind {
+            Break::Explicit => (),
+            Break::Soft => {
+                let soft = self.candidate.pop();
+                let mut hyphen = self.hyphen_at(pos - 1);
+                hyphen.insert = soft.insert;
+                self.candidate.push(hyphen);
+            }
+            Break::Dictionary => {
+                let hyphen = self.hyphen_at(pos - 1);
+                self.candidate.push(hyphen);
+            }
+        }
+        self.commit_candidate_word(composition);
+        // the rest of the word started before the glyph at `charpos`, so the new candidate can't take its position from it
+        self.candidate.strpos = pos;
+        self.move_candidate_to_newline();
+        for gs in rest.into_iter().rev() {
+            self.candidate.push(gs);
+        }
+        self.cursor.line_height = self.candidate.height;
+    }
     fn is_insert_point(&self) -> bool {
         if let Some(ip) = self.insertion_point {
             if ip == self.charpos {
@@ -496,6 +675,7 @@ impl Typesetter {
         self.cursor.pt.y += self.cursor.line_height;
         self.cursor.pt.x = self.bb.min.x;
         self.cursor.line_height = self.candidate.height;
+        self.line_count += 1;
         // now set the current candidate word's origin to the beginning of this new line
         self.candidate.origin = self.cursor.pt;
     }
@@ -565,6 +745,12 @@ fn tsw_debug(tsw: &TypesetWord) {
 
 /// Find glyph for char using latin regular, emoji, ja, zh, and kr font data
 pub fn style_glyph(ch: char, base_style: &GlyphStyle) -> GlyphSprite {
+    if let Some(slot) = base_style.glyph_set_slot() {
+        return match crate::glyphset::loaded_glyph(slot, ch) {
+            Ok(g) => g,
+            _ => style_glyph(ch, &GlyphStyle::Regular),
+        };
+    }
     match xous::LANG {
         "zh" => {
             style_wrapper!(zh_rules, base_style, ch)
@@ -1,8 +1,8 @@
 #![cfg_attr(target_os = "none", no_std)]
 #![cfg_attr(target_os = "none", no_main)]
 
-mod emoji;
-use emoji::*;
+mod symbols;
+use symbols::*;
 
 use gam::api::SetCanvasBoundsRequest;
 use ime_plugin_api::{ImefCallback, ImefDescriptor, ImefOpcode};
@@ -65,6 +65,8 @@ struct InputTracker {
 
     /// render the predictions. Slightly awkward because this code comes from before we had libstd
     pred_options: [Option<String>; MAX_PREDICTION_OPTIONS],
+    /// which prediction is on the line, if it was brought up with the arrow keys
+    recall: Option<usize>,
     #[cfg(feature = "tts")]
     tts: TtsFrontend,
 }
@@ -88,6 +90,7 @@ impl InputTracker {
             last_height: 0,
             was_grown: false,
             pred_options: Default::default(),
+            recall: None,
             #[cfg(feature="tts")]
             tts: TtsFrontend::new(xns).unwrap(),
         }
@@ -116,8 +119,8 @@ impl InputTracker {
     pub fn is_init(&self) -> bool {
         self.input_canvas.is_some() && self.pred_canvas.is_some() && self.predictor.is_some()
     }
-    pub fn activate_emoji(&self) {
-        self.gam.raise_menu(gam::EMOJI_MENU_NAME).expect("couldn't activate emoji menu");
+    pub fn activate_symbol_picker(&self) {
+        self.gam.raise_menu(gam::SYMBOL_PICKER_NAME).expect("couldn't activate symbol picker");
     }
 
     pub fn clear_area(&mut self) -> Result<(), xous::Error> {
@@ -171,6 +174,44 @@ impl InputTracker {
         Ok(())
     }
 
+    /// Whether the predictor offers whole lines, like a shell history, rather than words
+    fn predicts_lines(&self) -> bool {
+        match self.pred_triggers {
+            Some(trigger) => trigger.newline && !trigger.whitespace && !trigger.punctuation,
+            None => false,
+        }
+    }
+    /// Puts `text` on the line in place of whatever was there, with the insertion point at its end
+    fn replace_line(&mut self, text: &str) {
+        self.line.clear();
+        self.line.push_str(text);
+        self.characters = text.chars().count();
+        self.insertion = self.characters;
+        self.pred_phrase.clear();
+        self.pred_phrase.push_str(text);
+        self.can_unpick = false;
+        self.last_trigger_char = Some(0);
+    }
+    /// Brings up the `index`th prediction on an otherwise empty line, if there is one. Returns whether there was.
+    fn recall_line(&mut self, index: usize) -> bool {
+        let pred = match self.predictor {
+            Some(pred) => pred,
+            None => return false,
+        };
+        if self.recall.is_none() {
+            // start from everything the predictor has, not just what matched what was typed before
+            pred.set_input(xous_ipc::String::<4000>::from_str("")).expect("couldn't update predictor with current input");
+        }
+        match pred.get_prediction(index as u32).expect("couldn't query prediction engine") {
+            Some(prediction) => {
+                self.replace_line(prediction.as_str().unwrap_or("UTF-8 Error"));
+                self.recall = Some(index);
+                true
+            }
+            None => false,
+        }
+    }
+
     fn insert_prediction(&mut self, index: usize) {
         let debug1 = false;
         if debug1{info!("IMEF|insert_prediction index {}", index);}
@@ -178,6 +219,12 @@ impl InputTracker {
             Some(s) => s,
             _ => return // if the index doesn't exist for some reason, do nothing without throwing an error
         };
+        if self.predicts_lines() {
+            // a whole line takes the place of what's been typed, which was only ever the search for it
+            let line = pred_str.clone();
+            self.replace_line(&line);
+            return;
+        }
         if debug1{info!("IMEF|insert_prediction string {}, last_trigger {:?}", pred_str, self.last_trigger_char);}
         if let Some(offset) = self.last_trigger_char {
             if offset < self.characters {
@@ -252,8 +299,32 @@ impl InputTracker {
             let mut do_redraw = false;
             for &k in newkeys.iter() {
                 if debug1{info!("got key '{}'", k);}
+                if k != '↑' && k != '↓' && k != '\u{0000}' {
+                    // anything else keeps the recalled line as it stands, to be edited or sent
+                    self.recall = None;
+                }
                 match k {
                     '\u{0000}' => (),
+                    // up and down step through the predictor's lines, newest first, while the line is empty or
+                    // still holds one of them
+                    '↑' if self.predicts_lines() && (self.characters == 0 || self.recall.is_some()) => {
+                        let next = self.recall.map_or(0, |index| index + 1);
+                        self.recall_line(next);
+                        do_redraw = true;
+                    }
+                    '↓' if self.recall.is_some() => {
+                        match self.recall {
+                            Some(index) if index > 0 => {
+                                self.recall_line(index - 1);
+                            }
+                            _ => {
+                                // past the newest is back to an empty line
+                                self.recall = None;
+                                self.replace_line("");
+                            }
+                        }
+                        do_redraw = true;
+                    }
                     '←' => { // move insertion point back
                         if self.insertion > 0 {
                             log::debug!("moving insertion point back");
@@ -295,6 +366,12 @@ impl InputTracker {
                         self.insert_prediction(0);
                         do_redraw = true;
                     }
+                    // tab completes a line with the first prediction, and asks for the ways to go on from there
+                    '\t' if self.predicts_lines() => {
+                        self.insert_prediction(0);
+                        update_predictor = true;
+                        do_redraw = true;
+                    }
                     '\u{0012}' => { // F2
                         self.insert_prediction(1);
                         do_redraw = true;
@@ -639,8 +716,8 @@ fn xmain() -> ! {
 
     let mut listener: Option<CID> = None;
 
-    // create the emoji menu handler
-    emoji_menu(xous::connect(imef_sid).unwrap());
+    // create the symbol picker, for characters that aren't on the keyboard
+    symbol_picker(xous::connect(imef_sid).unwrap());
 
     log::trace!("Initialized but still waiting for my canvas Gids");
     loop {
@@ -708,7 +785,7 @@ fn xmain() -> ! {
                         ];
                         log::trace!("tracking keys: {:?}", keys);
                         if keys[0] == '😊' {
-                            tracker.activate_emoji();
+                            tracker.activate_symbol_picker();
                         } else {
                             if let Some(line) = tracker.update(keys, false).expect("couldn't update input tracker with latest key presses") {
                                 if dbglistener{info!("sending listeners {:?}", line);}
@@ -0,0 +1,30 @@
+use ime_plugin_api::ImefOpcode;
+use num_traits::*;
+use locales::t;
+
+use gam::SymbolPage;
+
+/// Characters the keyboard has no key for, only ones the fonts can draw
+const SYMBOLS: &str = "€£¥¢¤§¶©®™°±×÷µ¹²³¼½¾«»‹›‘’‚“”„•…–—†‡¡¿¦¬¨¯´¸ªº";
+const ACCENTED: &str = "àáâãäåæçèéêëìíîïðñòóôõöøœùúûüýþÿßÀÁÂÃÄÅÆÇÈÉÊËÌÍÎÏÐÑÒÓÔÕÖØŒÙÚÛÜÝÞ";
+const EMOJI: &str = "😃😄😁😅😂🤣😊😇🙂😉😍😘😋😜🤔😐😒🙄😬😌😔😴😷🤒😎😕😟😮😲😳😢😭😱😡👍👎👏🙏👋💪❤💔🔥✨🎉⭐✅❌❓❗☕🍕🍺🎂🌞🌧⚡🏠🚗✈📞📷🔒🔑💡";
+
+/// Sets up the symbol picker, which hands what's picked back to us as a key press
+pub(crate) fn symbol_picker(imef_conn: xous::CID) {
+    // the picker has to be created in a thread otherwise we get a deadlock while it registers with the GAM,
+    // because of the IMEF's unique place in the graphics hierarchy.
+    let _ = std::thread::spawn({
+        move || {
+            gam::symbol_picker(
+                vec![
+                    SymbolPage { title: t!("symbols.symbols", xous::LANG), symbols: SYMBOLS.chars().collect() },
+                    SymbolPage { title: t!("symbols.accented", xous::LANG), symbols: ACCENTED.chars().collect() },
+                    SymbolPage { title: t!("symbols.emoji", xous::LANG), symbols: EMOJI.chars().collect() },
+                ],
+                gam::SYMBOL_PICKER_NAME,
+                imef_conn,
+                ImefOpcode::ProcessKeys.to_u32().unwrap(),
+            );
+        }
+    });
+}
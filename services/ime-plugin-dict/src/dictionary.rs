@@ -0,0 +1,310 @@
+//! The words the predictor knows: a short built-in list of common words, and the words the user has typed,
+//! with how often. The learned words are kept in the PDDB under `DICT_NAME`, one key per language, as lines
+//! of a count and a word, least recently used first.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+const DICT_NAME: &str = "ime.dict";
+/// the most words learned; past this, the least used are forgotten
+const MAX_LEARNED: usize = 1000;
+/// words shorter than this aren't worth predicting, and longer ones are probably not words
+const MIN_WORD_LEN: usize = 2;
+const MAX_WORD_LEN: usize = 32;
+/// how many words are learned between saves, as every save is a write to flash
+const SAVE_INTERVAL: u32 = 8;
+
+/// common English words, most used first
+const ENGLISH: &str = "the and that have for not with you this but his from they say her she will one all would \
+    there their what out about who get which when make can like time just him know take people into year your \
+    good some could them see other than then now look only come its over think also back after use two how our \
+    work first well way even new want because any these give day most thanks thank please sorry yes okay \
+    meeting tomorrow today tonight morning afternoon evening week weekend later soon where here again \
+    something nothing everything anything someone everyone great really very much many little long right \
+    still should never always maybe sure call send find tell ask feel leave put mean keep let begin seem help \
+    talk turn start show hear play run move live believe hold bring happen write provide sit stand lose pay \
+    meet include continue learn change lead understand watch follow stop create speak read spend grow open \
+    walk offer remember love consider appear buy wait serve die build stay fall reach kill remain \
+    home house place world school family friend problem question number point government company hand part \
+    message phone password network address";
+
+struct Learned {
+    count: u32,
+    /// when the word was last typed, on `Dictionary::clock`
+    last_used: u64,
+}
+
+pub(crate) struct Dictionary {
+    /// what the user has typed, as typed, with how many times and how recently
+    learned: HashMap<String, Learned>,
+    /// counts up with every word learned or loaded, to tell which was used more recently
+    clock: u64,
+    builtin: Vec<&'static str>,
+    /// the most recently learned word, so a pick can be taken back
+    last_learned: Option<String>,
+    /// words learned since the last save
+    unsaved: u32,
+    loaded: bool,
+}
+
+impl Dictionary {
+    pub(crate) fn new() -> Self {
+        let builtin = match xous::LANG {
+            "en" | "en-tts" => ENGLISH.split_whitespace().collect(),
+            _ => Vec::new(),
+        };
+        Dictionary {
+            learned: HashMap::new(),
+            clock: 0,
+            builtin,
+            last_learned: None,
+            unsaved: 0,
+            loaded: false,
+        }
+    }
+
+    /// Up to `max` words that start with `prefix`, best first, with their first letter capitalized if
+    /// `prefix`'s is
+    pub(crate) fn complete(&self, prefix: &str, max: usize) -> Vec<String> {
+        let lower = prefix.to_lowercase();
+        if lower.is_empty() {
+            return Vec::new();
+        }
+        let matches = |word: &str| word.len() > prefix.len() && word.to_lowercase().starts_with(&lower);
+        let mut learned: Vec<(&String, &Learned)> = self.learned.iter().filter(|(w, _)| matches(w)).collect();
+        learned.sort_by(|a, b| b.1.count.cmp(&a.1.count).then(b.1.last_used.cmp(&a.1.last_used)));
+        let mut words: Vec<String> = learned.into_iter().map(|(w, _)| w.clone()).collect();
+        for &word in self.builtin.iter() {
+            if words.len() >= max {
+                break;
+            }
+            if matches(word) && !words.iter().any(|w| w.to_lowercase() == word) {
+                words.push(word.to_string());
+            }
+        }
+        words.truncate(max);
+        let capitalized = prefix.chars().next().map(|c| c.is_uppercase()).unwrap_or(false);
+        if capitalized {
+            for word in words.iter_mut() {
+                let mut chars = word.chars();
+                if let Some(first) = chars.next() {
+                    *word = first.to_uppercase().chain(chars).collect();
+                }
+            }
+        }
+        words
+    }
+
+    /// Counts `word` as typed once more. Returns whether it was taken as a word at all.
+    pub(crate) fn learn(&mut self, word: &str) -> bool {
+        let len = word.chars().count();
+        if len < MIN_WORD_LEN || len > MAX_WORD_LEN || !word.chars().all(char::is_alphabetic) {
+            return false;
+        }
+        self.clock += 1;
+        let learned = self.learned.entry(word.to_string()).or_insert(Learned { count: 0, last_used: 0 });
+        learned.count += 1;
+        learned.last_used = self.clock;
+        self.last_learned = Some(word.to_string());
+        self.unsaved += 1;
+        if self.learned.len() > MAX_LEARNED {
+            self.forget_least_used();
+        }
+        true
+    }
+    /// Takes back the most recent `learn()`
+    pub(crate) fn unlearn(&mut self) {
+        if let Some(word) = self.last_learned.take() {
+            if let Some(learned) = self.learned.get_mut(&word) {
+                learned.count -= 1;
+                if learned.count == 0 {
+                    self.learned.remove(&word);
+                }
+            }
+            self.unsaved += 1;
+        }
+    }
+    /// Forgets the word typed the fewest times, the one typed longest ago of those. The word just learned is
+    /// kept, as it would otherwise be the first to go once the dictionary is full.
+    fn forget_least_used(&mut self) {
+        let last_learned = self.last_learned.as_ref();
+        if let Some(word) = self
+            .learned
+            .iter()
+            .filter(|(w, _)| Some(*w) != last_learned)
+            .min_by_key(|(_, l)| (l.count, l.last_used))
+            .map(|(w, _)| w.clone())
+        {
+            self.learned.remove(&word);
+        }
+    }
+
+    /// Reads in the saved words, if they haven't been already and the PDDB is mounted. Anything learned before
+    /// then is added to them.
+    pub(crate) fn load(&mut self, pddb: &mut pddb::Pddb) {
+        if self.loaded || !pddb.is_mounted() {
+            return;
+        }
+        self.loaded = true;
+        let mut text = String::new();
+        match pddb.get(DICT_NAME, xous::LANG, None, false, false, None, None::<fn()>) {
+            Ok(mut key) => {
+                if key.read_to_string(&mut text).is_err() {
+                    log::warn!("couldn't read the learned words");
+                    return;
+                }
+            }
+            Err(_) => return, // nothing's been learned yet
+        }
+        self.load_text(&text);
+        log::info!("loaded {} learned words", self.learned.len());
+    }
+    /// Adds in the words saved as `text`, which come ahead of anything learned this boot
+    fn load_text(&mut self, text: &str) {
+        let lines = text.lines().count() as u64;
+        // anything learned before the load is more recent than all of what's loaded
+        for learned in self.learned.values_mut() {
+            learned.last_used += lines;
+        }
+        for (index, line) in text.lines().enumerate() {
+            let mut fields = line.splitn(2, ' ');
+            match (fields.next().and_then(|c| c.parse::<u32>().ok()), fields.next()) {
+                (Some(count), Some(word)) => {
+                    let learned = self.learned.entry(word.to_string()).or_insert(Learned {
+                        count: 0,
+                        last_used: index as u64 + 1,
+                    });
+                    learned.count += count;
+                }
+                _ => log::warn!("ignoring learned word we don't understand: {}", line),
+            }
+        }
+        self.clock += lines;
+        while self.learned.len() > MAX_LEARNED {
+            self.forget_least_used();
+        }
+    }
+    /// The learned words as they're saved
+    fn saved_text(&self) -> String {
+        let mut words: Vec<(&String, &Learned)> = self.learned.iter().collect();
+        words.sort_by_key(|(_, l)| l.last_used);
+        let mut text = String::new();
+        for (word, learned) in words {
+            text.push_str(&format!("{} {}\n", learned.count, word));
+        }
+        text
+    }
+
+    /// Saves the learned words, once enough have changed since the last save to be worth it
+    pub(crate) fn save(&mut self, pddb: &mut pddb::Pddb) {
+        if !self.loaded || self.unsaved < SAVE_INTERVAL {
+            return;
+        }
+        let text = self.saved_text();
+        // the key is replaced whole, as it's easier than working out how much of it has changed
+        pddb.delete_key(DICT_NAME, xous::LANG, None).ok();
+        match pddb.get(DICT_NAME, xous::LANG, None, true, true, Some(text.len()), None::<fn()>) {
+            Ok(mut key) => match key.write_all(text.as_bytes()).and_then(|_| key.flush()) {
+                Ok(_) => self.unsaved = 0,
+                Err(e) => log::error!("couldn't save the learned words: {:?}", e),
+            },
+            Err(e) => log::error!("couldn't save the learned words: {:?}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary(builtin: &[&'static str]) -> Dictionary {
+        Dictionary {
+            learned: HashMap::new(),
+            clock: 0,
+            builtin: builtin.to_vec(),
+            last_learned: None,
+            unsaved: 0,
+            loaded: true,
+        }
+    }
+
+    #[test]
+    fn completes_from_learned_then_builtin_words() {
+        let mut dict = dictionary(&["the", "there", "their", "them"]);
+        assert!(dict.complete("", 4).is_empty());
+        assert_eq!(dict.complete("the", 4), vec!["there", "their", "them"]);
+        dict.learn("thermos");
+        dict.learn("theory");
+        dict.learn("theory");
+        // most typed first, and a learned word isn't offered again from the built-in list
+        dict.learn("them");
+        assert_eq!(dict.complete("the", 4), vec!["theory", "them", "thermos", "there"]);
+        assert_eq!(dict.complete("the", 2), vec!["theory", "them"]);
+        // the word typed so far isn't a completion of itself
+        assert!(dict.complete("theory", 4).is_empty());
+        assert_eq!(dict.complete("Th", 2), vec!["Theory", "Them"]);
+    }
+
+    #[test]
+    fn learns_only_words() {
+        let mut dict = dictionary(&[]);
+        assert!(!dict.learn("a"));
+        assert!(!dict.learn("h4x"));
+        assert!(!dict.learn(&"a".repeat(MAX_WORD_LEN + 1)));
+        assert!(dict.learn("café"));
+        assert_eq!(dict.complete("ca", 4), vec!["café"]);
+        dict.unlearn();
+        assert!(dict.complete("ca", 4).is_empty());
+    }
+
+    #[test]
+    fn forgets_the_least_used_oldest_word() {
+        let mut dict = dictionary(&[]);
+        let words: Vec<String> = (0..MAX_LEARNED).map(|i| format!("w{}", word_for(i))).collect();
+        for word in words.iter() {
+            assert!(dict.learn(word));
+        }
+        dict.learn(&words[0]);
+        // full, with every word but the first typed once; the oldest of those goes, and not the new word
+        assert!(dict.learn("newcomer"));
+        assert_eq!(dict.learned.len(), MAX_LEARNED);
+        assert!(dict.learned.contains_key("newcomer"));
+        assert!(dict.learned.contains_key(&words[0]));
+        assert!(!dict.learned.contains_key(&words[1]));
+        assert!(dict.learned.contains_key(&words[2]));
+    }
+
+    /// a distinct all-letter word for `i`
+    fn word_for(i: usize) -> String {
+        let mut word = String::new();
+        let mut i = i;
+        loop {
+            word.push((b'a' + (i % 26) as u8) as char);
+            i /= 26;
+            if i == 0 {
+                break word;
+            }
+        }
+    }
+
+    #[test]
+    fn saved_words_load_back() {
+        let mut dict = dictionary(&[]);
+        dict.learn("older");
+        dict.learn("often");
+        dict.learn("often");
+        dict.learn("newer");
+        let text = dict.saved_text();
+        assert_eq!(text, "1 older\n2 often\n1 newer\n");
+
+        let mut loaded = dictionary(&[]);
+        // learned before the PDDB mounted, so it's more recent than anything saved
+        loaded.learn("odd");
+        loaded.learn("often");
+        loaded.load_text(&text);
+        loaded.load_text("garbage\n");
+        assert_eq!(loaded.learned.len(), 4);
+        assert_eq!(loaded.learned["often"].count, 3);
+        assert_eq!(loaded.saved_text(), "1 older\n1 newer\n1 odd\n3 often\n");
+    }
+}
@@ -0,0 +1,11 @@
+#![cfg_attr(target_os = "none", no_std)]
+
+//! A word predictor: as a word is typed, it offers the words it knows that start that way, the ones the
+//! user types most often first. Every word typed is learned, and kept in the PDDB once it's mounted.
+//!
+//! It's the predictor a chat app gets if it doesn't name one of its own when registering with the GAM.
+
+pub const SERVER_NAME_IME_PLUGIN_DICT: &str = "_IME dictionary plugin_";
+
+// just inherit all the default from the ime_plugin_api
+pub use ime_plugin_api::*;
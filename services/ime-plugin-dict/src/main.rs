@@ -0,0 +1,98 @@
+#![cfg_attr(target_os = "none", no_std)]
+#![cfg_attr(target_os = "none", no_main)]
+
+mod dictionary;
+use dictionary::Dictionary;
+
+use ime_plugin_api::*;
+
+use log::{error, info};
+
+use num_traits::FromPrimitive;
+use xous_ipc::{Buffer, String};
+
+/// how many predictions the IME front end asks for
+const MAX_PREDICTIONS: usize = 4;
+
+#[xous::xous_main]
+fn xmain() -> ! {
+    log_server::init_wait().unwrap();
+    log::set_max_level(log::LevelFilter::Info);
+    info!("my PID is {}", xous::process::id());
+
+    let xns = xous_names::XousNames::new().unwrap();
+    // one connection only, should be the IME front end
+    let ime_dict_sid = xns
+        .register_name(ime_plugin_dict::SERVER_NAME_IME_PLUGIN_DICT, Some(1))
+        .expect("can't register server");
+    log::trace!("registered with NS -- {:?}", ime_dict_sid);
+
+    let mut dictionary = Dictionary::new();
+    // connected on first use, so the PDDB doesn't hold up our boot
+    let mut pddb: Option<pddb::Pddb> = None;
+    let mut predictions: Vec<std::string::String> = Vec::new();
+
+    let mytriggers = PredictionTriggers {
+        newline: false,
+        punctuation: true,
+        whitespace: true,
+    };
+
+    info!("ready to accept requests");
+    loop {
+        let mut msg = xous::receive_message(ime_dict_sid).unwrap();
+        log::trace!("received message {:?}", msg);
+        match FromPrimitive::from_usize(msg.body.id()) {
+            Some(Opcode::Input) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let s = buffer.as_flat::<String<4000>, _>().unwrap();
+                // the input is the word typed so far
+                dictionary.load(pddb.get_or_insert_with(pddb::Pddb::new));
+                predictions = dictionary.complete(s.as_str(), MAX_PREDICTIONS);
+            }
+            Some(Opcode::Picked) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let s = buffer.as_flat::<String<4000>, _>().unwrap();
+                let pddb = pddb.get_or_insert_with(pddb::Pddb::new);
+                dictionary.load(pddb);
+                if dictionary.learn(s.as_str()) {
+                    dictionary.save(pddb);
+                }
+                // the word is done, so there's nothing to predict until the next one is started
+                predictions.clear();
+            }
+            Some(Opcode::Prediction) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let mut prediction: Prediction = buffer.to_original::<Prediction, _>().unwrap();
+                log::trace!("querying prediction index {}", prediction.index);
+                prediction.string.clear();
+                match predictions.get(prediction.index as usize) {
+                    Some(word) => {
+                        use core::fmt::Write;
+                        prediction.valid = write!(prediction.string, "{}", word).is_ok();
+                    }
+                    None => prediction.valid = false,
+                }
+                buffer.replace(Return::Prediction(prediction)).expect("couldn't return Prediction");
+            }
+            Some(Opcode::Unpick) => {
+                dictionary.unlearn();
+            }
+            Some(Opcode::GetPredictionTriggers) => {
+                xous::return_scalar(msg.sender, mytriggers.into()).expect("couldn't return GetPredictionTriggers");
+            }
+            Some(Opcode::Quit) => {
+                error!("received quit, goodbye!");
+                break;
+            }
+            None => {
+                error!("unknown Opcode");
+            }
+        }
+    }
+    log::trace!("main loop exit, destroying servers");
+    xns.unregister_server(ime_dict_sid).unwrap();
+    xous::destroy_server(ime_dict_sid).unwrap();
+    log::trace!("quitting");
+    xous::terminate_process(0)
+}
@@ -0,0 +1,222 @@
+//! The lines typed into the shell, oldest first. They're kept in the PDDB under `HISTORY_DICT`, one per line of
+//! the `HISTORY_KEY` key, so they're still there after a reboot.
+//!
+//! A line typed with a space in front of it, or one that carries a password or other secret anywhere in it (so
+//! `run add boot wlan setpass ...` too), isn't kept.
+//!
+//! Lines are written out every `SAVE_INTERVAL` kept, to spare the PDDB a rewrite of the key for each one, so the
+//! last few typed can be lost if the power goes.
+
+use std::io::{Read, Write};
+
+const HISTORY_DICT: &str = "shellchat.history";
+const HISTORY_KEY: &str = "history";
+/// the most lines kept; past this, the oldest are forgotten
+const HISTORY_MAX: usize = 100;
+/// how many lines are kept between writes to the PDDB
+pub(crate) const SAVE_INTERVAL: usize = 4;
+/// commands whose arguments are secret, as words
+const PRIVATE_COMMANDS: [&str; 3] = ["wlan setpass", "pddb put", "net proxy"];
+
+pub(crate) struct History {
+    lines: Vec<String>,
+    loaded: bool,
+    /// lines kept since the last save
+    unsaved: usize,
+    /// the last line `push` kept, and where it stood before being moved up, so it can be taken back
+    last_push: Option<Option<usize>>,
+}
+
+/// Whether `line` carries a secret. Words are compared, so extra spaces don't hide one.
+fn is_private(line: &str) -> bool {
+    let words = line.split_whitespace().collect::<Vec<&str>>().join(" ");
+    PRIVATE_COMMANDS.iter().any(|private| {
+        words.match_indices(private).any(|(at, _)| {
+            (at == 0 || words.as_bytes()[at - 1] == b' ')
+                && words.as_bytes().get(at + private.len()).map_or(true, |&b| b == b' ')
+        })
+    })
+}
+
+impl History {
+    pub(crate) fn new() -> Self {
+        History { lines: Vec::new(), loaded: false, unsaved: 0, last_push: None }
+    }
+
+    /// The `index`th newest line with `search` in it, whatever the case, or with an empty search, just the
+    /// `index`th newest line
+    pub(crate) fn find(&self, search: &str, index: usize) -> Option<&str> {
+        let search = search.to_lowercase();
+        self.lines.iter().rev().filter(|line| line.to_lowercase().contains(&search)).nth(index).map(|line| line.as_str())
+    }
+
+    /// Adds `line` as the newest, moving it up if it's already there. Returns whether it was kept.
+    pub(crate) fn push(&mut self, line: &str) -> bool {
+        if line.trim().is_empty() || line.starts_with(' ') || is_private(line) {
+            self.last_push = None;
+            return false;
+        }
+        let was_at = self.lines.iter().position(|l| l == line);
+        if let Some(at) = was_at {
+            self.lines.remove(at);
+        }
+        self.lines.push(line.to_string());
+        if self.lines.len() > HISTORY_MAX {
+            self.lines.remove(0);
+        }
+        self.last_push = Some(was_at);
+        self.unsaved += 1;
+        true
+    }
+    /// Takes back the last `push`, if it kept its line, putting a line that was moved up back where it was.
+    /// Returns whether there was anything to take back.
+    pub(crate) fn pop(&mut self) -> bool {
+        match self.last_push.take() {
+            Some(was_at) => {
+                if let Some(line) = self.lines.pop() {
+                    if let Some(at) = was_at {
+                        self.lines.insert(at.min(self.lines.len()), line);
+                    }
+                }
+                self.unsaved += 1;
+                true
+            }
+            None => false,
+        }
+    }
+    /// Whether enough has changed since the last save to write the lines out
+    pub(crate) fn needs_save(&self) -> bool {
+        self.unsaved >= SAVE_INTERVAL
+    }
+
+    /// Reads in the saved lines, if they haven't been already and the PDDB is mounted. Anything typed before
+    /// then comes after them.
+    pub(crate) fn load(&mut self, pddb: &mut pddb::Pddb) {
+        if self.loaded || !pddb.is_mounted() {
+            return;
+        }
+        self.loaded = true;
+        let mut text = String::new();
+        match pddb.get(HISTORY_DICT, HISTORY_KEY, None, false, false, None, None::<fn()>) {
+            Ok(mut key) => {
+                if key.read_to_string(&mut text).is_err() {
+                    log::warn!("couldn't read the shell history");
+                    return;
+                }
+            }
+            Err(_) => return, // nothing's been typed yet
+        }
+        let typed = core::mem::take(&mut self.lines);
+        for line in text.lines().chain(typed.iter().map(|l| l.as_str())) {
+            self.push(line);
+        }
+        // only what was typed before the load is new
+        self.unsaved = typed.len();
+        self.last_push = None;
+        log::info!("loaded {} lines of shell history", self.lines.len());
+    }
+
+    pub(crate) fn save(&mut self, pddb: &mut pddb::Pddb) {
+        if !self.loaded {
+            return;
+        }
+        self.unsaved = 0;
+        let mut text = String::new();
+        for line in self.lines.iter() {
+            text.push_str(line);
+            text.push('\n');
+        }
+        // the key is replaced whole, as the PDDB can't shorten a key in place
+        pddb.delete_key(HISTORY_DICT, HISTORY_KEY, None).ok();
+        match pddb.get(HISTORY_DICT, HISTORY_KEY, None, true, true, Some(text.len()), None::<fn()>) {
+            Ok(mut key) => {
+                if let Err(e) = key.write_all(text.as_bytes()).and_then(|_| key.flush()) {
+                    log::error!("couldn't save the shell history: {:?}", e);
+                }
+            }
+            Err(e) => log::error!("couldn't save the shell history: {:?}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(lines: &[&str]) -> History {
+        let mut history = History::new();
+        for line in lines {
+            history.push(line);
+        }
+        history
+    }
+
+    #[test]
+    fn find_newest_first() {
+        let history = history(&["ver xous", "net ping 10.0.0.1", "Ver ec", "echo hi"]);
+        assert_eq!(history.find("", 0), Some("echo hi"));
+        assert_eq!(history.find("", 3), Some("ver xous"));
+        assert_eq!(history.find("", 4), None);
+        assert_eq!(history.find("ver", 0), Some("Ver ec"));
+        assert_eq!(history.find("VER", 1), Some("ver xous"));
+        assert_eq!(history.find("ver", 2), None);
+        assert_eq!(history.find("pddb", 0), None);
+    }
+
+    #[test]
+    fn push_moves_up_and_forgets_the_oldest() {
+        let mut history = history(&["a", "b", "c"]);
+        assert!(history.push("a"));
+        assert_eq!(history.lines, vec!["b", "c", "a"]);
+        for i in 0..HISTORY_MAX {
+            history.push(&format!("line {}", i));
+        }
+        assert_eq!(history.lines.len(), HISTORY_MAX);
+        assert_eq!(history.find("", HISTORY_MAX - 1), Some("line 0"));
+    }
+
+    #[test]
+    fn push_skips_private_lines() {
+        let mut history = History::new();
+        assert!(!history.push(""));
+        assert!(!history.push("   "));
+        assert!(!history.push(" ver xous"));
+        assert!(!history.push("wlan setpass hunter2"));
+        assert!(!history.push("wlan  setpass hunter2"));
+        assert!(!history.push("pddb put wallet:seed 1234"));
+        assert!(!history.push("net proxy 10.0.0.1:1080"));
+        assert!(!history.push("run add boot wlan setpass hunter2"));
+        assert!(history.push("wlan setssid cafe"));
+        assert!(history.push("pddb putative"));
+        assert_eq!(history.lines, vec!["wlan setssid cafe", "pddb putative"]);
+    }
+
+    #[test]
+    fn pop_only_takes_back_a_kept_line() {
+        let mut history = history(&["a", "b", "c"]);
+        assert!(!history.push("wlan setpass hunter2"));
+        assert!(!history.pop());
+        assert_eq!(history.lines, vec!["a", "b", "c"]);
+        // a line that was moved up goes back where it was
+        history.push("a");
+        assert!(history.pop());
+        assert_eq!(history.lines, vec!["a", "b", "c"]);
+        assert!(!history.pop());
+        history.push("d");
+        assert!(history.pop());
+        assert_eq!(history.lines, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn saves_are_batched() {
+        let mut history = History::new();
+        for i in 0..SAVE_INTERVAL - 1 {
+            history.push(&format!("line {}", i));
+            assert!(!history.needs_save());
+        }
+        assert!(!history.push("wlan setpass hunter2"));
+        assert!(!history.needs_save());
+        history.push("one more");
+        assert!(history.needs_save());
+    }
+}
@@ -4,3 +4,21 @@ pub const SERVER_NAME_IME_PLUGIN_SHELL: &str = "_IME shell plugin_";
 
 // just inherit all the default from the ime_plugin_api
 pub use ime_plugin_api::*;
+
+/// Where the predictor asks the shell how the line typed so far could be completed. The shell registers a
+/// server under this name, and answers `CompletionOpcode::Complete` with the `Completion` filled in.
+pub const SERVER_NAME_SHELL_COMPLETION: &str = "_Shell completion_";
+
+#[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive)]
+pub enum CompletionOpcode {
+    /// complete a line, lent as a `Completion`
+    Complete = 0,
+}
+
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct Completion {
+    /// the line typed so far
+    pub line: xous_ipc::String::<1024>,
+    /// set by the shell: the whole line, completed, each way it could go, one per line, as many as fit
+    pub candidates: xous_ipc::String::<1024>,
+}
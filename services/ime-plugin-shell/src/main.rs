@@ -1,12 +1,44 @@
 #![cfg_attr(target_os = "none", no_std)]
 #![cfg_attr(target_os = "none", no_main)]
 
+mod history;
+use history::History;
+
 use ime_plugin_api::*;
 
 use log::{error, info};
 
 use xous_ipc::{String, Buffer};
-use num_traits::FromPrimitive;
+use num_traits::{FromPrimitive, ToPrimitive};
+
+/// The ways the shell says `line` could be completed, if it's running and there's anything on the line yet
+fn complete(xns: &xous_names::XousNames, completion_conn: &mut Option<xous::CID>, line: &str) -> Vec<std::string::String> {
+    if line.trim().is_empty() {
+        return Vec::new();
+    }
+    if completion_conn.is_none() {
+        *completion_conn = xns.request_connection(ime_plugin_shell::SERVER_NAME_SHELL_COMPLETION).ok();
+    }
+    let conn = match completion_conn {
+        Some(conn) => *conn,
+        None => return Vec::new(),
+    };
+    let completion = ime_plugin_shell::Completion {
+        line: String::<1024>::from_str(line),
+        candidates: String::<1024>::new(),
+    };
+    let mut buf = match Buffer::into_buf(completion) {
+        Ok(buf) => buf,
+        Err(_) => return Vec::new(),
+    };
+    if buf.lend_mut(conn, ime_plugin_shell::CompletionOpcode::Complete.to_u32().unwrap()).is_err() {
+        return Vec::new();
+    }
+    match buf.to_original::<ime_plugin_shell::Completion, _>() {
+        Ok(completion) => completion.candidates.as_str().unwrap_or("").lines().map(std::string::String::from).collect(),
+        Err(_) => Vec::new(),
+    }
+}
 
 #[xous::xous_main]
 fn xmain() -> ! {
@@ -19,21 +51,15 @@ fn xmain() -> ! {
     let ime_sh_sid = xns.register_name(ime_plugin_shell::SERVER_NAME_IME_PLUGIN_SHELL, Some(1)).expect("can't register server");
     log::trace!("registered with NS -- {:?}", ime_sh_sid);
 
-    let mut history: Vec<String<64>> = Vec::new();
-    let history_max = 4;
-
-    if false { // loads defaults into the predictor array to test things
-        use core::fmt::Write as CoreWriter;
-        let mut test1: String::<64> = String::new();
-        write!(test1, "This〰should overflow the box").unwrap();
-        history.push(test1);
-        let mut test2: String::<64> = String::new();
-        write!(test2, "Another string too long").unwrap();
-        history.push(test2);
-        let mut test3: String::<64> = String::new();
-        write!(test3, "未雨绸缪").unwrap();
-        history.push(test3);
-    }
+    let mut history = History::new();
+    // connected on first use, so the PDDB doesn't hold up our boot
+    let mut pddb: Option<pddb::Pddb> = None;
+    // what's been typed of the line so far, which picks out the history lines that are offered
+    let mut search = std::string::String::new();
+    // how the shell says the line could be completed, offered ahead of the history
+    let mut completions: Vec<std::string::String> = Vec::new();
+    // connected on first use, as the shell starts after us
+    let mut completion_conn: Option<xous::CID> = None;
 
     let mytriggers = PredictionTriggers {
         newline: true,
@@ -47,57 +73,51 @@ fn xmain() -> ! {
         log::trace!("received message {:?}", msg);
         match FromPrimitive::from_usize(msg.body.id()) {
             Some(Opcode::Input) => {
-                // shell does nothing with the input, it only keeps track of
-                // the picked results
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let s = buffer.as_flat::<String::<4000>, _>().unwrap();
+                // the input is the whole line so far, as the only trigger is the newline
+                search.clear();
+                search.push_str(s.as_str());
+                completions = complete(&xns, &mut completion_conn, &search);
             }
             Some(Opcode::Picked) => {
                 let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
                 let s = buffer.as_flat::<String::<4000>, _>().unwrap();
-                // the API allows for large picked feedback, but this implementation only keeps the first 64 characters
-                let mut local_s: String<64> = String::new();
-                use core::fmt::Write;
-                write!(local_s, "{}", s.as_str()).expect("overflowed history variable");
                 log::trace!("storing history value | {}", s.as_str());
-                if history.len() == history_max {
-                    history.remove(0);
+                let pddb = pddb.get_or_insert_with(pddb::Pddb::new);
+                history.load(pddb);
+                if history.push(s.as_str()) && history.needs_save() {
+                    history.save(pddb);
                 }
-                history.push(local_s);
-                log::trace!("history has length {}", history.len());
+                // the next line starts out empty, so it's offered the most recent lines
+                search.clear();
+                completions.clear();
             }
             Some(Opcode::Prediction) => {
                 let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
                 let mut prediction: Prediction = buffer.to_original::<Prediction, _>().unwrap();
                 log::trace!("querying prediction index {}", prediction.index);
-                log::trace!("{:?}", prediction);
-                if history.len() > 0 && ((prediction.index as usize) < history.len()) {
-                    let mut index = prediction.index;
-                    if index >= history.len() as u32 {
-                        index = history.len() as u32 - 1;
-                    }
-                    let mut i = 1;
-                    for &s in history.iter() {
-                        // iterator is from oldest to newest, so do some math to go from newest to oldest
-                        // TIL: there is a .rev() feature in iterators. Next time maybe use that instead.
-                        if (history.len() as u32 - i) == index {
-                            // decompose the string into a character-by-character sequence
-                            // and then stuff byte-by-byte, as fits, into the return array
-                            prediction.string.clear();
-                            for ch in s.as_str().unwrap().chars() {
-                                if let Ok(_) = prediction.string.push(ch) {
-                                    // it's ok, carry on.
-                                } else {
-                                    // we ran out of space, stop copying
-                                    break;
-                                }
+                history.load(pddb.get_or_insert_with(pddb::Pddb::new));
+                prediction.string.clear();
+                let index = prediction.index as usize;
+                let found = match completions.get(index) {
+                    Some(line) => Some(line.as_str()),
+                    None => history.find(&search, index - completions.len()),
+                };
+                match found {
+                    Some(line) => {
+                        // stuff the line character by character, as fits, into the return array
+                        for ch in line.chars() {
+                            if prediction.string.push(ch).is_err() {
+                                break;
                             }
-                            prediction.valid = true;
-                            break;
                         }
-                        i = i + 1;
+                        prediction.valid = true;
+                    }
+                    None => {
+                        prediction.valid = false;
+                        log::trace!("no prediction found");
                     }
-                } else { // there is no history
-                    prediction.valid = false;
-                    log::trace!("no prediction found");
                 }
                 log::trace!("returning index {} string {:?}", prediction.index, prediction.string);
 
@@ -105,12 +125,11 @@ fn xmain() -> ! {
                 buffer.replace(Return::Prediction(prediction)).expect("couldn't return Prediction");
             }
             Some(Opcode::Unpick) => {
-                if history.len() == 1 {
-                    let _ = history.remove(0);
-                } else if history.len() > 1 {
-                    let _ = history.pop(); // discard the last entry
+                if history.pop() && history.needs_save() {
+                    if let Some(pddb) = pddb.as_mut() {
+                        history.save(pddb);
+                    }
                 }
-                // in case of 0 length, do nothing
             }
             Some(Opcode::GetPredictionTriggers) => {
                 xous::return_scalar(msg.sender, mytriggers.into()).expect("couldn't return GetPredictionTriggers");
@@ -1,5 +1,12 @@
 pub const SERVER_NAME_KBD: &str      = "_Matrix keyboard driver_";
 
+/// how long in ms a key is held before it starts repeating, until told otherwise
+pub const DEFAULT_REPEAT_DELAY_MS: u32 = 500;
+/// how often in ms a held key repeats
+pub const DEFAULT_REPEAT_RATE_MS: u32 = 20;
+/// how long in ms a key has to stay up to count as let go; 0 leaves debouncing to the hardware
+pub const DEFAULT_DEBOUNCE_MS: u32 = 0;
+
 #[derive(Debug, Default, Copy, Clone)]
 pub struct ScanCode {
     /// base key value
@@ -76,6 +83,35 @@ pub(crate) enum Opcode {
 
     /// Suspend/resume callback
     SuspendResume,
+
+    /// change what a key sends in a layout, given as a `KeyRemap`
+    SetRemap,
+    /// put every key in a layout back to what the layout says it sends
+    ClearRemaps, //(KeyMap)
+
+    /// request for hot keys: keys pressed while select is held, which then don't go to the ScanCode listener.
+    /// Only taken from the GAM, which routes them on to whoever registered each one.
+    RegisterHotkeyListener,
+
+    /// set how long a key has to stay up before it counts as let go, in ms
+    SetDebounce, //(u32)
+
+    /// request for every key going down or coming up, with when it did, as `KeyEvent`s. Only taken from the
+    /// GAM, which passes them on to the focused app if it has asked for them.
+    RegisterKeyEventListener,
+}
+
+/// Changes what a key sends in a layout. The key is named by what it sends in the layout, untouched and
+/// unshifted, so remapping 'q' in QWERTY changes the key with a Q on it, whatever it's been remapped to since.
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub(crate) struct KeyRemap {
+    pub layout: KeyMap,
+    pub label: u32,
+    /// key, shift, hold and alt, as they are in `ScanCode`, with 0 wherever the layout's meaning is to be kept;
+    /// `None` puts the key back to the layout's
+    pub code: Option<[u32; 4]>,
+    /// set by the keyboard server: whether the layout has a key that sends `label`
+    pub found: bool,
 }
 
 // this structure is used to register a keyboard listener. Currently, we only accept
@@ -87,6 +123,31 @@ pub(crate) struct KeyboardRegistration {
     pub listener_op_id: usize,
 }
 
+/// A key going down or coming up, with the ticktimer time it did so, for telling long presses from taps and
+/// the like. It goes over IPC as the first three arguments of a scalar message: see `to_args()`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub rc: RowCol,
+    pub down: bool,
+    pub timestamp_ms: u64,
+}
+impl KeyEvent {
+    pub fn to_args(&self) -> [usize; 3] {
+        [
+            self.rc.r as usize | (self.rc.c as usize) << 8 | if self.down { 1 << 16 } else { 0 },
+            self.timestamp_ms as u32 as usize,
+            (self.timestamp_ms >> 32) as u32 as usize,
+        ]
+    }
+    pub fn from_args(arg1: usize, arg2: usize, arg3: usize) -> KeyEvent {
+        KeyEvent {
+            rc: RowCol { r: arg1 as u8, c: (arg1 >> 8) as u8 },
+            down: arg1 & (1 << 16) != 0,
+            timestamp_ms: arg2 as u32 as u64 | (arg3 as u32 as u64) << 32,
+        }
+    }
+}
+
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct RowCol {
     pub r: u8,
@@ -112,3 +173,26 @@ impl KeyRawStates {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_events_roundtrip() {
+        let events = [
+            KeyEvent { rc: RowCol { r: 0, c: 0 }, down: false, timestamp_ms: 0 },
+            KeyEvent { rc: RowCol { r: 5, c: 2 }, down: true, timestamp_ms: 1234 },
+            // the timestamp is split across two arguments, so one past 32 bits has to make it through whole
+            KeyEvent { rc: RowCol { r: 8, c: 9 }, down: true, timestamp_ms: 0x1_2345_6789 },
+            KeyEvent { rc: RowCol { r: 255, c: 255 }, down: false, timestamp_ms: u64::MAX },
+        ];
+        for event in events.iter() {
+            let args = event.to_args();
+            for arg in args.iter() {
+                assert!(*arg <= u32::MAX as usize, "arguments have to fit a 32-bit usize");
+            }
+            assert_eq!(KeyEvent::from_args(args[0], args[1], args[2]), *event);
+        }
+    }
+}
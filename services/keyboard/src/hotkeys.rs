@@ -0,0 +1,152 @@
+//! Picks hot keys out of the raw key states: keys pressed while select is held. The keyboard server queues
+//! them up for the GAM, which routes them to whoever registered them.
+
+use crate::api::{KeyRawStates, RowCol};
+use std::collections::HashSet;
+
+/// the middle of the D-pad, which is held down to make a hot key of the next key pressed
+pub(crate) const SELECT_KEY: RowCol = RowCol { r: 5, c: 2 };
+
+#[derive(Default)]
+pub(crate) struct HotkeyFilter {
+    select_held: bool,
+    /// whether a hot key was pressed while select was held, so letting go of select sends nothing
+    select_chorded: bool,
+    /// keys pressed as hot keys, whose keyups are swallowed
+    hotkey_keys: HashSet<RowCol>,
+    /// hot keys waiting to be sent on
+    hotkeys: Vec<char>,
+}
+impl HotkeyFilter {
+    /// forgets what's held, for when the keyboard is reset or comes back from suspend
+    pub(crate) fn reset(&mut self) {
+        self.select_held = false;
+        self.select_chorded = false;
+        self.hotkey_keys.clear();
+    }
+    /// the hot keys pressed since the last call
+    pub(crate) fn take_hotkeys(&mut self) -> Vec<char> {
+        core::mem::take(&mut self.hotkeys)
+    }
+    /// Takes select, and any key pressed while it's held, out of `krs`, with `key_of` giving what each key
+    /// sends. Those keys are queued up as hot keys, and select itself goes into `ks` when it's let go, unless it
+    /// was used for a hot key. Select doesn't repeat, so it can be held for as long as it takes to find the other
+    /// key. Returns the keys that are left, and whether select was tapped on its own.
+    pub(crate) fn filter(&mut self, krs: &KeyRawStates, ks: &mut Vec<char>, key_of: impl Fn(RowCol) -> Option<char>) -> (KeyRawStates, bool) {
+        let mut filtered = KeyRawStates::new();
+        let mut tapped = false;
+        for &rc in krs.keydowns.iter() {
+            if rc == SELECT_KEY {
+                self.select_held = true;
+            } else if self.select_held {
+                if let Some(key) = key_of(rc) {
+                    self.hotkeys.push(key);
+                }
+                self.hotkey_keys.insert(rc);
+                self.select_chorded = true;
+            } else {
+                filtered.keydowns.push(rc);
+            }
+        }
+        for &rc in krs.keyups.iter() {
+            if rc == SELECT_KEY {
+                self.select_held = false;
+                if !self.select_chorded {
+                    if let Some(key) = key_of(rc) {
+                        ks.push(key);
+                    }
+                    tapped = true;
+                }
+                self.select_chorded = false;
+            } else if !self.hotkey_keys.remove(&rc) {
+                filtered.keyups.push(rc);
+            }
+        }
+        (filtered, tapped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: RowCol = RowCol { r: 2, c: 0 };
+    const B: RowCol = RowCol { r: 8, c: 5 };
+
+    fn key_of(rc: RowCol) -> Option<char> {
+        match rc {
+            SELECT_KEY => Some('\u{e00f}'),
+            A => Some('a'),
+            B => Some('b'),
+            _ => None,
+        }
+    }
+
+    fn states(keydowns: &[RowCol], keyups: &[RowCol]) -> KeyRawStates {
+        let mut krs = KeyRawStates::new();
+        krs.keydowns.extend_from_slice(keydowns);
+        krs.keyups.extend_from_slice(keyups);
+        krs
+    }
+
+    #[test]
+    fn keys_without_select_pass_through() {
+        let mut filter = HotkeyFilter::default();
+        let mut ks = Vec::new();
+        let (left, tapped) = filter.filter(&states(&[A], &[B]), &mut ks, key_of);
+        assert_eq!(left.keydowns, vec![A]);
+        assert_eq!(left.keyups, vec![B]);
+        assert!(!tapped);
+        assert!(ks.is_empty());
+        assert!(filter.take_hotkeys().is_empty());
+    }
+
+    #[test]
+    fn keys_pressed_with_select_held_are_hot_keys() {
+        let mut filter = HotkeyFilter::default();
+        let mut ks = Vec::new();
+        let (left, _) = filter.filter(&states(&[SELECT_KEY], &[]), &mut ks, key_of);
+        assert!(left.keydowns.is_empty());
+        // a key pressed and let go while select is down, in separate scans
+        let (left, _) = filter.filter(&states(&[A], &[]), &mut ks, key_of);
+        assert!(left.keydowns.is_empty());
+        let (left, _) = filter.filter(&states(&[], &[A]), &mut ks, key_of);
+        assert!(left.keyups.is_empty());
+        let (left, tapped) = filter.filter(&states(&[B], &[SELECT_KEY]), &mut ks, key_of);
+        assert!(left.keydowns.is_empty() && left.keyups.is_empty());
+        // select was used for a chord, so it sends nothing of its own
+        assert!(!tapped);
+        assert!(ks.is_empty());
+        assert_eq!(filter.take_hotkeys(), vec!['a', 'b']);
+        assert!(filter.take_hotkeys().is_empty());
+        // a hot key let go after select is still swallowed
+        let (left, _) = filter.filter(&states(&[], &[B]), &mut ks, key_of);
+        assert!(left.keyups.is_empty());
+        // and after that, keys are ordinary again
+        let (left, _) = filter.filter(&states(&[A], &[]), &mut ks, key_of);
+        assert_eq!(left.keydowns, vec![A]);
+    }
+
+    #[test]
+    fn select_tapped_alone_is_a_key() {
+        let mut filter = HotkeyFilter::default();
+        let mut ks = Vec::new();
+        filter.filter(&states(&[SELECT_KEY], &[]), &mut ks, key_of);
+        let (left, tapped) = filter.filter(&states(&[], &[SELECT_KEY]), &mut ks, key_of);
+        assert!(left.keyups.is_empty());
+        assert!(tapped);
+        assert_eq!(ks, vec!['\u{e00f}']);
+        assert!(filter.take_hotkeys().is_empty());
+    }
+
+    #[test]
+    fn reset_forgets_select() {
+        let mut filter = HotkeyFilter::default();
+        let mut ks = Vec::new();
+        filter.filter(&states(&[SELECT_KEY], &[]), &mut ks, key_of);
+        filter.reset();
+        let (left, _) = filter.filter(&states(&[A], &[]), &mut ks, key_of);
+        assert_eq!(left.keydowns, vec![A]);
+        assert!(filter.take_hotkeys().is_empty());
+    }
+}
@@ -41,6 +41,26 @@ impl Keyboard {
         .expect("couldn't register listener");
     }
 
+    pub fn register_hotkey_listener(&self, server_name: &str, action_opcode: usize) {
+        let kr = KeyboardRegistration {
+            server_name: String::<64>::from_str(server_name),
+            listener_op_id: action_opcode
+        };
+        let buf = Buffer::into_buf(kr).unwrap();
+        buf.lend(self.conn, Opcode::RegisterHotkeyListener.to_u32().unwrap())
+        .expect("couldn't register listener");
+    }
+
+    pub fn register_key_event_listener(&self, server_name: &str, action_opcode: usize) {
+        let kr = KeyboardRegistration {
+            server_name: String::<64>::from_str(server_name),
+            listener_op_id: action_opcode
+        };
+        let buf = Buffer::into_buf(kr).unwrap();
+        buf.lend(self.conn, Opcode::RegisterKeyEventListener.to_u32().unwrap())
+        .expect("couldn't register listener");
+    }
+
     pub fn set_vibe(&self, enable: bool) -> Result<(), xous::Error> {
         let ena =
             if enable { 1 }
@@ -70,6 +90,63 @@ impl Keyboard {
         }
     }
 
+    /// Has the key that sends `label` in `layout` send `code` instead. Fields of `code` left as `None` keep
+    /// what the layout has. Returns whether `layout` has a key that sends `label`.
+    ///
+    /// Remaps don't apply to Braille chords, and the shift keys still work as shift keys whatever they're
+    /// remapped to. They last until reboot; the status service keeps them in the PDDB and puts them back.
+    pub fn set_remap(&self, layout: KeyMap, label: char, code: ScanCode) -> Result<bool, xous::Error> {
+        let to_u32 = |c: Option<char>| c.map(|c| c as u32).unwrap_or(0);
+        self.remap(KeyRemap {
+            layout,
+            label: label as u32,
+            code: Some([to_u32(code.key), to_u32(code.shift), to_u32(code.hold), to_u32(code.alt)]),
+            found: false,
+        })
+    }
+    /// Puts the key that sends `label` in `layout` back to what the layout has it send
+    pub fn clear_remap(&self, layout: KeyMap, label: char) -> Result<bool, xous::Error> {
+        self.remap(KeyRemap {
+            layout,
+            label: label as u32,
+            code: None,
+            found: false,
+        })
+    }
+    fn remap(&self, remap: KeyRemap) -> Result<bool, xous::Error> {
+        let mut buf = Buffer::into_buf(remap).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.conn, Opcode::SetRemap.to_u32().unwrap())?;
+        let remap = buf.to_original::<KeyRemap, _>().or(Err(xous::Error::InternalError))?;
+        Ok(remap.found)
+    }
+    pub fn clear_remaps(&self, layout: KeyMap) -> Result<(), xous::Error> {
+        send_message(self.conn,
+            Message::new_scalar(Opcode::ClearRemaps.to_usize().unwrap(),
+            layout.into(),
+            0, 0, 0)
+        ).map(|_| ())
+    }
+
+    /// Sets how long a key is held, in ms, before it starts repeating, and then how often it repeats. These last
+    /// until reboot; the status service keeps the user's choice in the PDDB and puts it back.
+    pub fn set_repeat(&self, delay: u32, rate: u32) -> Result<(), xous::Error> {
+        send_message(self.conn,
+            Message::new_scalar(Opcode::SetRepeat.to_usize().unwrap(),
+            rate as usize,
+            delay as usize,
+            0, 0)
+        ).map(|_| ())
+    }
+    /// Sets how long a key has to stay up, in ms, before it counts as let go, for keys that chatter as they're
+    /// pressed. 0 turns debouncing off, leaving it to the hardware.
+    pub fn set_debounce(&self, debounce: u32) -> Result<(), xous::Error> {
+        send_message(self.conn,
+            Message::new_scalar(Opcode::SetDebounce.to_usize().unwrap(),
+            debounce as usize,
+            0, 0, 0)
+        ).map(|_| ())
+    }
+
     #[cfg(not(any(target_os = "none", target_os = "xous")))]
     pub fn hostmode_inject_key(&self, c: char) {
         send_message(self.conn,
@@ -4,6 +4,8 @@
 mod api;
 use api::*;
 mod mappings;
+#[cfg(any(target_os = "none", target_os = "xous", test))]
+mod hotkeys;
 
 use log::info;
 
@@ -15,11 +17,12 @@ mod implementation {
     use utralib::generated::*;
     use crate::{RowCol, KeyRawStates, api::*};
     use crate::mappings::*;
+    use crate::hotkeys::HotkeyFilter;
     use ticktimer_server::Ticktimer;
     use xous::CID;
     use num_traits::ToPrimitive;
     use susres::{RegManager, RegOrField, SuspendResume};
-    use std::collections::HashSet;
+    use std::collections::{HashMap, HashSet};
     use std::convert::TryInto;
 
     /// note: the code is structured to use at most 16 rows or 16 cols
@@ -68,6 +71,32 @@ mod implementation {
         settings: xous::MemoryRange,
         /// a handle to the spinor block so we can update our settings
         spinor: spinor::Spinor,
+        /// what keys send in place of what their layout says, by layout code and key; fields that are `None`
+        /// keep the layout's meaning
+        remaps: HashMap::<(usize, RowCol), ScanCode>,
+        /// whether there's a hot key listener, without which select is just another key
+        hotkeys_enabled: bool,
+        hotkey_filter: HotkeyFilter,
+        /// how long in ms a key has to stay up before it's taken as let go, so a worn dome that chatters as it's
+        /// pressed doesn't type the key twice. 0 takes every keyup as it comes.
+        debounce: u32,
+        /// keys that have come up, but not for long enough yet to count, with when they came up
+        bouncing: HashMap::<RowCol, u64>,
+        /// whether there's a key event listener, without which key events aren't kept
+        key_events_enabled: bool,
+        /// keydowns and keyups waiting to be sent on, with when they happened
+        key_events: Vec::<KeyEvent>,
+    }
+
+    /// what a key sends in a layout, before any remapping
+    fn layout_scancode(map: KeyMap, rc: RowCol) -> ScanCode {
+        match map {
+            KeyMap::Qwerty => map_qwerty(rc),
+            KeyMap::Dvorak => map_dvorak(rc),
+            KeyMap::Azerty => map_azerty(rc),
+            KeyMap::Qwertz => map_qwertz(rc),
+            _ => ScanCode {key: None, shift: None, hold: None, alt: None},
+        }
     }
 
     fn handle_kbd(_irq_no: usize, arg: *mut usize) {
@@ -167,8 +196,8 @@ mod implementation {
                 last_state: HashSet::with_capacity(16),
                 ticktimer,
                 map: default_map,
-                delay: 500,
-                rate: 20,
+                delay: DEFAULT_REPEAT_DELAY_MS,
+                rate: DEFAULT_REPEAT_RATE_MS,
                 shift_down: false,
                 shift_up: false,
                 alt_down: false,
@@ -184,6 +213,13 @@ mod implementation {
                 debug: 0,
                 settings: setting_page,
                 spinor: spinor::Spinor::new(&xns).unwrap(),
+                remaps: HashMap::new(),
+                hotkeys_enabled: false,
+                hotkey_filter: HotkeyFilter::default(),
+                debounce: DEFAULT_DEBOUNCE_MS,
+                bouncing: HashMap::new(),
+                key_events_enabled: false,
+                key_events: Vec::new(),
             };
 
             xous::claim_interrupt(
@@ -223,6 +259,9 @@ mod implementation {
             self.chord_captured = false;
             self.chord_active = 0;
             self.chord = [[false; KBD_COLS]; KBD_ROWS];
+            self.hotkey_filter.reset();
+            self.bouncing.clear();
+            self.key_events.clear();
 
             // ensure interrupts are re-enabled -- this could /shouldn't/ be necessary but we're having
             // some strange resume behavior, trying to see if this resolves it.
@@ -249,6 +288,77 @@ mod implementation {
             self.map = KeyMap::from(code as usize);
             self.map
         }
+        /// Applies `remap` to the key in its layout that sends its label, returning whether there is one
+        pub(crate) fn set_remap(&mut self, remap: &KeyRemap) -> bool {
+            let label = match core::char::from_u32(remap.label) {
+                Some(label) => label,
+                None => return false,
+            };
+            let layout: usize = remap.layout.into();
+            for r in 0..KBD_ROWS as u8 {
+                for c in 0..KBD_COLS as u8 {
+                    let rc = RowCol::new(r, c);
+                    if layout_scancode(remap.layout, rc).key != Some(label) {
+                        continue;
+                    }
+                    match remap.code {
+                        Some(code) => {
+                            let to_char = |c: u32| if c == 0 { None } else { core::char::from_u32(c) };
+                            self.remaps.insert((layout, rc), ScanCode {
+                                key: to_char(code[0]),
+                                shift: to_char(code[1]),
+                                hold: to_char(code[2]),
+                                alt: to_char(code[3]),
+                            });
+                        }
+                        None => {
+                            self.remaps.remove(&(layout, rc));
+                        }
+                    }
+                    return true;
+                }
+            }
+            false
+        }
+        pub(crate) fn clear_remaps(&mut self, map: KeyMap) {
+            let layout: usize = map.into();
+            self.remaps.retain(|&(l, _), _| l != layout);
+        }
+        /// what a key sends in the current layout, remapping included
+        fn scancode(&self, rc: RowCol) -> ScanCode {
+            let code = layout_scancode(self.map, rc);
+            let layout: usize = self.map.into();
+            match self.remaps.get(&(layout, rc)) {
+                Some(remap) => ScanCode {
+                    key: remap.key.or(code.key),
+                    shift: remap.shift.or(code.shift),
+                    hold: remap.hold.or(code.hold),
+                    alt: remap.alt.or(code.alt),
+                },
+                None => code,
+            }
+        }
+        pub(crate) fn set_hotkeys_enabled(&mut self, enabled: bool) {
+            self.hotkeys_enabled = enabled;
+        }
+        /// the hot keys pressed since the last call
+        pub(crate) fn take_hotkeys(&mut self) -> Vec<char> {
+            self.hotkey_filter.take_hotkeys()
+        }
+        /// Takes select, and any key pressed while it's held, out of `krs`; see `HotkeyFilter::filter()`
+        fn filter_hotkeys(&mut self, krs: &KeyRawStates, ks: &mut Vec<char>) -> KeyRawStates {
+            let mut hotkey_filter = core::mem::take(&mut self.hotkey_filter);
+            let (filtered, select_tapped) = hotkey_filter.filter(krs, ks, |rc| self.scancode(rc).key);
+            self.hotkey_filter = hotkey_filter;
+            if select_tapped {
+                // a tap of shift before select is used up by it, as it would be by any other key
+                self.shift_down = false;
+                self.shift_up = false;
+                self.alt_down = false;
+                self.alt_up = false;
+            }
+            filtered
+        }
         pub(crate) fn set_repeat(&mut self, rate: u32, delay: u32) {
             self.rate = rate;
             self.delay = delay;
@@ -259,6 +369,25 @@ mod implementation {
         pub(crate) fn get_repeat_check_interval(&self) -> u32 {
             self.rate
         }
+        pub(crate) fn set_debounce(&mut self, debounce: u32) {
+            self.debounce = debounce;
+        }
+        /// whether there are keyups being held back until it's clear they aren't bounces
+        pub(crate) fn is_debouncing(&self) -> bool {
+            !self.bouncing.is_empty()
+        }
+        pub(crate) fn get_debounce(&self) -> u32 {
+            self.debounce
+        }
+        pub(crate) fn set_key_events_enabled(&mut self, enabled: bool) {
+            self.key_events_enabled = enabled;
+            if !enabled {
+                self.key_events.clear();
+            }
+        }
+        pub(crate) fn take_key_events(&mut self) -> Vec<KeyEvent> {
+            core::mem::take(&mut self.key_events)
+        }
 
         pub(crate) fn poll(&mut self) {
             // disable the interrupt while we're polling, to avoid a race condition...
@@ -291,16 +420,45 @@ mod implementation {
             log::trace!("update last_state: {:?}", self.last_state);
 
             let mut krs = KeyRawStates::new();
+            let now = self.ticktimer.elapsed_ms();
 
             // compute the key-ups: this would be codes that are in the last_state, but not in the incoming
-            // new_state
+            // new_state. With debouncing on, they wait to see if the key stays up.
             for &rc in self.last_state.difference(&self.new_state) {
-                krs.keyups.push(rc);
+                if self.debounce == 0 {
+                    krs.keyups.push(rc);
+                } else {
+                    self.bouncing.insert(rc, now);
+                }
             }
 
-            // compute key-downs: codes that are in the new_state, but not in last_state
+            // compute key-downs: codes that are in the new_state, but not in last_state. A key that's back
+            // down before it was up long enough never really came up, so it isn't pressed again.
             for &rc in self.new_state.difference(&self.last_state) {
-                krs.keydowns.push(rc);
+                if self.bouncing.remove(&rc).is_none() {
+                    krs.keydowns.push(rc);
+                }
+            }
+            let debounce = self.debounce as u64;
+            // a debounced keyup is stamped with when the key came up, not with when we were sure of it
+            let mut debounced: Vec<(RowCol, u64)> = Vec::new();
+            self.bouncing.retain(|&rc, &mut up| {
+                if now - up >= debounce {
+                    krs.keyups.push(rc);
+                    debounced.push((rc, up));
+                    false
+                } else {
+                    true
+                }
+            });
+            if self.key_events_enabled {
+                for &rc in krs.keydowns.iter() {
+                    self.key_events.push(KeyEvent { rc, down: true, timestamp_ms: now });
+                }
+                for &rc in krs.keyups.iter() {
+                    let timestamp_ms = debounced.iter().find(|(d, _)| *d == rc).map(|&(_, up)| up).unwrap_or(now);
+                    self.key_events.push(KeyEvent { rc, down: false, timestamp_ms });
+                }
             }
 
             self.last_state.clear();
@@ -464,6 +622,13 @@ mod implementation {
               to determine if a "hold" modifier applies
              */
             let mut ks: Vec<char> = Vec::new();
+            let filtered;
+            let krs = if self.hotkeys_enabled {
+                filtered = self.filter_hotkeys(krs, &mut ks);
+                &filtered
+            } else {
+                krs
+            };
 
             // first check for shift and alt keys
             for rc in krs.keydowns.iter() {
@@ -537,13 +702,7 @@ mod implementation {
                 self.chord_timestamp = self.ticktimer.elapsed_ms();
             }
             for &rc in krs.keydowns.iter() {
-                let code = match self.map {
-                    KeyMap::Qwerty => map_qwerty(rc),
-                    KeyMap::Dvorak => map_dvorak(rc),
-                    KeyMap::Azerty => map_azerty(rc),
-                    KeyMap::Qwertz => map_qwertz(rc),
-                    _ => ScanCode {key: None, shift: None, hold: None, alt: None},
-                };
+                let code = self.scancode(rc);
                 if code.hold == None { // if there isn't a pre-defined meaning if the key is held, it's a repeating key
                     if let Some(key) = code.key {
                         self.repeating_key = Some(key);
@@ -564,13 +723,7 @@ mod implementation {
 
             for &rc in keyups_noshift.iter() {
                 // info!("interpreting keyups_noshift entry {:?}", rc);
-                let code = match self.map {
-                    KeyMap::Qwerty => map_qwerty(rc),
-                    KeyMap::Dvorak => map_dvorak(rc),
-                    KeyMap::Azerty => map_azerty(rc),
-                    KeyMap::Qwertz => map_qwertz(rc),
-                    _ => ScanCode {key: None, shift: None, hold: None, alt: None},
-                };
+                let code = self.scancode(rc);
                 // delete the key repeat if there is one
                 if code.hold == None {
                     if let Some(key) = code.key {
@@ -673,7 +826,8 @@ mod implementation {
             Keyboard {
                 cid: xous::connect(sid).unwrap(),
                 map: KeyMap::Qwerty,
-                rate: 20,
+                rate: DEFAULT_REPEAT_RATE_MS,
+                // hosted mode keeps the shorter delay it has always had
                 delay: 200,
                 chord_interval: 50,
                 debug: 0,
@@ -689,6 +843,16 @@ mod implementation {
         }
         pub fn get_map(&self) -> KeyMap {self.map}
 
+        pub(crate) fn set_remap(&mut self, _remap: &KeyRemap) -> bool {
+            // there's no matrix in hosted mode, so no keys to remap
+            false
+        }
+        pub(crate) fn clear_remaps(&mut self, _map: KeyMap) {}
+        pub(crate) fn set_hotkeys_enabled(&mut self, _enabled: bool) {}
+        pub(crate) fn take_hotkeys(&mut self) -> Vec<char> {
+            Vec::new()
+        }
+
         pub fn update(&self) -> KeyRawStates {
             KeyRawStates::new()
         }
@@ -716,10 +880,40 @@ mod implementation {
         pub(crate) fn get_repeat_check_interval(&self) -> u32 {
             self.rate
         }
+        pub(crate) fn set_debounce(&mut self, _debounce: u32) {}
+        pub(crate) fn set_key_events_enabled(&mut self, _enabled: bool) {}
+        pub(crate) fn take_key_events(&mut self) -> Vec<KeyEvent> {
+            Vec::new()
+        }
+        pub(crate) fn is_debouncing(&self) -> bool {
+            false
+        }
+        pub(crate) fn get_debounce(&self) -> u32 {
+            0
+        }
         pub(crate) fn poll(&mut self) {}
     }
 }
 
+/// Whether `sender` is the GAM, the only process that may listen to every key or to the hot keys. It's known by the name it was
+/// built with, which apps loaded at runtime don't have.
+fn from_gam(sender: xous::MessageSender) -> bool {
+    #[cfg(any(target_os = "none", target_os = "xous"))]
+    {
+        let mut name = [0u8; 64];
+        match sender.pid().map(|pid| xous::process_name(pid, &mut name)) {
+            Some(Ok(len)) if len > 0 && len < name.len() => &name[..len] == b"gam",
+            _ => false,
+        }
+    }
+    #[cfg(not(any(target_os = "none", target_os = "xous")))]
+    {
+        // processes don't have names in hosted mode
+        let _ = sender;
+        true
+    }
+}
+
 #[xous::xous_main]
 fn xmain() -> ! {
     use crate::implementation::Keyboard;
@@ -730,7 +924,7 @@ fn xmain() -> ! {
     let xns = xous_names::XousNames::new().unwrap();
     // connections expected:
     //  - GAM
-    //  - graphics (if building for hosted mode)
+    //  - graphics (if building for hosted mode), or the golden-image harness if graphics is headless
     //  - oqc (for factory test)
     //  - status sub system (for setting the layout)
     #[cfg(any(target_os = "none", target_os = "xous"))]
@@ -753,6 +947,10 @@ fn xmain() -> ! {
     let mut listener_op: Option<usize> = None;
     let mut raw_listener_conn: Option<CID> = None;
     let mut raw_listener_op: Option<u32> = None;
+    let mut hotkey_listener_conn: Option<CID> = None;
+    let mut hotkey_listener_op: Option<usize> = None;
+    let mut key_event_listener_conn: Option<CID> = None;
+    let mut key_event_listener_op: Option<usize> = None;
 
     let mut vibe = false;
     let llio = llio::Llio::new(&xns);
@@ -807,6 +1005,48 @@ fn xmain() -> ! {
                     }
                 }
             },
+            Some(Opcode::RegisterHotkeyListener) => {
+                if !from_gam(msg.sender) {
+                    log::warn!("PID {:?} tried to listen to hot keys", msg.sender.pid());
+                    continue;
+                }
+                let buffer = unsafe{Buffer::from_memory_message(msg.body.memory_message().unwrap())};
+                let kr = buffer.as_flat::<KeyboardRegistration, _>().unwrap();
+                match xns.request_connection_blocking(kr.server_name.as_str()) {
+                    Ok(cid) => {
+                        hotkey_listener_conn = Some(cid);
+                        hotkey_listener_op = Some(kr.listener_op_id as usize);
+                        kbd.set_hotkeys_enabled(true);
+                    }
+                    Err(e) => {
+                        log::error!("couldn't connect to listener: {:?}", e);
+                        hotkey_listener_conn = None;
+                        hotkey_listener_op = None;
+                        kbd.set_hotkeys_enabled(false);
+                    }
+                }
+            },
+            Some(Opcode::RegisterKeyEventListener) => {
+                if !from_gam(msg.sender) {
+                    log::warn!("PID {:?} tried to listen to key events", msg.sender.pid());
+                    continue;
+                }
+                let buffer = unsafe{Buffer::from_memory_message(msg.body.memory_message().unwrap())};
+                let kr = buffer.as_flat::<KeyboardRegistration, _>().unwrap();
+                match xns.request_connection_blocking(kr.server_name.as_str()) {
+                    Ok(cid) => {
+                        key_event_listener_conn = Some(cid);
+                        key_event_listener_op = Some(kr.listener_op_id as usize);
+                        kbd.set_key_events_enabled(true);
+                    }
+                    Err(e) => {
+                        log::error!("couldn't connect to listener: {:?}", e);
+                        key_event_listener_conn = None;
+                        key_event_listener_op = None;
+                        kbd.set_key_events_enabled(false);
+                    }
+                }
+            },
             Some(Opcode::SelectKeyMap) => msg_scalar_unpack!(msg, km, _, _, _, {
                 kbd.set_map(KeyMap::from(km))
             }),
@@ -815,12 +1055,24 @@ fn xmain() -> ! {
                     kbd.get_map().into()
                 ).expect("can't retrieve keymap");
             }),
+            Some(Opcode::SetRemap) => {
+                let mut buffer = unsafe{Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap())};
+                let mut remap = buffer.to_original::<KeyRemap, _>().unwrap();
+                remap.found = kbd.set_remap(&remap);
+                buffer.replace(remap).unwrap();
+            },
+            Some(Opcode::ClearRemaps) => msg_scalar_unpack!(msg, km, _, _, _, {
+                kbd.clear_remaps(KeyMap::from(km));
+            }),
             Some(Opcode::SetRepeat) => msg_scalar_unpack!(msg, rate, delay, _, _, {
                 kbd.set_repeat(rate as u32, delay as u32);
             }),
             Some(Opcode::SetChordInterval) => msg_scalar_unpack!(msg, delay, _, _, _, {
                 kbd.set_chord_interval(delay as u32);
             }),
+            Some(Opcode::SetDebounce) => msg_scalar_unpack!(msg, debounce, _, _, _, {
+                kbd.set_debounce(debounce as u32);
+            }),
             Some(Opcode::InjectKey) => msg_scalar_unpack!(msg, k, _, _, _, {
                 // key substitutions to help things work better
                 // 1b5b317e = home
@@ -946,6 +1198,27 @@ fn xmain() -> ! {
                         ).expect("couldn't send key codes to listener");
                     }
                 }
+                // and hot keys, which go to their own listener
+                for hotkey in kbd.take_hotkeys() {
+                    if let (Some(conn), Some(op)) = (hotkey_listener_conn, hotkey_listener_op) {
+                        if vibe {
+                            llio.vibe(llio::VibePattern::Short).unwrap();
+                        }
+                        log::trace!("sending hot key {:?}", hotkey);
+                        xous::send_message(conn,
+                            xous::Message::new_scalar(op, hotkey as u32 as usize, 0, 0, 0)
+                        ).expect("couldn't send hot key to listener");
+                    }
+                }
+                // and the raw key events, with their timestamps
+                for event in kbd.take_key_events() {
+                    if let (Some(conn), Some(op)) = (key_event_listener_conn, key_event_listener_op) {
+                        let args = event.to_args();
+                        xous::send_message(conn,
+                            xous::Message::new_scalar(op, args[0], args[1], args[2], 0)
+                        ).expect("couldn't send key event to listener");
+                    }
+                }
                 // as long as we have a keydown, keep pinging the loop at a high rate. this consumes more power, but keydowns are relatively rare.
                 if kbd.is_repeating_key() {
                     log::trace!("keydowns hold");
@@ -955,6 +1228,13 @@ fn xmain() -> ! {
                     xous::send_message(self_cid,
                         xous::Message::new_scalar(Opcode::HandlerTrigger.to_usize().unwrap(), 0, 0, 0, 0)
                     ).unwrap();
+                } else if kbd.is_debouncing() {
+                    // come back once the keys that came up have been up long enough to send their keyups
+                    ticktimer.sleep_ms(kbd.get_debounce() as _).unwrap();
+                    kbd.poll();
+                    xous::send_message(self_cid,
+                        xous::Message::new_scalar(Opcode::HandlerTrigger.to_usize().unwrap(), 0, 0, 0, 0)
+                    ).unwrap();
                 }
             },
             None => {log::error!("couldn't convert opcode"); break}
@@ -728,7 +728,8 @@ fn xmain() -> ! {
     // - status (for setting time)
     // - shellchat (for testing)
     // - rootkeys (for coordinating self-reboot)
-    let rtc_sid = xns.register_name(api::SERVER_NAME_RTC, Some(3)).expect("can't register server");
+    // - net (for network time)
+    let rtc_sid = xns.register_name(api::SERVER_NAME_RTC, Some(4)).expect("can't register server");
     log::trace!("registered with NS -- {:?}", rtc_sid);
     let _ = thread::spawn({
         let rtc_sid = rtc_sid.clone();
@@ -408,16 +408,93 @@ mod implementation {
     use crate::api::Weekday;
     use chrono::prelude::*;
     use num_traits::ToPrimitive;
+    #[cfg(feature = "virtual-clock")]
+    use std::sync::{Arc, Mutex};
+
+    /// With the `virtual-clock` feature, the time is this plus the ticktimer's virtual clock, until it's set
+    #[cfg(feature = "virtual-clock")]
+    const VIRTUAL_EPOCH: (i32, u32, u32) = (2021, 1, 1);
+
+    /// The wall-clock time: the host's own, or under the `virtual-clock` feature, one that moves only with
+    /// the ticktimer's virtual clock, so tests that depend on the date and time are reproducible
+    #[derive(Clone)]
+    struct Clock {
+        /// what the time was when the ticktimer's clock read 0
+        #[cfg(feature = "virtual-clock")]
+        base: Arc<Mutex<NaiveDateTime>>,
+    }
+    impl Clock {
+        fn new() -> Clock {
+            Clock {
+                #[cfg(feature = "virtual-clock")]
+                base: Arc::new(Mutex::new(
+                    NaiveDate::from_ymd(VIRTUAL_EPOCH.0, VIRTUAL_EPOCH.1, VIRTUAL_EPOCH.2).and_hms(0, 0, 0)
+                )),
+            }
+        }
+        #[cfg(not(feature = "virtual-clock"))]
+        fn now(&self) -> crate::api::DateTime {
+            to_datetime(&Local::now())
+        }
+        #[cfg(feature = "virtual-clock")]
+        fn now(&self) -> crate::api::DateTime {
+            let elapsed = ticktimer_server::Ticktimer::new().unwrap().elapsed_ms();
+            to_datetime(&(*self.base.lock().unwrap() + chrono::Duration::milliseconds(elapsed as i64)))
+        }
+        /// Only the virtual clock can be set; the host's is left alone
+        #[cfg(feature = "virtual-clock")]
+        fn set(&self, secs: u8, mins: u8, hours: u8, days: u8, months: u8, years: u8) -> bool {
+            match NaiveDate::from_ymd_opt(2000 + years as i32, months as u32, days as u32)
+                .and_then(|date| date.and_hms_opt(hours as u32, mins as u32, secs as u32))
+            {
+                Some(set_to) => {
+                    let elapsed = ticktimer_server::Ticktimer::new().unwrap().elapsed_ms();
+                    *self.base.lock().unwrap() = set_to - chrono::Duration::milliseconds(elapsed as i64);
+                    true
+                }
+                None => false,
+            }
+        }
+        #[cfg(not(feature = "virtual-clock"))]
+        fn set(&self, _secs: u8, _mins: u8, _hours: u8, _days: u8, _months: u8, _years: u8) -> bool {
+            true
+        }
+    }
+
+    fn to_datetime<T: Datelike + Timelike>(now: &T) -> crate::api::DateTime {
+        let wday: Weekday = match now.weekday() {
+            chrono::Weekday::Mon => Weekday::Monday,
+            chrono::Weekday::Tue => Weekday::Tuesday,
+            chrono::Weekday::Wed => Weekday::Wednesday,
+            chrono::Weekday::Thu => Weekday::Thursday,
+            chrono::Weekday::Fri => Weekday::Friday,
+            chrono::Weekday::Sat => Weekday::Saturday,
+            chrono::Weekday::Sun => Weekday::Sunday,
+        };
+        crate::api::DateTime {
+            seconds: now.second() as u8,
+            minutes: now.minute() as u8,
+            hours: now.hour() as u8,
+            months: now.month() as u8,
+            days: now.day() as u8,
+            years: (now.year() - 2000) as u8,
+            weekday: wday,
+        }
+    }
+
     pub struct Rtc {
         cb_conn: xous::CID,
+        clock: Clock,
     }
 
     impl Rtc {
         pub fn new(_xns: &xous_names::XousNames, cb_to_main: xous::CID) -> Rtc {
             let sid = xous::create_server().unwrap();
             let cid = xous::connect(sid).unwrap();
+            let clock = Clock::new();
 
             std::thread::spawn({
+                let clock = clock.clone();
                 move || {
                     log::trace!("rtc callback server started");
                     loop {
@@ -425,25 +502,7 @@ mod implementation {
                         log::trace!("rtc callback got msg: {:?}", msg);
                         // we only have one purpose, and that's to send this message.
                         log::trace!("rtc_get sending time to main server");
-                        let now = Local::now();
-                        let wday: Weekday = match now.weekday() {
-                            chrono::Weekday::Mon => Weekday::Monday,
-                            chrono::Weekday::Tue => Weekday::Tuesday,
-                            chrono::Weekday::Wed => Weekday::Wednesday,
-                            chrono::Weekday::Thu => Weekday::Thursday,
-                            chrono::Weekday::Fri => Weekday::Friday,
-                            chrono::Weekday::Sat => Weekday::Saturday,
-                            chrono::Weekday::Sun => Weekday::Sunday,
-                        };
-                        let dt = crate::api::DateTime {
-                            seconds: now.second() as u8,
-                            minutes: now.minute() as u8,
-                            hours: now.hour() as u8,
-                            months: now.month() as u8,
-                            days: now.day() as u8,
-                            years: (now.year() - 2000) as u8,
-                            weekday: wday,
-                        };
+                        let dt = clock.now();
                         let buf = xous_ipc::Buffer::into_buf(dt).unwrap();
                         buf.send(cb_to_main, crate::api::RtcOpcode::ResponseDateTime.to_u32().unwrap()).unwrap();
                     }
@@ -451,37 +510,19 @@ mod implementation {
             });
             Rtc {
                 cb_conn: cid,
+                clock,
             }
         }
-        pub fn rtc_set(&mut self, _secs: u8, _mins: u8, _hours: u8, _days: u8, _months: u8, _years: u8, _day: Weekday)
+        pub fn rtc_set(&mut self, secs: u8, mins: u8, hours: u8, days: u8, months: u8, years: u8, _day: Weekday)
            -> Result<bool, xous::Error> {
-               Ok(true)
+               Ok(self.clock.set(secs, mins, hours, days, months, years))
         }
         pub fn rtc_get(&mut self) -> Result<(), xous::Error> {
             xous::send_message(self.cb_conn, xous::Message::new_scalar(0, 0, 0, 0, 0)).unwrap();
             Ok(())
         }
         pub fn rtc_get_blocking(&mut self) -> Result<crate::api::DateTime, xous::Error> {
-            let now = Local::now();
-            let wday: Weekday = match now.weekday() {
-                chrono::Weekday::Mon => Weekday::Monday,
-                chrono::Weekday::Tue => Weekday::Tuesday,
-                chrono::Weekday::Wed => Weekday::Wednesday,
-                chrono::Weekday::Thu => Weekday::Thursday,
-                chrono::Weekday::Fri => Weekday::Friday,
-                chrono::Weekday::Sat => Weekday::Saturday,
-                chrono::Weekday::Sun => Weekday::Sunday,
-            };
-            let dt = crate::api::DateTime {
-                seconds: now.second() as u8,
-                minutes: now.minute() as u8,
-                hours: now.hour() as u8,
-                months: now.month() as u8,
-                days: now.day() as u8,
-                years: (now.year() - 2000) as u8,
-                weekday: wday,
-            };
-            Ok(dt)
+            Ok(self.clock.now())
         }
         pub fn wakeup_alarm(&mut self, _seconds: u8) { }
         pub fn clear_wakeup_alarm(&mut self) { }
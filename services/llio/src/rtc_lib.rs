@@ -76,6 +76,12 @@ impl Rtc {
             Message::new_scalar(RtcOpcode::RequestDateTime.to_usize().unwrap(), 0, 0, 0, 0)
         ).map(|_|())
     }
+    /// Reads the RTC, blocking until the read is done. Takes ~10-20ms.
+    pub fn get_datetime(&self) -> Result<DateTime, xous::Error> {
+        let mut buf = Buffer::into_buf(DateTime::default()).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.conn, RtcOpcode::RequestDateTimeBlocking.to_u32().unwrap())?;
+        buf.to_original::<DateTime, _>().or(Err(xous::Error::InternalError))
+    }
     /// wakeup alarm will force the system on if it is off, but does not trigger an interrupt on the CPU
     pub fn set_wakeup_alarm(&self, seconds_from_now: u8) -> Result<(), xous::Error> {
         send_message(self.conn,
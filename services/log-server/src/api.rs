@@ -11,6 +11,27 @@ pub struct LogRecord {
     pub args: [u8; 3000],
 }
 
+/// PDDB dictionary the status server saves crash reports in, one key per crash
+pub const CRASH_REPORT_DICT: &str = "crash.reports";
+
+/// Bytes of recent log output kept for crash reports
+pub const RECENT_LINES_LEN: usize = 2048;
+/// Bytes of panic text kept for crash reports
+pub const PANIC_TEXT_LEN: usize = 1024;
+
+/// What the log server remembers for crash reports: the last lines that were
+/// logged, oldest first, and the text of the last panic.
+#[repr(C)]
+pub struct RecentLogRecord {
+    /// How many panics have been logged since boot; 0 if there hasn't been one
+    pub panic_sequence: u32,
+    pub panic_pid: u32,
+    pub panic_length: u32,
+    pub panic: [u8; PANIC_TEXT_LEN],
+    pub lines_length: u32,
+    pub lines: [u8; RECENT_LINES_LEN],
+}
+
 #[derive(Debug, PartialEq, num_derive::FromPrimitive, num_derive::ToPrimitive)]
 pub enum Opcode {
     /// A `LogRecord` message, delivering structured log output
@@ -25,6 +46,21 @@ pub enum Opcode {
     /// A `xous::StringBuffer` containing this program's name
     ProgramName = 3,
 
+    /// A mutably lent `RecentLogRecord`, filled in by the server
+    GetRecentLog = 4,
+
+    /// A scalar that turns the kernel's IPC trace on if arg1 is 1, or off if it's 0
+    SetIpcTrace = 5,
+
+    /// A scalar asking for the IPC trace to be printed, which turns tracing off
+    DumpIpcTrace = 6,
+
+    /// A scalar that turns the kernel's profiler on if arg1 is 1, or off if it's 0
+    SetProfile = 7,
+
+    /// A scalar asking for the profile to be printed, which turns the profiler off
+    DumpProfile = 8,
+
     /// A panic occurred, and a panic log is forthcoming
     PanicStarted = 1000,
 
@@ -0,0 +1,88 @@
+use crate::api::{RecentLogRecord, PANIC_TEXT_LEN, RECENT_LINES_LEN};
+
+/// Longest line kept; the rest of a longer one is dropped
+const LINE_MAX: usize = 160;
+
+/// The tail of the log and the last panic, kept for crash reports. Whole lines
+/// are dropped from the front to make room.
+pub struct History {
+    lines: [u8; RECENT_LINES_LEN],
+    lines_length: usize,
+    line: [u8; LINE_MAX],
+    line_length: usize,
+    panic_sequence: u32,
+    panic_pid: u32,
+    panic: [u8; PANIC_TEXT_LEN],
+    panic_length: usize,
+}
+
+impl History {
+    pub fn new() -> History {
+        History {
+            lines: [0; RECENT_LINES_LEN],
+            lines_length: 0,
+            line: [0; LINE_MAX],
+            line_length: 0,
+            panic_sequence: 0,
+            panic_pid: 0,
+            panic: [0; PANIC_TEXT_LEN],
+            panic_length: 0,
+        }
+    }
+
+    /// Add to the line being built up
+    pub fn push(&mut self, bytes: &[u8]) {
+        let room = LINE_MAX - 1 - self.line_length;
+        let count = bytes.len().min(room);
+        self.line[self.line_length..self.line_length + count].copy_from_slice(&bytes[..count]);
+        self.line_length += count;
+    }
+
+    /// Finish the line being built up and keep it
+    pub fn end_line(&mut self) {
+        self.line[self.line_length] = b'\n';
+        let length = self.line_length + 1;
+        self.line_length = 0;
+        while self.lines_length + length > RECENT_LINES_LEN {
+            let first = self.lines[..self.lines_length]
+                .iter()
+                .position(|&c| c == b'\n')
+                .map(|i| i + 1)
+                .unwrap_or(self.lines_length);
+            self.lines.copy_within(first..self.lines_length, 0);
+            self.lines_length -= first;
+        }
+        self.lines[self.lines_length..self.lines_length + length]
+            .copy_from_slice(&self.line[..length]);
+        self.lines_length += length;
+    }
+
+    pub fn start_panic(&mut self, pid: u32) {
+        self.panic_pid = pid;
+        self.panic_length = 0;
+    }
+
+    pub fn push_panic(&mut self, bytes: &[u8]) {
+        let count = bytes.len().min(PANIC_TEXT_LEN - self.panic_length);
+        self.panic[self.panic_length..self.panic_length + count].copy_from_slice(&bytes[..count]);
+        self.panic_length += count;
+    }
+
+    pub fn finish_panic(&mut self) {
+        self.panic_sequence = self.panic_sequence.wrapping_add(1).max(1);
+    }
+
+    /// The PID and text of the last panic
+    pub fn panic(&self) -> (u32, &[u8]) {
+        (self.panic_pid, &self.panic[..self.panic_length])
+    }
+
+    pub fn fill(&self, recent: &mut RecentLogRecord) {
+        recent.panic_sequence = self.panic_sequence;
+        recent.panic_pid = self.panic_pid;
+        recent.panic_length = self.panic_length as u32;
+        recent.panic[..self.panic_length].copy_from_slice(&self.panic[..self.panic_length]);
+        recent.lines_length = self.lines_length as u32;
+        recent.lines[..self.lines_length].copy_from_slice(&self.lines[..self.lines_length]);
+    }
+}
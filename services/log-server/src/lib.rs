@@ -118,6 +118,8 @@ pub fn init() -> Result<(), LogError> {
         }
         log::set_logger(&XOUS_LOGGER).map_err(|_| LogError::LoggerExists)?;
         log::set_max_level(log::LevelFilter::Info);
+        #[cfg(target_os = "xous")]
+        set_panic_hook();
         Ok(())
     } else {
         Err(LogError::NoConnection)
@@ -136,9 +138,108 @@ pub fn init_wait() -> Result<(), log::SetLoggerError> {
     }
     log::set_logger(&XOUS_LOGGER)?;
     log::set_max_level(log::LevelFilter::Info);
+    #[cfg(target_os = "xous")]
+    set_panic_hook();
     Ok(())
 }
 
+/// Report panics to the log server with a backtrace, in place of the
+/// standard library's report. The log server shows them on the screen, too.
+#[cfg(target_os = "xous")]
+fn set_panic_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        if let Some(mut writer) = xous::PanicWriter::new() {
+            writeln!(writer, "{}", info).ok();
+            writer.write_backtrace();
+        }
+    }));
+}
+
 pub fn resume() {
     unsafe { XOUS_LOGGER_BACKING.as_mut().unwrap().resume() };
 }
+
+/// The text of a panic, as the log server remembers it
+pub struct Panic {
+    /// How many panics have been logged since boot, this one included
+    pub sequence: u32,
+    pub pid: u32,
+    pub text: String,
+}
+
+/// What the log server remembers for crash reports
+pub struct RecentLog {
+    /// The last lines that were logged, oldest first
+    pub lines: String,
+    /// The last panic, if there has been one
+    pub panic: Option<Panic>,
+}
+
+/// Ask the log server for the last lines it logged and the last panic it saw
+pub fn recent_log() -> Result<RecentLog, xous::Error> {
+    // The kernel hands back the connection the logger already uses, so it's left open
+    let conn = xous::connect(xous::SID::from_bytes(b"xous-log-server ").unwrap())?;
+    let mut buffer = Buffer::new(core::mem::size_of::<api::RecentLogRecord>());
+    buffer.lend_mut(conn, api::Opcode::GetRecentLog.to_u32().unwrap())?;
+    let record = unsafe { &*(buffer.as_ptr() as *const api::RecentLogRecord) };
+    let text = |bytes: &[u8], length: u32| {
+        String::from_utf8_lossy(&bytes[..(length as usize).min(bytes.len())]).into_owned()
+    };
+    Ok(RecentLog {
+        lines: text(&record.lines, record.lines_length),
+        panic: if record.panic_sequence != 0 {
+            Some(Panic {
+                sequence: record.panic_sequence,
+                pid: record.panic_pid,
+                text: text(&record.panic, record.panic_length),
+            })
+        } else {
+            None
+        },
+    })
+}
+
+/// Turn the kernel's trace of message sends, receives and returns on or off. Turning it on starts a fresh
+/// trace.
+pub fn set_ipc_trace(enable: bool) -> Result<(), xous::Error> {
+    let conn = xous::connect(xous::SID::from_bytes(b"xous-log-server ").unwrap())?;
+    xous::send_message(
+        conn,
+        xous::Message::new_scalar(api::Opcode::SetIpcTrace.to_usize().unwrap(), enable as usize, 0, 0, 0),
+    )
+    .map(|_| ())
+}
+
+/// Have the log server print the IPC trace to its output. This turns the trace off, so the output shows what
+/// led up to the call.
+pub fn dump_ipc_trace() -> Result<(), xous::Error> {
+    let conn = xous::connect(xous::SID::from_bytes(b"xous-log-server ").unwrap())?;
+    xous::send_message(
+        conn,
+        xous::Message::new_scalar(api::Opcode::DumpIpcTrace.to_usize().unwrap(), 0, 0, 0, 0),
+    )
+    .map(|_| ())
+}
+
+/// Turn the kernel's sampling profiler on or off; it's only there in hardware kernels built with the `profile`
+/// feature. Hosted mode has none, as its processes run on the host, where the host's own profiler can see them.
+/// Turning it on starts a fresh profile.
+pub fn set_profile(enable: bool) -> Result<(), xous::Error> {
+    let conn = xous::connect(xous::SID::from_bytes(b"xous-log-server ").unwrap())?;
+    xous::send_message(
+        conn,
+        xous::Message::new_scalar(api::Opcode::SetProfile.to_usize().unwrap(), enable as usize, 0, 0, 0),
+    )
+    .map(|_| ())
+}
+
+/// Have the log server print the profile to its output, for `profile-fold` (`tools/src/bin/profile-fold.rs`)
+/// to turn into a flame graph. This turns the profiler off.
+pub fn dump_profile() -> Result<(), xous::Error> {
+    let conn = xous::connect(xous::SID::from_bytes(b"xous-log-server ").unwrap())?;
+    xous::send_message(
+        conn,
+        xous::Message::new_scalar(api::Opcode::DumpProfile.to_usize().unwrap(), 0, 0, 0, 0),
+    )
+    .map(|_| ())
+}
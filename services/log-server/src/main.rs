@@ -3,6 +3,8 @@
 
 mod api;
 use api::*;
+mod history;
+use history::History;
 
 #[cfg(any(target_os = "none", target_os = "xous"))]
 #[macro_use]
@@ -247,14 +249,130 @@ mod implementation {
     }
 }
 
+/// Print what the kernel traced of IPC, oldest first. Reading the trace turns it off, so what's printed
+/// leads up to the moment it was asked for.
+fn dump_ipc_trace(output: &mut implementation::OutputWriter) {
+    writeln!(output, "IPC trace (timestamp, event, client PID -> server PID:slot, opcode, bytes):").unwrap();
+    let result = xous::read_ipc_trace(|event| {
+        writeln!(
+            output,
+            "  {:>20} {:?}{} {} -> {}:{} {} {}",
+            event.timestamp,
+            event.kind,
+            if event.blocking { " (blocking)" } else { "" },
+            event.client,
+            event.server,
+            event.server_index,
+            event.id,
+            event.size
+        )
+        .unwrap();
+    });
+    match result {
+        Ok(count) => writeln!(
+            output,
+            "IPC trace: {} events recorded, the last {} shown; tracing is now off",
+            count,
+            count.min(xous::IPC_TRACE_EVENTS)
+        )
+        .unwrap(),
+        Err(e) => writeln!(output, "Couldn't read the IPC trace: {:?}", e).unwrap(),
+    }
+}
+
+/// Print the profile, oldest sample first, in the form `profile-fold` (`tools/src/bin/profile-fold.rs`) reads: a `PROF` line for each
+/// sample, then a `PNAM` line naming each process that was sampled. Reading the profile turns it off.
+fn dump_profile(output: &mut implementation::OutputWriter) {
+    writeln!(output, "Profile (PID, pc, ra):").unwrap();
+    let mut sampled = [false; 256];
+    let result = xous::read_profile(|sample| {
+        sampled[sample.pid as usize] = true;
+        writeln!(output, "PROF {} {:08x} {:08x}", sample.pid, sample.pc, sample.ra).unwrap();
+    });
+    for (pid, _) in sampled.iter().enumerate().filter(|(_, &sampled)| sampled) {
+        let mut name = [0u8; 64];
+        let len = xous::PID::new(pid as u8)
+            .and_then(|pid| xous::process_name(pid, &mut name).ok())
+            .unwrap_or(0);
+        let name = core::str::from_utf8(&name[..len.min(name.len())]).unwrap_or("");
+        writeln!(output, "PNAM {} {}", pid, if name.is_empty() { "?" } else { name }).unwrap();
+    }
+    match result {
+        Ok(count) => writeln!(
+            output,
+            "Profile: {} samples taken, the last {} shown; profiling is now off",
+            count,
+            count.min(xous::PROFILE_SAMPLES)
+        )
+        .unwrap(),
+        Err(e) => writeln!(output, "Couldn't read the profile: {:?}", e).unwrap(),
+    }
+}
+
+/// The text of a panic, as it's lent to the graphics server: its length as a
+/// `u32`, and then the text itself, cut short if it doesn't fit
+struct PanicScreen<'a> {
+    buffer: xous_ipc::Buffer<'a>,
+    len: usize,
+}
+impl PanicScreen<'_> {
+    fn push(&mut self, bytes: &[u8]) {
+        let count = bytes.len().min(self.buffer.len() - 4 - self.len);
+        self.buffer[4 + self.len..4 + self.len + count].copy_from_slice(&bytes[..count]);
+        self.len += count;
+    }
+}
+impl core::fmt::Write for PanicScreen<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.push(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Hand the panic that just finished to the graphics server, which draws it
+/// over whatever was on the screen. Nothing is shown if the graphics server
+/// isn't running.
+fn show_panic(history: &History) {
+    let conn = match xous::try_connect(xous::SID::from_bytes(b"panic-to-screen!").unwrap()) {
+        Ok(conn) => conn,
+        Err(_) => return,
+    };
+    let (pid, text) = history.panic();
+    let mut name = [0u8; 64];
+    let name_len = xous::PID::new(pid as u8)
+        .and_then(|pid| xous::process_name(pid, &mut name).ok())
+        .unwrap_or(0)
+        .min(name.len());
+
+    let mut screen = PanicScreen {
+        buffer: xous_ipc::Buffer::new(4096),
+        len: 0,
+    };
+    write!(screen, "PANIC in PID {}", pid).ok();
+    if name_len != 0 {
+        screen.push(b" (");
+        screen.push(&name[..name_len]);
+        screen.push(b")");
+    }
+    screen.push(b"\n\n");
+    screen.push(text);
+    let len = screen.len as u32;
+    screen.buffer[..4].copy_from_slice(&len.to_le_bytes());
+    screen.buffer.lend(conn, 0).ok();
+}
+
 fn handle_scalar(
     output: &mut implementation::OutputWriter,
     sender: xous::MessageSender,
     msg: &xous::ScalarMessage,
     sender_pid: xous::PID,
+    history: &mut History,
 ) {
     match msg.id {
-        1000 => writeln!(output, "PANIC in PID {}:", sender_pid).unwrap(),
+        1000 => {
+            writeln!(output, "PANIC in PID {}:", sender_pid).unwrap();
+            history.start_panic(sender_pid.get() as u32);
+        }
         1100 => (),
         1101..=1132 => {
             let mut output_bfr = [0u8; core::mem::size_of::<usize>() * 4];
@@ -282,8 +400,31 @@ fn handle_scalar(
                 }
                 output.putc(*c);
             }
+            history.push_panic(&output_bfr[..total_chars.min(output_bfr.len())]);
+        }
+        1200 => {
+            writeln!(output, "Terminating process").unwrap();
+            history.finish_panic();
+            show_panic(history);
         }
-        1200 => writeln!(output, "Terminating process").unwrap(),
+        // the trace shows who talks to whom, so it's kept to the same processes as the recent log
+        5 | 6 if !is_trusted(sender) => {
+            writeln!(output, "Refusing the IPC trace to PID {:?}", sender.pid()).unwrap()
+        }
+        // and the profile shows where every process is, so it's kept to them as well
+        7 | 8 if !is_trusted(sender) => {
+            writeln!(output, "Refusing the profiler to PID {:?}", sender.pid()).unwrap()
+        }
+        5 => match xous::set_ipc_trace(msg.arg1 != 0) {
+            Ok(_) => writeln!(output, "IPC trace {}", if msg.arg1 != 0 { "on" } else { "off" }).unwrap(),
+            Err(e) => writeln!(output, "Couldn't set the IPC trace: {:?}", e).unwrap(),
+        },
+        6 => dump_ipc_trace(output),
+        7 => match xous::set_profile(msg.arg1 != 0) {
+            Ok(_) => writeln!(output, "Profiler {}", if msg.arg1 != 0 { "on" } else { "off" }).unwrap(),
+            Err(e) => writeln!(output, "Couldn't set the profiler: {:?}", e).unwrap(),
+        },
+        8 => dump_profile(output),
         2000 => {
             #[cfg(any(target_os = "none", target_os = "xous"))]
             crate::debug::DEFAULT.enable_rx();
@@ -298,12 +439,50 @@ fn handle_scalar(
     }
 }
 
+/// Processes that can read back what others have logged, or trace their messages: the crash reporter in the
+/// status bar, and the shell. They're known by the name they were built with, which apps loaded at runtime don't have.
+const TRUSTED_READERS: [&str; 2] = ["status", "shellchat"];
+
+fn is_trusted(sender: xous::MessageSender) -> bool {
+    #[cfg(any(target_os = "none", target_os = "xous"))]
+    {
+        let mut name = [0u8; 64];
+        match sender.pid().map(|pid| xous::process_name(pid, &mut name)) {
+            Some(Ok(len)) if len > 0 && len < name.len() => core::str::from_utf8(&name[..len])
+                .map(|n| TRUSTED_READERS.contains(&n))
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+    #[cfg(not(any(target_os = "none", target_os = "xous")))]
+    {
+        // processes don't have names in hosted mode
+        let _ = sender;
+        true
+    }
+}
+
 fn handle_opcode(
     output: &mut implementation::OutputWriter,
     sender: xous::MessageSender,
     opcode: api::Opcode,
-    message: &xous::Message,
+    message: &mut xous::Message,
+    history: &mut History,
 ) {
+    if opcode == api::Opcode::GetRecentLog {
+        if !is_trusted(sender) {
+            // the buffer goes back as it came, which reads as an empty log
+            writeln!(output, "Refusing the recent log to PID {:?}", sender.pid()).unwrap();
+            return;
+        }
+        if let Some(mem) = message.memory_message_mut() {
+            if mem.buf.len() >= core::mem::size_of::<RecentLogRecord>() {
+                let recent = unsafe { &mut *(mem.buf.as_mut_ptr() as *mut RecentLogRecord) };
+                history.fill(recent);
+            }
+        }
+        return;
+    }
     if let Some(mem) = message.memory_message() {
         match opcode {
             api::Opcode::LogRecord => {
@@ -355,6 +534,13 @@ fn handle_opcode(
                     write!(output, ":{}", line).ok();
                 }
                 writeln!(output, ")").ok();
+
+                history.push(level.as_bytes());
+                history.push(b":");
+                history.push(module_slice);
+                history.push(b": ");
+                history.push(args_slice);
+                history.end_line();
             }
             api::Opcode::StandardOutput | api::Opcode::StandardError => {
                 // let mut buffer_start_offset = mem.offset.map(|o| o.get()).unwrap_or(0);
@@ -387,7 +573,7 @@ fn handle_opcode(
         }
     } else if let Some(scalar) = message.scalar_message() {
         // Scalar message
-        handle_scalar(output, sender, scalar, sender.pid().unwrap());
+        handle_scalar(output, sender, scalar, sender.pid().unwrap(), history);
     }
 }
 
@@ -398,17 +584,20 @@ fn reader_thread(arg: usize) {
     writeln!(output, "LOG: Server listening on address {:?}", server_addr).unwrap();
 
     println!("LOG: my PID is {}", xous::process::id());
+    // Claim the kernel's IPC trace, so it's only ever controlled through this server
+    xous::set_ipc_trace(false).ok();
     let mut counter: usize = 0;
+    let mut history = History::new();
     loop {
         if counter.trailing_zeros() >= 12 {
             writeln!(output, "LOG: Counter tick: {}", counter).unwrap();
         }
         counter += 1;
         // writeln!(output, "LOG: Waiting for an event...").unwrap();
-        let envelope = xous::syscall::receive_message(server_addr).expect("couldn't get address");
+        let mut envelope = xous::syscall::receive_message(server_addr).expect("couldn't get address");
         let sender = envelope.sender;
         if let Some(opcode) = FromPrimitive::from_usize(envelope.body.id()) {
-            handle_opcode(output, sender, opcode, &envelope.body);
+            handle_opcode(output, sender, opcode, &mut envelope.body, &mut history);
         } else {
             writeln!(
                 output,
@@ -19,6 +19,13 @@ pub struct ManagedPromptWithFixedResponse {
     pub prompt: xous_ipc::String::<1024>,
 }
 #[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct ManagedPromptWithBitfieldResponse {
+    pub token: [u32; 4],
+    pub prompt: xous_ipc::String::<1024>,
+    /// items that start out checked, by their position in the list
+    pub preselected: u32,
+}
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
 pub struct ManagedListItem {
     pub token: [u32; 4],
     pub item: ItemName,
@@ -33,6 +40,15 @@ pub struct ManagedPromptWithTextResponse {
     pub validator_op: u32,
 }
 #[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct ManagedPromptWithNumericResponse {
+    pub token: [u32; 4],
+    pub prompt: xous_ipc::String::<1024>,
+    /// digit count and value limits; checked by the modal before it returns
+    pub limits: NumericLimits,
+    /// show `*` instead of the entered digits
+    pub masked: bool,
+}
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
 pub struct ManagedNotification {
     pub token: [u32; 4],
     pub message: xous_ipc::String::<1024>,
@@ -67,8 +83,12 @@ pub(crate) enum Opcode {
     PromptWithFixedResponse,
     /// ask a question, get multiple responses from a list of defined items (check box)
     PromptWithMultiResponse,
+    /// ask a question, get back which of the defined items were checked, as a bitfield (check box)
+    PromptWithBitfieldResponse,
     /// ask a question, get a free-form answer back
     PromptWithTextResponse,
+    /// ask for a number, get back a string of digits that satisfies the requested limits
+    PromptWithNumericResponse,
     /// simple notification
     Notification,
     /// dynamic notification - a simple non-interactive notification that allows its text to be dynamically updated
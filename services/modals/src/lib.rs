@@ -86,6 +86,30 @@ impl Modals {
         }
     }
 
+    /// Prompts for a number. Only digits can be entered; the modal re-prompts until the entry satisfies `limits`,
+    /// so the returned string is guaranteed to be non-empty and parseable as a `u64`. Set `masked` for PINs.
+    pub fn get_number(&self, prompt: &str, limits: NumericLimits, masked: bool) -> Result<TextEntryPayload, xous::Error> {
+        self.lock();
+        let spec = ManagedPromptWithNumericResponse {
+            token: self.token,
+            prompt: xous_ipc::String::from_str(prompt),
+            limits,
+            masked,
+        };
+        let mut buf = Buffer::into_buf(spec).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.conn, Opcode::PromptWithNumericResponse.to_u32().unwrap()).or(Err(xous::Error::InternalError))?;
+        match buf.to_original::<TextEntryPayload, _>() {
+            Ok(response) => Ok(response),
+            _ => Err(xous::Error::InternalError)
+        }
+    }
+
+    /// Convenience wrapper around `get_number` for callers that want the parsed value.
+    pub fn get_u64(&self, prompt: &str, min: u64, max: u64) -> Result<u64, xous::Error> {
+        let digits = self.get_number(prompt, NumericLimits::range(min, max), false)?;
+        digits.as_str().parse::<u64>().or(Err(xous::Error::InternalError))
+    }
+
     /// this blocks until the notification has been acknowledged.
     pub fn show_notification(&self, notification: &str) -> Result<(), xous::Error> {
         self.lock();
@@ -177,6 +201,25 @@ impl Modals {
         Ok(ret)
     }
 
+    /// Like `get_checkbox`, but returns the selection as a bitfield where bit `n` is set if the `n`th item added
+    /// with `add_list_item` was checked. Items in `preselected` start out checked. Use this when the caller
+    /// already has the items in a list, or when more than `MAX_ITEMS` could be checked; only the first
+    /// `MAX_CHECKBOX_BITS` items can be checked.
+    pub fn get_checkbox_bits(&self, prompt: &str, preselected: u32) -> Result<u32, xous::Error> {
+        self.lock();
+        let spec = ManagedPromptWithBitfieldResponse {
+            token: self.token,
+            prompt: xous_ipc::String::from_str(prompt),
+            preselected,
+        };
+        let mut buf = Buffer::into_buf(spec).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.conn, Opcode::PromptWithBitfieldResponse.to_u32().unwrap()).or(Err(xous::Error::InternalError))?;
+        match buf.to_original::<CheckBoxBitfield, _>() {
+            Ok(bits) => Ok(bits.0),
+            _ => Err(xous::Error::InternalError)
+        }
+    }
+
     pub fn dynamic_notification(&self, title: Option<&str>, text: Option<&str>) -> Result<(), xous::Error> {
         self.lock();
         let spec = DynamicNotification {
@@ -30,13 +30,16 @@ enum RendererState {
     /// running state
     RunRadio(ManagedPromptWithFixedResponse),
     RunCheckBox(ManagedPromptWithFixedResponse),
+    RunCheckBits(ManagedPromptWithBitfieldResponse),
     RunText(ManagedPromptWithTextResponse),
+    RunNumeric(ManagedPromptWithNumericResponse),
     RunProgress(ManagedProgress),
     RunNotification(ManagedNotification),
     /// response ready state
     ResponseText(TextEntryPayload),
     ResponseRadio(ItemName),
     ResponseCheckBox(CheckBoxPayload),
+    ResponseCheckBits(CheckBoxBitfield),
     RunDynamicNotification(DynamicNotification),
 }
 
@@ -47,8 +50,10 @@ pub(crate) enum RendererOp {
     FinishProgress,
 
     TextEntryReturn,
+    NumericEntryReturn,
     RadioReturn,
     CheckBoxReturn,
+    CheckBitsReturn,
     NotificationReturn,
 
     AddModalItem,
@@ -150,6 +155,23 @@ fn xmain() -> ! {
                                 renderer_modal.activate();
                                 log::debug!("should be active!");
                             },
+                            RendererState::RunNumeric(config) => {
+                                log::debug!("initiating numeric entry modal");
+                                #[cfg(feature="tts")]
+                                tts.tts_simple(config.prompt.as_str().unwrap()).unwrap();
+                                let numeric_action = NumericEntry::new(
+                                    config.masked,
+                                    config.limits,
+                                    renderer_cid,
+                                    RendererOp::NumericEntryReturn.to_u32().unwrap()
+                                );
+                                renderer_modal.modify(
+                                    Some(ActionType::NumericEntry(numeric_action)),
+                                    Some(config.prompt.as_str().unwrap()), false,
+                                    None, true, None
+                                );
+                                renderer_modal.activate();
+                            },
                             RendererState::RunNotification(config) => {
                                 #[cfg(feature="tts")]
                                 tts.tts_simple(config.message.as_str().unwrap()).unwrap();
@@ -218,6 +240,31 @@ fn xmain() -> ! {
                                 );
                                 renderer_modal.activate();
                             },
+                            RendererState::RunCheckBits(config) => {
+                                let mut checkbox = gam::modal::CheckBoxes::new_bitfield(
+                                    renderer_cid,
+                                    RendererOp::CheckBitsReturn.to_u32().unwrap(),
+                                    config.preselected
+                                );
+                                if fixed_items.len() > MAX_CHECKBOX_BITS {
+                                    log::warn!("{} items in a bitfield check box list, only the first {} can be checked", fixed_items.len(), MAX_CHECKBOX_BITS);
+                                }
+                                for item in fixed_items.iter() {
+                                    checkbox.add_item(*item);
+                                }
+                                fixed_items.clear();
+                                #[cfg(feature="tts")]
+                                {
+                                    tts.tts_blocking(t!("modals.checkbox", xous::LANG)).unwrap();
+                                    tts.tts_blocking(config.prompt.as_str().unwrap()).unwrap();
+                                }
+                                renderer_modal.modify(
+                                    Some(ActionType::CheckBoxes(checkbox)),
+                                    Some(config.prompt.as_str().unwrap()), false,
+                                    None, true, None
+                                );
+                                renderer_modal.activate();
+                            },
                             RendererState::RunDynamicNotification(config) => {
                                 let mut top_text = String::new();
                                 if let Some(title) = config.title {
@@ -344,6 +391,22 @@ fn xmain() -> ! {
                             }
                         }
                     }
+                    Some(RendererOp::NumericEntryReturn) => {
+                        let mut mutex_op = op.lock().unwrap();
+                        match *mutex_op {
+                            RendererState::RunNumeric(_config) => {
+                                // limits were already enforced by the NumericEntry action before it returned
+                                let buf = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                                let text = buf.to_original::<gam::modal::TextEntryPayload, _>().unwrap();
+                                *mutex_op = RendererState::ResponseText(text);
+                            }
+                            RendererState::None => log::warn!("Numeric entry detected a fat finger event, ignoring."),
+                            _ => {
+                                log::error!("UX return opcode does not match our current operation in flight. This is a serious internal error.");
+                                panic!("UX return opcode does not match our current operation in flight. This is a serious internal error.");
+                            }
+                        }
+                    }
                     Some(RendererOp::NotificationReturn) => {
                         let mut mutex_op = op.lock().unwrap();
                         match *mutex_op {
@@ -395,6 +458,22 @@ fn xmain() -> ! {
                             }
                         }
                     }
+                    Some(RendererOp::CheckBitsReturn) => {
+                        let mut mutex_op = op.lock().unwrap();
+                        match *mutex_op {
+                            RendererState::RunCheckBits(_config) => {
+                                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                                let bits = buffer.to_original::<CheckBoxBitfield, _>().unwrap();
+                                *mutex_op = RendererState::ResponseCheckBits(bits);
+                            }
+                            RendererState::ResponseCheckBits(_) => log::warn!("Check boxes detected a fat finger event, ignoring."),
+                            RendererState::None => log::warn!("Check boxes detected a fat finger event, ignoring."),
+                            _ => {
+                                log::error!("UX return opcode does not match our current operation in flight. This is a serious internal error.");
+                                panic!("UX return opcode does not match our current operation in flight. This is a serious internal error.");
+                            }
+                        }
+                    }
                     Some(RendererOp::ModalRedraw) => {
                         renderer_modal.redraw();
                     },
@@ -510,6 +589,35 @@ fn xmain() -> ! {
                     tt.sleep_ms(100).unwrap(); // don't put the idle in the match/lock(), it'll prevent the other thread from running!
                 }
             },
+            Some(Opcode::PromptWithBitfieldResponse) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let spec = buffer.to_original::<ManagedPromptWithBitfieldResponse, _>().unwrap();
+                if spec.token != token_lock.unwrap_or(default_nonce) {
+                    log::warn!("Attempt to access modals without a mutex lock. Ignoring.");
+                    buffer.replace(CheckBoxBitfield(0)).unwrap();
+                    continue;
+                }
+                *op.lock().unwrap() = RendererState::RunCheckBits(spec);
+                send_message(
+                renderer_cid,
+                    Message::new_scalar(RendererOp::InitiateOp.to_usize().unwrap(), 0, 0, 0, 0)
+                ).expect("couldn't initiate UX op");
+                loop {
+                    match *op.lock().unwrap() {
+                        RendererState::RunCheckBits(_) => (),
+                        RendererState::ResponseCheckBits(bits) => {
+                            buffer.replace(bits).unwrap();
+                            token_lock = None;
+                            break;
+                        },
+                        _ => {
+                            log::error!("Illegal state transition in renderer");
+                            panic!("Illegal state transition in renderer");
+                        }
+                    }
+                    tt.sleep_ms(100).unwrap(); // don't put the idle in the match/lock(), it'll prevent the other thread from running!
+                }
+            },
             Some(Opcode::PromptWithTextResponse) => {
                 let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
                 let spec = buffer.to_original::<ManagedPromptWithTextResponse, _>().unwrap();
@@ -539,6 +647,35 @@ fn xmain() -> ! {
                     tt.sleep_ms(100).unwrap(); // don't put the idle in the match/lock(), it'll prevent the other thread from running!
                 }
             },
+            Some(Opcode::PromptWithNumericResponse) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let spec = buffer.to_original::<ManagedPromptWithNumericResponse, _>().unwrap();
+                if spec.token != token_lock.unwrap_or(default_nonce) {
+                    log::warn!("Attempt to access modals without a mutex lock. Ignoring.");
+                    buffer.replace(TextEntryPayload::new()).unwrap();
+                    continue;
+                }
+                *op.lock().unwrap() = RendererState::RunNumeric(spec);
+                send_message(
+                renderer_cid,
+                    Message::new_scalar(RendererOp::InitiateOp.to_usize().unwrap(), 0, 0, 0, 0)
+                ).expect("couldn't initiate UX op");
+                loop {
+                    match *op.lock().unwrap() {
+                        RendererState::RunNumeric(_) => (),
+                        RendererState::ResponseText(text) => {
+                            buffer.replace(text).unwrap();
+                            token_lock = None;
+                            break;
+                        },
+                        _ => {
+                            log::error!("Illegal state transition in renderer");
+                            panic!("Illegal state transition in renderer");
+                        }
+                    }
+                    tt.sleep_ms(100).unwrap(); // don't put the idle in the match/lock(), it'll prevent the other thread from running!
+                }
+            },
             Some(Opcode::Notification) => {
                 let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
                 let spec = buffer.to_original::<ManagedNotification, _>().unwrap();
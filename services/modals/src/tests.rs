@@ -49,6 +49,21 @@ pub(crate) fn spawn_test() {
                 _ => log::error!("get_checkbox failed"),
             }
 
+            // test check box with a bitfield response; the first and last items start out checked
+            for item in CHECKBOX_TEST {
+                modals.add_list_item(item).expect("couldn't build checkbox list");
+            }
+            match modals.get_checkbox_bits("Pick as many as you like:", 1 | 1 << (CHECKBOX_TEST.len() - 1)) {
+                Ok(bits) => {
+                    for (index, item) in CHECKBOX_TEST.iter().enumerate() {
+                        if bits & (1 << index) != 0 {
+                            log::info!("{}", item);
+                        }
+                    }
+                },
+                _ => log::error!("get_checkbox_bits failed"),
+            }
+
             // test notificatons
             log::info!("testing notification");
             modals.show_notification("This is a test!").expect("notification failed");
@@ -82,6 +97,18 @@ pub(crate) fn spawn_test() {
             }
             log::info!("text input test done");
 
+            // test the numeric entry modal
+            log::info!("test numeric input");
+            match modals.get_number("Enter a 6-digit PIN", NumericLimits::fixed_len(6), true) {
+                Ok(pin) => log::info!("PIN length: {}", pin.as_str().len()),
+                _ => log::error!("get_number failed"),
+            }
+            match modals.get_u64("Enter a port number", 1, 65535) {
+                Ok(port) => log::info!("Port: {}", port),
+                _ => log::error!("get_u64 failed"),
+            }
+            log::info!("numeric input test done");
+
             // test notificatons
             log::info!("testing notification");
             modals.show_notification("这是一个测验!").expect("notification failed");
@@ -175,6 +175,50 @@ pub(crate) enum Opcode {
 
     /// BlockingScalar call to set the NODELAY / "Nagle" value of this connection
     StdSetNodelay = 39,
+
+    /// Get the record of the last network time sync, as an `Option<TimeSync>`
+    GetTimeSync = 40,
+
+    /// BlockingScalar call to join the IPv4 multicast group whose address is given as big-endian octets in arg1,
+    /// on behalf of the UDP socket bound to the port in arg2. Returns 0 on success, 1 if the group couldn't be
+    /// joined.
+    UdpJoinMulticast = 41,
+    /// BlockingScalar call to leave an IPv4 multicast group; same arguments and return as `UdpJoinMulticast`.
+    /// The group is only left once no socket is in it; closing a socket leaves all of its groups.
+    UdpLeaveMulticast = 42,
+
+    /// Fetch a page of the Wi-Fi scan results, as a `ScanResultPage` whose `start` says where the page starts
+    FetchScanResults = 43,
+
+    /// BlockingScalar call to get what the network we're on reaches, as a `Connectivity`
+    GetConnectivity = 44,
+
+    /// Fetch the traffic counts of each process that has used the network, as a `ProcessStatsList`
+    GetProcessStats = 45,
+    /// Fetch a page of the traffic counts of each open socket, as a `SocketStatsPage` whose `start` says
+    /// where the page starts
+    GetSocketStats = 46,
+
+    /// Have `NetEvent`s sent to a server, given as a `NetEventSubscription`
+    SubscribeNetEvents = 47,
+    /// BlockingScalar call to stop sending `NetEvent`s to the server whose SID is in the args. Returns 1 if it
+    /// was subscribed, 0 if not.
+    UnsubNetEvents = 48,
+
+    /// Get our IPv6 addresses, as an `Option<Ipv6Config>`
+    GetIpv6Config = 49,
+
+    /// BlockingScalar call to set the SOCKS5 proxy TCP connects go through: the proxy's IPv4 address as a
+    /// big-endian u32 in arg1, or 0 for no proxy, and its port in arg2. Returns 1, or 0 if the caller may not.
+    SetSocksProxy = 50,
+    /// BlockingScalar call that returns the SOCKS5 proxy's address and port, or 0s if there isn't one
+    GetSocksProxy = 51,
+    /// BlockingScalar call to have the caller's TCP connects go direct even with a proxy set (arg1 = 1), or
+    /// to go back to using the proxy (arg1 = 0)
+    SocksOptOut = 52,
+
+    /// Sent by our own watchdog thread; answered straight away, to show the main loop is still running
+    WatchdogPing = 53,
 }
 
 #[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone, Default)]
@@ -185,6 +229,132 @@ pub(crate) struct SsidList {
     pub(crate) list: [Option<SsidRecord>; 32],
 }
 
+/// What the network we're on reaches, as found by probing a known URL
+#[derive(Debug, Copy, Clone, PartialEq, Eq, num_derive::FromPrimitive, num_derive::ToPrimitive)]
+pub enum Connectivity {
+    /// Not on a network, or it hasn't been probed yet
+    Unknown,
+    /// On a network that doesn't reach the internet
+    NoInternet,
+    /// Behind a captive portal that wants a sign-in first. Until then, a TLS connection will fail, or at best
+    /// reach the portal instead of the host asked for.
+    Portal,
+    Online,
+}
+
+/// Traffic through a socket, or a process's sockets. Bytes count payload only, as handed to or taken from a
+/// socket, so headers and retransmissions aren't in them. Packets count datagrams for UDP and ICMP; TCP is a
+/// stream, so for it they count the writes and reads that moved any data.
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone, Default)]
+pub struct TrafficCounts {
+    pub tx_bytes: u64,
+    pub rx_bytes: u64,
+    pub tx_packets: u32,
+    pub rx_packets: u32,
+}
+
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+pub enum SocketKind {
+    Tcp,
+    Udp,
+    Icmp,
+}
+
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone)]
+pub struct SocketStats {
+    /// The process that opened the socket, or 0 for the Net server's own
+    pub pid: u8,
+    pub kind: SocketKind,
+    pub local_port: u16,
+    pub counts: TrafficCounts,
+}
+
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone)]
+pub struct ProcessStats {
+    /// The process, or 0 for the Net server itself
+    pub pid: u8,
+    /// How many sockets the process has open now
+    pub open_sockets: u16,
+    /// The process's traffic since boot, including through sockets since closed
+    pub counts: TrafficCounts,
+}
+
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone, Default)]
+pub(crate) struct ProcessStatsList {
+    /// Only so many processes run at once, so they all fit in one message
+    pub(crate) list: [Option<ProcessStats>; 32],
+}
+
+pub(crate) const SOCKET_STATS_PAGE_LEN: usize = 32;
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone, Default)]
+pub(crate) struct SocketStatsPage {
+    /// the index of the first socket in the page, filled in by the caller
+    pub(crate) start: u16,
+    /// how many sockets there are in all
+    pub(crate) total: u16,
+    pub(crate) list: [Option<SocketStats>; SOCKET_STATS_PAGE_LEN],
+}
+
+/// How an access point is secured
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+pub enum WifiSecurity {
+    /// The EC didn't pass on the access point's beacon, or it uses key management the WF200 doesn't know
+    Unknown,
+    Open,
+    Wep,
+    WpaPsk,
+    Wpa2Psk,
+    Wpa3Sae,
+    /// WPA2 with 802.1X, which the WF200 can't join
+    Wpa2Enterprise,
+}
+
+/// An access point seen by the connection manager's scans
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone)]
+pub struct ScanResult {
+    pub ssid: xous_ipc::String<32>,
+    /// Reported as the negative of actual rssi in dBm, as with `SsidRecord`: -42dBm is reported as `42u8`
+    pub rssi: u8,
+    pub security: WifiSecurity,
+    /// Whether a password for the network is stored in the PDDB, so it can be joined without asking for one
+    pub known: bool,
+}
+
+pub(crate) const SCAN_PAGE_LEN: usize = 16;
+/// Scan results are handed out a page at a time, strongest signal first, so there's no limit on how many
+/// there can be. The results can change between pages; `generation` changes along with them, so a caller
+/// can tell when to start over.
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone, Default)]
+pub(crate) struct ScanResultPage {
+    /// the index of the first result in the page, filled in by the caller
+    pub(crate) start: u16,
+    /// how many results there are in all
+    pub(crate) total: u16,
+    pub(crate) generation: u32,
+    pub(crate) results: [Option<ScanResult>; SCAN_PAGE_LEN],
+}
+
+/// The last time the RTC was checked against network time, and what was done about it.
+///
+/// The RTC keeps local time, so `utc_offset_secs` is needed to get UTC out of it. An app that needs UTC to the
+/// second, such as one for TOTP codes, can also take `skew_ms - correction_secs * 1000` (what's left of the
+/// RTC's error) into account, and see from `ticktimer_ms` how long the RTC has been drifting since.
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone)]
+pub struct TimeSync {
+    /// UTC at the sync, in seconds since 1970
+    pub utc_secs: u64,
+    /// `Ticktimer::elapsed_ms()` at the sync
+    pub ticktimer_ms: u64,
+    /// How far ahead of network time the RTC was, in ms, before it was corrected; negative if it was behind
+    pub skew_ms: i64,
+    /// How far ahead of UTC the RTC runs, in seconds; that is, the time zone it was set to
+    pub utc_offset_secs: i32,
+    /// How many seconds were taken off the RTC; negative if they were added
+    pub correction_secs: i32,
+    /// How long the server took to answer, in ms; the sync could be off by up to half of this
+    pub round_trip_ms: u32,
+}
+
 #[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone)]
 pub enum XousServerId {
     /// A SID that is shared directly with the Net crate; a private, single-use SID for best security
@@ -217,6 +387,36 @@ pub(crate) struct WifiStateSubscription {
     pub opcode: u32,
 }
 
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone)]
+pub struct Ipv6Config {
+    pub link_local: [u8; 16],
+    /// the address made from the prefix the router advertises, if there's a router
+    pub global: Option<[u8; 16]>,
+    pub prefix_len: u8,
+    /// the router's link-local address
+    pub router: Option<[u8; 16]>,
+}
+
+#[derive(Debug, Archive, Serialize, Deserialize, Copy, Clone)]
+pub(crate) struct NetEventSubscription {
+    pub sid: [u32; 4],
+    pub opcode: u32,
+}
+
+/// Changes to the network, as sent to subscribers in arg1 of a scalar message whose ID is the opcode they
+/// subscribed with. Addresses in the other args are big-endian octets packed into a u32.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, num_derive::FromPrimitive, num_derive::ToPrimitive)]
+pub enum NetEvent {
+    /// We've joined an access point
+    LinkUp,
+    /// We've lost the access point, and with it our address
+    LinkDown,
+    /// We have a new address, given in arg2
+    IpAcquired,
+    /// The DNS servers have changed; the new ones are in arg2 and arg3, and arg3 is 0 if there's only the one
+    DnsChanged,
+}
+
 #[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive)]
 pub(crate) enum NetCallback {
     Ping,
@@ -29,6 +29,7 @@ pub(crate) enum ConnectionManagerOpcode {
     SubscribeWifiStats,
     UnsubWifiStats,
     FetchSsidList,
+    FetchScanResults,
     ComInt,
     SuspendResume,
     Quit,
@@ -91,7 +92,13 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
     let mut wifi_state = WifiState::Unknown;
     let mut last_wifi_state = wifi_state;
     let mut ssid_list = HashMap::<String, u8>::new();
+    // bumped whenever ssid_list changes, so someone paging through the scan results can tell
+    let mut scan_generation: u32 = 0;
+    // how each AP in ssid_list is secured, where the EC has said
+    let mut ssid_security = HashMap::<String, WifiSecurity>::new();
     let mut ssid_attempted = HashSet::<String>::new();
+    // the network we last tried to join, whose profile says whether to wait for DHCP once we're on it
+    let mut joining_ssid: Option<String> = None;
     let mut wait_count = 0;
     let mut scan_count = 0;
 
@@ -225,7 +232,17 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
                                 ConnectResult::Success => {
                                     scan_state = SsidScanState::Idle;
                                     activity_interval.store(0, Ordering::SeqCst);
-                                    WifiState::WaitDhcp
+                                    if joined_static(&mut pddb, &joining_ssid) {
+                                        // there's no lease to wait for; the Net server sets the address up itself
+                                        wifi_stats_cache = com.wlan_status().unwrap();
+                                        for &sub in status_subscribers.keys() {
+                                            let buf = Buffer::into_buf(com::WlanStatusIpc::from_status(wifi_stats_cache)).or(Err(xous::Error::InternalError)).unwrap();
+                                            buf.send(sub, WifiStateCallback::Update.to_u32().unwrap()).or(Err(xous::Error::InternalError)).unwrap();
+                                        }
+                                        WifiState::Connected
+                                    } else {
+                                        WifiState::WaitDhcp
+                                    }
                                 },
                                 ConnectResult::NoMatchingAp => WifiState::InvalidAp,
                                 ConnectResult::Timeout => WifiState::Retry,
@@ -239,6 +256,8 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
                         ComIntSources::Disconnect => {
                             log::info!("{:?}", source);
                             ssid_list.clear(); // clear the ssid list because a likely cause of disconnect is we've moved out of range
+                            ssid_security.clear();
+                            scan_generation = scan_generation.wrapping_add(1);
                             com.set_ssid_scanning(true).unwrap();
                             scan_state = SsidScanState::Scanning;
                             wifi_state = WifiState::Disconnected;
@@ -251,9 +270,11 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
                                     for (rssi, ssid) in slist.iter() {
                                         ssid_list.insert(ssid.to_string(), *rssi);
                                     }
+                                    scan_generation = scan_generation.wrapping_add(1);
                                 },
                                 _ => continue,
                             }
+                            update_security(&com, &mut ssid_security);
                         },
                         ComIntSources::WlanSsidScanFinished => {
                             log::info!("{:?}", source);
@@ -262,9 +283,11 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
                                     for (rssi, ssid) in slist.iter() {
                                         ssid_list.insert(ssid.to_string(), *rssi);
                                     }
+                                    scan_generation = scan_generation.wrapping_add(1);
                                 },
                                 _ => continue,
                             }
+                            update_security(&com, &mut ssid_security);
                             scan_state = SsidScanState::Idle;
                         }
                         ComIntSources::WlanIpConfigUpdate => {
@@ -278,7 +301,7 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
                                 let buf = Buffer::into_buf(com::WlanStatusIpc::from_status(wifi_stats_cache)).or(Err(xous::Error::InternalError)).unwrap();
                                 buf.send(sub, WifiStateCallback::Update.to_u32().unwrap()).or(Err(xous::Error::InternalError)).unwrap();
                             }
-                            if wifi_stats_cache.ipv4.dhcp == com_rs_ref::DhcpState::Bound {
+                            if wifi_stats_cache.ipv4.dhcp == com_rs_ref::DhcpState::Bound || joined_static(&mut pddb, &joining_ssid) {
                                 wifi_state = WifiState::Connected;
                             } else {
                                 wifi_state = WifiState::WaitDhcp;
@@ -313,15 +336,21 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
 
                         if let Ok(ap_list_vec) = pddb.list_keys(AP_DICT_NAME, None) {
                             let mut ap_list = HashSet::<String>::new();
+                            let mut profiles = HashMap::<String, net::NetProfile>::new();
                             for ap in ap_list_vec {
-                                ap_list.insert(ap);
+                                let profile = net::load_profile(&mut pddb, &ap);
+                                // networks that aren't to be joined automatically aren't candidates at all
+                                if profile.auto_join {
+                                    ap_list.insert(ap.to_string());
+                                    profiles.insert(ap, profile);
+                                }
                             }
                             match wifi_state {
                                 WifiState::Unknown | WifiState::Disconnected | WifiState::InvalidAp | WifiState::InvalidAuth => {
                                     if (scan_state == SsidScanState::Idle) || scan_count > SCAN_COUNT_MAX {
                                         scan_count = 0;
                                         // wait until we're done scanning before trying to connect
-                                        if let Some(ssid) = get_next_ssid(&mut ssid_list, &mut ssid_attempted, ap_list) {
+                                        if let Some(ssid) = get_next_ssid(&mut ssid_list, &mut ssid_attempted, ap_list, &profiles) {
                                             let mut wpa_pw_file = pddb.get(AP_DICT_NAME, &ssid, None, false, false, None, Some(||{})).expect("couldn't retrieve AP password");
                                             let mut wp_pw_raw = [0u8; com::api::WF200_PASS_MAX_LEN];
                                             if let Ok(readlen) = wpa_pw_file.read(&mut wp_pw_raw) {
@@ -330,6 +359,7 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
                                                 com.wlan_set_ssid(&ssid).expect("couldn't set SSID");
                                                 com.wlan_set_pass(pw).expect("couldn't set password");
                                                 com.wlan_join().expect("couldn't issue join command");
+                                                joining_ssid = Some(ssid.to_string());
                                                 wifi_state = WifiState::Connecting;
                                             }
                                         } else {
@@ -445,6 +475,31 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
                 }
                 buffer.replace(ret_list).expect("couldn't return config");
             },
+            Some(ConnectionManagerOpcode::FetchScanResults) => {
+                let mut buffer = unsafe {
+                    Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap())
+                };
+                let mut page = buffer.to_original::<ScanResultPage, _>().unwrap();
+                let known: HashSet<String> = if pddb.is_mounted() {
+                    pddb.list_keys(AP_DICT_NAME, None).unwrap_or(Vec::new()).into_iter().collect()
+                } else {
+                    HashSet::new()
+                };
+                let mut results: Vec<(&String, &u8)> = ssid_list.iter().filter(|(ssid, _)| !ssid.is_empty()).collect();
+                // rssi is the negative of the dBm, so the smallest is the strongest
+                results.sort_by(|a, b| a.1.cmp(b.1).then(a.0.cmp(b.0)));
+                page.total = results.len() as u16;
+                page.generation = scan_generation;
+                for (slot, (ssid, rssi)) in page.results.iter_mut().zip(results.iter().skip(page.start as usize)) {
+                    *slot = Some(ScanResult {
+                        ssid: xous_ipc::String::<32>::from_str(ssid),
+                        rssi: **rssi,
+                        security: ssid_security.get(*ssid).copied().unwrap_or(WifiSecurity::Unknown),
+                        known: known.contains(*ssid),
+                    });
+                }
+                buffer.replace(page).expect("couldn't return scan results");
+            },
             Some(ConnectionManagerOpcode::Run) => msg_scalar_unpack!(msg, _, _, _, _, {
                 if !run.swap(true, Ordering::SeqCst) {
                     if !pumping.load(Ordering::SeqCst) { // avoid having multiple pump messages being sent if a user tries to rapidly toggle the run/stop switch
@@ -471,7 +526,33 @@ pub(crate) fn connection_manager(sid: xous::SID, activity_interval: Arc<AtomicU3
     xous::destroy_server(sid).unwrap();
 }
 
-fn get_next_ssid(ssid_list_map: &mut HashMap<String, u8>, ssid_attempted: &mut HashSet<String>, ap_list: HashSet::<String>) -> Option<String> {
+/// Whether the network being joined has a static address, so there's no DHCP lease to wait for
+fn joined_static(pddb: &mut pddb::Pddb, joining_ssid: &Option<String>) -> bool {
+    match joining_ssid {
+        Some(ssid) => net::load_profile(pddb, ssid).static_ipv4.is_some(),
+        None => false,
+    }
+}
+
+/// Of `candidates`, the one with the highest priority in its profile, taking the strongest signal of those
+/// Notes how each AP in the latest scan results is secured
+fn update_security(com: &com::Com, ssid_security: &mut HashMap<String, WifiSecurity>) {
+    for ap in com.ssid_fetch_ies().unwrap_or_default() {
+        let len = (ap.len as usize).min(ap.ies.len());
+        let security = crate::wifi_security::security_from_ies(ap.capability, &ap.ies[..len]);
+        ssid_security.insert(ap.name.to_string(), security);
+    }
+}
+
+fn best_candidate<'a>(candidates: impl Iterator<Item = &'a String>, ssid_list_map: &HashMap<String, u8>, profiles: &HashMap<String, net::NetProfile>) -> Option<&'a String> {
+    candidates.max_by_key(|ssid| (
+        profiles.get(*ssid).map_or(0, |p| p.priority),
+        // rssi is the negative of the dBm, so the smallest is the strongest
+        std::cmp::Reverse(ssid_list_map.get(*ssid).copied().unwrap_or(u8::MAX)),
+    ))
+}
+
+fn get_next_ssid(ssid_list_map: &mut HashMap<String, u8>, ssid_attempted: &mut HashSet<String>, ap_list: HashSet::<String>, profiles: &HashMap<String, net::NetProfile>) -> Option<String> {
     log::trace!("ap_list: {:?}", ap_list);
     log::trace!("ssid_list: {:?}", ssid_list_map);
     // 0. convert the HashMap of ssid_list into a HashSet
@@ -499,7 +580,7 @@ fn get_next_ssid(ssid_list_map: &mut HashMap<String, u8>, ssid_attempted: &mut H
     log::trace!("untried_candidates: {:?}", untried_candidate_list);
 
     if untried_candidate_list.len() > 0 {
-        if let Some(candidate) = untried_candidate_list.into_iter().next() {
+        if let Some(candidate) = best_candidate(untried_candidate_list.iter(), ssid_list_map, profiles) {
             ssid_attempted.insert(candidate.to_string());
             log::debug!("SSID connect attempt: {:?}", candidate);
             Some(candidate.to_string())
@@ -511,7 +592,7 @@ fn get_next_ssid(ssid_list_map: &mut HashMap<String, u8>, ssid_attempted: &mut H
         // clear the ssid_attempted list and start from scratch
         log::debug!("Exhausted all candidates, starting over again...");
         ssid_attempted.clear();
-        if let Some(candidate) = all_candidate_list.iter().next() {
+        if let Some(candidate) = best_candidate(all_candidate_list.iter(), ssid_list_map, profiles) {
             ssid_attempted.insert(candidate.to_string());
             log::debug!("SSID connect attempt: {:?}", candidate);
             Some(candidate.to_string())
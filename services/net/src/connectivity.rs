@@ -0,0 +1,103 @@
+//! Works out what the network we're on reaches. Whenever we get a new address, as told by a `NetEvent`,
+//! and every so often after that, `PROBE_URL` is asked for without following redirects: a 204 means we're online, and any other answer,
+//! typically a redirect to a sign-in page or the page itself, that a captive portal is in the way. No
+//! answer at all means the network doesn't reach the internet.
+
+use crate::api::{Connectivity, NetEvent};
+use net::http::{Client, Method};
+use num_traits::*;
+use smoltcp::time::Duration;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+const PROBE_URL: &str = "http://connectivitycheck.gstatic.com/generate_204";
+const PROBE_TIMEOUT_MS: u64 = 5_000;
+/// ID of the scalar messages the `NetEvent`s come in as
+const NET_EVENT_ID: u32 = 0;
+/// how soon to probe again when online, in case the network stops reaching the internet
+const RECHECK_ONLINE_MS: u64 = 10 * 60 * 1000;
+/// and when not, so signing in to a portal is noticed soon after
+const RECHECK_OFFLINE_MS: u64 = 30 * 1000;
+
+pub(crate) fn connectivity(state: Arc<AtomicU32>) {
+    let ticktimer = ticktimer_server::Ticktimer::new().unwrap();
+    let netmgr = net::NetManager::new();
+    let client = Client::new()
+        .timeout(Duration::from_millis(PROBE_TIMEOUT_MS))
+        .max_redirects(0);
+    let sid = xous::create_server().unwrap();
+    netmgr
+        .net_events_subscribe(sid, NET_EVENT_ID)
+        .expect("couldn't subscribe to net events");
+    // we may already have had an address by the time we subscribed
+    let mut addressed = netmgr.get_ipv4_config().is_some();
+    let mut next_probe_ms = 0;
+    loop {
+        if addressed && ticktimer.elapsed_ms() >= next_probe_ms {
+            let result = probe(&client);
+            let last = state.swap(result.to_u32().unwrap(), Ordering::SeqCst);
+            if last != result.to_u32().unwrap() {
+                log::info!("connectivity: {:?}", result);
+            }
+            next_probe_ms = ticktimer.elapsed_ms()
+                + if result == Connectivity::Online {
+                    RECHECK_ONLINE_MS
+                } else {
+                    RECHECK_OFFLINE_MS
+                };
+        }
+        // with no address there's nothing to probe until an event says otherwise, so wait as long as it takes
+        let wait_ms = if addressed {
+            next_probe_ms.saturating_sub(ticktimer.elapsed_ms()).max(1) as usize
+        } else {
+            0
+        };
+        let msg = match xous::receive_message_timeout(sid, wait_ms) {
+            Ok(msg) => msg,
+            Err(xous::Error::Timeout) => continue,
+            Err(e) => {
+                log::warn!("couldn't wait for net events: {:?}", e);
+                ticktimer.sleep_ms(RECHECK_OFFLINE_MS as usize).unwrap();
+                continue;
+            }
+        };
+        if msg.body.id() != NET_EVENT_ID as usize {
+            continue;
+        }
+        let event = msg
+            .body
+            .scalar_message()
+            .and_then(|scalar| FromPrimitive::from_usize(scalar.arg1));
+        match event {
+            Some(NetEvent::IpAcquired) => {
+                addressed = true;
+                next_probe_ms = 0;
+            }
+            Some(NetEvent::LinkDown) => {
+                addressed = false;
+                state.store(Connectivity::Unknown.to_u32().unwrap(), Ordering::SeqCst);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn probe(client: &Client) -> Connectivity {
+    // a HEAD request, so a portal's sign-in page doesn't have to be read in to tell it's there
+    match client.request(Method::Head, PROBE_URL, None, &[]) {
+        Ok(response) if response.status == 204 => Connectivity::Online,
+        Ok(response) => {
+            log::debug!(
+                "connectivity probe answered {} {}, location {:?}",
+                response.status,
+                response.reason,
+                response.header("Location")
+            );
+            Connectivity::Portal
+        }
+        Err(e) => {
+            log::debug!("connectivity probe failed: {:?}", e);
+            Connectivity::NoInternet
+        }
+    }
+}
@@ -0,0 +1,341 @@
+//! IPv6 address configuration. The EC only hands us an IPv4 config, so IPv6 is set up here: a link-local
+//! address from our MAC as soon as we're on a network, and a global one by SLAAC (RFC 4862) from whatever
+//! prefix the router advertises. Router advertisements are also where IPv6 DNS servers come from (RFC 8106).
+//!
+//! Duplicate address detection is skipped, as the addresses are made from a MAC that should be unique on
+//! the link anyway. Lifetimes are honored only to the extent that a lifetime of 0 withdraws a prefix, router or
+//! DNS server; a network's advertisements otherwise keep coming as long as we're on it. Everything learned is
+//! forgotten when we leave the network, or when the router starts advertising a different prefix.
+//!
+//! All of it depends on the EC passing IPv6 frames up to us, along with the IPv4 ones.
+
+use smoltcp::wire::{Ipv6Address, Ipv6Cidr};
+
+const ICMPV6: u8 = 58;
+const ROUTER_SOLICIT: u8 = 133;
+const ROUTER_ADVERT: u8 = 134;
+const OPT_SOURCE_LLADDR: u8 = 1;
+const OPT_PREFIX_INFO: u8 = 3;
+const OPT_RDNSS: u8 = 25;
+/// the prefix info flag that says addresses can be made from the prefix
+const PREFIX_AUTONOMOUS: u8 = 0x40;
+/// SLAAC only makes 64-bit interface identifiers
+const SLAAC_PREFIX_LEN: u8 = 64;
+const ALL_ROUTERS: Ipv6Address = Ipv6Address([0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+
+/// What we've learned from the router
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct Ipv6State {
+    pub(crate) global: Option<Ipv6Cidr>,
+    pub(crate) router: Option<Ipv6Address>,
+    pub(crate) dns: Vec<Ipv6Address>,
+}
+
+#[derive(Debug)]
+pub(crate) struct RouterAdvert {
+    router: Ipv6Address,
+    router_lifetime_s: u16,
+    /// autonomous /64 prefixes, with their valid lifetimes
+    prefixes: Vec<(Ipv6Address, u32)>,
+    /// DNS servers, with their lifetime
+    dns: Vec<(Ipv6Address, u32)>,
+}
+
+/// The interface identifier made from `mac`, by way of EUI-64
+fn interface_id(mac: [u8; 6]) -> [u8; 8] {
+    [mac[0] ^ 0x02, mac[1], mac[2], 0xff, 0xfe, mac[3], mac[4], mac[5]]
+}
+
+pub(crate) fn link_local(mac: [u8; 6]) -> Ipv6Address {
+    let mut addr = [0u8; 16];
+    addr[0] = 0xfe;
+    addr[1] = 0x80;
+    addr[8..].copy_from_slice(&interface_id(mac));
+    Ipv6Address(addr)
+}
+
+fn slaac_address(prefix: Ipv6Address, mac: [u8; 6]) -> Ipv6Address {
+    let mut addr = prefix.0;
+    addr[8..].copy_from_slice(&interface_id(mac));
+    Ipv6Address(addr)
+}
+
+/// The ICMPv6 checksum of `icmp`, to go between `src` and `dst`. Over a message that already has its
+/// checksum in it, this comes out 0 if the checksum is right.
+fn checksum(src: &Ipv6Address, dst: &Ipv6Address, icmp: &[u8]) -> u16 {
+    let mut sum: u32 = 0;
+    let mut add = |data: &[u8]| {
+        for chunk in data.chunks(2) {
+            let word = if chunk.len() == 2 {
+                u16::from_be_bytes([chunk[0], chunk[1]])
+            } else {
+                u16::from_be_bytes([chunk[0], 0])
+            };
+            sum += word as u32;
+        }
+    };
+    add(src.as_bytes());
+    add(dst.as_bytes());
+    add(&(icmp.len() as u32).to_be_bytes());
+    add(&[0, 0, 0, ICMPV6]);
+    add(icmp);
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// A router solicitation from `src`, as a whole IPv6 packet, so a router tells us about itself now rather
+/// than in its own time
+pub(crate) fn router_solicit(src: Ipv6Address, mac: [u8; 6]) -> Vec<u8> {
+    let mut icmp = vec![ROUTER_SOLICIT, 0, 0, 0, 0, 0, 0, 0, OPT_SOURCE_LLADDR, 1];
+    icmp.extend_from_slice(&mac);
+    let sum = checksum(&src, &ALL_ROUTERS, &icmp);
+    icmp[2..4].copy_from_slice(&sum.to_be_bytes());
+
+    let mut pkt = vec![0x60, 0, 0, 0];
+    pkt.extend_from_slice(&(icmp.len() as u16).to_be_bytes());
+    pkt.push(ICMPV6);
+    pkt.push(255); // the hop limit neighbor discovery requires, so it's known not to have been routed
+    pkt.extend_from_slice(src.as_bytes());
+    pkt.extend_from_slice(ALL_ROUTERS.as_bytes());
+    pkt.extend_from_slice(&icmp);
+    pkt
+}
+
+fn read_u32(data: &[u8], index: usize) -> u32 {
+    u32::from_be_bytes([data[index], data[index + 1], data[index + 2], data[index + 3]])
+}
+
+/// The router advertisement in `pkt`, a whole IPv6 packet, if that's what it is
+pub(crate) fn parse_router_advert(pkt: &[u8]) -> Option<RouterAdvert> {
+    if pkt.len() < 40 || pkt[0] >> 4 != 6 || pkt[6] != ICMPV6 || pkt[7] != 255 {
+        return None;
+    }
+    let src = Ipv6Address::from_bytes(&pkt[8..24]);
+    let dst = Ipv6Address::from_bytes(&pkt[24..40]);
+    let len = u16::from_be_bytes([pkt[4], pkt[5]]) as usize;
+    let icmp = pkt.get(40..40 + len)?;
+    if icmp.len() < 16 || icmp[0] != ROUTER_ADVERT || icmp[1] != 0 || checksum(&src, &dst, icmp) != 0 {
+        return None;
+    }
+    let mut ra = RouterAdvert {
+        router: src,
+        router_lifetime_s: u16::from_be_bytes([icmp[6], icmp[7]]),
+        prefixes: Vec::new(),
+        dns: Vec::new(),
+    };
+    let mut options = &icmp[16..];
+    while options.len() >= 2 {
+        let opt_len = options[1] as usize * 8;
+        if opt_len == 0 || opt_len > options.len() {
+            break;
+        }
+        let opt = &options[..opt_len];
+        match opt[0] {
+            OPT_PREFIX_INFO if opt_len == 32 => {
+                let prefix = Ipv6Address::from_bytes(&opt[16..32]);
+                if opt[2] == SLAAC_PREFIX_LEN && opt[3] & PREFIX_AUTONOMOUS != 0 && !prefix.is_link_local() {
+                    ra.prefixes.push((prefix, read_u32(opt, 4)));
+                }
+            }
+            OPT_RDNSS if opt_len >= 24 => {
+                let lifetime = read_u32(opt, 4);
+                for addr in opt[8..].chunks_exact(16) {
+                    ra.dns.push((Ipv6Address::from_bytes(addr), lifetime));
+                }
+            }
+            _ => {}
+        }
+        options = &options[opt_len..];
+    }
+    Some(ra)
+}
+
+impl Ipv6State {
+    /// Takes in what `ra` says, returning whether anything changed
+    pub(crate) fn update(&mut self, ra: &RouterAdvert, mac: [u8; 6]) -> bool {
+        let before = self.clone();
+        // A new prefix means the network has been renumbered, or it's another network altogether, so the
+        // router and DNS servers that went with the old one can't be counted on
+        let offered = ra.prefixes.iter().find(|(_, valid_lifetime)| *valid_lifetime > 0);
+        if let (Some(global), Some(&(prefix, _))) = (self.global, offered) {
+            if global.address() != slaac_address(prefix, mac) {
+                *self = Ipv6State::default();
+            }
+        }
+        if ra.router_lifetime_s > 0 {
+            self.router = Some(ra.router);
+        } else if self.router == Some(ra.router) {
+            self.router = None;
+        }
+        // only the one global address is kept, from the first prefix offered
+        for &(prefix, valid_lifetime) in ra.prefixes.iter() {
+            let addr = Ipv6Cidr::new(slaac_address(prefix, mac), SLAAC_PREFIX_LEN);
+            if valid_lifetime > 0 {
+                if self.global.is_none() {
+                    self.global = Some(addr);
+                }
+                break;
+            } else if self.global == Some(addr) {
+                self.global = None;
+            }
+        }
+        for &(addr, lifetime) in ra.dns.iter() {
+            if lifetime == 0 {
+                self.dns.retain(|&a| a != addr);
+            } else if !self.dns.contains(&addr) {
+                self.dns.push(addr);
+            }
+        }
+        *self != before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAC: [u8; 6] = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55];
+    const ROUTER: Ipv6Address = Ipv6Address([0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    const ALL_NODES: Ipv6Address = Ipv6Address([0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+    fn addr(s: &str) -> Ipv6Address {
+        let words = |part: &str| -> Vec<u16> {
+            part.split(':').filter(|w| !w.is_empty()).map(|w| u16::from_str_radix(w, 16).unwrap()).collect()
+        };
+        let (head, tail) = s.split_once("::").unwrap_or((s, ""));
+        let (head, tail) = (words(head), words(tail));
+        let mut all = [0u16; 8];
+        all[..head.len()].copy_from_slice(&head);
+        all[8 - tail.len()..].copy_from_slice(&tail);
+        Ipv6Address::new(all[0], all[1], all[2], all[3], all[4], all[5], all[6], all[7])
+    }
+
+    /// A prefix information option for `prefix`/`len`, with the autonomous flag if `auto`
+    fn prefix_opt(prefix: Ipv6Address, len: u8, auto: bool, valid_s: u32) -> Vec<u8> {
+        let flags = if auto { 0x80 | PREFIX_AUTONOMOUS } else { 0x80 };
+        let mut opt = vec![OPT_PREFIX_INFO, 4, len, flags];
+        opt.extend_from_slice(&valid_s.to_be_bytes());
+        opt.extend_from_slice(&valid_s.to_be_bytes()); // preferred lifetime
+        opt.extend_from_slice(&[0; 4]);
+        opt.extend_from_slice(prefix.as_bytes());
+        opt
+    }
+
+    fn rdnss_opt(servers: &[Ipv6Address], lifetime_s: u32) -> Vec<u8> {
+        let mut opt = vec![OPT_RDNSS, 1 + 2 * servers.len() as u8, 0, 0];
+        opt.extend_from_slice(&lifetime_s.to_be_bytes());
+        for server in servers {
+            opt.extend_from_slice(server.as_bytes());
+        }
+        opt
+    }
+
+    /// A router advertisement from `ROUTER` carrying `options`, as a whole IPv6 packet
+    fn advert(router_lifetime_s: u16, options: &[Vec<u8>]) -> Vec<u8> {
+        let mut icmp = vec![ROUTER_ADVERT, 0, 0, 0, 64, 0];
+        icmp.extend_from_slice(&router_lifetime_s.to_be_bytes());
+        icmp.extend_from_slice(&[0; 8]); // reachable time and retransmit timer
+        for opt in options {
+            icmp.extend_from_slice(opt);
+        }
+        let sum = checksum(&ROUTER, &ALL_NODES, &icmp);
+        icmp[2..4].copy_from_slice(&sum.to_be_bytes());
+        let mut pkt = vec![0x60, 0, 0, 0];
+        pkt.extend_from_slice(&(icmp.len() as u16).to_be_bytes());
+        pkt.extend_from_slice(&[ICMPV6, 255]);
+        pkt.extend_from_slice(ROUTER.as_bytes());
+        pkt.extend_from_slice(ALL_NODES.as_bytes());
+        pkt.extend_from_slice(&icmp);
+        pkt
+    }
+
+    fn parsed(router_lifetime_s: u16, options: &[Vec<u8>]) -> RouterAdvert {
+        parse_router_advert(&advert(router_lifetime_s, options)).unwrap()
+    }
+
+    #[test]
+    fn addresses_from_the_mac() {
+        assert_eq!(link_local(MAC), addr("fe80::211:22ff:fe33:4455"));
+        assert_eq!(slaac_address(addr("2001:db8:1:2::"), MAC), addr("2001:db8:1:2:211:22ff:fe33:4455"));
+    }
+
+    #[test]
+    fn router_solicit_is_well_formed() {
+        let src = link_local(MAC);
+        let pkt = router_solicit(src, MAC);
+        assert_eq!(&pkt[24..40], ALL_ROUTERS.as_bytes());
+        assert_eq!(checksum(&src, &ALL_ROUTERS, &pkt[40..]), 0);
+        assert_eq!(&pkt[pkt.len() - 6..], &MAC);
+    }
+
+    #[test]
+    fn parses_router_adverts() {
+        let dns = [addr("2001:db8::53"), addr("2001:db8::54")];
+        let pkt = advert(
+            1800,
+            &[
+                prefix_opt(addr("2001:db8:1:2::"), 64, true, 86400),
+                // not usable for SLAAC: the wrong length, not autonomous, or link-local
+                prefix_opt(addr("2001:db8:9::"), 48, true, 86400),
+                prefix_opt(addr("2001:db8:8:8::"), 64, false, 86400),
+                prefix_opt(addr("fe80::"), 64, true, 86400),
+                rdnss_opt(&dns, 600),
+            ],
+        );
+        let ra = parse_router_advert(&pkt).unwrap();
+        assert_eq!(ra.router, ROUTER);
+        assert_eq!(ra.router_lifetime_s, 1800);
+        assert_eq!(ra.prefixes, vec![(addr("2001:db8:1:2::"), 86400)]);
+        assert_eq!(ra.dns, vec![(dns[0], 600), (dns[1], 600)]);
+
+        let mut corrupt = pkt.clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        assert!(parse_router_advert(&corrupt).is_none());
+        // anything that's been through a router isn't from one on our link
+        let mut forwarded = pkt.clone();
+        forwarded[7] = 254;
+        assert!(parse_router_advert(&forwarded).is_none());
+        assert!(parse_router_advert(&pkt[..50]).is_none());
+    }
+
+    #[test]
+    fn state_follows_the_adverts() {
+        let prefix = addr("2001:db8:1:2::");
+        let dns = addr("2001:db8::53");
+        let first = parsed(1800, &[prefix_opt(prefix, 64, true, 86400), rdnss_opt(&[dns], 600)]);
+        let mut state = Ipv6State::default();
+        assert!(state.update(&first, MAC));
+        assert_eq!(state.global, Some(Ipv6Cidr::new(slaac_address(prefix, MAC), 64)));
+        assert_eq!(state.router, Some(ROUTER));
+        assert_eq!(state.dns, vec![dns]);
+        // the same again is no news
+        assert!(!state.update(&first, MAC));
+
+        // a lifetime of 0 withdraws what it's given for
+        assert!(state.update(&parsed(1800, &[rdnss_opt(&[dns], 0)]), MAC));
+        assert!(state.dns.is_empty());
+        assert!(state.update(&parsed(0, &[]), MAC));
+        assert_eq!(state.router, None);
+        assert!(state.global.is_some());
+        assert!(state.update(&parsed(1800, &[prefix_opt(prefix, 64, true, 0)]), MAC));
+        assert_eq!(state.global, None);
+    }
+
+    #[test]
+    fn a_new_prefix_replaces_the_state() {
+        let old = parsed(
+            1800,
+            &[prefix_opt(addr("2001:db8:1:2::"), 64, true, 86400), rdnss_opt(&[addr("2001:db8::53")], 600)],
+        );
+        let mut state = Ipv6State::default();
+        state.update(&old, MAC);
+
+        let new_prefix = addr("2001:db8:7:7::");
+        assert!(state.update(&parsed(1800, &[prefix_opt(new_prefix, 64, true, 86400)]), MAC));
+        assert_eq!(state.global, Some(Ipv6Cidr::new(slaac_address(new_prefix, MAC), 64)));
+        assert_eq!(state.router, Some(ROUTER));
+        assert!(state.dns.is_empty());
+    }
+}
@@ -5,9 +5,12 @@ use com::{Ipv4Conf, SsidRecord};
 use xous::{CID, send_message, Message};
 use xous_ipc::Buffer;
 use num_traits::*;
+use std::net::{Ipv4Addr, SocketAddrV4};
 
 pub mod protocols;
 pub use protocols::*;
+pub mod profile;
+pub use profile::*;
 pub use smoltcp::time::Duration;
 pub use api::*;
 pub use smoltcp::wire::IpEndpoint;
@@ -72,6 +75,12 @@ impl NetManager {
             None
         }
     }
+    /// Our IPv6 addresses, or `None` if we're not on a network
+    pub fn get_ipv6_config(&self) -> Option<Ipv6Config> {
+        let mut buf = Buffer::into_buf(None::<Ipv6Config>).expect("Couldn't convert to memory structure");
+        buf.lend_mut(self.netconn.conn(), Opcode::GetIpv6Config.to_u32().unwrap()).expect("Couldn't execute GetIpv6Config opcode");
+        buf.to_original().expect("couldn't restore config structure")
+    }
     pub fn reset(&self) {
         send_message(
             self.netconn.conn(),
@@ -147,6 +156,27 @@ impl NetManager {
         }
         Ok(())
     }
+    /// Has a scalar message with `opcode` as its ID sent to the server `sid` whenever the link or our address
+    /// changes; arg1 says which `NetEvent` it is. Lasts until `net_events_unsubscribe`, or the server goes away.
+    pub fn net_events_subscribe(&self, sid: xous::SID, opcode: u32) -> Result<(), xous::Error> {
+        let sub = NetEventSubscription {
+            sid: sid.to_array(),
+            opcode,
+        };
+        let buf = Buffer::into_buf(sub).or(Err(xous::Error::InternalError))?;
+        buf.send(self.netconn.conn(), Opcode::SubscribeNetEvents.to_u32().unwrap()).map(|_| ())
+    }
+    pub fn net_events_unsubscribe(&self, sid: xous::SID) -> Result<(), xous::Error> {
+        let s = sid.to_array();
+        send_message(self.netconn.conn(),
+            Message::new_blocking_scalar(Opcode::UnsubNetEvents.to_usize().unwrap(),
+            s[0] as usize,
+            s[1] as usize,
+            s[2] as usize,
+            s[3] as usize,
+            )
+        ).map(|_| ())
+    }
     pub fn wifi_get_ssid_list(&self) -> Result<Vec::<SsidRecord>, xous::Error> {
         let alloc = SsidList::default();
         let mut buf = Buffer::into_buf(alloc).map_err(|_| xous::Error::InternalError)?;
@@ -160,6 +190,109 @@ impl NetManager {
         }
         Ok(ret)
     }
+    /// The access points seen by the connection manager's most recent scans, strongest signal first. Unlike
+    /// `wifi_get_ssid_list()`, there's no limit on how many are returned.
+    pub fn wifi_get_scan_results(&self) -> Result<Vec<ScanResult>, xous::Error> {
+        let mut ret = Vec::<ScanResult>::new();
+        let mut generation = None;
+        loop {
+            let page = ScanResultPage {
+                start: ret.len() as u16,
+                ..Default::default()
+            };
+            let mut buf = Buffer::into_buf(page).map_err(|_| xous::Error::InternalError)?;
+            buf.lend_mut(self.netconn.conn(), Opcode::FetchScanResults.to_u32().unwrap())?;
+            let page = buf.to_original::<ScanResultPage, _>().map_err(|_| xous::Error::InternalError)?;
+            if generation.map_or(false, |g| g != page.generation) {
+                // the results changed while we were paging through them, so the pages could overlap or miss some
+                ret.clear();
+                generation = None;
+                continue;
+            }
+            generation = Some(page.generation);
+            let fetched = ret.len();
+            ret.extend(page.results.iter().filter_map(|r| *r));
+            if ret.len() >= page.total as usize || ret.len() == fetched {
+                return Ok(ret);
+            }
+        }
+    }
+    /// What the network we're on reaches; worth a look before making a TLS connection, in case a captive portal
+    /// is in the way
+    pub fn get_connectivity(&self) -> Result<Connectivity, xous::Error> {
+        match send_message(self.netconn.conn(),
+            Message::new_blocking_scalar(Opcode::GetConnectivity.to_usize().unwrap(), 0, 0, 0, 0)
+        )? {
+            xous::Result::Scalar1(state) => FromPrimitive::from_usize(state).ok_or(xous::Error::InternalError),
+            _ => Err(xous::Error::InternalError),
+        }
+    }
+    /// Sends TCP connections from all processes out through the SOCKS5 proxy at `proxy`, or straight to
+    /// where they're going if it's `None`. The setting is kept in the PDDB, if it's mounted. Only the shell and
+    /// the status bar may set it; anyone else gets `AccessDenied`.
+    pub fn set_socks_proxy(&self, proxy: Option<SocketAddrV4>) -> Result<(), xous::Error> {
+        let (addr, port) = match proxy {
+            Some(proxy) => (u32::from_be_bytes(proxy.ip().octets()), proxy.port()),
+            None => (0, 0),
+        };
+        match send_message(self.netconn.conn(),
+            Message::new_blocking_scalar(Opcode::SetSocksProxy.to_usize().unwrap(), addr as usize, port as usize, 0, 0)
+        )? {
+            xous::Result::Scalar1(1) => Ok(()),
+            xous::Result::Scalar1(_) => Err(xous::Error::AccessDenied),
+            _ => Err(xous::Error::InternalError),
+        }
+    }
+    pub fn get_socks_proxy(&self) -> Result<Option<SocketAddrV4>, xous::Error> {
+        match send_message(self.netconn.conn(),
+            Message::new_blocking_scalar(Opcode::GetSocksProxy.to_usize().unwrap(), 0, 0, 0, 0)
+        )? {
+            xous::Result::Scalar2(0, _) => Ok(None),
+            xous::Result::Scalar2(addr, port) => Ok(Some(SocketAddrV4::new(Ipv4Addr::from(addr as u32), port as u16))),
+            _ => Err(xous::Error::InternalError),
+        }
+    }
+    /// Have this process's TCP connections go direct even when there's a proxy set, for things such as
+    /// a connection to a local network device that the proxy couldn't reach. This lasts until it's turned
+    /// back off or the process exits.
+    pub fn socks_opt_out(&self, opt_out: bool) -> Result<(), xous::Error> {
+        send_message(self.netconn.conn(),
+            Message::new_blocking_scalar(Opcode::SocksOptOut.to_usize().unwrap(), if opt_out { 1 } else { 0 }, 0, 0, 0)
+        ).map(|_| ())
+    }
+    /// The traffic of each process that has used the network since boot, busiest first. PID 0 is the Net
+    /// server itself, which sends and answers pings.
+    pub fn get_process_stats(&self) -> Result<Vec<ProcessStats>, xous::Error> {
+        let mut buf = Buffer::into_buf(ProcessStatsList::default()).map_err(|_| xous::Error::InternalError)?;
+        buf.lend_mut(self.netconn.conn(), Opcode::GetProcessStats.to_u32().unwrap())?;
+        let list = buf.to_original::<ProcessStatsList, _>().map_err(|_| xous::Error::InternalError)?;
+        Ok(list.list.iter().filter_map(|p| *p).collect())
+    }
+    /// The traffic through each socket that's open now
+    pub fn get_socket_stats(&self) -> Result<Vec<SocketStats>, xous::Error> {
+        let mut ret = Vec::<SocketStats>::new();
+        loop {
+            let page = SocketStatsPage {
+                start: ret.len() as u16,
+                ..Default::default()
+            };
+            let mut buf = Buffer::into_buf(page).map_err(|_| xous::Error::InternalError)?;
+            buf.lend_mut(self.netconn.conn(), Opcode::GetSocketStats.to_u32().unwrap())?;
+            let page = buf.to_original::<SocketStatsPage, _>().map_err(|_| xous::Error::InternalError)?;
+            let fetched = ret.len();
+            ret.extend(page.list.iter().filter_map(|s| *s));
+            // sockets can come and go between pages, so this is a snapshot, not an exact one
+            if ret.len() >= page.total as usize || ret.len() == fetched {
+                return Ok(ret);
+            }
+        }
+    }
+    /// How the RTC's last sync with network time went, or `None` if it hasn't been synced since boot
+    pub fn get_time_sync(&self) -> Result<Option<TimeSync>, xous::Error> {
+        let mut buf = Buffer::into_buf(None::<TimeSync>).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.netconn.conn(), Opcode::GetTimeSync.to_u32().unwrap())?;
+        buf.to_original::<Option<TimeSync>, _>().or(Err(xous::Error::InternalError))
+    }
     pub fn connection_manager_stop(&self) -> Result<(), xous::Error> {
         send_message(self.netconn.conn(),
             Message::new_scalar(Opcode::ConnMgrStartStop.to_usize().unwrap(), 0, 0,0, 0)
@@ -4,13 +4,20 @@
 mod api;
 use api::*;
 use com::api::{ComIntSources, Ipv4Conf, NET_MTU};
+use com_rs_ref::ConnectResult;
 use num_traits::*;
 use ticktimer_server::Ticktimer;
 
 mod connection_manager;
+mod connectivity;
 mod device;
+mod ipv6;
+mod socks;
+mod stats;
+mod time_sync;
+mod wifi_security;
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryInto;
 use xous::{msg_blocking_scalar_unpack, msg_scalar_unpack, send_message, Message, CID, SID};
 use xous_ipc::Buffer;
@@ -19,7 +26,9 @@ use byteorder::{ByteOrder, NetworkEndian};
 use smoltcp::iface::{Interface, InterfaceBuilder, NeighborCache, Routes};
 use smoltcp::phy::{Device, Medium};
 use smoltcp::socket::{IcmpEndpoint, IcmpPacketMetadata, IcmpSocket, IcmpSocketBuffer, SocketSet};
+use smoltcp::socket::{RawPacketMetadata, RawSocket, RawSocketBuffer};
 use smoltcp::wire::{EthernetAddress, IpAddress, IpCidr, IpEndpoint, Ipv4Address, Ipv4Cidr};
+use smoltcp::wire::{IpProtocol, IpVersion, Ipv6Address, Ipv6Cidr};
 use smoltcp::wire::{Icmpv4Packet, Icmpv4Repr, Icmpv6Packet, Icmpv6Repr};
 
 use core::num::NonZeroU64;
@@ -46,6 +55,106 @@ where
     });
 }
 
+/// The addresses a new interface starts out with: an IPv4 one, then IPv6 ones for `set_ipv6_config` to fill in
+fn unspecified_ip_addrs() -> [IpCidr; 3] {
+    [
+        IpCidr::new(Ipv4Address::UNSPECIFIED.into(), 0),
+        IpCidr::new(Ipv6Address::UNSPECIFIED.into(), 0),
+        IpCidr::new(Ipv6Address::UNSPECIFIED.into(), 0),
+    ]
+}
+
+/// Puts our IPv6 addresses and default route on `iface`. The global address, if there is one, goes ahead of
+/// the link-local one, so it's the one picked as the source of outgoing traffic.
+fn set_ipv6_config<DeviceT>(
+    iface: &mut Interface<'_, DeviceT>,
+    link_local: Ipv6Address,
+    state: &ipv6::Ipv6State,
+) where
+    DeviceT: for<'d> Device<'d>,
+{
+    let link_local = Ipv6Cidr::new(link_local, 64);
+    iface.update_ip_addrs(|addrs| {
+        addrs[1] = IpCidr::Ipv6(state.global.unwrap_or(link_local));
+        addrs[2] = IpCidr::Ipv6(link_local);
+    });
+    iface.routes_mut().remove_default_ipv6_route();
+    if let Some(router) = state.router {
+        if let Err(e) = iface.routes_mut().add_default_ipv6_route(router) {
+            log::error!("couldn't add default IPv6 route: {}", e);
+        }
+    }
+}
+
+/// Hands `addr` to the DNS server's IPv6 hook
+fn notify_ipv6_dns(hook: &mut XousScalarEndpoint, addr: Ipv6Address) {
+    let words: Vec<Option<u32>> = addr
+        .as_bytes()
+        .chunks_exact(4)
+        .map(|w| Some(u32::from_be_bytes(w.try_into().unwrap())))
+        .collect();
+    hook.notify_custom_args([words[0], words[1], words[2], words[3]]);
+}
+
+/// The address our IPv6 traffic goes out from
+fn ipv6_source<DeviceT>(iface: &Interface<'_, DeviceT>) -> IpAddress
+where
+    DeviceT: for<'d> Device<'d>,
+{
+    iface
+        .ip_addrs()
+        .iter()
+        .map(|cidr| cidr.address())
+        .find(|addr| matches!(addr, IpAddress::Ipv6(_)))
+        .unwrap_or(IpAddress::Ipv6(Ipv6Address::UNSPECIFIED))
+}
+
+/// Gives up the memberships of the UDP socket bound to `port`, leaving any group no other socket is in
+fn leave_multicast_groups<DeviceT>(
+    iface: &mut Interface<'_, DeviceT>,
+    members: &mut HashMap<Ipv4Address, Vec<u16>>,
+    port: u16,
+    timestamp: Instant,
+) where
+    DeviceT: for<'d> Device<'d>,
+{
+    members.retain(|group, ports| {
+        ports.retain(|&p| p != port);
+        if ports.is_empty() {
+            if let Err(e) = iface.leave_multicast_group(*group, timestamp) {
+                log::warn!("couldn't leave multicast group {}: {:?}", group, e);
+            }
+        }
+        !ports.is_empty()
+    });
+}
+
+/// Tells each subscriber about `event`, forgetting any that have gone away
+fn notify_net_event(
+    subscribers: &mut HashMap<[u32; 4], (CID, u32)>,
+    event: NetEvent,
+    arg2: usize,
+    arg3: usize,
+) {
+    log::debug!("net event {:?}", event);
+    subscribers.retain(|_, &mut (cid, opcode)| {
+        match xous::try_send_message(
+            cid,
+            Message::new_scalar(opcode as usize, event.to_usize().unwrap(), arg2, arg3, 0),
+        ) {
+            Ok(_) => true,
+            Err(xous::Error::ServerNotFound) => {
+                unsafe { xous::disconnect(cid).ok() };
+                false
+            }
+            Err(e) => {
+                log::warn!("couldn't send {:?} to a subscriber: {:?}", event, e);
+                true
+            }
+        }
+    });
+}
+
 #[derive(num_derive::FromPrimitive, num_derive::ToPrimitive, Debug)]
 enum WaitOp {
     WaitMs,
@@ -112,6 +221,29 @@ fn set_com_ints(com_int_list: &mut Vec<ComIntSources>) {
     com_int_list.push(ComIntSources::WfxErr);
 }
 
+/// The profile for the network we're on, or the default one if there isn't one or no network is joined
+fn current_profile(com: &mut com::Com, pddb: &mut Option<pddb::Pddb>) -> net::NetProfile {
+    let ssid = match com.wlan_status().ok().and_then(|status| status.ssid) {
+        Some(ssid) => ssid,
+        None => return net::NetProfile::default(),
+    };
+    let pddb = pddb.get_or_insert_with(pddb::Pddb::new);
+    if !pddb.is_mounted() {
+        return net::NetProfile::default();
+    }
+    net::load_profile(pddb, ssid.name.as_str().unwrap_or(""))
+}
+
+/// Whether `pid` has asked to go around the SOCKS proxy. Processes that have exited are let go of
+/// first, so that one which later starts with the same PID uses the proxy like any other.
+fn socks_opted_out(opt_outs: &mut HashSet<Option<xous::PID>>, pid: Option<xous::PID>) -> bool {
+    opt_outs.retain(|p| match p {
+        Some(p) => xous::cpu_time(Some(*p)) != Err(xous::Error::ProcessNotFound),
+        None => true,
+    });
+    opt_outs.contains(&pid)
+}
+
 fn parse_address(data: &[u8]) -> Option<smoltcp::wire::IpAddress> {
     let mut i = data.iter();
     match i.next() {
@@ -202,6 +334,16 @@ fn respond_with_connected(
     bfr[3] = remote_port;
 }
 
+/// A libstd connect waiting to complete: (envelope, handle, fd, local_port, remote_port, proxy handshake)
+type ConnectWaiting = (
+    xous::MessageEnvelope,
+    SocketHandle,
+    u16,
+    u16,
+    u16,
+    Option<socks::Handshake>,
+);
+
 /// Insert `Some(value)` into the first slot in the Vec that is `None`,
 /// or append it to the end if there is no free slot
 fn insert_or_append<T>(arr: &mut Vec<Option<T>>, val: T) -> usize {
@@ -227,9 +369,13 @@ fn std_tcp_connect(
     mut msg: xous::MessageEnvelope,
     local_port: u16,
     sockets: &mut SocketSet,
-    tcp_connect_waiting: &mut Vec<Option<(xous::MessageEnvelope, SocketHandle, u16, u16, u16)>>,
+    tcp_connect_waiting: &mut Vec<Option<ConnectWaiting>>,
     our_sockets: &mut Vec<Option<SocketHandle>>,
+    stats: &mut stats::NetStats,
+    proxy: Option<IpEndpoint>,
+    now_ms: u64,
 ) {
+    let pid = msg.sender.pid();
     // Ignore nonblocking and scalar messages
     let body = match msg.body.memory_message_mut() {
         Some(b) => b,
@@ -259,9 +405,18 @@ fn std_tcp_connect(
         TcpSocketBuffer::new(vec![0; TCP_BUFFER_SIZE]),
     );
 
+    // with a proxy set, it's the proxy we connect to, and it's told where we're going once we're connected
+    let (endpoint, handshake) = match proxy {
+        Some(proxy) => {
+            let timeout = timeout_ms.map(|t| t.get()).unwrap_or(socks::DEFAULT_TIMEOUT_MS);
+            (proxy, Some(socks::Handshake::new(address, remote_port, now_ms + timeout)))
+        }
+        None => (IpEndpoint::new(address, remote_port), None),
+    };
+
     // Attempt to connect, returning the error if there is one
     if let Err(e) = tcp_socket
-        .connect((address, remote_port), local_port)
+        .connect(endpoint, local_port)
         .map_err(|e| match e {
             smoltcp::Error::Illegal => NetError::SocketInUse,
             smoltcp::Error::Unaddressable => NetError::Unaddressable,
@@ -276,13 +431,14 @@ fn std_tcp_connect(
     tcp_socket.set_timeout(timeout_ms.map(|t| Duration::from_millis(t.get())));
 
     let handle = sockets.add(tcp_socket);
+    stats.open(handle, pid, SocketKind::Tcp, local_port);
 
     // Add the socket onto the list of sockets waiting to connect, since the connection will
     // take time.
     let idx = insert_or_append(our_sockets, handle) as u16;
     insert_or_append(
         tcp_connect_waiting,
-        (msg, handle, idx, local_port, remote_port),
+        (msg, handle, idx, local_port, remote_port, handshake),
     );
 }
 
@@ -292,6 +448,7 @@ fn std_tcp_tx(
     sockets: &mut SocketSet,
     tcp_tx_waiting: &mut Vec<Option<WaitingSocket>>,
     our_sockets: &Vec<Option<SocketHandle>>,
+    stats: &mut stats::NetStats,
 ) {
     let connection_handle_index = (msg.body.id() >> 16) & 0xffff;
     let body = match msg.body.memory_message_mut() {
@@ -353,6 +510,9 @@ fn std_tcp_tx(
     };
 
     log::trace!("sent {}", sent_octets);
+    if sent_octets > 0 {
+        stats.sent(*handle, sent_octets);
+    }
     let response_data = body.buf.as_slice_mut::<u32>();
     body.valid = xous::MemorySize::new(sent_octets);
     response_data[0] = 0;
@@ -365,6 +525,7 @@ fn std_tcp_rx(
     sockets: &mut SocketSet,
     tcp_rx_waiting: &mut Vec<Option<WaitingSocket>>,
     our_sockets: &Vec<Option<SocketHandle>>,
+    stats: &mut stats::NetStats,
 ) {
     let connection_handle_index = (msg.body.id() >> 16) & 0xffff;
     let body = match msg.body.memory_message_mut() {
@@ -393,6 +554,9 @@ fn std_tcp_rx(
             Ok(bytes) => {
                 body.valid = xous::MemorySize::new(bytes);
                 log::trace!("set body.valid = {:?}", body.valid);
+                if bytes > 0 {
+                    stats.received(*handle, bytes);
+                }
             }
             Err(e) => {
                 log::error!("unable to receive: {:?}", e);
@@ -480,7 +644,7 @@ fn xmain() -> ! {
 
     // bring the EC into a sane state for the network -- that is, reset the EC
     let mut llio = llio::Llio::new(&xns);
-    let com = com::Com::new(&xns).unwrap();
+    let mut com = com::Com::new(&xns).unwrap();
     let timer = ticktimer_server::Ticktimer::new().unwrap();
 
     // we need a trng for port numbers
@@ -503,6 +667,19 @@ fn xmain() -> ! {
     const MAX_DELAY_THREADS: u32 = 10; // limit the number of concurrent delay threads. Typically we have 1-2 running at any time, but DoS conditions could lead to many more.
     let delay_threads = Arc::new(AtomicU32::new(0));
     let mut net_config: Option<Ipv4Conf> = None;
+    // hosted mode uses the host's sockets, so there's no network to join; but on the segment, the instance
+    // has an address of its own for its peers to reach it at
+    #[cfg(not(any(target_os = "none", target_os = "xous")))]
+    if let Some(addr) = net::segment_address() {
+        let mut config = Ipv4Conf::default();
+        config.addr = addr.octets();
+        // locally administered, so the link-local IPv6 address is unique to the instance too
+        config.mac = [0x02, 0, 0, 0, 0, addr.octets()[3]];
+        log::info!("hosted instance on the virtual segment at {}", addr);
+        net_config = Some(config);
+    }
+    // only connected to once a network is joined, so starting up doesn't wait on the PDDB
+    let mut pddb: Option<pddb::Pddb> = None;
 
     // storage for all our sockets
     let mut sockets = SocketSet::new(vec![]);
@@ -521,15 +698,15 @@ fn xmain() -> ! {
 
     // When a client issues a Connect request, it will get placed here while the connection is
     // being established.
-    let mut tcp_connect_waiting: Vec<
-        Option<(
-            xous::MessageEnvelope,
-            SocketHandle,
-            u16, /* fd */
-            u16, /* local_port */
-            u16, /* remote_port */
-        )>,
-    > = Vec::new();
+    let mut tcp_connect_waiting: Vec<Option<ConnectWaiting>> = Vec::new();
+
+    // the SOCKS5 proxy TCP connects go through, if one is set, and the processes that go direct anyway
+    let mut socks_proxy: Option<IpEndpoint> = None;
+    let mut socks_opt_out = HashSet::<Option<xous::PID>>::new();
+    // the proxy is read from the PDDB the first time a network is joined, by which time it's likely mounted
+    let mut socks_proxy_loaded = false;
+    // `TcpConnect` connections still waiting on the proxy to connect them on
+    let mut tcp_handshakes = HashMap::<SocketHandle, socks::Handshake>::new();
 
     // ping storage
     // up to four concurrent pings in the queue
@@ -557,6 +734,19 @@ fn xmain() -> ! {
         .bind(IcmpEndpoint::Ident(ident))
         .expect("couldn't bind to icmp socket");
     let icmp_handle = sockets.add(icmp_socket);
+    // router advertisements, for IPv6 address configuration
+    let ndisc_socket = RawSocket::new(
+        IpVersion::Ipv6,
+        IpProtocol::Icmpv6,
+        RawSocketBuffer::new(vec![RawPacketMetadata::EMPTY; 4], vec![0; 2048]),
+        RawSocketBuffer::new(vec![RawPacketMetadata::EMPTY; 1], vec![0; 128]),
+    );
+    let ndisc_handle = sockets.add(ndisc_socket);
+    let mut ipv6_state = ipv6::Ipv6State::default();
+
+    // traffic counts, by socket and by process
+    let mut net_stats = stats::NetStats::new();
+    net_stats.open(icmp_handle, None, SocketKind::Icmp, 0);
     let mut seq: u16 = 0;
     // this record stores the origin time + IP address of the outgoing ping sequence number
     let mut ping_destinations = HashMap::<PingConnection, HashMap<u16, u64>>::new();
@@ -568,14 +758,20 @@ fn xmain() -> ! {
     // for Rx, copies of a CID,SID tuple are kept for every clone is kept in a HashMap. This
     // allows for the Rx data to be cc:'d to each clone, and identified by SID upon drop
     let mut udp_clones = HashMap::<u16, HashMap<[u32; 4], CID>>::new(); // additional clones for UDP responders
+    // the local port of each socket that joined a multicast group, once per join. The interface stays in a
+    // group for as long as any socket wants it, and rejoins them all whenever it's rebuilt.
+    let mut multicast_members = HashMap::<Ipv4Address, Vec<u16>>::new();
 
     // tcp storage
     let mut tcp_handles = HashMap::<TcpConnection, TcpState>::new();
     let mut tcp_listeners = HashMap::<u16, Vec<TcpState>>::new();
 
+    // servers to tell about link and address changes, by SID: (CID, opcode)
+    let mut event_subscribers = HashMap::<[u32; 4], (CID, u32)>::new();
+
     // other link storage
     let neighbor_cache = NeighborCache::new(BTreeMap::new());
-    let ip_addrs = [IpCidr::new(Ipv4Address::UNSPECIFIED.into(), 0)];
+    let ip_addrs = unspecified_ip_addrs();
     let routes = Routes::new(BTreeMap::new());
 
     let device = device::NetPhy::new(&xns);
@@ -584,7 +780,8 @@ fn xmain() -> ! {
     let medium = device.capabilities().medium;
     let mut builder = InterfaceBuilder::new(device)
         .ip_addrs(ip_addrs)
-        .routes(routes);
+        .routes(routes)
+        .ipv4_multicast_groups(BTreeMap::new());
     if medium == Medium::Ethernet {
         builder = builder
             .ethernet_addr(EthernetAddress::from_bytes(&[0; 6]))
@@ -624,6 +821,29 @@ fn xmain() -> ! {
         }
     });
 
+    // keep the RTC in step with network time
+    let last_time_sync = Arc::new(std::sync::Mutex::new(None));
+    #[cfg(not(feature = "renode-minimal"))]
+    thread::spawn({
+        let last_time_sync = last_time_sync.clone();
+        move || {
+            time_sync::time_sync(last_time_sync);
+        }
+    });
+
+    // check for a captive portal whenever we get an address
+    let connectivity = Arc::new(AtomicU32::new(Connectivity::Unknown.to_u32().unwrap()));
+    #[cfg(not(feature = "renode-minimal"))]
+    thread::spawn({
+        let connectivity = connectivity.clone();
+        move || {
+            connectivity::connectivity(connectivity);
+        }
+    });
+
+    // scans and joins can keep the loop busy for a few seconds, so give it some slack
+    watchdog::watch_server("net", 5000, net_sid, Opcode::WatchdogPing.to_usize().unwrap());
+
     let mut cid_to_disconnect: Option<CID> = None;
     loop {
         let mut msg = xous::receive_message(net_sid).unwrap();
@@ -713,8 +933,7 @@ fn xmain() -> ! {
                             icmp_repr.emit(&mut icmp_packet, &device_caps.checksum);
                         }
                         IpAddress::Ipv6(_) => {
-                            // not sure if this is a valid thing to do, to just assign the source some number like this??
-                            let src_ipv6 = IpAddress::v6(0xfdaa, 0, 0, 0, 0, 0, 0, 1);
+                            let src_ipv6 = ipv6_source(&iface);
                             let icmp_repr = Icmpv6Repr::EchoRequest {
                                 ident,
                                 seq_no: seq,
@@ -732,6 +951,7 @@ fn xmain() -> ! {
                         _ => unimplemented!(),
                     }
                     seq += 1;
+                    net_stats.sent(icmp_handle, echo_payload.len());
                     // fire off a Pump to get the stack to actually transmit the ping; this call merely queues it for sending
                     xous::try_send_message(
                         net_conn,
@@ -824,6 +1044,7 @@ fn xmain() -> ! {
                 // Pick a random locak port using the system's TRNG
                 let local_port = (trng.get_u32().unwrap() % 16384 + 49152) as u16;
                 let pid = msg.sender.pid();
+                let proxy = socks_proxy.filter(|_| !socks_opted_out(&mut socks_opt_out, pid));
 
                 std_tcp_connect(
                     msg,
@@ -831,6 +1052,9 @@ fn xmain() -> ! {
                     &mut sockets,
                     &mut tcp_connect_waiting,
                     process_sockets.entry(pid).or_default(),
+                    &mut net_stats,
+                    proxy,
+                    timer.elapsed_ms(),
                 );
             }
 
@@ -842,6 +1066,7 @@ fn xmain() -> ! {
                     &mut sockets,
                     &mut tcp_tx_waiting,
                     process_sockets.entry(pid).or_default(),
+                    &mut net_stats,
                 );
             }
 
@@ -858,6 +1083,7 @@ fn xmain() -> ! {
                     &mut sockets,
                     &mut tcp_rx_waiting,
                     process_sockets.entry(pid).or_default(),
+                    &mut net_stats,
                 );
             }
 
@@ -881,6 +1107,7 @@ fn xmain() -> ! {
                 };
                 sockets.get::<TcpSocket>(handle).close();
                 sockets.remove(handle);
+                net_stats.close(handle);
                 if let Some(response) = msg.body.memory_message_mut() {
                     response.buf.as_slice_mut::<u8>()[0] = 0;
                 } else if !msg.body.is_blocking() && msg.body.is_blocking() {
@@ -1022,6 +1249,7 @@ fn xmain() -> ! {
             }
 
             Some(Opcode::TcpConnect) => {
+                let pid = msg.sender.pid();
                 let mut buf = unsafe {
                     Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap())
                 };
@@ -1041,7 +1269,17 @@ fn xmain() -> ! {
                     tcp_socket.set_keep_alive(Some(Duration::from_millis(keepalive)));
                 }
                 let local_port = (49152 + trng.get_u32().unwrap() % 16384) as u16;
-                match tcp_socket.connect((address, remote_port), local_port) {
+                let proxy = socks_proxy.filter(|_| !socks_opted_out(&mut socks_opt_out, pid));
+                // the connection is still known by where it's going, even when it's the proxy we connect to
+                let (endpoint, handshake) = match proxy {
+                    Some(proxy) => {
+                        let timeout = tcpspec.timeout_ms.unwrap_or(socks::DEFAULT_TIMEOUT_MS);
+                        let deadline = timer.elapsed_ms() + timeout;
+                        (proxy, Some(socks::Handshake::new(address, remote_port, deadline)))
+                    }
+                    None => (IpEndpoint::new(address, remote_port), None),
+                };
+                match tcp_socket.connect(endpoint, local_port) {
                     Ok(_) => {
                         let connection = TcpConnection {
                             remote: address,
@@ -1049,6 +1287,10 @@ fn xmain() -> ! {
                             local_port,
                         };
                         let handle = sockets.add(tcp_socket);
+                        net_stats.open(handle, pid, SocketKind::Tcp, local_port);
+                        if let Some(handshake) = handshake {
+                            tcp_handshakes.insert(handle, handshake);
+                        }
                         let sid = tcpspec.cb_sid;
                         let cid = xous::connect(SID::from_array(sid)).unwrap();
                         let tcp_cb_state = TcpState {
@@ -1173,11 +1415,20 @@ fn xmain() -> ! {
                 };
                 if let Some(tcp_state) = tcp_handles.get(&connection) {
                     let mut socket = sockets.get::<TcpSocket>(tcp_state.handle);
-                    if socket.can_send() {
+                    if tcp_handshakes.contains_key(&tcp_state.handle) {
+                        // nothing goes out until the proxy has connected us on, so it's retried like a full buffer
+                        tcp_tx.result = Some(NetMemResponse::SocketInUse);
+                    } else if socket.state() == smoltcp::socket::TcpState::Closed {
+                        // turned away by the proxy, or reset by the other end; retrying won't help
+                        tcp_tx.result = Some(NetMemResponse::LibraryError);
+                    } else if socket.can_send() {
                         tcp_tx.result = match socket.send_slice(&tcp_tx.data[..tcp_tx.len as usize])
                         {
                             Ok(octets) => {
                                 log::trace!("sent {}", octets);
+                                if octets > 0 {
+                                    net_stats.sent(tcp_state.handle, octets);
+                                }
                                 tcp_tx.len = octets as u16;
                                 Some(NetMemResponse::Sent(octets as u16))
                             }
@@ -1206,8 +1457,10 @@ fn xmain() -> ! {
                         local_port,
                     };
                     if let Some(tcp_state) = tcp_handles.remove(&connection) {
+                        tcp_handshakes.remove(&tcp_state.handle);
                         sockets.get::<TcpSocket>(tcp_state.handle).close();
                         sockets.remove(tcp_state.handle);
+                        net_stats.close(tcp_state.handle);
                         tcpspec.result = Some(NetMemResponse::Ok);
                     } else {
                         tcpspec.result = Some(NetMemResponse::Invalid);
@@ -1218,6 +1471,7 @@ fn xmain() -> ! {
                 buf.replace(tcpspec).unwrap();
             }
             Some(Opcode::TcpListen) => {
+                let pid = msg.sender.pid();
                 let mut buf = unsafe {
                     Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap())
                 };
@@ -1238,6 +1492,7 @@ fn xmain() -> ! {
                             }
                         }
                         let handle = sockets.add(tcp_socket);
+                        net_stats.open(handle, pid, SocketKind::Tcp, tcpspec.local_port);
                         let sid = tcpspec.cb_sid;
                         let cid = xous::connect(SID::from_array(sid)).unwrap();
                         log::trace!("Listener with cid {}, sid {:x?} registered", cid, sid);
@@ -1289,6 +1544,7 @@ fn xmain() -> ! {
                                     tcpspec.local_port
                                 );
                                 sockets.remove(tcp_state.handle);
+                                net_stats.close(tcp_state.handle);
                                 tcpspec.result = Some(NetMemResponse::Ok);
                                 // this may leave an empty vector in the tcp_listeners structure, but I think that's OK
                             }
@@ -1336,6 +1592,7 @@ fn xmain() -> ! {
                 buf.replace(tcpspec).unwrap();
             }
             Some(Opcode::UdpBind) => {
+                let pid = msg.sender.pid();
                 let mut buf = unsafe {
                     Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap())
                 };
@@ -1375,8 +1632,10 @@ fn xmain() -> ! {
                     match udp_socket.bind(udpspec.port) {
                         Ok(_) => {
                             let sid = SID::from_array(udpspec.cb_sid);
+                            let handle = sockets.add(udp_socket);
+                            net_stats.open(handle, pid, SocketKind::Udp, udpspec.port);
                             let udpstate = UdpState {
-                                handle: sockets.add(udp_socket),
+                                handle,
                                 cid: xous::connect(sid).unwrap(),
                                 sid,
                             };
@@ -1416,6 +1675,13 @@ fn xmain() -> ! {
                                 None => {
                                     sockets.get::<UdpSocket>(udpstate.handle).close();
                                     sockets.remove(udpstate.handle);
+                                    net_stats.close(udpstate.handle);
+                                    leave_multicast_groups(
+                                        &mut iface,
+                                        &mut multicast_members,
+                                        udpspec.port,
+                                        Instant::from_millis(timer.elapsed_ms() as i64),
+                                    );
                                     buf.replace(NetMemResponse::Ok).unwrap();
                                 }
                                 // if the clone map has entries, promote an arbitrary map entry to the primary handle
@@ -1425,6 +1691,13 @@ fn xmain() -> ! {
                                         udp_clones.remove(&udpspec.port);
                                         sockets.get::<UdpSocket>(udpstate.handle).close();
                                         sockets.remove(udpstate.handle);
+                                        net_stats.close(udpstate.handle);
+                                        leave_multicast_groups(
+                                            &mut iface,
+                                            &mut multicast_members,
+                                            udpspec.port,
+                                            Instant::from_millis(timer.elapsed_ms() as i64),
+                                        );
                                         buf.replace(NetMemResponse::Ok).unwrap();
                                     } else {
                                         // take an arbitrary key, re-insert it into the handles map.
@@ -1465,7 +1738,10 @@ fn xmain() -> ! {
                             );
                             let mut socket = sockets.get::<UdpSocket>(udpstate.handle);
                             match socket.send_slice(&udp_tx.data[..udp_tx.len as usize], endpoint) {
-                                Ok(_) => buf.replace(NetMemResponse::Sent(udp_tx.len)).unwrap(),
+                                Ok(_) => {
+                                    net_stats.sent(udpstate.handle, udp_tx.len as usize);
+                                    buf.replace(NetMemResponse::Sent(udp_tx.len)).unwrap()
+                                }
                                 _ => buf.replace(NetMemResponse::LibraryError).unwrap(),
                             }
                             // fire off a Pump to get the stack to actually transmit the ping; the send call merely queues it for sending
@@ -1519,6 +1795,53 @@ fn xmain() -> ! {
                     }
                 }
             }),
+            Some(Opcode::UdpJoinMulticast) => msg_blocking_scalar_unpack!(msg, be_octets, port, _, _, {
+                let group = Ipv4Address::from_bytes(&(be_octets as u32).to_be_bytes());
+                let port = port as u16;
+                if !udp_handles.contains_key(&port) {
+                    log::error!("multicast join for {} from unbound port {}", group, port);
+                    xous::return_scalar(msg.sender, 1).unwrap();
+                    continue;
+                }
+                if !multicast_members.contains_key(&group) {
+                    let timestamp = Instant::from_millis(timer.elapsed_ms() as i64);
+                    if let Err(e) = iface.join_multicast_group(group, timestamp) {
+                        log::error!("couldn't join multicast group {}: {:?}", group, e);
+                        xous::return_scalar(msg.sender, 1).unwrap();
+                        continue;
+                    }
+                }
+                multicast_members.entry(group).or_default().push(port);
+                xous::return_scalar(msg.sender, 0).unwrap();
+            }),
+            Some(Opcode::UdpLeaveMulticast) => msg_blocking_scalar_unpack!(msg, be_octets, port, _, _, {
+                let group = Ipv4Address::from_bytes(&(be_octets as u32).to_be_bytes());
+                let port = port as u16;
+                let ports = match multicast_members.get_mut(&group) {
+                    Some(ports) => ports,
+                    None => {
+                        xous::return_scalar(msg.sender, 1).unwrap();
+                        continue;
+                    }
+                };
+                match ports.iter().position(|&p| p == port) {
+                    Some(index) => {
+                        ports.remove(index);
+                    }
+                    None => {
+                        xous::return_scalar(msg.sender, 1).unwrap();
+                        continue;
+                    }
+                }
+                if ports.is_empty() {
+                    multicast_members.remove(&group);
+                    let timestamp = Instant::from_millis(timer.elapsed_ms() as i64);
+                    if let Err(e) = iface.leave_multicast_group(group, timestamp) {
+                        log::warn!("couldn't leave multicast group {}: {:?}", group, e);
+                    }
+                }
+                xous::return_scalar(msg.sender, 0).unwrap();
+            }),
 
             Some(Opcode::ComInterrupt) => {
                 com_int_list.clear();
@@ -1557,14 +1880,59 @@ fn xmain() -> ! {
                                 ComIntSources::BatteryCritical => {
                                     log::warn!("Battery is critical! TODO: go into SHIP mode");
                                 }
-                                ComIntSources::WlanIpConfigUpdate => {
+                                ComIntSources::Disconnect => {
+                                    notify_net_event(&mut event_subscribers, NetEvent::LinkDown, 0, 0);
+                                    // the next network has its own router and prefix
+                                    ipv6_state = ipv6::Ipv6State::default();
+                                    if let Some(config) = net_config {
+                                        set_ipv6_config(&mut iface, ipv6::link_local(config.mac), &ipv6_state);
+                                    }
+                                }
+                                ComIntSources::Connect | ComIntSources::WlanIpConfigUpdate => {
+                                    if pending == ComIntSources::Connect
+                                        && matches!(
+                                            ConnectResult::decode_u16(raw_rxlen as u16),
+                                            ConnectResult::Success
+                                        )
+                                    {
+                                        notify_net_event(&mut event_subscribers, NetEvent::LinkUp, 0, 0);
+                                    }
+                                    let profile = current_profile(&mut com, &mut pddb);
+                                    if !socks_proxy_loaded {
+                                        if let Some(pddb) = pddb.as_mut().filter(|p| p.is_mounted()) {
+                                            socks_proxy = socks::load_proxy(pddb);
+                                            socks_proxy_loaded = true;
+                                            log::info!("SOCKS5 proxy is {:?}", socks_proxy);
+                                        }
+                                    }
+                                    // a network with a static address is configured as soon as it's joined; the
+                                    // rest wait for their DHCP lease
+                                    if pending == ComIntSources::Connect
+                                        && (profile.static_ipv4.is_none()
+                                            || !matches!(
+                                                ConnectResult::decode_u16(raw_rxlen as u16),
+                                                ConnectResult::Success
+                                            ))
+                                    {
+                                        continue;
+                                    }
                                     // right now the WLAN implementation only does IPV4. So IPV6 compatibility ends here.
                                     // if IPV6 gets added to the EC/COM bus, ideally this is one of a couple spots in Xous that needs a tweak.
-                                    let config = com
+                                    let mut config = com
                                         .wlan_get_config()
                                         .expect("couldn't retrieve updated ipv4 config");
+                                    let mut prefix_len = 24;
+                                    if let Some(ipv4) = profile.static_ipv4 {
+                                        config.addr = ipv4.addr;
+                                        config.gtwy = ipv4.gateway;
+                                        prefix_len = ipv4.prefix_len;
+                                    }
+                                    if profile.dns[0] != [0, 0, 0, 0] {
+                                        config.dns1 = profile.dns[0];
+                                        config.dns2 = profile.dns[1];
+                                    }
                                     log::info!("Network config acquired: {:?}", config);
-                                    net_config = Some(config);
+                                    let previous = net_config.replace(config);
                                     let mac = EthernetAddress::from_bytes(&config.mac);
 
                                     // we need to clear the ARP cache in case we've migrated base stations (e.g. in a wireless network
@@ -1573,14 +1941,14 @@ fn xmain() -> ! {
                                     // to neatly clear the ARP cache as the BTreeMap that underlies it is moved into the container and
                                     // no "clear" API is exposed, so let's just rebuild the whole interface if we get a DHCP renewal.
                                     let neighbor_cache = NeighborCache::new(BTreeMap::new());
-                                    let ip_addrs =
-                                        [IpCidr::new(Ipv4Address::UNSPECIFIED.into(), 0)];
+                                    let ip_addrs = unspecified_ip_addrs();
                                     let routes = Routes::new(BTreeMap::new());
                                     let device = device::NetPhy::new(&xns);
                                     let medium = device.capabilities().medium;
                                     let mut builder = InterfaceBuilder::new(device)
                                         .ip_addrs(ip_addrs)
-                                        .routes(routes);
+                                        .routes(routes)
+                                        .ipv4_multicast_groups(BTreeMap::new());
                                     if medium == Medium::Ethernet {
                                         builder = builder
                                             .ethernet_addr(mac)
@@ -1595,7 +1963,7 @@ fn xmain() -> ! {
                                             config.addr[2],
                                             config.addr[3],
                                         ),
-                                        24,
+                                        prefix_len,
                                     );
                                     set_ipv4_addr(&mut iface, ip_addr);
                                     let default_v4_gw = Ipv4Address::new(
@@ -1614,7 +1982,30 @@ fn xmain() -> ! {
                                         ),
                                         Err(e) => log::error!("routing table update error: {}", e),
                                     }
+                                    // the new interface isn't in any groups; joining them again also reports our
+                                    // membership from the new address
+                                    let timestamp = Instant::from_millis(timer.elapsed_ms() as i64);
+                                    for group in multicast_members.keys() {
+                                        if let Err(e) = iface.join_multicast_group(*group, timestamp) {
+                                            log::error!(
+                                                "couldn't rejoin multicast group {}: {:?}",
+                                                group,
+                                                e
+                                            );
+                                        }
+                                    }
+                                    // IPv6 keeps what it learned from the router, as that's still the same network;
+                                    // a disconnect in between will have already cleared it
+                                    let link_local = ipv6::link_local(config.mac);
+                                    set_ipv6_config(&mut iface, link_local, &ipv6_state);
+                                    if ipv6_state.router.is_none() {
+                                        let rs = ipv6::router_solicit(link_local, config.mac);
+                                        sockets.get::<RawSocket>(ndisc_handle).send_slice(&rs).ok();
+                                    }
                                     dns_allclear_hook.notify();
+                                    for &addr in ipv6_state.dns.iter() {
+                                        notify_ipv6_dns(&mut dns_ipv6_hook, addr);
+                                    }
                                     dns_ipv4_hook.notify_custom_args([
                                         Some(u32::from_be_bytes(config.dns1)),
                                         None,
@@ -1631,6 +2022,25 @@ fn xmain() -> ! {
                                             None,
                                         ]);
                                     }
+                                    // a lease renewal usually hands back what we had, which isn't news
+                                    if previous.map(|p| p.addr) != Some(config.addr) {
+                                        notify_net_event(
+                                            &mut event_subscribers,
+                                            NetEvent::IpAcquired,
+                                            u32::from_be_bytes(config.addr) as usize,
+                                            0,
+                                        );
+                                    }
+                                    if previous.map(|p| (p.dns1, p.dns2))
+                                        != Some((config.dns1, config.dns2))
+                                    {
+                                        notify_net_event(
+                                            &mut event_subscribers,
+                                            NetEvent::DnsChanged,
+                                            u32::from_be_bytes(config.dns1) as usize,
+                                            u32::from_be_bytes(config.dns2) as usize,
+                                        );
+                                    }
                                 }
                                 ComIntSources::WlanRxReady => {
                                     activity_interval.store(0, Ordering::Relaxed); // reset the activity interval to 0
@@ -1700,10 +2110,33 @@ fn xmain() -> ! {
                     }
                 }
 
+                // connections through the proxy see none of what comes in until the proxy has connected them on
+                tcp_handshakes.retain(|&handle, handshake| {
+                    use smoltcp::socket::TcpState;
+                    let mut socket = sockets.get::<TcpSocket>(handle);
+                    let result = match socket.state() {
+                        TcpState::SynSent | TcpState::SynReceived if handshake.timed_out(now) => {
+                            Err(NetError::TimedOut)
+                        }
+                        TcpState::SynSent | TcpState::SynReceived => return true,
+                        TcpState::Established => match handshake.poll(&mut socket, now) {
+                            Ok(false) => return true,
+                            Ok(true) => Ok(()),
+                            Err(e) => Err(e),
+                        },
+                        _ => Err(NetError::LibraryError),
+                    };
+                    if let Err(e) = result {
+                        log::warn!("couldn't connect through the proxy: {:?}", e);
+                        socket.abort();
+                    }
+                    false
+                });
+
                 // this block handles TCP rx
                 {
                     for (_connection, tcp_state) in tcp_handles.iter() {
-                        if !tcp_state.shutdown_rx {
+                        if !tcp_state.shutdown_rx && !tcp_handshakes.contains_key(&tcp_state.handle) {
                             let mut socket = sockets.get::<TcpSocket>(tcp_state.handle);
                             if socket.can_recv() {
                                 match socket.recv(|data| {
@@ -1721,9 +2154,13 @@ fn xmain() -> ! {
                                         NetTcpCallback::RxData.to_u32().unwrap(),
                                     )
                                     .expect("couldn't send TCP response");
-                                    (data.len(), ())
+                                    (data.len(), data.len())
                                 }) {
-                                    Ok(_) => (),
+                                    Ok(count) => {
+                                        if count > 0 {
+                                            net_stats.received(tcp_state.handle, count);
+                                        }
+                                    }
                                     Err(e) => match e {
                                         smoltcp::Error::Illegal => {
                                             log::warn!("TCP fast open not supported");
@@ -1741,12 +2178,35 @@ fn xmain() -> ! {
                     }
                 }
 
+                // this block handles router advertisements, which give us our IPv6 address and DNS servers
+                {
+                    let mut socket = sockets.get::<RawSocket>(ndisc_handle);
+                    while socket.can_recv() {
+                        let ra = match socket.recv() {
+                            Ok(pkt) => ipv6::parse_router_advert(pkt),
+                            Err(_) => break,
+                        };
+                        if let (Some(ra), Some(config)) = (ra, net_config) {
+                            if ipv6_state.update(&ra, config.mac) {
+                                log::info!("IPv6 config updated: {:?}", ipv6_state);
+                                let link_local = ipv6::link_local(config.mac);
+                                set_ipv6_config(&mut iface, link_local, &ipv6_state);
+                                // the DNS server's hook only adds; a withdrawn server stays on its list
+                                // until the next lease renewal, when the list is built again
+                                for &addr in ipv6_state.dns.iter() {
+                                    notify_ipv6_dns(&mut dns_ipv6_hook, addr);
+                                }
+                            }
+                        }
+                    }
+                }
+
                 // Connect calls take time to establish. This block checks to see if connections
                 // have been made and issues callbacks as necessary.
                 for connection in tcp_connect_waiting.iter_mut() {
                     use smoltcp::socket::TcpState;
-                    let socket;
-                    let (env, _handle, fd, local_port, remote_port) = {
+                    let mut socket;
+                    let proxied = {
                         // If the connection is blank, or if it's still waiting to get
                         // connected, don't do anything.
                         match connection {
@@ -1758,16 +2218,33 @@ fn xmain() -> ! {
                                 {
                                     continue;
                                 }
+                                // through a proxy, we're not connected until the proxy says we are
+                                match s.5.as_mut() {
+                                    Some(handshake) if socket.state() == TcpState::Established => {
+                                        match handshake.poll(&mut socket, now) {
+                                            Ok(false) => continue,
+                                            Ok(true) => Ok(()),
+                                            Err(e) => Err(e),
+                                        }
+                                    }
+                                    _ => Ok(()),
+                                }
                             }
                         }
-                        connection.take().unwrap()
                     };
+                    let (env, _handle, fd, local_port, remote_port, _) = connection.take().unwrap();
 
                     log::trace!("tcp state is {:?}", socket.state());
-                    if socket.state() == TcpState::Established {
-                        respond_with_connected(env, fd, local_port, remote_port);
-                    } else {
-                        respond_with_error(env, NetError::TimedOut);
+                    match proxied {
+                        Ok(()) if socket.state() == TcpState::Established => {
+                            respond_with_connected(env, fd, local_port, remote_port);
+                        }
+                        Ok(()) => respond_with_error(env, NetError::TimedOut),
+                        Err(e) => {
+                            log::warn!("couldn't connect through the proxy: {:?}", e);
+                            socket.abort();
+                            respond_with_error(env, e);
+                        }
                     }
                 }
 
@@ -1776,7 +2253,7 @@ fn xmain() -> ! {
                     let mut socket;
                     let WaitingSocket {
                         mut env,
-                        handle: _,
+                        handle,
                         expiry: _,
                     } = {
                         match connection {
@@ -1809,6 +2286,9 @@ fn xmain() -> ! {
                     match socket.recv_slice(body.buf.as_slice_mut()) {
                         Ok(count) => {
                             body.valid = xous::MemorySize::new(count);
+                            if count > 0 {
+                                net_stats.received(handle, count);
+                            }
                         }
                         Err(e) => {
                             log::trace!("unable to receive: {:?}", e);
@@ -1822,7 +2302,7 @@ fn xmain() -> ! {
                     let mut socket;
                     let WaitingSocket {
                         mut env,
-                        handle: _,
+                        handle,
                         expiry: _,
                     } = {
                         match connection {
@@ -1876,6 +2356,9 @@ fn xmain() -> ! {
                     };
 
                     log::trace!("sent {}", sent_octets);
+                    if sent_octets > 0 {
+                        net_stats.sent(handle, sent_octets);
+                    }
                     let response_data = body.buf.as_slice_mut::<u32>();
                     body.valid = xous::MemorySize::new(sent_octets);
                     response_data[0] = 0;
@@ -1957,6 +2440,7 @@ fn xmain() -> ! {
                         let mut socket = sockets.get::<UdpSocket>(handle);
                         match socket.recv() {
                             Ok((data, endpoint)) => {
+                                net_stats.received(handle, data.len());
                                 log::trace!(
                                     "udp:{} recv data: {:x?} from {}",
                                     port,
@@ -2007,6 +2491,7 @@ fn xmain() -> ! {
                             .recv()
                             .expect("couldn't receive on socket despite asserting availability");
                         log::trace!("icmp payload: {:x?}", payload);
+                        net_stats.received(icmp_handle, payload.len());
                         let now = timer.elapsed_ms();
 
                         for (connection, waiting_queue) in ping_destinations.iter_mut() {
@@ -2093,8 +2578,7 @@ fn xmain() -> ! {
                                 }
 
                                 IpAddress::Ipv6(_) => {
-                                    // NOTE: actually not sure what src_ipv6 should be. This is just from an example.
-                                    let src_ipv6 = IpAddress::v6(0xfdaa, 0, 0, 0, 0, 0, 0, 1);
+                                    let src_ipv6 = ipv6_source(&iface);
                                     let icmp_packet = Icmpv6Packet::new_checked(&payload).unwrap();
                                     let icmp_repr = Icmpv6Repr::parse(
                                         &remote_addr,
@@ -2310,6 +2794,131 @@ fn xmain() -> ! {
                 };
                 buffer.replace(ser).expect("couldn't return config");
             }
+            Some(Opcode::GetIpv6Config) => {
+                let mut buffer = unsafe {
+                    Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap())
+                };
+                let config = net_config.map(|config| Ipv6Config {
+                    link_local: ipv6::link_local(config.mac).0,
+                    global: ipv6_state.global.map(|cidr| cidr.address().0),
+                    prefix_len: ipv6_state.global.map(|cidr| cidr.prefix_len()).unwrap_or(64),
+                    router: ipv6_state.router.map(|router| router.0),
+                });
+                buffer.replace(config).expect("couldn't return config");
+            }
+            Some(Opcode::GetConnectivity) => msg_blocking_scalar_unpack!(msg, _, _, _, _, {
+                xous::return_scalar(msg.sender, connectivity.load(Ordering::SeqCst) as usize)
+                    .expect("couldn't return connectivity");
+            }),
+            Some(Opcode::GetProcessStats) => {
+                let mut buffer = unsafe {
+                    Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap())
+                };
+                let mut ret = ProcessStatsList::default();
+                for (dst, src) in ret.list.iter_mut().zip(net_stats.processes().into_iter()) {
+                    *dst = Some(src);
+                }
+                buffer.replace(ret).expect("couldn't return process stats");
+            }
+            Some(Opcode::GetSocketStats) => {
+                let mut buffer = unsafe {
+                    Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap())
+                };
+                let mut page = buffer
+                    .to_original::<SocketStatsPage, _>()
+                    .expect("couldn't restore original");
+                let all = net_stats.sockets();
+                page.total = all.len() as u16;
+                page.list = [None; SOCKET_STATS_PAGE_LEN];
+                for (dst, src) in page
+                    .list
+                    .iter_mut()
+                    .zip(all.into_iter().skip(page.start as usize))
+                {
+                    *dst = Some(src);
+                }
+                buffer.replace(page).expect("couldn't return socket stats");
+            }
+            Some(Opcode::SubscribeNetEvents) => {
+                let buffer =
+                    unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let sub = buffer.to_original::<NetEventSubscription, _>().unwrap();
+                match xous::connect(SID::from_array(sub.sid)) {
+                    Ok(cid) => {
+                        if let Some((old_cid, _)) =
+                            event_subscribers.insert(sub.sid, (cid, sub.opcode))
+                        {
+                            // connecting again gave us the same CID, so this is just a new opcode
+                            if old_cid != cid {
+                                unsafe { xous::disconnect(old_cid).ok() };
+                            }
+                        }
+                    }
+                    Err(e) => log::error!("couldn't connect to net event subscriber: {:?}", e),
+                }
+            }
+            Some(Opcode::UnsubNetEvents) => msg_blocking_scalar_unpack!(msg, s0, s1, s2, s3, {
+                let sid = [s0 as u32, s1 as u32, s2 as u32, s3 as u32];
+                if let Some((cid, _)) = event_subscribers.remove(&sid) {
+                    unsafe { xous::disconnect(cid).ok() };
+                    xous::return_scalar(msg.sender, 1).unwrap();
+                } else {
+                    xous::return_scalar(msg.sender, 0).unwrap();
+                }
+            }),
+            Some(Opcode::SetSocksProxy) => msg_blocking_scalar_unpack!(msg, addr, port, _, _, {
+                if !socks::may_set_proxy(msg.sender) {
+                    log::warn!("{:?} isn't allowed to set the proxy", msg.sender.pid());
+                    xous::return_scalar(msg.sender, 0).unwrap();
+                } else {
+                    socks_proxy = if addr == 0 {
+                        None
+                    } else {
+                        let addr = Ipv4Address::from_bytes(&(addr as u32).to_be_bytes());
+                        Some(IpEndpoint::new(IpAddress::Ipv4(addr), port as u16))
+                    };
+                    log::info!("SOCKS5 proxy set to {:?}", socks_proxy);
+                    let pddb = pddb.get_or_insert_with(pddb::Pddb::new);
+                    if pddb.is_mounted() {
+                        // a proxy set now is the one that counts, whatever's stored
+                        socks_proxy_loaded = true;
+                        if let Err(e) = socks::save_proxy(pddb, socks_proxy) {
+                            log::error!("couldn't store the proxy: {:?}", e);
+                        }
+                    } else {
+                        log::warn!("PDDB isn't mounted, so the proxy lasts only until reboot");
+                    }
+                    xous::return_scalar(msg.sender, 1).unwrap();
+                }
+            }),
+            Some(Opcode::GetSocksProxy) => msg_blocking_scalar_unpack!(msg, _, _, _, _, {
+                let (addr, port) = match socks_proxy {
+                    Some(IpEndpoint {
+                        addr: IpAddress::Ipv4(addr),
+                        port,
+                    }) => (u32::from_be_bytes(addr.0), port),
+                    _ => (0, 0),
+                };
+                xous::return_scalar2(msg.sender, addr as usize, port as usize).unwrap();
+            }),
+            Some(Opcode::SocksOptOut) => msg_blocking_scalar_unpack!(msg, opt_out, _, _, _, {
+                let pid = msg.sender.pid();
+                // clears out the opt-outs of processes that have since exited
+                socks_opted_out(&mut socks_opt_out, pid);
+                if opt_out != 0 {
+                    socks_opt_out.insert(pid);
+                } else {
+                    socks_opt_out.remove(&pid);
+                }
+                xous::return_scalar(msg.sender, 1).unwrap();
+            }),
+            Some(Opcode::GetTimeSync) => {
+                let mut buffer = unsafe {
+                    Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap())
+                };
+                let record: Option<TimeSync> = *last_time_sync.lock().unwrap();
+                buffer.replace(record).expect("couldn't return time sync record");
+            }
             Some(Opcode::SubscribeWifiStats) => {
                 let buffer =
                     unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
@@ -2364,6 +2973,26 @@ fn xmain() -> ! {
                     .expect("couldn't restore original");
                 buffer.replace(ret_list).expect("couldn't return config");
             }
+            Some(Opcode::FetchScanResults) => {
+                let mut buffer = unsafe {
+                    Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap())
+                };
+                let page = buffer
+                    .to_original::<ScanResultPage, _>()
+                    .expect("couldn't restore original");
+                let mut buf = Buffer::into_buf(page).expect("couldn't convert to memory message");
+                buf.lend_mut(
+                    cm_cid,
+                    connection_manager::ConnectionManagerOpcode::FetchScanResults
+                        .to_u32()
+                        .unwrap(),
+                )
+                .expect("couldn't forward scan results request");
+                let page = buf
+                    .to_original::<ScanResultPage, _>()
+                    .expect("couldn't restore original");
+                buffer.replace(page).expect("couldn't return scan results");
+            }
             Some(Opcode::ConnMgrStartStop) => msg_scalar_unpack!(msg, code, _, _, _, {
                 if code == 0 {
                     // 0 is stop, 1 is start
@@ -2400,14 +3029,18 @@ fn xmain() -> ! {
             }),
             Some(Opcode::Reset) => {
                 net_config = None;
+                // a different network could have a different prefix, so start IPv6 over too
+                ipv6_state = ipv6::Ipv6State::default();
                 let neighbor_cache = NeighborCache::new(BTreeMap::new());
-                let ip_addrs = [IpCidr::new(Ipv4Address::UNSPECIFIED.into(), 0)];
+                let ip_addrs = unspecified_ip_addrs();
                 let routes = Routes::new(BTreeMap::new());
                 let device = device::NetPhy::new(&xns);
                 let medium = device.capabilities().medium;
+                // memberships are kept, and joined again once there's a new address
                 let mut builder = InterfaceBuilder::new(device)
                     .ip_addrs(ip_addrs)
-                    .routes(routes);
+                    .routes(routes)
+                    .ipv4_multicast_groups(BTreeMap::new());
                 if medium == Medium::Ethernet {
                     builder = builder
                         .ethernet_addr(EthernetAddress::from_bytes(&[0; 6]))
@@ -2420,6 +3053,11 @@ fn xmain() -> ! {
                 xous::return_scalar(msg.sender, 1).unwrap();
             }
             Some(Opcode::SuspendResume) => xous::msg_scalar_unpack!(msg, token, _, _, _, {
+                // While we're suspended the EC stays on the network: it keeps the association, answers ARP
+                // and renews the DHCP lease by itself. Keepalive offload isn't done: the EC can't keep our
+                // TCP sessions alive, as the COM protocol has no way to hand it keepalives, and smoltcp doesn't
+                // expose the sequence numbers a keepalive needs. Both need changing first. Until then, a session
+                // survives a suspend only if the peer doesn't time it out meanwhile.
                 com_int_list.clear();
                 com.ints_enable(&com_int_list); // disable all the interrupts
 
@@ -2437,7 +3075,17 @@ fn xmain() -> ! {
                 com_int_list.push(ComIntSources::WlanSsidScanFinished);
                 com_int_list.push(ComIntSources::WfxErr);
                 com.ints_enable(&com_int_list);
+                // keepalives and retransmits that came due while we slept go out now, rather than whenever
+                // the next packet happens to arrive
+                xous::try_send_message(
+                    net_conn,
+                    Message::new_scalar(Opcode::NetPump.to_usize().unwrap(), 0, 0, 0, 0),
+                )
+                .ok();
             }),
+            Some(Opcode::WatchdogPing) => {
+                xous::return_scalar(msg.sender, 1).expect("couldn't answer the watchdog");
+            }
             Some(Opcode::Quit) => {
                 log::warn!("quit received");
                 break;
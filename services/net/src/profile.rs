@@ -0,0 +1,128 @@
+//! Per-network settings, kept in the PDDB under `PROFILE_DICT_NAME` with the SSID as the key name. A known
+//! network without a profile gets `NetProfile::default()`: it's joined automatically, at priority 0, and takes
+//! its address by DHCP. A change to a profile takes effect the next time the network is joined.
+//!
+//! A static address is configured by the Net server once the network is joined, in place of whatever DHCP
+//! hands out; the EC may still ask for a lease, but it's no longer waited on.
+
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+pub const PROFILE_DICT_NAME: &str = "wlan.profiles";
+const PROFILE_VERSION: u8 = 1;
+const PROFILE_LEN: usize = 20;
+
+const FLAG_AUTO_JOIN: u8 = 0x1;
+const FLAG_STATIC: u8 = 0x2;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StaticIpv4 {
+    pub addr: [u8; 4],
+    /// The length of the subnet's prefix: 24 for a mask of 255.255.255.0
+    pub prefix_len: u8,
+    pub gateway: [u8; 4],
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct NetProfile {
+    /// Of the known networks in range, the one with the highest priority is joined first; networks with the
+    /// same priority are tried strongest signal first
+    pub priority: u8,
+    /// Whether the connection manager joins the network by itself
+    pub auto_join: bool,
+    /// The address to use, or `None` to take one by DHCP
+    pub static_ipv4: Option<StaticIpv4>,
+    /// DNS servers to use in place of the ones handed out by DHCP, or 0.0.0.0 to use those. Needed along with
+    /// a static address, as there's then nothing to hand any out.
+    pub dns: [[u8; 4]; 2],
+}
+
+impl Default for NetProfile {
+    fn default() -> Self {
+        NetProfile {
+            priority: 0,
+            auto_join: true,
+            static_ipv4: None,
+            dns: [[0; 4]; 2],
+        }
+    }
+}
+
+impl NetProfile {
+    fn to_bytes(&self) -> [u8; PROFILE_LEN] {
+        let mut data = [0u8; PROFILE_LEN];
+        data[0] = PROFILE_VERSION;
+        data[1] = self.priority;
+        if self.auto_join {
+            data[2] |= FLAG_AUTO_JOIN;
+        }
+        if let Some(ipv4) = self.static_ipv4 {
+            data[2] |= FLAG_STATIC;
+            data[3..7].copy_from_slice(&ipv4.addr);
+            data[7] = ipv4.prefix_len;
+            data[8..12].copy_from_slice(&ipv4.gateway);
+        }
+        data[12..16].copy_from_slice(&self.dns[0]);
+        data[16..20].copy_from_slice(&self.dns[1]);
+        data
+    }
+
+    fn from_bytes(data: &[u8]) -> Option<NetProfile> {
+        if data.len() < PROFILE_LEN || data[0] != PROFILE_VERSION {
+            return None;
+        }
+        let quad = |i: usize| [data[i], data[i + 1], data[i + 2], data[i + 3]];
+        Some(NetProfile {
+            priority: data[1],
+            auto_join: data[2] & FLAG_AUTO_JOIN != 0,
+            static_ipv4: if data[2] & FLAG_STATIC != 0 {
+                Some(StaticIpv4 {
+                    addr: quad(3),
+                    prefix_len: data[7],
+                    gateway: quad(8),
+                })
+            } else {
+                None
+            },
+            dns: [quad(12), quad(16)],
+        })
+    }
+}
+
+/// The profile stored for `ssid`, or the default one if there isn't one, or it can't be read
+pub fn load_profile(pddb: &mut pddb::Pddb, ssid: &str) -> NetProfile {
+    let mut data = [0u8; PROFILE_LEN];
+    match pddb.get(PROFILE_DICT_NAME, ssid, None, false, false, None, None::<fn()>) {
+        Ok(mut key) => match key.read(&mut data) {
+            Ok(len) => NetProfile::from_bytes(&data[..len]).unwrap_or_else(|| {
+                log::warn!("profile for {} isn't one we understand, using the default", ssid);
+                NetProfile::default()
+            }),
+            Err(_) => NetProfile::default(),
+        },
+        Err(_) => NetProfile::default(),
+    }
+}
+
+pub fn save_profile(pddb: &mut pddb::Pddb, ssid: &str, profile: &NetProfile) -> Result<()> {
+    if let Some(ipv4) = profile.static_ipv4 {
+        if ipv4.prefix_len > 32 {
+            return Err(Error::new(ErrorKind::InvalidInput, "prefix is longer than 32 bits"));
+        }
+    }
+    let mut key = pddb.get(
+        PROFILE_DICT_NAME,
+        ssid,
+        None,
+        true,
+        true,
+        Some(PROFILE_LEN),
+        None::<fn()>,
+    )?;
+    key.write_all(&profile.to_bytes())?;
+    key.flush()
+}
+
+/// Forgets the profile for `ssid`, so it goes back to the default
+pub fn delete_profile(pddb: &mut pddb::Pddb, ssid: &str) -> Result<()> {
+    pddb.delete_key(PROFILE_DICT_NAME, ssid, None)
+}
@@ -32,3 +32,23 @@ pub use tcp_listener::*;
 pub mod tcp_hosted;
 #[cfg(not(any(target_os = "none", target_os = "xous")))]
 pub use tcp_hosted::*;
+
+#[cfg(not(any(target_os = "none", target_os = "xous")))]
+pub mod segment_hosted;
+#[cfg(not(any(target_os = "none", target_os = "xous")))]
+pub use segment_hosted::*;
+
+// ring 0.16, under rustls, has no port to the device target yet, so the feature only builds hosted
+#[cfg(all(feature = "tls", any(target_os = "none", target_os = "xous")))]
+compile_error!("the `tls` feature is experimental and hosted-only until ring builds for riscv32imac-unknown-xous-elf");
+#[cfg(feature = "tls")]
+pub mod tls;
+#[cfg(feature = "tls")]
+pub use tls::*;
+
+pub mod sntp;
+pub use sntp::*;
+
+pub mod http;
+
+pub mod websocket;
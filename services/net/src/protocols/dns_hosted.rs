@@ -1,31 +1,68 @@
 use std::net::{IpAddr, Ipv4Addr};
 use std::io::Result;
+use std::collections::HashSet;
 
+/// where the host keeps its DNS servers
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+
+/// The host has no Net server to be told of DNS servers by DHCP, so the list starts out with the host's
+/// own servers, and otherwise behaves as it does on hardware.
 pub struct DnsServerManager {
+    servers: HashSet::<IpAddr>,
+    freeze: bool,
 }
 
 impl DnsServerManager {
     pub fn register(_xns: &xous_names::XousNames) -> Result<DnsServerManager> {
+        let mut servers = HashSet::new();
+        if let Ok(conf) = std::fs::read_to_string(RESOLV_CONF) {
+            for line in conf.lines() {
+                let mut words = line.split_whitespace();
+                if words.next() == Some("nameserver") {
+                    // the resolver's socket is IPv4, so IPv6 servers are no use to it
+                    if let Some(Ok(addr)) = words.next().map(|w| w.parse::<Ipv4Addr>()) {
+                        servers.insert(IpAddr::V4(addr));
+                    }
+                }
+            }
+        }
+        if servers.is_empty() {
+            log::warn!("no DNS servers found in {}, using 1.1.1.1", RESOLV_CONF);
+            servers.insert(IpAddr::V4(Ipv4Addr::new(1,  1, 1, 1)));
+        }
+        log::info!("hosted DNS servers: {:?}", servers);
         Ok(DnsServerManager {
+            servers,
+            freeze: false,
         })
     }
 
-    /// Fake function that always returns true
-    pub fn add_server(&mut self, _addr: IpAddr) -> bool {
-        true
+    /// Returns true if server was not already present, false if it's already there.
+    pub fn add_server(&mut self, addr: IpAddr) -> bool {
+        if !self.freeze {
+            self.servers.insert(addr)
+        } else {
+            false
+        }
     }
-    /// Fake function that always returns true
-    pub fn remove_server(&mut self, _addr: IpAddr) -> bool {
-        true
+    /// Returns true if the server was removed, false if the server wasn't in the table and thus couldn't be removed.
+    pub fn remove_server(&mut self, addr: IpAddr) -> bool {
+        if !self.freeze {
+            self.servers.remove(&addr)
+        } else {
+            false
+        }
     }
-    /// Fake function
     pub fn clear(&mut self) {
+        if !self.freeze {
+            self.servers.clear();
+        }
     }
-    /// Fake function
-    pub fn set_freeze(&mut self, _freeze: bool) {
+    pub fn set_freeze(&mut self, freeze: bool) {
+        self.freeze = freeze;
     }
-    /// Always returns 1.1.1.1
+    /// Get one of the DNS servers. Which one we get, we don't know!
     pub fn get_random(&self) -> Option<IpAddr> {
-        Some(IpAddr::V4(Ipv4Addr::new(1,  1, 1, 1)))
+        self.servers.iter().next().copied()
     }
 }
@@ -0,0 +1,745 @@
+//! A small HTTP/1.1 client, over `TcpStream` for `http://` URLs and `TlsStream` for `https://` ones.
+//!
+//! Each request is made on a connection of its own, which is closed once the response is in, and the whole
+//! body is read into memory, so there's a limit on how big it can be. Chunked bodies are decoded, and
+//! redirects are followed, other than from `https://` to `http://`. `https://` URLs need the `tls` feature. Content encodings such as gzip aren't
+//! asked for, and URLs with user names or IPv6 addresses in them aren't supported.
+//!
+//! `get()` and `post()` cover most uses; a `Client` can also set timeouts, limits and extra headers.
+
+use std::io::{self, BufRead, BufReader, Error, ErrorKind, Read, Write};
+
+use smoltcp::time::Duration;
+
+use crate::TcpStream;
+#[cfg(feature = "tls")]
+use crate::TlsStream;
+
+const DEFAULT_TIMEOUT_MS: u64 = 10_000;
+const DEFAULT_MAX_REDIRECTS: usize = 5;
+const DEFAULT_MAX_BODY_LEN: usize = 1024 * 1024;
+/// Longest status line, header or chunk size line a server can send
+const MAX_LINE_LEN: usize = 8192;
+const MAX_HEADERS: usize = 100;
+/// Headers that carry credentials, which aren't sent on when a redirect leads to another host
+const CREDENTIAL_HEADERS: [&str; 3] = ["Authorization", "Proxy-Authorization", "Cookie"];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+}
+impl Method {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub reason: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Where the response came from, which isn't the URL asked for if there were redirects
+    pub url: String,
+}
+impl Response {
+    /// The first header called `name`, which is matched without regard to case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// GETs `url` with a default `Client`
+pub fn get(url: &str) -> io::Result<Response> {
+    Client::new().get(url)
+}
+/// POSTs `body` to `url` with a default `Client`
+pub fn post(url: &str, content_type: &str, body: &[u8]) -> io::Result<Response> {
+    Client::new().post(url, content_type, body)
+}
+
+pub struct Client {
+    timeout: Duration,
+    max_redirects: usize,
+    max_body_len: usize,
+    headers: Vec<(String, String)>,
+}
+
+impl Client {
+    pub fn new() -> Client {
+        Client {
+            timeout: Duration::from_millis(DEFAULT_TIMEOUT_MS),
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            max_body_len: DEFAULT_MAX_BODY_LEN,
+            headers: Vec::new(),
+        }
+    }
+    /// How long to wait for a connection, and then for each read and write
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    /// How many redirects to follow before giving up; 0 hands the redirect back as the response
+    pub fn max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+    /// The biggest body that will be read; a response with a bigger one fails with `ErrorKind::InvalidData`
+    pub fn max_body_len(mut self, max_body_len: usize) -> Self {
+        self.max_body_len = max_body_len;
+        self
+    }
+    /// Adds a header to every request, such as `Authorization`. Credentials are left off requests that a
+    /// redirect sends to a different host, port or scheme from the URL asked for.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn get(&self, url: &str) -> io::Result<Response> {
+        self.request(Method::Get, url, None, &[])
+    }
+    pub fn post(&self, url: &str, content_type: &str, body: &[u8]) -> io::Result<Response> {
+        self.request(Method::Post, url, Some(content_type), body)
+    }
+
+    /// Sends a request and reads the response, following any redirects. A response with an error status,
+    /// such as 404, is still `Ok`; it's up to the caller to check `Response::status`.
+    pub fn request(
+        &self,
+        method: Method,
+        url: &str,
+        content_type: Option<&str>,
+        body: &[u8],
+    ) -> io::Result<Response> {
+        for (name, value) in self.headers.iter() {
+            if !is_token(name) || value.contains(|c: char| c == '\r' || c == '\n') {
+                return Err(Error::new(ErrorKind::InvalidInput, "invalid header"));
+            }
+        }
+        if content_type.map_or(false, |ct| ct.contains(|c: char| c.is_ascii_control())) {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid content type"));
+        }
+        let mut url = Url::parse(url)?;
+        let origin = url.origin();
+        let mut method = method;
+        let mut content_type = content_type;
+        let mut body = body;
+        let mut redirects = 0;
+        loop {
+            let credentials = url.origin() == origin;
+            let response = self.request_once(method, &url, content_type, body, credentials)?;
+            let location = match response.status {
+                301 | 302 | 303 | 307 | 308 => response.header("Location"),
+                _ => None,
+            };
+            let location = match location {
+                Some(location) if redirects < self.max_redirects => location,
+                _ => return Ok(response),
+            };
+            redirects += 1;
+            let next = url.join(location)?;
+            if url.tls && !next.tls {
+                return Err(Error::new(
+                    ErrorKind::PermissionDenied,
+                    "refusing to redirect from https to http",
+                ));
+            }
+            log::debug!("{} redirected to {}", url, next);
+            // 303 means to GET the result; 301 and 302 are taken the same way after a POST, as browsers do
+            if response.status == 303
+                || (method == Method::Post && (response.status == 301 || response.status == 302))
+            {
+                if method != Method::Head {
+                    method = Method::Get;
+                }
+                content_type = None;
+                body = &[];
+            }
+            url = next;
+        }
+    }
+
+    fn request_once(
+        &self,
+        method: Method,
+        url: &Url,
+        content_type: Option<&str>,
+        body: &[u8],
+        credentials: bool,
+    ) -> io::Result<Response> {
+        let mut stream = Stream::connect(url, self.timeout)?;
+        stream.write_all(
+            self.head(method, url, content_type, body, credentials)
+                .as_bytes(),
+        )?;
+        stream.write_all(body)?;
+        stream.flush()?;
+
+        let mut reader = BufReader::new(stream);
+        let response = read_response(&mut reader, method, self.max_body_len, url.to_string())?;
+        #[cfg(feature = "tls")]
+        if let Stream::Tls(stream) = reader.get_mut() {
+            stream.close().ok();
+        }
+        Ok(response)
+    }
+
+    /// The request line and headers, with the blank line that ends them. Headers with credentials in them
+    /// are only put in if `credentials` is set.
+    fn head(
+        &self,
+        method: Method,
+        url: &Url,
+        content_type: Option<&str>,
+        body: &[u8],
+        credentials: bool,
+    ) -> String {
+        let mut head = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: Xous\r\nAccept-Encoding: identity\r\nConnection: close\r\n",
+            method.as_str(),
+            url.path,
+            url.authority()
+        );
+        if let Some(content_type) = content_type {
+            head.push_str(&format!("Content-Type: {}\r\n", content_type));
+        }
+        if !body.is_empty() || method == Method::Post || method == Method::Put {
+            head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        for (name, value) in self.headers.iter() {
+            if !credentials
+                && CREDENTIAL_HEADERS
+                    .iter()
+                    .any(|c| c.eq_ignore_ascii_case(name))
+            {
+                continue;
+            }
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        head
+    }
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Client::new()
+    }
+}
+
+pub(crate) enum Stream {
+    Tcp(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(TlsStream),
+}
+impl Stream {
+    /// Connects to `url`'s host, over TLS if it's an `https://` URL
+    pub(crate) fn connect(url: &Url, timeout: Duration) -> io::Result<Stream> {
+        #[cfg(not(feature = "tls"))]
+        if url.tls {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "built without TLS support",
+            ));
+        }
+        #[cfg(feature = "tls")]
+        if url.tls {
+            let mut stream =
+                TlsStream::connect_xous(&url.host, (url.host.as_str(), url.port), Some(timeout))?;
+            stream.get_mut().set_read_timeout(Some(timeout))?;
+            stream.get_mut().set_write_timeout(Some(timeout))?;
+            stream.handshake()?;
+            return Ok(Stream::Tls(stream));
+        }
+        let mut stream =
+            TcpStream::connect_xous((url.host.as_str(), url.port), Some(timeout), None)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        Ok(Stream::Tcp(stream))
+    }
+
+    pub(crate) fn tcp_mut(&mut self) -> &mut TcpStream {
+        match self {
+            Stream::Tcp(stream) => stream,
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.get_mut(),
+        }
+    }
+}
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.write(buf),
+        }
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+pub(crate) struct Url {
+    pub(crate) tls: bool,
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    /// the path and query, without any fragment
+    pub(crate) path: String,
+}
+
+impl Url {
+    pub(crate) fn parse(url: &str) -> io::Result<Url> {
+        let invalid = |why| Error::new(ErrorKind::InvalidInput, why);
+        if url.contains(|c: char| c.is_ascii_control() || c == ' ') {
+            return Err(invalid("URL has spaces or control characters in it"));
+        }
+        let (tls, rest) = match url.find("://") {
+            Some(i) if url[..i].eq_ignore_ascii_case("https") => (true, &url[i + 3..]),
+            Some(i) if url[..i].eq_ignore_ascii_case("http") => (false, &url[i + 3..]),
+            _ => return Err(invalid("only http:// and https:// URLs are supported")),
+        };
+        let (authority, path) = match rest.find(|c| c == '/' || c == '?' || c == '#') {
+            Some(i) => rest.split_at(i),
+            None => (rest, "/"),
+        };
+        let path = path.split('#').next().unwrap();
+        let path = match path {
+            "" => "/".to_string(),
+            p if p.starts_with('?') => format!("/{}", p),
+            p => p.to_string(),
+        };
+        if authority.contains(|c| c == '@' || c == '[') {
+            return Err(invalid(
+                "URLs with user names or IPv6 addresses aren't supported",
+            ));
+        }
+        let (host, port) = match authority.split_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse::<u16>().map_err(|_| invalid("invalid port"))?,
+            ),
+            None => (authority, if tls { 443 } else { 80 }),
+        };
+        if host.is_empty() {
+            return Err(invalid("URL has no host"));
+        }
+        Ok(Url {
+            tls,
+            host: host.to_string(),
+            port,
+            path,
+        })
+    }
+
+    /// Where `location`, from a redirect in the response to this URL, points to
+    fn join(&self, location: &str) -> io::Result<Url> {
+        if location.contains("://") {
+            Url::parse(location)
+        } else if location.starts_with("//") {
+            Url::parse(&format!("{}:{}", self.scheme(), location))
+        } else if location.starts_with('/') {
+            Url::parse(&format!(
+                "{}://{}{}",
+                self.scheme(),
+                self.authority(),
+                location
+            ))
+        } else {
+            let dir = self.path.split('?').next().unwrap();
+            let dir = &dir[..dir.rfind('/').map_or(0, |i| i + 1)];
+            Url::parse(&format!(
+                "{}://{}/{}{}",
+                self.scheme(),
+                self.authority(),
+                dir.trim_start_matches('/'),
+                location
+            ))
+        }
+    }
+
+    /// The scheme, host and port, which have to stay the same for credentials to be sent
+    fn origin(&self) -> (bool, String, u16) {
+        (self.tls, self.host.to_ascii_lowercase(), self.port)
+    }
+
+    fn scheme(&self) -> &'static str {
+        if self.tls {
+            "https"
+        } else {
+            "http"
+        }
+    }
+
+    /// The host, with the port if it isn't the scheme's usual one
+    pub(crate) fn authority(&self) -> String {
+        if self.port == if self.tls { 443 } else { 80 } {
+            self.host.clone()
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+impl std::fmt::Display for Url {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}://{}{}", self.scheme(), self.authority(), self.path)
+    }
+}
+
+/// Whether `s` can be a header name
+pub(crate) fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Reads a line, without the line ending
+pub(crate) fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = Vec::new();
+    reader
+        .by_ref()
+        .take(MAX_LINE_LEN as u64 + 1)
+        .read_until(b'\n', &mut line)?;
+    if line.last() != Some(&b'\n') {
+        return if line.len() > MAX_LINE_LEN {
+            Err(Error::new(
+                ErrorKind::InvalidData,
+                "line in response is too long",
+            ))
+        } else {
+            Err(Error::new(ErrorKind::UnexpectedEof, "response ended early"))
+        };
+    }
+    line.pop();
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    Ok(String::from_utf8_lossy(&line).into_owned())
+}
+
+/// Reads a status line and the headers after it
+pub(crate) fn read_head(
+    reader: &mut impl BufRead,
+) -> io::Result<(u16, String, Vec<(String, String)>)> {
+    let invalid = |why| Error::new(ErrorKind::InvalidData, why);
+    let line = read_line(reader)?;
+    let mut parts = line.splitn(3, ' ');
+    if !parts
+        .next()
+        .map_or(false, |version| version.starts_with("HTTP/1."))
+    {
+        return Err(invalid("not an HTTP/1.x response"));
+    }
+    let status = parts
+        .next()
+        .and_then(|s| s.parse::<u16>().ok())
+        .ok_or_else(|| invalid("invalid status"))?;
+    let reason = parts.next().unwrap_or("").to_string();
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(reader)?;
+        if line.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err(invalid("too many headers in response"));
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| invalid("invalid header"))?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+    Ok((status, reason, headers))
+}
+
+fn read_response(
+    reader: &mut impl BufRead,
+    method: Method,
+    max_body_len: usize,
+    url: String,
+) -> io::Result<Response> {
+    let invalid = |why| Error::new(ErrorKind::InvalidData, why);
+    let too_big = || Error::new(ErrorKind::InvalidData, "response body is too big");
+    let (status, reason, headers) = loop {
+        let (status, reason, headers) = read_head(reader)?;
+        // interim responses, such as 100 Continue, come before the real one
+        if status >= 200 {
+            break (status, reason, headers);
+        }
+    };
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    };
+
+    let mut body = Vec::new();
+    if method == Method::Head || status == 204 || status == 304 {
+        // no body
+    } else if header("Transfer-Encoding").map_or(false, |te| {
+        te.rsplit(',')
+            .next()
+            .unwrap()
+            .trim()
+            .eq_ignore_ascii_case("chunked")
+    }) {
+        loop {
+            let line = read_line(reader)?;
+            let size = usize::from_str_radix(line.split(';').next().unwrap().trim(), 16)
+                .map_err(|_| invalid("invalid chunk size"))?;
+            if size == 0 {
+                // skip over any trailers
+                while !read_line(reader)?.is_empty() {}
+                break;
+            }
+            if size > max_body_len - body.len() {
+                return Err(too_big());
+            }
+            let start = body.len();
+            body.resize(start + size, 0);
+            reader.read_exact(&mut body[start..])?;
+            if !read_line(reader)?.is_empty() {
+                return Err(invalid("chunk is longer than its size"));
+            }
+        }
+    } else if let Some(len) = header("Content-Length") {
+        let len = len
+            .parse::<usize>()
+            .map_err(|_| invalid("invalid content length"))?;
+        if len > max_body_len {
+            return Err(too_big());
+        }
+        body.resize(len, 0);
+        reader.read_exact(&mut body)?;
+    } else {
+        // the body runs until the server closes the connection
+        reader
+            .by_ref()
+            .take(max_body_len as u64 + 1)
+            .read_to_end(&mut body)?;
+        if body.len() > max_body_len {
+            return Err(too_big());
+        }
+    }
+    Ok(Response {
+        status,
+        reason,
+        headers,
+        body,
+        url,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn response(text: &str, method: Method, max_body_len: usize) -> io::Result<Response> {
+        read_response(
+            &mut Cursor::new(text.as_bytes()),
+            method,
+            max_body_len,
+            "http://test/".to_string(),
+        )
+    }
+
+    #[test]
+    fn status_line_and_headers() {
+        let mut reader = Cursor::new(
+            &b"HTTP/1.1 404 Not Found\r\nContent-Type: text/plain\r\nX-Empty:\r\nSet: a:b \r\n\r\n"
+                [..],
+        );
+        let (status, reason, headers) = read_head(&mut reader).unwrap();
+        assert_eq!(status, 404);
+        assert_eq!(reason, "Not Found");
+        assert_eq!(
+            headers,
+            vec![
+                ("Content-Type".to_string(), "text/plain".to_string()),
+                ("X-Empty".to_string(), "".to_string()),
+                ("Set".to_string(), "a:b".to_string()),
+            ]
+        );
+
+        // the reason can be left out, and lines can end with a bare newline
+        let (status, reason, _) = read_head(&mut Cursor::new(&b"HTTP/1.0 200\n\n"[..])).unwrap();
+        assert_eq!((status, reason.as_str()), (200, ""));
+
+        for bad in [
+            "HTTP/2 200 OK\r\n\r\n",
+            "HTTP/1.1 OK\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nno colon\r\n\r\n",
+        ] {
+            assert_eq!(
+                read_head(&mut Cursor::new(bad.as_bytes()))
+                    .unwrap_err()
+                    .kind(),
+                ErrorKind::InvalidData
+            );
+        }
+        assert_eq!(
+            read_head(&mut Cursor::new(&b"HTTP/1.1 200 OK\r\nHost: x\r\n"[..]))
+                .unwrap_err()
+                .kind(),
+            ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn interim_responses_are_skipped() {
+        let r = response(
+            "HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 201 Created\r\ncontent-length: 2\r\n\r\nok",
+            Method::Post,
+            100,
+        )
+        .unwrap();
+        assert_eq!(r.status, 201);
+        assert_eq!(r.header("Content-Length"), Some("2"));
+        assert_eq!(r.body, b"ok");
+        assert!(r.is_success());
+    }
+
+    #[test]
+    fn content_length_and_close_delimited_bodies() {
+        let r = response(
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello, and more",
+            Method::Get,
+            100,
+        )
+        .unwrap();
+        assert_eq!(r.body, b"hello");
+        let r = response("HTTP/1.1 200 OK\r\n\r\nuntil the end", Method::Get, 100).unwrap();
+        assert_eq!(r.body, b"until the end");
+        let r = response(
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n",
+            Method::Head,
+            100,
+        )
+        .unwrap();
+        assert!(r.body.is_empty());
+
+        assert!(response(
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello",
+            Method::Get,
+            4
+        )
+        .is_err());
+        assert!(response("HTTP/1.1 200 OK\r\n\r\nhello", Method::Get, 4).is_err());
+        assert!(response(
+            "HTTP/1.1 200 OK\r\nContent-Length: five\r\n\r\nhello",
+            Method::Get,
+            100
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn chunked_bodies() {
+        let r = response(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: gzip, Chunked\r\n\r\n5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\nTrailer: x\r\n\r\n",
+            Method::Get,
+            100,
+        )
+        .unwrap();
+        assert_eq!(r.body, b"hello, world");
+
+        let chunked = |chunks: &str, max| {
+            response(
+                &format!(
+                    "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{}",
+                    chunks
+                ),
+                Method::Get,
+                max,
+            )
+        };
+        assert!(chunked("5\r\nhello\r\n0\r\n\r\n", 4).is_err());
+        assert!(chunked("3\r\nhello\r\n0\r\n\r\n", 100).is_err());
+        assert!(chunked("zz\r\nhello\r\n0\r\n\r\n", 100).is_err());
+        assert!(chunked("5\r\nhello\r\n", 100).is_err());
+    }
+
+    #[test]
+    fn redirect_locations() {
+        let base = Url::parse("https://example.com/a/b?q=1").unwrap();
+        assert_eq!(
+            base.join("c").unwrap().to_string(),
+            "https://example.com/a/c"
+        );
+        assert_eq!(
+            base.join("/d").unwrap().to_string(),
+            "https://example.com/d"
+        );
+        assert_eq!(
+            base.join("//other.org/e").unwrap().to_string(),
+            "https://other.org/e"
+        );
+        assert_eq!(
+            base.join("http://other.org:8080/f").unwrap().to_string(),
+            "http://other.org:8080/f"
+        );
+    }
+
+    #[test]
+    fn credentials_only_go_to_the_same_origin() {
+        let client = Client::new()
+            .header("Authorization", "Bearer secret")
+            .header("cookie", "a=b")
+            .header("X-Other", "1");
+        let url = Url::parse("http://example.com/").unwrap();
+        let head = client.head(Method::Get, &url, None, &[], true);
+        assert!(head.contains("Authorization: Bearer secret\r\n"));
+        assert!(head.contains("cookie: a=b\r\n"));
+        let head = client.head(Method::Get, &url, None, &[], false);
+        assert!(!head.contains("secret"));
+        assert!(!head.contains("a=b"));
+        assert!(head.contains("X-Other: 1\r\n"));
+        assert!(head.ends_with("\r\n\r\n"));
+
+        let origin = url.origin();
+        assert_eq!(url.join("/other").unwrap().origin(), origin);
+        assert_eq!(
+            Url::parse("http://EXAMPLE.com:80/x").unwrap().origin(),
+            origin
+        );
+        assert_ne!(url.join("//elsewhere.com/").unwrap().origin(), origin);
+        assert_ne!(
+            url.join("http://example.com:8080/").unwrap().origin(),
+            origin
+        );
+        assert_ne!(url.join("https://example.com/").unwrap().origin(), origin);
+    }
+}
@@ -47,6 +47,29 @@ use num_traits::*;
 /// closer to a full utility. This is unlike the UDP and TCP implementations, which
 /// are ignorant of what's being done with them.
 
+/// The outcome of a run of pings, as summed up by `Ping::blocking_stats`
+#[derive(Debug, Copy, Clone, Default)]
+pub struct PingStats {
+    pub transmitted: u32,
+    pub received: u32,
+    /// how many times a destination unreachable came back instead of a reply
+    pub unreachable: u32,
+    /// round trip times of the replies; all 0 if there weren't any
+    pub rtt_min_ms: u32,
+    pub rtt_avg_ms: u32,
+    pub rtt_max_ms: u32,
+}
+impl PingStats {
+    /// The share of the pings sent that got no reply, in percent
+    pub fn loss_percent(&self) -> u32 {
+        if self.transmitted == 0 {
+            0
+        } else {
+            (self.transmitted - self.received) * 100 / self.transmitted
+        }
+    }
+}
+
 pub struct Ping {
     net: NetConn,
     callback_server: Option<XousServerId>,
@@ -230,6 +253,71 @@ impl Ping {
         (reachable.load(Ordering::SeqCst), ping_time.load(Ordering::SeqCst))
     }
 
+    /// Sends `count` pings and blocks until they've all been answered or timed out. Like `ping`, each one is
+    /// sent `interval_ms` after the last, or as soon as the last one is done if that takes longer. The timeout
+    /// and TTL are whatever was last set through a `settings_handle`.
+    pub fn blocking_stats(remote: IpAddr, count: usize, interval_ms: usize) -> PingStats {
+        let xns = xous_names::XousNames::new().unwrap();
+        let net = NetConn::new(&xns).unwrap();
+        let tt = ticktimer_server::Ticktimer::new().unwrap();
+        let sid = xous::create_server().unwrap();
+        let mut stats = PingStats::default();
+        let mut rtt_total: u64 = 0;
+        for i in 0..count {
+            let start = tt.elapsed_ms();
+            let ping = NetPingPacket {
+                endpoint: NetIpAddr::from(remote),
+                server: XousServerId::PrivateSid(sid.to_array()),
+                return_opcode: 0,
+                sent_ok: None,
+            };
+            let mut buf = Buffer::into_buf(ping).expect("couldn't allocate memory to send Ping");
+            buf.lend_mut(net.conn(), Opcode::Ping.to_u32().unwrap()).expect("couldn't send Ping command");
+            let ret = buf.to_original::<NetPingPacket, _>().unwrap();
+            if ret.sent_ok == Some(true) {
+                stats.transmitted += 1;
+                // the Net server sends a Drop once it has nothing outstanding for us, so this ping is done with
+                loop {
+                    let msg = xous::receive_message(sid).unwrap();
+                    msg_scalar_unpack!(msg, op, _addr, _seq, elapsed, {
+                        match FromPrimitive::from_usize(op & 0xFF) {
+                            Some(NetPingCallback::Drop) => {
+                                break;
+                            }
+                            Some(NetPingCallback::NoErr) => {
+                                let rtt = elapsed as u32;
+                                if stats.received == 0 || rtt < stats.rtt_min_ms {
+                                    stats.rtt_min_ms = rtt;
+                                }
+                                stats.rtt_max_ms = stats.rtt_max_ms.max(rtt);
+                                rtt_total += rtt as u64;
+                                stats.received += 1;
+                            }
+                            Some(NetPingCallback::Unreachable) => {
+                                stats.unreachable += 1;
+                            }
+                            Some(NetPingCallback::Timeout) => {}
+                            None => {
+                                log::error!("Unknown opcode received in one-time server: {:?}", op);
+                            }
+                        }
+                    });
+                }
+            } else {
+                log::warn!("Couldn't send ping {} to {:?} (maybe ICMP socket is busy?)", i, remote);
+            }
+            let spent = (tt.elapsed_ms() - start) as usize;
+            if i + 1 < count && spent < interval_ms {
+                tt.sleep_ms(interval_ms - spent).unwrap();
+            }
+        }
+        xous::destroy_server(sid).expect("couldn't destroy one-time use server");
+        if stats.received > 0 {
+            stats.rtt_avg_ms = (rtt_total / stats.received as u64) as u32;
+        }
+        stats
+    }
+
     pub fn set_timeout(&mut self, timeout_ms: u32) {
         send_message(
             self.net.conn(),
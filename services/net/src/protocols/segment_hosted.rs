@@ -0,0 +1,83 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+/// which instance this is, counting from 1, when several are run side by side
+const INSTANCE_VAR: &str = "XOUS_NET_INSTANCE";
+/// instance N is at 127.0.100.N, which the host carries over its loopback interface
+const SEGMENT_PREFIX: [u8; 3] = [127, 0, 100];
+
+/// This instance's address on the virtual segment shared by hosted instances, or None if it's the only
+/// instance. Each instance is started with its own `XOUS_NET_INSTANCE`, and reaches the others at
+/// `segment_peer()`.
+pub fn segment_address() -> Option<Ipv4Addr> {
+    let instance = std::env::var(INSTANCE_VAR).ok()?;
+    match instance.parse::<u8>() {
+        Ok(n) if n > 0 && n < 255 => Some(segment_peer(n)),
+        _ => {
+            log::warn!("{} should be from 1 to 254, not {}; this instance isn't on the segment", INSTANCE_VAR, instance);
+            None
+        }
+    }
+}
+
+/// The address of instance `instance` on the segment
+pub fn segment_peer(instance: u8) -> Ipv4Addr {
+    Ipv4Addr::new(SEGMENT_PREFIX[0], SEGMENT_PREFIX[1], SEGMENT_PREFIX[2], instance)
+}
+
+/// Where a socket asked to be bound to `addr` is really bound. On the segment, a socket on a fixed port is
+/// bound to this instance's address in place of the unspecified or loopback address, so that instances can
+/// listen on the same port, and a peer's connection lands on the instance it was meant for. Sockets on
+/// ephemeral ports are clients, and keep the host's full network.
+pub(crate) fn segment_bind(addr: &SocketAddr) -> SocketAddr {
+    bind_on(addr, segment_address())
+}
+
+/// `segment_bind()`, for an instance at `segment`
+fn bind_on(addr: &SocketAddr, segment: Option<Ipv4Addr>) -> SocketAddr {
+    match (addr.ip(), segment) {
+        (IpAddr::V4(ip), Some(segment)) if addr.port() != 0 && (ip.is_unspecified() || ip.is_loopback()) => {
+            SocketAddr::new(IpAddr::V4(segment), addr.port())
+        }
+        _ => *addr,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bind(addr: &str, segment: Option<Ipv4Addr>) -> SocketAddr {
+        bind_on(&addr.parse().unwrap(), segment)
+    }
+
+    #[test]
+    fn fixed_ports_move_onto_the_segment() {
+        let segment = Some(segment_peer(2));
+        assert_eq!(bind("0.0.0.0:80", segment), "127.0.100.2:80".parse().unwrap());
+        assert_eq!(bind("127.0.0.1:8080", segment), "127.0.100.2:8080".parse().unwrap());
+        // anywhere in 127/8 is loopback
+        assert_eq!(bind("127.1.2.3:53", segment), "127.0.100.2:53".parse().unwrap());
+        assert_eq!(bind("127.0.100.2:53", segment), "127.0.100.2:53".parse().unwrap());
+    }
+
+    #[test]
+    fn ephemeral_ports_stay_put() {
+        let segment = Some(segment_peer(2));
+        assert_eq!(bind("0.0.0.0:0", segment), "0.0.0.0:0".parse().unwrap());
+        assert_eq!(bind("127.0.0.1:0", segment), "127.0.0.1:0".parse().unwrap());
+    }
+
+    #[test]
+    fn explicit_addresses_stay_put() {
+        let segment = Some(segment_peer(2));
+        assert_eq!(bind("192.168.1.10:80", segment), "192.168.1.10:80".parse().unwrap());
+        assert_eq!(bind("[::]:80", segment), "[::]:80".parse().unwrap());
+        assert_eq!(bind("[::1]:80", segment), "[::1]:80".parse().unwrap());
+    }
+
+    #[test]
+    fn nothing_moves_off_the_segment() {
+        assert_eq!(bind("0.0.0.0:80", None), "0.0.0.0:80".parse().unwrap());
+        assert_eq!(bind("127.0.0.1:80", None), "127.0.0.1:80".parse().unwrap());
+    }
+}
@@ -0,0 +1,97 @@
+//! A Simple Network Time Protocol (RFC 4330) client. `sntp_query()` asks one server for the time; the Net
+//! server uses it to keep the RTC in step, and `NetManager::get_time_sync()` says how that last went.
+
+use std::convert::TryInto;
+use std::io::{Error, ErrorKind, Result};
+use std::net::ToSocketAddrs;
+
+use smoltcp::time::Duration;
+
+use crate::UdpSocket;
+
+pub const NTP_PORT: u16 = 123;
+/// Seconds from the NTP epoch, 1900, to the Unix epoch, 1970
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+const NTP_PKT_LEN: usize = 48;
+
+/// The time, as told by a server
+#[derive(Debug, Copy, Clone)]
+pub struct SntpTime {
+    /// UTC when the reply came in, in ms since 1970
+    pub utc_ms: u64,
+    /// How long the reply took to come back, less the time the server spent on it. The time can be off by as
+    /// much as half of this.
+    pub round_trip_ms: u64,
+    /// `Ticktimer::elapsed_ms()` when the reply came in
+    pub ticktimer_ms: u64,
+    /// How many servers away from a reference clock the server is
+    pub stratum: u8,
+}
+
+/// An NTP timestamp, as ms since 1970. Timestamps are 32-bit seconds, so they wrap in 2036; one in the first
+/// half of the range is taken to be after the wrap.
+fn timestamp_ms(ts: &[u8]) -> u64 {
+    let secs = u32::from_be_bytes(ts[..4].try_into().unwrap()) as u64;
+    let frac = u32::from_be_bytes(ts[4..8].try_into().unwrap()) as u64;
+    let secs = if secs < 0x8000_0000 {
+        secs + (1 << 32)
+    } else {
+        secs
+    };
+    (secs - NTP_UNIX_OFFSET) * 1000 + ((frac * 1000) >> 32)
+}
+
+/// Asks `server` for the time, waiting up to `timeout` for it to answer
+pub fn sntp_query<A: ToSocketAddrs>(server: A, timeout: Duration) -> Result<SntpTime> {
+    let xns = xous_names::XousNames::new().unwrap();
+    let trng = trng::Trng::new(&xns).unwrap();
+    let ticktimer = ticktimer_server::Ticktimer::new().unwrap();
+    let local_port = (49152 + trng.get_u32().unwrap() % 16384) as u16;
+    let mut socket = UdpSocket::bind_xous(
+        format!("127.0.0.1:{}", local_port),
+        Some(NTP_PKT_LEN as u16),
+    )?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.connect_xous(server)?;
+
+    // LI 0, version 4, mode 3 (client). The transmit timestamp is ours to fill in, and comes back as the
+    // originate timestamp; a random one makes a forged reply hard to pass off.
+    let mut request = [0u8; NTP_PKT_LEN];
+    request[0] = 0x23;
+    let nonce = (trng.get_u32().unwrap() as u64) << 32 | trng.get_u32().unwrap() as u64;
+    request[40..48].copy_from_slice(&nonce.to_be_bytes());
+
+    let sent_ms = ticktimer.elapsed_ms();
+    socket.send(&request)?;
+    let mut reply = [0u8; NTP_PKT_LEN];
+    let len = socket.recv(&mut reply)?;
+    let ticktimer_ms = ticktimer.elapsed_ms();
+    let elapsed_ms = ticktimer_ms - sent_ms;
+
+    if len < NTP_PKT_LEN || reply[0] & 0x7 != 4 || reply[24..32] != request[40..48] {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "not a reply to our request",
+        ));
+    }
+    if reply[0] >> 6 == 3 {
+        return Err(Error::new(ErrorKind::Other, "server isn't synchronized"));
+    }
+    let stratum = reply[1];
+    if stratum == 0 {
+        // a "kiss-o'-death": the server wants us to go away, or at least slow down
+        return Err(Error::new(
+            ErrorKind::ConnectionRefused,
+            "server refused the request",
+        ));
+    }
+    let received = timestamp_ms(&reply[32..40]);
+    let transmitted = timestamp_ms(&reply[40..48]);
+    let round_trip_ms = elapsed_ms.saturating_sub(transmitted.saturating_sub(received));
+    Ok(SntpTime {
+        utc_ms: transmitted + round_trip_ms / 2,
+        round_trip_ms,
+        ticktimer_ms,
+        stratum,
+    })
+}
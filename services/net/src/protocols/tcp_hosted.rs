@@ -1,25 +1,49 @@
-use std::net::{SocketAddr, ToSocketAddrs};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
 use std::io;
 use std::io::{Error, ErrorKind, Result};
 use std::io::{Read, Write};
 use std::net::TcpStream as TcpStreamHosted;
 use smoltcp::time::Duration;
 use std::net::Shutdown;
-use std::unimplemented;
 use std::net::TcpListener as TcpListenerHosted;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use crate::api::XousScalarEndpoint;
+
+/// how long the threads below wait before trying a non-blocking socket again
+const POLL_INTERVAL_MS: u64 = 10;
+
+/// The host has no way to wait on a socket without reading it, so once a scalar notification is asked for,
+/// a thread does the reading and sends the notification whenever data arrives, like the Net server does.
+struct RxPump {
+    rx_buf: Mutex<VecDeque<u8>>,
+    notify: Mutex<XousScalarEndpoint>,
+    /// the far end closed, or the stream failed
+    eof: AtomicBool,
+}
 
 pub struct TcpStream {
     stream: TcpStreamHosted,
+    /// set once `set_scalar_notification()` is called: from then on, data is read from here
+    pump: Option<Arc<RxPump>>,
+    nonblocking: bool,
 }
 
 impl TcpStream {
+    fn from_hosted(stream: TcpStreamHosted) -> TcpStream {
+        TcpStream {
+            stream,
+            pump: None,
+            nonblocking: false,
+        }
+    }
     pub fn connect(maybe_socket: io::Result<&SocketAddr>) -> io::Result<TcpStream> {
         if let Ok(socket) = maybe_socket {
             match TcpStreamHosted::connect(socket) {
                 Ok(stream) => {
-                    Ok(TcpStream {
-                        stream,
-                    })
+                    Ok(TcpStream::from_hosted(stream))
                 },
                 Err(e) => Err(e),
             }
@@ -32,9 +56,7 @@ impl TcpStream {
             let d = std::time::Duration::from_millis(duration.total_millis());
             match TcpStreamHosted::connect_timeout(socket, d) {
                 Ok(stream) => {
-                    Ok(TcpStream {
-                        stream,
-                    })
+                    Ok(TcpStream::from_hosted(stream))
                 },
                 Err(e) => Err(e),
             }
@@ -53,9 +75,7 @@ impl TcpStream {
                         Some(socket_addr) => {
                             match TcpStreamHosted::connect_timeout(&socket_addr, d) {
                                 Ok(stream) => {
-                                    Ok(TcpStream {
-                                        stream,
-                                    })
+                                    Ok(TcpStream::from_hosted(stream))
                                 },
                                 Err(e) => Err(e),
                             }
@@ -68,21 +88,94 @@ impl TcpStream {
         } else {
             match TcpStreamHosted::connect(addr) {
                 Ok(stream) => {
-                    Ok(TcpStream {
-                        stream,
-                    })
+                    Ok(TcpStream::from_hosted(stream))
                 },
                 Err(e) => Err(e),
             }
         }
     }
-    /// dev note: if you need this function in hosted mode, contact bunnie.
-    pub fn set_scalar_notification(&mut self, _cid: xous::CID, _op: usize, _args: [Option<usize>; 4]) {
-        unimplemented!()
+    pub fn set_scalar_notification(&mut self, cid: xous::CID, op: usize, args: [Option<usize>; 4]) {
+        if self.pump.is_none() {
+            match self.start_pump() {
+                Ok(pump) => self.pump = Some(pump),
+                Err(e) => {
+                    log::error!("couldn't start reading the stream for notifications: {:?}", e);
+                    return;
+                }
+            }
+        }
+        if let Some(pump) = &self.pump {
+            pump.notify.lock().unwrap().set(cid, op, args);
+        }
     }
-    /// dev note: if you need this function in hosted mode, contact bunnie.
     pub fn clear_scalar_notification(&mut self) {
-        unimplemented!()
+        if let Some(pump) = &self.pump {
+            pump.notify.lock().unwrap().clear();
+        }
+    }
+    fn start_pump(&self) -> io::Result<Arc<RxPump>> {
+        let mut stream = self.stream.try_clone()?;
+        let pump = Arc::new(RxPump {
+            rx_buf: Mutex::new(VecDeque::new()),
+            notify: Mutex::new(XousScalarEndpoint::new()),
+            eof: AtomicBool::new(false),
+        });
+        thread::spawn({
+            let pump = pump.clone();
+            move || {
+                let mut buf = [0u8; 4096];
+                loop {
+                    match stream.read(&mut buf) {
+                        Ok(0) => break,
+                        Ok(len) => {
+                            pump.rx_buf.lock().unwrap().extend(&buf[..len]);
+                            pump.notify.lock().unwrap().notify();
+                        }
+                        // the socket is shared with the TcpStream, so its timeouts and non-blocking mode apply here too
+                        Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+                            thread::sleep(std::time::Duration::from_millis(POLL_INTERVAL_MS));
+                        }
+                        Err(e) if e.kind() == ErrorKind::Interrupted => (),
+                        Err(e) => {
+                            log::debug!("hosted stream read ended: {:?}", e);
+                            break;
+                        }
+                    }
+                }
+                pump.eof.store(true, Ordering::SeqCst);
+                // wake the reader up, so it sees the stream has ended
+                pump.notify.lock().unwrap().notify();
+            }
+        });
+        Ok(pump)
+    }
+    /// Reads what the pump has gathered, waiting as the stream's timeout and non-blocking mode say
+    fn read_pump(&self, pump: &RxPump, buf: &mut [u8]) -> io::Result<usize> {
+        let deadline = self.stream.read_timeout()?.map(|t| std::time::Instant::now() + t);
+        loop {
+            {
+                let mut rx_buf = pump.rx_buf.lock().unwrap();
+                if !rx_buf.is_empty() || buf.is_empty() {
+                    let len = rx_buf.len().min(buf.len());
+                    for (dst, src) in buf.iter_mut().zip(rx_buf.drain(..len)) {
+                        *dst = src;
+                    }
+                    return Ok(len);
+                }
+            }
+            if pump.eof.load(Ordering::SeqCst) {
+                return Ok(0);
+            }
+            if self.nonblocking {
+                return Err(Error::new(ErrorKind::WouldBlock, "no data available"));
+            }
+            if let Some(deadline) = deadline {
+                if std::time::Instant::now() >= deadline {
+                    return Err(Error::new(ErrorKind::WouldBlock, "TCP Rx timeout reached"));
+                }
+            }
+            thread::sleep(std::time::Duration::from_millis(POLL_INTERVAL_MS));
+        }
     }
 
     pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
@@ -163,6 +256,7 @@ impl TcpStream {
     }
 
     pub fn set_nonblocking(&mut self, setting: bool) -> io::Result<()> {
+        self.nonblocking = setting;
         self.stream.set_nonblocking(setting)
     }
 
@@ -170,7 +264,10 @@ impl TcpStream {
         match self.stream.try_clone() {
             Ok(stream) => {
                 Ok(TcpStream {
-                    stream
+                    stream,
+                    // data that's been read already has to reach the duplicate too
+                    pump: self.pump.clone(),
+                    nonblocking: self.nonblocking,
                 })
             },
             Err(e) => Err(e)
@@ -178,13 +275,38 @@ impl TcpStream {
     }
 
     pub fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
-        self.stream.peek(buf)
+        if let Some(pump) = &self.pump {
+            let rx_buf = pump.rx_buf.lock().unwrap();
+            let len = rx_buf.len().min(buf.len());
+            for (dst, &src) in buf.iter_mut().zip(rx_buf.iter()) {
+                *dst = src;
+            }
+            Ok(len)
+        } else {
+            self.stream.peek(buf)
+        }
     }
 }
 
 impl Read for TcpStream {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.stream.read(buf)
+        if let Some(pump) = &self.pump {
+            self.read_pump(pump, buf)
+        } else {
+            self.stream.read(buf)
+        }
+    }
+}
+
+impl Drop for TcpStream {
+    fn drop(&mut self) {
+        if let Some(pump) = &self.pump {
+            // the pump's thread holds a copy of the socket, so it would stay open after the last TcpStream is
+            // gone. Shutting it down closes it for the far end, and ends the thread's read.
+            if Arc::strong_count(pump) <= 2 && !pump.eof.load(Ordering::SeqCst) {
+                self.stream.shutdown(Shutdown::Both).ok();
+            }
+        }
     }
 }
 
@@ -202,20 +324,36 @@ impl Write for TcpStream {
 
 
 
+/// Connections accepted by a thread, once a scalar notification is asked for, for `accept()` to hand out
+struct AcceptPump {
+    incoming: Mutex<VecDeque<(TcpStreamHosted, SocketAddr)>>,
+    notify: Mutex<XousScalarEndpoint>,
+    /// set when the listener is dropped, to stop the thread
+    closed: AtomicBool,
+}
+
 pub struct TcpListener {
     listener: TcpListenerHosted,
+    /// set once `set_scalar_notification()` is called: from then on, connections are taken from here
+    pump: Option<Arc<AcceptPump>>,
+    nonblocking: bool,
 }
 
 impl TcpListener {
+    fn from_hosted(listener: TcpListenerHosted) -> TcpListener {
+        TcpListener {
+            listener,
+            pump: None,
+            nonblocking: false,
+        }
+    }
     pub fn bind(maybe_socket: io::Result<&SocketAddr>) -> io::Result<TcpListener> {
         match maybe_socket {
             Ok(socket) => {
-                match TcpListenerHosted::bind(socket) {
+                match TcpListenerHosted::bind(crate::segment_bind(socket)) {
                     Ok(listener) => {
                         Ok(
-                            TcpListener {
-                                listener,
-                            }
+                            TcpListener::from_hosted(listener)
                         )
                     }
                     Err(e) => Err(e)
@@ -226,32 +364,88 @@ impl TcpListener {
     }
 
     pub fn bind_xous<A: ToSocketAddrs>(addr: A) -> Result<TcpListener> {
-        match TcpListenerHosted::bind(addr) {
+        let addrs: Vec<SocketAddr> = addr.to_socket_addrs()?.map(|a| crate::segment_bind(&a)).collect();
+        match TcpListenerHosted::bind(&addrs[..]) {
             Ok(listener) => {
                 Ok(
-                    TcpListener {
-                        listener,
-                    }
+                    TcpListener::from_hosted(listener)
                 )
             }
             Err(e) => Err(e)
         }
     }
 
-    pub fn set_scalar_notification(&mut self, _cid: xous::CID, _op: usize, _args: [Option<usize>; 4]) {
-        unimplemented!()
+    pub fn set_scalar_notification(&mut self, cid: xous::CID, op: usize, args: [Option<usize>; 4]) {
+        if self.pump.is_none() {
+            match self.start_pump() {
+                Ok(pump) => self.pump = Some(pump),
+                Err(e) => {
+                    log::error!("couldn't start accepting for notifications: {:?}", e);
+                    return;
+                }
+            }
+        }
+        if let Some(pump) = &self.pump {
+            pump.notify.lock().unwrap().set(cid, op, args);
+        }
     }
     pub fn clear_scalar_notification(&mut self) {
-        unimplemented!()
+        if let Some(pump) = &self.pump {
+            pump.notify.lock().unwrap().clear();
+        }
+    }
+    fn start_pump(&self) -> io::Result<Arc<AcceptPump>> {
+        let listener = self.listener.try_clone()?;
+        let pump = Arc::new(AcceptPump {
+            incoming: Mutex::new(VecDeque::new()),
+            notify: Mutex::new(XousScalarEndpoint::new()),
+            closed: AtomicBool::new(false),
+        });
+        thread::spawn({
+            let pump = pump.clone();
+            move || {
+                loop {
+                    let accepted = listener.accept();
+                    if pump.closed.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    match accepted {
+                        Ok(connection) => {
+                            pump.incoming.lock().unwrap().push_back(connection);
+                            pump.notify.lock().unwrap().notify();
+                        }
+                        Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::Interrupted => {
+                            thread::sleep(std::time::Duration::from_millis(POLL_INTERVAL_MS));
+                        }
+                        Err(e) => {
+                            log::error!("hosted listener stopped accepting: {:?}", e);
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+        Ok(pump)
     }
 
     pub fn accept(&mut self) -> io::Result<(TcpStream, SocketAddr)> {
+        if let Some(pump) = &self.pump {
+            loop {
+                if let Some((stream, addr)) = pump.incoming.lock().unwrap().pop_front() {
+                    // the accepted socket inherits the listener's non-blocking mode on some hosts
+                    stream.set_nonblocking(false)?;
+                    return Ok((TcpStream::from_hosted(stream), addr));
+                }
+                if self.nonblocking {
+                    return Err(Error::new(ErrorKind::WouldBlock, "no connection waiting"));
+                }
+                thread::sleep(std::time::Duration::from_millis(POLL_INTERVAL_MS));
+            }
+        }
         match self.listener.accept() {
             Ok((stream, addr)) => {
                 Ok((
-                    TcpStream {
-                        stream,
-                    },
+                    TcpStream::from_hosted(stream),
                     addr
                 ))
             }
@@ -278,6 +472,7 @@ impl TcpListener {
     }
 
     pub fn set_nonblocking(&mut self, setting: bool) -> io::Result<()> {
+        self.nonblocking = setting;
         self.listener.set_nonblocking(setting)
     }
 
@@ -285,3 +480,18 @@ impl TcpListener {
         Err(Error::new(ErrorKind::Other, "Xous does not support cloned listeners"))
     }
 }
+
+impl Drop for TcpListener {
+    fn drop(&mut self) {
+        if let Some(pump) = &self.pump {
+            pump.closed.store(true, Ordering::SeqCst);
+            // the thread may be blocked in accept(), which nothing but a connection will end
+            if let Ok(mut addr) = self.listener.local_addr() {
+                if addr.ip().is_unspecified() {
+                    addr.set_ip(if addr.is_ipv4() { Ipv4Addr::LOCALHOST.into() } else { Ipv6Addr::LOCALHOST.into() });
+                }
+                TcpStreamHosted::connect(addr).ok();
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! A TLS 1.2/1.3 client, layered over `TcpStream` with rustls. A `TlsStream` reads and writes like the
+//! `TcpStream` under it, with the handshake done as part of the first read or write.
+//!
+//! This is experimental, and only builds hosted: rustls needs ring, and ring 0.16 doesn't build for
+//! riscv32imac-unknown-xous-elf, so this isn't part of any hardware image yet.
+//!
+//! Servers are checked against the Mozilla root store that's built in, plus any roots that have been added
+//! to the PDDB with `trust_root()`. Roots are kept in the `tls.trusted` dictionary, one DER certificate per
+//! key, so they can be added and taken away without a new build. A root trusted this way is trusted by every
+//! app, so the user is asked before one is added.
+
+use std::convert::TryFrom;
+use std::io::{self, Error, ErrorKind, Read, Write};
+use std::net::ToSocketAddrs;
+use std::sync::Arc;
+
+use locales::t;
+use sha2::Digest;
+use smoltcp::time::Duration;
+
+use crate::TcpStream;
+
+/// PDDB dictionary of roots trusted on top of the built-in ones
+pub const TLS_TRUST_DICT: &str = "tls.trusted";
+
+/// The roots a server's certificate has to chain up to: the built-in ones, and those in the PDDB if it's
+/// mounted. A root in the PDDB that doesn't parse is skipped.
+pub fn root_store() -> rustls::RootCertStore {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|ta| {
+        rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(ta.subject, ta.spki, ta.name_constraints)
+    }));
+    let mut pddb = pddb::Pddb::new();
+    if !pddb.is_mounted() {
+        return roots;
+    }
+    for name in pddb.list_keys(TLS_TRUST_DICT, None).unwrap_or_default() {
+        let mut der = Vec::new();
+        let read = pddb
+            .get(TLS_TRUST_DICT, &name, None, false, false, None, None::<fn()>)
+            .and_then(|mut key| key.read_to_end(&mut der));
+        match read.map(|_| roots.add(&rustls::Certificate(der))) {
+            Ok(Ok(())) => log::debug!("trusting root {}", name),
+            Ok(Err(e)) => log::warn!("skipping root {}, which doesn't parse: {:?}", name, e),
+            Err(e) => log::warn!("couldn't read root {}: {:?}", name, e),
+        }
+    }
+    roots
+}
+
+/// Trust `der`, a DER-encoded root certificate, from now on, under `name`, once the user agrees to it. A root
+/// already saved under that name is replaced. Gives `ErrorKind::PermissionDenied` if the user says no.
+pub fn trust_root(name: &str, der: &[u8]) -> io::Result<()> {
+    webpki::TrustAnchor::try_from_cert_der(der).map_err(|_| Error::new(ErrorKind::InvalidData, "not a certificate"))?;
+    if !user_trusts(name, der)? {
+        return Err(Error::new(ErrorKind::PermissionDenied, "the user didn't trust the root"));
+    }
+    let mut pddb = pddb::Pddb::new();
+    pddb.delete_key(TLS_TRUST_DICT, name, None).ok();
+    let mut key = pddb.get(TLS_TRUST_DICT, name, None, true, true, Some(der.len()), None::<fn()>)?;
+    key.write_all(der)?;
+    key.flush()
+}
+
+/// Asks the user whether to trust `der`, showing its name and SHA-256 fingerprint
+fn user_trusts(name: &str, der: &[u8]) -> io::Result<bool> {
+    let xns = xous_names::XousNames::new().unwrap();
+    let modals = modals::Modals::new(&xns).map_err(|_| Error::new(ErrorKind::Other, "couldn't reach modals"))?;
+    let fingerprint: String = sha2::Sha256::digest(der).iter().map(|b| format!("{:02x}", b)).collect();
+    let yes = t!("net.tls_trust_yes", xous::LANG);
+    let no = t!("net.tls_trust_no", xous::LANG);
+    let question = format!("{}\n\n{}\nSHA-256 {}", t!("net.tls_trust_prompt", xous::LANG), name, fingerprint);
+    let asked = modals.add_list_item(yes).and(modals.add_list_item(no)).and_then(|_| modals.get_radiobutton(&question));
+    match asked {
+        Ok(answer) => Ok(answer == yes),
+        Err(_) => Err(Error::new(ErrorKind::Other, "couldn't ask the user")),
+    }
+}
+
+/// Stop trusting the root saved under `name`. Built-in roots can't be taken away.
+pub fn untrust_root(name: &str) -> io::Result<()> {
+    pddb::Pddb::new().delete_key(TLS_TRUST_DICT, name, None)
+}
+
+pub struct TlsStream {
+    stream: rustls::StreamOwned<rustls::ClientConnection, TcpStream>,
+}
+
+impl TlsStream {
+    /// Starts a TLS session with `host` over a `TcpStream` that's already connected to it. `host` is the name
+    /// the server's certificate has to be for.
+    pub fn new(host: &str, sock: TcpStream) -> io::Result<TlsStream> {
+        TlsStream::with_roots(host, sock, root_store())
+    }
+    /// Like `new()`, but checks the server against `roots` only
+    pub fn with_roots(host: &str, sock: TcpStream, roots: rustls::RootCertStore) -> io::Result<TlsStream> {
+        let config = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let server_name = rustls::ServerName::try_from(host)
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "invalid host name"))?;
+        let conn = rustls::ClientConnection::new(Arc::new(config), server_name)
+            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+        Ok(TlsStream {
+            stream: rustls::StreamOwned::new(conn, sock),
+        })
+    }
+    /// Connects to `addr`, which is `host` already looked up, and starts a TLS session with it
+    pub fn connect_xous<A: ToSocketAddrs>(host: &str, addr: A, timeout: Option<Duration>) -> io::Result<TlsStream> {
+        TlsStream::new(host, TcpStream::connect_xous(addr, timeout, None)?)
+    }
+    /// Finishes the handshake now, rather than on the first read or write, so a server that can't be trusted
+    /// is found out before anything is sent to it
+    pub fn handshake(&mut self) -> io::Result<()> {
+        while self.stream.conn.is_handshaking() {
+            self.stream.conn.complete_io(&mut self.stream.sock)?;
+        }
+        Ok(())
+    }
+    /// The `TcpStream` under the session, for setting timeouts and the like
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.stream.sock
+    }
+    pub fn get_mut(&mut self) -> &mut TcpStream {
+        &mut self.stream.sock
+    }
+    /// Ends the session, letting the server know that no more data is coming
+    pub fn close(&mut self) -> io::Result<()> {
+        self.stream.conn.send_close_notify();
+        self.stream.flush()
+    }
+}
+
+impl Read for TlsStream {
+    /// A server that hangs up without closing the session first gives `ErrorKind::UnexpectedEof`, as what it
+    /// sent may have been cut short.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.read(buf)
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
@@ -394,7 +394,38 @@ impl UdpSocket {
         Ok(())
     }
 
-    //////// the remaining functions are currently unimplemented: we don't have multicast or broadcast support //////////
+    /// Joins an IPv4 multicast group, so the group's traffic to this socket's port is received. The Net server
+    /// stays in the group until this socket leaves it or is closed, or longer if other sockets joined it too.
+    /// `interface` is ignored, as there is only the one.
+    pub fn join_multicast_v4(&self, multiaddr: &Ipv4Addr, _interface: &Ipv4Addr) -> io::Result<()> {
+        self.multicast_op(Opcode::UdpJoinMulticast, multiaddr)
+    }
+
+    pub fn leave_multicast_v4(&self, multiaddr: &Ipv4Addr, _interface: &Ipv4Addr) -> io::Result<()> {
+        self.multicast_op(Opcode::UdpLeaveMulticast, multiaddr)
+    }
+
+    fn multicast_op(&self, op: Opcode, multiaddr: &Ipv4Addr) -> io::Result<()> {
+        if !multiaddr.is_multicast() {
+            return Err(Error::new(ErrorKind::InvalidInput, "not a multicast address"));
+        }
+        let be_octets = u32::from_be_bytes(multiaddr.octets());
+        match send_message(
+            self.net.conn(),
+            Message::new_blocking_scalar(
+                op.to_usize().unwrap(),
+                be_octets as usize,
+                self.socket_addr.port() as usize,
+                0,
+                0,
+            )
+        ) {
+            Ok(xous::Result::Scalar1(0)) => Ok(()),
+            _ => Err(Error::new(ErrorKind::Other, "Net server couldn't change multicast membership")),
+        }
+    }
+
+    //////// the remaining functions are currently unimplemented: we don't have broadcast support //////////
     pub fn set_broadcast(&self, _: bool) -> io::Result<()> {
         unimplemented!("work in progress")
     }
@@ -419,14 +450,6 @@ impl UdpSocket {
         unimplemented!("work in progress")
     }
 
-    pub fn join_multicast_v4(&self, _: &Ipv4Addr, _: &Ipv4Addr) -> io::Result<()> {
-        unimplemented!("work in progress")
-    }
-
-    pub fn leave_multicast_v4(&self, _: &Ipv4Addr, _: &Ipv4Addr) -> io::Result<()> {
-        unimplemented!("work in progress")
-    }
-
     pub fn set_multicast_loop_v6(&self, _: bool) -> io::Result<()> {
         unimplemented!("ipv6 not implemented")
     }
@@ -1,5 +1,4 @@
 use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
-use std::unimplemented;
 use std::io;
 use std::io::{Error, ErrorKind, Result};
 use std::net::UdpSocket as UdpSocketHosted;
@@ -58,7 +57,7 @@ impl UdpSocket {
         }
     }
     fn bind_inner(socket: &SocketAddr) -> Result<UdpSocket> {
-        if let Ok(udpsocket_naked) = UdpSocketHosted::bind(socket) {
+        if let Ok(udpsocket_naked) = UdpSocketHosted::bind(crate::segment_bind(socket)) {
             let rx_buf = Arc::new(Mutex::new(Vec::new()));
             let notify = Arc::new(Mutex::new(XousScalarEndpoint::new()));
             let should_drop = Arc::new(AtomicBool::new(false));
@@ -76,13 +75,16 @@ impl UdpSocket {
                         if should_drop.load(Ordering::Relaxed) {
                             break;
                         } else {
-                            if let Ok((len, addr)) = udpsocket.lock().unwrap().recv_from(&mut buf) {
+                            let received = udpsocket.lock().unwrap().recv_from(&mut buf);
+                            if let Ok((len, addr)) = received {
                                 log::info!("received {} bytes from {:?}", len, addr);
                                 rx_buf.lock().unwrap().push(UdpRx { data: buf[..len].to_vec(), from: addr });
                                 notify.lock().unwrap().notify(); // this will only notify if a destination has been set
+                            } else {
+                                // nothing waiting: give some time for things to not deadlock. Packets that are waiting
+                                // are taken back to back, so a burst isn't held to one packet per sleep.
+                                tt.sleep_ms(100).unwrap();
                             }
-                            // give some time for things to not deadlock
-                            tt.sleep_ms(100).unwrap();
                         }
                     }
                 }
@@ -202,6 +204,9 @@ impl UdpSocket {
                     socket_addr
                 ));
             }
+            if self.nonblocking {
+                return Err(Error::new(ErrorKind::WouldBlock, "no UDP packet waiting"));
+            }
             if timeout < self.ticktimer.elapsed_ms() {
                 return Err(Error::new(ErrorKind::WouldBlock, "UDP Rx timeout reached"));
             }
@@ -264,53 +269,52 @@ impl UdpSocket {
         Ok(())
     }
 
-    //////// the remaining functions are currently unimplemented: we don't have multicast or broadcast support //////////
-    pub fn set_broadcast(&self, _: bool) -> io::Result<()> {
-        unimplemented!("work in progress")
+    pub fn set_broadcast(&self, setting: bool) -> io::Result<()> {
+        self.socket.lock().unwrap().set_broadcast(setting)
     }
 
     pub fn broadcast(&self) -> io::Result<bool> {
-        unimplemented!("work in progress")
+        self.socket.lock().unwrap().broadcast()
     }
 
-    pub fn set_multicast_loop_v4(&self, _: bool) -> io::Result<()> {
-        unimplemented!("work in progress")
+    pub fn set_multicast_loop_v4(&self, setting: bool) -> io::Result<()> {
+        self.socket.lock().unwrap().set_multicast_loop_v4(setting)
     }
 
     pub fn multicast_loop_v4(&self) -> io::Result<bool> {
-        unimplemented!("work in progress")
+        self.socket.lock().unwrap().multicast_loop_v4()
     }
 
-    pub fn set_multicast_ttl_v4(&self, _: u32) -> io::Result<()> {
-        unimplemented!("work in progress")
+    pub fn set_multicast_ttl_v4(&self, ttl: u32) -> io::Result<()> {
+        self.socket.lock().unwrap().set_multicast_ttl_v4(ttl)
     }
 
     pub fn multicast_ttl_v4(&self) -> io::Result<u32> {
-        unimplemented!("work in progress")
+        self.socket.lock().unwrap().multicast_ttl_v4()
     }
 
-    pub fn join_multicast_v4(&self, _: &Ipv4Addr, _: &Ipv4Addr) -> io::Result<()> {
-        unimplemented!("work in progress")
+    pub fn join_multicast_v4(&self, multiaddr: &Ipv4Addr, interface: &Ipv4Addr) -> io::Result<()> {
+        self.socket.lock().unwrap().join_multicast_v4(multiaddr, interface)
     }
 
-    pub fn leave_multicast_v4(&self, _: &Ipv4Addr, _: &Ipv4Addr) -> io::Result<()> {
-        unimplemented!("work in progress")
+    pub fn leave_multicast_v4(&self, multiaddr: &Ipv4Addr, interface: &Ipv4Addr) -> io::Result<()> {
+        self.socket.lock().unwrap().leave_multicast_v4(multiaddr, interface)
     }
 
-    pub fn set_multicast_loop_v6(&self, _: bool) -> io::Result<()> {
-        unimplemented!("ipv6 not implemented")
+    pub fn set_multicast_loop_v6(&self, setting: bool) -> io::Result<()> {
+        self.socket.lock().unwrap().set_multicast_loop_v6(setting)
     }
 
     pub fn multicast_loop_v6(&self) -> io::Result<bool> {
-        unimplemented!("ipv6 not implemented")
+        self.socket.lock().unwrap().multicast_loop_v6()
     }
 
-    pub fn join_multicast_v6(&self, _: &Ipv6Addr, _: u32) -> io::Result<()> {
-        unimplemented!("ipv6 not implemented")
+    pub fn join_multicast_v6(&self, multiaddr: &Ipv6Addr, interface: u32) -> io::Result<()> {
+        self.socket.lock().unwrap().join_multicast_v6(multiaddr, interface)
     }
 
-    pub fn leave_multicast_v6(&self, _: &Ipv6Addr, _: u32) -> io::Result<()> {
-        unimplemented!("ipv6 not implemented")
+    pub fn leave_multicast_v6(&self, multiaddr: &Ipv6Addr, interface: u32) -> io::Result<()> {
+        self.socket.lock().unwrap().leave_multicast_v6(multiaddr, interface)
     }
 }
 
@@ -0,0 +1,573 @@
+//! A WebSocket (RFC 6455) client, over `TcpStream` for `ws://` URLs and `TlsStream` for `wss://` ones. `wss://`
+//! URLs need the `tls` feature.
+//!
+//! `WebSocket::connect()` makes the opening handshake; after that, `send()` and `read()` carry whole
+//! messages, and fragmented ones are put back together. Pings are answered as they come in, and a close
+//! from the server is answered before `read()` hands it back. No extensions, such as compression, are
+//! offered.
+//!
+//! A `WebSocketBuilder` can also set timeouts, limits, subprotocols and extra headers.
+
+use std::convert::TryInto;
+use std::io::{self, BufReader, Error, ErrorKind, Read, Write};
+use std::net::Shutdown;
+
+use smoltcp::time::Duration;
+
+use crate::http::{is_token, read_head, Stream, Url};
+
+const DEFAULT_TIMEOUT_MS: u64 = 10_000;
+const DEFAULT_MAX_MESSAGE_LEN: usize = 1024 * 1024;
+/// how long `close()` waits for the server to finish the closing handshake
+const CLOSE_TIMEOUT_MS: u64 = 5_000;
+/// added to the key we send, to make the one the server has to send back
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const READ_CHUNK_LEN: usize = 4096;
+/// control frames can't carry any more than this
+const MAX_CONTROL_LEN: usize = 125;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+// close codes, from RFC 6455 section 7.4.1
+pub const CLOSE_NORMAL: u16 = 1000;
+pub const CLOSE_GOING_AWAY: u16 = 1001;
+pub const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+pub const CLOSE_INVALID_DATA: u16 = 1007;
+pub const CLOSE_TOO_BIG: u16 = 1009;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    /// The close code and reason, if the other end gave one
+    Close(Option<(u16, String)>),
+}
+
+pub struct WebSocketBuilder {
+    timeout: Duration,
+    max_message_len: usize,
+    protocols: Vec<String>,
+    headers: Vec<(String, String)>,
+}
+
+impl WebSocketBuilder {
+    pub fn new() -> WebSocketBuilder {
+        WebSocketBuilder {
+            timeout: Duration::from_millis(DEFAULT_TIMEOUT_MS),
+            max_message_len: DEFAULT_MAX_MESSAGE_LEN,
+            protocols: Vec::new(),
+            headers: Vec::new(),
+        }
+    }
+
+    /// How long to wait for the connection and the handshake, and for each write after that
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The longest message that will be read; the connection is closed if the server sends a longer one
+    pub fn max_message_len(mut self, max_message_len: usize) -> Self {
+        self.max_message_len = max_message_len;
+        self
+    }
+
+    /// Offers a subprotocol, in order of preference. `WebSocket::protocol()` says which, if any, the server
+    /// picked.
+    pub fn protocol(mut self, protocol: &str) -> Self {
+        self.protocols.push(protocol.to_string());
+        self
+    }
+
+    /// A header to send with the handshake, such as `Authorization`
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn connect(&self, url: &str) -> io::Result<WebSocket> {
+        let invalid = |why| Error::new(ErrorKind::InvalidInput, why);
+        for (name, value) in self.headers.iter() {
+            if !is_token(name) || value.contains(|c: char| c == '\r' || c == '\n') {
+                return Err(invalid("invalid header"));
+            }
+        }
+        if !self.protocols.iter().all(|p| is_token(p)) {
+            return Err(invalid("invalid subprotocol"));
+        }
+        // the handshake is an HTTP request, so the URL is taken apart as the HTTP one it stands for
+        let url = match url.find("://") {
+            Some(i) if url[..i].eq_ignore_ascii_case("wss") => format!("https{}", &url[i..]),
+            Some(i) if url[..i].eq_ignore_ascii_case("ws") => format!("http{}", &url[i..]),
+            _ => return Err(invalid("only ws:// and wss:// URLs are supported")),
+        };
+        let url = Url::parse(&url)?;
+
+        let xns = xous_names::XousNames::new().unwrap();
+        let trng = trng::Trng::new(&xns).unwrap();
+        let mut nonce = [0u8; 16];
+        for chunk in nonce.chunks_mut(4) {
+            chunk.copy_from_slice(&trng.get_u32().unwrap().to_le_bytes());
+        }
+        let key = base64(&nonce);
+
+        let mut stream = Stream::connect(&url, self.timeout)?;
+        let mut head = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: Xous\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n",
+            url.path,
+            url.authority(),
+            key
+        );
+        if !self.protocols.is_empty() {
+            head.push_str(&format!(
+                "Sec-WebSocket-Protocol: {}\r\n",
+                self.protocols.join(", ")
+            ));
+        }
+        for (name, value) in self.headers.iter() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes())?;
+        stream.flush()?;
+
+        let mut reader = BufReader::new(stream);
+        let (status, reason, headers) = read_head(&mut reader)?;
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.as_str())
+        };
+        let refused = |why: String| Error::new(ErrorKind::ConnectionRefused, why);
+        if status != 101 {
+            return Err(refused(format!(
+                "server answered the handshake with {} {}",
+                status, reason
+            )));
+        }
+        if !header("Upgrade").map_or(false, |u| u.eq_ignore_ascii_case("websocket"))
+            || !header("Connection").map_or(false, |c| {
+                c.split(',')
+                    .any(|t| t.trim().eq_ignore_ascii_case("upgrade"))
+            })
+        {
+            return Err(refused("server didn't upgrade to a WebSocket".to_string()));
+        }
+        let accept = base64(&sha1(format!("{}{}", key, ACCEPT_GUID).as_bytes()));
+        if header("Sec-WebSocket-Accept") != Some(accept.as_str()) {
+            return Err(refused("server sent the wrong accept key".to_string()));
+        }
+        if header("Sec-WebSocket-Extensions").is_some() {
+            return Err(refused(
+                "server asked for an extension we didn't offer".to_string(),
+            ));
+        }
+        let protocol = header("Sec-WebSocket-Protocol").map(|p| p.to_string());
+        if let Some(p) = protocol.as_ref() {
+            if !self.protocols.contains(p) {
+                return Err(refused(format!(
+                    "server picked a subprotocol we didn't offer: {}",
+                    p
+                )));
+            }
+        }
+
+        // frames can follow right behind the handshake, so keep whatever was read past it
+        let rx = reader.buffer().to_vec();
+        let mut stream = reader.into_inner();
+        stream.tcp_mut().set_read_timeout(None)?;
+        log::debug!("WebSocket open to {}", url);
+        Ok(WebSocket {
+            stream,
+            rx,
+            partial: None,
+            max_message_len: self.max_message_len,
+            protocol,
+            trng,
+            close_sent: false,
+            close_received: false,
+        })
+    }
+}
+
+impl Default for WebSocketBuilder {
+    fn default() -> Self {
+        WebSocketBuilder::new()
+    }
+}
+
+pub struct WebSocket {
+    stream: Stream,
+    /// what has been read but not yet taken as frames
+    rx: Vec<u8>,
+    /// the opcode and payload, so far, of a message that came fragmented
+    partial: Option<(u8, Vec<u8>)>,
+    max_message_len: usize,
+    protocol: Option<String>,
+    /// for the masking keys, which every frame from a client has to have
+    trng: trng::Trng,
+    /// once we've sent a close, nothing more can be sent
+    close_sent: bool,
+    /// once the server has sent one, nothing more will come
+    close_received: bool,
+}
+
+impl WebSocket {
+    /// Opens a WebSocket to `url`, with the defaults a `WebSocketBuilder` starts with
+    pub fn connect(url: &str) -> io::Result<WebSocket> {
+        WebSocketBuilder::new().connect(url)
+    }
+
+    /// The subprotocol the server picked, if any
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    /// How long `read()` waits for a message; `None`, the default, waits forever. A read that runs out of
+    /// time fails with `ErrorKind::WouldBlock`, and can be tried again without losing anything.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.tcp_mut().set_read_timeout(timeout)
+    }
+
+    pub fn send(&mut self, message: Message) -> io::Result<()> {
+        if self.close_sent {
+            return Err(Error::new(
+                ErrorKind::NotConnected,
+                "the WebSocket is closed",
+            ));
+        }
+        match message {
+            Message::Text(text) => self.send_frame(OP_TEXT, text.as_bytes()),
+            Message::Binary(data) => self.send_frame(OP_BINARY, &data),
+            Message::Ping(data) | Message::Pong(data) if data.len() > MAX_CONTROL_LEN => {
+                Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "a ping or pong can't carry that much",
+                ))
+            }
+            Message::Ping(data) => self.send_frame(OP_PING, &data),
+            Message::Pong(data) => self.send_frame(OP_PONG, &data),
+            Message::Close(Some((code, reason))) => self.send_close(Some(code), &reason),
+            Message::Close(None) => self.send_close(None, ""),
+        }
+    }
+
+    /// The next message from the server. Pings are answered before they're handed back. Once a close has
+    /// come in, it's answered and the connection shut down, and any more reads fail.
+    pub fn read(&mut self) -> io::Result<Message> {
+        loop {
+            if self.close_received {
+                return Err(Error::new(
+                    ErrorKind::NotConnected,
+                    "the WebSocket is closed",
+                ));
+            }
+            let (fin, opcode, payload) = self.read_frame()?;
+            match opcode {
+                OP_PING => {
+                    if !self.close_sent {
+                        self.send_frame(OP_PONG, &payload)?;
+                    }
+                    return Ok(Message::Ping(payload));
+                }
+                OP_PONG => return Ok(Message::Pong(payload)),
+                OP_CLOSE => {
+                    let close = if payload.is_empty() {
+                        None
+                    } else {
+                        if payload.len() < 2 {
+                            return Err(self.fail(CLOSE_PROTOCOL_ERROR, "invalid close frame"));
+                        }
+                        let code = u16::from_be_bytes([payload[0], payload[1]]);
+                        if !valid_close_code(code) {
+                            return Err(self.fail(CLOSE_PROTOCOL_ERROR, "invalid close code"));
+                        }
+                        match String::from_utf8(payload[2..].to_vec()) {
+                            Ok(reason) => Some((code, reason)),
+                            Err(_) => {
+                                return Err(
+                                    self.fail(CLOSE_INVALID_DATA, "close reason isn't UTF-8")
+                                )
+                            }
+                        }
+                    };
+                    self.close_received = true;
+                    if !self.close_sent {
+                        // a close is answered with the code it came with
+                        self.send_close(close.as_ref().map(|(code, _)| *code), "")
+                            .ok();
+                    }
+                    self.shutdown();
+                    return Ok(Message::Close(close));
+                }
+                OP_TEXT | OP_BINARY => {
+                    if self.partial.is_some() {
+                        return Err(self.fail(
+                            CLOSE_PROTOCOL_ERROR,
+                            "new message before the last one was finished",
+                        ));
+                    }
+                    if fin {
+                        return self.message(opcode, payload);
+                    }
+                    self.partial = Some((opcode, payload));
+                }
+                OP_CONTINUATION => {
+                    let (opcode, mut data) = match self.partial.take() {
+                        Some(partial) => partial,
+                        None => {
+                            return Err(self.fail(
+                                CLOSE_PROTOCOL_ERROR,
+                                "continuation without a message to continue",
+                            ))
+                        }
+                    };
+                    if payload.len() > self.max_message_len - data.len() {
+                        return Err(self.fail(CLOSE_TOO_BIG, "message is too big"));
+                    }
+                    data.extend_from_slice(&payload);
+                    if fin {
+                        return self.message(opcode, data);
+                    }
+                    self.partial = Some((opcode, data));
+                }
+                _ => return Err(self.fail(CLOSE_PROTOCOL_ERROR, "unknown opcode")),
+            }
+        }
+    }
+
+    /// Closes the WebSocket with `code` and `reason`, waiting a few seconds for the server to close its end.
+    /// Any messages that come in meanwhile are dropped.
+    pub fn close(&mut self, code: u16, reason: &str) -> io::Result<()> {
+        if !self.close_sent {
+            self.send_close(Some(code), reason)?;
+        }
+        self.set_read_timeout(Some(Duration::from_millis(CLOSE_TIMEOUT_MS)))?;
+        while !self.close_received {
+            if let Err(e) = self.read() {
+                log::debug!("WebSocket closed without the server's close: {:?}", e);
+                break;
+            }
+        }
+        self.shutdown();
+        Ok(())
+    }
+
+    fn message(&mut self, opcode: u8, data: Vec<u8>) -> io::Result<Message> {
+        if opcode == OP_BINARY {
+            return Ok(Message::Binary(data));
+        }
+        match String::from_utf8(data) {
+            Ok(text) => Ok(Message::Text(text)),
+            Err(_) => Err(self.fail(CLOSE_INVALID_DATA, "text message isn't UTF-8")),
+        }
+    }
+
+    /// Reads the next frame, as whether it's the last of its message, its opcode, and its payload. Nothing
+    /// is taken out of `rx` until a whole frame is in, so a read that times out loses nothing.
+    fn read_frame(&mut self) -> io::Result<(bool, u8, Vec<u8>)> {
+        loop {
+            if let Some((header_len, payload_len)) = self.frame_len()? {
+                if self.rx.len() >= header_len + payload_len {
+                    let fin = self.rx[0] & 0x80 != 0;
+                    let opcode = self.rx[0] & 0xf;
+                    let payload = self.rx[header_len..header_len + payload_len].to_vec();
+                    self.rx.drain(..header_len + payload_len);
+                    return Ok((fin, opcode, payload));
+                }
+            }
+            let mut chunk = [0u8; READ_CHUNK_LEN];
+            let len = self.stream.read(&mut chunk)?;
+            if len == 0 {
+                self.close_received = true;
+                return Err(Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "connection closed without a close frame",
+                ));
+            }
+            self.rx.extend_from_slice(&chunk[..len]);
+        }
+    }
+
+    /// The lengths of the header and payload of the frame at the start of `rx`, once enough of it is in to
+    /// tell
+    fn frame_len(&mut self) -> io::Result<Option<(usize, usize)>> {
+        if self.rx.len() < 2 {
+            return Ok(None);
+        }
+        let (b0, b1) = (self.rx[0], self.rx[1]);
+        if b0 & 0x70 != 0 {
+            return Err(self.fail(CLOSE_PROTOCOL_ERROR, "reserved bits set with no extension"));
+        }
+        if b1 & 0x80 != 0 {
+            return Err(self.fail(CLOSE_PROTOCOL_ERROR, "server masked a frame"));
+        }
+        let (header_len, payload_len) = match b1 & 0x7f {
+            126 if self.rx.len() < 4 => return Ok(None),
+            126 => (
+                4,
+                u16::from_be_bytes(self.rx[2..4].try_into().unwrap()) as u64,
+            ),
+            127 if self.rx.len() < 10 => return Ok(None),
+            127 => (10, u64::from_be_bytes(self.rx[2..10].try_into().unwrap())),
+            len => (2, len as u64),
+        };
+        if b0 & 0x8 != 0 && (b0 & 0x80 == 0 || payload_len > MAX_CONTROL_LEN as u64) {
+            return Err(self.fail(
+                CLOSE_PROTOCOL_ERROR,
+                "control frame is fragmented or too long",
+            ));
+        }
+        if payload_len > self.max_message_len as u64 {
+            return Err(self.fail(CLOSE_TOO_BIG, "message is too big"));
+        }
+        Ok(Some((header_len, payload_len as usize)))
+    }
+
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        let mut frame = Vec::with_capacity(payload.len() + 14);
+        frame.push(0x80 | opcode);
+        // every frame from a client is masked
+        match payload.len() {
+            len if len < 126 => frame.push(0x80 | len as u8),
+            len if len <= 0xffff => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(0x80 | 127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        let mask = self
+            .trng
+            .get_u32()
+            .map_err(|_| Error::new(ErrorKind::Other, "couldn't get a masking key"))?
+            .to_le_bytes();
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().zip(mask.iter().cycle()).map(|(b, m)| b ^ m));
+        self.stream.write_all(&frame)?;
+        self.stream.flush()
+    }
+
+    fn send_close(&mut self, code: Option<u16>, reason: &str) -> io::Result<()> {
+        let mut payload = Vec::new();
+        if let Some(code) = code {
+            if reason.len() > MAX_CONTROL_LEN - 2 {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "close reason is too long",
+                ));
+            }
+            payload.extend_from_slice(&code.to_be_bytes());
+            payload.extend_from_slice(reason.as_bytes());
+        }
+        self.close_sent = true;
+        self.send_frame(OP_CLOSE, &payload)
+    }
+
+    /// Closes the connection with `code`, because of something wrong with what the server sent
+    fn fail(&mut self, code: u16, why: &'static str) -> Error {
+        log::warn!("failing WebSocket: {}", why);
+        if !self.close_sent {
+            self.send_close(Some(code), "").ok();
+        }
+        self.close_received = true;
+        self.shutdown();
+        Error::new(ErrorKind::InvalidData, why)
+    }
+
+    fn shutdown(&mut self) {
+        #[cfg(feature = "tls")]
+        if let Stream::Tls(stream) = &mut self.stream {
+            stream.close().ok();
+        }
+        self.stream.tcp_mut().shutdown(Shutdown::Both).ok();
+    }
+}
+
+/// Whether a server may close with `code`. Some codes are only for reporting what happened locally, and
+/// must never be sent.
+fn valid_close_code(code: u16) -> bool {
+    match code {
+        1000..=1003 | 1007..=1014 => true,
+        3000..=4999 => true,
+        _ => false,
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits, (i, &b)| bits | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// SHA-1, which the handshake uses so the server can show it understood the request. Nothing here relies on
+/// it for security.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+    for block in msg.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &w) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(w);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e].iter()) {
+            *h = h.wrapping_add(*v);
+        }
+    }
+    let mut out = [0u8; 20];
+    for (chunk, h) in out.chunks_mut(4).zip(h.iter()) {
+        chunk.copy_from_slice(&h.to_be_bytes());
+    }
+    out
+}
@@ -0,0 +1,241 @@
+//! Outbound connections by way of a SOCKS5 proxy (RFC 1928). When a proxy is set, a TCP connect connects to
+//! the proxy instead. A libstd `TcpStream` isn't told it's connected until the proxy has connected it on to
+//! where it asked to go; a `TcpStream::connect_xous` one is, as ever, told straight away, and its writes are
+//! held off until then. A process that has opted out goes direct, as do all connections while no proxy is set.
+//!
+//! Only the "no authentication" method is offered. Addresses are handed to the proxy as they are, since by
+//! the time a connect gets here the name has already been looked up; so DNS still goes out the usual way.
+//!
+//! The proxy is kept in the PDDB under `PROXY_DICT`, and only the shell and the status bar's menus may set it,
+//! as it sees all of everyone's traffic.
+
+use std::io::{Read, Write};
+
+use smoltcp::socket::TcpSocket;
+use smoltcp::wire::{IpAddress, IpEndpoint, Ipv4Address};
+
+use crate::api::NetError;
+
+pub(crate) const PROXY_DICT: &str = "net.proxy";
+const PROXY_KEY: &str = "socks5";
+/// The processes that may set the proxy. They're known by the name they were built with, which apps loaded at
+/// runtime don't have.
+const TRUSTED_SETTERS: [&str; 2] = ["shellchat", "status"];
+
+const VERSION: u8 = 5;
+const METHOD_NO_AUTH: u8 = 0;
+const CMD_CONNECT: u8 = 1;
+const ATYP_IPV4: u8 = 1;
+const ATYP_DOMAIN: u8 = 3;
+const ATYP_IPV6: u8 = 4;
+/// how long the proxy gets to connect us, if the caller didn't ask for a timeout of its own
+pub(crate) const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+
+#[derive(Debug)]
+enum State {
+    /// waiting on our connection to the proxy
+    Connecting,
+    /// waiting for the proxy to pick an authentication method
+    Method,
+    /// waiting for the proxy to say whether it's connected us to the target
+    Reply,
+}
+
+#[derive(Debug)]
+pub(crate) struct Handshake {
+    state: State,
+    target: IpAddress,
+    target_port: u16,
+    deadline_ms: u64,
+    received: Vec<u8>,
+}
+
+impl Handshake {
+    pub(crate) fn new(target: IpAddress, target_port: u16, deadline_ms: u64) -> Self {
+        Handshake {
+            state: State::Connecting,
+            target,
+            target_port,
+            deadline_ms,
+            received: Vec::new(),
+        }
+    }
+
+    /// Whether the proxy has had all the time it gets, for a handshake still waiting on the connection to it
+    pub(crate) fn timed_out(&self, now_ms: u64) -> bool {
+        now_ms >= self.deadline_ms
+    }
+
+    /// Moves the handshake along with whatever `socket`, now connected to the proxy, has for us. Returns
+    /// `Ok(true)` once the proxy has connected us on, and `Ok(false)` while there's more to wait for.
+    pub(crate) fn poll(&mut self, socket: &mut TcpSocket, now_ms: u64) -> Result<bool, NetError> {
+        if self.timed_out(now_ms) {
+            return Err(NetError::TimedOut);
+        }
+        if !socket.may_send() {
+            log::warn!("proxy closed the connection");
+            return Err(NetError::LibraryError);
+        }
+        if let State::Connecting = self.state {
+            send(socket, &[VERSION, 1, METHOD_NO_AUTH])?;
+            self.state = State::Method;
+        }
+        match self.state {
+            State::Method => {
+                if !self.fill(socket, 2)? {
+                    return Ok(false);
+                }
+                if self.received[0] != VERSION || self.received[1] != METHOD_NO_AUTH {
+                    log::warn!("proxy won't take a connection without authentication");
+                    return Err(NetError::LibraryError);
+                }
+                self.received.clear();
+                send(socket, &self.request())?;
+                self.state = State::Reply;
+                Ok(false)
+            }
+            State::Reply => {
+                if !self.fill(socket, 5)? {
+                    return Ok(false);
+                }
+                if self.received[0] != VERSION {
+                    return Err(NetError::LibraryError);
+                }
+                // the reply carries the address the proxy connected from, which we have no use for, but it
+                // has to be read past all the same
+                let reply_len = match self.received[3] {
+                    ATYP_IPV4 => 4 + 4 + 2,
+                    ATYP_IPV6 => 4 + 16 + 2,
+                    ATYP_DOMAIN => 4 + 1 + self.received[4] as usize + 2,
+                    _ => return Err(NetError::LibraryError),
+                };
+                if !self.fill(socket, reply_len)? {
+                    return Ok(false);
+                }
+                match self.received[1] {
+                    0 => Ok(true),
+                    // network or host unreachable
+                    3 | 4 => Err(NetError::Unaddressable),
+                    // TTL expired
+                    6 => Err(NetError::TimedOut),
+                    code => {
+                        log::warn!("proxy couldn't connect us: reply {}", code);
+                        Err(NetError::LibraryError)
+                    }
+                }
+            }
+            State::Connecting => Ok(false),
+        }
+    }
+
+    /// Reads from `socket` until we've got `len` bytes of the proxy's answer, and no further, as whatever
+    /// comes after the answer is from the target and is for the caller. Returns whether we've got them all.
+    fn fill(&mut self, socket: &mut TcpSocket, len: usize) -> Result<bool, NetError> {
+        while self.received.len() < len && socket.can_recv() {
+            let mut data = [0u8; 32];
+            let want = (len - self.received.len()).min(data.len());
+            let count = socket
+                .recv_slice(&mut data[..want])
+                .map_err(|_| NetError::LibraryError)?;
+            if count == 0 {
+                break;
+            }
+            self.received.extend_from_slice(&data[..count]);
+        }
+        Ok(self.received.len() >= len)
+    }
+
+    fn request(&self) -> Vec<u8> {
+        let mut request = vec![VERSION, CMD_CONNECT, 0];
+        match self.target {
+            IpAddress::Ipv6(addr) => {
+                request.push(ATYP_IPV6);
+                request.extend_from_slice(addr.as_bytes());
+            }
+            IpAddress::Ipv4(addr) => {
+                request.push(ATYP_IPV4);
+                request.extend_from_slice(addr.as_bytes());
+            }
+            _ => unreachable!("connects are only ever made to IPv4 or IPv6 addresses"),
+        }
+        request.extend_from_slice(&self.target_port.to_be_bytes());
+        request
+    }
+}
+
+fn send(socket: &mut TcpSocket, data: &[u8]) -> Result<(), NetError> {
+    // the socket's buffer is empty this early on, so it all goes in at once
+    match socket.send_slice(data) {
+        Ok(len) if len == data.len() => Ok(()),
+        _ => Err(NetError::LibraryError),
+    }
+}
+
+/// Whether `sender` may change the proxy
+pub(crate) fn may_set_proxy(sender: xous::MessageSender) -> bool {
+    #[cfg(any(target_os = "none", target_os = "xous"))]
+    {
+        let mut name = [0u8; 64];
+        match sender.pid().map(|pid| xous::process_name(pid, &mut name)) {
+            Some(Ok(len)) if len > 0 && len < name.len() => core::str::from_utf8(&name[..len])
+                .map(|n| TRUSTED_SETTERS.contains(&n))
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+    #[cfg(not(any(target_os = "none", target_os = "xous")))]
+    {
+        // processes don't have names in hosted mode
+        let _ = sender;
+        true
+    }
+}
+
+/// The proxy stored in the PDDB, if there is one
+pub(crate) fn load_proxy(pddb: &mut pddb::Pddb) -> Option<IpEndpoint> {
+    let mut data = [0u8; 6];
+    let mut key = pddb
+        .get(
+            PROXY_DICT,
+            PROXY_KEY,
+            None,
+            false,
+            false,
+            None,
+            None::<fn()>,
+        )
+        .ok()?;
+    match key.read(&mut data) {
+        Ok(6) => Some(IpEndpoint::new(
+            IpAddress::Ipv4(Ipv4Address::from_bytes(&data[..4])),
+            u16::from_be_bytes([data[4], data[5]]),
+        )),
+        _ => None,
+    }
+}
+
+pub(crate) fn save_proxy(pddb: &mut pddb::Pddb, proxy: Option<IpEndpoint>) -> std::io::Result<()> {
+    let (addr, port) = match proxy {
+        Some(IpEndpoint {
+            addr: IpAddress::Ipv4(addr),
+            port,
+        }) => (addr, port),
+        _ => {
+            // there's nothing to delete if no proxy was ever set
+            pddb.delete_key(PROXY_DICT, PROXY_KEY, None).ok();
+            return Ok(());
+        }
+    };
+    let mut key = pddb.get(
+        PROXY_DICT,
+        PROXY_KEY,
+        None,
+        true,
+        true,
+        Some(6),
+        None::<fn()>,
+    )?;
+    key.write_all(addr.as_bytes())?;
+    key.write_all(&port.to_be_bytes())?;
+    key.flush()
+}
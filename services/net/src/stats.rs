@@ -0,0 +1,106 @@
+//! Counts the traffic through each socket, and totals it for the process that opened the socket, so it can be
+//! seen which app is using the radio. A process's totals outlive its sockets, so they cover everything it has
+//! sent or received since boot. Sockets the Net server opens for itself, such as the one for pings, are
+//! counted against PID 0.
+
+use crate::api::{ProcessStats, SocketKind, SocketStats, TrafficCounts};
+use smoltcp::socket::SocketHandle;
+use std::collections::HashMap;
+
+struct SocketEntry {
+    pid: Option<xous::PID>,
+    kind: SocketKind,
+    local_port: u16,
+    counts: TrafficCounts,
+}
+
+#[derive(Default)]
+pub(crate) struct NetStats {
+    sockets: HashMap<SocketHandle, SocketEntry>,
+    processes: HashMap<Option<xous::PID>, TrafficCounts>,
+}
+
+impl NetStats {
+    pub(crate) fn new() -> Self {
+        NetStats::default()
+    }
+
+    pub(crate) fn open(
+        &mut self,
+        handle: SocketHandle,
+        pid: Option<xous::PID>,
+        kind: SocketKind,
+        local_port: u16,
+    ) {
+        self.processes.entry(pid).or_default();
+        self.sockets.insert(
+            handle,
+            SocketEntry {
+                pid,
+                kind,
+                local_port,
+                counts: TrafficCounts::default(),
+            },
+        );
+    }
+
+    pub(crate) fn close(&mut self, handle: SocketHandle) {
+        self.sockets.remove(&handle);
+    }
+
+    pub(crate) fn sent(&mut self, handle: SocketHandle, bytes: usize) {
+        self.count(handle, |counts| {
+            counts.tx_bytes += bytes as u64;
+            counts.tx_packets += 1;
+        });
+    }
+
+    pub(crate) fn received(&mut self, handle: SocketHandle, bytes: usize) {
+        self.count(handle, |counts| {
+            counts.rx_bytes += bytes as u64;
+            counts.rx_packets += 1;
+        });
+    }
+
+    fn count<F: Fn(&mut TrafficCounts)>(&mut self, handle: SocketHandle, f: F) {
+        if let Some(socket) = self.sockets.get_mut(&handle) {
+            f(&mut socket.counts);
+            f(self.processes.entry(socket.pid).or_default());
+        }
+    }
+
+    /// Every process that has opened a socket, busiest first
+    pub(crate) fn processes(&self) -> Vec<ProcessStats> {
+        let mut list: Vec<ProcessStats> = self
+            .processes
+            .iter()
+            .map(|(pid, counts)| ProcessStats {
+                pid: pid_to_u8(*pid),
+                open_sockets: self.sockets.values().filter(|s| s.pid == *pid).count() as u16,
+                counts: *counts,
+            })
+            .collect();
+        list.sort_by_key(|p| std::cmp::Reverse(p.counts.tx_bytes + p.counts.rx_bytes));
+        list
+    }
+
+    /// Every open socket, in a consistent order so the list can be paged through
+    pub(crate) fn sockets(&self) -> Vec<SocketStats> {
+        let mut list: Vec<SocketStats> = self
+            .sockets
+            .values()
+            .map(|s| SocketStats {
+                pid: pid_to_u8(s.pid),
+                kind: s.kind,
+                local_port: s.local_port,
+                counts: s.counts,
+            })
+            .collect();
+        list.sort_by_key(|s| (s.pid, s.local_port));
+        list
+    }
+}
+
+fn pid_to_u8(pid: Option<xous::PID>) -> u8 {
+    pid.map(|p| p.get()).unwrap_or(0)
+}
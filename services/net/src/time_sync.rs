@@ -0,0 +1,248 @@
+//! Keeps the RTC in step with network time. Once the network is up, the thread here asks an NTP server for
+//! the time every `SYNC_INTERVAL_MS`, and corrects the RTC by however much it's off.
+//!
+//! The RTC keeps local time, as set by the user, and has no record of the time zone it's in. The zone is
+//! taken to be the RTC's distance from UTC, rounded to the nearest quarter hour; what's left over is how far
+//! the RTC has drifted. So an RTC that has drifted by more than 7.5 minutes is corrected to the wrong quarter
+//! hour, and one that's more than a day out (likely never set) is set to UTC.
+//!
+//! An error of up to `STEP_THRESHOLD_MS` is slewed away, a second at a time every `SLEW_INTERVAL_MS`, so the
+//! clock never jumps by much; a bigger one is stepped out all at once.
+
+use crate::api::TimeSync;
+use llio::{DateTime, Weekday};
+use smoltcp::time::Duration;
+use std::io::{Error, ErrorKind, Result};
+use std::sync::{Arc, Mutex};
+
+const NTP_SERVER: &str = "pool.ntp.org";
+const SYNC_INTERVAL_MS: usize = 60 * 60 * 1000;
+/// how soon to try again after a sync fails, or while waiting for the network to come up
+const RETRY_INTERVAL_MS: usize = 60 * 1000;
+const SLEW_INTERVAL_MS: usize = 60 * 1000;
+const STEP_THRESHOLD_MS: i64 = 16_000;
+/// the RTC only counts whole seconds, so it can't be kept any closer than this
+const TOLERANCE_MS: i64 = 1_000;
+const QUERY_TIMEOUT_MS: u64 = 5_000;
+const ZONE_QUANTUM_MS: i64 = 15 * 60 * 1000;
+/// time zones run from UTC-12 to UTC+14
+const MAX_ZONE_MS: i64 = 14 * 60 * 60 * 1000;
+
+pub(crate) fn time_sync(last_sync: Arc<Mutex<Option<TimeSync>>>) {
+    let xns = xous_names::XousNames::new().unwrap();
+    let rtc = llio::Rtc::new(&xns);
+    let ticktimer = ticktimer_server::Ticktimer::new().unwrap();
+    let netmgr = net::NetManager::new();
+    loop {
+        let interval = if netmgr.get_ipv4_config().is_none() {
+            RETRY_INTERVAL_MS
+        } else {
+            match sync(&rtc, &ticktimer) {
+                Ok(record) => {
+                    log::info!("time sync: {:?}", record);
+                    let remaining_ms = record.skew_ms - record.correction_secs as i64 * 1000;
+                    *last_sync.lock().unwrap() = Some(record);
+                    if remaining_ms.abs() >= TOLERANCE_MS {
+                        SLEW_INTERVAL_MS
+                    } else {
+                        SYNC_INTERVAL_MS
+                    }
+                }
+                Err(e) => {
+                    log::warn!("time sync with {} failed: {:?}", NTP_SERVER, e);
+                    RETRY_INTERVAL_MS
+                }
+            }
+        };
+        ticktimer.sleep_ms(interval).unwrap();
+    }
+}
+
+fn sync(rtc: &llio::Rtc, ticktimer: &ticktimer_server::Ticktimer) -> Result<TimeSync> {
+    let time = net::sntp_query(
+        (NTP_SERVER, net::NTP_PORT),
+        Duration::from_millis(QUERY_TIMEOUT_MS),
+    )?;
+    let dt = rtc
+        .get_datetime()
+        .map_err(|e| Error::new(ErrorKind::Other, format!("couldn't read the RTC: {:?}", e)))?;
+    let utc_ms = (time.utc_ms + (ticktimer.elapsed_ms() - time.ticktimer_ms)) as i64;
+    // the RTC reads the second it's in, so on average it's half a second further along than it says
+    let ahead_ms = rtc_secs(&dt).unwrap_or(0) * 1000 + 500 - utc_ms;
+    let mut zone_ms =
+        (ahead_ms + ZONE_QUANTUM_MS / 2).div_euclid(ZONE_QUANTUM_MS) * ZONE_QUANTUM_MS;
+    if zone_ms.abs() > MAX_ZONE_MS {
+        zone_ms = 0;
+    }
+    let skew_ms = ahead_ms - zone_ms;
+    let correction_secs = if skew_ms.abs() < TOLERANCE_MS {
+        0
+    } else if skew_ms.abs() <= STEP_THRESHOLD_MS {
+        skew_ms.signum()
+    } else {
+        (skew_ms + 500).div_euclid(1000)
+    };
+    if correction_secs != 0 {
+        // the RTC has moved on since it was read, so work from the time now
+        let now_ms = (time.utc_ms + (ticktimer.elapsed_ms() - time.ticktimer_ms)) as i64;
+        let local_ms = now_ms + zone_ms + skew_ms - correction_secs * 1000;
+        rtc.set_rtc(to_datetime((local_ms + 500).div_euclid(1000)))
+            .map_err(|e| Error::new(ErrorKind::Other, format!("couldn't set the RTC: {:?}", e)))?;
+    }
+    Ok(TimeSync {
+        utc_secs: time.utc_ms / 1000,
+        ticktimer_ms: time.ticktimer_ms,
+        skew_ms,
+        utc_offset_secs: (zone_ms / 1000) as i32,
+        correction_secs: correction_secs as i32,
+        round_trip_ms: time.round_trip_ms as u32,
+    })
+}
+
+/// Days from 1970-01-01 to the given date. Only good for dates after 1970, which is all the RTC can hold.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// The date `days` after 1970-01-01, as (year, month, day)
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// The RTC's time as seconds since 1970, or `None` if it doesn't hold a valid date
+fn rtc_secs(dt: &DateTime) -> Option<i64> {
+    if dt.months < 1
+        || dt.months > 12
+        || dt.days < 1
+        || dt.days > 31
+        || dt.hours > 23
+        || dt.minutes > 59
+        || dt.seconds > 59
+    {
+        return None;
+    }
+    let days = days_from_civil(2000 + dt.years as i64, dt.months as i64, dt.days as i64);
+    Some(((days * 24 + dt.hours as i64) * 60 + dt.minutes as i64) * 60 + dt.seconds as i64)
+}
+
+fn to_datetime(secs: i64) -> DateTime {
+    let days = secs.div_euclid(86400);
+    let secs = secs.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    // 1970-01-01 was a Thursday
+    let weekday = match (days + 4).rem_euclid(7) {
+        0 => Weekday::Sunday,
+        1 => Weekday::Monday,
+        2 => Weekday::Tuesday,
+        3 => Weekday::Wednesday,
+        4 => Weekday::Thursday,
+        5 => Weekday::Friday,
+        _ => Weekday::Saturday,
+    };
+    DateTime {
+        seconds: (secs % 60) as u8,
+        minutes: (secs / 60 % 60) as u8,
+        hours: (secs / 3600) as u8,
+        days: day as u8,
+        months: month as u8,
+        years: (year - 2000) as u8,
+        weekday,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leap_years() {
+        let feb_length = |year| days_from_civil(year, 3, 1) - days_from_civil(year, 2, 1);
+        assert_eq!(feb_length(2023), 28);
+        assert_eq!(feb_length(2024), 29);
+        // centuries are only leap years every 400 years
+        assert_eq!(feb_length(2000), 29);
+        assert_eq!(feb_length(2100), 28);
+        assert_eq!(
+            civil_from_days(days_from_civil(2024, 2, 28) + 1),
+            (2024, 2, 29)
+        );
+        assert_eq!(
+            civil_from_days(days_from_civil(2023, 2, 28) + 1),
+            (2023, 3, 1)
+        );
+    }
+
+    #[test]
+    fn month_and_year_rollover() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(
+            civil_from_days(days_from_civil(2023, 1, 31) + 1),
+            (2023, 2, 1)
+        );
+        assert_eq!(
+            civil_from_days(days_from_civil(2023, 4, 30) + 1),
+            (2023, 5, 1)
+        );
+        assert_eq!(
+            civil_from_days(days_from_civil(2023, 12, 31) + 1),
+            (2024, 1, 1)
+        );
+    }
+
+    #[test]
+    fn every_day_of_the_rtc_range_round_trips() {
+        let first = days_from_civil(2000, 1, 1);
+        let last = days_from_civil(2099, 12, 31);
+        let mut previous = civil_from_days(first - 1);
+        assert_eq!(previous, (1999, 12, 31));
+        for days in first..=last {
+            let date = civil_from_days(days);
+            assert_eq!(days_from_civil(date.0, date.1, date.2), days);
+            // each day follows on from the one before
+            let (year, month, day) = previous;
+            assert!(
+                date == (year, month, day + 1)
+                    || (date.2 == 1 && (date.1 == month + 1 || date == (year + 1, 1, 1))),
+                "{:?} after {:?}",
+                date,
+                previous
+            );
+            previous = date;
+        }
+    }
+
+    #[test]
+    fn datetime_conversion() {
+        let dt = to_datetime(2_147_483_647);
+        assert_eq!(
+            (dt.years, dt.months, dt.days, dt.hours, dt.minutes, dt.seconds),
+            (38, 1, 19, 3, 14, 7)
+        );
+        assert!(matches!(dt.weekday, Weekday::Tuesday));
+        assert_eq!(rtc_secs(&dt), Some(2_147_483_647));
+
+        let leap_day = 11016 * 86400;
+        let dt = to_datetime(leap_day);
+        assert_eq!((dt.years, dt.months, dt.days), (0, 2, 29));
+        assert!(matches!(dt.weekday, Weekday::Tuesday));
+        assert_eq!(rtc_secs(&dt), Some(leap_day));
+
+        let mut bad = to_datetime(leap_day);
+        bad.months = 13;
+        assert_eq!(rtc_secs(&bad), None);
+    }
+}
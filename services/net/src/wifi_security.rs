@@ -0,0 +1,200 @@
+//! Works out how an access point is secured from its beacon: the privacy bit of the capability field, and the
+//! RSN (WPA2 and WPA3) and WPA information elements. An access point that offers more than one way in is reported
+//! by the one the WF200 joins with, so a WPA2/WPA3 transition network is `Wpa2Psk`.
+
+use crate::api::WifiSecurity;
+
+/// Capability bit set by an access point that needs WEP or better
+const CAPABILITY_PRIVACY: u16 = 1 << 4;
+const IE_RSN: u8 = 48;
+const IE_VENDOR: u8 = 221;
+const RSN_OUI: [u8; 3] = [0x00, 0x0f, 0xac];
+const WPA_OUI: [u8; 3] = [0x00, 0x50, 0xf2];
+/// Vendor IE type of WPA, under `WPA_OUI`
+const WPA_TYPE: u8 = 1;
+
+// Key management suites, under `RSN_OUI` or `WPA_OUI`
+const AKM_8021X: u8 = 1;
+const AKM_PSK: u8 = 2;
+const AKM_PSK_SHA256: u8 = 6;
+const AKM_SAE: u8 = 8;
+const AKM_SAE_EXT: u8 = 24;
+
+pub(crate) fn security_from_ies(capability: u16, ies: &[u8]) -> WifiSecurity {
+    let mut rsn = None;
+    let mut wpa = None;
+    let mut rest = ies;
+    while rest.len() >= 2 {
+        let (id, len) = (rest[0], rest[1] as usize);
+        if rest.len() < 2 + len {
+            // the EC only passes on so much of each beacon, so the last element can be cut short
+            break;
+        }
+        let body = &rest[2..2 + len];
+        match id {
+            IE_RSN if rsn.is_none() => rsn = Some(body),
+            IE_VENDOR
+                if wpa.is_none()
+                    && body.len() >= 4
+                    && body[..3] == WPA_OUI
+                    && body[3] == WPA_TYPE =>
+            {
+                wpa = Some(&body[4..])
+            }
+            _ => {}
+        }
+        rest = &rest[2 + len..];
+    }
+    if let Some(body) = rsn {
+        match akm_suites(body, RSN_OUI) {
+            Some(akms) if akms.contains(&AKM_PSK) || akms.contains(&AKM_PSK_SHA256) => {
+                WifiSecurity::Wpa2Psk
+            }
+            Some(akms) if akms.contains(&AKM_SAE) || akms.contains(&AKM_SAE_EXT) => {
+                WifiSecurity::Wpa3Sae
+            }
+            Some(akms) if akms.contains(&AKM_8021X) => WifiSecurity::Wpa2Enterprise,
+            _ => WifiSecurity::Unknown,
+        }
+    } else if let Some(body) = wpa {
+        match akm_suites(body, WPA_OUI) {
+            Some(akms) if akms.contains(&AKM_PSK) => WifiSecurity::WpaPsk,
+            _ => WifiSecurity::Unknown,
+        }
+    } else if capability & CAPABILITY_PRIVACY != 0 {
+        WifiSecurity::Wep
+    } else {
+        WifiSecurity::Open
+    }
+}
+
+/// The key management suites listed in the body of an RSN or WPA element, which both start with a version, a
+/// group cipher and a list of pairwise ciphers. Suites from other vendors are left out. `None` if the body is
+/// cut short; an element that stops before the suites means the default, which is 802.1X.
+fn akm_suites(body: &[u8], oui: [u8; 3]) -> Option<Vec<u8>> {
+    // version and group cipher
+    let mut rest = body.get(6..)?;
+    if rest.is_empty() {
+        return Some(vec![AKM_8021X]);
+    }
+    let pairwise = u16::from_le_bytes([*rest.first()?, *rest.get(1)?]) as usize;
+    rest = rest.get(2 + 4 * pairwise..)?;
+    if rest.is_empty() {
+        return Some(vec![AKM_8021X]);
+    }
+    let count = u16::from_le_bytes([*rest.first()?, *rest.get(1)?]) as usize;
+    let suites = rest.get(2..2 + 4 * count)?;
+    Some(
+        suites
+            .chunks(4)
+            .filter(|suite| suite[..3] == oui)
+            .map(|suite| suite[3])
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An RSN element with CCMP ciphers and the given key management suites
+    fn rsn(akms: &[u8]) -> Vec<u8> {
+        let mut body = vec![1, 0, 0x00, 0x0f, 0xac, 4, 1, 0, 0x00, 0x0f, 0xac, 4];
+        body.extend_from_slice(&(akms.len() as u16).to_le_bytes());
+        for akm in akms {
+            body.extend_from_slice(&[0x00, 0x0f, 0xac, *akm]);
+        }
+        body.extend_from_slice(&[0, 0]); // RSN capabilities
+        let mut ie = vec![IE_RSN, body.len() as u8];
+        ie.extend(body);
+        ie
+    }
+
+    fn wpa(akm: u8) -> Vec<u8> {
+        vec![
+            IE_VENDOR, 22, 0x00, 0x50, 0xf2, 1, 1, 0, 0x00, 0x50, 0xf2, 2, 1, 0, 0x00, 0x50, 0xf2,
+            2, 1, 0, 0x00, 0x50, 0xf2, akm,
+        ]
+    }
+
+    fn ssid(name: &[u8]) -> Vec<u8> {
+        let mut ie = vec![0, name.len() as u8];
+        ie.extend_from_slice(name);
+        ie
+    }
+
+    #[test]
+    fn open_and_wep() {
+        assert_eq!(
+            security_from_ies(0x0001, &ssid(b"cafe")),
+            WifiSecurity::Open
+        );
+        assert_eq!(security_from_ies(0x0011, &ssid(b"cafe")), WifiSecurity::Wep);
+        assert_eq!(security_from_ies(0x0001, &[]), WifiSecurity::Open);
+    }
+
+    #[test]
+    fn rsn_key_management() {
+        let with = |ie: Vec<u8>| [ssid(b"home"), ie].concat();
+        assert_eq!(
+            security_from_ies(0x0011, &with(rsn(&[AKM_PSK]))),
+            WifiSecurity::Wpa2Psk
+        );
+        assert_eq!(
+            security_from_ies(0x0011, &with(rsn(&[AKM_PSK_SHA256]))),
+            WifiSecurity::Wpa2Psk
+        );
+        assert_eq!(
+            security_from_ies(0x0011, &with(rsn(&[AKM_SAE]))),
+            WifiSecurity::Wpa3Sae
+        );
+        assert_eq!(
+            security_from_ies(0x0011, &with(rsn(&[AKM_SAE, AKM_PSK]))),
+            WifiSecurity::Wpa2Psk
+        );
+        assert_eq!(
+            security_from_ies(0x0011, &with(rsn(&[AKM_8021X]))),
+            WifiSecurity::Wpa2Enterprise
+        );
+        assert_eq!(
+            security_from_ies(0x0011, &with(rsn(&[]))),
+            WifiSecurity::Unknown
+        );
+    }
+
+    #[test]
+    fn rsn_is_preferred_over_wpa() {
+        assert_eq!(
+            security_from_ies(0x0011, &wpa(AKM_PSK)),
+            WifiSecurity::WpaPsk
+        );
+        assert_eq!(
+            security_from_ies(0x0011, &wpa(AKM_8021X)),
+            WifiSecurity::Unknown
+        );
+        let both = [wpa(AKM_PSK), rsn(&[AKM_PSK])].concat();
+        assert_eq!(security_from_ies(0x0011, &both), WifiSecurity::Wpa2Psk);
+        // other vendor elements are skipped over
+        let other = [vec![IE_VENDOR, 4, 0x00, 0x50, 0xf2, 4], wpa(AKM_PSK)].concat();
+        assert_eq!(security_from_ies(0x0011, &other), WifiSecurity::WpaPsk);
+    }
+
+    #[test]
+    fn short_elements() {
+        // an RSN element with only a version and group cipher defaults to 802.1X
+        let bare = [IE_RSN, 6, 1, 0, 0x00, 0x0f, 0xac, 4];
+        assert_eq!(
+            security_from_ies(0x0011, &bare),
+            WifiSecurity::Wpa2Enterprise
+        );
+        // one that claims more suites than it holds can't be read
+        let mut cut = rsn(&[AKM_PSK]);
+        cut[1] = 16;
+        cut.truncate(18);
+        assert_eq!(security_from_ies(0x0011, &cut), WifiSecurity::Unknown);
+        // and one that runs off the end of what the EC passed on is ignored
+        let mut truncated = rsn(&[AKM_PSK]);
+        truncated.truncate(10);
+        assert_eq!(security_from_ies(0x0011, &truncated), WifiSecurity::Wep);
+    }
+}
@@ -74,6 +74,8 @@ pub(crate) const BCRYPT_COST: u32 = 7;   // 10 is the minimum recommended by OWA
 pub(crate) enum Opcode {
     IsMounted,
     TryMount,
+    /// BlockingScalar call that's only returned once the PDDB is mounted
+    WaitForMount,
 
     ListBasis,
     LatestBasis,
@@ -4,8 +4,17 @@ use crate::api::*;
 use std::sync::Once;
 use std::mem::MaybeUninit;
 
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::io::prelude::*;
+use std::io::SeekFrom;
+use std::convert::TryInto;
+
+/// where images are swapped with `tools/pddbdbg.py`, and with hardware, relative to the kernel's directory
+const IMAGE_DIR: &str = "../tools/pddb-images";
+/// names an image in `IMAGE_DIR` to run on, instead of a blank FLASH
+const IMAGE_VAR: &str = "XOUS_PDDB_IMAGE";
+/// length of each basis name in a key file
+const KEYFILE_NAME_LEN: usize = 64;
 
 // This is considered bad practice for Rust to use a global singleton.
 // However, this hack puts the burden of emulation on the emulator, while
@@ -16,6 +25,41 @@ use std::io::prelude::*;
 // Note that this is a concurrently accessed, unsafe, unchecked vector.
 struct FlashSingleton {
     memory: Vec::<u8>,
+    /// The image the memory was loaded from, if it was. Everything written to the memory is written to the
+    /// image too, so it's always an exact copy of the FLASH, ready to be written back to a device.
+    backing: Option<File>,
+}
+impl FlashSingleton {
+    fn write(&mut self, offset: usize, data: &[u8]) {
+        self.memory[offset..offset + data.len()].copy_from_slice(data);
+        if let Some(file) = self.backing.as_mut() {
+            file.seek(SeekFrom::Start(offset as u64))
+                .and_then(|_| file.write_all(data))
+                .expect("couldn't write through to the PDDB image");
+        }
+    }
+}
+
+/// Opens the image named by `IMAGE_VAR`, if there is one. Returns the image and its contents.
+fn open_image() -> Option<(File, Vec::<u8>)> {
+    let name = std::env::var(IMAGE_VAR).ok()?;
+    let path = format!("{}/{}.bin", IMAGE_DIR, name);
+    let mut file = match OpenOptions::new().read(true).write(true).open(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            log::error!("couldn't open PDDB image {}: {:?}; starting from blank FLASH", path, e);
+            return None;
+        }
+    };
+    let mut memory = Vec::<u8>::with_capacity(PDDB_A_LEN);
+    file.read_to_end(&mut memory).expect("couldn't read the PDDB image");
+    if memory.len() != PDDB_A_LEN {
+        log::error!("PDDB image {} is {} bytes, but the PDDB is {} bytes; starting from blank FLASH",
+            path, memory.len(), PDDB_A_LEN);
+        return None;
+    }
+    log::info!("running on PDDB image {}, which will be updated in place", path);
+    Some((file, memory))
 }
 
 fn flashmem() -> &'static mut FlashSingleton {
@@ -24,12 +68,21 @@ fn flashmem() -> &'static mut FlashSingleton {
 
     unsafe {
         ONCE.call_once(|| {
-            let mut memory = Vec::<u8>::with_capacity(PDDB_A_LEN);
-            for _ in 0..PDDB_A_LEN {
-                memory.push(0xFF);
-            }
-            let flashmem = FlashSingleton {
-                memory,
+            let flashmem = match open_image() {
+                Some((file, memory)) => FlashSingleton {
+                    memory,
+                    backing: Some(file),
+                },
+                None => {
+                    let mut memory = Vec::<u8>::with_capacity(PDDB_A_LEN);
+                    for _ in 0..PDDB_A_LEN {
+                        memory.push(0xFF);
+                    }
+                    FlashSingleton {
+                        memory,
+                        backing: None,
+                    }
+                }
             };
             SINGLETON.write(flashmem);
         });
@@ -39,9 +92,16 @@ fn flashmem() -> &'static mut FlashSingleton {
 
 #[derive(Copy, Clone)]
 pub struct KeyExport {
-    pub basis_name: [u8; 64],
+    pub basis_name: [u8; KEYFILE_NAME_LEN],
     pub key: [u8; 32],
 }
+/// What's needed to mount an image from a device, other than the image. The system basis key is wrapped
+/// with the device's root keys, which never leave it, so it has to be given unwrapped; and the device's DNA
+/// goes into the AAD of every page.
+pub struct ImageKeys {
+    pub system_key: Option<[u8; 32]>,
+    pub dna: Option<u64>,
+}
 pub struct EmuStorage {
 }
 impl EmuStorage {
@@ -62,9 +122,7 @@ impl EmuStorage {
     }
     /// used to reset the storage for repeated test case generation
     pub fn reset(&mut self) {
-        for b in flashmem().memory.as_mut_slice() {
-            *b = 0xFF;
-        }
+        flashmem().write(0, &vec![0xFF; PDDB_A_LEN]);
     }
     pub fn dump_fs(&self, name: &Option<String>) {
         let defaultname = String::from("pddb");
@@ -73,7 +131,9 @@ impl EmuStorage {
         f.write_all(flashmem().memory.as_slice()).unwrap();
         f.flush().unwrap();
     }
-    pub fn dump_keys(&self, known_keys: &[KeyExport], name: &Option<String>) {
+    /// The key file is a little-endian u32 count of keys, then each key as a zero-padded basis name followed by
+    /// the key, then the little-endian u64 DNA of the device the image belongs to.
+    pub fn dump_keys(&self, known_keys: &[KeyExport], dna: u64, name: &Option<String>) {
         let defaultname = String::from("pddb");
         let rootname = name.as_ref().unwrap_or(&defaultname);
         let mut f = File::create(format!("../tools/pddb-images/{}.key", rootname)).unwrap();
@@ -82,8 +142,36 @@ impl EmuStorage {
             f.write_all(&key.basis_name).unwrap();
             f.write_all(&key.key).unwrap();
         }
+        f.write_all(&dna.to_le_bytes()).unwrap();
         f.flush().unwrap();
     }
+    /// Reads the key file that goes with the image named by `IMAGE_VAR`, in the format written by `dump_keys()`.
+    /// Key files written before the DNA was added have none, and belong to images made in hosted mode.
+    pub fn image_keys(&self, system_basis: &str) -> Option<ImageKeys> {
+        flashmem().backing.as_ref()?;
+        let path = format!("{}/{}.key", IMAGE_DIR, std::env::var(IMAGE_VAR).ok()?);
+        let file = match std::fs::read(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                log::warn!("couldn't read {}: {:?}; the image can only be mounted with root keys", path, e);
+                return None;
+            }
+        };
+        const ENTRY_LEN: usize = KEYFILE_NAME_LEN + 32;
+        let count = u32::from_le_bytes(file.get(..4)?.try_into().unwrap()) as usize;
+        let entries = file.get(4..4 + count * ENTRY_LEN)?;
+        let mut keys = ImageKeys { system_key: None, dna: None };
+        for entry in entries.chunks_exact(ENTRY_LEN) {
+            let name_len = entry[..KEYFILE_NAME_LEN].iter().position(|&b| b == 0).unwrap_or(KEYFILE_NAME_LEN);
+            if &entry[..name_len] == system_basis.as_bytes() {
+                keys.system_key = Some(entry[KEYFILE_NAME_LEN..].try_into().unwrap());
+            }
+        }
+        if let Some(dna) = file.get(4 + count * ENTRY_LEN..4 + count * ENTRY_LEN + 8) {
+            keys.dna = Some(u64::from_le_bytes(dna.try_into().unwrap()));
+        }
+        Some(keys)
+    }
 }
 
 pub struct HostedSpinor {
@@ -95,17 +183,11 @@ impl HostedSpinor {
     }
     pub fn patch(&self, _region: &[u8], _region_base: u32, data: &[u8], offset: u32) -> Result<(), xous::Error> {
         // println!("patch at {:x}+{}", offset, data.len());
-        for (&src, dst) in data.iter().zip(
-            flashmem().memory.as_mut_slice()[offset as usize..offset as usize + data.len()].iter_mut()
-        ) {
-            *dst = src;
-        }
+        flashmem().write(offset as usize, data);
         Ok(())
     }
     pub fn bulk_erase(&self, start: u32, len: u32) -> Result<(), xous::Error> {
-        for b in flashmem().memory.as_mut_slice()[(start - xous::PDDB_LOC) as usize .. (start - xous::PDDB_LOC + len) as usize].iter_mut() {
-            *b = 0xFF;
-        }
+        flashmem().write((start - xous::PDDB_LOC) as usize, &vec![0xFF; len as usize]);
         Ok(())
     }
 }
\ No newline at end of file
@@ -161,7 +161,7 @@ impl PddbOs {
         // emulated
         #[cfg(not(any(target_os = "none", target_os = "xous")))]
         let ret = {
-            PddbOs {
+            let mut pddb = PddbOs {
                 spinor: HostedSpinor::new(),
                 rootkeys: root_keys::RootKeys::new(&xns, Some(AesRootkeyType::User0)).expect("FATAL: couldn't access RootKeys!"),
                 tt: ticktimer_server::Ticktimer::new().unwrap(),
@@ -179,7 +179,19 @@ impl PddbOs {
                 fspace_log_len: 0,
                 dna: llio.soc_dna().unwrap(),
                 entropy: trngpool,
+            };
+            // an image from a device can't be unlocked by the emulated root keys, so it's given its own keys
+            if let Some(keys) = pddb.pddb_mr.image_keys(PDDB_DEFAULT_SYSTEM_BASIS) {
+                if let Some(dna) = keys.dna {
+                    pddb.dna = dna;
+                }
+                if let Some(key) = keys.system_key {
+                    log::info!("(hosted mode) using the system basis key given with the PDDB image");
+                    pddb.cipher_ecb = Some(Aes256::new(GenericArray::from_slice(&key)));
+                    pddb.system_basis_key = Some(key);
+                }
             }
+            pddb
         };
         ret
     }
@@ -206,7 +218,7 @@ impl PddbOs {
                 export.push(*key);
             }
         }
-        self.pddb_mr.dump_keys(&export, &name);
+        self.pddb_mr.dump_keys(&export, self.dna, &name);
     }
     #[allow(dead_code)]
     #[cfg(any(target_os = "none", target_os = "xous"))]
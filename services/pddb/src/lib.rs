@@ -108,6 +108,12 @@ impl Pddb {
             _ => panic!("Internal error"),
         }
     }
+    /// Blocks until the PDDB is mounted, for things that can't start until it is. That may be never, if the user
+    /// doesn't unlock it, so it's best called from a thread of its own.
+    pub fn wait_for_mount(&self) {
+        send_message(self.conn, Message::new_blocking_scalar(
+            Opcode::WaitForMount.to_usize().unwrap(), 0, 0, 0, 0)).expect("couldn't execute WaitForMount query");
+    }
     /// return a list of all open bases
     pub fn list_basis(&self) -> Vec::<String> {
         let list_alloc = PddbBasisList {
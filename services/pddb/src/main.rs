@@ -484,6 +484,8 @@ fn xmain() -> ! {
     let mut key_token: Option<[u32; 4]> = None;
     let mut dict_list = Vec::<String>::new(); // storage for dict lists
     let mut dict_token: Option<[u32; 4]> = None;
+    // callers of `WaitForMount`, answered once the PDDB is mounted
+    let mut mount_waiters = Vec::<xous::MessageEnvelope>::new();
 
     // register a suspend/resume listener
     let mut susres = susres::Susres::new(Some(susres::SuspendOrder::Early), &xns,
@@ -502,6 +504,13 @@ fn xmain() -> ! {
                     xous::return_scalar(msg.sender, 0).expect("couldn't return scalar");
                 }
             }),
+            Some(Opcode::WaitForMount) => {
+                if basis_cache.basis_count() > 0 {
+                    xous::return_scalar(msg.sender, 1).expect("couldn't return scalar");
+                } else {
+                    mount_waiters.push(msg);
+                }
+            }
             Some(Opcode::TryMount) => xous::msg_blocking_scalar_unpack!(msg, _, _, _, _, {
                 if basis_cache.basis_count() > 0 {
                     xous::return_scalar(msg.sender, 1).expect("couldn't return scalar");
@@ -1062,6 +1071,11 @@ fn xmain() -> ! {
                 log::error!("couldn't convert opcode: {:?}", msg);
             }
         }
+        if !mount_waiters.is_empty() && basis_cache.basis_count() > 0 {
+            for waiter in mount_waiters.drain(..) {
+                xous::return_scalar(waiter.sender, 1).expect("couldn't return scalar");
+            }
+        }
     }
     // clean up our program
     log::trace!("main loop exit, destroying servers");
@@ -9,6 +9,8 @@ pub(crate) enum Opcode {
     KeysInitialized,
     /// check that the digital signature on the gateware
     CheckGatewareSignature,
+    /// check the digital signature on an app image, lent with its length in `valid`
+    CheckAppSignature,
     /// check if the efuse has been locked down
     IsEfuseSecured,
     /// quick check to see if the JTAG can read its IDCODE
@@ -69,6 +71,17 @@ pub(crate) enum Opcode {
     Quit
 }
 
+/// Whose key an app image was signed with
+#[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive, PartialEq, Eq, Copy, Clone)]
+pub enum AppSigner {
+    /// this device's own self-signing key
+    SelfSign = 1,
+    /// a third party key provisioned into this device
+    ThirdParty = 2,
+    /// the developer key, whose private half is public, so anyone can sign with it
+    DevKey = 3,
+}
+
 #[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive, PartialEq, Eq)]
 pub enum PasswordRetentionPolicy {
     AlwaysKeep,
@@ -2157,6 +2157,39 @@ impl<'a> RootKeys {
         sigtype
     }
 
+    /// Checks the signature on an app image. It's laid out like a signed kernel: a signature record in the first
+    /// `SIGBLOCK_SIZE` bytes, followed by the signed data. The keys are tried in the same order as for the gateware.
+    pub fn check_app_signature(&mut self, image: &[u8]) -> SignatureResult {
+        if image.len() < SIGBLOCK_SIZE as usize {
+            return SignatureResult::Invalid;
+        }
+        let sig_rec: &SignatureInFlash = unsafe{(image.as_ptr() as *const SignatureInFlash).as_ref().unwrap()};
+        let signed_len = sig_rec.signed_len as usize;
+        if sig_rec.version != SIG_VERSION || signed_len > image.len() - SIGBLOCK_SIZE as usize {
+            log::warn!("app image has a malformed signature record");
+            return SignatureResult::Invalid;
+        }
+        let sig = Signature::new(sig_rec.signature);
+        let signed = &image[SIGBLOCK_SIZE as usize..SIGBLOCK_SIZE as usize + signed_len];
+
+        for &(loc, sigtype) in [
+            (KeyRomLocs::SELFSIGN_PUBKEY, SignatureResult::SelfSignOk),
+            (KeyRomLocs::THIRDPARTY_PUBKEY, SignatureResult::ThirdPartyOk),
+            (KeyRomLocs::DEVELOPER_PUBKEY, SignatureResult::DevKeyOk),
+        ].iter() {
+            let pubkey_bytes = self.read_key_256(loc);
+            // skip keys that were never provisioned
+            if pubkey_bytes.iter().all(|&b| b == 0) {
+                continue;
+            }
+            let pubkey = PublicKey::from_bytes(&pubkey_bytes).expect("public key was not valid");
+            if pubkey.verify_strict(signed, &sig).is_ok() {
+                return sigtype;
+            }
+        }
+        SignatureResult::Invalid
+    }
+
     pub fn fetch_gw_metadata(&self, region_enum: GatewareRegion) -> MetadataInFlash {
         let region = match region_enum {
             GatewareRegion::Boot => self.gateware(),
@@ -100,6 +100,23 @@ impl RootKeys {
         }
     }
 
+    /// this will check the signature on an app image, as made by `tools/sign_image.py --app-image`.
+    /// `image` must be page-aligned, and its first `len` bytes hold the image; it is lent to
+    /// the key server for the duration of the check.
+    /// returns the kind of key the app was signed with, or None if no key known to this device signed it
+    pub fn check_app_signature(&self, image: xous::MemoryRange, len: usize) -> Result<Option<AppSigner>, xous::Error> {
+        let request = xous::MemoryMessage {
+            id: Opcode::CheckAppSignature.to_usize().unwrap(),
+            buf: image,
+            offset: None,
+            valid: xous::MemorySize::new(len),
+        };
+        match send_message(self.conn, Message::MutableBorrow(request))? {
+            xous::Result::MemoryReturned(_, valid) => Ok(valid.and_then(|v| FromPrimitive::from_usize(v.get()))),
+            _ => Err(xous::Error::InternalError),
+        }
+    }
+
     pub fn is_efuse_secured(&self) -> Result<Option<bool>, xous::Error> {
         let response = send_message(self.conn,
             Message::new_blocking_scalar(Opcode::IsEfuseSecured.to_usize().unwrap(), 0, 0, 0, 0)
@@ -32,6 +32,7 @@ const CONSOLE_SENTINEL: &'static str = "CONS_SENTINEL|";
 #[cfg(any(target_os = "none", target_os = "xous"))]
 mod bcrypt;
 
+#[derive(Copy, Clone)]
 pub enum SignatureResult {
     SelfSignOk,
     ThirdPartyOk,
@@ -192,6 +193,9 @@ mod implementation {
             log::info!("done");
             SignatureResult::DevKeyOk
         }
+        pub fn check_app_signature(&mut self, _image: &[u8]) -> SignatureResult {
+            SignatureResult::DevKeyOk
+        }
         pub fn is_pcache_update_password_valid(&self) -> bool {
             false
         }
@@ -312,8 +316,14 @@ fn xmain() -> ! {
           1. Shellchat for test initiation
           2. Main menu -> trigger initialization
           3. PDDB
+          4. App loader, to check app signatures
     */
-    let keys_sid = xns.register_name(api::SERVER_NAME_KEYS, Some(3)).expect("can't register server");
+    // the key oracles are in here too, so nothing else may reach us, even after these connections are made
+    let keys_sid = xns.register_name_with_allow_list(
+        api::SERVER_NAME_KEYS,
+        Some(4),
+        &["shellchat", "status", "pddb", "app-loader"],
+    ).expect("can't register server");
 
     let mut keys = RootKeys::new();
     log::info!("Boot FPGA key source: {:?}", keys.fpga_key_source());
@@ -1070,6 +1080,19 @@ fn xmain() -> ! {
                     xous::return_scalar(msg.sender, 2).expect("couldn't send return value");
                 }
             }),
+            Some(Opcode::CheckAppSignature) => {
+                let mem = msg.body.memory_message_mut().unwrap();
+                let len = mem.valid.map(|v| v.get()).unwrap_or(0).min(mem.buf.len());
+                let image = &mem.buf.as_slice::<u8>()[..len];
+                let signer = match keys.check_app_signature(image) {
+                    SignatureResult::SelfSignOk => Some(AppSigner::SelfSign),
+                    SignatureResult::ThirdPartyOk => Some(AppSigner::ThirdParty),
+                    SignatureResult::DevKeyOk => Some(AppSigner::DevKey),
+                    SignatureResult::Invalid => None,
+                };
+                // the signer goes back in `valid`; the image itself is left alone
+                mem.valid = signer.and_then(|s| xous::MemorySize::new(s.to_usize().unwrap()));
+            }
             Some(Opcode::TestUx) => msg_blocking_scalar_unpack!(msg, _arg, _, _, _, {
                 // dummy test for now
                 xous::return_scalar(msg.sender, 1234).unwrap();
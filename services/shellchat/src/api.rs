@@ -0,0 +1,59 @@
+use xous_ipc::String;
+
+// nothing prevents the two from being the same, other than naming conventions
+pub const SERVER_NAME_SHELLCHAT: &str = "_Shell chat application_"; // used internally by xous-names
+
+#[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive)]
+pub enum ShellOpcode {
+    /// a line of text has arrived
+    Line = 0, // make sure we occupy opcodes with discriminants < 1000, as the rest are used for callbacks
+    /// redraw our UI
+    Redraw,
+    /// change focus
+    ChangeFocus,
+    /// the user agreed to run the startup script, lent as a `String`. Only taken from shellchat's own threads.
+    RunStartup,
+    /// add a verb handled by another server
+    RegisterVerb,
+    /// take back a verb added with `RegisterVerb`
+    UnregisterVerb,
+    /// the response to a registered verb, from the server that handles it, as a `VerbResponse`
+    VerbResponse,
+    /// complete a line for the shell predictor, lent as an `ime_plugin_shell::Completion`
+    Complete,
+    /// exit the application
+    Quit,
+}
+
+/// A verb added to the shell by another server
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct VerbRegistration {
+    /// what's typed to run it: one word, with no spaces
+    pub verb: String::<32>,
+    /// shown when the verb is typed on its own
+    pub help: String::<256>,
+    /// the server the verb's arguments are sent to
+    pub sid: [u32; 4],
+    /// the opcode they're sent with
+    pub opcode: u32,
+    /// set by the shell: false if the verb wasn't added
+    pub accepted: bool,
+}
+
+/// Sent to the server of a registered verb, as a memory message, when the verb is typed with arguments
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct VerbInvocation {
+    pub verb: String::<32>,
+    /// everything typed after the verb
+    pub args: String::<1024>,
+    /// handed back with the response, which is dropped unless it matches an invocation still waiting on one
+    pub token: u32,
+}
+
+/// Sent back by the server of a registered verb with the text to show
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Copy, Clone)]
+pub struct VerbResponse {
+    /// the `token` of the `VerbInvocation` being answered
+    pub token: u32,
+    pub text: String::<1024>,
+}
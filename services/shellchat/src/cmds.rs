@@ -2,7 +2,7 @@ use xous::{MessageEnvelope};
 use xous_ipc::String;
 use core::fmt::Write;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, Mutex};
 /////////////////////////// Common items to all commands
 pub trait ShellCmdApi<'a> {
@@ -14,6 +14,10 @@ pub trait ShellCmdApi<'a> {
         log::info!("received unhandled message {:?}", msg);
         Ok(None)
     }
+    // called to offer ways to complete the arguments typed so far; each is the whole of the arguments, completed
+    fn complete(&mut self, _args: &str, _env: &mut CommonEnv) -> Vec::<std::string::String> {
+        Vec::new()
+    }
 
     // created with cmd_api! macro
     // checks if the command matches the current verb in question
@@ -37,6 +41,19 @@ macro_rules! cmd_api {
     };
 }
 
+/// Completes the last word of `args` with each of `words` that it's the start of, for `complete()`. The
+/// earlier words are kept as they are.
+pub fn complete_last<'a>(args: &str, words: impl IntoIterator<Item = &'a str>) -> Vec::<std::string::String> {
+    let (before, last) = match args.rfind(' ') {
+        Some(space) => args.split_at(space + 1),
+        None => ("", args),
+    };
+    words.into_iter()
+        .filter(|word| word.starts_with(last) && *word != last)
+        .map(|word| format!("{}{}", before, word))
+        .collect()
+}
+
 use trng::*;
 /////////////////////////// Command shell integration
 pub struct CommonEnv {
@@ -50,6 +67,12 @@ pub struct CommonEnv {
     trng: Trng,
     netmgr: net::NetManager,
     xns: xous_names::XousNames,
+    /// commands from a script started by `run`, still to be run
+    script: VecDeque::<String::<1024>>,
+    /// set while the commands of a script are being run
+    script_running: bool,
+    /// shellchat's app token, for GAM calls that are only honored for the app with focus
+    gam_token: [u32; 4],
 }
 impl CommonEnv {
     pub fn register_handler(&mut self, verb: String::<256>) -> u32 {
@@ -72,7 +95,7 @@ impl CommonEnv {
         1. mod/use the new command
         2. create an entry for the command's storage in the CmdEnv structure
         3. initialize the persistant storage here
-        4. add it to the "commands" array in the dispatch() routine below
+        4. add it to the "commands" array in the commands! macro below
 
     Side note: if your command doesn't require persistent storage, you could,
     technically, generate the command dynamically every time it's called. Echo
@@ -101,6 +124,14 @@ mod wlan;     use wlan::*;
 mod jtag_cmd; use jtag_cmd::*;
 mod net_cmd;  use net_cmd::*;
 mod pddb_cmd; use pddb_cmd::*;
+mod screenshot; use screenshot::*;
+mod crash;    use crash::*;
+mod app;      use app::*;
+mod ipctrace; use ipctrace::*;
+mod prof;     use prof::*;
+mod run;      use run::*;
+mod top;      use top::*;
+pub(crate) use run::{confirm_startup, read_script, script_lines, STARTUP_SCRIPT};
 
 #[cfg(feature="tts")]
 mod tts;
@@ -119,12 +150,55 @@ use sha::*;
 mod aes_cmd;
 #[cfg(feature="benchmarks")]
 use aes_cmd::*;
+#[cfg(feature="benchmarks")]
+mod bench;
+#[cfg(feature="benchmarks")]
+use bench::*;
 //mod fcc;      use fcc::*;
 //mod pds; // dependency of the FCC file
 
+/// A verb registered by another server: what's typed after it is sent there, to be answered in its own time
+struct ExternalVerb {
+    help: String::<256>,
+    sid: [u32; 4],
+    conn: xous::CID,
+    opcode: u32,
+}
+
+/// how many invocations of external verbs are remembered as waiting on a response; past this the oldest is
+/// forgotten, as a server may never respond
+const PENDING_VERBS_MAX: usize = 8;
+
+/// The tokens of the external verb invocations still waiting on a response
+#[derive(Default)]
+struct PendingVerbs {
+    tokens: VecDeque::<u32>,
+}
+impl PendingVerbs {
+    fn add(&mut self, token: u32) {
+        if self.tokens.len() >= PENDING_VERBS_MAX {
+            self.tokens.pop_front();
+        }
+        self.tokens.push_back(token);
+    }
+    /// Whether `token` was waiting on a response; it isn't any more
+    fn take(&mut self, token: u32) -> bool {
+        match self.tokens.iter().position(|&t| t == token) {
+            Some(index) => {
+                self.tokens.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
 pub struct CmdEnv {
     common_env: CommonEnv,
     lastverb: String::<256>,
+    /// verbs registered by other servers, by verb
+    external: HashMap::<std::string::String, ExternalVerb>,
+    pending_verbs: PendingVerbs,
     ///// 2. declare storage for your command here.
     test_cmd: Test,
     sleep_cmd: Sleep,
@@ -141,7 +215,14 @@ pub struct CmdEnv {
     jtag_cmd: JtagCmd,
     net_cmd: NetCmd,
     pddb_cmd: PddbCmd,
+    screenshot_cmd: Screenshot,
+    crash_cmd: Crash,
+    app_cmd: App,
+    ipctrace_cmd: IpcTrace,
+    prof_cmd: Prof,
     wlan_cmd: Wlan,
+    run_cmd: Run,
+    top_cmd: Top,
 
     #[cfg(feature="tts")]
     tts_cmd: Tts,
@@ -152,10 +233,69 @@ pub struct CmdEnv {
     aes_cmd: Aes,
     #[cfg(feature="benchmarks")]
     engine_cmd: Engine,
+    #[cfg(feature="benchmarks")]
+    bench_cmd: Bench,
     //fcc_cmd: Fcc,
 }
+
+/// Lays out every command in the slice `$commands`, borrowing the storage in CmdEnv `$env`, so they can be
+/// searched through
+macro_rules! commands {
+    ($env:ident, $commands:ident) => {
+        let mut echo_cmd = Echo {}; // this command has no persistent storage, so we can "create" it every time the commands are looked up (but it's a zero-cost absraction so this doesn't actually create any instructions)
+        let mut ver_cmd = Ver{};
+        let mut backlight_cmd = Backlight{};
+        let mut accel_cmd = Accel{};
+        let mut console_cmd = Console{};
+        let $commands: &mut [& mut dyn ShellCmdApi] = &mut [
+            ///// 4. add your command to this array, so that it can be looked up and dispatched
+            &mut echo_cmd,
+            &mut $env.test_cmd,
+            &mut $env.sleep_cmd,
+            &mut $env.sensors_cmd,
+            //&mut $env.callback_cmd,
+            &mut $env.rtc_cmd,
+            &mut $env.vibe_cmd,
+            &mut $env.ssid_cmd,
+            &mut ver_cmd,
+            //&mut $env.audio_cmd,
+            &mut backlight_cmd,
+            &mut accel_cmd,
+            &mut $env.ecup_cmd,
+            &mut $env.trng_cmd,
+            &mut console_cmd,
+            // &mut $env.memtest_cmd,
+            &mut $env.keys_cmd,
+            &mut $env.wlan_cmd,
+            &mut $env.jtag_cmd,
+            &mut $env.net_cmd,
+            &mut $env.pddb_cmd,
+            &mut $env.screenshot_cmd,
+            &mut $env.crash_cmd,
+            &mut $env.app_cmd,
+            &mut $env.ipctrace_cmd,
+            &mut $env.prof_cmd,
+            &mut $env.run_cmd,
+            &mut $env.top_cmd,
+
+            #[cfg(feature="tts")]
+            &mut $env.tts_cmd,
+
+            #[cfg(feature="benchmarks")]
+            &mut $env.sha_cmd,
+            #[cfg(feature="benchmarks")]
+            &mut $env.aes_cmd,
+            #[cfg(feature="benchmarks")]
+            &mut $env.engine_cmd,
+            #[cfg(feature="benchmarks")]
+            &mut $env.bench_cmd,
+            //&mut $env.fcc_cmd,
+        ];
+    };
+}
+
 impl CmdEnv {
-    pub fn new(xns: &xous_names::XousNames) -> CmdEnv {
+    pub fn new(xns: &xous_names::XousNames, gam_token: [u32; 4]) -> CmdEnv {
         let ticktimer = ticktimer_server::Ticktimer::new().expect("Couldn't connect to Ticktimer");
         let mut common = CommonEnv {
             llio: llio::Llio::new(&xns),
@@ -168,6 +308,9 @@ impl CmdEnv {
             trng: Trng::new(&xns).unwrap(),
             xns: xous_names::XousNames::new().unwrap(),
             netmgr: net::NetManager::new(),
+            script: VecDeque::new(),
+            script_running: false,
+            gam_token,
         };
         //let fcc = Fcc::new(&mut common);
         #[cfg(feature="benchmarks")]
@@ -195,6 +338,8 @@ impl CmdEnv {
         CmdEnv {
             common_env: common,
             lastverb: String::<256>::new(),
+            external: HashMap::new(),
+            pending_verbs: PendingVerbs::default(),
             ///// 3. initialize your storage, by calling new()
             test_cmd: Test::new(&xns),
             sleep_cmd: Sleep::new(&xns),
@@ -211,7 +356,14 @@ impl CmdEnv {
             jtag_cmd: JtagCmd::new(&xns),
             net_cmd: NetCmd::new(&xns),
             pddb_cmd: PddbCmd::new(&xns),
+            screenshot_cmd: Screenshot::new(),
+            crash_cmd: Crash::new(),
+            app_cmd: App::new(&xns),
+            ipctrace_cmd: IpcTrace::new(),
+            prof_cmd: Prof::new(),
             wlan_cmd: Wlan::new(),
+            run_cmd: Run::new(),
+            top_cmd: Top::new(&xns),
 
             #[cfg(feature="tts")]
             tts_cmd: Tts::new(&xns),
@@ -222,53 +374,130 @@ impl CmdEnv {
             aes_cmd: aes,
             #[cfg(feature="benchmarks")]
             engine_cmd: engine,
+            #[cfg(feature="benchmarks")]
+            bench_cmd: Bench::new(&xns),
             //fcc_cmd: fcc,
         }
     }
 
-    pub fn dispatch(&mut self, maybe_cmdline: Option<&mut String::<1024>>, maybe_callback: Option<&MessageEnvelope>) -> Result<Option<String::<1024>>, xous::Error> {
-        let mut ret = String::<1024>::new();
+    /// Queues the commands of `text`, a script the user has already agreed to run, as if it had been started
+    /// with `run`
+    pub fn queue_script(&mut self, text: &str) {
+        self.common_env.script.extend(script_lines(text));
+    }
 
-        let mut echo_cmd = Echo {}; // this command has no persistent storage, so we can "create" it every time we call dispatch (but it's a zero-cost absraction so this doesn't actually create any instructions)
-        let mut ver_cmd = Ver{};
-        let mut backlight_cmd = Backlight{};
-        let mut accel_cmd = Accel{};
-        let mut console_cmd = Console{};
-        let commands: &mut [& mut dyn ShellCmdApi] = &mut [
-            ///// 4. add your command to this array, so that it can be looked up and dispatched
-            &mut echo_cmd,
-            &mut self.test_cmd,
-            &mut self.sleep_cmd,
-            &mut self.sensors_cmd,
-            //&mut self.callback_cmd,
-            &mut self.rtc_cmd,
-            &mut self.vibe_cmd,
-            &mut self.ssid_cmd,
-            &mut ver_cmd,
-            //&mut self.audio_cmd,
-            &mut backlight_cmd,
-            &mut accel_cmd,
-            &mut self.ecup_cmd,
-            &mut self.trng_cmd,
-            &mut console_cmd,
-            // &mut self.memtest_cmd,
-            &mut self.keys_cmd,
-            &mut self.wlan_cmd,
-            &mut self.jtag_cmd,
-            &mut self.net_cmd,
-            &mut self.pddb_cmd,
+    /// The next command of the script being run, if there's one left. A script's commands are dispatched
+    /// one at a time by the caller, just like typed lines, so each gets its own response.
+    pub fn next_script_line(&mut self) -> Option<String::<1024>> {
+        let line = self.common_env.script.pop_front();
+        self.common_env.script_running = line.is_some();
+        line
+    }
 
-            #[cfg(feature="tts")]
-            &mut self.tts_cmd,
+    /// The ways `line` could be completed, each as the whole line: verbs while the first word is still being
+    /// typed, then whatever the verb's command offers for its arguments.
+    pub fn complete(&mut self, line: &str) -> Vec::<std::string::String> {
+        commands!(self, commands);
 
-            #[cfg(feature="benchmarks")]
-            &mut self.sha_cmd,
-            #[cfg(feature="benchmarks")]
-            &mut self.aes_cmd,
-            #[cfg(feature="benchmarks")]
-            &mut self.engine_cmd,
-            //&mut self.fcc_cmd,
-        ];
+        match line.split_once(' ') {
+            None => {
+                let mut verbs: Vec<&str> = commands.iter().map(|cmd| cmd.verb()).collect();
+                verbs.extend(self.external.keys().map(|verb| verb.as_str()));
+                verbs.sort();
+                // a space after the verb, ready for its arguments
+                complete_last(line, verbs).into_iter().map(|verb| format!("{} ", verb)).collect()
+            }
+            Some((verb, args)) => {
+                for cmd in commands.iter_mut() {
+                    if cmd.matches(verb) {
+                        return cmd.complete(args, &mut self.common_env)
+                            .into_iter()
+                            .map(|args| format!("{} {}", verb, args))
+                            .collect();
+                    }
+                }
+                Vec::new()
+            }
+        }
+    }
+
+    /// Adds a verb handled by another server. Returns false if another server already has it, or it isn't
+    /// one word. A registered verb with the name of a built-in command is never run, as the built-in is found
+    /// first.
+    pub fn register_external(&mut self, registration: &shellchat::api::VerbRegistration) -> bool {
+        let verb = registration.verb.to_str();
+        if verb.is_empty() || verb.contains(' ') || self.external.contains_key(verb) {
+            return false;
+        }
+        match xous::connect(xous::SID::from_array(registration.sid)) {
+            Ok(conn) => {
+                log::info!("registered external verb {}", verb);
+                self.external.insert(verb.to_string(), ExternalVerb {
+                    help: registration.help,
+                    sid: registration.sid,
+                    conn,
+                    opcode: registration.opcode,
+                });
+                true
+            }
+            Err(e) => {
+                log::error!("couldn't connect to the server for external verb {}: {:?}", verb, e);
+                false
+            }
+        }
+    }
+    /// Takes back a verb added with `register_external()`, if it was added by the same server.
+    // the connection is left open, as the server may have other verbs registered through it
+    pub fn unregister_external(&mut self, registration: &shellchat::api::VerbRegistration) {
+        let verb = registration.verb.to_str();
+        if self.external.get(verb).map(|external| external.sid == registration.sid).unwrap_or(false) {
+            log::info!("unregistered external verb {}", verb);
+            self.external.remove(verb);
+        }
+    }
+    /// Whether a response with `token` answers an external verb still waiting on one. Each is only answered once.
+    pub fn take_verb_response(&mut self, token: u32) -> bool {
+        self.pending_verbs.take(token)
+    }
+    /// Sends `args` on to the server that registered `verb`. Nothing comes back here: the server responds
+    /// with a `VerbResponse` message when it's ready.
+    fn invoke_external(&mut self, verb: &str, args: &String::<1024>) -> Result<Option<String::<1024>>, xous::Error> {
+        let mut ret = String::<1024>::new();
+        let external = match self.external.get(verb) {
+            Some(external) => external,
+            None => return Ok(None),
+        };
+        if args.len() == 0 {
+            write!(ret, "{}", external.help.to_str()).unwrap();
+            return Ok(Some(ret));
+        }
+        // random, so another server can't guess its way into the response
+        let token = self.common_env.trng.get_u32().unwrap();
+        let invocation = shellchat::api::VerbInvocation {
+            verb: String::<32>::from_str(verb),
+            args: *args,
+            token,
+        };
+        let buf = xous_ipc::Buffer::into_buf(invocation).or(Err(xous::Error::InternalError))?;
+        match buf.send(external.conn, external.opcode) {
+            Ok(_) => {
+                self.pending_verbs.add(token);
+                Ok(None)
+            }
+            Err(e) => {
+                // the server has gone away, so the verb goes with it
+                log::error!("couldn't send external verb {}: {:?}", verb, e);
+                self.external.remove(verb);
+                write!(ret, "{} is no longer available", verb).unwrap();
+                Ok(Some(ret))
+            }
+        }
+    }
+
+    pub fn dispatch(&mut self, maybe_cmdline: Option<&mut String::<1024>>, maybe_callback: Option<&MessageEnvelope>) -> Result<Option<String::<1024>>, xous::Error> {
+        let mut ret = String::<1024>::new();
+
+        commands!(self, commands);
 
         if let Some(cmdline) = maybe_cmdline {
             let maybe_verb = tokenize(cmdline);
@@ -289,6 +518,11 @@ impl CmdEnv {
                     };
                 }
 
+                // then the verbs registered by other servers
+                if !match_found && self.external.contains_key(verb) {
+                    return self.invoke_external(verb, cmdline);
+                }
+
                 // if none match, create a list of available commands
                 if !match_found {
                     let mut first = true;
@@ -300,6 +534,12 @@ impl CmdEnv {
                         ret.append(cmd.verb())?;
                         first = false;
                     }
+                    let mut external: Vec<&std::string::String> = self.external.keys().collect();
+                    external.sort();
+                    for verb in external {
+                        ret.append(", ")?;
+                        ret.append(verb)?;
+                    }
                     Ok(Some(ret))
                 } else {
                     cmd_ret
@@ -371,3 +611,32 @@ pub fn tokenize(line: &mut String::<1024>) -> Option<String::<1024>> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verb_responses_are_taken_once() {
+        let mut pending = PendingVerbs::default();
+        pending.add(7);
+        pending.add(9);
+        assert!(!pending.take(8));
+        assert!(pending.take(9));
+        assert!(!pending.take(9));
+        assert!(pending.take(7));
+        assert!(!pending.take(7));
+    }
+
+    #[test]
+    fn oldest_pending_verb_is_forgotten() {
+        let mut pending = PendingVerbs::default();
+        for token in 0..=PENDING_VERBS_MAX as u32 {
+            pending.add(token);
+        }
+        assert!(!pending.take(0));
+        for token in 1..=PENDING_VERBS_MAX as u32 {
+            assert!(pending.take(token));
+        }
+    }
+}
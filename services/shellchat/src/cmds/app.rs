@@ -0,0 +1,69 @@
+use crate::{ShellCmdApi, CommonEnv};
+use xous_ipc::String;
+
+pub struct App {
+    loader: app_loader::AppLoader,
+}
+impl App {
+    pub fn new(xns: &xous_names::XousNames) -> App {
+        App {
+            loader: app_loader::AppLoader::new(xns).expect("couldn't connect to the app loader"),
+        }
+    }
+}
+
+impl<'a> ShellCmdApi<'a> for App {
+    cmd_api!(app); // inserts boilerplate for command API
+
+    fn process(&mut self, args: String::<1024>, _env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+        use core::fmt::Write;
+        let mut ret = String::<1024>::new();
+        let helpstring = "app [list] [run <name>] [kill <pid>] [remove <name>]";
+
+        let mut tokens = args.as_str().unwrap().split(' ');
+        match tokens.next() {
+            Some("list") | Some("") | None => {
+                match self.loader.list() {
+                    Ok(apps) if apps.is_empty() => write!(ret, "No apps installed").unwrap(),
+                    Ok(apps) => {
+                        for app in apps.iter() {
+                            let written = match app.pid {
+                                Some(pid) => write!(ret, "{} (running as PID {})\n", app.name, pid),
+                                None => write!(ret, "{}\n", app.name),
+                            };
+                            if written.is_err() {
+                                break; // overflowed return buffer
+                            }
+                        }
+                    }
+                    Err(e) => write!(ret, "Couldn't list apps: {:?}", e).unwrap(),
+                }
+            }
+            Some("run") => match tokens.next() {
+                Some(name) => match self.loader.launch(name) {
+                    Ok(pid) => write!(ret, "Started {} as PID {}", name, pid.get()).unwrap(),
+                    Err(e) => write!(ret, "Couldn't start {}: {:?}", name, e).unwrap(),
+                },
+                None => write!(ret, "{}", helpstring).unwrap(),
+            },
+            Some("kill") => match tokens.next().and_then(|p| p.parse::<u8>().ok()).and_then(xous::PID::new) {
+                Some(pid) => match self.loader.kill(pid) {
+                    Ok(_) => write!(ret, "Stopped PID {}", pid.get()).unwrap(),
+                    Err(e) => write!(ret, "Couldn't stop PID {}: {:?}", pid.get(), e).unwrap(),
+                },
+                None => write!(ret, "{}", helpstring).unwrap(),
+            },
+            Some("remove") => match tokens.next() {
+                Some(name) => match self.loader.uninstall(name) {
+                    Ok(_) => write!(ret, "Removed {}", name).unwrap(),
+                    Err(e) => write!(ret, "Couldn't remove {}: {:?}", name, e).unwrap(),
+                },
+                None => write!(ret, "{}", helpstring).unwrap(),
+            },
+            _ => {
+                write!(ret, "{}", helpstring).unwrap();
+            }
+        }
+        Ok(Some(ret))
+    }
+}
@@ -0,0 +1,298 @@
+use crate::{ShellCmdApi, CommonEnv};
+use xous::MessageEnvelope;
+use xous_ipc::String;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use aes::{Aes256, Aes256Soft};
+use cipher::{BlockDecrypt, BlockEncrypt, NewBlockCipher};
+use cipher::generic_array::GenericArray;
+use digest::Digest;
+use rand_core::RngCore;
+use sha2::FallbackStrategy;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Suite {
+    Sha,
+    Aes,
+    Curve25519,
+}
+
+#[derive(Copy, Clone)]
+enum Op {
+    Sha512,
+    AesEncrypt,
+    AesDecrypt,
+    X25519,
+    Ed25519Sign,
+    Ed25519Verify,
+}
+impl Op {
+    fn suite(&self) -> Suite {
+        match self {
+            Op::Sha512 => Suite::Sha,
+            Op::AesEncrypt | Op::AesDecrypt => Suite::Aes,
+            Op::X25519 | Op::Ed25519Sign | Op::Ed25519Verify => Suite::Curve25519,
+        }
+    }
+    fn name(&self) -> &'static str {
+        match self {
+            Op::Sha512 => "sha512",
+            Op::AesEncrypt => "aes256 enc",
+            Op::AesDecrypt => "aes256 dec",
+            Op::X25519 => "x25519",
+            Op::Ed25519Sign => "ed25519 sign",
+            Op::Ed25519Verify => "ed25519 verify",
+        }
+    }
+}
+
+/// One line of the results: `iters` of `op` on `len` bytes, done by the hardware or in software
+struct Round {
+    op: Op,
+    hw: bool,
+    len: usize,
+    iters: usize,
+}
+const fn round(op: Op, hw: bool, len: usize, iters: usize) -> Round { Round { op, hw, len, iters } }
+
+/// the largest `len` of any round
+const MAX_LEN: usize = 8192;
+/// Run in this order, and reported a suite at a time. Iterations are picked so each round takes about
+/// a second on hardware: the ticktimer only counts milliseconds, so short rounds would be all rounding.
+/// Curve25519 is always done by the engine, as the dalek crates are patched to use it.
+const ROUNDS: [Round; 21] = [
+    round(Op::Sha512, true, 64, 200),
+    round(Op::Sha512, false, 64, 200),
+    round(Op::Sha512, true, 1024, 100),
+    round(Op::Sha512, false, 1024, 100),
+    round(Op::Sha512, true, 8192, 50),
+    round(Op::Sha512, false, 8192, 20),
+    round(Op::AesEncrypt, true, 16, 2000),
+    round(Op::AesEncrypt, false, 16, 2000),
+    round(Op::AesDecrypt, true, 16, 2000),
+    round(Op::AesDecrypt, false, 16, 2000),
+    round(Op::AesEncrypt, true, 1024, 200),
+    round(Op::AesEncrypt, false, 1024, 100),
+    round(Op::AesDecrypt, true, 1024, 200),
+    round(Op::AesDecrypt, false, 1024, 100),
+    round(Op::AesEncrypt, true, 8192, 20),
+    round(Op::AesEncrypt, false, 8192, 10),
+    round(Op::AesDecrypt, true, 8192, 20),
+    round(Op::AesDecrypt, false, 8192, 10),
+    round(Op::X25519, true, 32, 50),
+    round(Op::Ed25519Sign, true, 64, 20),
+    round(Op::Ed25519Verify, true, 64, 20),
+];
+
+/// Runs `round`, returning how many milliseconds it took and whether every result checked out
+fn run_round(round: &Round, data: &[u8], tt: &ticktimer_server::Ticktimer, trng: &mut trng::Trng) -> (u64, bool) {
+    let data = &data[..round.len];
+    let mut pass = true;
+    match round.op {
+        Op::Sha512 => {
+            let strategy = if round.hw { FallbackStrategy::WaitForHardware } else { FallbackStrategy::SoftwareOnly };
+            // the other mode's digest is the reference, so a broken hasher can't agree with itself
+            let mut reference = sha2::Sha512::new_with_strategy(
+                if round.hw { FallbackStrategy::SoftwareOnly } else { FallbackStrategy::WaitForHardware });
+            reference.update(data);
+            let expected = reference.finalize();
+            let start = tt.elapsed_ms();
+            for _ in 0..round.iters {
+                let mut hasher = sha2::Sha512::new_with_strategy(strategy);
+                hasher.update(data);
+                if hasher.finalize() != expected {
+                    pass = false;
+                }
+            }
+            (tt.elapsed_ms() - start, pass)
+        }
+        Op::AesEncrypt | Op::AesDecrypt => {
+            let mut key = [0u8; 32];
+            trng.fill_bytes(&mut key);
+            let key = GenericArray::from_slice(&key);
+            let cipher_hw = Aes256::new(key);
+            let cipher_sw = Aes256Soft::new(key);
+            let encrypt = |buf: &mut [u8], hw: bool| {
+                for chunk in buf.chunks_exact_mut(aes::BLOCK_SIZE) {
+                    let block = GenericArray::from_mut_slice(chunk);
+                    if hw { cipher_hw.encrypt_block(block) } else { cipher_sw.encrypt_block(block) }
+                }
+            };
+            let decrypt = |buf: &mut [u8], hw: bool| {
+                for chunk in buf.chunks_exact_mut(aes::BLOCK_SIZE) {
+                    let block = GenericArray::from_mut_slice(chunk);
+                    if hw { cipher_hw.decrypt_block(block) } else { cipher_sw.decrypt_block(block) }
+                }
+            };
+            // the ciphertext comes from the other mode, so both directions are checked against it
+            let mut ciphertext = data.to_vec();
+            encrypt(&mut ciphertext, !round.hw);
+            let mut buf = vec![0u8; round.len];
+            let start = tt.elapsed_ms();
+            for _ in 0..round.iters {
+                if let Op::AesEncrypt = round.op {
+                    buf.copy_from_slice(data);
+                    encrypt(&mut buf, round.hw);
+                    if buf != ciphertext {
+                        pass = false;
+                    }
+                } else {
+                    buf.copy_from_slice(&ciphertext);
+                    decrypt(&mut buf, round.hw);
+                    if buf != data {
+                        pass = false;
+                    }
+                }
+            }
+            (tt.elapsed_ms() - start, pass)
+        }
+        Op::X25519 => {
+            use x25519_dalek::{PublicKey, StaticSecret};
+            let alice_secret = StaticSecret::new(&mut *trng);
+            let bob_secret = StaticSecret::new(&mut *trng);
+            let bob_public = PublicKey::from(&bob_secret);
+            let expected = bob_secret.diffie_hellman(&PublicKey::from(&alice_secret));
+            let start = tt.elapsed_ms();
+            for _ in 0..round.iters {
+                if alice_secret.diffie_hellman(&bob_public).as_bytes() != expected.as_bytes() {
+                    pass = false;
+                }
+            }
+            (tt.elapsed_ms() - start, pass)
+        }
+        Op::Ed25519Sign | Op::Ed25519Verify => {
+            use ed25519_dalek::{Keypair, Signer, Verifier};
+            let keypair = Keypair::generate(&mut *trng);
+            let signature = keypair.sign(data);
+            let start = tt.elapsed_ms();
+            for _ in 0..round.iters {
+                if let Op::Ed25519Sign = round.op {
+                    if keypair.sign(data) != signature {
+                        pass = false;
+                    }
+                } else if keypair.public.verify(data, &signature).is_err() {
+                    pass = false;
+                }
+            }
+            (tt.elapsed_ms() - start, pass)
+        }
+    }
+}
+
+pub struct Bench {
+    callback_id: Option<u32>,
+    callback_conn: xous::CID,
+    /// set while the rounds are being run, so only one run goes at a time
+    running: Arc<AtomicBool>,
+    /// the results of the suite being run, shown once its last round is done
+    report: std::string::String,
+}
+impl Bench {
+    pub fn new(xns: &xous_names::XousNames) -> Self {
+        Bench {
+            callback_id: None,
+            callback_conn: xns.request_connection_blocking(crate::SERVER_NAME_SHELLCHAT).unwrap(),
+            running: Arc::new(AtomicBool::new(false)),
+            report: std::string::String::new(),
+        }
+    }
+
+    /// Runs every round on a thread of its own, sending a callback with the result of each:
+    /// (index of the round, milliseconds taken, whether it passed)
+    fn start(&mut self, callback_id: u32) {
+        let conn = self.callback_conn;
+        let running = self.running.clone();
+        std::thread::spawn(move || {
+            let xns = xous_names::XousNames::new().unwrap();
+            let mut trng = trng::Trng::new(&xns).unwrap();
+            let tt = ticktimer_server::Ticktimer::new().unwrap();
+            let mut data = vec![0u8; MAX_LEN];
+            trng.fill_bytes(&mut data);
+            for (index, round) in ROUNDS.iter().enumerate() {
+                let (elapsed, pass) = run_round(round, &data, &tt, &mut trng);
+                xous::send_message(conn,
+                    xous::Message::new_scalar(callback_id as usize, index, elapsed as usize, if pass {1} else {0}, 0)
+                ).expect("couldn't send bench result");
+            }
+            running.store(false, Ordering::SeqCst);
+        });
+    }
+}
+
+/**
+bench shell command:
+- bench crypto: time the hardware SHA-512, AES and Curve25519 engines, and the software versions of the
+  first two, over a range of sizes
+
+Each round is listed with its time per operation and its throughput, and FAIL if any of its results didn't
+match the other mode's (or, for Curve25519, its own). Results are shown a suite at a time, as the whole run
+takes about half a minute. A driver that's got slower, or fallen back to software, shows up as a hardware
+row no faster than the one below it.
+*/
+impl<'a> ShellCmdApi<'a> for Bench {
+    cmd_api!(bench); // inserts boilerplate for command API
+
+    fn process(&mut self, args: String::<1024>, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+        let callback_id = match self.callback_id {
+            Some(id) => id,
+            None => {
+                let id = env.register_handler(String::<256>::from_str(self.verb()));
+                self.callback_id = Some(id);
+                id
+            }
+        };
+        let mut ret = String::<1024>::new();
+        let helpstring = "bench [crypto]";
+
+        let mut tokens = args.as_str().unwrap().split(' ');
+        match tokens.next() {
+            Some("crypto") => {
+                if self.running.swap(true, Ordering::SeqCst) {
+                    write!(ret, "bench is already running").unwrap();
+                } else {
+                    self.report.clear();
+                    self.start(callback_id);
+                    write!(ret, "Starting {} crypto benchmark rounds", ROUNDS.len()).unwrap();
+                }
+            }
+            _ => write!(ret, "{}", helpstring).unwrap(),
+        }
+        Ok(Some(ret))
+    }
+
+    fn callback(&mut self, msg: &MessageEnvelope, _env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+        let mut result = None;
+        xous::msg_scalar_unpack!(msg, index, elapsed, pass, _, {
+            let round = match ROUNDS.get(index) {
+                Some(round) => round,
+                None => return Ok(None),
+            };
+            if self.report.is_empty() {
+                self.report.push_str("op mode bytes time/op rate\n");
+            }
+            // milliseconds are too coarse for one op, so it's in microseconds
+            let us_per_op = elapsed as u64 * 1000 / round.iters as u64;
+            let bytes_per_sec = if elapsed > 0 { (round.len * round.iters) as u64 * 1000 / elapsed as u64 } else { 0 };
+            self.report.push_str(&format!("{} {} {} {}us {}kB/s{}\n",
+                round.op.name(), if round.hw { "hw" } else { "sw" }, round.len, us_per_op, bytes_per_sec / 1024,
+                if pass != 0 { "" } else { " FAIL" }
+            ));
+            let suite_done = match ROUNDS.get(index + 1) {
+                Some(next) => next.op.suite() != round.op.suite(),
+                None => true,
+            };
+            if suite_done {
+                let mut ret = String::<1024>::new();
+                // a suite's rows fit, but be sure of it
+                if ret.append(&self.report).is_err() {
+                    log::warn!("bench results were cut short");
+                }
+                self.report.clear();
+                result = Some(ret);
+            }
+        });
+        Ok(result)
+    }
+}
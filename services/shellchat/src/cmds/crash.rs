@@ -0,0 +1,100 @@
+use crate::{ShellCmdApi, CommonEnv};
+use xous_ipc::String;
+use log_server::api::CRASH_REPORT_DICT;
+
+/// how much of a report goes in one reply, leaving room for the page count
+const PAGE_LEN: usize = 900;
+
+pub struct Crash {
+    pddb: pddb::Pddb,
+}
+impl Crash {
+    pub fn new() -> Crash {
+        Crash {
+            pddb: pddb::Pddb::new(),
+        }
+    }
+    fn read(&mut self, keyname: &str) -> Option<std::string::String> {
+        use std::io::Read;
+        let mut key = self.pddb.get(CRASH_REPORT_DICT, keyname, None, false, false, None, None::<fn()>).ok()?;
+        let mut report = Vec::<u8>::new();
+        key.read_to_end(&mut report).ok()?;
+        Some(std::string::String::from_utf8_lossy(&report).into_owned())
+    }
+}
+
+/// Splits a report into pages of whole lines, of at most `PAGE_LEN` bytes; a line longer than a page is cut
+/// at the last character that fits
+fn pages(report: &str) -> Vec<std::string::String> {
+    let mut pages = Vec::new();
+    let mut page = std::string::String::new();
+    for line in report.lines() {
+        let cut = line
+            .char_indices()
+            .map(|(i, c)| i + c.len_utf8())
+            .take_while(|&end| end < PAGE_LEN)
+            .last()
+            .unwrap_or(0);
+        let line = &line[..cut];
+        if page.len() + line.len() + 1 > PAGE_LEN {
+            pages.push(core::mem::take(&mut page));
+        }
+        page.push_str(line);
+        page.push('\n');
+    }
+    if !page.is_empty() {
+        pages.push(page);
+    }
+    pages
+}
+
+impl<'a> ShellCmdApi<'a> for Crash {
+    cmd_api!(crash); // inserts boilerplate for command API
+
+    fn process(&mut self, args: String::<1024>, _env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+        use core::fmt::Write;
+        let mut ret = String::<1024>::new();
+        let helpstring = "crash [list] [show <number> [page]] [clear]";
+
+        let mut tokens = args.as_str().unwrap().split(' ');
+        match tokens.next() {
+            Some("list") | Some("") | None => {
+                let mut keys = self.pddb.list_keys(CRASH_REPORT_DICT, None).unwrap_or_default();
+                keys.sort();
+                if keys.is_empty() {
+                    write!(ret, "No crash reports").unwrap();
+                }
+                for keyname in keys.iter() {
+                    let summary = self.read(keyname).and_then(|r| r.lines().next().map(|l| l.to_string()));
+                    if write!(ret, "{}: {}\n", keyname, summary.unwrap_or_default()).is_err() {
+                        break; // overflowed return buffer
+                    }
+                }
+            }
+            Some("show") => {
+                let keyname = tokens.next().and_then(|n| n.parse::<u32>().ok()).map(|n| format!("{:04}", n));
+                let page = tokens.next().and_then(|p| p.parse::<usize>().ok()).unwrap_or(1);
+                match keyname.and_then(|k| self.read(&k)) {
+                    Some(report) => {
+                        let pages = pages(&report);
+                        match pages.get(page.saturating_sub(1)) {
+                            Some(text) => write!(ret, "{}[page {} of {}]", text, page, pages.len()).unwrap(),
+                            None => write!(ret, "The report has {} pages", pages.len()).unwrap(),
+                        }
+                    }
+                    None => write!(ret, "No such crash report; 'crash list' shows what there is").unwrap(),
+                }
+            }
+            Some("clear") => {
+                match self.pddb.delete_dict(CRASH_REPORT_DICT, None) {
+                    Ok(_) => write!(ret, "Crash reports deleted").unwrap(),
+                    Err(e) => write!(ret, "Couldn't delete crash reports: {:?}", e).unwrap(),
+                }
+            }
+            _ => {
+                write!(ret, "{}", helpstring).unwrap();
+            }
+        }
+        Ok(Some(ret))
+    }
+}
@@ -0,0 +1,41 @@
+use crate::{ShellCmdApi, CommonEnv};
+use xous_ipc::String;
+
+#[derive(Debug)]
+pub struct IpcTrace {
+}
+impl IpcTrace {
+    pub fn new() -> IpcTrace {
+        IpcTrace {}
+    }
+}
+
+impl<'a> ShellCmdApi<'a> for IpcTrace {
+    cmd_api!(ipctrace); // inserts boilerplate for command API
+
+    fn process(&mut self, args: String::<1024>, _env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+        use core::fmt::Write;
+        let mut ret = String::<1024>::new();
+        let helpstring = "ipctrace [on] [off] [dump]";
+
+        let mut tokens = args.as_str().unwrap().split(' ');
+        match tokens.next() {
+            Some("on") => match log_server::set_ipc_trace(true) {
+                Ok(_) => write!(ret, "Tracing IPC; 'ipctrace dump' prints it to the log").unwrap(),
+                Err(e) => write!(ret, "Couldn't start the IPC trace: {:?}", e).unwrap(),
+            },
+            Some("off") => match log_server::set_ipc_trace(false) {
+                Ok(_) => write!(ret, "IPC trace stopped").unwrap(),
+                Err(e) => write!(ret, "Couldn't stop the IPC trace: {:?}", e).unwrap(),
+            },
+            Some("dump") => match log_server::dump_ipc_trace() {
+                Ok(_) => write!(ret, "IPC trace written to the log").unwrap(),
+                Err(e) => write!(ret, "Couldn't dump the IPC trace: {:?}", e).unwrap(),
+            },
+            _ => {
+                write!(ret, "{}", helpstring).unwrap();
+            }
+        }
+        Ok(Some(ret))
+    }
+}
@@ -55,10 +55,10 @@ impl<'a> ShellCmdApi<'a> for NetCmd {
         use core::fmt::Write;
         let mut ret = String::<1024>::new();
         #[cfg(any(target_os = "none", target_os = "xous"))]
-        let helpstring = "net [udp [port]] [udpclose] [udpclone] [udpcloneclose] [ping [host] [count]] [tcpget host/path]";
+        let helpstring = "net [udp [port]] [udpclose] [udpclone] [udpcloneclose] [ping [host] [count]] [tcpget host/path] [tlsget host/path] [stats [sockets]] [proxy [a.b.c.d:port | off]]";
         // no ping in hosted mode -- why would you need it? we're using the host's network connection.
         #[cfg(not(any(target_os = "none", target_os = "xous")))]
-        let helpstring = "net [udp [port]] [udpclose] [udpclone] [udpcloneclose] [count]] [tcpget host/path]";
+        let helpstring = "net [udp [port]] [udpclose] [udpclone] [udpcloneclose] [count]] [tcpget host/path] [tlsget host/path] [stats [sockets]] [proxy [a.b.c.d:port | off]]";
 
         let mut tokens = args.as_str().unwrap().split(' ');
 
@@ -69,7 +69,7 @@ impl<'a> ShellCmdApi<'a> for NetCmd {
                     // a form such as:
                     // bunniefoo.com./bunnie/test.txt
                     // It will break on everything else. The `url` crate is nice but "large" for a demo.
-                    // There is no https support, obvs.
+                    // For https, see tlsget.
                     if let Some(url) = tokens.next() {
                         match url.split_once('/') {
                             Some((host, path)) => {
@@ -87,26 +87,7 @@ impl<'a> ShellCmdApi<'a> for NetCmd {
                                                 log::debug!("write timeout: {:?}", stream.write_timeout().unwrap().unwrap().total_millis());
                                                 log::info!("my socket: {:?}", stream.socket_addr());
                                                 log::info!("peer addr: {:?}", stream.peer_addr());
-                                                log::info!("sending GET request");
-                                                match write!(stream, "GET /{} HTTP/1.1\r\n", path) {
-                                                    Ok(_) => log::trace!("sent GET"),
-                                                    Err(e) => {
-                                                        log::error!("GET err {:?}", e);
-                                                        write!(ret, "Error sending GET: {:?}", e).unwrap();
-                                                    }
-                                                }
-                                                write!(stream, "Host: {}\r\nAccept: */*\r\nUser-Agent: Precursor/0.9.6\r\n", host).expect("stream error");
-                                                write!(stream, "Connection: close\r\n").expect("stream error");
-                                                write!(stream, "\r\n").expect("stream error");
-                                                log::info!("fetching response....");
-                                                let mut buf = [0u8; 512];
-                                                match stream.read(&mut buf) {
-                                                    Ok(len) => {
-                                                        log::trace!("raw response ({}): {:?}", len, &buf[..len]);
-                                                        write!(ret, "{}", std::string::String::from_utf8_lossy(&buf[..len])).unwrap();
-                                                    }
-                                                    Err(e) => write!(ret, "Didn't get response from host: {:?}", e).unwrap(),
-                                                }
+                                                http_get(&mut stream, host, path, &mut ret);
                                             }
                                             Err(e) => write!(ret, "Couldn't connect to {}:80: {:?}", host, e).unwrap(),
                                         }
@@ -120,6 +101,35 @@ impl<'a> ShellCmdApi<'a> for NetCmd {
                         write!(ret, "Usage: tcpget bunniefoo.com/bunnie/test.txt").unwrap();
                     }
                 }
+                #[cfg(not(feature = "tls"))]
+                "tlsget" => {
+                    write!(ret, "This build doesn't have TLS support").unwrap();
+                }
+                #[cfg(feature = "tls")]
+                "tlsget" => {
+                    // same URL form as tcpget, but the fetch is made over TLS on port 443
+                    match tokens.next().and_then(|url| url.split_once('/')) {
+                        Some((host, path)) => match self.dns.lookup(host) {
+                            Ok(ipaddr) => {
+                                log::info!("resolved {} to {:?}", host, ipaddr);
+                                match net::TlsStream::connect_xous(host, (IpAddr::from(ipaddr), 443), Some(Duration::from_millis(5000))) {
+                                    Ok(mut stream) => {
+                                        stream.get_mut().set_read_timeout(Some(Duration::from_millis(10_000))).unwrap();
+                                        stream.get_mut().set_write_timeout(Some(Duration::from_millis(10_000))).unwrap();
+                                        match stream.handshake() {
+                                            Ok(_) => http_get(&mut stream, host, path, &mut ret),
+                                            Err(e) => write!(ret, "TLS handshake with {} failed: {:?}", host, e).unwrap(),
+                                        }
+                                        stream.close().ok();
+                                    }
+                                    Err(e) => write!(ret, "Couldn't connect to {}:443: {:?}", host, e).unwrap(),
+                                }
+                            }
+                            _ => write!(ret, "Couldn't resolve {}", host).unwrap(),
+                        },
+                        None => write!(ret, "Usage: tlsget bunniefoo.com/bunnie/test.txt").unwrap(),
+                    }
+                }
                 "server" => {
                     // PLEASE NOTE:
                     // Trying to make a TCP server of some kind? Don't be shy to open an issue at
@@ -254,6 +264,57 @@ impl<'a> ShellCmdApi<'a> for NetCmd {
                     self.udp_clone = None;
                     write!(ret, "Closed cloned UDP socket").unwrap();
                 }
+                "stats" => {
+                    let netmgr = net::NetManager::new();
+                    if tokens.next() == Some("sockets") {
+                        match netmgr.get_socket_stats() {
+                            Ok(list) => {
+                                for s in list {
+                                    write!(ret, "{:?}:{} pid {}: tx {}B/{} rx {}B/{}\n",
+                                        s.kind, s.local_port, s.pid,
+                                        s.counts.tx_bytes, s.counts.tx_packets,
+                                        s.counts.rx_bytes, s.counts.rx_packets,
+                                    ).ok(); // truncates if there are too many sockets to list
+                                }
+                            }
+                            Err(e) => write!(ret, "Couldn't get socket stats: {:?}", e).unwrap(),
+                        }
+                    } else {
+                        match netmgr.get_process_stats() {
+                            Ok(list) => {
+                                for p in list {
+                                    write!(ret, "pid {} ({} open): tx {}B/{} rx {}B/{}\n",
+                                        p.pid, p.open_sockets,
+                                        p.counts.tx_bytes, p.counts.tx_packets,
+                                        p.counts.rx_bytes, p.counts.rx_packets,
+                                    ).ok();
+                                }
+                            }
+                            Err(e) => write!(ret, "Couldn't get process stats: {:?}", e).unwrap(),
+                        }
+                    }
+                }
+                "proxy" => {
+                    let netmgr = net::NetManager::new();
+                    match tokens.next() {
+                        Some("off") => match netmgr.set_socks_proxy(None) {
+                            Ok(()) => write!(ret, "SOCKS5 proxy off").unwrap(),
+                            Err(e) => write!(ret, "Couldn't turn off proxy: {:?}", e).unwrap(),
+                        },
+                        Some(addr) => match addr.parse::<std::net::SocketAddrV4>() {
+                            Ok(proxy) => match netmgr.set_socks_proxy(Some(proxy)) {
+                                Ok(()) => write!(ret, "SOCKS5 proxy set to {}", proxy).unwrap(),
+                                Err(e) => write!(ret, "Couldn't set proxy: {:?}", e).unwrap(),
+                            },
+                            Err(_) => write!(ret, "usage: net proxy [a.b.c.d:port | off]").unwrap(),
+                        },
+                        None => match netmgr.get_socks_proxy() {
+                            Ok(Some(proxy)) => write!(ret, "SOCKS5 proxy is {}", proxy).unwrap(),
+                            Ok(None) => write!(ret, "No SOCKS5 proxy set").unwrap(),
+                            Err(e) => write!(ret, "Couldn't get proxy: {:?}", e).unwrap(),
+                        },
+                    }
+                }
                 "dns" => {
                     if let Some(name) = tokens.next() {
                         match self.dns.lookup(name) {
@@ -449,3 +510,28 @@ impl<'a> ShellCmdApi<'a> for NetCmd {
         Ok(Some(ret))
     }
 }
+
+/// Sends a minimal HTTP GET for `path` over `stream`, and puts the start of the response in `ret`
+fn http_get(stream: &mut (impl Read + Write), host: &str, path: &str, ret: &mut String::<1024>) {
+    use core::fmt::Write as FmtWrite;
+    log::info!("sending GET request");
+    match write!(stream, "GET /{} HTTP/1.1\r\n", path) {
+        Ok(_) => log::trace!("sent GET"),
+        Err(e) => {
+            log::error!("GET err {:?}", e);
+            write!(ret, "Error sending GET: {:?}", e).unwrap();
+        }
+    }
+    write!(stream, "Host: {}\r\nAccept: */*\r\nUser-Agent: Precursor/0.9.6\r\n", host).expect("stream error");
+    write!(stream, "Connection: close\r\n").expect("stream error");
+    write!(stream, "\r\n").expect("stream error");
+    log::info!("fetching response....");
+    let mut buf = [0u8; 512];
+    match stream.read(&mut buf) {
+        Ok(len) => {
+            log::trace!("raw response ({}): {:?}", len, &buf[..len]);
+            write!(ret, "{}", std::string::String::from_utf8_lossy(&buf[..len])).unwrap();
+        }
+        Err(e) => write!(ret, "Didn't get response from host: {:?}", e).unwrap(),
+    }
+}
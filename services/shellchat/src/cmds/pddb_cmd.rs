@@ -1,4 +1,4 @@
-use crate::{ShellCmdApi, CommonEnv};
+use crate::{complete_last, ShellCmdApi, CommonEnv};
 use xous_ipc::String;
 
 pub struct PddbCmd {
@@ -12,16 +12,63 @@ impl PddbCmd {
     }
 }
 
+/// bytes shown on each line of a hexdump
+const HEXDUMP_WIDTH: usize = 16;
+/// the subcommands, for completion
+const SUBCOMMANDS: [&str; 10] = [
+    "basislist", "dictlist", "keylist", "query", "loadglyphs", "ls", "get", "put", "del", "hexdump",
+];
+
+impl PddbCmd {
+    /// The whole of a key's value
+    fn read_key(&mut self, dict: &str, keyname: &str, basis: Option<&str>) -> std::io::Result<Vec<u8>> {
+        use std::io::Read;
+        let mut key = self.pddb.get(dict, keyname, basis, false, false, None, None::<fn()>)?;
+        let mut value = Vec::<u8>::new();
+        key.read_to_end(&mut value)?;
+        Ok(value)
+    }
+    /// Replaces a key's value, creating the key and its dictionary if they don't exist yet
+    fn write_key(&mut self, dict: &str, keyname: &str, basis: Option<&str>, value: &[u8]) -> std::io::Result<()> {
+        use std::io::Write;
+        // the key is replaced whole, as the PDDB can't shorten a key in place
+        self.pddb.delete_key(dict, keyname, basis).ok();
+        let mut key = self.pddb.get(dict, keyname, basis, true, true, Some(value.len()), None::<fn()>)?;
+        key.write_all(value)?;
+        key.flush()
+    }
+}
+
+/**
+pddb shell command. Every subcommand but basislist takes `-b basis` right after it, to look only in that
+basis; otherwise the most recently opened basis that has the key is used.
+- basislist: list the open bases
+- dictlist: list the first few dictionaries
+- keylist dict: list the first few keys in a dictionary
+- query dict:key: show the start of a key's value
+- loadglyphs dict:key slot: load a glyph set from a key into a loadable glyph slot
+- ls [dict]: list all the dictionaries, or all the keys in a dictionary, as many as fit
+- get dict:key: show a key's value as text
+- put dict:key ...: set a key's value to ... (... can include spaces), creating it if need be
+- del dict:key: delete a key; del dict deletes a whole dictionary
+- hexdump dict:key [offset]: show a key's value in hex, from offset on, as much as fits
+*/
 impl<'a> ShellCmdApi<'a> for PddbCmd {
     cmd_api!(pddb); // inserts boilerplate for command API
 
-    fn process(&mut self, args: String::<1024>, _env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+    fn process(&mut self, args: String::<1024>, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
         use core::fmt::Write;
         let mut ret = String::<1024>::new();
-        let helpstring = "pddb [basislist] [dictlist] [keylist] [query]";
+        let helpstring = "pddb [basislist] [dictlist] [keylist] [query] [loadglyphs] [ls] [get] [put] [del] [hexdump]";
 
-        let mut tokens = args.as_str().unwrap().split(' ');
+        let mut tokens = args.as_str().unwrap().split(' ').peekable();
         if let Some(sub_cmd) = tokens.next() {
+            let basis = if tokens.peek() == Some(&"-b") {
+                tokens.next();
+                tokens.next()
+            } else {
+                None
+            };
             match sub_cmd {
                 "basislist" => {
                     let bases = self.pddb.list_basis();
@@ -41,7 +88,7 @@ impl<'a> ShellCmdApi<'a> for PddbCmd {
                 "query" => {
                     if let Some(descriptor) = tokens.next() {
                         if let Some((dict, keyname)) = descriptor.split_once(':') {
-                            match self.pddb.get(dict, keyname, None,
+                            match self.pddb.get(dict, keyname, basis,
                                 false, false, None, None::<fn()>) {
                                 Ok(mut key) => {
                                     use std::io::Read;
@@ -74,9 +121,35 @@ impl<'a> ShellCmdApi<'a> for PddbCmd {
                         write!(ret, "Missing query of form 'dict:key'").unwrap();
                     }
                 }
+                "loadglyphs" => {
+                    // loadglyphs dict:key slot -- installs a glyph set stored in the PDDB for use with GlyphStyle::Loaded<slot>
+                    let descriptor = tokens.next();
+                    let slot = tokens.next().and_then(|s| s.parse::<usize>().ok());
+                    if let (Some((dict, keyname)), Some(slot)) = (descriptor.and_then(|d| d.split_once(':')), slot) {
+                        match self.pddb.get(dict, keyname, basis,
+                            false, false, None, None::<fn()>) {
+                            Ok(mut key) => {
+                                use std::io::Read;
+                                let mut blob = Vec::<u8>::new();
+                                match key.read_to_end(&mut blob) {
+                                    Ok(len) => {
+                                        match env.gam.load_glyph_set(slot, &blob) {
+                                            Ok(_) => write!(ret, "Loaded {} bytes of glyphs into slot {}", len, slot).unwrap(),
+                                            Err(e) => write!(ret, "Couldn't load glyph set: {:?}", e).unwrap(),
+                                        }
+                                    }
+                                    _ => write!(ret, "Error encountered reading {}:{}", dict, keyname).unwrap()
+                                }
+                            }
+                            _ => write!(ret, "{}:{} not found or other error", dict, keyname).unwrap()
+                        }
+                    } else {
+                        write!(ret, "loadglyphs is of form 'dict:key slot'").unwrap();
+                    }
+                }
                 "keylist" => {
                     if let Some(dict) = tokens.next() {
-                        match self.pddb.list_keys(dict, None) {
+                        match self.pddb.list_keys(dict, basis) {
                             Ok(list) => {
                                 let checked_len = if list.len() > 6 {
                                     write!(ret, "First 6 keys of {}:", list.len()).unwrap();
@@ -103,7 +176,7 @@ impl<'a> ShellCmdApi<'a> for PddbCmd {
                     }
                 }
                 "dictlist" => {
-                    match self.pddb.list_dict(None) {
+                    match self.pddb.list_dict(basis) {
                         Ok(list) => {
                             let checked_len = if list.len() > 6 {
                                 write!(ret, "First 6 dicts of {}:", list.len()).unwrap();
@@ -126,6 +199,96 @@ impl<'a> ShellCmdApi<'a> for PddbCmd {
                         Err(_) => write!(ret, "Error encountered listing dictionaries").ok().unwrap_or(()),
                     }
                 }
+                "ls" => {
+                    let dict = tokens.next().filter(|d| !d.is_empty());
+                    let list = match dict {
+                        Some(dict) => self.pddb.list_keys(dict, basis),
+                        None => self.pddb.list_dict(basis),
+                    };
+                    match list {
+                        Ok(mut list) => {
+                            list.sort();
+                            match dict {
+                                Some(dict) => write!(ret, "{} keys in {}:\n", list.len(), dict).unwrap(),
+                                None => write!(ret, "{} dictionaries:\n", list.len()).unwrap(),
+                            }
+                            for name in list.iter() {
+                                if ret.append(&format!("{}\n", name)).is_err() {
+                                    break; // overflowed return buffer
+                                }
+                            }
+                        }
+                        Err(_) => write!(ret, "{} does not exist or other error", dict.unwrap_or("basis")).unwrap(),
+                    }
+                }
+                "get" => {
+                    match tokens.next().and_then(|d| d.split_once(':')) {
+                        Some((dict, keyname)) => match self.read_key(dict, keyname, basis) {
+                            Ok(value) => match std::str::from_utf8(&value) {
+                                Ok(text) => {
+                                    for c in text.chars() {
+                                        if ret.push(c).is_err() {
+                                            break; // the rest doesn't fit in the return buffer
+                                        }
+                                    }
+                                }
+                                Err(_) => write!(ret, "{}:{} isn't text, try hexdump", dict, keyname).unwrap(),
+                            },
+                            Err(_) => write!(ret, "{}:{} not found or other error", dict, keyname).unwrap(),
+                        },
+                        None => write!(ret, "get is of form 'dict:key'").unwrap(),
+                    }
+                }
+                "put" => {
+                    match tokens.next().and_then(|d| d.split_once(':')) {
+                        Some((dict, keyname)) => {
+                            let value = tokens.collect::<Vec<&str>>().join(" ");
+                            match self.write_key(dict, keyname, basis, value.as_bytes()) {
+                                Ok(_) => write!(ret, "Wrote {} bytes to {}:{}", value.len(), dict, keyname).unwrap(),
+                                Err(e) => write!(ret, "Couldn't write {}:{}: {:?}", dict, keyname, e).unwrap(),
+                            }
+                        }
+                        None => write!(ret, "put is of form 'dict:key value'").unwrap(),
+                    }
+                }
+                "del" => {
+                    match tokens.next() {
+                        Some(descriptor) if !descriptor.is_empty() => {
+                            let result = match descriptor.split_once(':') {
+                                Some((dict, keyname)) => self.pddb.delete_key(dict, keyname, basis),
+                                None => self.pddb.delete_dict(descriptor, basis),
+                            };
+                            match result {
+                                Ok(_) => write!(ret, "Deleted {}", descriptor).unwrap(),
+                                Err(_) => write!(ret, "{} not found or other error", descriptor).unwrap(),
+                            }
+                        }
+                        _ => write!(ret, "del is of form 'dict:key' or 'dict'").unwrap(),
+                    }
+                }
+                "hexdump" => {
+                    let descriptor = tokens.next().and_then(|d| d.split_once(':'));
+                    let offset = tokens.next().and_then(|s| s.parse::<usize>().ok()).unwrap_or(0);
+                    match descriptor {
+                        Some((dict, keyname)) => match self.read_key(dict, keyname, basis) {
+                            Ok(value) => {
+                                write!(ret, "{} bytes\n", value.len()).unwrap();
+                                for (index, line) in value.get(offset..).unwrap_or(&[]).chunks(HEXDUMP_WIDTH).enumerate() {
+                                    let mut hex = std::string::String::new();
+                                    for b in line.iter() {
+                                        hex.push_str(&format!("{:02x} ", b));
+                                    }
+                                    // a long key is cut off where the return buffer fills up, so start from an offset to see the rest
+                                    if ret.append(&format!("{:06x}: {}\n", offset + index * HEXDUMP_WIDTH, hex.trim_end())).is_err() {
+                                        break;
+                                    }
+                                }
+                            }
+                            Err(_) => write!(ret, "{}:{} not found or other error", dict, keyname).unwrap(),
+                        },
+                        None => write!(ret, "hexdump is of form 'dict:key [offset]'").unwrap(),
+                    }
+                }
                 _ => {
                     write!(ret, "{}", helpstring).unwrap();
                 }
@@ -136,4 +299,29 @@ impl<'a> ShellCmdApi<'a> for PddbCmd {
         }
         Ok(Some(ret))
     }
+
+    fn complete(&mut self, args: &str, _env: &mut CommonEnv) -> Vec<std::string::String> {
+        let (sub_cmd, name) = match args.split_once(' ') {
+            None => return complete_last(args, SUBCOMMANDS.iter().copied()),
+            Some(split) => split,
+        };
+        // only the name right after the subcommand is completed, and only once there's something to look in
+        if name.contains(' ') || !self.pddb.is_mounted() {
+            return Vec::new();
+        }
+        let names: Vec<std::string::String> = match (sub_cmd, name.split_once(':')) {
+            ("keylist" | "ls" | "del", None) => self.pddb.list_dict(None).unwrap_or_default(),
+            ("query" | "loadglyphs" | "get" | "put" | "hexdump", None) => {
+                // a key is always named by its dictionary first
+                self.pddb.list_dict(None).unwrap_or_default().into_iter().map(|dict| format!("{}:", dict)).collect()
+            }
+            ("query" | "loadglyphs" | "get" | "put" | "hexdump" | "del", Some((dict, _))) => {
+                self.pddb.list_keys(dict, None).unwrap_or_default().into_iter().map(|key| format!("{}:{}", dict, key)).collect()
+            }
+            _ => Vec::new(),
+        };
+        let mut names = complete_last(name, names.iter().map(|n| n.as_str()));
+        names.sort();
+        names.into_iter().map(|name| format!("{} {}", sub_cmd, name)).collect()
+    }
 }
@@ -0,0 +1,45 @@
+use crate::{complete_last, ShellCmdApi, CommonEnv};
+use xous_ipc::String;
+
+#[derive(Debug)]
+pub struct Prof {
+}
+impl Prof {
+    pub fn new() -> Prof {
+        Prof {}
+    }
+}
+
+impl<'a> ShellCmdApi<'a> for Prof {
+    cmd_api!(prof); // inserts boilerplate for command API
+
+    fn process(&mut self, args: String::<1024>, _env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+        use core::fmt::Write;
+        let mut ret = String::<1024>::new();
+        let helpstring = "prof [on] [off] [dump]";
+
+        let mut tokens = args.as_str().unwrap().split(' ');
+        match tokens.next() {
+            Some("on") => match log_server::set_profile(true) {
+                Ok(_) => write!(ret, "Profiling; 'prof dump' prints the samples to the log").unwrap(),
+                Err(e) => write!(ret, "Couldn't start the profiler: {:?}", e).unwrap(),
+            },
+            Some("off") => match log_server::set_profile(false) {
+                Ok(_) => write!(ret, "Profiler stopped").unwrap(),
+                Err(e) => write!(ret, "Couldn't stop the profiler: {:?}", e).unwrap(),
+            },
+            Some("dump") => match log_server::dump_profile() {
+                Ok(_) => write!(ret, "Profile written to the log").unwrap(),
+                Err(e) => write!(ret, "Couldn't dump the profile: {:?}", e).unwrap(),
+            },
+            _ => {
+                write!(ret, "{}", helpstring).unwrap();
+            }
+        }
+        Ok(Some(ret))
+    }
+
+    fn complete(&mut self, args: &str, _env: &mut CommonEnv) -> Vec::<std::string::String> {
+        complete_last(args, ["on", "off", "dump"].iter().copied())
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{CommonEnv, ShellCmdApi};
+use core::fmt::Write;
+use locales::t;
+use std::io::{Read, Write as PddbWrite};
+use xous_ipc::String;
+
+/// where scripts named without a dictionary are kept, apart from the shell's history so that can't be run
+pub(crate) const SCRIPT_DICT: &str = "shellchat.scripts";
+/// the script offered to be run as soon as the PDDB is mounted
+pub(crate) const STARTUP_SCRIPT: &str = "rc";
+/// how much of the startup script is shown when asking whether to run it
+const STARTUP_PREVIEW_LINES: usize = 8;
+
+pub struct Run {
+    pddb: pddb::Pddb,
+}
+impl Run {
+    pub fn new() -> Self {
+        Run { pddb: pddb::Pddb::new() }
+    }
+}
+
+/// The dictionary and key of the script called `name`: either `dict:key`, or just a key in `SCRIPT_DICT`
+fn locate(name: &str) -> (&str, &str) {
+    name.split_once(':').unwrap_or((SCRIPT_DICT, name))
+}
+
+/// The text of a script, or `None` if there's no such key
+pub(crate) fn read_script(pddb: &mut pddb::Pddb, name: &str) -> Option<std::string::String> {
+    let (dict, key) = locate(name);
+    let mut text = std::string::String::new();
+    match pddb.get(dict, key, None, false, false, None, None::<fn()>) {
+        Ok(mut script) => script.read_to_string(&mut text).ok().map(|_| text),
+        Err(_) => None,
+    }
+}
+
+fn write_script(pddb: &mut pddb::Pddb, name: &str, text: &str) -> Result<(), std::io::Error> {
+    let (dict, key) = locate(name);
+    // the key is replaced whole, as the PDDB can't shorten a key in place
+    pddb.delete_key(dict, key, None).ok();
+    let mut script = pddb.get(dict, key, None, true, true, Some(text.len()), None::<fn()>)?;
+    script.write_all(text.as_bytes())?;
+    script.flush()
+}
+
+/// The commands in a script, one per line. Blank lines, and lines starting with `#`, are skipped.
+pub(crate) fn script_lines(text: &str) -> Vec<String<1024>> {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| String::<1024>::from_str(line))
+        .collect()
+}
+
+/// Asks the user whether to run the startup script `text`, showing them what's in it
+pub(crate) fn confirm_startup(text: &str) -> bool {
+    let xns = xous_names::XousNames::new().unwrap();
+    let modals = match modals::Modals::new(&xns) {
+        Ok(modals) => modals,
+        Err(_) => return false,
+    };
+    let lines = script_lines(text);
+    let mut question = std::string::String::from(t!("shellchat.rc-prompt", xous::LANG));
+    question.push('\n');
+    for line in lines.iter().take(STARTUP_PREVIEW_LINES) {
+        question.push('\n');
+        question.push_str(line.as_str().unwrap_or(""));
+    }
+    if lines.len() > STARTUP_PREVIEW_LINES {
+        question.push_str("\n...");
+    }
+    let yes = t!("shellchat.rc-yes", xous::LANG);
+    let no = t!("shellchat.rc-no", xous::LANG);
+    match modals.add_list_item(yes).and(modals.add_list_item(no)).and_then(|_| modals.get_radiobutton(&question)) {
+        Ok(answer) => answer == yes,
+        Err(_) => false,
+    }
+}
+
+/**
+run shell command:
+- run name: run the commands in the script `name`, one after the other, as if they'd been typed
+- add name ...: add the command ... (... can include spaces) to the end of the script `name`
+- show name: list the commands in the script `name`
+- delete name: delete the script `name`
+
+A script is a PDDB key holding one command per line. `name` is a key in the `shellchat.scripts` dictionary,
+or `dict:key` for a key anywhere else. The script `rc` in the `shellchat.scripts` dictionary is offered to
+be run whenever the PDDB is mounted, e.g. with `wlan join` and `rtc` to get connected and set the time at boot. Scripts can't
+run other scripts.
+*/
+impl<'a> ShellCmdApi<'a> for Run {
+    cmd_api!(run); // inserts boilerplate for command API
+
+    fn process(&mut self, args: String<1024>, env: &mut CommonEnv) -> Result<Option<String<1024>>, xous::Error> {
+        let mut ret = String::<1024>::new();
+        let helpstring = "run [name] [add name ...] [show name] [delete name]";
+
+        let mut tokens = args.as_str().unwrap().split(' ');
+        let sub_cmd = match tokens.next() {
+            Some(sub_cmd) if !sub_cmd.is_empty() => sub_cmd,
+            _ => {
+                write!(ret, "{}", helpstring).unwrap();
+                return Ok(Some(ret));
+            }
+        };
+        match sub_cmd {
+            "add" => match tokens.next() {
+                Some(name) if !name.is_empty() => {
+                    let line = tokens.collect::<Vec<&str>>().join(" ");
+                    if line.trim().is_empty() {
+                        write!(ret, "Missing the command to add").unwrap();
+                    } else {
+                        let mut text = read_script(&mut self.pddb, name).unwrap_or_default();
+                        if !text.is_empty() && !text.ends_with('\n') {
+                            text.push('\n');
+                        }
+                        text.push_str(line.trim());
+                        text.push('\n');
+                        match write_script(&mut self.pddb, name, &text) {
+                            Ok(_) => write!(ret, "Added to {}: {}", name, line.trim()).unwrap(),
+                            Err(e) => write!(ret, "Couldn't save {}: {:?}", name, e).unwrap(),
+                        }
+                    }
+                }
+                _ => write!(ret, "Missing script name").unwrap(),
+            },
+            "show" => match tokens.next() {
+                Some(name) if !name.is_empty() => match read_script(&mut self.pddb, name) {
+                    Some(text) => {
+                        for line in script_lines(&text) {
+                            // a long script is cut off where the return buffer fills up
+                            if ret.append(&format!("{}\n", line)).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                    None => write!(ret, "{} not found", name).unwrap(),
+                },
+                _ => write!(ret, "Missing script name").unwrap(),
+            },
+            "delete" => match tokens.next() {
+                Some(name) if !name.is_empty() => {
+                    let (dict, key) = locate(name);
+                    match self.pddb.delete_key(dict, key, None) {
+                        Ok(_) => write!(ret, "Deleted {}", name).unwrap(),
+                        Err(_) => write!(ret, "{} not found", name).unwrap(),
+                    }
+                }
+                _ => write!(ret, "Missing script name").unwrap(),
+            },
+            name => {
+                if env.script_running {
+                    write!(ret, "Scripts can't run other scripts").unwrap();
+                } else {
+                    match read_script(&mut self.pddb, name) {
+                        Some(text) => {
+                            let lines = script_lines(&text);
+                            write!(ret, "Running {} commands from {}", lines.len(), name).unwrap();
+                            env.script.extend(lines);
+                        }
+                        None => write!(ret, "{} not found", name).unwrap(),
+                    }
+                }
+            }
+        }
+        Ok(Some(ret))
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{ShellCmdApi, CommonEnv};
+use xous_ipc::String;
+
+/// captures are stored as binary PBM images, one key per capture
+const SCREENSHOT_DICT: &str = "screenshots";
+
+pub struct Screenshot {
+    pddb: pddb::Pddb,
+}
+impl Screenshot {
+    pub fn new() -> Screenshot {
+        Screenshot {
+            pddb: pddb::Pddb::new(),
+        }
+    }
+}
+
+impl<'a> ShellCmdApi<'a> for Screenshot {
+    cmd_api!(screenshot); // inserts boilerplate for command API
+
+    fn process(&mut self, args: String::<1024>, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+        use core::fmt::Write;
+        use std::io::Write as IoWrite;
+        let mut ret = String::<1024>::new();
+
+        let keyname = match args.as_str().unwrap().split(' ').next() {
+            Some(name) if name.len() > 0 => std::string::String::from(name),
+            _ => format!("shot{}", env.ticktimer.elapsed_ms()),
+        };
+
+        // the GAM asks the user before it captures anything
+        let capture = match env.gam.screenshot(env.gam_token) {
+            Ok(capture) => capture,
+            Err(e) => {
+                write!(ret, "Couldn't capture the screen: {:?}", e).unwrap();
+                return Ok(Some(ret));
+            }
+        };
+        let (width, height) = capture.size();
+        let header = format!("P4\n{} {}\n", width, height);
+        let raster = capture.raster;
+        match self.pddb.get(SCREENSHOT_DICT, &keyname, None,
+            true, true, Some(header.len() + raster.len()), None::<fn()>) {
+            Ok(mut key) => {
+                match key.write_all(header.as_bytes())
+                    .and_then(|_| key.write_all(&raster))
+                    .and_then(|_| key.flush()) {
+                    Ok(_) => write!(ret, "Saved screenshot to {}:{}", SCREENSHOT_DICT, keyname).unwrap(),
+                    Err(e) => write!(ret, "Error writing {}:{}: {:?}", SCREENSHOT_DICT, keyname, e).unwrap(),
+                }
+            }
+            Err(e) => write!(ret, "Couldn't create {}:{}: {:?}", SCREENSHOT_DICT, keyname, e).unwrap(),
+        }
+        Ok(Some(ret))
+    }
+}
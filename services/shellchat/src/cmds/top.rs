@@ -0,0 +1,161 @@
+use crate::{ShellCmdApi, CommonEnv};
+use xous::MessageEnvelope;
+use xous_ipc::String;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// how many refreshes there are, if no count is given
+const DEFAULT_REFRESHES: usize = 10;
+/// how many of the busiest processes are listed, so a refresh fits in one bubble
+const TOP_ROWS: usize = 8;
+/// the highest PID a process can have on hardware
+const MAX_PID: u8 = 64;
+
+pub struct Top {
+    callback_id: Option<u32>,
+    callback_conn: xous::CID,
+    /// bumped whenever refreshing starts or stops, so the thread timing the last refreshes knows to quit
+    generation: Arc<AtomicUsize>,
+    /// the CPU time of each process, by PID, when last listed
+    last_cpu: HashMap<u8, u64>,
+    /// the CPU time of the whole CPU, when last listed
+    last_total: u64,
+}
+impl Top {
+    pub fn new(xns: &xous_names::XousNames) -> Self {
+        Top {
+            callback_id: None,
+            callback_conn: xns.request_connection_blocking(crate::SERVER_NAME_SHELLCHAT).unwrap(),
+            generation: Arc::new(AtomicUsize::new(0)),
+            last_cpu: HashMap::new(),
+            last_total: 0,
+        }
+    }
+
+    /// Samples every process, and lists the busiest since the last time
+    fn list(&mut self) -> String::<1024> {
+        let mut ret = String::<1024>::new();
+        let total = xous::cpu_time(None).unwrap_or(0);
+        let elapsed = total.wrapping_sub(self.last_total);
+        self.last_total = total;
+
+        // (pid, name, cycles since the last sample, stats)
+        let mut rows: Vec<(u8, std::string::String, u64, xous::ProcessStats)> = Vec::new();
+        for raw_pid in 1..=MAX_PID {
+            let pid = match xous::PID::new(raw_pid) {
+                Some(pid) => pid,
+                None => continue,
+            };
+            let (cpu, stats) = match (xous::cpu_time(Some(pid)), xous::process_stats(pid)) {
+                (Ok(cpu), Ok(stats)) => (cpu, stats),
+                // there's no process with this PID
+                _ => {
+                    self.last_cpu.remove(&raw_pid);
+                    continue;
+                }
+            };
+            let cycles = cpu.wrapping_sub(self.last_cpu.insert(raw_pid, cpu).unwrap_or(0));
+            let mut name = [0u8; 32];
+            let name = match xous::process_name(pid, &mut name) {
+                Ok(len) if len > 0 => std::string::String::from_utf8_lossy(&name[..len.min(name.len())]).to_string(),
+                _ => std::string::String::from("?"),
+            };
+            rows.push((raw_pid, name, cycles, stats));
+        }
+        rows.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+
+        write!(ret, "{} processes\nPID name CPU heap mem msgs\n", rows.len()).unwrap();
+        for (pid, name, cycles, stats) in rows.iter().take(TOP_ROWS) {
+            let percent = if elapsed > 0 { cycles * 100 / elapsed } else { 0 };
+            // pages are 4 KiB
+            let line = format!("{} {} {}% {}k {}k {}\n",
+                pid, name, percent, stats.heap_used / 1024, stats.pages * 4, stats.queued);
+            // the table is cut off where the return buffer fills up
+            if ret.append(&line).is_err() {
+                break;
+            }
+        }
+        ret
+    }
+
+    /// Sends a callback to the shell every `secs` seconds, `count` times, or until refreshing is stopped or
+    /// restarted
+    fn start_refresh(&mut self, secs: usize, count: usize, callback_id: u32) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let current = self.generation.clone();
+        let conn = self.callback_conn;
+        std::thread::spawn(move || {
+            let tt = ticktimer_server::Ticktimer::new().unwrap();
+            for _ in 0..count {
+                tt.sleep_ms(secs.saturating_mul(1000)).unwrap();
+                if current.load(Ordering::SeqCst) != generation {
+                    break;
+                }
+                xous::send_message(conn,
+                    xous::Message::new_scalar(callback_id as usize, 0, 0, 0, 0)
+                ).expect("couldn't send top refresh");
+            }
+        });
+    }
+}
+
+/**
+top shell command:
+- top: list the busiest processes
+- top seconds [count]: list them, then again every `seconds`, `count` times (10 if not given)
+- top stop: stop listing them
+
+Each process is listed with its share of the CPU since the last refresh (since boot, the first time), how
+far its heap has grown, how much memory it owns in all, and how many messages are waiting for its servers.
+A process that's busy when it should be idle drains the battery; one that keeps growing, or has messages
+piling up, is likely stuck.
+*/
+impl<'a> ShellCmdApi<'a> for Top {
+    cmd_api!(top); // inserts boilerplate for command API
+
+    fn process(&mut self, args: String::<1024>, env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+        let callback_id = match self.callback_id {
+            Some(id) => id,
+            None => {
+                let id = env.register_handler(String::<256>::from_str(self.verb()));
+                self.callback_id = Some(id);
+                id
+            }
+        };
+        let mut ret = String::<1024>::new();
+        let helpstring = "top [seconds [count]] [stop]";
+
+        let mut tokens = args.as_str().unwrap().split(' ');
+        match tokens.next() {
+            Some("stop") => {
+                self.generation.fetch_add(1, Ordering::SeqCst);
+                write!(ret, "top stopped").unwrap();
+            }
+            Some(secs) if !secs.is_empty() => {
+                let count = match tokens.next() {
+                    Some(count) => count.parse::<usize>().ok().filter(|&count| count > 0),
+                    None => Some(DEFAULT_REFRESHES),
+                };
+                match (secs.parse::<usize>(), count) {
+                    (Ok(secs), Some(count)) if secs > 0 => {
+                        self.start_refresh(secs, count, callback_id);
+                        ret = self.list();
+                    }
+                    _ => write!(ret, "{}", helpstring).unwrap(),
+                }
+            }
+            // a single listing, which also stops any refreshing
+            _ => {
+                self.generation.fetch_add(1, Ordering::SeqCst);
+                ret = self.list();
+            }
+        }
+        Ok(Some(ret))
+    }
+
+    fn callback(&mut self, _msg: &MessageEnvelope, _env: &mut CommonEnv) -> Result<Option<String::<1024>>, xous::Error> {
+        Ok(Some(self.list()))
+    }
+}
@@ -1,8 +1,13 @@
-use crate::{CommonEnv, ShellCmdApi};
+use crate::{complete_last, CommonEnv, ShellCmdApi};
 use core::fmt::Write;
 use std::io::Write as PddbWrite;
 use xous_ipc::String;
 
+/// the subcommands, for completion
+const SUBCOMMANDS: [&str; 12] = [
+    "on", "off", "setssid", "setpass", "join", "leave", "status", "save", "known", "static", "dhcp", "priority",
+];
+
 #[derive(Debug)]
 pub struct Wlan {
     current_ssid: Option<std::string::String>,
@@ -24,6 +29,9 @@ wlan shell command:
         and password, otherwise NOP
 - leave: if joined, disconnect from AP
 - status: get wlan radio status (power state? connected? AP info?)
+- static addr/prefix gateway [dns]: use a fixed address on the network set with setssid
+- dhcp: go back to taking an address by DHCP on the network set with setssid
+- priority n: set the auto-join priority of the network set with setssid; higher goes first
 */
 impl<'a> ShellCmdApi<'a> for Wlan {
     cmd_api!(wlan); // inserts boilerplate for command API
@@ -34,7 +42,7 @@ impl<'a> ShellCmdApi<'a> for Wlan {
         env: &mut CommonEnv,
     ) -> Result<Option<String<1024>>, xous::Error> {
         let mut ret = String::<1024>::new();
-        let helpstring = "wlan [on] [off] [setssid ...] [setpass ...] [join] [leave] [status] [save] [known]";
+        let helpstring = "wlan [on] [off] [setssid ...] [setpass ...] [join] [leave] [status] [save] [known] [static addr/prefix gateway [dns]] [dhcp] [priority n]";
         let mut show_help = false;
 
         let mut tokens = args.as_str().unwrap().split(' ');
@@ -134,6 +142,34 @@ impl<'a> ShellCmdApi<'a> for Wlan {
                         }
                     }
                 }
+                "static" | "dhcp" | "priority" => {
+                    if let Some(ssid) = &self.current_ssid {
+                        let mut pddb = pddb::Pddb::new();
+                        let mut profile = net::load_profile(&mut pddb, ssid);
+                        let parsed = match sub_cmd {
+                            "static" => parse_static(&mut tokens).map(|(ipv4, dns)| {
+                                profile.static_ipv4 = Some(ipv4);
+                                profile.dns = [dns, [0; 4]];
+                            }),
+                            "dhcp" => {
+                                profile.static_ipv4 = None;
+                                profile.dns = [[0; 4]; 2];
+                                Some(())
+                            }
+                            _ => tokens.next().and_then(|p| p.parse::<u8>().ok()).map(|p| profile.priority = p),
+                        };
+                        if parsed.is_none() {
+                            show_help = true;
+                        } else {
+                            match net::save_profile(&mut pddb, ssid, &profile) {
+                                Ok(_) => write!(ret, "Profile for {} saved; it applies from the next join.", ssid).unwrap(),
+                                Err(e) => write!(ret, "PDDB error storing profile: {:?}", e).unwrap(),
+                            }
+                        }
+                    } else {
+                        write!(ret, "No SSID currently set").unwrap();
+                    }
+                }
                 "join" => {
                     let _ = match env.com.wlan_join() {
                         Ok(_) => {
@@ -171,6 +207,44 @@ impl<'a> ShellCmdApi<'a> for Wlan {
         }
         Ok(Some(ret))
     }
+
+    fn complete(&mut self, args: &str, env: &mut CommonEnv) -> Vec<std::string::String> {
+        match args.split_once(' ') {
+            None => complete_last(args, SUBCOMMANDS.iter().copied()),
+            Some(("setssid", ssid)) => {
+                // the networks in range, and the ones with a saved config
+                let mut names: Vec<std::string::String> = env.netmgr.wifi_get_ssid_list().unwrap_or_default()
+                    .iter()
+                    .filter_map(|record| record.name.as_str().ok().filter(|name| !name.is_empty()).map(std::string::String::from))
+                    .collect();
+                let mut pddb = pddb::Pddb::new();
+                if pddb.is_mounted() {
+                    names.extend(pddb.list_keys(net::AP_DICT_NAME, None).unwrap_or_default());
+                }
+                names.sort();
+                names.dedup();
+                // the SSID is the whole of the rest of the line, spaces and all
+                names.iter()
+                    .filter(|name| name.starts_with(ssid) && name.as_str() != ssid)
+                    .map(|name| format!("setssid {}", name))
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Parses `addr/prefix gateway [dns]`; without a DNS server, the gateway is taken to be one
+fn parse_static<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Option<(net::StaticIpv4, [u8; 4])> {
+    let (addr, prefix_len) = tokens.next()?.split_once('/')?;
+    let addr = addr.parse::<std::net::Ipv4Addr>().ok()?.octets();
+    let prefix_len = prefix_len.parse::<u8>().ok().filter(|&p| p <= 32)?;
+    let gateway = tokens.next()?.parse::<std::net::Ipv4Addr>().ok()?.octets();
+    let dns = match tokens.next() {
+        Some(dns) => dns.parse::<std::net::Ipv4Addr>().ok()?.octets(),
+        None => gateway,
+    };
+    Some((net::StaticIpv4 { addr, prefix_len, gateway }, dns))
 }
 
 /**
@@ -0,0 +1,75 @@
+#![cfg_attr(target_os = "none", no_std)]
+
+//! Lets other servers add verbs to the shell, without having to be built into it.
+//!
+//! A server registers a verb with `register_verb()`, naming its own SID and an opcode. When the verb is typed
+//! with arguments, the shell sends them to that server as a `VerbInvocation`, and shows whatever the server
+//! passes back with `respond()`, along with the invocation's token, as the response. Each invocation gets one
+//! response; anything else sent back is dropped. The verb typed on its own shows the help text given when it
+//! was registered. Built-in commands always take precedence over registered verbs of the same name.
+
+pub mod api;
+pub use api::*;
+
+use num_traits::ToPrimitive;
+use xous::{CID, SID};
+use xous_ipc::{Buffer, String};
+
+pub struct Shellchat {
+    conn: CID,
+}
+impl Shellchat {
+    pub fn new(xns: &xous_names::XousNames) -> Result<Self, xous::Error> {
+        REFCOUNT.fetch_add(1, Ordering::Relaxed);
+        let conn = xns.request_connection_blocking(api::SERVER_NAME_SHELLCHAT).expect("Can't connect to shellchat");
+        Ok(Shellchat { conn })
+    }
+
+    /// Adds `verb` to the shell, to be sent to `sid` with `opcode`. Returns false if another server already
+    /// has the verb, or it isn't one word of at most 32 bytes.
+    pub fn register_verb(&self, verb: &str, help: &str, sid: SID, opcode: u32) -> Result<bool, xous::Error> {
+        if verb.len() > 32 {
+            return Ok(false);
+        }
+        let registration = VerbRegistration {
+            verb: String::<32>::from_str(verb),
+            help: String::<256>::from_str(help),
+            sid: sid.to_array(),
+            opcode,
+            accepted: false,
+        };
+        let mut buf = Buffer::into_buf(registration).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(self.conn, ShellOpcode::RegisterVerb.to_u32().unwrap()).or(Err(xous::Error::InternalError))?;
+        let ret = buf.to_original::<VerbRegistration, _>().or(Err(xous::Error::InternalError))?;
+        Ok(ret.accepted)
+    }
+    /// Takes back a verb given to `register_verb()`. Only the server it was registered to can take it back.
+    pub fn unregister_verb(&self, verb: &str, sid: SID) -> Result<(), xous::Error> {
+        let registration = VerbRegistration {
+            verb: String::<32>::from_str(verb),
+            help: String::<256>::new(),
+            sid: sid.to_array(),
+            opcode: 0,
+            accepted: false,
+        };
+        let buf = Buffer::into_buf(registration).or(Err(xous::Error::InternalError))?;
+        buf.lend(self.conn, ShellOpcode::UnregisterVerb.to_u32().unwrap()).map(|_| ())
+    }
+    /// Shows `text` as the response to the `VerbInvocation` with `token`. It doesn't wait for the shell.
+    pub fn respond(&self, token: u32, text: &str) -> Result<(), xous::Error> {
+        let response = VerbResponse { token, text: String::<1024>::from_str(text) };
+        let buf = Buffer::into_buf(response).or(Err(xous::Error::InternalError))?;
+        buf.send(self.conn, ShellOpcode::VerbResponse.to_u32().unwrap()).map(|_| ())
+    }
+}
+
+use core::sync::atomic::{AtomicU32, Ordering};
+static REFCOUNT: AtomicU32 = AtomicU32::new(0);
+impl Drop for Shellchat {
+    fn drop(&mut self) {
+        // now de-allocate myself. It's unsafe because we are responsible to make sure nobody else is using the connection.
+        if REFCOUNT.fetch_sub(1, Ordering::Relaxed) == 1 {
+            unsafe{xous::disconnect(self.conn).unwrap();}
+        }
+    }
+}
@@ -27,6 +27,9 @@ impl<'a> ShellCmdApi<'a> for Echo {
 Once you've added your command to the directory, go to the `cmds.rs` file, and follow
 the four-step instructions embedded within the file, starting around line 40.
 
+A server outside of the shell can add a verb of its own while it's running, with
+`shellchat::Shellchat::register_verb()`; see the library docs for how the arguments get to it.
+
 Check for more detailed docs under Modules/cmds "Shell Chat" below
 */
 use log::info;
@@ -44,12 +47,17 @@ mod cmds;
 use cmds::*;
 
 mod oqc_test;
+mod pager;
+use pager::*;
 
 #[cfg(feature="tts")]
 use locales::t;
 #[cfg(feature="tts")]
 use tts_frontend::*;
 
+/// longest startup script that's run at boot; it's passed whole from the thread that asks the user
+const STARTUP_SCRIPT_LEN: usize = 4000;
+
 #[derive(Debug)]
 struct History {
     // the history record
@@ -82,6 +90,8 @@ struct Repl {
 
     // command environment
     env: CmdEnv,
+    // a response too long for the screen, while it's being paged through
+    pager: Option<Pager>,
 
     // our security token for making changes to our record on the GAM
     token: [u32; 4],
@@ -105,6 +115,7 @@ impl Repl{
             audioframe_id: None,
             rawkeys_id: None,
             focuschange_id: Some(ShellOpcode::ChangeFocus.to_u32().unwrap()),
+            lifecycle_id: None,
         }).expect("couldn't register Ux context for shellchat");
 
         let content = gam.request_content_canvas(token.unwrap()).expect("couldn't get content canvas");
@@ -124,7 +135,8 @@ impl Repl{
             bubble_margin: Point::new(4, 4),
             bubble_radius: 4,
             bubble_space: 4,
-            env: CmdEnv::new(xns),
+            env: CmdEnv::new(xns, token.unwrap()),
+            pager: None,
             token: token.unwrap(),
             #[cfg(feature="tts")]
             tts: TtsFrontend::new(xns).unwrap(),
@@ -142,6 +154,32 @@ impl Repl{
         self.msg = Some(message);
     }
 
+    /// Records a response from a command, and pages it if it's too long to read in a bubble
+    fn respond(&mut self, res: xous_ipc::String::<1024>) {
+        #[cfg(feature="tts")]
+        {
+            let mut output = t!("shellchat.output-tts", xous::LANG).to_string();
+            output.push_str(res.as_str().unwrap_or("UTF-8 error"));
+            self.tts.tts_simple(&output).unwrap();
+        }
+        let text = res.as_str().unwrap_or("UTF-8 Error");
+        let mut pager = Pager::new(text);
+        self.pager = if pager.fit(&self.gam, self.content, self.pager_area().br - self.pager_area().tl) {
+            Some(pager)
+        } else {
+            None
+        };
+        let output_history = History {
+            text: String::from(text),
+            is_input: false
+        };
+        self.circular_push(output_history);
+    }
+    /// where pages are drawn: the whole content canvas, inside the margin
+    fn pager_area(&self) -> Rectangle {
+        Rectangle::new(self.margin, self.screensize - self.margin)
+    }
+
     fn circular_push(&mut self, item: History) {
         if self.history.len() >= self.history_len {
             self.history.remove(0);
@@ -152,6 +190,24 @@ impl Repl{
     /// update the loop, in response to various inputs
     fn update(&mut self, was_callback: bool) -> Result<(), xous::Error> {
         let debug1 = false;
+        // while a response is being paged, lines typed turn its pages, unless they're commands
+        let paged = match (self.pager.as_mut(), self.input.as_deref()) {
+            (Some(pager), Some(line)) => Some(pager.input(line)),
+            _ => None,
+        };
+        match paged {
+            Some(PagerInput::Turned) => {
+                self.input = None;
+                return self.redraw();
+            }
+            Some(PagerInput::Closed) => {
+                self.pager = None;
+                self.input = None;
+                return self.redraw();
+            }
+            Some(PagerInput::Run) => self.pager = None,
+            None => (),
+        }
         // if we had an input string, do something
         if let Some(local) = &self.input {
             let input_history = History {
@@ -175,39 +231,31 @@ impl Repl{
         if let Some(local) = &self.input {
             log::trace!("processing line: {}", local);
             if let Some(res) = self.env.dispatch(Some(&mut xous_ipc::String::<1024>::from_str(&local)), None).expect("command dispatch failed") {
-                #[cfg(feature="tts")]
-                {
-                    let mut output = t!("shellchat.output-tts", xous::LANG).to_string();
-                    output.push_str(res.as_str().unwrap_or("UTF-8 error"));
-                    self.tts.tts_simple(&output).unwrap();
-                }
-                let output_history = History {
-                    text: String::from(res.as_str().unwrap_or("UTF-8 Error")),
-                    is_input: false
-                };
-                self.circular_push(output_history);
+                self.respond(res);
             } else {
                 dirty = false;
             }
         } else if let Some(msg) = &self.msg {
             log::trace!("processing callback msg: {:?}", msg);
             if let Some(res) = self.env.dispatch(None, Some(msg)).expect("callback failed") {
-                #[cfg(feature="tts")]
-                {
-                    let mut output = t!("shellchat.output-tts", xous::LANG).to_string();
-                    output.push_str(res.as_str().unwrap_or("UTF-8 error"));
-                    self.tts.tts_simple(&output).unwrap();
-                }
-                let output_history = History {
-                    text: String::from(res.as_str().unwrap_or("UTF-8 Error")),
-                    is_input: false
-                };
-                self.circular_push(output_history);
+                self.respond(res);
             } else {
                 dirty = false;
             }
         }
 
+        // a script started by the line above has its commands run one after the other, as if they'd been typed
+        while let Some(mut line) = self.env.next_script_line() {
+            self.circular_push(History {
+                text: String::from(line.as_str().unwrap_or("UTF-8 Error")),
+                is_input: true,
+            });
+            if let Some(res) = self.env.dispatch(Some(&mut line), None).expect("command dispatch failed") {
+                self.respond(res);
+            }
+            dirty = true;
+        }
+
         // clear all the inputs to the loop, so we don't process them twice
         self.input = None;
         self.msg = None;
@@ -236,8 +284,22 @@ impl Repl{
     }
     fn redraw(&mut self) -> Result<(), xous::Error> {
         log::trace!("going into redraw");
+        // the canvas changes shape if the screen is turned on its side, so re-read it on every redraw
+        self.screensize = self.gam.get_canvas_bounds(self.content).expect("couldn't get dimensions of content canvas");
+        self.bubble_width = ((self.screensize.x / 5) * 4) as u16;
         self.clear_area();
 
+        if let Some(mut pager) = self.pager.take() {
+            // the pages are laid out again if the screen was turned
+            let area = self.pager_area();
+            if pager.fit(&self.gam, self.content, area.br - area.tl) {
+                pager.draw(&self.gam, self.content, area);
+                self.pager = Some(pager);
+                self.gam.redraw().expect("couldn't redraw screen");
+                return Ok(());
+            }
+        }
+
         // this defines the bottom border of the text bubbles as they stack up wards
         let mut bubble_baseline = self.screensize.y - self.margin.y;
 
@@ -293,23 +355,9 @@ impl Repl{
 
 ////////////////// local message passing from Ux Callback
 use num_traits::{ToPrimitive, FromPrimitive};
-
-#[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive)]
-enum ShellOpcode {
-    /// a line of text has arrived
-    Line = 0, // make sure we occupy opcodes with discriminants < 1000, as the rest are used for callbacks
-    /// redraw our UI
-    Redraw,
-    /// change focus
-    ChangeFocus,
-    /// exit the application
-    Quit,
-}
+use shellchat::api::{ShellOpcode, VerbRegistration, VerbResponse, SERVER_NAME_SHELLCHAT};
 //////////////////
 
-// nothing prevents the two from being the same, other than naming conventions
-pub(crate) const SERVER_NAME_SHELLCHAT: &str = "_Shell chat application_"; // used internally by xous-names
-
 #[xous::xous_main]
 fn xmain() -> ! {
     log_server::init_wait().unwrap();
@@ -329,6 +377,45 @@ fn xmain() -> ! {
     let mut was_callback = false;
 
     let mut allow_redraw = true;
+
+    // the startup script can only be read once the PDDB is mounted, which waits on the user unlocking it. Anything
+    // that can write to the PDDB can write the script, so it's only run once the user has seen what's in it, and
+    // it's the text they saw that's run, not whatever the key holds by then.
+    let self_cid = xous::connect(shch_sid).expect("couldn't connect to my own server");
+    std::thread::spawn(move || {
+        let mut pddb = pddb::Pddb::new();
+        pddb.wait_for_mount();
+        match read_script(&mut pddb, STARTUP_SCRIPT) {
+            Some(text) if text.len() > STARTUP_SCRIPT_LEN => log::warn!("the startup script is too long to run at boot"),
+            Some(text) if confirm_startup(&text) => {
+                let script = Buffer::into_buf(xous_ipc::String::<STARTUP_SCRIPT_LEN>::from_str(&text))
+                    .expect("couldn't pass on the startup script");
+                script.lend(self_cid, ShellOpcode::RunStartup.to_u32().unwrap()).expect("couldn't start the startup script");
+            }
+            _ => (),
+        }
+    });
+
+    // the shell predictor asks how lines could be completed while they're typed. It's passed on to the main
+    // loop, which has the commands, through a server of its own that only the predictor can connect to.
+    let completion_sid = xns.register_name(ime_plugin_shell::SERVER_NAME_SHELL_COMPLETION, Some(1)).expect("can't register completion server");
+    std::thread::spawn(move || {
+        loop {
+            let mut msg = xous::receive_message(completion_sid).unwrap();
+            match FromPrimitive::from_usize(msg.body.id()) {
+                Some(ime_plugin_shell::CompletionOpcode::Complete) => {
+                    let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                    let completion = buffer.to_original::<ime_plugin_shell::Completion, _>().unwrap();
+                    let mut forward = Buffer::into_buf(completion).expect("couldn't forward completion");
+                    forward.lend_mut(self_cid, ShellOpcode::Complete.to_u32().unwrap()).expect("couldn't forward completion");
+                    let completed = forward.to_original::<ime_plugin_shell::Completion, _>().unwrap();
+                    buffer.replace(completed).expect("couldn't return Completion");
+                }
+                None => log::error!("unknown completion opcode {}", msg.body.id()),
+            }
+        }
+    });
+
     log::trace!("starting main loop");
     loop {
         let msg = xous::receive_message(shch_sid).unwrap();
@@ -364,6 +451,53 @@ fn xmain() -> ! {
                     }
                 }
             }),
+            Some(ShellOpcode::RunStartup) => {
+                // only the thread that asked the user may start it
+                if msg.sender.pid() != xous::current_pid().ok() {
+                    log::warn!("PID {:?} tried to run the startup script", msg.sender.pid());
+                    continue;
+                }
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let script = buffer.as_flat::<xous_ipc::String<STARTUP_SCRIPT_LEN>, _>().unwrap();
+                repl.env.queue_script(script.as_str());
+                update_repl = true;
+                was_callback = false;
+            }
+            Some(ShellOpcode::RegisterVerb) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let mut registration = buffer.to_original::<VerbRegistration, _>().unwrap();
+                registration.accepted = repl.env.register_external(&registration);
+                buffer.replace(registration).expect("couldn't return VerbRegistration");
+            }
+            Some(ShellOpcode::UnregisterVerb) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let registration = buffer.to_original::<VerbRegistration, _>().unwrap();
+                repl.env.unregister_external(&registration);
+            }
+            Some(ShellOpcode::VerbResponse) => {
+                let buffer = unsafe { Buffer::from_memory_message(msg.body.memory_message().unwrap()) };
+                let response = buffer.to_original::<VerbResponse, _>().unwrap();
+                if repl.env.take_verb_response(response.token) {
+                    repl.respond(response.text);
+                    if allow_redraw {
+                        repl.redraw().expect("REPL couldn't redraw");
+                    }
+                } else {
+                    log::warn!("dropping a verb response that nothing is waiting on");
+                }
+            }
+            Some(ShellOpcode::Complete) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let mut completion = buffer.to_original::<ime_plugin_shell::Completion, _>().unwrap();
+                completion.candidates.clear();
+                for candidate in repl.env.complete(completion.line.as_str().unwrap_or("")) {
+                    // as many as fit
+                    if completion.candidates.append(&format!("{}\n", candidate)).is_err() {
+                        break;
+                    }
+                }
+                buffer.replace(completion).expect("couldn't return Completion");
+            }
             Some(ShellOpcode::Quit) => {
                 log::error!("got Quit");
                 break;
@@ -0,0 +1,138 @@
+//! Shows a response too long for the screen a page at a time, in place of the chat bubbles, with the lines
+//! on the page and how many there are underneath. While it's up, an empty line turns to the next page, `p` goes
+//! back a page and `q` puts the bubbles back. Any other line closes the pager and is run as usual.
+
+use core::fmt::Write;
+use graphics_server::api::GlyphStyle;
+use graphics_server::{DrawStyle, Gid, PixelColor, Point, Rectangle, TextBounds, TextView};
+use locales::t;
+
+/// What to do with a line typed while the pager is up
+pub(crate) enum PagerInput {
+    /// it turned the page
+    Turned,
+    /// it closed the pager
+    Closed,
+    /// it closed the pager, and it's a command to be run
+    Run,
+}
+
+pub(crate) struct Pager {
+    lines: Vec<String>,
+    /// the first line of each page, and the line after its last
+    pages: Vec<(usize, usize)>,
+    page: usize,
+    /// the size the pages were laid out for
+    laid_out: Point,
+}
+
+impl Pager {
+    pub(crate) fn new(text: &str) -> Self {
+        Pager {
+            lines: text.lines().map(String::from).collect(),
+            pages: Vec::new(),
+            page: 0,
+            laid_out: Point::new(0, 0),
+        }
+    }
+
+    /// The height of `lines[start..end]`, drawn `width` wide
+    fn measure(&self, gam: &gam::Gam, canvas: Gid, width: u16, start: usize, end: usize) -> i16 {
+        let mut tv = TextView::new(canvas, TextBounds::GrowableFromTl(Point::new(0, 0), width));
+        tv.style = GlyphStyle::Regular;
+        tv.margin = Point::new(0, 0);
+        tv.ellipsis = false;
+        tv.insertion = None;
+        write!(tv.text, "{}", self.lines[start..end].join("\n")).unwrap();
+        gam.bounds_compute_textview(&mut tv).expect("couldn't measure pager text");
+        match tv.bounds_computed {
+            Some(bounds) => bounds.br.y - bounds.tl.y,
+            // it ran off the canvas
+            None => i16::MAX,
+        }
+    }
+    /// Splits the lines into pages that fit in `size`, unless they already have been for that size. Returns
+    /// whether it takes more than one page, as otherwise there's nothing to page through.
+    pub(crate) fn fit(&mut self, gam: &gam::Gam, canvas: Gid, size: Point) -> bool {
+        if size == self.laid_out {
+            return self.pages.len() > 1;
+        }
+        let top_line = self.pages.get(self.page).map(|&(start, _)| start).unwrap_or(0);
+        let height = size.y - self.footer_height(gam);
+        let width = size.x as u16;
+        self.pages.clear();
+        let mut start = 0;
+        while start < self.lines.len() {
+            // a page has at least one line, even if it runs off the bottom
+            let mut end = start + 1;
+            while end < self.lines.len() && self.measure(gam, canvas, width, start, end + 1) <= height {
+                end += 1;
+            }
+            self.pages.push((start, end));
+            start = end;
+        }
+        // stay on the page with the line that was at the top before
+        self.page = self.pages.iter().position(|&(start, end)| top_line >= start && top_line < end).unwrap_or(0);
+        self.laid_out = size;
+        self.pages.len() > 1
+    }
+    fn footer_height(&self, gam: &gam::Gam) -> i16 {
+        gam.glyph_height_hint(GlyphStyle::Small).expect("couldn't get glyph height hint") as i16 + 4
+    }
+
+    pub(crate) fn input(&mut self, line: &str) -> PagerInput {
+        match line.trim() {
+            "" => {
+                if self.page + 1 < self.pages.len() {
+                    self.page += 1;
+                    PagerInput::Turned
+                } else {
+                    // past the last page is done
+                    PagerInput::Closed
+                }
+            }
+            "p" => {
+                self.page = self.page.saturating_sub(1);
+                PagerInput::Turned
+            }
+            "q" => PagerInput::Closed,
+            _ => PagerInput::Run,
+        }
+    }
+
+    /// Draws the current page in `area`, which is where the pages were laid out to fit
+    pub(crate) fn draw(&self, gam: &gam::Gam, canvas: Gid, area: Rectangle) {
+        let (start, end) = match self.pages.get(self.page) {
+            Some(&page) => page,
+            None => return,
+        };
+        let footer_top = area.br.y - self.footer_height(gam);
+        let mut page_tv = TextView::new(canvas, TextBounds::BoundingBox(Rectangle::new(area.tl, Point::new(area.br.x, footer_top))));
+        page_tv.style = GlyphStyle::Regular;
+        page_tv.margin = Point::new(0, 0);
+        page_tv.draw_border = false;
+        page_tv.clear_area = true;
+        page_tv.ellipsis = true;
+        page_tv.insertion = None;
+        write!(page_tv.text, "{}", self.lines[start..end].join("\n")).unwrap();
+        gam.post_textview(&mut page_tv).expect("couldn't draw pager text");
+
+        gam.draw_line(canvas, graphics_server::Line::new_with_style(
+            Point::new(area.tl.x, footer_top), Point::new(area.br.x, footer_top),
+            DrawStyle::new(PixelColor::Dark, PixelColor::Dark, 1))
+        ).expect("couldn't draw pager footer line");
+        let mut footer_tv = TextView::new(canvas,
+            TextBounds::BoundingBox(Rectangle::new(Point::new(area.tl.x, footer_top + 2), area.br)));
+        footer_tv.style = GlyphStyle::Small;
+        footer_tv.margin = Point::new(0, 0);
+        footer_tv.draw_border = false;
+        footer_tv.clear_area = true;
+        footer_tv.ellipsis = true;
+        footer_tv.insertion = None;
+        write!(footer_tv.text, "{} {}-{}/{}  {}",
+            t!("shellchat.pager-lines", xous::LANG), start + 1, end, self.lines.len(),
+            t!("shellchat.pager-keys", xous::LANG)
+        ).unwrap();
+        gam.post_textview(&mut footer_tv).expect("couldn't draw pager footer");
+    }
+}
@@ -2,28 +2,80 @@ use gam::*;
 use locales::t;
 use num_traits::*;
 
-use crate::{StatusOpcode, app_autogen};
+use crate::{StatusOpcode, app_autogen, DISPLAY_SETTINGS_DICT};
 
-pub fn create_app_menu(status_conn: xous::CID) {
-    let mut menu_items = Vec::<MenuItem>::new();
+/// the launcher layout is saved as one entry name per line
+const LAUNCHER_ORDER_KEY: &str = "launcher.order";
+const LAUNCHER_MRU_KEY: &str = "launcher.mru";
 
-    menu_items.push(MenuItem {
+pub fn create_app_launcher(status_conn: xous::CID, launcher_mgr: xous::SID) -> LauncherMatic {
+    let mut entries = Vec::<LauncherEntry>::new();
+
+    entries.push(LauncherEntry {
         name: xous_ipc::String::from_str(t!("appmenu.shellchat", xous::LANG)),
-        action_conn: Some(status_conn),
+        icon: None,
+        action_conn: status_conn,
         action_opcode: StatusOpcode::SwitchToShellchat.to_u32().unwrap(),
-        action_payload: MenuPayload::Scalar([0, 0, 0, 0]),
-        close_on_select: true,
+        action_payload: [0, 0, 0, 0],
     });
 
-    // insert the application menu items
-    app_autogen::app_menu_items(&mut menu_items, status_conn);
+    // insert the application entries
+    app_autogen::app_launcher_entries(&mut entries, status_conn);
 
-    menu_items.push(MenuItem {
-        name: xous_ipc::String::from_str(t!("mainmenu.closemenu", xous::LANG)),
-        action_conn: None,
-        action_opcode: 0,
-        action_payload: MenuPayload::Scalar([0, 0, 0, 0]),
-        close_on_select: true,
-    });
-    menu_matic(menu_items, gam::APP_MENU_NAME, None);
+    launcher_matic(
+        entries,
+        t!("mainmenu.closemenu", xous::LANG),
+        gam::APP_MENU_NAME,
+        launcher_mgr,
+        Some((status_conn, StatusOpcode::LauncherChanged.to_u32().unwrap())),
+    )
+}
+
+fn load_names(pddb: &mut pddb::Pddb, key: &str, names: &mut [Option<xous_ipc::String::<64>>]) -> bool {
+    use std::io::Read;
+    let mut text = String::new();
+    match pddb.get(DISPLAY_SETTINGS_DICT, key, None, false, false, None, None::<fn()>) {
+        Ok(mut k) => if k.read_to_string(&mut text).is_err() {
+            return false;
+        },
+        Err(_) => return false,
+    }
+    for (dst, name) in names.iter_mut().zip(text.lines().filter(|l| l.len() > 0)) {
+        *dst = Some(xous_ipc::String::from_str(name));
+    }
+    true
+}
+/// Returns the saved launcher layout, if there is one
+pub fn load_launcher_layout(pddb: &mut pddb::Pddb) -> Option<LauncherLayout> {
+    let mut layout = LauncherLayout::new();
+    let has_order = load_names(pddb, LAUNCHER_ORDER_KEY, &mut layout.order);
+    let has_mru = load_names(pddb, LAUNCHER_MRU_KEY, &mut layout.mru);
+    if has_order || has_mru {
+        Some(layout)
+    } else {
+        None
+    }
+}
+
+fn save_names(pddb: &mut pddb::Pddb, key: &str, names: &[Option<xous_ipc::String::<64>>]) {
+    use std::io::Write;
+    let mut text = String::new();
+    for name in names.iter().flatten() {
+        text.push_str(name.as_str().unwrap_or(""));
+        text.push('\n');
+    }
+    // start from an empty key, so that a longer layout saved before doesn't leave its tail behind
+    pddb.delete_key(DISPLAY_SETTINGS_DICT, key, None).ok();
+    match pddb.get(DISPLAY_SETTINGS_DICT, key, None, true, true, Some(text.len()), None::<fn()>) {
+        Ok(mut k) => {
+            k.write_all(text.as_bytes())
+                .and_then(|_| k.flush())
+                .unwrap_or_else(|e| log::error!("couldn't save {}: {:?}", key, e));
+        }
+        Err(e) => log::error!("couldn't save {}: {:?}", key, e),
+    }
+}
+pub fn save_launcher_layout(pddb: &mut pddb::Pddb, layout: &LauncherLayout) {
+    save_names(pddb, LAUNCHER_ORDER_KEY, &layout.order);
+    save_names(pddb, LAUNCHER_MRU_KEY, &layout.mru);
 }
@@ -0,0 +1,120 @@
+//! Turns process faults recorded by the kernel, and panics seen by the log server, into crash reports in the
+//! PDDB, so a failure in the field can be looked at later with `crash` in the shell. Each report is plain text;
+//! its first line is a summary, and the recent log output is at the end.
+
+use core::fmt::Write;
+use log_server::api::CRASH_REPORT_DICT;
+use std::io::Write as IoWrite;
+
+/// Most reports kept; the oldest are deleted to make room
+const MAX_REPORTS: usize = 8;
+
+/// RISC-V names of `x1` through `x31`, in the order the kernel saves them
+const REGISTER_NAMES: [&str; 31] = [
+    "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4", "a5", "a6",
+    "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4", "t5", "t6",
+];
+
+pub(crate) struct CrashReporter {
+    fault_seen: usize,
+    panic_seen: u32,
+    /// reports written before the PDDB was mounted
+    pending: Vec<String>,
+}
+impl CrashReporter {
+    pub fn new() -> CrashReporter {
+        CrashReporter {
+            fault_seen: 0,
+            panic_seen: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Looks for faults and panics that haven't been reported yet, and saves them once the PDDB is mounted.
+    pub fn poll(&mut self, pddb: &mut pddb::Pddb, uptime_ms: u64) {
+        let fault = match xous::crash_record() {
+            Ok(Some((sequence, record))) if sequence != self.fault_seen => {
+                self.fault_seen = sequence;
+                Some(record)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                log::warn!("couldn't read the kernel's crash record: {:?}", e);
+                None
+            }
+        };
+        let recent = match log_server::recent_log() {
+            Ok(recent) => recent,
+            Err(e) => {
+                log::warn!("couldn't get the recent log: {:?}", e);
+                return;
+            }
+        };
+        let panic = match recent.panic {
+            Some(panic) if panic.sequence != self.panic_seen => {
+                self.panic_seen = panic.sequence;
+                Some(panic)
+            }
+            _ => None,
+        };
+        if let Some(record) = fault {
+            self.pending.push(fault_report(&record, uptime_ms, &recent.lines));
+        }
+        if let Some(panic) = panic {
+            self.pending.push(panic_report(&panic, uptime_ms, &recent.lines));
+        }
+        if !self.pending.is_empty() && pddb.is_mounted() {
+            for report in self.pending.drain(..) {
+                save(pddb, &report);
+            }
+        }
+    }
+}
+
+fn fault_report(record: &xous::CrashRecord, uptime_ms: u64, lines: &str) -> String {
+    let mut report = String::new();
+    writeln!(report, "fault in PID {} at pc {:08x}, cause {}, detail {:08x}",
+        record.pid, record.pc, record.cause, record.detail).unwrap();
+    writeln!(report, "uptime: {} ms", uptime_ms).unwrap();
+    writeln!(report, "\nregisters:").unwrap();
+    for (i, (name, value)) in REGISTER_NAMES.iter().zip(record.registers.iter()).enumerate() {
+        write!(report, "{:>3}:{:08x}{}", name, value, if i % 4 == 3 { "\n" } else { " " }).unwrap();
+    }
+    writeln!(report, "\n\nstack from {:08x}:", record.registers[1]).unwrap();
+    for (i, word) in record.stack.iter().enumerate() {
+        write!(report, "{:08x}{}", word, if i % 4 == 3 { "\n" } else { " " }).unwrap();
+    }
+    writeln!(report, "\nlog:\n{}", lines).unwrap();
+    report
+}
+
+fn panic_report(panic: &log_server::Panic, uptime_ms: u64, lines: &str) -> String {
+    let mut report = String::new();
+    writeln!(report, "panic in PID {}: {}", panic.pid, panic.text.lines().next().unwrap_or("")).unwrap();
+    writeln!(report, "uptime: {} ms", uptime_ms).unwrap();
+    writeln!(report, "\npanic:\n{}", panic.text).unwrap();
+    writeln!(report, "\nlog:\n{}", lines).unwrap();
+    report
+}
+
+/// Reports are numbered in the order they were saved, across reboots
+fn save(pddb: &mut pddb::Pddb, report: &str) {
+    let mut numbers: Vec<u32> = pddb.list_keys(CRASH_REPORT_DICT, None)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|k| k.parse::<u32>().ok())
+        .collect();
+    numbers.sort_unstable();
+    while numbers.len() >= MAX_REPORTS {
+        let oldest = numbers.remove(0);
+        pddb.delete_key(CRASH_REPORT_DICT, &format!("{:04}", oldest), None).ok();
+    }
+    let keyname = format!("{:04}", numbers.last().map(|n| n + 1).unwrap_or(1));
+    match pddb.get(CRASH_REPORT_DICT, &keyname, None, true, true, Some(report.len()), None::<fn()>) {
+        Ok(mut key) => match key.write_all(report.as_bytes()).and_then(|_| key.flush()) {
+            Ok(_) => log::info!("saved crash report {}", keyname),
+            Err(e) => log::error!("couldn't write crash report {}: {:?}", keyname, e),
+        },
+        Err(e) => log::error!("couldn't make crash report {}: {:?}", keyname, e),
+    }
+}
@@ -1,6 +1,7 @@
 use gam::*;
 use num_traits::*;
-use keyboard::KeyMap;
+use keyboard::{KeyMap, ScanCode};
+use locales::t;
 
 use crate::StatusOpcode;
 
@@ -56,5 +57,184 @@ pub fn create_kbd_menu(status_conn: xous::CID, kbd_mgr: xous::SID) -> MenuMatic
         });
     }
 
+    menu_items.push(MenuItem {
+        name: xous_ipc::String::from_str(t!("kbdmenu.remap", xous::LANG)),
+        action_conn: Some(status_conn),
+        action_opcode: StatusOpcode::UxRemapKey.to_u32().unwrap(),
+        action_payload: MenuPayload::Scalar([0, 0, 0, 0]),
+        close_on_select: true,
+    });
+    menu_items.push(MenuItem {
+        name: xous_ipc::String::from_str(t!("kbdmenu.clear_remaps", xous::LANG)),
+        action_conn: Some(status_conn),
+        action_opcode: StatusOpcode::ClearRemaps.to_u32().unwrap(),
+        action_payload: MenuPayload::Scalar([0, 0, 0, 0]),
+        close_on_select: true,
+    });
+    menu_items.push(MenuItem {
+        name: xous_ipc::String::from_str(t!("kbdmenu.timing", xous::LANG)),
+        action_conn: Some(status_conn),
+        action_opcode: StatusOpcode::UxKeyTiming.to_u32().unwrap(),
+        action_payload: MenuPayload::Scalar([0, 0, 0, 0]),
+        close_on_select: true,
+    });
+
     menu_matic(menu_items, gam::KBD_MENU_NAME, Some(kbd_mgr)).expect("couldn't create MenuMatic manager")
 }
+
+/// Key remaps are saved one layout to a key, one remap to a line: the label and then the key, shift, hold and
+/// alt codes, all as hex code points, with 0 for "as the layout has it"
+const KBD_REMAP_DICT: &str = "kbd.remaps";
+
+fn layout_key(map: KeyMap) -> Option<&'static str> {
+    match map {
+        KeyMap::Qwerty => Some("qwerty"),
+        KeyMap::Azerty => Some("azerty"),
+        KeyMap::Qwertz => Some("qwertz"),
+        KeyMap::Dvorak => Some("dvorak"),
+        _ => None,
+    }
+}
+
+fn load_layout_remaps(pddb: &mut pddb::Pddb, map: KeyMap) -> Vec<(char, ScanCode)> {
+    use std::io::Read;
+    let mut remaps = Vec::new();
+    let mut text = String::new();
+    match layout_key(map).map(|key| pddb.get(KBD_REMAP_DICT, key, None, false, false, None, None::<fn()>)) {
+        Some(Ok(mut k)) => if k.read_to_string(&mut text).is_err() {
+            return remaps;
+        },
+        _ => return remaps,
+    }
+    for line in text.lines() {
+        let codes: Vec<Option<char>> = line.split(' ')
+            .map(|c| u32::from_str_radix(c, 16).ok().and_then(|c| if c == 0 { None } else { std::char::from_u32(c) }))
+            .collect();
+        if let [Some(label), key, shift, hold, alt] = codes[..] {
+            remaps.push((label, ScanCode { key, shift, hold, alt }));
+        } else {
+            log::warn!("ignoring key remap we don't understand: {}", line);
+        }
+    }
+    remaps
+}
+
+fn save_layout_remaps(pddb: &mut pddb::Pddb, map: KeyMap, remaps: &[(char, ScanCode)]) {
+    use std::io::Write;
+    let key = match layout_key(map) {
+        Some(key) => key,
+        None => return,
+    };
+    let code = |c: Option<char>| c.map(|c| c as u32).unwrap_or(0);
+    let mut text = String::new();
+    for (label, sc) in remaps.iter() {
+        text.push_str(&format!("{:x} {:x} {:x} {:x} {:x}\n",
+            *label as u32, code(sc.key), code(sc.shift), code(sc.hold), code(sc.alt)));
+    }
+    pddb.delete_key(KBD_REMAP_DICT, key, None).ok();
+    if remaps.is_empty() {
+        return;
+    }
+    match pddb.get(KBD_REMAP_DICT, key, None, true, true, Some(text.len()), None::<fn()>) {
+        Ok(mut k) => {
+            k.write_all(text.as_bytes())
+                .and_then(|_| k.flush())
+                .unwrap_or_else(|e| log::error!("couldn't save key remaps: {:?}", e));
+        }
+        Err(e) => log::error!("couldn't save key remaps: {:?}", e),
+    }
+}
+
+/// Hands the keyboard server every saved remap, for all layouts, as it forgets them on reboot
+pub fn load_remaps(pddb: &mut pddb::Pddb, kbd: &keyboard::Keyboard) {
+    for &map in [KeyMap::Qwerty, KeyMap::Azerty, KeyMap::Qwertz, KeyMap::Dvorak].iter() {
+        for (label, code) in load_layout_remaps(pddb, map) {
+            if !kbd.set_remap(map, label, code).unwrap_or(false) {
+                log::warn!("{:?} has no key for saved remap of '{}'", map, label);
+            }
+        }
+    }
+}
+
+/// Remembers a remap the keyboard server has taken, replacing any earlier one of the same key
+pub fn save_remap(pddb: &mut pddb::Pddb, map: KeyMap, label: char, code: ScanCode) {
+    let mut remaps = load_layout_remaps(pddb, map);
+    remaps.retain(|(l, _)| *l != label);
+    remaps.push((label, code));
+    save_layout_remaps(pddb, map, &remaps);
+}
+
+pub fn clear_saved_remaps(pddb: &mut pddb::Pddb, map: KeyMap) {
+    save_layout_remaps(pddb, map, &[]);
+}
+
+/// The key repeat and debounce settings are saved as the repeat delay, repeat rate and debounce time, in ms,
+/// each as a little-endian u32
+const KBD_SETTINGS_DICT: &str = "kbd.settings";
+const KBD_TIMING_KEY: &str = "timing";
+
+/// The settings the user may pick from, in ms
+pub const REPEAT_DELAY_RANGE_MS: core::ops::RangeInclusive<u32> = 100..=5000;
+/// repeating any faster than this would keep the keyboard server too busy to do anything else
+pub const REPEAT_RATE_RANGE_MS: core::ops::RangeInclusive<u32> = 10..=1000;
+pub const DEBOUNCE_RANGE_MS: core::ops::RangeInclusive<u32> = 0..=100;
+
+#[derive(Debug, Copy, Clone)]
+pub struct KeyTiming {
+    pub delay: u32,
+    pub rate: u32,
+    pub debounce: u32,
+}
+impl Default for KeyTiming {
+    fn default() -> Self {
+        KeyTiming {
+            delay: keyboard::DEFAULT_REPEAT_DELAY_MS,
+            rate: keyboard::DEFAULT_REPEAT_RATE_MS,
+            debounce: keyboard::DEFAULT_DEBOUNCE_MS,
+        }
+    }
+}
+
+pub fn apply_key_timing(kbd: &keyboard::Keyboard, timing: KeyTiming) {
+    kbd.set_repeat(timing.delay, timing.rate).expect("couldn't set key repeat");
+    kbd.set_debounce(timing.debounce).expect("couldn't set key debounce");
+}
+
+/// The saved key timing, if there is one, handed to the keyboard server, as it goes back to the defaults on reboot
+pub fn load_key_timing(pddb: &mut pddb::Pddb, kbd: &keyboard::Keyboard) -> Option<KeyTiming> {
+    use std::convert::TryInto;
+    use std::io::Read;
+    let mut data = [0u8; 12];
+    let mut key = pddb.get(KBD_SETTINGS_DICT, KBD_TIMING_KEY, None, false, false, None, None::<fn()>).ok()?;
+    if key.read(&mut data).unwrap_or(0) != data.len() {
+        log::warn!("ignoring saved key timing we don't understand");
+        return None;
+    }
+    // kept to what the menu allows, so a damaged record can't set a rate that swamps the keyboard server
+    let field = |i: usize, range: core::ops::RangeInclusive<u32>| {
+        u32::from_le_bytes(data[i..i + 4].try_into().unwrap()).max(*range.start()).min(*range.end())
+    };
+    let timing = KeyTiming {
+        delay: field(0, REPEAT_DELAY_RANGE_MS),
+        rate: field(4, REPEAT_RATE_RANGE_MS),
+        debounce: field(8, DEBOUNCE_RANGE_MS),
+    };
+    apply_key_timing(kbd, timing);
+    Some(timing)
+}
+
+pub fn save_key_timing(pddb: &mut pddb::Pddb, timing: KeyTiming) {
+    use std::io::Write;
+    let mut data = [0u8; 12];
+    data[0..4].copy_from_slice(&timing.delay.to_le_bytes());
+    data[4..8].copy_from_slice(&timing.rate.to_le_bytes());
+    data[8..12].copy_from_slice(&timing.debounce.to_le_bytes());
+    match pddb.get(KBD_SETTINGS_DICT, KBD_TIMING_KEY, None, true, true, Some(data.len()), None::<fn()>) {
+        Ok(mut key) => {
+            key.write_all(&data)
+                .and_then(|_| key.flush())
+                .unwrap_or_else(|e| log::error!("couldn't save key timing: {:?}", e));
+        }
+        Err(e) => log::error!("couldn't save key timing: {:?}", e),
+    }
+}
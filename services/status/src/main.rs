@@ -7,6 +7,8 @@ mod appmenu;
 use appmenu::*;
 mod kbdmenu;
 use kbdmenu::*;
+mod crash;
+use crash::CrashReporter;
 mod app_autogen;
 
 use com::api::*;
@@ -17,7 +19,7 @@ use graphics_server::*;
 use graphics_server::api::GlyphStyle;
 use locales::t;
 use gam::modal::*;
-use gam::{GamObjectList, GamObjectType};
+use gam::{GamObjectList, GamObjectType, StatusSlotList};
 use llio::Weekday;
 
 use std::collections::HashMap;
@@ -32,6 +34,13 @@ use std::thread;
 
 const SERVER_NAME_STATUS: &str = "_Status bar manager_";
 const SERVER_NAME_STATUS_GID: &str = "_Status bar GID receiver_";
+/// where display preferences are kept, so they survive a reboot once the PDDB is mounted
+pub(crate) const DISPLAY_SETTINGS_DICT: &str = "gam.settings";
+const DISPLAY_INVERTED_KEY: &str = "inverted";
+const DISPLAY_ROTATION_KEY: &str = "rotation";
+const DISPLAY_LARGE_TEXT_KEY: &str = "largetext";
+/// soft-hyphenation dictionaries for the typesetter, with one key per language (as in `xous::LANG`)
+const HYPHENATION_DICT: &str = "gfx.hyphenation";
 
 #[derive(Debug, num_derive::FromPrimitive, num_derive::ToPrimitive)]
 pub(crate) enum StatusOpcode {
@@ -60,6 +69,12 @@ pub(crate) enum StatusOpcode {
 
     /// Set the keyboard map
     SetKeyboard,
+    /// Pulls up the UI for changing what a key sends, from the keyboard menu
+    UxRemapKey,
+    /// Puts every key in the current layout back to what it sends, from the keyboard menu
+    ClearRemaps,
+    /// Pulls up the UI for setting key repeat and debounce, from the keyboard menu
+    UxKeyTiming,
 
     /// Suspend handler from the main menu
     TrySuspend,
@@ -67,6 +82,14 @@ pub(crate) enum StatusOpcode {
     BatteryDisconnect,
     /// for returning wifi stats
     WifiStats,
+    /// Flips the display between normal and light-on-dark, from the main menu
+    ToggleInverted,
+    /// Turns the screen a quarter turn further clockwise, from the main menu
+    RotateScreen,
+    /// Switches text between its normal size and the doubled accessibility size, from the main menu
+    ToggleLargeText,
+    /// The app launcher's order or recently used apps changed
+    LauncherChanged,
     Quit,
 }
 
@@ -258,6 +281,18 @@ fn xmain() -> ! {
     security_tv.invert = true;
     write!(&mut security_tv, "{}", t!("secnote.startup", xous::LANG)).unwrap();
     gam.post_textview(&mut security_tv).unwrap();
+    // indicator slots claimed by other services, drawn over the right end of the security line
+    let mut slots_tv = TextView::new(
+        status_gid,
+        TextBounds::GrowableFromTr(Point::new(screensize.x, screensize.y / 2 + 1), (screensize.x / 3) as u16),
+    );
+    slots_tv.style = GlyphStyle::Regular;
+    slots_tv.draw_border = false;
+    slots_tv.margin = Point::new(0, 0);
+    slots_tv.token = security_tv.token;
+    slots_tv.clear_area = true;
+    slots_tv.invert = true;
+    let mut slot_list: Option<StatusSlotList> = None;
     gam.draw_line(status_gid, Line::new_with_style(
         Point::new(0, screensize.y), screensize,
         DrawStyle::new(PixelColor::Light, PixelColor::Light, 1))).unwrap();
@@ -317,6 +352,7 @@ fn xmain() -> ! {
 
     let dt_pump_interval = 15;
     let charger_pump_interval = 180;
+    let crash_interval = 5;
     let batt_interval;
     let secnotes_interval;
     if cfg!(feature = "braille") {
@@ -344,7 +380,8 @@ fn xmain() -> ! {
 
     log::debug!("starting main menu thread");
     create_main_menu(keys.clone(), xous::connect(status_sid).unwrap(), &com);
-    create_app_menu(xous::connect(status_sid).unwrap());
+    let launcher_mgr = xous::create_server().unwrap();
+    let launcher = create_app_launcher(xous::connect(status_sid).unwrap(), launcher_mgr);
     let kbd_mgr = xous::create_server().unwrap();
     let kbd_menumatic = create_kbd_menu(xous::connect(status_sid).unwrap(), kbd_mgr);
     let kbd = keyboard::Keyboard::new(&xns).unwrap();
@@ -363,6 +400,19 @@ fn xmain() -> ! {
     log::debug!("subscribe to wifi updates");
     netmgr.wifi_state_subscribe(cb_cid, StatusOpcode::WifiStats.to_u32().unwrap()).unwrap();
     let mut wifi_status: WlanStatus = WlanStatus::from_ipc(WlanStatusIpc::default());
+    let mut pddb = pddb::Pddb::new();
+    let mut inverted = false;
+    let mut rotation = gam::Rotation::Upright;
+    let mut large_text = false;
+    // the saved display setting can only be read once the PDDB is mounted, which happens some time after boot
+    let mut display_settings_loaded = false;
+    // likewise the launcher layout; it's only saved once it has been loaded, so a change made during boot can't clobber it
+    let mut launcher_layout_loaded = false;
+    let mut key_remaps_loaded = false;
+    let mut key_timing = KeyTiming::default();
+    let mut key_timing_loaded = false;
+    let mut hyphenation_loaded = false;
+    let mut crash_reporter = CrashReporter::new();
 
     #[cfg(feature="tts")]
     thread::spawn({
@@ -412,6 +462,11 @@ fn xmain() -> ! {
                                 ssid.name.as_str().unwrap_or("UTF-8 Erorr"),
                                 ssid.rssi,
                             ).unwrap();
+                            match netmgr.get_connectivity() {
+                                Ok(net::Connectivity::Portal) => write!(&mut battstats_tv, " ({})", t!("stats.portal", xous::LANG)).unwrap(),
+                                Ok(net::Connectivity::NoInternet) => write!(&mut battstats_tv, " ({})", t!("stats.no_internet", xous::LANG)).unwrap(),
+                                _ => {}
+                            }
                         } else {
                             write!(
                                 &mut battstats_tv,
@@ -459,6 +514,14 @@ fn xmain() -> ! {
                     gam.draw_list(draw_list).expect("couldn't draw object list");
                 }
 
+                // the GAM arbitrates the indicator slots; a change means the whole security line has to be redone
+                if let Some(token) = slots_tv.token {
+                    let latest = gam.status_slots(token).expect("couldn't fetch status slots");
+                    if slot_list.map_or(true, |l| l.generation != latest.generation) {
+                        secnotes_force_redraw = true;
+                    }
+                    slot_list = Some(latest);
+                }
                 // update the security status, if any
                 let (is_locked, force_update) = llio.debug_usb(None).unwrap();
                 if (debug_locked != is_locked)
@@ -506,10 +569,80 @@ fn xmain() -> ! {
 
                     secnotes_force_redraw = false;
                     gam.post_textview(&mut security_tv).unwrap();
+                    if let Some(list) = slot_list {
+                        if list.count > 0 {
+                            slots_tv.clear_str();
+                            for slot in list.slots[..list.count].iter() {
+                                write!(&mut slots_tv, " {}", slot).unwrap();
+                            }
+                            gam.post_textview(&mut slots_tv).unwrap();
+                        }
+                    }
                     gam.draw_line(status_gid, Line::new_with_style(
                         Point::new(0, screensize.y), screensize,
                         DrawStyle::new(PixelColor::Light, PixelColor::Light, 1))).unwrap();
                 }
+                if !display_settings_loaded && (stats_phase % secnotes_interval) == 1 && pddb.is_mounted() {
+                    display_settings_loaded = true;
+                    if let Ok(mut key) = pddb.get(DISPLAY_SETTINGS_DICT, DISPLAY_INVERTED_KEY, None, false, false, None, None::<fn()>) {
+                        use std::io::Read;
+                        let mut setting = [0u8; 1];
+                        if key.read(&mut setting).unwrap_or(0) == 1 && (setting[0] != 0) != inverted {
+                            inverted = setting[0] != 0;
+                            gam.set_inverted(security_tv.token.unwrap(), inverted).expect("couldn't set display inversion");
+                        }
+                    }
+                    if let Ok(mut key) = pddb.get(DISPLAY_SETTINGS_DICT, DISPLAY_ROTATION_KEY, None, false, false, None, None::<fn()>) {
+                        use std::io::Read;
+                        let mut setting = [0u8; 1];
+                        if key.read(&mut setting).unwrap_or(0) == 1 {
+                            if let Some(saved) = FromPrimitive::from_u8(setting[0]) {
+                                rotation = saved;
+                                gam.set_rotation(security_tv.token.unwrap(), rotation).expect("couldn't set screen rotation");
+                            }
+                        }
+                    }
+                    if let Ok(mut key) = pddb.get(DISPLAY_SETTINGS_DICT, DISPLAY_LARGE_TEXT_KEY, None, false, false, None, None::<fn()>) {
+                        use std::io::Read;
+                        let mut setting = [0u8; 1];
+                        if key.read(&mut setting).unwrap_or(0) == 1 && (setting[0] != 0) != large_text {
+                            large_text = setting[0] != 0;
+                            gam.set_large_text(security_tv.token.unwrap(), large_text).expect("couldn't set large text");
+                        }
+                    }
+                }
+                if !launcher_layout_loaded && (stats_phase % secnotes_interval) == 1 && pddb.is_mounted() {
+                    launcher_layout_loaded = true;
+                    if let Some(layout) = load_launcher_layout(&mut pddb) {
+                        launcher.set_layout(layout);
+                    }
+                }
+                if !key_remaps_loaded && (stats_phase % secnotes_interval) == 1 && pddb.is_mounted() {
+                    key_remaps_loaded = true;
+                    load_remaps(&mut pddb, &kbd);
+                }
+                if !key_timing_loaded && (stats_phase % secnotes_interval) == 1 && pddb.is_mounted() {
+                    key_timing_loaded = true;
+                    if let Some(timing) = load_key_timing(&mut pddb, &kbd) {
+                        key_timing = timing;
+                    }
+                }
+                if !hyphenation_loaded && (stats_phase % secnotes_interval) == 1 && pddb.is_mounted() {
+                    hyphenation_loaded = true;
+                    // the dictionary is optional; without one, words are only broken at hyphens in the text
+                    if let Ok(mut key) = pddb.get(HYPHENATION_DICT, xous::LANG, None, false, false, None, None::<fn()>) {
+                        use std::io::Read;
+                        let mut dictionary = Vec::<u8>::new();
+                        match key.read_to_end(&mut dictionary) {
+                            Ok(_) => gam.load_hyphenation(security_tv.token.unwrap(), &dictionary)
+                                .unwrap_or_else(|e| log::warn!("couldn't load hyphenation dictionary: {:?}", e)),
+                            Err(e) => log::warn!("couldn't read hyphenation dictionary: {:?}", e),
+                        }
+                    }
+                }
+                if (stats_phase % crash_interval) == 3 {
+                    crash_reporter.poll(&mut pddb, elapsed_time);
+                }
                 if (stats_phase % batt_interval) == (batt_interval - 1) {
                     com.req_batt_stats()
                         .expect("Can't get battery stats from COM");
@@ -740,6 +873,64 @@ fn xmain() -> ! {
                 let map = keyboard::KeyMap::from(code);
                 kbd.set_keymap(map).expect("couldn't set keyboard mapping");
             }),
+            Some(StatusOpcode::UxRemapKey) => {
+                ticktimer.sleep_ms(100).ok(); // yield for a moment to allow the previous menu to close
+                let map = kbd.get_keymap().expect("couldn't get key mapping");
+                let label = modals.get_text(
+                    t!("kbd.remap_which", xous::LANG),
+                    Some(remap_ux_validator), Some(ValidatorOp::UxRemapLabel.to_u32().unwrap())
+                ).expect("couldn't get key to remap").as_str().chars().next().expect("pre-validated input was empty!");
+                let sends = modals.get_text(
+                    t!("kbd.remap_to", xous::LANG),
+                    Some(remap_ux_validator), Some(ValidatorOp::UxRemapTo.to_u32().unwrap())
+                ).expect("couldn't get what the key sends");
+                let mut chars = sends.as_str().chars();
+                let code = keyboard::ScanCode {
+                    key: chars.next(),
+                    shift: chars.next(),
+                    hold: None,
+                    alt: None,
+                };
+                match kbd.set_remap(map, label, code) {
+                    Ok(true) => {
+                        if pddb.is_mounted() {
+                            save_remap(&mut pddb, map, label, code);
+                        } else {
+                            log::warn!("PDDB isn't mounted, so the remap of '{}' only lasts until reboot", label);
+                        }
+                    }
+                    Ok(false) => modals.show_notification(t!("kbd.remap_nokey", xous::LANG)).expect("couldn't show notification"),
+                    Err(e) => log::error!("couldn't remap key: {:?}", e),
+                }
+            },
+            Some(StatusOpcode::ClearRemaps) => {
+                let map = kbd.get_keymap().expect("couldn't get key mapping");
+                kbd.clear_remaps(map).expect("couldn't clear key remaps");
+                if pddb.is_mounted() {
+                    clear_saved_remaps(&mut pddb, map);
+                }
+            },
+            Some(StatusOpcode::UxKeyTiming) => {
+                ticktimer.sleep_ms(100).ok(); // yield for a moment to allow the previous menu to close
+                let ask = |prompt: &str, now: u32, op: ValidatorOp| -> u32 {
+                    modals.get_text(
+                        &format!("{} {}", prompt, now),
+                        Some(key_timing_ux_validator), Some(op.to_u32().unwrap())
+                    ).expect("couldn't get key timing").as_str().parse::<u32>().expect("pre-validated input failed to re-parse!")
+                };
+                key_timing = KeyTiming {
+                    delay: ask(t!("kbd.repeat_delay", xous::LANG), key_timing.delay, ValidatorOp::UxRepeatDelay),
+                    rate: ask(t!("kbd.repeat_rate", xous::LANG), key_timing.rate, ValidatorOp::UxRepeatRate),
+                    debounce: ask(t!("kbd.debounce", xous::LANG), key_timing.debounce, ValidatorOp::UxDebounce),
+                };
+                apply_key_timing(&kbd, key_timing);
+                if pddb.is_mounted() {
+                    save_key_timing(&mut pddb, key_timing);
+                } else {
+                    // don't let a saved setting override the user's choice once the PDDB does mount
+                    key_timing_loaded = true;
+                }
+            },
             Some(StatusOpcode::SwitchToShellchat) => {
                 ticktimer.sleep_ms(100).ok();
                 sec_notes.lock().unwrap().remove(&"current_app".to_string());
@@ -782,6 +973,64 @@ fn xmain() -> ! {
                     com.power_off_soc().unwrap();
                 }
             },
+            Some(StatusOpcode::ToggleInverted) => {
+                inverted = !inverted;
+                gam.set_inverted(security_tv.token.unwrap(), inverted).expect("couldn't set display inversion");
+                if pddb.is_mounted() {
+                    use std::io::Write;
+                    match pddb.get(DISPLAY_SETTINGS_DICT, DISPLAY_INVERTED_KEY, None, true, true, Some(1), None::<fn()>) {
+                        Ok(mut key) => {
+                            key.write_all(&[if inverted { 1 } else { 0 }])
+                                .and_then(|_| key.flush())
+                                .unwrap_or_else(|e| log::error!("couldn't save display inversion: {:?}", e));
+                        }
+                        Err(e) => log::error!("couldn't save display inversion: {:?}", e),
+                    }
+                } else {
+                    // don't let a saved setting override the user's choice once the PDDB does mount
+                    display_settings_loaded = true;
+                }
+            },
+            Some(StatusOpcode::RotateScreen) => {
+                rotation = rotation.next();
+                gam.set_rotation(security_tv.token.unwrap(), rotation).expect("couldn't set screen rotation");
+                if pddb.is_mounted() {
+                    use std::io::Write;
+                    match pddb.get(DISPLAY_SETTINGS_DICT, DISPLAY_ROTATION_KEY, None, true, true, Some(1), None::<fn()>) {
+                        Ok(mut key) => {
+                            key.write_all(&[rotation.to_u8().unwrap()])
+                                .and_then(|_| key.flush())
+                                .unwrap_or_else(|e| log::error!("couldn't save screen rotation: {:?}", e));
+                        }
+                        Err(e) => log::error!("couldn't save screen rotation: {:?}", e),
+                    }
+                } else {
+                    display_settings_loaded = true;
+                }
+            },
+            Some(StatusOpcode::ToggleLargeText) => {
+                large_text = !large_text;
+                gam.set_large_text(security_tv.token.unwrap(), large_text).expect("couldn't set large text");
+                if pddb.is_mounted() {
+                    use std::io::Write;
+                    match pddb.get(DISPLAY_SETTINGS_DICT, DISPLAY_LARGE_TEXT_KEY, None, true, true, Some(1), None::<fn()>) {
+                        Ok(mut key) => {
+                            key.write_all(&[if large_text { 1 } else { 0 }])
+                                .and_then(|_| key.flush())
+                                .unwrap_or_else(|e| log::error!("couldn't save large text: {:?}", e));
+                        }
+                        Err(e) => log::error!("couldn't save large text: {:?}", e),
+                    }
+                } else {
+                    display_settings_loaded = true;
+                }
+            },
+            Some(StatusOpcode::LauncherChanged) => {
+                // changes made before the PDDB is mounted are dropped, and the saved layout is restored on mount
+                if launcher_layout_loaded {
+                    save_launcher_layout(&mut pddb, &launcher.layout());
+                }
+            },
             Some(StatusOpcode::Quit) => {
                 break;
             }
@@ -814,6 +1063,50 @@ pub(crate) enum ValidatorOp {
     UxHour,
     UxMinute,
     UxSeconds,
+    UxRemapLabel,
+    UxRemapTo,
+    UxRepeatDelay,
+    UxRepeatRate,
+    UxDebounce,
+}
+
+fn key_timing_ux_validator(input: TextEntryPayload, opcode: u32) -> Option<ValidatorErr> {
+    let ms = match input.as_str().parse::<u32>() {
+        Ok(ms) => ms,
+        _ => return Some(ValidatorErr::from_str(t!("rtc.integer_err", xous::LANG))),
+    };
+    let range = match FromPrimitive::from_u32(opcode) {
+        Some(ValidatorOp::UxRepeatDelay) => REPEAT_DELAY_RANGE_MS,
+        Some(ValidatorOp::UxRepeatRate) => REPEAT_RATE_RANGE_MS,
+        Some(ValidatorOp::UxDebounce) => DEBOUNCE_RANGE_MS,
+        _ => {
+            log::error!("internal error: invalid opcode was sent to validator: {:?}", opcode);
+            panic!("internal error: invalid opcode was sent to validator");
+        }
+    };
+    if range.contains(&ms) {
+        None
+    } else {
+        Some(ValidatorErr::from_str(t!("rtc.range_err", xous::LANG)))
+    }
+}
+
+fn remap_ux_validator(input: TextEntryPayload, opcode: u32) -> Option<ValidatorErr> {
+    let count = input.as_str().chars().count();
+    let ok = match FromPrimitive::from_u32(opcode) {
+        Some(ValidatorOp::UxRemapLabel) => count == 1,
+        // what it sends, and optionally what it sends with shift
+        Some(ValidatorOp::UxRemapTo) => count == 1 || count == 2,
+        _ => {
+            log::error!("internal error: invalid opcode was sent to validator: {:?}", opcode);
+            panic!("internal error: invalid opcode was sent to validator");
+        }
+    };
+    if ok {
+        None
+    } else {
+        Some(ValidatorErr::from_str(t!("kbd.remap_len_err", xous::LANG)))
+    }
 }
 
 fn rtc_ux_validator(input: TextEntryPayload, opcode: u32) -> Option<ValidatorErr> {
@@ -32,6 +32,33 @@ pub fn create_main_menu(keys: Arc<Mutex<RootKeys>>, status_conn: xous::CID, com:
         close_on_select: true,
     });
 
+    #[cfg(not(feature="tts"))]
+    menuitems.push(MenuItem {
+        name: String::from_str(t!("mainmenu.invert", xous::LANG)),
+        action_conn: Some(status_conn),
+        action_opcode: StatusOpcode::ToggleInverted.to_u32().unwrap(),
+        action_payload: MenuPayload::Scalar([0, 0, 0, 0]),
+        close_on_select: true,
+    });
+
+    #[cfg(not(feature="tts"))]
+    menuitems.push(MenuItem {
+        name: String::from_str(t!("mainmenu.rotate", xous::LANG)),
+        action_conn: Some(status_conn),
+        action_opcode: StatusOpcode::RotateScreen.to_u32().unwrap(),
+        action_payload: MenuPayload::Scalar([0, 0, 0, 0]),
+        close_on_select: true,
+    });
+
+    #[cfg(not(feature="tts"))]
+    menuitems.push(MenuItem {
+        name: String::from_str(t!("mainmenu.largetext", xous::LANG)),
+        action_conn: Some(status_conn),
+        action_opcode: StatusOpcode::ToggleLargeText.to_u32().unwrap(),
+        action_payload: MenuPayload::Scalar([0, 0, 0, 0]),
+        close_on_select: true,
+    });
+
     menuitems.push(MenuItem {
         name: String::from_str(t!("mainmenu.sleep", xous::LANG)),
         action_conn: Some(status_conn),
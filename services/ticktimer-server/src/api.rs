@@ -47,9 +47,54 @@ pub(crate) enum Opcode {
     /// *arg1*: An integer of some sort, such as the address of the Condvar
     /// *arg2*: The number of conditions to notify
     NotifyCondition = 9,
+
+    /// Have a message sent to a server at a given time, and optionally every so often after that
+    ///
+    /// # Arguments
+    ///
+    /// A `CallRequest`, lent mutably. `handle` comes back as the call's handle, or 0 if it was refused.
+    ///
+    /// At each expiry the server gets a non-blocking scalar with the requested opcode. *arg1* is the handle,
+    /// and *arg2* and *arg3* are the lower and upper halves of the time it was due, in ms since boot.
+    RegisterCall = 10,
+
+    /// Stop a call made with `RegisterCall`
+    ///
+    /// # Arguments
+    ///
+    /// *arg1*: The handle of the call
+    CancelCall = 11,
+
+    /// Move a virtual clock on, firing everything that falls due along the way. Only hosted builds with
+    /// the `virtual-clock` feature have one.
+    ///
+    /// # Arguments
+    ///
+    /// *arg1*: The lower half of the number of ms to advance by
+    /// *arg2*: The upper half
+    ///
+    /// Returns 1 once the clock has been advanced, or 0 straight away if it can't be.
+    AdvanceClock = 12,
+
+    /// Hand feeding the hardware WDT over to the caller, which from then on has to send `PingWdt` often
+    /// enough itself. Until this is sent, the WDT is fed whenever the ticktimer gets a message. Only the
+    /// watchdog server may claim it, which it does when it starts.
+    ClaimWdt = 13,
 }
 
 #[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
 pub struct VersionString {
     pub version: xous_ipc::String::<512>,
-}
\ No newline at end of file
+}
+
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct CallRequest {
+    /// The server to send the message to
+    pub sid: (u32, u32, u32, u32),
+    pub opcode: u32,
+    /// When the first message goes out, in ms since boot
+    pub at_ms: u64,
+    /// Time between messages after the first, or 0 to send just the one
+    pub period_ms: u64,
+    pub handle: u32,
+}
@@ -61,6 +61,16 @@ impl Ticktimer {
         .expect("Couldn't send WDT ping");
     }
 
+    /// Stops the ticktimer feeding the WDT on its own, leaving it to `ping_wdt()` calls from the caller.
+    /// Only the first caller gets it.
+    pub fn claim_wdt(&self) {
+        send_message(
+            self.conn,
+            xous::Message::new_scalar(api::Opcode::ClaimWdt.to_usize().unwrap(), 0, 0, 0, 0),
+        )
+        .expect("Couldn't claim the WDT");
+    }
+
     pub fn get_version(&self) -> String {
         let alloc = api::VersionString {
             version: xous_ipc::String::new(),
@@ -167,6 +177,99 @@ impl Ticktimer {
         .map(|r| r == xous::Result::Scalar1(0))
         .expect("couldn't notify condition");
     }
+
+    /// Have the ticktimer send a message to a server once the clock reaches `at_ms`, as
+    /// returned by `elapsed_ms()`. A time that has already passed sends it right away.
+    ///
+    /// The server gets a non-blocking scalar `opcode`, with the handle of the call in *arg1* and
+    /// the time it was due in *arg2* (lower half) and *arg3* (upper half).
+    ///
+    /// # Returns:
+    ///
+    ///     * the handle of the call, which `cancel_call()` takes
+    ///     * Error::OutOfMemory: the call was refused, because the server to call doesn't exist
+    ///       or the ticktimer has too many calls pending
+    pub fn call_at(&self, at_ms: u64, sid: xous::SID, opcode: usize) -> Result<u32, Error> {
+        self.register_call(at_ms, 0, sid, opcode)
+    }
+
+    /// Have the ticktimer send a message to a server every `period_ms` milliseconds, starting
+    /// `period_ms` from now. The message is the same as for `call_at()`. A server that doesn't
+    /// keep up misses calls rather than having them pile up in its queue. A period of 0 is
+    /// taken as 1 ms.
+    pub fn call_every(&self, period_ms: u64, sid: xous::SID, opcode: usize) -> Result<u32, Error> {
+        let period_ms = period_ms.max(1);
+        self.register_call(self.elapsed_ms() + period_ms, period_ms, sid, opcode)
+    }
+
+    fn register_call(
+        &self,
+        at_ms: u64,
+        period_ms: u64,
+        sid: xous::SID,
+        opcode: usize,
+    ) -> Result<u32, Error> {
+        let request = api::CallRequest {
+            sid: sid.to_u32(),
+            opcode: opcode as u32,
+            at_ms,
+            period_ms,
+            handle: 0,
+        };
+        let mut buf = xous_ipc::Buffer::into_buf(request).or(Err(Error::InternalError))?;
+        buf.lend_mut(self.conn, api::Opcode::RegisterCall.to_u32().unwrap())?;
+        let handle = buf
+            .to_original::<api::CallRequest, _>()
+            .or(Err(Error::InternalError))?
+            .handle;
+        if handle == 0 {
+            Err(Error::OutOfMemory)
+        } else {
+            Ok(handle)
+        }
+    }
+
+    /// Stop a call made with `call_at()` or `call_every()`. A message that was already on its
+    /// way may still arrive.
+    pub fn cancel_call(&self, handle: u32) {
+        send_message(
+            self.conn,
+            xous::Message::new_scalar(
+                api::Opcode::CancelCall.to_usize().unwrap(),
+                handle as usize,
+                0,
+                0,
+                0,
+            ),
+        )
+        .expect("couldn't cancel call");
+    }
+
+    /// Move the clock on by `ms`, which only a hosted build with the `virtual-clock` feature can do.
+    /// Everything that falls due on the way is fired in order, each at the time it was due, before this
+    /// returns. Until then, the clock stands still: `elapsed_ms()` only changes when it's advanced.
+    ///
+    /// # Returns:
+    ///
+    ///     * Error::UnhandledSyscall: the clock can't be advanced, because it's the real one,
+    ///       or because someone else is advancing it
+    pub fn advance_clock(&self, ms: u64) -> Result<(), Error> {
+        let response = send_message(
+            self.conn,
+            xous::Message::new_blocking_scalar(
+                api::Opcode::AdvanceClock.to_usize().unwrap(),
+                (ms & 0xFFFF_FFFF) as usize,
+                (ms >> 32) as usize,
+                0,
+                0,
+            ),
+        )?;
+        if response == xous::Result::Scalar1(1) {
+            Ok(())
+        } else {
+            Err(Error::UnhandledSyscall)
+        }
+    }
 }
 
 use core::sync::atomic::{AtomicU32, Ordering};
@@ -4,17 +4,45 @@
 
 mod api;
 mod version;
+mod wheel;
+#[cfg(any(feature = "virtual-clock", test))]
+mod virtual_clock;
 
-use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use log::{error, info};
+use wheel::TimerWheel;
 
 type TimeoutExpiry = i64;
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+/// Most calls registered with `RegisterCall` that can be pending at once
+const MAX_CALLS: usize = 256;
+
+/// Whether `sender` is the watchdog server, the only process that may take over feeding the WDT. It's known by
+/// the name it was built with, which apps loaded at runtime don't have.
+fn is_watchdog(sender: xous::MessageSender) -> bool {
+    #[cfg(any(target_os = "none", target_os = "xous"))]
+    {
+        let mut name = [0u8; 64];
+        match sender.pid().map(|pid| xous::process_name(pid, &mut name)) {
+            Some(Ok(len)) if len > 0 && len < name.len() => &name[..len] == b"watchdog",
+            _ => false,
+        }
+    }
+    #[cfg(not(any(target_os = "none", target_os = "xous")))]
+    {
+        // processes don't have names in hosted mode
+        let _ = sender;
+        true
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
 pub enum RequestKind {
     Sleep = 0,
     Timeout = 1,
+    /// A message to send on a client's behalf. There's no sender to return to; `data` is the call's handle.
+    Call = 2,
 }
 
 #[derive(Eq)]
@@ -84,8 +112,10 @@ mod implementation {
         // Safe because we're in an interrupt, and this interrupt is only
         // enabled when this value is not None.
         let response = xtt.current_response.take().unwrap();
-        xous::return_scalar(response.sender, response.kind as usize)
-            .expect("couldn't send response");
+        if response.kind != super::RequestKind::Call {
+            xous::return_scalar(response.sender, response.kind as usize)
+                .expect("couldn't send response");
+        }
 
         // Disable the timer
         xtt.csr.wfo(utra::ticktimer::EV_ENABLE_ALARM, 0);
@@ -280,10 +310,18 @@ mod implementation {
                 self.csr.r(utra::ticktimer::MSLEEP_TARGET0)
             );
         }
+
+        /// Real time can't be advanced: returns false, and `sender` is left for the caller to answer
+        pub fn advance_clock(&mut self, _sender: xous::MessageSender, _ms: u64) -> bool {
+            false
+        }
+        pub fn recalculated(&mut self) {
+            // dummy function, does nothing
+        }
     }
 }
 
-#[cfg(not(any(target_os = "none", target_os = "xous")))]
+#[cfg(all(not(any(target_os = "none", target_os = "xous")), not(feature = "virtual-clock")))]
 mod implementation {
     use crate::RequestKind;
 
@@ -295,8 +333,7 @@ mod implementation {
     enum SleepComms {
         InterruptSleep,
         StartSleep(
-            xous::MessageSender,
-            i64, /* ms */
+            TimerRequest,
             u64, /* elapsed */
         ),
     }
@@ -323,10 +360,12 @@ mod implementation {
                     match result {
                         Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
                             let response = current_response.take().unwrap();
-                            #[cfg(feature = "debug-print")]
-                            log::info!("Returning scalar to {}", response.sender);
-                            xous::return_scalar(response.sender, response.kind as usize)
-                                .expect("couldn't send response");
+                            if response.kind != RequestKind::Call {
+                                #[cfg(feature = "debug-print")]
+                                log::info!("Returning scalar to {}", response.sender);
+                                xous::return_scalar(response.sender, response.kind as usize)
+                                    .expect("couldn't send response");
+                            }
 
                             // This is dangerous and may panic if the queue is full.
                             xous::try_send_message(
@@ -349,33 +388,28 @@ mod implementation {
                             timeout = None;
                             time_remaining_sender.send(current_response.take()).unwrap()
                         }
-                        Ok(SleepComms::StartSleep(new_sender, expiry, elapsed)) => {
-                            let mut duration = expiry - (elapsed as i64);
+                        Ok(SleepComms::StartSleep(request, elapsed)) => {
+                            let mut duration = request.msec - (elapsed as i64);
                             if duration > 0 {
                                 #[cfg(feature = "debug-print")]
                                 log::info!(
                                     "Starting sleep for {} ms, returning to {}",
                                     duration,
-                                    new_sender
+                                    request.sender
                                 );
                             } else {
                                 #[cfg(feature = "debug-print")]
                                 log::info!(
                                     "Clamping duration to 0 (was: {})m returning to {}",
                                     duration,
-                                    new_sender
+                                    request.sender
                                 );
                                 duration = 0;
                             }
                             timeout = Some(std::time::Duration::from_millis(
                                 duration.try_into().unwrap(),
                             ));
-                            current_response = Some(TimerRequest {
-                                sender: new_sender,
-                                msec: expiry,
-                                kind: RequestKind::Sleep,
-                                data: 0,
-                            });
+                            current_response = Some(request);
                         }
                     }
                 }
@@ -410,12 +444,9 @@ mod implementation {
                 self.elapsed_ms(),
                 request.sender
             );
+            let elapsed = self.elapsed_ms();
             self.sleep_comms
-                .send(SleepComms::StartSleep(
-                    request.sender,
-                    request.msec as i64,
-                    self.elapsed_ms(),
-                ))
+                .send(SleepComms::StartSleep(request, elapsed))
                 .unwrap();
         }
 
@@ -432,6 +463,119 @@ mod implementation {
         }
         pub fn suspend(&self) {}
         pub fn resume(&self) {}
+
+        /// Real time can't be advanced: returns false, and `sender` is left for the caller to answer
+        pub fn advance_clock(&mut self, _sender: xous::MessageSender, _ms: u64) -> bool {
+            false
+        }
+        pub fn recalculated(&mut self) {
+            // dummy function, does nothing
+        }
+    }
+}
+
+/// A clock that stands still until it's advanced with `AdvanceClock`, so that hosted-mode tests of
+/// anything timed come out the same on every run, and take no longer than it takes to run them.
+///
+/// An advance moves the clock to each deadline it passes in turn, firing what's due there exactly as the
+/// timer would, and only answers once nothing more is due before its end. A thread woken along the way
+/// that sleeps again before then is woken again within the same advance, so long as it asks in time; to
+/// be sure of every wakeup of something that repeats, advance by no more than its period at once.
+#[cfg(all(not(any(target_os = "none", target_os = "xous")), feature = "virtual-clock"))]
+mod implementation {
+    use crate::RequestKind;
+
+    use super::TimerRequest;
+    use crate::virtual_clock::VirtualClock;
+    use num_traits::ToPrimitive;
+
+    pub struct XousTickTimer {
+        cid: xous::CID,
+        clock: VirtualClock,
+    }
+
+    impl XousTickTimer {
+        pub fn new(cid: xous::CID) -> XousTickTimer {
+            log::info!("the ticktimer is running on a virtual clock");
+            XousTickTimer { cid, clock: VirtualClock::new() }
+        }
+
+        pub fn reset(&mut self) {
+            self.clock.reset();
+        }
+
+        pub fn elapsed_ms(&self) -> u64 {
+            self.clock.now() as u64
+        }
+
+        pub fn stop_interrupt(&mut self) -> Option<TimerRequest> {
+            self.clock.take_pending()
+        }
+
+        pub fn schedule_response(&mut self, request: TimerRequest) {
+            if let Some(due) = self.clock.schedule(request) {
+                self.fire(due);
+            }
+        }
+
+        /// Does what the hosted timer's thread does when a sleep runs out, at the moment it was due
+        fn fire(&mut self, response: TimerRequest) {
+            if response.kind != RequestKind::Call {
+                xous::return_scalar(response.sender, response.kind as usize)
+                    .expect("couldn't send response");
+            }
+            xous::try_send_message(
+                self.cid,
+                xous::Message::Scalar(xous::ScalarMessage {
+                    id: crate::api::Opcode::RecalculateSleep.to_usize().unwrap(),
+                    arg1: response.sender.to_usize(),
+                    arg2: response.kind as usize,
+                    arg3: response.data,
+                    arg4: 0,
+                }),
+            )
+            .unwrap();
+        }
+
+        /// Moves the clock on by `ms`, answering `sender` once it's there. Returns false, leaving `sender`
+        /// for the caller to answer, if an advance is already going on.
+        pub fn advance_clock(&mut self, sender: xous::MessageSender, ms: u64) -> bool {
+            if !self.clock.advance(sender, ms) {
+                return false;
+            }
+            if let Some(next) = self.clock.take_pending() {
+                self.schedule_response(next);
+            }
+            self.settle();
+            true
+        }
+
+        /// Called once the sleep heap has been recalculated after a request fired
+        pub fn recalculated(&mut self) {
+            self.clock.recalculated();
+            self.settle();
+        }
+
+        /// Finishes the advance, if nothing more is due before its end
+        fn settle(&mut self) {
+            if let Some(sender) = self.clock.settle() {
+                xous::return_scalar(sender, 1).expect("couldn't answer clock advance");
+            }
+        }
+
+        #[allow(dead_code)]
+        pub fn reset_wdt(&self) {
+            // dummy function, does nothing
+        }
+        pub fn register_suspend_listener(
+            &self,
+            _opcode: u32,
+            _cid: xous::CID,
+        ) -> Result<(), xous::Error> {
+            Ok(())
+        }
+        pub fn suspend(&self) {}
+        pub fn resume(&self) {}
     }
 }
 
@@ -443,13 +587,13 @@ use susres::SuspendOrder;
 /// remaining.
 fn stop_sleep(
     ticktimer: &mut XousTickTimer,
-    sleep_heap: &mut BTreeMap<TimeoutExpiry, TimerRequest>, // min-heap with Reverse
+    sleep_heap: &mut TimerWheel,
 ) {
     // If there's a sleep request ongoing now, grab it.
     if let Some(current) = ticktimer.stop_interrupt() {
         #[cfg(feature = "debug-print")]
         info!("Existing request was {:?}", current);
-        sleep_heap.insert(current.msec, current);
+        sleep_heap.insert(current);
     } else {
         #[cfg(feature = "debug-print")]
         info!("There was no existing sleep() request");
@@ -458,14 +602,11 @@ fn stop_sleep(
 
 fn start_sleep(
     ticktimer: &mut XousTickTimer,
-    sleep_heap: &mut BTreeMap<TimeoutExpiry, TimerRequest>, // min-heap with Reverse
+    sleep_heap: &mut TimerWheel,
 ) {
     // If there are items in the sleep heap, take the next item that will expire.
-    // TODO: Replace this with `.min()` when it's stabilized:
-    // https://github.com/rust-lang/rust/issues/62924
-    let next_timeout_msec = sleep_heap.iter().min().map(|(msec, _)| *msec);
-    if let Some(msec) = next_timeout_msec {
-        let next_response = sleep_heap.remove(&msec).unwrap();
+    sleep_heap.advance(ticktimer.elapsed_ms() as i64);
+    if let Some(next_response) = sleep_heap.pop_next() {
         #[cfg(feature = "debug-print")]
         info!(
             "scheduling a response at {} to {} (heap: {:?})",
@@ -489,7 +630,7 @@ fn start_sleep(
 /// reordering the list.
 fn recalculate_sleep(
     ticktimer: &mut XousTickTimer,
-    sleep_heap: &mut BTreeMap<TimeoutExpiry, TimerRequest>, // min-heap with Reverse
+    sleep_heap: &mut TimerWheel,
     new: Option<TimerRequest>,
 ) {
     stop_sleep(ticktimer, sleep_heap);
@@ -499,15 +640,11 @@ fn recalculate_sleep(
         #[cfg(feature = "debug-print")]
         info!("New sleep request was: {:?}", request);
 
-        // Ensure that each timeout only exists once inside the tree
         request.msec += ticktimer.elapsed_ms() as i64;
-        while sleep_heap.contains_key(&request.msec) {
-            request.msec += 1;
-        }
 
         #[cfg(feature = "debug-print")]
         info!("Modified, the request was: {:?}", request);
-        sleep_heap.insert(request.msec, request);
+        sleep_heap.insert(request);
     } else {
         #[cfg(feature = "debug-print")]
         info!("No new sleep request");
@@ -516,6 +653,107 @@ fn recalculate_sleep(
     start_sleep(ticktimer, sleep_heap);
 }
 
+/// A message sent on a client's behalf once its time comes, registered with `RegisterCall`
+struct Call {
+    /// The process that registered the call, which is the only one that may cancel it
+    pid: Option<xous::PID>,
+    sid: SidKey,
+    cid: xous::CID,
+    opcode: usize,
+    /// When the call is next due, in ms since boot
+    due: TimeoutExpiry,
+    /// Time between calls, or 0 if it's sent just once
+    period: TimeoutExpiry,
+}
+
+type SidKey = (u32, u32, u32, u32);
+
+/// The connections calls are sent on, one per server however many calls it has pending, so
+/// that registering calls doesn't use up the ticktimer's connection table.
+#[derive(Default)]
+struct CallConnections {
+    by_sid: HashMap<SidKey, (xous::CID, usize)>,
+}
+
+impl CallConnections {
+    /// The connection to `sid` for one more call, connecting if this is the first
+    fn acquire(&mut self, sid: SidKey) -> Option<xous::CID> {
+        if let Some((cid, users)) = self.by_sid.get_mut(&sid) {
+            *users += 1;
+            return Some(*cid);
+        }
+        // Never block on the server to be called: it has to exist already
+        let cid = xous::try_connect(xous::SID::from_u32(sid.0, sid.1, sid.2, sid.3)).ok()?;
+        self.by_sid.insert(sid, (cid, 1));
+        Some(cid)
+    }
+
+    /// A call on `sid` is gone. Disconnect once the last one is.
+    fn release(&mut self, sid: SidKey) {
+        let last = match self.by_sid.get_mut(&sid) {
+            Some((_, users)) => {
+                *users -= 1;
+                *users == 0
+            }
+            None => false,
+        };
+        if last {
+            let (cid, _) = self.by_sid.remove(&sid).unwrap();
+            // No call refers to the connection any more, so nothing else can be using it
+            unsafe { xous::disconnect(cid).ok() };
+        }
+    }
+}
+
+/// Send the message for a call that has come due. If the call repeats, return the request for
+/// the next time round; otherwise the call is forgotten.
+fn fire_call(
+    calls: &mut HashMap<usize, Call>,
+    connections: &mut CallConnections,
+    handle: usize,
+    now: TimeoutExpiry,
+) -> Option<TimerRequest> {
+    // The call may have been cancelled after it fired
+    let call = calls.get_mut(&handle)?;
+    let sent = xous::try_send_message(
+        call.cid,
+        xous::Message::new_scalar(
+            call.opcode,
+            handle,
+            (call.due & 0xFFFF_FFFF) as usize,
+            (call.due >> 32) as usize,
+            0,
+        ),
+    );
+    // A full queue only costs the server this one message. Any other error means
+    // the server is gone, so there's nobody left to call.
+    let keep = match sent {
+        Ok(_) | Err(xous::Error::ServerQueueFull) => call.period != 0,
+        Err(e) => {
+            info!("dropping call {} after it couldn't be sent: {:?}", handle, e);
+            false
+        }
+    };
+    if !keep {
+        let sid = call.sid;
+        calls.remove(&handle);
+        connections.release(sid);
+        return None;
+    }
+
+    // If the ticktimer fell behind, skip the calls that were missed rather than sending them all at once
+    call.due += call.period;
+    if call.due <= now {
+        call.due += ((now - call.due) / call.period + 1) * call.period;
+    }
+    Some(TimerRequest {
+        msec: call.due,
+        sender: xous::MessageSender::from_usize(0),
+        kind: RequestKind::Call,
+        data: handle,
+    })
+}
+
 #[xous::xous_main]
 fn xmain() -> ! {
     log_server::init_wait().unwrap();
@@ -555,7 +793,13 @@ fn xmain() -> ! {
     // A list of all sleep requests in the system, sorted by the time at which it
     // expires. That is, if a request comes in to sleep for 1000 ms, and the ticktimer
     // is currently at 900, the Request will be `1900`.
-    let mut sleep_heap: BTreeMap<TimeoutExpiry, TimerRequest> = BTreeMap::new();
+    let mut sleep_heap = TimerWheel::new();
+
+    // Messages that are sent on behalf of other servers, by handle. The next handle to
+    // be given out never goes back to 0, which is how a refused call is reported.
+    let mut calls: HashMap<usize, Call> = HashMap::new();
+    let mut call_connections = CallConnections::default();
+    let mut next_call_handle: usize = 1;
 
     // A list of message IDs that are waiting to receive a Notification. This queue is drained
     // by threads sending `NotifyCondition` to us, or by a condvar timing out.
@@ -587,9 +831,14 @@ fn xmain() -> ! {
     let mut mutex_hash: HashMap<Option<xous::PID>, HashMap<usize, VecDeque<xous::MessageSender>>> =
         HashMap::new();
 
+    // The process that feeds the WDT itself, once one has claimed it
+    let mut wdt_owner: Option<xous::PID> = None;
+
     loop {
         #[cfg(feature = "watchdog")]
-        ticktimer.reset_wdt();
+        if wdt_owner.is_none() {
+            ticktimer.reset_wdt();
+        }
         //#[cfg(feature = "watchdog")] // for debugging the watchdog
         //ticktimer.check_wdt();
 
@@ -633,8 +882,9 @@ fn xmain() -> ! {
                         && (request_kind == RequestKind::Timeout as usize)
                         && (sender > 0)
                     {
+                        // The entry is filed under the waiter's process, not ours
                         let entries = notify_hash
-                            .entry(msg.sender.pid())
+                            .entry(xous::MessageSender::from_usize(sender).pid())
                             .or_default()
                             .entry(condvar)
                             .or_default();
@@ -649,8 +899,20 @@ fn xmain() -> ! {
                             entries.remove(idx);
                         }
                     }
+
+                    // If a call came due, send it, and put it back on the wheel if it repeats.
+                    if sender_pid == xous::process::id()
+                        && (request_kind == RequestKind::Call as usize)
+                    {
+                        let now = ticktimer.elapsed_ms() as i64;
+                        if let Some(next) = fire_call(&mut calls, &mut call_connections, condvar, now) {
+                            stop_sleep(&mut ticktimer, &mut sleep_heap);
+                            sleep_heap.insert(next);
+                        }
+                    }
                 }
                 recalculate_sleep(&mut ticktimer, &mut sleep_heap, None);
+                ticktimer.recalculated();
             }
             Some(api::Opcode::SuspendResume) => xous::msg_scalar_unpack!(msg, token, _, _, _, {
                 ticktimer.suspend();
@@ -662,6 +924,17 @@ fn xmain() -> ! {
             Some(api::Opcode::PingWdt) => {
                 ticktimer.reset_wdt();
             }
+            Some(api::Opcode::ClaimWdt) => {
+                if !is_watchdog(msg.sender) {
+                    info!("PID {:?} tried to claim the WDT, but isn't the watchdog", msg.sender.pid());
+                } else if wdt_owner.is_none() {
+                    info!("WDT is now fed by PID {:?}", msg.sender.pid());
+                    wdt_owner = msg.sender.pid();
+                    ticktimer.reset_wdt();
+                } else {
+                    info!("PID {:?} tried to claim the WDT, but {:?} has it", msg.sender.pid(), wdt_owner);
+                }
+            }
             Some(api::Opcode::GetVersion) => {
                 let mut buf = unsafe {
                     xous_ipc::Buffer::from_memory_message_mut(
@@ -807,29 +1080,23 @@ fn xmain() -> ! {
                     stop_sleep(&mut ticktimer, &mut sleep_heap);
                     for entry in awaiting.drain(..available_count) {
                         // Remove each entry in the timeout set
-                        sleep_heap.retain(|_, v| {
-                            if v.sender == entry {
-                                false
-                            } else {
-                                true
-                            }
-                        });
+                        sleep_heap.remove(RequestKind::Timeout, entry.to_usize());
                         xous::return_scalar(entry, 0).expect("couldn't send response");
                     }
 
                     // If there are leftover requested, add them to the list of
                     // notofications that will be responded to immediately.
-                    if available_count - requested_count > 0 {
+                    if requested_count > available_count {
                         #[cfg(feature = "debug-print")]
                         log::trace!(
                             "Adding {} spare sleep requests to immediate_notifications list",
-                            available_count - requested_count
+                            requested_count - available_count
                         );
                         *immedaite_notifications
                             .entry(pid)
                             .or_default()
                             .entry(condvar)
-                            .or_default() += available_count - requested_count;
+                            .or_default() += requested_count - available_count;
                     }
 
                     // Resume sleeping, which re-enables interrupts and queues the
@@ -841,6 +1108,59 @@ fn xmain() -> ! {
                     );
                 }
             }
+            Some(api::Opcode::RegisterCall) => {
+                let mut buf = unsafe {
+                    xous_ipc::Buffer::from_memory_message_mut(
+                        msg.body.memory_message_mut().unwrap(),
+                    )
+                };
+                let mut request = buf.to_original::<api::CallRequest, _>().unwrap();
+                request.handle = 0;
+                let sid = (request.sid.0, request.sid.1, request.sid.2, request.sid.3);
+                if calls.len() >= MAX_CALLS {
+                    info!("refusing a call, there are already {} pending", calls.len());
+                } else if let Some(cid) = call_connections.acquire(sid) {
+                    let handle = next_call_handle;
+                    next_call_handle = next_call_handle.wrapping_add(1).max(1);
+                    calls.insert(
+                        handle,
+                        Call {
+                            pid: msg.sender.pid(),
+                            sid,
+                            cid,
+                            opcode: request.opcode as usize,
+                            due: request.at_ms as i64,
+                            period: request.period_ms as i64,
+                        },
+                    );
+                    stop_sleep(&mut ticktimer, &mut sleep_heap);
+                    sleep_heap.insert(TimerRequest {
+                        msec: request.at_ms as i64,
+                        sender: xous::MessageSender::from_usize(0),
+                        kind: RequestKind::Call,
+                        data: handle,
+                    });
+                    start_sleep(&mut ticktimer, &mut sleep_heap);
+                    request.handle = handle as u32;
+                }
+                buf.replace(request).unwrap();
+            }
+            Some(api::Opcode::CancelCall) => xous::msg_scalar_unpack!(msg, handle, _, _, _, {
+                if calls.get(&handle).map(|call| call.pid) == Some(msg.sender.pid()) {
+                    let call = calls.remove(&handle).unwrap();
+                    call_connections.release(call.sid);
+                    stop_sleep(&mut ticktimer, &mut sleep_heap);
+                    sleep_heap.remove(RequestKind::Call, handle);
+                    start_sleep(&mut ticktimer, &mut sleep_heap);
+                }
+            }),
+            Some(api::Opcode::AdvanceClock) => xous::msg_blocking_scalar_unpack!(msg, lower, upper, _, _, {
+                let ms = lower as u64 | ((upper as u64) << 32);
+                // the clock answers the sender itself, once it's been advanced
+                if !ticktimer.advance_clock(msg.sender, ms) {
+                    xous::return_scalar(msg.sender, 0).expect("couldn't refuse clock advance");
+                }
+            }),
             None => {
                 error!("couldn't convert opcode");
             }
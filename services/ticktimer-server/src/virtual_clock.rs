@@ -0,0 +1,139 @@
+//! The bookkeeping behind the hosted virtual clock: where it is, where an advance is taking it, and whether the
+//! advance is done. It makes no calls of its own; the ticktimer does what it says to.
+
+use crate::{TimeoutExpiry, TimerRequest};
+
+pub(crate) struct VirtualClock {
+    /// the time, in ms since boot
+    now: TimeoutExpiry,
+    /// where the clock is headed, while it's being advanced; otherwise, where it is
+    target: TimeoutExpiry,
+    /// the next request to fall due
+    pending: Option<TimerRequest>,
+    /// requests that have fired, but that the sleep heap hasn't yet been recalculated for
+    in_flight: usize,
+    /// who asked for the advance that's going on
+    advancing: Option<xous::MessageSender>,
+}
+
+impl VirtualClock {
+    pub(crate) fn new() -> Self {
+        VirtualClock { now: 0, target: 0, pending: None, in_flight: 0, advancing: None }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.now = 0;
+        self.target = 0;
+    }
+
+    pub(crate) fn now(&self) -> TimeoutExpiry {
+        self.now
+    }
+
+    /// Takes back the request waiting to fall due
+    pub(crate) fn take_pending(&mut self) -> Option<TimerRequest> {
+        self.pending.take()
+    }
+
+    /// Holds `request` until the clock reaches it. Returns it instead, to be fired, if it's already due or falls
+    /// due within the advance going on; the clock is moved up to it.
+    pub(crate) fn schedule(&mut self, request: TimerRequest) -> Option<TimerRequest> {
+        if request.msec <= self.target {
+            self.now = self.now.max(request.msec);
+            self.in_flight += 1;
+            Some(request)
+        } else {
+            self.pending = Some(request);
+            None
+        }
+    }
+
+    /// Starts moving the clock on by `ms`, for `sender`. Returns false if an advance is already going on.
+    /// Whatever's pending then has to be scheduled again, and the advance settled.
+    pub(crate) fn advance(&mut self, sender: xous::MessageSender, ms: u64) -> bool {
+        if self.advancing.is_some() {
+            return false;
+        }
+        self.target = self.now + ms as TimeoutExpiry;
+        self.advancing = Some(sender);
+        true
+    }
+
+    /// The sleep heap has been recalculated after a request fired
+    pub(crate) fn recalculated(&mut self) {
+        self.in_flight = self.in_flight.saturating_sub(1);
+    }
+
+    /// Finishes the advance if nothing more is due before its end, returning who to answer
+    pub(crate) fn settle(&mut self) -> Option<xous::MessageSender> {
+        if self.in_flight > 0 {
+            return None;
+        }
+        let sender = self.advancing.take()?;
+        self.now = self.target;
+        Some(sender)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RequestKind;
+
+    fn sleep(sender: usize, msec: TimeoutExpiry) -> TimerRequest {
+        TimerRequest {
+            msec,
+            sender: xous::MessageSender::from_usize(sender),
+            kind: RequestKind::Sleep,
+            data: 0,
+        }
+    }
+    /// An advance as the ticktimer does it: what's pending is scheduled again. Returns what fired.
+    fn advance(clock: &mut VirtualClock, ms: u64) -> Option<TimeoutExpiry> {
+        assert!(clock.advance(xous::MessageSender::from_usize(99), ms));
+        clock.take_pending().and_then(|next| clock.schedule(next)).map(|fired| fired.msec)
+    }
+
+    #[test]
+    fn stands_still_until_advanced() {
+        let mut clock = VirtualClock::new();
+        assert!(clock.schedule(sleep(1, 150)).is_none());
+        assert_eq!(clock.now(), 0);
+        assert_eq!(clock.settle(), None);
+        assert_eq!(advance(&mut clock, 100), None);
+        assert_eq!(clock.settle(), Some(xous::MessageSender::from_usize(99)));
+        assert_eq!(clock.now(), 100);
+        // the sleep is still waiting
+        assert_eq!(clock.take_pending().map(|request| request.msec), Some(150));
+    }
+
+    #[test]
+    fn fires_what_falls_due_on_the_way() {
+        let mut clock = VirtualClock::new();
+        assert!(clock.schedule(sleep(1, 150)).is_none());
+        assert_eq!(advance(&mut clock, 200), Some(150));
+        // fired at the moment it was due, and the advance waits on the sleep heap
+        assert_eq!(clock.now(), 150);
+        assert_eq!(clock.settle(), None);
+        assert!(!clock.advance(xous::MessageSender::from_usize(2), 10));
+        // a sleep asked for in time is woken within the same advance
+        assert_eq!(clock.schedule(sleep(1, 180)).map(|request| request.msec), Some(180));
+        clock.recalculated();
+        assert_eq!(clock.settle(), None);
+        assert!(clock.schedule(sleep(1, 250)).is_none());
+        clock.recalculated();
+        assert_eq!(clock.settle(), Some(xous::MessageSender::from_usize(99)));
+        assert_eq!(clock.now(), 200);
+        assert_eq!(advance(&mut clock, 50), Some(250));
+    }
+
+    #[test]
+    fn reset_goes_back_to_boot() {
+        let mut clock = VirtualClock::new();
+        advance(&mut clock, 1000);
+        clock.settle();
+        clock.reset();
+        assert_eq!(clock.now(), 0);
+        assert!(clock.schedule(sleep(1, 10)).is_none());
+    }
+}
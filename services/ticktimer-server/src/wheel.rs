@@ -0,0 +1,263 @@
+//! A hierarchical timer wheel holding every pending timer request.
+//!
+//! The wheel has `LEVELS` levels of 64 slots. A slot on level 0 is one millisecond wide, and each level up a
+//! slot is 64 times wider than on the level below. A request goes on the lowest level where its expiry and the
+//! wheel's current time agree on every bit above that level, so level 0 holds what's due within the next 64 ms,
+//! level 1 what's due within about four seconds, and so on up. As time moves past the start of a slot, the slot
+//! is emptied into the levels below it.
+//!
+//! Adding a request, removing one, and finding the one that expires next all take a bounded amount of work,
+//! however many requests are pending.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::{RequestKind, TimeoutExpiry, TimerRequest};
+
+const SLOT_BITS: u32 = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+const LEVELS: usize = 7;
+
+/// Furthest into the future a request can be placed, a bit over two years. Requests that expire later than
+/// this are kept on the top level until they come into range.
+const MAX_DELAY_MS: u64 = 1 << 36;
+
+#[derive(Clone, Copy)]
+enum Place {
+    Due,
+    Slot(usize, usize),
+}
+
+struct Level {
+    /// Bit `n` is set when slot `n` holds anything
+    occupied: u64,
+    slots: Vec<Vec<TimerRequest>>,
+}
+
+pub struct TimerWheel {
+    /// The time the wheel was last moved up to, in ms since boot
+    now: u64,
+    levels: Vec<Level>,
+    /// Requests whose time had already come when they were put on the wheel, oldest first
+    due: VecDeque<TimerRequest>,
+    /// Where each pending request is, by kind and sender (or handle, for a call)
+    index: HashMap<(RequestKind, usize), Place>,
+}
+
+fn key(request: &TimerRequest) -> (RequestKind, usize) {
+    match request.kind {
+        RequestKind::Call => (RequestKind::Call, request.data),
+        kind => (kind, request.sender.to_usize()),
+    }
+}
+
+impl TimerWheel {
+    pub fn new() -> TimerWheel {
+        TimerWheel {
+            now: 0,
+            levels: (0..LEVELS)
+                .map(|_| Level {
+                    occupied: 0,
+                    slots: (0..SLOTS).map(|_| Vec::new()).collect(),
+                })
+                .collect(),
+            due: VecDeque::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    /// Adds a request, which expires at `request.msec` ms since boot
+    pub fn insert(&mut self, request: TimerRequest) {
+        let key = key(&request);
+        let expiry = request.msec.max(0) as u64;
+        if expiry <= self.now {
+            self.due.push_back(request);
+            self.index.insert(key, Place::Due);
+            return;
+        }
+        let at = expiry.min(self.now + MAX_DELAY_MS);
+        let masked = (at ^ self.now) | (SLOTS as u64 - 1);
+        let level = ((63 - masked.leading_zeros()) / SLOT_BITS) as usize;
+        let slot = (at >> (level as u32 * SLOT_BITS)) as usize & (SLOTS - 1);
+        self.levels[level].slots[slot].push(request);
+        self.levels[level].occupied |= 1 << slot;
+        self.index.insert(key, Place::Slot(level, slot));
+    }
+
+    /// Takes out the request of the given kind from `id`, which is the sender for a sleep or a timeout and
+    /// the handle for a call. Returns `None` if there's no such request pending.
+    pub fn remove(&mut self, kind: RequestKind, id: usize) -> Option<TimerRequest> {
+        let matches = move |request: &TimerRequest| key(request) == (kind, id);
+        match self.index.remove(&(kind, id))? {
+            Place::Due => {
+                let position = self.due.iter().position(matches)?;
+                self.due.remove(position)
+            }
+            Place::Slot(level, slot) => {
+                let requests = &mut self.levels[level].slots[slot];
+                let position = requests.iter().position(matches)?;
+                let request = requests.swap_remove(position);
+                if requests.is_empty() {
+                    self.levels[level].occupied &= !(1 << slot);
+                }
+                Some(request)
+            }
+        }
+    }
+
+    /// Moves the wheel's time forward to `now`, emptying every slot that it passes into the levels below
+    pub fn advance(&mut self, now: TimeoutExpiry) {
+        let now = now.max(0) as u64;
+        while let Some((level, slot)) = self.first_slot() {
+            let start = self.slot_start(level, slot);
+            if start > now {
+                break;
+            }
+            self.now = start;
+            self.levels[level].occupied &= !(1 << slot);
+            for request in core::mem::take(&mut self.levels[level].slots[slot]) {
+                self.insert(request);
+            }
+        }
+        self.now = self.now.max(now);
+    }
+
+    /// Takes out the request that expires soonest, or the oldest of those whose time has already come
+    pub fn pop_next(&mut self) -> Option<TimerRequest> {
+        let request = match self.due.pop_front() {
+            Some(request) => request,
+            None => {
+                let (level, slot) = self.first_slot()?;
+                let requests = &mut self.levels[level].slots[slot];
+                // Everything in a level 0 slot expires together; a wider slot has to be looked through
+                let (soonest, _) = requests.iter().enumerate().min_by_key(|(_, r)| r.msec)?;
+                let request = requests.swap_remove(soonest);
+                if requests.is_empty() {
+                    self.levels[level].occupied &= !(1 << slot);
+                }
+                request
+            }
+        };
+        self.index.remove(&key(&request));
+        Some(request)
+    }
+
+    /// The first occupied slot on the lowest occupied level, which holds the soonest requests. No slot behind
+    /// the wheel's time on its level is ever occupied, so there's nothing to wrap around.
+    fn first_slot(&self) -> Option<(usize, usize)> {
+        for (level, l) in self.levels.iter().enumerate() {
+            let here = (self.now >> (level as u32 * SLOT_BITS)) as usize & (SLOTS - 1);
+            let ahead = l.occupied >> here;
+            if ahead != 0 {
+                return Some((level, here + ahead.trailing_zeros() as usize));
+            }
+        }
+        None
+    }
+
+    /// The time at which a slot begins, given the wheel's current time
+    fn slot_start(&self, level: usize, slot: usize) -> u64 {
+        let width = level as u32 * SLOT_BITS;
+        let span = width + SLOT_BITS;
+        ((self.now >> span) << span) | ((slot as u64) << width)
+    }
+}
+
+impl core::fmt::Debug for TimerWheel {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "TimerWheel {{ now: {}, pending: {}, due: {} }}",
+            self.now,
+            self.index.len(),
+            self.due.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sleep(sender: usize, msec: TimeoutExpiry) -> TimerRequest {
+        TimerRequest {
+            msec,
+            sender: xous::MessageSender::from_usize(sender),
+            kind: RequestKind::Sleep,
+            data: 0,
+        }
+    }
+
+    fn drain(wheel: &mut TimerWheel) -> Vec<TimeoutExpiry> {
+        core::iter::from_fn(|| wheel.pop_next())
+            .map(|request| request.msec)
+            .collect()
+    }
+
+    #[test]
+    fn pops_in_expiry_order_across_levels() {
+        let mut wheel = TimerWheel::new();
+        let times = [5_000_000, 3, 70, 64, 1, 4_100, 300_000, 63, 65];
+        for (sender, &msec) in times.iter().enumerate() {
+            wheel.insert(sleep(sender + 1, msec));
+        }
+        let mut sorted = times.to_vec();
+        sorted.sort();
+        assert_eq!(drain(&mut wheel), sorted);
+        assert!(wheel.pop_next().is_none());
+    }
+
+    #[test]
+    fn advancing_cascades_slots_down() {
+        let mut wheel = TimerWheel::new();
+        wheel.insert(sleep(1, 10_000));
+        wheel.insert(sleep(2, 10_050));
+        wheel.insert(sleep(3, 200));
+        wheel.advance(9_990);
+        // Everything that was passed over is due straight away
+        assert_eq!(wheel.pop_next().map(|r| r.msec), Some(200));
+        assert_eq!(drain(&mut wheel), vec![10_000, 10_050]);
+    }
+
+    #[test]
+    fn past_requests_are_due_oldest_first() {
+        let mut wheel = TimerWheel::new();
+        wheel.advance(1_000);
+        wheel.insert(sleep(1, 900));
+        wheel.insert(sleep(2, -5));
+        wheel.insert(sleep(3, 1_001));
+        let senders: Vec<usize> = core::iter::from_fn(|| wheel.pop_next())
+            .map(|r| r.sender.to_usize())
+            .collect();
+        assert_eq!(senders, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn remove_finds_requests_by_kind_and_id() {
+        let mut wheel = TimerWheel::new();
+        wheel.insert(sleep(7, 100));
+        wheel.insert(TimerRequest {
+            msec: 100,
+            sender: xous::MessageSender::from_usize(0),
+            kind: RequestKind::Call,
+            data: 7,
+        });
+        wheel.insert(sleep(8, 5_000));
+        assert!(wheel.remove(RequestKind::Timeout, 7).is_none());
+        assert_eq!(wheel.remove(RequestKind::Call, 7).map(|r| r.data), Some(7));
+        assert!(wheel.remove(RequestKind::Call, 7).is_none());
+        assert_eq!(
+            wheel.remove(RequestKind::Sleep, 8).map(|r| r.msec),
+            Some(5_000)
+        );
+        assert_eq!(drain(&mut wheel), vec![100]);
+    }
+
+    #[test]
+    fn far_future_requests_stay_on_the_wheel() {
+        let mut wheel = TimerWheel::new();
+        let far = (MAX_DELAY_MS * 3) as TimeoutExpiry;
+        wheel.insert(sleep(1, far));
+        wheel.insert(sleep(2, 10));
+        assert_eq!(drain(&mut wheel), vec![10, far]);
+    }
+}
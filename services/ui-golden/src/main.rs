@@ -0,0 +1,213 @@
+#![cfg_attr(target_os = "none", no_std)]
+#![cfg_attr(target_os = "none", no_main)]
+
+mod scenarios;
+use scenarios::*;
+
+use gam::{GlyphStyle, SCREENSHOT_ROW_BYTES};
+use std::fs;
+
+/// where the reference images are kept, wherever hosted mode is run from
+const GOLDEN_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../tools/golden-images");
+/// if this is set, the reference images are replaced with what's on the screen instead of being checked
+const UPDATE_VAR: &str = "XOUS_GOLDEN_UPDATE";
+/// how long a change to the UI is given to start drawing, before waiting for the screen to stop changing
+const SETTLE_MS: usize = 500;
+/// how often the screen is captured while waiting for it to stop changing
+const POLL_MS: usize = 100;
+/// how long the screen can keep changing before it's captured anyway
+const SETTLE_TIMEOUT_MS: u64 = 5000;
+
+/// What the scenarios drive the UI with
+pub(crate) struct Harness {
+    pub gam: gam::Gam,
+    /// the screen is read straight from the headless graphics server, which takes one connection more for
+    /// this; the GAM only captures it for the app with focus, once the user has said so
+    gfx: graphics_server::Gfx,
+    pub modals: modals::Modals,
+    pub tt: ticktimer_server::Ticktimer,
+    kbd: keyboard::Keyboard,
+}
+impl Harness {
+    /// Lets `ms` go by. On the virtual clock nothing else moves time on, so the harness does.
+    pub fn wait(&self, ms: usize) {
+        #[cfg(feature = "virtual-clock")]
+        self.tt.advance_clock(ms as u64).expect("couldn't advance the virtual clock");
+        #[cfg(not(feature = "virtual-clock"))]
+        self.tt.sleep_ms(ms).unwrap();
+    }
+    /// Types `keys`, as though from the keyboard
+    pub fn keys(&self, keys: &str) {
+        for k in keys.chars() {
+            self.kbd.hostmode_inject_key(k);
+        }
+    }
+    fn capture(&self) -> Vec<u8> {
+        let mut raster = vec![0u8; graphics_server::SCREENSHOT_LEN];
+        self.gfx.screenshot(&mut raster).expect("couldn't capture the screen");
+        raster
+    }
+    /// Waits for the screen to stop changing, and returns what's on it
+    pub fn settle(&self) -> Vec<u8> {
+        self.wait(SETTLE_MS);
+        let start = self.tt.elapsed_ms();
+        // the scenarios all run with the screen upright, so every capture has the reference images' layout
+        let mut last = self.capture();
+        loop {
+            self.wait(POLL_MS);
+            let screen = self.capture();
+            if screen == last {
+                return screen;
+            }
+            if self.tt.elapsed_ms() - start > SETTLE_TIMEOUT_MS {
+                log::warn!("the screen was still changing after {}ms", SETTLE_TIMEOUT_MS);
+                return screen;
+            }
+            last = screen;
+        }
+    }
+}
+
+/// Where two screens differ: how many pixels, and the box around them, as (left, top, right, bottom)
+struct Difference {
+    pixels: usize,
+    bounds: (usize, usize, usize, usize),
+}
+
+/// Compares two rasters, leaving out the first `skip_lines` lines. Returns the XOR of the two, with the lines
+/// left out blank, along with where they differ; or None if they're the same.
+fn compare(expected: &[u8], actual: &[u8], skip_lines: usize) -> Option<(Vec<u8>, Difference)> {
+    let mut xor = vec![0u8; actual.len()];
+    let mut diff = Difference { pixels: 0, bounds: (usize::MAX, usize::MAX, 0, 0) };
+    for (y, ((e, a), x)) in expected.chunks_exact(SCREENSHOT_ROW_BYTES)
+        .zip(actual.chunks_exact(SCREENSHOT_ROW_BYTES))
+        .zip(xor.chunks_exact_mut(SCREENSHOT_ROW_BYTES))
+        .enumerate()
+        .skip(skip_lines)
+    {
+        for (col, ((&e, &a), x)) in e.iter().zip(a.iter()).zip(x.iter_mut()).enumerate() {
+            *x = e ^ a;
+            if *x == 0 {
+                continue;
+            }
+            diff.pixels += x.count_ones() as usize;
+            // most significant bit first
+            let left = col * 8 + x.leading_zeros() as usize;
+            let right = col * 8 + 7 - x.trailing_zeros() as usize;
+            diff.bounds.0 = diff.bounds.0.min(left);
+            diff.bounds.1 = diff.bounds.1.min(y);
+            diff.bounds.2 = diff.bounds.2.max(right);
+            diff.bounds.3 = y;
+        }
+    }
+    if diff.pixels > 0 {
+        Some((xor, diff))
+    } else {
+        None
+    }
+}
+
+/// The header of a binary PBM, which is what the images are kept as so they can be looked at with anything
+fn pbm_header(raster: &[u8]) -> String {
+    format!("P4\n{} {}\n", SCREENSHOT_ROW_BYTES * 8, raster.len() / SCREENSHOT_ROW_BYTES)
+}
+fn write_pbm(path: &str, raster: &[u8]) {
+    let mut file = pbm_header(raster).into_bytes();
+    file.extend_from_slice(raster);
+    if let Err(e) = fs::write(path, &file) {
+        log::error!("couldn't write {}: {:?}", path, e);
+    }
+}
+/// Reads back an image written by `write_pbm()`, returning its raster if it's the size of `like`
+fn read_pbm(path: &str, like: &[u8]) -> Option<Vec<u8>> {
+    let file = fs::read(path).ok()?;
+    let header = pbm_header(like);
+    if file.len() == header.len() + like.len() && file.starts_with(header.as_bytes()) {
+        Some(file[header.len()..].to_vec())
+    } else {
+        None
+    }
+}
+
+/// Checks `screen` against the reference image for `name`, or replaces the reference if `update` is set.
+/// Returns false if it didn't match.
+fn check(name: &str, screen: &[u8], skip_lines: usize, update: bool) -> bool {
+    let reference = format!("{}/{}.pbm", GOLDEN_DIR, name);
+    let actual = format!("{}/{}.actual.pbm", GOLDEN_DIR, name);
+    let diff = format!("{}/{}.diff.pbm", GOLDEN_DIR, name);
+    // what's left from the last run would be mistaken for a failure of this one
+    fs::remove_file(&actual).ok();
+    fs::remove_file(&diff).ok();
+    if update {
+        write_pbm(&reference, screen);
+        log::info!("{}: reference updated", name);
+        return true;
+    }
+    let expected = match read_pbm(&reference, screen) {
+        Some(expected) => expected,
+        None => {
+            log::error!("{}: FAIL, there's no usable reference image at {} (run with {} set to make one)",
+                name, reference, UPDATE_VAR);
+            write_pbm(&actual, screen);
+            return false;
+        }
+    };
+    match compare(&expected, screen, skip_lines) {
+        None => {
+            log::info!("{}: pass", name);
+            true
+        }
+        Some((xor, difference)) => {
+            let (left, top, right, bottom) = difference.bounds;
+            log::error!("{}: FAIL, {} pixels differ, between ({}, {}) and ({}, {}); see {} and {}",
+                name, difference.pixels, left, top, right, bottom, actual, diff);
+            write_pbm(&actual, screen);
+            write_pbm(&diff, &xor);
+            false
+        }
+    }
+}
+
+#[xous::xous_main]
+fn xmain() -> ! {
+    log_server::init_wait().unwrap();
+    log::set_max_level(log::LevelFilter::Info);
+    log::info!("my PID is {}", xous::process::id());
+
+    let xns = xous_names::XousNames::new().unwrap();
+    let harness = Harness {
+        gam: gam::Gam::new(&xns).expect("can't connect to GAM"),
+        gfx: graphics_server::Gfx::new(&xns).expect("can't connect to GFX"),
+        modals: modals::Modals::new(&xns).expect("can't connect to Modals"),
+        tt: ticktimer_server::Ticktimer::new().expect("can't connect to ticktimer"),
+        kbd: keyboard::Keyboard::new(&xns).expect("can't connect to KBD"),
+    };
+    while !harness.gam.trusted_init_done().unwrap_or(false) {
+        harness.wait(POLL_MS);
+    }
+    // the status bar shows the time and the battery, which differ from run to run, so it's left out. It's
+    // sized at boot, so its height has to be found before any scenario turns on large text.
+    let status_lines = harness.gam.glyph_height_hint(GlyphStyle::Cjk).expect("couldn't get glyph height") * 2;
+    let update = std::env::var_os(UPDATE_VAR).is_some();
+    fs::create_dir_all(GOLDEN_DIR).expect("couldn't create the golden image directory");
+
+    let mut failures = 0;
+    for scenario in SCENARIOS.iter() {
+        (scenario.setup)(&harness);
+        let screen = harness.settle();
+        if !check(scenario.name, &screen, status_lines, update) {
+            failures += 1;
+        }
+        (scenario.teardown)(&harness);
+        harness.settle();
+    }
+    if failures == 0 {
+        log::info!("golden images: all {} scenarios passed", SCENARIOS.len());
+    } else {
+        log::error!("golden images: {} of {} scenarios FAILED", failures, SCENARIOS.len());
+    }
+
+    // take the whole hosted system down, so the run can be scripted
+    xous::rsyscall(xous::SysCall::Shutdown).expect("couldn't shut down");
+    xous::terminate_process(0)
+}
@@ -0,0 +1,130 @@
+use crate::Harness;
+
+/// One screen to check. Each scenario starts from the home screen, and must leave the UI there when it's done.
+pub(crate) struct Scenario {
+    /// names the reference image, `<name>.pbm`
+    pub name: &'static str,
+    /// puts the UI into the state to be captured
+    pub setup: fn(&Harness),
+    /// takes the UI back to the home screen
+    pub teardown: fn(&Harness),
+}
+
+/// Run in this order. Add a scenario here for any new widget, modal or app screen that should keep its layout.
+pub(crate) const SCENARIOS: [Scenario; 9] = [
+    Scenario { name: "home", setup: nothing, teardown: nothing },
+    Scenario { name: "main-menu", setup: main_menu, teardown: close_menu },
+    Scenario { name: "notification", setup: notification, teardown: dismiss },
+    Scenario { name: "notification-large-text", setup: notification_large_text, teardown: dismiss_large_text },
+    Scenario { name: "radiobuttons", setup: radiobuttons, teardown: confirm_radiobuttons },
+    Scenario { name: "checkboxes", setup: checkboxes, teardown: confirm_checkboxes },
+    Scenario { name: "text-entry", setup: text_entry, teardown: dismiss },
+    Scenario { name: "progress", setup: progress, teardown: finish_progress },
+    Scenario { name: "xtotp-code", setup: totp_code, teardown: dismiss },
+];
+
+const ITEMS: [&'static str; 4] = ["zebra", "cow", "horse", "cat"];
+const SELECT: &str = "∴";
+const DOWN: &str = "↓";
+
+/// Puts up a modal from a thread of its own, as modals don't return until they're dismissed
+fn in_modal(f: fn(&modals::Modals)) {
+    std::thread::spawn(move || {
+        let xns = xous_names::XousNames::new().unwrap();
+        let modals = modals::Modals::new(&xns).expect("can't connect to Modals");
+        f(&modals);
+    });
+}
+
+fn nothing(_h: &Harness) {}
+
+fn main_menu(h: &Harness) {
+    h.gam.raise_menu(gam::MAIN_MENU_NAME).expect("couldn't raise the main menu");
+}
+fn close_menu(h: &Harness) {
+    h.gam.relinquish_focus().expect("couldn't close the main menu");
+}
+
+fn notification(_h: &Harness) {
+    in_modal(|modals| modals.show_notification("This is a golden-image test of the notification modal.").unwrap());
+}
+fn dismiss(h: &Harness) {
+    h.keys(SELECT);
+}
+/// Large text is the status bar's to set, so it's toggled the way the user would, from the main menu
+fn toggle_large_text(h: &Harness) {
+    // after the two backlight items, the inversion and the rotation
+    main_menu(h);
+    h.settle();
+    h.keys(&DOWN.repeat(4));
+    h.keys(SELECT);
+    h.settle();
+}
+fn notification_large_text(h: &Harness) {
+    toggle_large_text(h);
+    notification(h);
+}
+fn dismiss_large_text(h: &Harness) {
+    dismiss(h);
+    toggle_large_text(h);
+}
+
+fn radiobuttons(h: &Harness) {
+    in_modal(|modals| {
+        for item in ITEMS {
+            modals.add_list_item(item).unwrap();
+        }
+        modals.get_radiobutton("Pick an animal:").unwrap();
+    });
+    // move the selection off the first item, so the cursor is seen to follow it
+    h.settle();
+    h.keys(DOWN);
+}
+fn confirm_radiobuttons(h: &Harness) {
+    // the OK button comes after the items
+    h.keys(&DOWN.repeat(ITEMS.len()));
+    h.keys(SELECT);
+}
+
+fn checkboxes(h: &Harness) {
+    in_modal(|modals| {
+        for item in ITEMS {
+            modals.add_list_item(item).unwrap();
+        }
+        modals.get_checkbox("Pick some animals:").unwrap();
+    });
+    // check the first and third items
+    h.settle();
+    h.keys(SELECT);
+    h.keys(&DOWN.repeat(2));
+    h.keys(SELECT);
+}
+fn confirm_checkboxes(h: &Harness) {
+    h.keys(&DOWN.repeat(ITEMS.len() - 2));
+    h.keys(SELECT);
+}
+
+fn text_entry(h: &Harness) {
+    in_modal(|modals| {
+        modals.get_text("Name your pet:", None, None).unwrap();
+    });
+    h.settle();
+    h.keys("Golden");
+}
+
+/// The code entry xtotp puts up: a fixed six digits, the whole code typed so it can be entered
+fn totp_code(h: &Harness) {
+    in_modal(|modals| {
+        modals.get_number("Enter the 6-digit code:", gam::modal::NumericLimits::fixed_len(6), false).unwrap();
+    });
+    h.settle();
+    h.keys("482913");
+}
+
+fn progress(h: &Harness) {
+    h.modals.start_progress("Golden images", 0, 100, 0).unwrap();
+    h.modals.update_progress(40).unwrap();
+}
+fn finish_progress(h: &Harness) {
+    h.modals.finish_progress().unwrap();
+}
@@ -0,0 +1,40 @@
+pub(crate) const SERVER_NAME_WATCHDOG: &str = "_Watchdog_";
+
+/// Longest name a service can register under; it's what the log shows when that service goes quiet
+pub const WATCHDOG_NAME_LEN: usize = 64;
+/// Shortest heartbeat interval the watchdog keeps to; shorter ones are rounded up to it, as anything faster
+/// would be lost in the jitter of the ticktimer and the scheduler.
+pub const MIN_INTERVAL_MS: u32 = 100;
+/// Most services that can be watched at once
+pub const MAX_WATCHED: usize = 32;
+/// How many intervals in a row a service can miss before the device is rebooted
+pub const MISSED_BEATS_TO_REBOOT: u64 = 3;
+
+#[derive(num_derive::FromPrimitive, num_derive::ToPrimitive, Debug)]
+pub(crate) enum Opcode {
+    /// Starts watching the caller, which has to be one of the boot processes the watchdog knows about. Takes a
+    /// `Registration`, and fills in its handle.
+    Register,
+    /// The service identified by the handle in arg1 is still alive
+    Heartbeat,
+    /// Stops watching the service identified by the handle in arg1
+    Unregister,
+    /// Sent by the server to itself to look for services that have gone quiet
+    Check,
+    /// Suspend/resume callback
+    SuspendResume,
+    /// Exits the server. Only taken from the watchdog's own threads.
+    Quit,
+}
+
+#[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub(crate) struct Registration {
+    pub name: xous_ipc::String<WATCHDOG_NAME_LEN>,
+    pub interval_ms: u32,
+    /// server the watchdog sends `snapshot_id` to before it reboots because of this service, so a thread that
+    /// is still running there can log what it knows
+    pub snapshot_sid: Option<(u32, u32, u32, u32)>,
+    pub snapshot_id: u32,
+    /// filled in by the watchdog; `None` if the registration was refused
+    pub handle: Option<u32>,
+}
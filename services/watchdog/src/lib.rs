@@ -0,0 +1,108 @@
+#![cfg_attr(target_os = "none", no_std)]
+
+pub mod api;
+use api::*;
+use num_traits::ToPrimitive;
+use xous::{send_message, Message, CID, SID};
+use xous_ipc::Buffer;
+
+/// A service's registration with the watchdog. Once it's made, `heartbeat()` has to be called at least once
+/// every `interval_ms`; a service that misses `MISSED_BEATS_TO_REBOOT` intervals in a row is taken to be
+/// hung, and the device is rebooted. Dropping the `Watchdog` stops the watching.
+pub struct Watchdog {
+    conn: CID,
+    handle: u32,
+}
+impl Watchdog {
+    pub fn new(xns: &xous_names::XousNames, name: &str, interval_ms: u32) -> Result<Self, xous::Error> {
+        Self::register(xns, name, interval_ms, None)
+    }
+    /// Like `new()`, but before rebooting because of this service the watchdog sends a scalar message with
+    /// `snapshot_id` to `snapshot_sid`, and leaves a moment for any thread of the service that is still
+    /// running to log its state. arg1 of the message is the handle of the registration.
+    pub fn new_with_snapshot(
+        xns: &xous_names::XousNames,
+        name: &str,
+        interval_ms: u32,
+        snapshot_sid: SID,
+        snapshot_id: u32,
+    ) -> Result<Self, xous::Error> {
+        Self::register(xns, name, interval_ms, Some((snapshot_sid, snapshot_id)))
+    }
+    fn register(
+        xns: &xous_names::XousNames,
+        name: &str,
+        interval_ms: u32,
+        snapshot: Option<(SID, u32)>,
+    ) -> Result<Self, xous::Error> {
+        REFCOUNT.fetch_add(1, Ordering::Relaxed);
+        let conn = xns.request_connection_blocking(api::SERVER_NAME_WATCHDOG).expect("Can't connect to Watchdog server");
+        let mut registration = Registration {
+            name: xous_ipc::String::<WATCHDOG_NAME_LEN>::from_str(name),
+            interval_ms,
+            snapshot_sid: snapshot.map(|(sid, _)| sid.to_u32()),
+            snapshot_id: snapshot.map(|(_, id)| id).unwrap_or(0),
+            handle: None,
+        };
+        let mut buf = Buffer::into_buf(registration).or(Err(xous::Error::InternalError))?;
+        buf.lend_mut(conn, Opcode::Register.to_u32().unwrap())?;
+        registration = buf.to_original::<Registration, _>().or(Err(xous::Error::InternalError))?;
+        match registration.handle {
+            Some(handle) => Ok(Watchdog { conn, handle }),
+            None => {
+                Self::release(conn);
+                Err(xous::Error::OutOfMemory)
+            }
+        }
+    }
+    /// Tells the watchdog the service is still alive. Cheap enough to call from the top of a server's loop.
+    pub fn heartbeat(&self) -> Result<(), xous::Error> {
+        send_message(self.conn,
+            Message::new_scalar(Opcode::Heartbeat.to_usize().unwrap(), self.handle as usize, 0, 0, 0)
+        ).map(|_| ())
+    }
+    pub fn handle(&self) -> u32 { self.handle }
+    fn release(conn: CID) {
+        if REFCOUNT.fetch_sub(1, Ordering::Relaxed) == 1 {
+            unsafe{xous::disconnect(conn).unwrap();}
+        }
+    }
+}
+
+/// Watches a server that spends its time blocked waiting for messages, which can't send heartbeats of its
+/// own. A thread started here sends the server a blocking scalar with `ping_id` every `interval_ms`, and sends
+/// a heartbeat each time the server answers it, so all the server's loop has to do is answer `ping_id`
+/// straight away. The registration is made from that thread as well, so a server in an image without a
+/// watchdog is simply never watched.
+pub fn watch_server(name: &'static str, interval_ms: u32, sid: SID, ping_id: usize) {
+    let conn = xous::connect(sid).expect("couldn't connect to the watched server");
+    std::thread::spawn(move || {
+        let xns = xous_names::XousNames::new().unwrap();
+        let tt = ticktimer_server::Ticktimer::new().unwrap();
+        let watchdog = match Watchdog::new(&xns, name, interval_ms) {
+            Ok(watchdog) => watchdog,
+            Err(e) => {
+                log::error!("couldn't register {} with the watchdog: {:?}", name, e);
+                return;
+            }
+        };
+        loop {
+            // a hung server never answers, and the watchdog sees the heartbeats stop
+            if send_message(conn, Message::new_blocking_scalar(ping_id, 0, 0, 0, 0)).is_ok() {
+                watchdog.heartbeat().ok();
+            }
+            tt.sleep_ms(interval_ms as usize).unwrap();
+        }
+    });
+}
+
+use core::sync::atomic::{AtomicU32, Ordering};
+static REFCOUNT: AtomicU32 = AtomicU32::new(0);
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        send_message(self.conn,
+            Message::new_scalar(Opcode::Unregister.to_usize().unwrap(), self.handle as usize, 0, 0, 0)
+        ).ok();
+        Self::release(self.conn);
+    }
+}
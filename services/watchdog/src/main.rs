@@ -0,0 +1,220 @@
+#![cfg_attr(target_os = "none", no_std)]
+#![cfg_attr(target_os = "none", no_main)]
+
+mod api;
+use api::*;
+
+use num_traits::{FromPrimitive, ToPrimitive};
+use std::collections::HashMap;
+use std::thread;
+use xous_ipc::Buffer;
+
+use log::info;
+
+/// How often the server looks for services that have gone quiet
+const CHECK_INTERVAL_MS: usize = MIN_INTERVAL_MS as usize;
+/// How long a service's snapshot hook gets to log its state before the reboot
+const SNAPSHOT_GRACE_MS: usize = 1000;
+/// Processes that may ask to be watched, by the name they were built with. Anything else could get the device
+/// rebooted just by registering and going quiet.
+#[cfg(any(target_os = "none", target_os = "xous"))]
+const WATCHABLE: [&str; 3] = ["gam", "graphics-server", "net"];
+
+/// Whether `sender` is one of the `WATCHABLE` processes
+fn may_register(sender: xous::MessageSender) -> bool {
+    #[cfg(any(target_os = "none", target_os = "xous"))]
+    {
+        let mut name = [0u8; 64];
+        match sender.pid().map(|pid| xous::process_name(pid, &mut name)) {
+            Some(Ok(len)) if len > 0 && len < name.len() => core::str::from_utf8(&name[..len])
+                .map(|n| WATCHABLE.contains(&n))
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+    #[cfg(not(any(target_os = "none", target_os = "xous")))]
+    {
+        // processes don't have names in hosted mode
+        let _ = sender;
+        true
+    }
+}
+
+struct Watched {
+    name: String,
+    pid: Option<xous::PID>,
+    interval_ms: u64,
+    last_beat: u64,
+    snapshot: Option<(xous::SID, u32)>,
+}
+impl Watched {
+    fn is_hung(&self, now: u64) -> bool {
+        now.saturating_sub(self.last_beat) > self.interval_ms * MISSED_BEATS_TO_REBOOT
+    }
+}
+
+/// Logs what is known about a service that stopped sending heartbeats, gives its snapshot hook a chance to
+/// run, and reboots. In hosted mode there is nothing to reboot, so the server just carries on.
+fn bite(handle: u32, offender: &Watched, watched: &HashMap<u32, Watched>, now: u64,
+    tt: &ticktimer_server::Ticktimer, susres: &susres::Susres) {
+    log::error!("{} (PID {:?}) missed {} heartbeats; its last one was {} ms ago, it promised one every {} ms",
+        offender.name, offender.pid.map(|p| p.get()), MISSED_BEATS_TO_REBOOT,
+        now.saturating_sub(offender.last_beat), offender.interval_ms);
+    if let Some(pid) = offender.pid {
+        match xous::cpu_time(Some(pid)) {
+            Ok(cycles) => log::error!("{} has run for {} cycles in all", offender.name, cycles),
+            Err(xous::Error::ProcessNotFound) => log::error!("{} has exited", offender.name),
+            Err(e) => log::error!("couldn't get the CPU time of {}: {:?}", offender.name, e),
+        }
+    }
+    for (h, w) in watched.iter() {
+        if *h != handle {
+            log::error!("  {} last checked in {} ms ago", w.name, now.saturating_sub(w.last_beat));
+        }
+    }
+    if let Some((sid, id)) = offender.snapshot {
+        match xous::connect(sid) {
+            Ok(cid) => {
+                if xous::send_message(cid, xous::Message::new_scalar(id as usize, handle as usize, 0, 0, 0)).is_ok() {
+                    tt.sleep_ms(SNAPSHOT_GRACE_MS).unwrap();
+                }
+                unsafe{xous::disconnect(cid).ok();}
+            }
+            Err(e) => log::error!("couldn't reach the snapshot hook of {}: {:?}", offender.name, e),
+        }
+    }
+    #[cfg(any(target_os = "none", target_os = "xous"))]
+    {
+        log::error!("rebooting");
+        susres.reboot(true).expect("couldn't request a reboot");
+    }
+    #[cfg(not(any(target_os = "none", target_os = "xous")))]
+    {
+        let _ = susres;
+        log::warn!("no reboot in hosted mode; carrying on without watching {}", offender.name);
+    }
+}
+
+#[xous::xous_main]
+fn xmain() -> ! {
+    log_server::init_wait().unwrap();
+    log::set_max_level(log::LevelFilter::Info);
+    info!("my PID is {}", xous::process::id());
+
+    let xns = xous_names::XousNames::new().unwrap();
+    let watchdog_sid = xns.register_name(api::SERVER_NAME_WATCHDOG, None).expect("can't register server");
+    log::trace!("registered with NS -- {:?}", watchdog_sid);
+
+    let tt = ticktimer_server::Ticktimer::new().unwrap();
+    // From here on the hardware WDT is only fed while every watched service is alive, so it also resets the
+    // device if this server hangs, or if the reboot it asks for never happens.
+    tt.claim_wdt();
+
+    // register a suspend/resume listener
+    let sr_cid = xous::connect(watchdog_sid).expect("couldn't create suspend callback connection");
+    let mut susres = susres::Susres::new(None, &xns, api::Opcode::SuspendResume as u32, sr_cid).expect("couldn't create suspend/resume object");
+
+    thread::spawn({
+        let local_cid = xous::connect(watchdog_sid).unwrap();
+        move || {
+            let tt = ticktimer_server::Ticktimer::new().unwrap();
+            loop {
+                tt.sleep_ms(CHECK_INTERVAL_MS).unwrap();
+                xous::send_message(
+                    local_cid,
+                    xous::Message::new_scalar(Opcode::Check.to_usize().unwrap(), 0, 0, 0, 0),
+                )
+                .expect("couldn't ask the watchdog to check");
+            }
+        }
+    });
+
+    let mut watched: HashMap<u32, Watched> = HashMap::new();
+    let mut next_handle: u32 = 1;
+    // once a reboot has been asked for, the WDT is left to run out in case it doesn't happen
+    let mut rebooting = false;
+
+    log::trace!("ready to accept requests");
+    loop {
+        let mut msg = xous::receive_message(watchdog_sid).unwrap();
+        match FromPrimitive::from_usize(msg.body.id()) {
+            Some(Opcode::Register) => {
+                let mut buffer = unsafe { Buffer::from_memory_message_mut(msg.body.memory_message_mut().unwrap()) };
+                let mut registration = buffer.to_original::<Registration, _>().unwrap();
+                if !may_register(msg.sender) {
+                    log::warn!("PID {:?} asked to be watched as {}, but isn't allowed to", msg.sender.pid(), registration.name);
+                    registration.handle = None;
+                } else if watched.len() < MAX_WATCHED {
+                    let handle = next_handle;
+                    next_handle = next_handle.wrapping_add(1).max(1);
+                    log::info!("watching {} with a heartbeat every {} ms", registration.name, registration.interval_ms);
+                    watched.insert(handle, Watched {
+                        name: String::from(registration.name.as_str().unwrap_or("(unnamed)")),
+                        pid: msg.sender.pid(),
+                        interval_ms: registration.interval_ms.max(MIN_INTERVAL_MS) as u64,
+                        last_beat: tt.elapsed_ms(),
+                        snapshot: registration.snapshot_sid
+                            .map(|s| (xous::SID::from_u32(s.0, s.1, s.2, s.3), registration.snapshot_id)),
+                    });
+                    registration.handle = Some(handle);
+                } else {
+                    log::error!("too many services to watch, refusing {}", registration.name);
+                    registration.handle = None;
+                }
+                buffer.replace(registration).unwrap();
+            }
+            Some(Opcode::Heartbeat) => xous::msg_scalar_unpack!(msg, handle, _, _, _, {
+                match watched.get_mut(&(handle as u32)) {
+                    // only the process that registered can keep its registration alive
+                    Some(w) if w.pid == msg.sender.pid() => w.last_beat = tt.elapsed_ms(),
+                    _ => log::warn!("heartbeat for unknown handle {} from PID {:?}", handle, msg.sender.pid()),
+                }
+            }),
+            Some(Opcode::Unregister) => xous::msg_scalar_unpack!(msg, handle, _, _, _, {
+                if watched.get(&(handle as u32)).map(|w| w.pid == msg.sender.pid()).unwrap_or(false) {
+                    let w = watched.remove(&(handle as u32)).unwrap();
+                    log::info!("no longer watching {}", w.name);
+                }
+            }),
+            Some(Opcode::Check) => {
+                let now = tt.elapsed_ms();
+                let hung = watched.iter().find(|(_, w)| w.is_hung(now)).map(|(h, _)| *h);
+                if let Some(handle) = hung {
+                    bite(handle, &watched[&handle], &watched, now, &tt, &susres);
+                    watched.remove(&handle);
+                    rebooting = cfg!(any(target_os = "none", target_os = "xous"));
+                } else if !rebooting {
+                    tt.ping_wdt();
+                }
+            }
+            Some(Opcode::SuspendResume) => xous::msg_scalar_unpack!(msg, token, _, _, _, {
+                // the ticktimer disarms the WDT for the suspend, but it may be a while before it gets there
+                tt.ping_wdt();
+                susres.suspend_until_resume(token).expect("couldn't execute suspend/resume");
+                // nobody sends heartbeats while the device sleeps
+                let now = tt.elapsed_ms();
+                for w in watched.values_mut() {
+                    w.last_beat = now;
+                }
+            }),
+            Some(Opcode::Quit) => {
+                // stopping the watchdog leaves the WDT unfed, so only the watchdog itself may ask
+                if msg.sender.pid() != xous::current_pid().ok() {
+                    log::warn!("PID {:?} asked the watchdog to quit, ignoring it", msg.sender.pid());
+                    continue;
+                }
+                log::warn!("Quit received, goodbye world!");
+                break;
+            },
+            None => {
+                log::error!("couldn't convert opcode: {:?}", msg);
+            }
+        }
+    }
+    // clean up our program
+    log::trace!("main loop exit, destroying servers");
+    xns.unregister_server(watchdog_sid).unwrap();
+    xous::destroy_server(watchdog_sid).unwrap();
+    log::trace!("quitting");
+    xous::terminate_process(0)
+}
@@ -1,5 +1,7 @@
 #[allow(dead_code)]
 pub const AUTHENTICATE_TIMEOUT: u32 = 10_000; // time in ms that a process has to respond to an authentication request
+/// The most process names a server's connection allow-list can hold
+pub const MAX_ALLOWED_CONNECTORS: usize = 8;
 
 #[derive(num_derive::FromPrimitive, num_derive::ToPrimitive)]
 #[non_exhaustive]
@@ -70,12 +72,17 @@ pub(crate) enum Return {
 
     /// Operation requested was otherwise successful (currently only used by disconnect to ack the disconnect)
     Success,
+
+    /// The server only accepts connections from the processes on its allow-list, and the caller isn't one of them
+    AccessDenied,
 }
 
 #[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
 pub(crate) struct Registration {
     pub name: xous_ipc::String<64>,
     pub conn_limit: Option<u32>,
+    /// if present, only processes with one of these names may connect
+    pub allowed: Option<[Option<xous_ipc::String<64>>; MAX_ALLOWED_CONNECTORS]>,
 }
 
 #[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
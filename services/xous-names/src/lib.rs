@@ -57,9 +57,46 @@ impl XousNames {
         name: &str,
         max_conns: Option<u32>,
     ) -> Result<xous::SID, xous::Error> {
+        self.register(name, max_conns, None)
+    }
+
+    /// Like `register_name()`, but only the processes named in `allowed` may connect to the server; anyone else
+    /// gets `AccessDenied`. Process names are the names of the programs the image was built from, so
+    /// processes loaded at runtime are never on the list. Allow-lists aren't enforced in hosted mode.
+    pub fn register_name_with_allow_list(
+        &self,
+        name: &str,
+        max_conns: Option<u32>,
+        allowed: &[&str],
+    ) -> Result<xous::SID, xous::Error> {
+        self.register(name, max_conns, Some(allowed))
+    }
+
+    fn register(
+        &self,
+        name: &str,
+        max_conns: Option<u32>,
+        allowed: Option<&[&str]>,
+    ) -> Result<xous::SID, xous::Error> {
+        let allowed = match allowed {
+            Some(names) => {
+                if names.len() > api::MAX_ALLOWED_CONNECTORS {
+                    return Err(xous::Error::OutOfMemory);
+                }
+                let mut list = [None; api::MAX_ALLOWED_CONNECTORS];
+                for (slot, name) in list.iter_mut().zip(names.iter()) {
+                    let mut entry = String::<64>::new();
+                    write!(entry, "{}", name).expect("name probably too long");
+                    *slot = Some(entry);
+                }
+                Some(list)
+            }
+            None => None,
+        };
         let mut registration = api::Registration {
             name: String::<64>::new(),
             conn_limit: max_conns,
+            allowed,
         };
         // could also do String::from_str() but in this case we want things to fail if the string is too long.
         write!(registration.name, "{}", name).expect("name probably too long");
@@ -93,6 +130,7 @@ impl XousNames {
 
         match buf.to_original().unwrap() {
             api::Return::CID((cid, token)) => Ok((cid, token)),
+            api::Return::AccessDenied => Err(xous::Error::AccessDenied),
             // api::Return::AuthenticateRequest(_) => Err(xous::Error::AccessDenied),
             _ => Err(xous::Error::ServerNotFound),
         }
@@ -123,6 +161,7 @@ impl XousNames {
 
         match buf.to_original().unwrap() {
             api::Return::CID((cid, _)) => Ok(cid),
+            api::Return::AccessDenied => Err(xous::Error::AccessDenied),
             // api::Return::AuthenticateRequest(_) => Err(xous::Error::AccessDenied),
             _ => Err(xous::Error::ServerNotFound),
         }
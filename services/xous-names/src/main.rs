@@ -26,6 +26,9 @@ enum ConnectError {
 
     /// The message was not a mutable memory message
     InvalidMessageType = 4,
+
+    /// The caller isn't on the server's allow-list
+    AccessDenied = 5,
 }
 
 #[derive(PartialEq)]
@@ -45,6 +48,7 @@ enum ConnectSuccess {
 
 #[cfg(any(target_os = "none", target_os = "xous"))]
 mod implementation {
+    use crate::api::XousServerName;
     use utralib::generated::*;
 
     pub struct D11cTimeout {
@@ -77,6 +81,18 @@ mod implementation {
             // and cause troubles for other processes.
         }
     }
+
+    /// Looks the process up by the name it was built with. Processes without a name, such as apps loaded at
+    /// runtime, are never allowed.
+    pub fn connector_allowed(pid: xous::PID, allowed: &[XousServerName]) -> bool {
+        let mut name = [0u8; 64];
+        match xous::process_name(pid, &mut name) {
+            Ok(len) if len > 0 && len < name.len() => core::str::from_utf8(&name[..len])
+                .map(|n| allowed.contains(&XousServerName::from_str(n)))
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
 }
 
 #[cfg(not(any(target_os = "none", target_os = "xous")))]
@@ -93,6 +109,11 @@ mod implementation {
             std::thread::sleep(std::time::Duration::from_millis(10));
         }
     }
+
+    /// Hosted processes have no names the kernel knows of, so allow-lists can't be enforced
+    pub fn connector_allowed(_pid: xous::PID, _allowed: &[crate::api::XousServerName]) -> bool {
+        true
+    }
 }
 
 /*
@@ -104,7 +125,7 @@ I'll take safety over speed in this case.
 Eventually, we shall endeavor to remove Heapless entirely, once we have a `libstd` in place
 and we can use heap-allocated Rust primitives...
 */
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 struct Connection {
     pub sid: xous::SID,
     pub current_conns: u32, // number of unauthenticated (inherentely trusted) connections
@@ -112,6 +133,7 @@ struct Connection {
     pub _allow_authenticate: bool,
     pub _auth_conns: u32,        // number of authenticated connections
     pub token: Option<[u32; 4]>, // a random number that must be presented to allow for disconnection for single-connection servers
+    pub allowed: Option<Vec<XousServerName>>, // if present, only processes with these names may connect
 }
 #[derive(Debug)]
 struct CheckedHashMap {
@@ -128,6 +150,7 @@ impl CheckedHashMap {
         name: XousServerName,
         sid: xous::SID,
         max_conns: Option<u32>,
+        allowed: Option<Vec<XousServerName>>,
     ) -> Result<(), xous::Error> {
         let token = if max_conns == Some(1) {
             // for the special case of 1-connection servers, provision a one-time use token for disconnects
@@ -148,6 +171,7 @@ impl CheckedHashMap {
                 _allow_authenticate: false, // for now, we don't support authenticated connections
                 _auth_conns: 0,
                 token,
+                allowed,
             },
         );
         Ok(())
@@ -174,6 +198,15 @@ impl CheckedHashMap {
         self.map.contains_key(name)
     }
 
+    /// Whether `pid` passes the allow-list of the server registered as `name`, if it has one. Unknown names
+    /// pass; `connect()` turns those away.
+    pub fn allows(&self, name: &XousServerName, pid: xous::PID) -> bool {
+        match self.map.get(name).and_then(|entry| entry.allowed.as_ref()) {
+            Some(allowed) => implementation::connector_allowed(pid, allowed),
+            None => true,
+        }
+    }
+
     pub fn connect(&mut self, name: &XousServerName) -> (Option<xous::SID>, Option<[u32; 4]>) {
         if let Some(entry) = self.map.get_mut(name) {
             match entry.max_conns {
@@ -294,6 +327,11 @@ fn blocking_connect(
         sender_pid
     );
 
+    if !name_table.allows(&name, sender_pid) {
+        log::warn!("process {:?} isn't on the allow-list of '{}'", sender_pid, name);
+        return Err(ConnectError::AccessDenied);
+    }
+
     // If the server already exists, attempt to make the connection. The connection can
     // only succeed if the
     if let (Some(server_sid), token) = name_table.connect(&name) {
@@ -395,8 +433,14 @@ fn xmain() -> ! {
                 if !name_table.contains_key(&name) {
                     let new_sid =
                         xous::create_server_id().expect("create server failed, maybe OOM?");
+                    let allowed = registration.allowed.map(|list| {
+                        list.iter()
+                            .filter_map(|n| n.as_ref())
+                            .map(|n| XousServerName::from_str(n.as_str().unwrap_or("")))
+                            .collect::<Vec<XousServerName>>()
+                    });
                     name_table
-                        .insert(name, new_sid, registration.conn_limit)
+                        .insert(name, new_sid, registration.conn_limit, allowed)
                         .expect("register name failure, maybe out of HashMap capacity?");
                     log::trace!("request successful, SID is {:?}", new_sid);
                     should_connect = true;
@@ -482,12 +526,16 @@ fn xmain() -> ! {
                         .expect("couldn't convert server name to string"),
                 );
                 log::trace!("Lookup request for '{}'", name);
+                let sender_pid = msg
+                    .sender
+                    .pid()
+                    .expect("can't extract sender PID on Lookup");
                 let response: api::Return;
-                if let (Some(server_sid), token) = name_table.connect(&name) {
-                    let sender_pid = msg
-                        .sender
-                        .pid()
-                        .expect("can't extract sender PID on Lookup");
+                if !name_table.allows(&name, sender_pid) {
+                    log::warn!("process {:?} isn't on the allow-list of '{}'", sender_pid, name);
+                    d11ctimeout.hosted_delay();
+                    response = api::Return::AccessDenied
+                } else if let (Some(server_sid), token) = name_table.connect(&name) {
                     match xous::connect_for_process(sender_pid, server_sid)
                         .expect("can't broker connection")
                     {
@@ -13,6 +13,8 @@ SYSTEM_BASIS = '.System'
 PAGE_SIZE = 4096
 VPAGE_SIZE = 4064
 MBBB_PAGES = 10
+# the DNA of the device the image came from, which goes into the AAD; it's 0 for images made in hosted mode
+DNA = 0
 
 # build a table mapping all non-printable characters to None
 NOPRINT_TRANS_TABLE = {
@@ -174,6 +176,12 @@ def main():
             name = name_bytes.decode('utf8', errors='ignore')
             key = raw_key[4 + i*96 + 64 : 4 + i*96 + 96]
             keys[name] = key
+        # key files written before the DNA was added to them end here
+        dna_bytes = raw_key[4 + num_keys*96 : 4 + num_keys*96 + 8]
+        if len(dna_bytes) == 8:
+            global DNA
+            DNA = int.from_bytes(dna_bytes, 'little')
+            logging.info("Device DNA: 0x{:016x}".format(DNA))
 
     logging.info("Found basis keys:")
     logging.info(str(keys))
@@ -571,7 +579,9 @@ class Basis:
         #desc += ' DictPtr: {:x}\n'.format(self.dict_ptr)
         return desc
 
-def basis_aad(name, version=0x01_01, dna=0):
+def basis_aad(name, version=0x01_01, dna=None):
+    if dna is None:
+        dna = DNA
     name_bytes = bytearray(name, 'utf-8')
     # name_bytes += bytearray(([0] * (Basis.MAX_NAME_LEN - len(name))))
     name_bytes += version.to_bytes(4, 'little')
@@ -739,7 +749,9 @@ class Fscb:
                 self.free_space[pp.page_number() * 4096] = pp
 
     # this is the "AAD" used to encrypt the FastSpace
-    def aad(version=0x01_01, dna=0):
+    def aad(version=0x01_01, dna=None):
+        if dna is None:
+            dna = DNA
         return bytearray([46, 70, 97, 115, 116, 83, 112, 97, 99, 101]) + version.to_bytes(4, 'little') + dna.to_bytes(8, 'little')
 
 class SpaceUpdate:
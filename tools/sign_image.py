@@ -67,6 +67,15 @@ def main():
     parser.add_argument(
         "--kernel-output", required=False, help="kernel output image", type=str, nargs='?', metavar=('kernel output image'),  const='../target/riscv32imac-unknown-none-elf/release/xous.img'
     )
+    parser.add_argument(
+        "--app-image", required=False, help="app ELF to sign for loading out of the PDDB", type=str, nargs='?', metavar=('app image')
+    )
+    parser.add_argument(
+        "--app-key", required=False, help="app signing key", type=str, nargs='?', metavar=('app signing key'), const=DEVKEY_PATH
+    )
+    parser.add_argument(
+        "--app-output", required=False, help="signed app output image", type=str, nargs='?', metavar=('signed app output image')
+    )
     parser.add_argument(
         "--defile", help="patch the resulting image, to create a test file to catch signature failure", default=False, action="store_true"
     )
@@ -135,6 +144,25 @@ def main():
     if kernel_pkey != None:
         blob_sign(args.kernel_image, kernel_output, kernel_pkey, defile=args.defile)
 
+    # apps are signed the same way as the kernel, so the app loader can check them
+    # with the keys in the keyrom before it runs them
+    if args.app_image:
+        app_key = args.app_key if args.app_key is not None else DEVKEY_PATH
+        app_output = args.app_output if args.app_output is not None else args.app_image + '.signed'
+        with open(app_key) as app_f:
+            app_pem = app_f.read()
+            try:
+                pem = PEM.decode(app_pem, None)
+            except:
+                passphrase = input("Enter app key passphrase: ")
+                pem = PEM.decode(app_pem, passphrase)
+
+            (app_pkey, pemtype, enc) = pem
+            if pemtype != 'PRIVATE KEY':
+                print("PEM type for app was not a private key. Aborting.")
+                exit(1)
+        blob_sign(args.app_image, app_output, app_pkey, defile=args.defile)
+
 if __name__ == "__main__":
     main()
     exit(0)
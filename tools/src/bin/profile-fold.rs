@@ -0,0 +1,126 @@
+//! Turns a profile printed by the log server, after `prof dump` in shellchat, into the folded stacks that
+//! `flamegraph.pl` and `inferno-flamegraph` draw. The log is read from stdin. Each sample is looked up in the
+//! ELF file of the process it was taken in, which is found in the given directory under the name the process
+//! was given when the image was built, so it has to be the directory of the same build.
+//!
+//! A sample only has the interrupted address and the return address register, so each stack is the process,
+//! then the caller as far as the return address tells, then the function that was running. In a function that
+//! calls others the return address can be left over from an earlier call, so the callers are a hint.
+
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, Read};
+use std::path::Path;
+use std::process;
+use tools::symbols::{find, read_symbols, Symbol};
+use xmas_elf::ElfFile;
+
+/// A `PROF <pid> <pc> <ra>` line
+fn parse_sample(line: &str) -> Option<(u8, u64, u64)> {
+    let mut words = line[line.find("PROF ")?..].split_whitespace().skip(1);
+    let pid = words.next()?.parse::<u8>().ok()?;
+    let pc = u64::from_str_radix(words.next()?, 16).ok()?;
+    let ra = u64::from_str_radix(words.next()?, 16).ok()?;
+    Some((pid, pc, ra))
+}
+
+/// A `PNAM <pid> <name>` line
+fn parse_name(line: &str) -> Option<(u8, String)> {
+    let mut words = line[line.find("PNAM ")?..].split_whitespace().skip(1);
+    let pid = words.next()?.parse::<u8>().ok()?;
+    Some((pid, words.next()?.to_owned()))
+}
+
+fn load_symbols(path: &Path) -> Vec<Symbol> {
+    let mut b = Vec::new();
+    if let Err(e) = File::open(path).and_then(|mut f| f.read_to_end(&mut b)) {
+        eprintln!("Unable to read {}: {}", path.display(), e);
+        return Vec::new();
+    }
+    match ElfFile::new(&b) {
+        Ok(elf) => {
+            let symbols = read_symbols(&elf);
+            if symbols.is_empty() {
+                eprintln!("{} has no symbols; was it stripped?", path.display());
+            }
+            symbols
+        }
+        Err(e) => {
+            eprintln!("Unable to parse {}: {}", path.display(), e);
+            Vec::new()
+        }
+    }
+}
+
+/// Folded stacks are split on `;`, which turns up in the names of generic functions that take arrays
+fn frame(symbol: &Symbol) -> String {
+    symbol.name.replace(';', ",")
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        println!(
+            "Usage: {} elf-directory [process=program.elf ...] < log.txt > profile.folded",
+            args.get(0).unwrap_or(&"profile-fold".to_owned())
+        );
+        return;
+    }
+    let dir = Path::new(&args[1]);
+    let mut paths = HashMap::new();
+    for arg in &args[2..] {
+        match arg.split_once('=') {
+            Some((name, path)) => {
+                paths.insert(name.to_owned(), Path::new(path).to_path_buf());
+            }
+            None => {
+                eprintln!("Expected process=program.elf, not {}", arg);
+                process::exit(1);
+            }
+        }
+    }
+
+    let mut samples = Vec::new();
+    let mut names = HashMap::new();
+    for line in io::stdin().lock().lines() {
+        let line = line.unwrap_or_default();
+        if let Some(sample) = parse_sample(&line) {
+            samples.push(sample);
+        } else if let Some((pid, name)) = parse_name(&line) {
+            names.insert(pid, name);
+        }
+    }
+    if samples.is_empty() {
+        eprintln!("No samples found; the log should have the output of `prof dump`");
+        process::exit(1);
+    }
+
+    let mut symbols: HashMap<String, Vec<Symbol>> = HashMap::new();
+    let mut stacks = BTreeMap::new();
+    for (pid, pc, ra) in samples {
+        let name = names.get(&pid).cloned().unwrap_or_else(|| format!("pid{}", pid));
+        let symbols = symbols.entry(name.clone()).or_insert_with(|| {
+            load_symbols(&paths.get(&name).cloned().unwrap_or_else(|| dir.join(&name)))
+        });
+        let mut stack = name;
+        match find(symbols, pc) {
+            Some(function) => {
+                // a return address is just past the call, which may be the last instruction of the function
+                if let Some(caller) = find(symbols, ra.saturating_sub(1)) {
+                    if caller.start != function.start {
+                        stack.push(';');
+                        stack.push_str(&frame(caller));
+                    }
+                }
+                stack.push(';');
+                stack.push_str(&frame(function));
+            }
+            None => stack.push_str(";[unknown]"),
+        }
+        *stacks.entry(stack).or_insert(0usize) += 1;
+    }
+    for (stack, count) in stacks {
+        println!("{} {}", stack, count);
+    }
+}
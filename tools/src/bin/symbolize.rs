@@ -0,0 +1,60 @@
+//! Turns the backtrace in a panic report into function names. The report is read from stdin, and is printed
+//! back out with each frame's function added to the end of its line. The frames are return addresses, as
+//! written by `xous::PanicWriter::write_backtrace()`, so they have to be looked up in the ELF file of the same
+//! build of the program that panicked.
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, Read};
+use std::process;
+use tools::symbols::{find, read_symbols};
+use xmas_elf::ElfFile;
+
+/// Picks the frame's address out of a line like `   3: 2001a2c4`
+fn frame_address(line: &str) -> Option<u64> {
+    let mut parts = line.trim().splitn(2, ':');
+    parts.next()?.trim().parse::<usize>().ok()?;
+    u64::from_str_radix(parts.next()?.trim(), 16).ok()
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 {
+        println!(
+            "Usage: {} program.elf < report.txt",
+            args.get(0).unwrap_or(&"symbolize".to_owned())
+        );
+        return;
+    }
+    let mut b = Vec::new();
+    File::open(&args[1])
+        .and_then(|mut f| f.read_to_end(&mut b))
+        .unwrap_or_else(|e| {
+            eprintln!("Unable to read {}: {}", args[1], e);
+            process::exit(1);
+        });
+    let elf = ElfFile::new(&b).unwrap_or_else(|e| {
+        eprintln!("Unable to parse {}: {}", args[1], e);
+        process::exit(1);
+    });
+    let symbols = read_symbols(&elf);
+    if symbols.is_empty() {
+        eprintln!("{} has no symbols; was it stripped?", args[1]);
+        process::exit(1);
+    }
+
+    for line in io::stdin().lock().lines() {
+        let line = line.unwrap_or_default();
+        match frame_address(&line) {
+            Some(address) => {
+                // a return address is just past the call, which may be the last instruction of the function
+                let call = address.saturating_sub(1);
+                match find(&symbols, call) {
+                    Some(s) => println!("{}  {}+{:#x}", line, s.name, address - s.start),
+                    None => println!("{}  ??", line),
+                }
+            }
+            None => println!("{}", line),
+        }
+    }
+}
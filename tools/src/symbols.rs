@@ -0,0 +1,44 @@
+//! The function symbols of an ELF file, for turning addresses back into names. The tools that use these look
+//! up addresses from a running program, so the ELF file has to come from the same build.
+
+use xmas_elf::sections::SectionData;
+use xmas_elf::symbol_table::{Entry, Type};
+use xmas_elf::ElfFile;
+
+pub struct Symbol {
+    pub start: u64,
+    pub end: u64,
+    pub name: String,
+}
+
+/// Reads the functions out of the symbol table, demangled and sorted by address. This is empty if the ELF
+/// file was stripped.
+pub fn read_symbols(elf: &ElfFile) -> Vec<Symbol> {
+    let mut symbols = Vec::new();
+    let section = match elf.find_section_by_name(".symtab") {
+        Some(section) => section,
+        None => return symbols,
+    };
+    let mut add = |entry: &dyn Entry| {
+        if entry.get_type() == Ok(Type::Func) && entry.size() != 0 {
+            let name = entry.get_name(elf).unwrap_or("<<error>>");
+            symbols.push(Symbol {
+                start: entry.value(),
+                end: entry.value() + entry.size(),
+                name: rustc_demangle::demangle(name).to_string(),
+            });
+        }
+    };
+    match section.get_data(elf) {
+        Ok(SectionData::SymbolTable32(entries)) => entries.iter().for_each(|e| add(e)),
+        Ok(SectionData::SymbolTable64(entries)) => entries.iter().for_each(|e| add(e)),
+        _ => (),
+    }
+    symbols.sort_by_key(|s| s.start);
+    symbols
+}
+
+/// The function that `address` is in
+pub fn find(symbols: &[Symbol], address: u64) -> Option<&Symbol> {
+    symbols.iter().find(|s| s.start <= address && address < s.end)
+}
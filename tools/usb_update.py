@@ -279,6 +279,21 @@ class PrecursorUsb:
         progress.finish()
         print("Erase finished")
 
+    # addr is relative to the base of FLASH (not absolute)
+    def flash_read(self, addr, length):
+        flash_region = int(self.regions['spiflash'][0], 0)
+        # read in chunks, so the watchdog can be kept at bay
+        chunklen = 65536
+        data = bytearray()
+        progress = ProgressBar(min_value=0, max_value=length, prefix='Reading ').start()
+        while len(data) < length:
+            self.ping_wdt()
+            data += self.burst_read(flash_region + addr + len(data), min(chunklen, length - len(data)))
+            progress.update(len(data))
+        progress.finish()
+        print("Read finished")
+        return data
+
     # addr is relative to the base of FLASH (not absolute)
     def flash_program(self, addr, data, verify=True):
         flash_region = int(self.regions['spiflash'][0], 0)
@@ -416,6 +431,9 @@ def main():
     parser.add_argument(
         "--erase-pddb", help="Erase the PDDB area", action="store_true"
     )
+    parser.add_argument(
+        "--dump-pddb", required=False, help="Copy the PDDB area into a file, before anything else is done. Write it back with `-i FILE 0x01D80000`.", type=str, metavar=('PDDB image file')
+    )
     parser.add_argument(
         "--audiotest", required=False, help="Test audio clip (must be 8kHz WAV)", type=str, nargs='?', metavar=('Test audio clip'), const="testaudio.wav"
     )
@@ -535,6 +553,12 @@ def main():
     print("Halting CPU.")
     pc_usb.poke(vexdbg_addr, 0x00020000)
 
+    if args.dump_pddb:
+        print("Copying PDDB region to '{}'".format(args.dump_pddb))
+        with open(args.dump_pddb, "wb") as f:
+            # the PDDB ends at EC_REGION_LOC (see xous-rs/src/definitions.rs), which is LOC_WF200 here
+            f.write(pc_usb.flash_read(locs['LOC_PDDB'][0], locs['LOC_WF200'][0] - locs['LOC_PDDB'][0]))
+
     if args.erase_pddb:
         print("Erasing PDDB region")
         pc_usb.erase_region(locs['LOC_PDDB'][0], locs['LOC_EC'][0] - locs['LOC_PDDB'][0])
@@ -0,0 +1,265 @@
+//! Lets a Xous server `await` its incoming messages and its outgoing blocking calls, instead of sitting in a
+//! `receive_message()` loop. Any number of tasks share the server's one thread: while one of them waits on a
+//! slow call to another server, the others keep taking messages.
+//!
+//! ```ignore
+//! let sid = xns.register_name(api::SERVER_NAME_DNS, None).expect("can't register server");
+//! let mut runtime = xous_async::Runtime::new(sid).expect("can't start async runtime");
+//! let handle = runtime.handle();
+//! runtime.spawn(async move {
+//!     loop {
+//!         let msg = handle.incoming().await;
+//!         match FromPrimitive::from_usize(msg.body.id()) {
+//!             // a lookup can take a while, so it gets a task of its own and the loop goes back to listening
+//!             Some(Opcode::Lookup) => handle.spawn(lookup(handle.clone(), msg)),
+//!             Some(Opcode::Quit) => break,
+//!             None => log::error!("couldn't convert opcode: {:?}", msg),
+//!         }
+//!     }
+//! });
+//! runtime.run();
+//! ```
+//!
+//! Blocking calls made through `Handle::send_message()` are carried out by a single helper thread, in the order
+//! they were made. A slow server holds up the other outgoing calls, but never the incoming messages.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+use xous::{Message, MessageEnvelope, CID, SID};
+
+/// Scalar message id the runtime sends to its own server to wake its thread. A server run on a `Runtime` must
+/// not use it for anything else; the same message from a client just causes an extra round of polling.
+pub const WAKE_OPCODE: usize = usize::MAX;
+
+type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+/// The part of the runtime that wakers reach, possibly from other threads
+struct Shared {
+    ready: Mutex<VecDeque<usize>>,
+    /// set while the runtime's thread is blocked in `receive_message()`
+    sleeping: AtomicBool,
+    self_conn: CID,
+}
+impl Shared {
+    fn schedule(&self, id: usize) {
+        self.ready.lock().unwrap().push_back(id);
+        if self.sleeping.swap(false, Ordering::SeqCst) {
+            xous::send_message(self.self_conn, Message::new_scalar(WAKE_OPCODE, 0, 0, 0, 0))
+                .expect("couldn't wake the async runtime");
+        }
+    }
+}
+
+struct TaskWaker {
+    id: usize,
+    shared: Arc<Shared>,
+}
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.shared.schedule(self.id);
+    }
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.shared.schedule(self.id);
+    }
+}
+
+#[derive(Default)]
+struct Inbox {
+    messages: VecDeque<MessageEnvelope>,
+    waiters: Vec<Waker>,
+}
+
+struct CallSlot {
+    result: Option<Result<xous::Result, xous::Error>>,
+    waker: Option<Waker>,
+}
+struct Call {
+    conn: CID,
+    msg: Message,
+    slot: Arc<Mutex<CallSlot>>,
+}
+
+/// The part of the runtime that tasks reach; it never leaves the server's thread
+struct Local {
+    inbox: RefCell<Inbox>,
+    spawned: RefCell<Vec<Task>>,
+    caller: RefCell<Option<mpsc::Sender<Call>>>,
+}
+
+/// What tasks use to get at the runtime they run on. Cheap to clone, so every task can have its own.
+#[derive(Clone)]
+pub struct Handle {
+    local: Rc<Local>,
+}
+impl Handle {
+    /// Adds a task to the runtime. It's first polled once the task that spawned it yields.
+    pub fn spawn(&self, task: impl Future<Output = ()> + 'static) {
+        self.local.spawned.borrow_mut().push(Box::pin(task));
+    }
+    /// Waits for the next message sent to the server. Each message goes to exactly one of the tasks waiting.
+    pub fn incoming(&self) -> Incoming {
+        Incoming { local: self.local.clone() }
+    }
+    /// Sends `msg` on `conn` and waits for the outcome, like `xous::send_message()`, but without holding up the
+    /// other tasks. The message goes out right away, whether or not the `Reply` is ever awaited.
+    pub fn send_message(&self, conn: CID, msg: Message) -> Reply {
+        let slot = Arc::new(Mutex::new(CallSlot { result: None, waker: None }));
+        self.caller()
+            .send(Call { conn, msg, slot: slot.clone() })
+            .expect("async runtime's caller thread is gone");
+        Reply { slot }
+    }
+    fn caller(&self) -> mpsc::Sender<Call> {
+        self.local.caller.borrow_mut().get_or_insert_with(|| {
+            let (tx, rx) = mpsc::channel::<Call>();
+            // ends when the runtime, and with it the sending side of the channel, goes away
+            std::thread::spawn(move || {
+                for call in rx.iter() {
+                    let result = xous::send_message(call.conn, call.msg);
+                    let mut slot = call.slot.lock().unwrap();
+                    slot.result = Some(result);
+                    if let Some(waker) = slot.waker.take() {
+                        waker.wake();
+                    }
+                }
+            });
+            tx
+        }).clone()
+    }
+}
+
+/// Resolves to the next message sent to the server, see `Handle::incoming()`
+pub struct Incoming {
+    local: Rc<Local>,
+}
+impl Future for Incoming {
+    type Output = MessageEnvelope;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<MessageEnvelope> {
+        let mut inbox = self.local.inbox.borrow_mut();
+        match inbox.messages.pop_front() {
+            Some(msg) => Poll::Ready(msg),
+            None => {
+                inbox.waiters.push(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Resolves to what `xous::send_message()` returned, see `Handle::send_message()`
+pub struct Reply {
+    slot: Arc<Mutex<CallSlot>>,
+}
+impl Future for Reply {
+    type Output = Result<xous::Result, xous::Error>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.slot.lock().unwrap();
+        match slot.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Runs tasks on the thread that calls `run()`, feeding them the messages sent to one server
+pub struct Runtime {
+    sid: SID,
+    shared: Arc<Shared>,
+    local: Rc<Local>,
+    tasks: Vec<Option<Task>>,
+}
+impl Runtime {
+    /// Makes a runtime for the server `sid`, which the caller has already created or registered
+    pub fn new(sid: SID) -> Result<Runtime, xous::Error> {
+        let self_conn = xous::connect(sid)?;
+        Ok(Runtime {
+            sid,
+            shared: Arc::new(Shared {
+                ready: Mutex::new(VecDeque::new()),
+                sleeping: AtomicBool::new(false),
+                self_conn,
+            }),
+            local: Rc::new(Local {
+                inbox: RefCell::new(Inbox::default()),
+                spawned: RefCell::new(Vec::new()),
+                caller: RefCell::new(None),
+            }),
+            tasks: Vec::new(),
+        })
+    }
+    pub fn handle(&self) -> Handle {
+        Handle { local: self.local.clone() }
+    }
+    pub fn spawn(&self, task: impl Future<Output = ()> + 'static) {
+        self.handle().spawn(task);
+    }
+    /// Runs the tasks until every one of them has finished. Whenever none of them can make progress, the thread
+    /// blocks until a message comes in. Messages nobody took by the time the last task finishes are dropped.
+    pub fn run(&mut self) {
+        loop {
+            self.adopt_spawned();
+            loop {
+                let next = self.shared.ready.lock().unwrap().pop_front();
+                let id = match next {
+                    Some(id) => id,
+                    None => break,
+                };
+                // a slot can be woken after its task has finished, or been reused; an extra poll does no harm
+                if let Some(task) = self.tasks.get_mut(id).and_then(|t| t.as_mut()) {
+                    let waker = Waker::from(Arc::new(TaskWaker { id, shared: self.shared.clone() }));
+                    if task.as_mut().poll(&mut Context::from_waker(&waker)).is_ready() {
+                        self.tasks[id] = None;
+                    }
+                }
+                self.adopt_spawned();
+            }
+            if self.tasks.iter().all(|t| t.is_none()) {
+                return;
+            }
+
+            // a task woken from another thread between here and `receive_message()` sends a message to wake us
+            self.shared.sleeping.store(true, Ordering::SeqCst);
+            if !self.shared.ready.lock().unwrap().is_empty() {
+                self.shared.sleeping.store(false, Ordering::SeqCst);
+                continue;
+            }
+            let msg = xous::receive_message(self.sid).expect("async runtime couldn't receive a message");
+            self.shared.sleeping.store(false, Ordering::SeqCst);
+            if msg.body.id() == WAKE_OPCODE && matches!(msg.body, Message::Scalar(_)) {
+                continue;
+            }
+            let mut inbox = self.local.inbox.borrow_mut();
+            inbox.messages.push_back(msg);
+            // whichever waiter gets polled first takes the message; the others go back to waiting
+            for waker in inbox.waiters.drain(..) {
+                waker.wake();
+            }
+        }
+    }
+    fn adopt_spawned(&mut self) {
+        let spawned: Vec<Task> = self.local.spawned.borrow_mut().drain(..).collect();
+        for task in spawned {
+            let id = match self.tasks.iter().position(|t| t.is_none()) {
+                Some(free) => {
+                    self.tasks[free] = Some(task);
+                    free
+                }
+                None => {
+                    self.tasks.push(Some(task));
+                    self.tasks.len() - 1
+                }
+            };
+            self.shared.ready.lock().unwrap().push_back(id);
+        }
+    }
+}
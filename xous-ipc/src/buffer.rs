@@ -2,8 +2,8 @@ use core::convert::TryInto;
 
 use rkyv::{ser::Serializer, Fallible};
 use xous::{
-    map_memory, send_message, unmap_memory, Error, MemoryAddress, MemoryFlags, MemoryMessage,
-    MemoryRange, MemorySize, Message, Result, CID,
+    map_memory, send_message, send_message_timeout, unmap_memory, Error, MemoryAddress,
+    MemoryFlags, MemoryMessage, MemoryRange, MemorySize, Message, Result, CID,
 };
 
 #[derive(Debug)]
@@ -163,6 +163,50 @@ impl<'a> Buffer<'a> {
         send_message(connection, Message::Borrow(msg))
     }
 
+    /// Perform a mutable lend of this Buffer to the server, giving up with
+    /// `Error::Timeout` if it hasn't answered within `timeout_ms` milliseconds.
+    /// After a timeout the Buffer holds what it did before the call.
+    #[allow(dead_code)]
+    pub fn lend_mut_timeout(
+        &mut self,
+        connection: CID,
+        id: u32,
+        timeout_ms: usize,
+    ) -> core::result::Result<Result, Error> {
+        let msg = MemoryMessage {
+            id: id as usize,
+            buf: self.valid,
+            offset: self.offset,
+            valid: MemorySize::new(self.slice.len()),
+        };
+
+        // Update the offset pointer if the server modified it.
+        let result = send_message_timeout(connection, Message::MutableBorrow(msg), timeout_ms);
+        if let Ok(Result::MemoryReturned(offset, _valid)) = result {
+            self.offset = offset;
+        }
+
+        result
+    }
+
+    /// Lend this Buffer to the server, giving up with `Error::Timeout` if it
+    /// hasn't answered within `timeout_ms` milliseconds.
+    #[allow(dead_code)]
+    pub fn lend_timeout(
+        &self,
+        connection: CID,
+        id: u32,
+        timeout_ms: usize,
+    ) -> core::result::Result<Result, Error> {
+        let msg = MemoryMessage {
+            id: id as usize,
+            buf: self.valid,
+            offset: self.offset,
+            valid: MemorySize::new(self.slice.len()),
+        };
+        send_message_timeout(connection, Message::Borrow(msg), timeout_ms)
+    }
+
     #[allow(dead_code)]
     pub fn send(mut self, connection: CID, id: u32) -> core::result::Result<Result, Error> {
         let msg = MemoryMessage {
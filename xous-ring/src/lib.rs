@@ -0,0 +1,435 @@
+//! A single-producer, single-consumer ring of byte frames between two processes, for paths where a message per
+//! frame costs too much: audio frames, USB bulk data, log streaming. The producer maps the ring's pages and lends
+//! them to the consumer with `xous::share_memory()`, so both see the same memory for as long as the ring is open.
+//! Frames go in and come out without a syscall; the kernel only gets involved when one side has to wait for the
+//! other, which then wakes it with a scalar message.
+//!
+//! ```ignore
+//! // producer: the server takes the ring on `Opcode::OpenStream`
+//! let mut ring = xous_ring::Producer::new(conn, Opcode::OpenStream.to_usize().unwrap(), 4)?;
+//! ring.push(&frame)?;
+//!
+//! // consumer, in the server's message loop, before it matches on the opcode: the ring's ID has
+//! // `xous::SHARED_MESSAGE_FLAG` set, so it only turns up where it's asked for
+//! if msg.body.shared_id() == Some(Opcode::OpenStream.to_usize().unwrap()) {
+//!     let mut ring = xous_ring::Consumer::accept(msg).expect("couldn't accept ring");
+//!     std::thread::spawn(move || {
+//!         let mut frame = [0u8; 1024];
+//!         while let Ok(Some(len)) = ring.pop(&mut frame) {
+//!             play(&frame[..len]);
+//!         }
+//!     });
+//!     continue;
+//! }
+//! ```
+//!
+//! Each side treats what the other writes to the ring as untrusted, so a misbehaving peer can garble the frames
+//! but can't make its partner read or write outside the ring. Rings need the memory of two processes to overlap,
+//! which hosted mode can't do, so there `Producer::new()` fails with `Error::UnhandledSyscall`.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use xous::{Error, MemoryFlags, MemoryMessage, MemoryRange, Message, MessageEnvelope, CID, SID};
+
+const PAGE_SIZE: usize = 4096;
+/// The start of the first page holds the `Header`; frames go in the rest
+const HEADER_LEN: usize = 64;
+/// Each frame is preceded by its length, as a little-endian `u32`
+const LEN_BYTES: usize = 4;
+/// The one message sent to the doorbell servers. `arg1` carries an error when the ring couldn't be opened.
+const DOORBELL: usize = 0;
+
+/// Shared by both sides. Each index is only moved by one side: `head` by the producer, `tail` by the consumer.
+#[repr(C)]
+struct Header {
+    head: AtomicU32,
+    tail: AtomicU32,
+    /// set by a side about to wait, so the other knows to ring its doorbell
+    producer_waiting: AtomicU32,
+    consumer_waiting: AtomicU32,
+    /// set by a side once it's gone
+    producer_closed: AtomicU32,
+    consumer_closed: AtomicU32,
+    /// set by the consumer once `consumer_sid` is filled in
+    consumer_ready: AtomicU32,
+    producer_sid: [AtomicU32; 4],
+    consumer_sid: [AtomicU32; 4],
+}
+
+/// The ring proper, at `base`, without the processes around it
+struct Ring {
+    base: *mut u8,
+    capacity: usize,
+}
+impl Ring {
+    /// `len` bytes starting at `base` must stay valid, and only be used by the ring, for as long as it lives
+    unsafe fn new(base: *mut u8, len: usize) -> Ring {
+        Ring { base, capacity: len - HEADER_LEN }
+    }
+    fn header(&self) -> &Header {
+        unsafe { &*(self.base as *const Header) }
+    }
+    fn load_sid(sid: &[AtomicU32; 4]) -> SID {
+        SID::from_u32(
+            sid[0].load(Ordering::Relaxed),
+            sid[1].load(Ordering::Relaxed),
+            sid[2].load(Ordering::Relaxed),
+            sid[3].load(Ordering::Relaxed),
+        )
+    }
+    fn store_sid(sid: &[AtomicU32; 4], value: SID) {
+        for (word, value) in sid.iter().zip(value.to_array().iter()) {
+            word.store(*value, Ordering::Relaxed);
+        }
+    }
+    /// Bytes waiting to be read, or `None` if the peer has put the indices out of range. One byte always stays
+    /// free, so a full ring can be told from an empty one.
+    fn used(&self) -> Option<usize> {
+        let head = self.header().head.load(Ordering::Acquire) as usize;
+        let tail = self.header().tail.load(Ordering::Acquire) as usize;
+        if head >= self.capacity || tail >= self.capacity {
+            return None;
+        }
+        Some((head + self.capacity - tail) % self.capacity)
+    }
+    fn is_empty(&self) -> bool {
+        self.used() == Some(0)
+    }
+    fn has_room(&self, len: usize) -> bool {
+        self.used().map_or(false, |used| used + LEN_BYTES + len < self.capacity)
+    }
+    /// Copies `src` in at `at`, wrapping around the end, and returns where it stopped
+    fn copy_in(&self, at: usize, src: &[u8]) -> usize {
+        let first = src.len().min(self.capacity - at);
+        unsafe {
+            let data = self.base.add(HEADER_LEN);
+            core::ptr::copy_nonoverlapping(src.as_ptr(), data.add(at), first);
+            core::ptr::copy_nonoverlapping(src.as_ptr().add(first), data, src.len() - first);
+        }
+        (at + src.len()) % self.capacity
+    }
+    fn copy_out(&self, at: usize, dst: &mut [u8]) -> usize {
+        let first = dst.len().min(self.capacity - at);
+        unsafe {
+            let data = self.base.add(HEADER_LEN);
+            core::ptr::copy_nonoverlapping(data.add(at), dst.as_mut_ptr(), first);
+            core::ptr::copy_nonoverlapping(data, dst.as_mut_ptr().add(first), dst.len() - first);
+        }
+        (at + dst.len()) % self.capacity
+    }
+    /// Adds a frame, or returns `false` if there isn't room for it yet
+    fn write(&self, frame: &[u8]) -> Result<bool, Error> {
+        if LEN_BYTES + frame.len() >= self.capacity {
+            return Err(Error::OutOfMemory);
+        }
+        let used = self.used().ok_or(Error::InternalError)?;
+        if used + LEN_BYTES + frame.len() >= self.capacity {
+            return Ok(false);
+        }
+        let head = self.header().head.load(Ordering::Relaxed) as usize;
+        let at = self.copy_in(head, &(frame.len() as u32).to_le_bytes());
+        let at = self.copy_in(at, frame);
+        self.header().head.store(at as u32, Ordering::Release);
+        Ok(true)
+    }
+    /// Takes the next frame into `buf` and returns its length, or `None` if there's no frame waiting. A frame
+    /// that doesn't fit in `buf` stays in the ring.
+    fn read(&self, buf: &mut [u8]) -> Result<Option<usize>, Error> {
+        let used = self.used().ok_or(Error::InternalError)?;
+        if used == 0 {
+            return Ok(None);
+        }
+        if used < LEN_BYTES {
+            return Err(Error::InternalError);
+        }
+        let tail = self.header().tail.load(Ordering::Relaxed) as usize;
+        let mut len = [0u8; LEN_BYTES];
+        let at = self.copy_out(tail, &mut len);
+        let len = u32::from_le_bytes(len) as usize;
+        if len > used - LEN_BYTES {
+            return Err(Error::InternalError);
+        }
+        if len > buf.len() {
+            return Err(Error::OutOfMemory);
+        }
+        let at = self.copy_out(at, &mut buf[..len]);
+        self.header().tail.store(at as u32, Ordering::Release);
+        Ok(Some(len))
+    }
+}
+
+fn ring_doorbell(cid: CID, arg: usize) {
+    // a full queue already holds a wakeup, so there's no need to wait for room in it
+    xous::try_send_message(cid, Message::new_scalar(DOORBELL, arg, 0, 0, 0)).ok();
+}
+
+/// The ring's pages, unmapped once both the producer and the thread lending them are done
+struct Pages(MemoryRange);
+impl Drop for Pages {
+    fn drop(&mut self) {
+        xous::unmap_memory(self.0).ok();
+    }
+}
+
+/// The writing end of a ring
+pub struct Producer {
+    ring: Ring,
+    _pages: Arc<Pages>,
+    doorbell: SID,
+    consumer: CID,
+}
+// the ring is only reached through `&mut self`
+unsafe impl Send for Producer {}
+
+impl Producer {
+    /// Opens a ring of `pages` pages with the server behind `conn`, which takes it by calling `Consumer::accept()`
+    /// on the `opcode` message. Blocks until the server has done so; if it drops the message instead, this fails
+    /// with `Error::ServerNotFound`.
+    pub fn new(conn: CID, opcode: usize, pages: usize) -> Result<Producer, Error> {
+        if pages == 0 {
+            return Err(Error::BadAddress);
+        }
+        let range = xous::map_memory(None, None, pages * PAGE_SIZE, MemoryFlags::R | MemoryFlags::W)?;
+        let pages = Arc::new(Pages(range));
+        // freshly mapped memory is zeroed, which is an empty ring with nobody waiting
+        let ring = unsafe { Ring::new(range.as_mut_ptr(), range.len()) };
+        let doorbell = xous::create_server()?;
+        Ring::store_sid(&ring.header().producer_sid, doorbell);
+
+        // the lend blocks until the consumer gives the pages back, so it gets a thread of its own
+        let own_bell = xous::connect(doorbell).map_err(|e| {
+            xous::destroy_server(doorbell).ok();
+            e
+        })?;
+        let lent = pages.clone();
+        std::thread::spawn(move || {
+            let result = xous::share_memory(
+                conn,
+                MemoryMessage { id: opcode, buf: lent.0, offset: None, valid: None },
+            );
+            let ring = unsafe { Ring::new(lent.0.as_mut_ptr(), lent.0.len()) };
+            ring.header().consumer_closed.store(1, Ordering::SeqCst);
+            ring_doorbell(own_bell, result.err().map_or(0, |e| e as usize));
+            unsafe { xous::disconnect(own_bell).ok() };
+        });
+
+        loop {
+            let msg = xous::receive_message(doorbell)?;
+            if ring.header().consumer_ready.load(Ordering::Acquire) != 0 {
+                break;
+            }
+            if ring.header().consumer_closed.load(Ordering::SeqCst) != 0 {
+                xous::destroy_server(doorbell).ok();
+                return Err(match msg.body.scalar_message() {
+                    Some(scalar) if scalar.arg1 != 0 => Error::from_usize(scalar.arg1),
+                    _ => Error::ServerNotFound,
+                });
+            }
+        }
+        let consumer = match xous::try_connect(Ring::load_sid(&ring.header().consumer_sid)) {
+            Ok(cid) => cid,
+            Err(e) => {
+                xous::destroy_server(doorbell).ok();
+                return Err(e);
+            }
+        };
+        Ok(Producer { ring, _pages: pages, doorbell, consumer })
+    }
+    /// Bytes in the ring, including the length that goes with each frame
+    pub fn capacity(&self) -> usize {
+        self.ring.capacity - 1
+    }
+    /// Adds a frame, waiting for room if the ring is full.
+    ///
+    /// # Errors
+    ///
+    /// * **ServerNotFound**: The consumer has closed the ring
+    /// * **OutOfMemory**: The frame is too big to ever fit in the ring
+    /// * **InternalError**: The consumer has garbled the ring
+    pub fn push(&mut self, frame: &[u8]) -> Result<(), Error> {
+        loop {
+            if self.try_push(frame)? {
+                return Ok(());
+            }
+            let header = self.ring.header();
+            header.producer_waiting.store(1, Ordering::SeqCst);
+            // room may have been made between the attempt and raising the flag
+            if self.ring.has_room(frame.len()) || header.consumer_closed.load(Ordering::SeqCst) != 0 {
+                header.producer_waiting.store(0, Ordering::SeqCst);
+                continue;
+            }
+            xous::receive_message(self.doorbell)?;
+        }
+    }
+    /// Adds a frame if there's room for it right now, and returns whether there was. Fails as `push()` does.
+    pub fn try_push(&mut self, frame: &[u8]) -> Result<bool, Error> {
+        let header = self.ring.header();
+        if header.consumer_closed.load(Ordering::SeqCst) != 0 {
+            return Err(Error::ServerNotFound);
+        }
+        if !self.ring.write(frame)? {
+            return Ok(false);
+        }
+        if header.consumer_waiting.swap(0, Ordering::SeqCst) != 0 {
+            ring_doorbell(self.consumer, 0);
+        }
+        Ok(true)
+    }
+}
+impl Drop for Producer {
+    fn drop(&mut self) {
+        // the consumer still gets the frames that are in the ring
+        self.ring.header().producer_closed.store(1, Ordering::SeqCst);
+        ring_doorbell(self.consumer, 0);
+        unsafe { xous::disconnect(self.consumer).ok() };
+        xous::destroy_server(self.doorbell).ok();
+    }
+}
+
+/// The reading end of a ring
+pub struct Consumer {
+    ring: Ring,
+    doorbell: SID,
+    producer: CID,
+    /// holding on to the message keeps the ring lent; dropping it gives the pages back
+    lend: Option<MessageEnvelope>,
+}
+unsafe impl Send for Consumer {}
+
+impl Consumer {
+    /// Takes the ring lent by `Producer::new()`. Dropping the message instead turns the producer away.
+    pub fn accept(msg: MessageEnvelope) -> Result<Consumer, Error> {
+        // an ordinary lend would leave the producer without the pages it writes frames to
+        let range = match (&msg.body, msg.body.shared_id()) {
+            (Message::MutableBorrow(mem), Some(_)) => mem.buf,
+            _ => return Err(Error::ShareViolation),
+        };
+        if range.len() < PAGE_SIZE {
+            return Err(Error::BadAddress);
+        }
+        let ring = unsafe { Ring::new(range.as_mut_ptr(), range.len()) };
+        let producer = xous::try_connect(Ring::load_sid(&ring.header().producer_sid))?;
+        let doorbell = match xous::create_server() {
+            Ok(sid) => sid,
+            Err(e) => {
+                unsafe { xous::disconnect(producer).ok() };
+                return Err(e);
+            }
+        };
+        Ring::store_sid(&ring.header().consumer_sid, doorbell);
+        ring.header().consumer_ready.store(1, Ordering::Release);
+        ring_doorbell(producer, 0);
+        Ok(Consumer { ring, doorbell, producer, lend: Some(msg) })
+    }
+    /// Takes the next frame into `buf` and returns its length, waiting for one if the ring is empty. Returns
+    /// `None` once the producer has closed the ring and every frame has been read.
+    ///
+    /// # Errors
+    ///
+    /// * **OutOfMemory**: The next frame doesn't fit in `buf`; it stays in the ring
+    /// * **InternalError**: The producer has garbled the ring
+    pub fn pop(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Error> {
+        loop {
+            let closed = self.is_closed();
+            if let Some(len) = self.try_pop(buf)? {
+                return Ok(Some(len));
+            }
+            if closed {
+                return Ok(None);
+            }
+            let header = self.ring.header();
+            header.consumer_waiting.store(1, Ordering::SeqCst);
+            // a frame may have come in between the attempt and raising the flag
+            if !self.ring.is_empty() || self.is_closed() {
+                header.consumer_waiting.store(0, Ordering::SeqCst);
+                continue;
+            }
+            xous::receive_message(self.doorbell)?;
+        }
+    }
+    /// Takes the next frame if there is one right now. Fails as `pop()` does.
+    pub fn try_pop(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Error> {
+        let len = self.ring.read(buf)?;
+        if len.is_some() && self.ring.header().producer_waiting.swap(0, Ordering::SeqCst) != 0 {
+            ring_doorbell(self.producer, 0);
+        }
+        Ok(len)
+    }
+    /// Whether the producer has closed the ring. There may still be frames in it.
+    pub fn is_closed(&self) -> bool {
+        self.ring.header().producer_closed.load(Ordering::SeqCst) != 0
+    }
+}
+impl Drop for Consumer {
+    fn drop(&mut self) {
+        self.ring.header().consumer_closed.store(1, Ordering::SeqCst);
+        ring_doorbell(self.producer, 0);
+        // gives the pages back, after which they mustn't be touched
+        self.lend.take();
+        unsafe { xous::disconnect(self.producer).ok() };
+        xous::destroy_server(self.doorbell).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ring(words: &mut Vec<u32>) -> Ring {
+        unsafe { Ring::new(words.as_mut_ptr() as *mut u8, words.len() * 4) }
+    }
+
+    #[test]
+    fn header_fits() {
+        assert!(core::mem::size_of::<Header>() <= HEADER_LEN);
+    }
+
+    #[test]
+    fn frames_wrap_around() {
+        let mut words = vec![0u32; (HEADER_LEN + 32) / 4];
+        let r = ring(&mut words);
+        let mut buf = [0u8; 16];
+        // frames of 4 + 10 bytes in a 32 byte ring go past the end every few rounds
+        for round in 0..20u8 {
+            let frame = [round; 10];
+            assert_eq!(r.write(&frame), Ok(true));
+            assert_eq!(r.write(&frame), Ok(true));
+            assert_eq!(r.write(&frame), Ok(false));
+            assert_eq!(r.read(&mut buf), Ok(Some(10)));
+            assert_eq!(&buf[..10], &frame);
+            assert_eq!(r.read(&mut buf), Ok(Some(10)));
+            assert_eq!(r.read(&mut buf), Ok(None));
+        }
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    fn frames_that_never_fit() {
+        let mut words = vec![0u32; (HEADER_LEN + 32) / 4];
+        let r = ring(&mut words);
+        assert_eq!(r.write(&[0u8; 28]), Err(Error::OutOfMemory));
+        assert_eq!(r.write(&[7u8; 27]), Ok(true));
+        // too big for the reader's buffer: it stays put until a bigger one comes along
+        let mut small = [0u8; 8];
+        assert_eq!(r.read(&mut small), Err(Error::OutOfMemory));
+        let mut big = [0u8; 32];
+        assert_eq!(r.read(&mut big), Ok(Some(27)));
+    }
+
+    #[test]
+    fn garbled_rings_are_refused() {
+        let mut words = vec![0u32; (HEADER_LEN + 32) / 4];
+        let r = ring(&mut words);
+        let mut buf = [0u8; 32];
+        r.header().head.store(32, Ordering::SeqCst);
+        assert_eq!(r.read(&mut buf), Err(Error::InternalError));
+        assert_eq!(r.write(&[0u8; 4]), Err(Error::InternalError));
+
+        // a length that claims more than was written
+        r.header().head.store(0, Ordering::SeqCst);
+        assert_eq!(r.write(&[1u8; 4]), Ok(true));
+        r.copy_in(0, &20u32.to_le_bytes());
+        assert_eq!(r.read(&mut buf), Err(Error::InternalError));
+    }
+}
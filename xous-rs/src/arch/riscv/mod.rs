@@ -154,15 +154,20 @@ pub fn process_to_args(call: usize, init: &ProcessInit) -> [usize; 8] {
 }
 
 pub fn args_to_process(
-    _a1: usize,
-    _a2: usize,
+    a1: usize,
+    a2: usize,
     _a3: usize,
     _a4: usize,
     _a5: usize,
     _a6: usize,
     _a7: usize,
 ) -> core::result::Result<ProcessInit, crate::Error> {
-    todo!()
+    let mut key = [0u8; 8];
+    key[0..4].copy_from_slice(&(a1 as u32).to_le_bytes());
+    key[4..8].copy_from_slice(&(a2 as u32).to_le_bytes());
+    Ok(ProcessInit {
+        key: ProcessKey::new(key),
+    })
 }
 
 pub fn create_thread_0_pre<U>(f: &fn() -> U) -> core::result::Result<ThreadInit, crate::Error>
@@ -329,18 +334,20 @@ where
     })
 }
 
-/// If no connection exists, create a new connection to the server. This means
-/// our parent PID will be PID1. Otherwise, reuse the same connection.
+/// There's no program to go with the name in `ProcessArgs`, so this always
+/// fails with `UnhandledSyscall`. Loaders build a process up themselves with
+/// `create_empty_process()`, `move_memory_to_process()` and `start_process()`.
 pub fn create_process_pre(_args: &ProcessArgs) -> core::result::Result<ProcessInit, crate::Error> {
-    todo!()
+    Err(crate::Error::UnhandledSyscall)
 }
 
+/// Never reached, as `create_process_pre()` always fails.
 pub fn create_process_post(
     _args: ProcessArgs,
     _init: ProcessInit,
     _pid: PID,
 ) -> core::result::Result<ProcessHandle, crate::Error> {
-    todo!()
+    Err(crate::Error::UnhandledSyscall)
 }
 
 pub fn create_thread_n_pre(
@@ -537,8 +537,26 @@ impl Message {
             &Message::Scalar(s) | &Message::BlockingScalar(s) => s.id,
         }
     }
+
+    /// The ID of a `MutableBorrow` that was lent with `share_memory()`, with
+    /// `SHARED_MESSAGE_FLAG` taken off, or `None` for any other message.
+    pub fn shared_id(&self) -> Option<usize> {
+        match self {
+            Message::MutableBorrow(mem) if mem.id & SHARED_MESSAGE_FLAG != 0 => {
+                Some(mem.id & !SHARED_MESSAGE_FLAG)
+            }
+            _ => None,
+        }
+    }
 }
 
+/// Set by the kernel in the ID of a `MutableBorrow` lent with `share_memory()`,
+/// whose sender keeps the memory mapped and can change it at any time, and
+/// cleared from the ID of every other one. A server that isn't expecting
+/// shared memory sees an ID it doesn't know and turns the message away; one
+/// that is asks for it with `Message::shared_id()`.
+pub const SHARED_MESSAGE_FLAG: usize = 1 << (usize::BITS - 1);
+
 impl TryFrom<(usize, usize, usize, usize, usize, usize)> for Message {
     type Error = ();
     fn try_from(
@@ -751,6 +769,223 @@ impl core::fmt::Display for MemoryType {
     }
 }
 
+/// How urgently the kernel hands a process the CPU. Whenever a process of a
+/// higher class is ready to run, it runs before any of a lower class.
+/// Processes of the same class take turns.
+#[repr(usize)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub enum Priority {
+    /// Maintenance work that can wait, such as PDDB compaction
+    Background = 0,
+
+    /// What every process starts out as
+    Normal = 1,
+
+    /// Work that has to keep up with hardware, such as the bottom halves of
+    /// audio and USB interrupt handlers. Only processes that own an
+    /// interrupt may run at this class.
+    Realtime = 2,
+}
+
+impl Priority {
+    pub fn from_usize(arg: usize) -> Option<Self> {
+        match arg {
+            0 => Some(Priority::Background),
+            1 => Some(Priority::Normal),
+            2 => Some(Priority::Realtime),
+            _ => None,
+        }
+    }
+}
+
+/// Words of stack saved in a `CrashRecord`, starting at the stack pointer
+pub const CRASH_STACK_WORDS: usize = 32;
+/// Length of a `CrashRecord` in words, which is how the kernel hands it out
+pub const CRASH_RECORD_WORDS: usize = 4 + 31 + CRASH_STACK_WORDS;
+
+/// What the kernel saves about the last fault that took down a process, for
+/// a crash report to be written once the process is gone
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CrashRecord {
+    pub pid: usize,
+    /// The architecture's code for the fault, which is `scause` on RISC-V
+    pub cause: usize,
+    pub pc: usize,
+    /// The address that faulted, or whatever else the architecture reports
+    /// about the fault, which is `stval` on RISC-V
+    pub detail: usize,
+    /// The registers of the faulting thread, `x1` through `x31` on RISC-V
+    pub registers: [usize; 31],
+    /// The top of the faulting thread's stack; words that couldn't be read
+    /// are 0
+    pub stack: [usize; CRASH_STACK_WORDS],
+}
+
+impl CrashRecord {
+    pub fn to_words(&self) -> [usize; CRASH_RECORD_WORDS] {
+        let mut words = [0usize; CRASH_RECORD_WORDS];
+        words[0] = self.pid;
+        words[1] = self.cause;
+        words[2] = self.pc;
+        words[3] = self.detail;
+        words[4..35].copy_from_slice(&self.registers);
+        words[35..].copy_from_slice(&self.stack);
+        words
+    }
+
+    pub fn from_words(words: &[usize; CRASH_RECORD_WORDS]) -> Self {
+        let mut record = CrashRecord {
+            pid: words[0],
+            cause: words[1],
+            pc: words[2],
+            detail: words[3],
+            registers: [0; 31],
+            stack: [0; CRASH_STACK_WORDS],
+        };
+        record.registers.copy_from_slice(&words[4..35]);
+        record.stack.copy_from_slice(&words[35..]);
+        record
+    }
+}
+
+/// How much memory a process is using, as seen by the kernel
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct MemoryStats {
+    /// Bytes the heap has been grown by
+    pub heap_used: usize,
+    /// Bytes the heap may grow to
+    pub heap_limit: usize,
+    /// Pages of physical memory the process owns, heap or otherwise
+    pub pages: usize,
+    /// The most pages the process has owned at any one time
+    pub peak_pages: usize,
+}
+pub const MEMORY_STATS_WORDS: usize = 4;
+
+/// What any one process is using, as seen by the kernel, for keeping an eye
+/// on processes other than the caller
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct ProcessStats {
+    /// Bytes the heap has been grown by
+    pub heap_used: usize,
+    /// Pages of physical memory the process owns, heap or otherwise
+    pub pages: usize,
+    /// Messages waiting in the queues of all of the servers it owns
+    pub queued: usize,
+}
+pub const PROCESS_STATS_WORDS: usize = 3;
+
+/// Buckets in each scheduler latency histogram. Bucket 0 counts wakeups
+/// that got the CPU within `1 << SCHED_LATENCY_BASE_LOG2` cycles, each bucket
+/// after it covers twice the span of the one before, and the last also
+/// counts everything slower.
+pub const SCHED_LATENCY_BUCKETS: usize = 16;
+pub const SCHED_LATENCY_BASE_LOG2: usize = 10;
+
+/// How many IPC events the kernel keeps while tracing is on; once it's
+/// full, the oldest are overwritten
+pub const IPC_TRACE_EVENTS: usize = 128;
+pub const IPC_TRACE_EVENT_WORDS: usize = 5;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum IpcTraceKind {
+    /// A client sent a message
+    Send = 0,
+    /// A server thread was handed the message
+    Receive = 1,
+    /// A server answered a blocking message
+    Return = 2,
+}
+
+/// One step of a message through the kernel, as recorded by the IPC trace
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct IpcTraceEvent {
+    /// When it happened: CPU cycles since reset on hardware, or nanoseconds
+    /// since the epoch in hosted mode
+    pub timestamp: u64,
+    pub kind: IpcTraceKind,
+    /// Whether the client waits for an answer
+    pub blocking: bool,
+    /// The process that sent the message, and that gets any answer
+    pub client: u8,
+    /// The process the server belongs to
+    pub server: u8,
+    /// The server's slot in the kernel, which tells apart the servers of one
+    /// process
+    pub server_index: u8,
+    /// The message's opcode; 0 for a `Return`
+    pub id: usize,
+    /// Bytes of memory that went with the message; 0 for scalars
+    pub size: usize,
+}
+
+impl IpcTraceEvent {
+    pub fn to_words(&self) -> [usize; IPC_TRACE_EVENT_WORDS] {
+        [
+            self.timestamp as u32 as usize,
+            (self.timestamp >> 32) as u32 as usize,
+            self.kind as usize
+                | (self.blocking as usize) << 2
+                | (self.client as usize) << 8
+                | (self.server as usize) << 16
+                | (self.server_index as usize) << 24,
+            self.id,
+            self.size,
+        ]
+    }
+
+    pub fn from_words(words: &[usize; IPC_TRACE_EVENT_WORDS]) -> Self {
+        IpcTraceEvent {
+            timestamp: words[0] as u64 | (words[1] as u64) << 32,
+            kind: match words[2] & 3 {
+                0 => IpcTraceKind::Send,
+                1 => IpcTraceKind::Receive,
+                _ => IpcTraceKind::Return,
+            },
+            blocking: words[2] & 4 != 0,
+            client: (words[2] >> 8) as u8,
+            server: (words[2] >> 16) as u8,
+            server_index: (words[2] >> 24) as u8,
+            id: words[3],
+            size: words[4],
+        }
+    }
+}
+
+/// How many samples the kernel's profiler keeps while it's on; once it's
+/// full, the oldest are overwritten. At the 20ms preemption tick that's over
+/// a minute of samples.
+pub const PROFILE_SAMPLES: usize = 4096;
+pub const PROFILE_SAMPLE_WORDS: usize = 3;
+
+/// Where the CPU was when an interrupt came in, as sampled by the profiler
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ProfileSample {
+    /// The process that was running. PID 1 is the kernel, which includes the
+    /// time spent idle.
+    pub pid: u8,
+    /// The address of the instruction that was interrupted
+    pub pc: usize,
+    /// The return address register. In a function that doesn't call any
+    /// other this points into its caller; elsewhere it may be stale.
+    pub ra: usize,
+}
+
+impl ProfileSample {
+    pub fn to_words(&self) -> [usize; PROFILE_SAMPLE_WORDS] {
+        [self.pid as usize, self.pc, self.ra]
+    }
+
+    pub fn from_words(words: &[usize; PROFILE_SAMPLE_WORDS]) -> Self {
+        ProfileSample {
+            pid: words[0] as u8,
+            pc: words[1],
+            ra: words[2],
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, PartialEq)]
 pub enum Result {
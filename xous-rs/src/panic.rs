@@ -0,0 +1,123 @@
+//! Panic reports for the log server. A report is a `1000` scalar naming the
+//! start of a panic, the text in chunks of up to four words sent as
+//! `1100 + length` scalars, and a `1200` scalar once it's all there. The
+//! messages are sent with `try_send_message()`, so a report can be made from
+//! anywhere, even an interrupt handler, but a chunk that doesn't fit in the
+//! log server's queue is lost.
+
+use crate::{try_connect, try_send_message, Message, CID, SID};
+
+/// Most frames `backtrace()` looks through
+pub const BACKTRACE_DEPTH: usize = 16;
+
+/// A frame bigger than this is taken to mean the chain of frame pointers is broken
+const MAX_FRAME_LEN: usize = 64 * 1024;
+
+/// Writes a panic report to the log server; see the module documentation.
+/// The report is finished when the writer is dropped.
+pub struct PanicWriter {
+    conn: CID,
+}
+
+impl PanicWriter {
+    /// Start a panic report, or return `None` if there's no log server to
+    /// send it to. If this process is already connected to the log server,
+    /// the kernel reuses that connection.
+    pub fn new() -> Option<PanicWriter> {
+        let conn = try_connect(SID::from_bytes(b"xous-log-server ").unwrap()).ok()?;
+        try_send_message(conn, Message::new_scalar(1000, 0, 0, 0, 0)).ok();
+        Some(PanicWriter { conn })
+    }
+
+    // Group `usize` bytes into a `usize` and return it, beginning
+    // from `offset` * sizeof(usize) bytes from the start. For example,
+    // `group_or_null([1,2,3,4,5,6,7,8], 1)` on a 32-bit system will
+    // return a usize with 5678 packed into it.
+    fn group_or_null(data: &[u8], offset: usize) -> usize {
+        let start = offset * core::mem::size_of::<usize>();
+        let mut out_array = [0u8; core::mem::size_of::<usize>()];
+        for (i, byte) in out_array.iter_mut().enumerate() {
+            *byte = data.get(start + i).copied().unwrap_or(0);
+        }
+        usize::from_le_bytes(out_array)
+    }
+
+    /// Write the return addresses found by `backtrace()`, one per line. The
+    /// addresses can be turned into function names on a host with the
+    /// `symbolize` tool, given the ELF file of the program that panicked.
+    pub fn write_backtrace(&mut self) {
+        use core::fmt::Write;
+        let mut frames = [0usize; BACKTRACE_DEPTH];
+        let count = backtrace(&mut frames);
+        if count == 0 {
+            return;
+        }
+        writeln!(self, "backtrace:").ok();
+        for (i, frame) in frames[..count].iter().enumerate() {
+            writeln!(self, "{:>4}: {:08x}", i, frame).ok();
+        }
+    }
+}
+
+impl core::fmt::Write for PanicWriter {
+    fn write_str(&mut self, s: &str) -> core::result::Result<(), core::fmt::Error> {
+        for c in s.as_bytes().chunks(core::mem::size_of::<usize>() * 4) {
+            // Text is grouped into 4x `usize` words. The id is 1100 plus
+            // the number of characters in this message.
+            let panic_msg = Message::new_scalar(
+                1100 + c.len(),
+                Self::group_or_null(c, 0),
+                Self::group_or_null(c, 1),
+                Self::group_or_null(c, 2),
+                Self::group_or_null(c, 3),
+            );
+            try_send_message(self.conn, panic_msg).ok();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PanicWriter {
+    fn drop(&mut self) {
+        try_send_message(self.conn, Message::new_scalar(1200, 0, 0, 0, 0)).ok();
+    }
+}
+
+/// Fill `frames` with the return addresses of the calls that led here,
+/// innermost first, and return how many were found. This follows the chain of
+/// frame pointers, so it only finds anything in programs built with
+/// `-C force-frame-pointers=yes`, and it stops early at a frame that doesn't
+/// look right.
+#[cfg(all(any(target_os = "none", target_os = "xous"), target_arch = "riscv32"))]
+pub fn backtrace(frames: &mut [usize]) -> usize {
+    let word = core::mem::size_of::<usize>();
+    let mut fp: usize;
+    unsafe { core::arch::asm!("mv {}, s0", out(reg) fp) };
+    let mut count = 0;
+    while count < frames.len() {
+        if fp < 2 * word || fp % word != 0 {
+            break;
+        }
+        // The return address and the caller's frame pointer are saved just
+        // below where the frame pointer points
+        let ra = unsafe { ((fp - word) as *const usize).read_volatile() };
+        let next = unsafe { ((fp - 2 * word) as *const usize).read_volatile() };
+        if ra == 0 {
+            break;
+        }
+        frames[count] = ra;
+        count += 1;
+        // The stack grows down, so a caller's frame is always above its callee's
+        if next <= fp || next - fp > MAX_FRAME_LEN {
+            break;
+        }
+        fp = next;
+    }
+    count
+}
+
+/// Backtraces are only taken on RISC-V; elsewhere there are no frames to report.
+#[cfg(not(all(any(target_os = "none", target_os = "xous"), target_arch = "riscv32")))]
+pub fn backtrace(_frames: &mut [usize]) -> usize {
+    0
+}
@@ -1,7 +1,10 @@
 use crate::{
-    pid_from_usize, CpuID, Error, MemoryAddress, MemoryFlags, MemoryMessage, MemoryRange,
-    MemorySize, MemoryType, Message, MessageEnvelope, MessageSender, ProcessArgs, ProcessInit,
-    Result, ScalarMessage, SysCallResult, ThreadInit, CID, PID, SID, TID,
+    pid_from_usize, CpuID, CrashRecord, Error, MemoryAddress, MemoryFlags, MemoryMessage,
+    MemoryRange, MemorySize, MemoryType, Message, MessageEnvelope, MessageSender, Priority,
+    ProcessArgs, ProcessInit, Result, ScalarMessage, SysCallResult, ThreadInit, CID,
+    CRASH_RECORD_WORDS, IPC_TRACE_EVENTS, IPC_TRACE_EVENT_WORDS, IpcTraceEvent, MemoryStats,
+    MEMORY_STATS_WORDS, PID, ProcessStats, PROCESS_STATS_WORDS, ProfileSample, PROFILE_SAMPLES,
+    PROFILE_SAMPLE_WORDS, SCHED_LATENCY_BUCKETS, SID, TID,
 };
 use core::convert::{TryFrom, TryInto};
 /* https://github.com/betrusted-io/xous-core/issues/90
@@ -425,11 +428,368 @@ pub enum SysCall {
         usize, /* stack pointer */
     ),
 
+    /// Block until a message is queued for any of the given servers, all of
+    /// which must belong to this process. The servers are named by the
+    /// connections this process holds to them, since that's all that fits.
+    /// The message is left in the queue, to be picked up with
+    /// `TryReceiveMessage`.
+    ///
+    /// # Returns
+    ///
+    /// * **Ok**: At least one of the servers has had a message queued
+    ///
+    /// # Errors
+    ///
+    /// * **ServerNotFound**: One of the connections doesn't lead to a server
+    ///                       this process owns
+    /// * **InvalidSyscall**: No servers were given, or more than fit
+    /// * **BlockedProcess**: When running in Hosted mode, this indicates that this
+    ///                       thread is blocking.
+    WaitMessage(usize /* count */, [CID; MAX_WAIT_SERVERS]),
+
+    /// Get the number of CPU cycles the given process has spent running, or
+    /// with `None`, the number of cycles the CPU has run since reset. Always
+    /// 0 in hosted mode.
+    ///
+    /// # Returns
+    ///
+    /// * **Scalar2**: The lower and upper 32 bits of the cycle count
+    ///
+    /// # Errors
+    ///
+    /// * **ProcessNotFound**: The given process doesn't exist
+    GetCpuTime(Option<PID>),
+
+    /// Get the number of CPU cycles the given thread has spent running.
+    /// Always 0 in hosted mode.
+    ///
+    /// # Returns
+    ///
+    /// * **Scalar2**: The lower and upper 32 bits of the cycle count
+    ///
+    /// # Errors
+    ///
+    /// * **ProcessNotFound**: The given process doesn't exist
+    /// * **ThreadNotAvailable**: The thread ID is out of range
+    GetThreadCpuTime(PID, TID),
+
+    /// Set the scheduling priority of this process or of one of its children.
+    ///
+    /// # Errors
+    ///
+    /// * **ProcessNotFound**: The given process doesn't exist
+    /// * **ProcessNotChild**: The given process is neither this process nor
+    ///                        one of its children
+    /// * **AccessDenied**: `Priority::Realtime` was asked for a process that
+    ///                     doesn't own an interrupt
+    SetPriority(PID, Priority),
+
+    /// Read one word of the record the kernel keeps of the last process
+    /// fault, laid out as in `CrashRecord::to_words()`. The first process to
+    /// make this call becomes the only one allowed to, as the record holds
+    /// another process' registers and stack.
+    ///
+    /// # Returns
+    ///
+    /// * **Scalar2**: The number of faults recorded since boot, which is 0 if
+    ///                there is no record yet, and the word asked for
+    ///
+    /// # Errors
+    ///
+    /// * **AccessDenied**: Another process reads the crash records
+    /// * **InvalidSyscall**: The index is past the end of the record
+    GetCrashRecord(usize /* index */),
+
+    /// Lend memory to a server mutably, as with a `MutableBorrow` message,
+    /// except that the memory stays mapped in this process while it's lent.
+    /// The server sees a `MutableBorrow` with `SHARED_MESSAGE_FLAG` set in its
+    /// ID, so only servers that check `Message::shared_id()` take it. Both
+    /// processes can then read and write the same pages until the server
+    /// returns them, so they must agree between themselves on who touches
+    /// what, and when.
+    ///
+    /// # Returns
+    ///
+    /// * **MemoryReturned**: The server returned the memory
+    ///
+    /// # Errors
+    ///
+    /// * **ServerNotFound**: The server does not exist
+    /// * **ShareViolation**: The memory is already lent
+    /// * **BadAlignment**: The memory isn't made up of whole pages
+    /// * **UnhandledSyscall**: In hosted mode, where processes can't share memory
+    ShareMemory(CID, MemoryMessage),
+
+    /// Move memory out of this process and into a child that was created
+    /// with `CreateProcess` and hasn't been started, at the given address.
+    /// The pages keep their flags, so a loader maps them with every flag it
+    /// needs, fills them in, strips what the program shouldn't have, and
+    /// then moves them.
+    ///
+    /// # Errors
+    ///
+    /// * **ProcessNotFound**: The process doesn't exist
+    /// * **ProcessNotChild**: The process isn't a child of this one
+    /// * **AccessDenied**: The process has already been started
+    /// * **BadAlignment**: The memory or the address isn't made up of whole pages
+    /// * **BadAddress**: The address is outside of the child's user area
+    /// * **MemoryInUse**: Something is already mapped there in the child
+    /// * **ShareViolation**: The memory is lent out
+    /// * **UnhandledSyscall**: In hosted mode, where processes can't be loaded
+    MoveMemoryToProcess(PID, MemoryRange, MemoryAddress /* destination */),
+
+    /// Start the first thread of a child that was created with
+    /// `CreateProcess`, at the given entrypoint. It gets a stack of its own,
+    /// and its PID as its first argument.
+    ///
+    /// # Errors
+    ///
+    /// * **ProcessNotFound**: The process doesn't exist
+    /// * **ProcessNotChild**: The process isn't a child of this one
+    /// * **AccessDenied**: The process has already been started
+    /// * **UnhandledSyscall**: In hosted mode, where processes can't be loaded
+    StartProcess(PID, usize /* entrypoint */),
+
+    /// Stop a child of this process and free everything it had, as though
+    /// it had terminated itself. Its servers go away, and connections to
+    /// them stop working.
+    ///
+    /// # Errors
+    ///
+    /// * **ProcessNotFound**: The process doesn't exist
+    /// * **ProcessNotChild**: The process isn't a child of this one
+    /// * **UnhandledSyscall**: In hosted mode, where processes can't be loaded
+    DestroyProcess(PID),
+
+    /// Read four bytes of the name a process was given when the image was
+    /// built, so servers can tell who is connecting to them. Processes that
+    /// were loaded at runtime have no name.
+    ///
+    /// # Returns
+    ///
+    /// * **Scalar2**: The length of the name in bytes, which is 0 if the
+    ///                process has no name, and the bytes at `index * 4` in
+    ///                little-endian order, padded with zeroes
+    ///
+    /// # Errors
+    ///
+    /// * **ProcessNotFound**: The process doesn't exist
+    /// * **InvalidSyscall**: The index is past the end of the name
+    /// * **UnhandledSyscall**: In hosted mode, where processes have no names
+    GetProcessName(PID, usize /* index */),
+
+    /// Turn the kernel's record of message sends, receives and returns on
+    /// or off. Turning it on starts a fresh trace. The first process to make
+    /// this call or `GetIpcTrace` becomes the only one allowed to, as the
+    /// trace shows who talks to whom.
+    ///
+    /// # Errors
+    ///
+    /// * **AccessDenied**: Another process controls the trace
+    SetIpcTrace(bool),
+
+    /// Read one word of the IPC trace, counting from the oldest event kept,
+    /// with each event laid out as in `IpcTraceEvent::to_words()`. Turn the
+    /// trace off first, or the events will move while they're read.
+    ///
+    /// # Returns
+    ///
+    /// * **Scalar2**: The number of events recorded since the trace was
+    ///                turned on, of which the last `IPC_TRACE_EVENTS` are
+    ///                kept, and the word asked for
+    ///
+    /// # Errors
+    ///
+    /// * **AccessDenied**: Another process controls the trace
+    /// * **InvalidSyscall**: The index is past the end of the trace
+    GetIpcTrace(usize /* index */),
+
+    /// Read one of the calling process' memory statistics, in the order of
+    /// the fields of `MemoryStats`. Page counts are always 0 in hosted mode.
+    ///
+    /// # Returns
+    ///
+    /// * **Scalar1**: The statistic asked for
+    ///
+    /// # Errors
+    ///
+    /// * **InvalidSyscall**: The index is past the last statistic
+    GetMemoryStats(usize /* index */),
+
+    /// Ask to be told when the server behind a connection is destroyed,
+    /// whether it shut down, its process exited, or it's being restarted.
+    /// The kernel then sends a non-blocking scalar message with id `opcode`
+    /// and the connection in `arg1` to `SID`, a server of the calling
+    /// process, from PID 1. Asking again for the same connection replaces
+    /// the earlier request.
+    ///
+    /// # Errors
+    ///
+    /// * **ServerNotFound**: The connection doesn't lead to a server, or
+    ///                       `SID` isn't a server of this process
+    /// * **OutOfMemory**: The kernel can't keep track of any more requests
+    NotifyServerExit(CID, SID /* notify */, usize /* opcode */),
+
+    /// Lend up to `MAX_LEND_SEGMENTS` separate ranges to a server in one
+    /// message, such as a header and the buffer it describes, without
+    /// copying them together first. The server sees an ordinary `Borrow` or
+    /// `MutableBorrow` of a single buffer, with the ranges laid out one after
+    /// another in the order given, and returns it the usual way. Each range
+    /// must be made up of whole pages, and there may be no gaps in the list.
+    ///
+    /// # Returns
+    ///
+    /// * **MemoryReturned**: The server returned the memory
+    ///
+    /// # Errors
+    ///
+    /// * **ServerNotFound**: The server does not exist
+    /// * **BadAddress**: No ranges were given, or the server belongs to this
+    ///                   process
+    /// * **OutOfMemory**: Too many lends like this one are already out
+    /// * **UnhandledSyscall**: In hosted mode, where memory is copied anyway
+    LendVectored(
+        CID,
+        usize, /* id */
+        bool,  /* mutable */
+        [Option<MemoryRange>; MAX_LEND_SEGMENTS],
+    ),
+
+    /// Put the calling thread to sleep until another thread of the same
+    /// process calls `WakeAddress` on the word at the given address, unless
+    /// that word no longer holds the expected value, in which case return
+    /// right away. Checking the word and going to sleep happen together, so
+    /// a wake that comes after the caller last looked at the word is never
+    /// missed.
+    ///
+    /// # Returns
+    ///
+    /// * **Ok**: The thread was woken, or the word had already changed
+    ///
+    /// # Errors
+    ///
+    /// * **BadAlignment**: The address isn't aligned to a word
+    /// * **BadAddress**: The address isn't mapped in this process
+    /// * **UnhandledSyscall**: In hosted mode
+    WaitAddress(usize /* address */, usize /* expected */),
+
+    /// Wake up to `count` threads of the calling process that are asleep in
+    /// `WaitAddress` on the given address. Which of them are woken, if there
+    /// are more than `count`, is not specified.
+    ///
+    /// # Returns
+    ///
+    /// * **Scalar1**: The number of threads that were woken
+    ///
+    /// # Errors
+    ///
+    /// * **UnhandledSyscall**: In hosted mode
+    WakeAddress(usize /* address */, usize /* count */),
+
+    /// Read one bucket of the scheduler latency histogram for a priority
+    /// class: how many times a process of that class that had been asleep
+    /// was woken and then got the CPU within the bucket's span of cycles, see
+    /// `SCHED_LATENCY_BUCKETS`. The histogram only exists in kernels built
+    /// with the `sched-latency` feature.
+    ///
+    /// # Returns
+    ///
+    /// * **Scalar1**: The count in the bucket
+    ///
+    /// # Errors
+    ///
+    /// * **InvalidSyscall**: The bucket is past the last one
+    /// * **UnhandledSyscall**: The kernel doesn't keep the histogram
+    GetSchedLatency(Priority, usize /* bucket */),
+
+    /// Count the messages waiting in a server's queue that haven't been
+    /// received yet. A server can use this to take several messages at once,
+    /// and a client that knows the SID can use it to hold off on sending more
+    /// work to a server that's falling behind.
+    ///
+    /// # Returns
+    ///
+    /// * **Scalar2**: The number of messages waiting, and how many the queue can hold
+    ///
+    /// # Errors
+    ///
+    /// * **ServerNotFound**: No server has the given SID
+    GetQueueDepth(SID),
+
+    /// Read one of the statistics of any process, in the order of the fields
+    /// of `ProcessStats`. Page counts are always 0 in hosted mode.
+    ///
+    /// # Returns
+    ///
+    /// * **Scalar1**: The statistic asked for
+    ///
+    /// # Errors
+    ///
+    /// * **ProcessNotFound**: There's no process with the given PID
+    /// * **InvalidSyscall**: The index is past the last statistic
+    GetProcessStats(PID, usize /* index */),
+
+    /// Turn the kernel's sampling profiler on or off. While it's on, each
+    /// interrupt records which process was running and where; see
+    /// `ProfileSample`. Turning it on starts a fresh profile. The profiler
+    /// only exists in kernels built with the `profile` feature. The first
+    /// process to call this or `GetProfile` is the only one that may.
+    ///
+    /// # Errors
+    ///
+    /// * **AccessDenied**: Another process has already claimed the profiler
+    /// * **UnhandledSyscall**: The kernel doesn't have the profiler
+    SetProfile(bool),
+
+    /// Read one word of the profile, counting from the oldest sample kept,
+    /// with each sample laid out as in `ProfileSample::to_words()`. Turn the
+    /// profiler off first, or the samples will move while they're read.
+    ///
+    /// # Returns
+    ///
+    /// * **Scalar2**: The number of samples taken since the profiler was
+    ///                turned on, of which the last `PROFILE_SAMPLES` are
+    ///                kept, and the word asked for
+    ///
+    /// # Errors
+    ///
+    /// * **AccessDenied**: Another process has already claimed the profiler
+    /// * **InvalidSyscall**: The index is past the end of the profile
+    /// * **UnhandledSyscall**: The kernel doesn't have the profiler
+    GetProfile(usize /* index */),
+
     /// This syscall does not exist. It captures all possible
     /// arguments so detailed analysis can be performed.
     Invalid(usize, usize, usize, usize, usize, usize, usize),
 }
 
+/// The most servers a single `WaitMessage` call can wait on
+pub const MAX_WAIT_SERVERS: usize = 6;
+
+/// The most ranges a single `LendVectored` call can lend
+pub const MAX_LEND_SEGMENTS: usize = 4;
+
+/// Largest range `LendVectored` can take, which keeps each one to a word
+const MAX_LEND_SEGMENT_PAGES: usize = 4096;
+
+/// A range packed into one word: its address, and its length in pages
+/// less one in the low bits, which are free since it starts on a page
+fn pack_segment(segment: &Option<MemoryRange>) -> usize {
+    segment
+        .map(|r| r.as_ptr() as usize | (r.len() / 4096 - 1))
+        .unwrap_or(0)
+}
+
+fn unpack_segment(word: usize) -> core::result::Result<Option<MemoryRange>, Error> {
+    if word == 0 {
+        return Ok(None);
+    }
+    if word & !0xfff == 0 {
+        return Err(Error::BadAddress);
+    }
+    unsafe { MemoryRange::new(word & !0xfff, ((word & 0xfff) + 1) * 4096) }.map(Some)
+}
+
 // #[derive(FromPrimitive)]
 pub enum SysCallNumber {
     MapMemory = 2,
@@ -468,6 +828,28 @@ pub enum SysCallNumber {
     Disconnect = 35,
     JoinThread = 36,
     SetExceptionHandler = 37,
+    WaitMessage = 38,
+    GetCpuTime = 39,
+    SetPriority = 40,
+    GetCrashRecord = 41,
+    ShareMemory = 42,
+    MoveMemoryToProcess = 43,
+    StartProcess = 44,
+    DestroyProcess = 45,
+    GetProcessName = 46,
+    SetIpcTrace = 47,
+    GetIpcTrace = 48,
+    GetMemoryStats = 49,
+    NotifyServerExit = 50,
+    LendVectored = 51,
+    WaitAddress = 52,
+    WakeAddress = 53,
+    GetSchedLatency = 54,
+    GetQueueDepth = 55,
+    GetProcessStats = 56,
+    SetProfile = 57,
+    GetProfile = 58,
+    GetThreadCpuTime = 59,
     Invalid,
 }
 
@@ -511,6 +893,28 @@ impl SysCallNumber {
             35 => Disconnect,
             36 => JoinThread,
             37 => SetExceptionHandler,
+            38 => WaitMessage,
+            39 => GetCpuTime,
+            40 => SetPriority,
+            41 => GetCrashRecord,
+            42 => ShareMemory,
+            43 => MoveMemoryToProcess,
+            44 => StartProcess,
+            45 => DestroyProcess,
+            46 => GetProcessName,
+            47 => SetIpcTrace,
+            48 => GetIpcTrace,
+            49 => GetMemoryStats,
+            50 => NotifyServerExit,
+            51 => LendVectored,
+            52 => WaitAddress,
+            53 => WakeAddress,
+            54 => GetSchedLatency,
+            55 => GetQueueDepth,
+            56 => GetProcessStats,
+            57 => SetProfile,
+            58 => GetProfile,
+            59 => GetThreadCpuTime,
             _ => Invalid,
         }
     }
@@ -852,6 +1256,232 @@ impl SysCall {
                 0,
                 0,
             ],
+            SysCall::WaitMessage(count, cids) => [
+                SysCallNumber::WaitMessage as usize,
+                *count,
+                cids[0] as usize,
+                cids[1] as usize,
+                cids[2] as usize,
+                cids[3] as usize,
+                cids[4] as usize,
+                cids[5] as usize,
+            ],
+            SysCall::GetCpuTime(pid) => [
+                SysCallNumber::GetCpuTime as usize,
+                pid.map(|p| p.get() as usize).unwrap_or(0),
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::SetPriority(pid, priority) => [
+                SysCallNumber::SetPriority as usize,
+                pid.get() as usize,
+                *priority as usize,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::GetCrashRecord(index) => [
+                SysCallNumber::GetCrashRecord as usize,
+                *index,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::ShareMemory(cid, mm) => [
+                SysCallNumber::ShareMemory as usize,
+                *cid as usize,
+                mm.id as usize,
+                mm.buf.as_ptr() as usize,
+                mm.buf.len(),
+                mm.offset.map(|x| x.get()).unwrap_or(0) as usize,
+                mm.valid.map(|x| x.get()).unwrap_or(0) as usize,
+                0,
+            ],
+            SysCall::MoveMemoryToProcess(pid, range, dest) => [
+                SysCallNumber::MoveMemoryToProcess as usize,
+                pid.get() as usize,
+                range.as_ptr() as usize,
+                range.len(),
+                dest.get(),
+                0,
+                0,
+                0,
+            ],
+            SysCall::StartProcess(pid, entrypoint) => [
+                SysCallNumber::StartProcess as usize,
+                pid.get() as usize,
+                *entrypoint,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::DestroyProcess(pid) => [
+                SysCallNumber::DestroyProcess as usize,
+                pid.get() as usize,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::GetProcessName(pid, index) => [
+                SysCallNumber::GetProcessName as usize,
+                pid.get() as usize,
+                *index,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::SetIpcTrace(enable) => [
+                SysCallNumber::SetIpcTrace as usize,
+                *enable as usize,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::GetIpcTrace(index) => [
+                SysCallNumber::GetIpcTrace as usize,
+                *index,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::GetMemoryStats(index) => [
+                SysCallNumber::GetMemoryStats as usize,
+                *index,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::NotifyServerExit(cid, notify, opcode) => {
+                let s = notify.to_u32();
+                [
+                    SysCallNumber::NotifyServerExit as usize,
+                    *cid as usize,
+                    s.0 as _,
+                    s.1 as _,
+                    s.2 as _,
+                    s.3 as _,
+                    *opcode,
+                    0,
+                ]
+            }
+            SysCall::LendVectored(cid, id, mutable, segments) => [
+                SysCallNumber::LendVectored as usize,
+                *cid as usize,
+                *id,
+                *mutable as usize,
+                pack_segment(&segments[0]),
+                pack_segment(&segments[1]),
+                pack_segment(&segments[2]),
+                pack_segment(&segments[3]),
+            ],
+            SysCall::WaitAddress(address, expected) => [
+                SysCallNumber::WaitAddress as usize,
+                *address,
+                *expected,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::WakeAddress(address, count) => [
+                SysCallNumber::WakeAddress as usize,
+                *address,
+                *count,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::GetProcessStats(pid, index) => [
+                SysCallNumber::GetProcessStats as usize,
+                pid.get() as usize,
+                *index,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::GetQueueDepth(sid) => {
+                let s = sid.to_u32();
+                [
+                    SysCallNumber::GetQueueDepth as usize,
+                    s.0 as _,
+                    s.1 as _,
+                    s.2 as _,
+                    s.3 as _,
+                    0,
+                    0,
+                    0,
+                ]
+            }
+            SysCall::GetSchedLatency(priority, bucket) => [
+                SysCallNumber::GetSchedLatency as usize,
+                *priority as usize,
+                *bucket,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::SetProfile(enable) => [
+                SysCallNumber::SetProfile as usize,
+                *enable as usize,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::GetProfile(index) => [
+                SysCallNumber::GetProfile as usize,
+                *index,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            SysCall::GetThreadCpuTime(pid, tid) => [
+                SysCallNumber::GetThreadCpuTime as usize,
+                pid.get() as usize,
+                *tid,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
             SysCall::Invalid(a1, a2, a3, a4, a5, a6, a7) => [
                 SysCallNumber::Invalid as usize,
                 *a1,
@@ -1015,6 +1645,67 @@ impl SysCall {
             SysCallNumber::Disconnect => SysCall::Disconnect(a1 as _),
             SysCallNumber::JoinThread => SysCall::JoinThread(a1 as _),
             SysCallNumber::SetExceptionHandler => SysCall::SetExceptionHandler(a1 as _, a2 as _),
+            SysCallNumber::WaitMessage => SysCall::WaitMessage(
+                a1,
+                [a2 as _, a3 as _, a4 as _, a5 as _, a6 as _, a7 as _],
+            ),
+            SysCallNumber::GetCpuTime => SysCall::GetCpuTime(PID::new(a1 as _)),
+            SysCallNumber::SetPriority => SysCall::SetPriority(
+                pid_from_usize(a1)?,
+                Priority::from_usize(a2).ok_or(Error::InvalidSyscall)?,
+            ),
+            SysCallNumber::GetCrashRecord => SysCall::GetCrashRecord(a1),
+            SysCallNumber::ShareMemory => SysCall::ShareMemory(
+                a1 as u32,
+                MemoryMessage {
+                    id: a2,
+                    buf: unsafe { MemoryRange::new(a3, a4) }?,
+                    offset: MemoryAddress::new(a5),
+                    valid: MemorySize::new(a6),
+                },
+            ),
+            SysCallNumber::MoveMemoryToProcess => SysCall::MoveMemoryToProcess(
+                pid_from_usize(a1)?,
+                unsafe { MemoryRange::new(a2, a3) }?,
+                MemoryAddress::new(a4).ok_or(Error::InvalidSyscall)?,
+            ),
+            SysCallNumber::StartProcess => SysCall::StartProcess(pid_from_usize(a1)?, a2),
+            SysCallNumber::DestroyProcess => SysCall::DestroyProcess(pid_from_usize(a1)?),
+            SysCallNumber::GetProcessName => SysCall::GetProcessName(pid_from_usize(a1)?, a2),
+            SysCallNumber::SetIpcTrace => SysCall::SetIpcTrace(a1 != 0),
+            SysCallNumber::GetIpcTrace => SysCall::GetIpcTrace(a1),
+            SysCallNumber::GetMemoryStats => SysCall::GetMemoryStats(a1),
+            SysCallNumber::NotifyServerExit => SysCall::NotifyServerExit(
+                a1 as _,
+                SID::from_u32(a2 as _, a3 as _, a4 as _, a5 as _),
+                a6,
+            ),
+            SysCallNumber::LendVectored => SysCall::LendVectored(
+                a1 as _,
+                a2,
+                a3 != 0,
+                [
+                    unpack_segment(a4)?,
+                    unpack_segment(a5)?,
+                    unpack_segment(a6)?,
+                    unpack_segment(a7)?,
+                ],
+            ),
+            SysCallNumber::WaitAddress => SysCall::WaitAddress(a1, a2),
+            SysCallNumber::WakeAddress => SysCall::WakeAddress(a1, a2),
+            SysCallNumber::GetQueueDepth => {
+                SysCall::GetQueueDepth(SID::from_u32(a1 as _, a2 as _, a3 as _, a4 as _))
+            }
+            SysCallNumber::GetProcessStats => SysCall::GetProcessStats(pid_from_usize(a1)?, a2),
+            SysCallNumber::GetSchedLatency => SysCall::GetSchedLatency(
+                Priority::from_usize(a1).ok_or(Error::InvalidSyscall)?,
+                a2,
+            ),
+            SysCallNumber::SetProfile => SysCall::SetProfile(a1 != 0),
+            SysCallNumber::GetProfile => SysCall::GetProfile(a1),
+            SysCallNumber::GetThreadCpuTime => {
+                SysCall::GetThreadCpuTime(PID::new(a1 as _).ok_or(Error::InvalidSyscall)?, a2)
+            }
             SysCallNumber::Invalid => SysCall::Invalid(a1, a2, a3, a4, a5, a6, a7),
         })
     }
@@ -1110,6 +1801,7 @@ impl SysCall {
             self,
             SysCall::TryConnect(_)
                 | SysCall::TryReceiveMessage(_)
+                | SysCall::GetQueueDepth(_)
                 | SysCall::ReturnToParent(_, _)
                 | SysCall::ReturnScalar2(_, _, _)
                 | SysCall::ReturnScalar1(_, _)
@@ -1402,9 +2094,9 @@ pub fn receive_message(server: SID) -> core::result::Result<MessageEnvelope, Err
 ///
 /// # Errors
 ///
+/// * **ServerNotFound**: The server does not exist, or belongs to another process
 pub fn try_receive_message(server: SID) -> core::result::Result<Option<MessageEnvelope>, Error> {
-    let result =
-        rsyscall(SysCall::TryReceiveMessage(server)).expect("Couldn't call ReceiveMessage");
+    let result = rsyscall(SysCall::TryReceiveMessage(server))?;
     if let Result::Message(envelope) = result {
         Ok(Some(envelope))
     } else if result == Result::None {
@@ -1416,6 +2108,107 @@ pub fn try_receive_message(server: SID) -> core::result::Result<Option<MessageEn
     }
 }
 
+/// Return how many messages are waiting in the queue of `server`, and how
+/// many it can hold; see `SysCall::GetQueueDepth`.
+///
+/// # Errors
+///
+/// * **ServerNotFound**: No server has the given SID
+pub fn queue_depth(server: SID) -> core::result::Result<(usize, usize), Error> {
+    rsyscall(SysCall::GetQueueDepth(server)).and_then(|result| {
+        if let Result::Scalar2(pending, capacity) = result {
+            Ok((pending, capacity))
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Get the statistics of any process; see `SysCall::GetProcessStats`.
+/// Sampled together with `cpu_time()` for each process, they show which one
+/// is busy, growing, or falling behind on its messages.
+pub fn process_stats(pid: PID) -> core::result::Result<ProcessStats, Error> {
+    let mut words = [0usize; PROCESS_STATS_WORDS];
+    for (index, word) in words.iter_mut().enumerate() {
+        *word = rsyscall(SysCall::GetProcessStats(pid, index)).and_then(|result| {
+            if let Result::Scalar1(value) = result {
+                Ok(value)
+            } else {
+                Err(Error::InternalError)
+            }
+        })?;
+    }
+    Ok(ProcessStats {
+        heap_used: words[0],
+        pages: words[1],
+        queued: words[2],
+    })
+}
+
+/// A set of servers belonging to this process that one thread receives
+/// messages from, instead of running a thread for each. Messages are taken
+/// from the servers in turn, so a busy server can't starve the others.
+pub struct ServerSet {
+    sids: [SID; MAX_WAIT_SERVERS],
+    cids: [CID; MAX_WAIT_SERVERS],
+    count: usize,
+    next: usize,
+}
+
+impl ServerSet {
+    /// Gather up to `MAX_WAIT_SERVERS` servers created by this process.
+    ///
+    /// # Errors
+    ///
+    /// * **OutOfMemory**: More servers were given than can be waited on at once
+    /// * **ServerNotFound**: One of the servers doesn't exist
+    pub fn new(servers: &[SID]) -> core::result::Result<ServerSet, Error> {
+        if servers.len() > MAX_WAIT_SERVERS {
+            return Err(Error::OutOfMemory);
+        }
+        let mut set = ServerSet {
+            sids: [SID::from_u32(0, 0, 0, 0); MAX_WAIT_SERVERS],
+            cids: [0; MAX_WAIT_SERVERS],
+            count: servers.len(),
+            next: 0,
+        };
+        for (i, &sid) in servers.iter().enumerate() {
+            set.sids[i] = sid;
+            // The kernel hands back the existing connection if there is one
+            set.cids[i] = connect(sid)?;
+        }
+        Ok(set)
+    }
+
+    /// Suspend the current thread until a message is received by any of the
+    /// servers in the set, and return it along with the server it was sent to.
+    ///
+    /// # Errors
+    ///
+    /// * **InvalidSyscall**: The set is empty
+    /// * **ServerNotFound**: One of the servers has been destroyed
+    pub fn receive_message(&mut self) -> core::result::Result<(SID, MessageEnvelope), Error> {
+        if self.count == 0 {
+            return Err(Error::InvalidSyscall);
+        }
+        loop {
+            for i in 0..self.count {
+                let idx = (self.next + i) % self.count;
+                if let Some(envelope) = try_receive_message(self.sids[idx])? {
+                    self.next = (idx + 1) % self.count;
+                    return Ok((self.sids[idx], envelope));
+                }
+            }
+            // Another thread may take the message that woke this one, in which
+            // case there's nothing to do but go around again.
+            let result = rsyscall(SysCall::WaitMessage(self.count, self.cids))?;
+            if let Result::Error(e) = result {
+                return Err(e);
+            }
+        }
+    }
+}
+
 /// Send a message to a server.  Depending on the mesage type (move or borrow), it
 /// will either block (borrow) or return immediately (move).
 /// If the message type is `borrow`, then the memory addresses pointed to will be
@@ -1693,6 +2486,380 @@ pub fn current_tid() -> core::result::Result<TID, Error> {
     })
 }
 
+/// Get the number of CPU cycles the given process has spent running, or with
+/// `None`, the number of cycles the CPU has run since reset. Sampling both
+/// twice gives each process' share of the CPU in between. Always 0 in hosted
+/// mode.
+pub fn cpu_time(pid: Option<PID>) -> core::result::Result<u64, Error> {
+    rsyscall(SysCall::GetCpuTime(pid)).and_then(|result| {
+        if let Result::Scalar2(lower, upper) = result {
+            Ok(lower as u64 | ((upper as u64) << 32))
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Get the number of CPU cycles the given thread has spent running. With
+/// `cpu_time()`, this shows which of a process' threads is keeping it busy.
+/// Always 0 in hosted mode.
+pub fn thread_cpu_time(pid: PID, tid: TID) -> core::result::Result<u64, Error> {
+    rsyscall(SysCall::GetThreadCpuTime(pid, tid)).and_then(|result| {
+        if let Result::Scalar2(lower, upper) = result {
+            Ok(lower as u64 | ((upper as u64) << 32))
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Set the scheduling priority of this process or of one of its children.
+/// Any process may lower itself to `Priority::Background`, for instance
+/// while it does maintenance, and come back to `Priority::Normal` after.
+pub fn set_priority(pid: PID, priority: Priority) -> core::result::Result<(), Error> {
+    rsyscall(SysCall::SetPriority(pid, priority)).and_then(|result| {
+        if let Result::Ok = result {
+            Ok(())
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Lend `message.buf` to a server while keeping it mapped here too, see
+/// `SysCall::ShareMemory`. Blocks until the server returns the memory, so a
+/// process usually makes this call from a thread set aside for it.
+pub fn share_memory(connection: CID, message: MemoryMessage) -> core::result::Result<Result, Error> {
+    if cfg!(not(any(target_os = "none", target_os = "xous"))) {
+        return Err(Error::UnhandledSyscall);
+    }
+    match rsyscall(SysCall::ShareMemory(connection, message)) {
+        Ok(Result::MemoryReturned(offset, valid)) => Ok(Result::MemoryReturned(offset, valid)),
+        Ok(Result::Ok) => Ok(Result::Ok),
+        Err(e) => Err(e),
+        v => panic!("Unexpected return value: {:?}", v),
+    }
+}
+
+/// Lend `segments` to a server as a single buffer, see `SysCall::LendVectored`,
+/// and block until the server returns it. This saves copying a header and a
+/// payload into one buffer when they live apart. Each range must be made up
+/// of whole pages, and no bigger than 16 MiB.
+pub fn lend_vectored(
+    connection: CID,
+    id: usize,
+    segments: &[MemoryRange],
+    mutable: bool,
+) -> core::result::Result<Result, Error> {
+    if cfg!(not(any(target_os = "none", target_os = "xous"))) {
+        return Err(Error::UnhandledSyscall);
+    }
+    if segments.is_empty() || segments.len() > MAX_LEND_SEGMENTS {
+        return Err(Error::InvalidSyscall);
+    }
+    let mut packed = [None; MAX_LEND_SEGMENTS];
+    for (slot, segment) in packed.iter_mut().zip(segments) {
+        if segment.as_ptr() as usize & 0xfff != 0
+            || segment.len() & 0xfff != 0
+            || segment.len() > MAX_LEND_SEGMENT_PAGES * 4096
+        {
+            return Err(Error::BadAlignment);
+        }
+        *slot = Some(*segment);
+    }
+    match rsyscall(SysCall::LendVectored(connection, id, mutable, packed)) {
+        Ok(Result::MemoryReturned(offset, valid)) => Ok(Result::MemoryReturned(offset, valid)),
+        Ok(Result::Ok) => Ok(Result::Ok),
+        Err(e) => Err(e),
+        v => panic!("Unexpected return value: {:?}", v),
+    }
+}
+
+/// Create a process with nothing in it but its context, for a loader to fill
+/// in with `move_memory_to_process()` and then start with `start_process()`.
+/// The new process is a child of this one.
+#[cfg(any(target_os = "none", target_os = "xous"))]
+pub fn create_empty_process() -> core::result::Result<PID, Error> {
+    let init = ProcessInit {
+        key: crate::arch::ProcessKey::new([0; 8]),
+    };
+    rsyscall(SysCall::CreateProcess(init)).and_then(|result| {
+        if let Result::ProcessID(pid) = result {
+            Ok(pid)
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Move `range` out of this process and into `pid`, a child that hasn't
+/// been started yet, where it appears at `dest`. See
+/// `SysCall::MoveMemoryToProcess`.
+pub fn move_memory_to_process(
+    pid: PID,
+    range: MemoryRange,
+    dest: MemoryAddress,
+) -> core::result::Result<(), Error> {
+    rsyscall(SysCall::MoveMemoryToProcess(pid, range, dest)).and_then(|result| {
+        if let Result::Ok = result {
+            Ok(())
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Start the first thread of `pid`, a child of this process, at
+/// `entrypoint`. It's scheduled alongside the processes started at boot,
+/// but this process keeps the right to stop it.
+pub fn start_process(pid: PID, entrypoint: usize) -> core::result::Result<(), Error> {
+    rsyscall(SysCall::StartProcess(pid, entrypoint)).and_then(|result| {
+        if let Result::Ok = result {
+            Ok(())
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Stop `pid`, a child of this process, and free everything it had.
+pub fn destroy_process(pid: PID) -> core::result::Result<(), Error> {
+    rsyscall(SysCall::DestroyProcess(pid)).and_then(|result| {
+        if let Result::Ok = result {
+            Ok(())
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Copy the name `pid` was given when the image was built into `name`, and
+/// return its length, which is 0 if the process has no name. A name longer
+/// than `name` is cut short, but the full length is still returned.
+pub fn process_name(pid: PID, name: &mut [u8]) -> core::result::Result<usize, Error> {
+    let mut len = 0;
+    let mut index = 0;
+    loop {
+        let (name_len, word) = rsyscall(SysCall::GetProcessName(pid, index)).and_then(|result| {
+            if let Result::Scalar2(name_len, word) = result {
+                Ok((name_len, word))
+            } else {
+                Err(Error::InternalError)
+            }
+        })?;
+        len = name_len;
+        for (i, b) in (word as u32).to_le_bytes().iter().enumerate() {
+            if let Some(dest) = name.get_mut(index * 4 + i) {
+                *dest = *b;
+            }
+        }
+        index += 1;
+        if index * 4 >= len.min(name.len()) {
+            return Ok(len);
+        }
+    }
+}
+
+/// Turn the kernel's IPC trace on or off; see `SysCall::SetIpcTrace`.
+pub fn set_ipc_trace(enable: bool) -> core::result::Result<(), Error> {
+    rsyscall(SysCall::SetIpcTrace(enable)).and_then(|result| {
+        if let Result::Ok = result {
+            Ok(())
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Turn the IPC trace off and hand each event it kept to `f`, oldest first.
+/// Returns the number of events recorded since the trace was turned on,
+/// which is more than were kept if the trace wrapped around.
+pub fn read_ipc_trace<F: FnMut(IpcTraceEvent)>(mut f: F) -> core::result::Result<usize, Error> {
+    set_ipc_trace(false)?;
+    let read = |index| {
+        rsyscall(SysCall::GetIpcTrace(index)).and_then(|result| {
+            if let Result::Scalar2(count, word) = result {
+                Ok((count, word))
+            } else {
+                Err(Error::InternalError)
+            }
+        })
+    };
+    let (count, _) = read(0)?;
+    for event in 0..count.min(IPC_TRACE_EVENTS) {
+        let mut words = [0usize; IPC_TRACE_EVENT_WORDS];
+        for (i, word) in words.iter_mut().enumerate() {
+            *word = read(event * IPC_TRACE_EVENT_WORDS + i)?.1;
+        }
+        f(IpcTraceEvent::from_words(&words));
+    }
+    Ok(count)
+}
+
+/// Have the kernel send a scalar message with id `opcode` to `notify`, one of
+/// the caller's own servers, once the server behind `connection` goes away.
+/// The connection is passed in `arg1`. A client that gets it can look the
+/// server up again and re-register, rather than blocking on a dead server.
+pub fn notify_server_exit(
+    connection: CID,
+    notify: SID,
+    opcode: usize,
+) -> core::result::Result<(), Error> {
+    rsyscall(SysCall::NotifyServerExit(connection, notify, opcode)).and_then(|result| {
+        if let Result::Ok = result {
+            Ok(())
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Put the calling thread to sleep until another thread of this process calls
+/// `wake_address()` on `word`, or return straight away if `word` no longer
+/// holds `expected`. This is the building block for locks and condition
+/// variables that don't spin: a thread that finds a lock taken sleeps on the
+/// lock word, and the thread that releases it wakes one sleeper. A thread can
+/// come back without having been woken, so callers check `word` again.
+pub fn wait_address(
+    word: &core::sync::atomic::AtomicUsize,
+    expected: usize,
+) -> core::result::Result<(), Error> {
+    let address = word as *const _ as usize;
+    rsyscall(SysCall::WaitAddress(address, expected)).and_then(|result| {
+        if let Result::Ok = result {
+            Ok(())
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Wake up to `count` threads sleeping in `wait_address()` on `word`, and
+/// return how many were woken. Pass `usize::MAX` to wake them all.
+pub fn wake_address(
+    word: &core::sync::atomic::AtomicUsize,
+    count: usize,
+) -> core::result::Result<usize, Error> {
+    let address = word as *const _ as usize;
+    rsyscall(SysCall::WakeAddress(address, count)).and_then(|result| {
+        if let Result::Scalar1(woken) = result {
+            Ok(woken)
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Get the scheduler latency histogram of `priority`, bucket by bucket; see
+/// `SYSCALL::GetSchedLatency`. The counts run from boot, so the histogram
+/// from a run of a known workload can be compared with the same run on
+/// another release.
+pub fn sched_latency(
+    priority: Priority,
+) -> core::result::Result<[usize; SCHED_LATENCY_BUCKETS], Error> {
+    let mut buckets = [0usize; SCHED_LATENCY_BUCKETS];
+    for (bucket, count) in buckets.iter_mut().enumerate() {
+        *count = rsyscall(SysCall::GetSchedLatency(priority, bucket)).and_then(|result| {
+            if let Result::Scalar1(value) = result {
+                Ok(value)
+            } else {
+                Err(Error::InternalError)
+            }
+        })?;
+    }
+    Ok(buckets)
+}
+
+/// Turn the kernel's sampling profiler on or off; see `SysCall::SetProfile`.
+pub fn set_profile(enable: bool) -> core::result::Result<(), Error> {
+    rsyscall(SysCall::SetProfile(enable)).and_then(|result| {
+        if let Result::Ok = result {
+            Ok(())
+        } else {
+            Err(Error::InternalError)
+        }
+    })
+}
+
+/// Turn the profiler off and hand each sample it kept to `f`, oldest first.
+/// Returns the number of samples taken since the profiler was turned on,
+/// which is more than were kept if the profile wrapped around.
+pub fn read_profile<F: FnMut(ProfileSample)>(mut f: F) -> core::result::Result<usize, Error> {
+    set_profile(false)?;
+    let read = |index| {
+        rsyscall(SysCall::GetProfile(index)).and_then(|result| {
+            if let Result::Scalar2(count, word) = result {
+                Ok((count, word))
+            } else {
+                Err(Error::InternalError)
+            }
+        })
+    };
+    let (count, _) = read(0)?;
+    for sample in 0..count.min(PROFILE_SAMPLES) {
+        let mut words = [0usize; PROFILE_SAMPLE_WORDS];
+        for (i, word) in words.iter_mut().enumerate() {
+            *word = read(sample * PROFILE_SAMPLE_WORDS + i)?.1;
+        }
+        f(ProfileSample::from_words(&words));
+    }
+    Ok(count)
+}
+
+/// Get the memory statistics of the calling process. Sampling them now and
+/// then from a long-running service shows whether its memory keeps growing,
+/// without having to instrument its allocations.
+pub fn memory_stats() -> core::result::Result<MemoryStats, Error> {
+    let mut words = [0usize; MEMORY_STATS_WORDS];
+    for (index, word) in words.iter_mut().enumerate() {
+        *word = rsyscall(SysCall::GetMemoryStats(index)).and_then(|result| {
+            if let Result::Scalar1(value) = result {
+                Ok(value)
+            } else {
+                Err(Error::InternalError)
+            }
+        })?;
+    }
+    Ok(MemoryStats {
+        heap_used: words[0],
+        heap_limit: words[1],
+        pages: words[2],
+        peak_pages: words[3],
+    })
+}
+
+/// Get the record of the last fault that took down a process, along with
+/// the number of faults recorded since boot, or `None` if there hasn't been
+/// one. Only one process may read crash records: the first that asks.
+pub fn crash_record() -> core::result::Result<Option<(usize, CrashRecord)>, Error> {
+    let read = |index| {
+        rsyscall(SysCall::GetCrashRecord(index)).and_then(|result| {
+            if let Result::Scalar2(sequence, word) = result {
+                Ok((sequence, word))
+            } else {
+                Err(Error::InternalError)
+            }
+        })
+    };
+    'again: loop {
+        let mut words = [0usize; CRASH_RECORD_WORDS];
+        let (sequence, first) = read(0)?;
+        if sequence == 0 {
+            return Ok(None);
+        }
+        words[0] = first;
+        for (index, word) in words.iter_mut().enumerate().skip(1) {
+            let (now, value) = read(index)?;
+            // Another process faulted while this one was being read
+            if now != sequence {
+                continue 'again;
+            }
+            *word = value;
+        }
+        return Ok(Some((sequence, CrashRecord::from_words(&words))));
+    }
+}
+
 pub fn destroy_server(sid: SID) -> core::result::Result<(), Error> {
     rsyscall(SysCall::DestroyServer(sid)).and_then(|result| {
         if let Result::Ok = result {
@@ -0,0 +1,423 @@
+//! Sends and receives that give up after a given number of milliseconds,
+//! returning `Error::Timeout`, so a client of a server that has stopped
+//! answering can recover instead of blocking forever.
+//!
+//! The kernel has no clock of its own, so the time is kept by the ticktimer
+//! server, and whoever is waiting sleeps on a ticktimer condition that is
+//! notified the moment there's something to do. The waiting is shared out to
+//! a small pool of worker threads, each with a private server to take jobs
+//! on, which are kept around for the next call rather than started afresh.
+//!
+//! A blocking send with a timeout is made by a worker, which the caller stops
+//! waiting for once the time is up. The worker lends the server its own copy
+//! of any memory, so the caller's buffer is never left with a server that may
+//! still be holding on to it. A receive with a timeout blocks as usual, and a
+//! worker ends it once the time is up by sending the server a
+//! `TIMEOUT_WAKEUP_ID` message, which the receive takes out again.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{
+    connect, create_server, destroy_server, map_memory, receive_message, send_message,
+    unmap_memory, Error, MemoryMessage, MemoryRange, Message, MessageEnvelope, Result, CID, SID,
+};
+
+/// The message a worker sends to end a `receive_message_timeout()` whose time
+/// is up, with the receive's serial number in *arg1*. The receive takes it
+/// out of the queue, but if a real message came in just as the time ran out,
+/// a late one is left behind for the next receive on that server. Servers
+/// that also call `receive_message()` themselves should ignore it.
+pub const TIMEOUT_WAKEUP_ID: usize = usize::MAX - 0x7103;
+
+/// Opcodes of the ticktimer server
+const TICKTIMER_ELAPSED_MS: usize = 0;
+const TICKTIMER_WAIT_FOR_CONDITION: usize = 8;
+const TICKTIMER_NOTIFY_CONDITION: usize = 9;
+
+/// Jobs for a worker, with the address of the job's page in *arg1*
+const WORK_SEND: usize = 0;
+const WORK_WAKEUP: usize = 1;
+
+/// How many idle workers are kept for later calls
+const WORKER_POOL_LEN: usize = 4;
+
+/// Connections to the idle workers, each as CID + 1, or 0 for an empty slot
+static IDLE_WORKERS: [AtomicUsize; WORKER_POOL_LEN] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+/// Tells a late wakeup apart from the one for the receive in progress
+static NEXT_SERIAL: AtomicUsize = AtomicUsize::new(1);
+
+/// The job is still in progress
+const JOB_PENDING: usize = 0;
+/// The send or receive was over before the time was up
+const JOB_FINISHED: usize = 1;
+/// The time ran out first
+const JOB_TIMED_OUT: usize = 2;
+
+/// Size of the page a job is kept in
+const CALL_PAGE_LEN: usize = 4096;
+
+fn ticktimer() -> core::result::Result<CID, Error> {
+    // The kernel hands back the existing connection if there is one
+    connect(SID::from_bytes(b"ticktimer-server").unwrap())
+}
+
+fn elapsed_ms(ticktimer: CID) -> core::result::Result<u64, Error> {
+    match send_message(
+        ticktimer,
+        Message::new_blocking_scalar(TICKTIMER_ELAPSED_MS, 0, 0, 0, 0),
+    )? {
+        Result::Scalar2(lower, upper) => Ok(lower as u64 | ((upper as u64) << 32)),
+        _ => Err(Error::InternalError),
+    }
+}
+
+/// Sleep until the job at `page` is notified, or until `ms` have passed.
+/// The ticktimer may wake us early, so callers check the clock again.
+fn wait_on(ticktimer: CID, page: usize, ms: u64) {
+    // A timeout of 0 would wait forever
+    let ms = ms.clamp(1, usize::MAX as u64) as usize;
+    send_message(
+        ticktimer,
+        Message::new_blocking_scalar(TICKTIMER_WAIT_FOR_CONDITION, page, ms, 0, 0),
+    )
+    .ok();
+}
+
+fn notify(ticktimer: CID, page: usize) {
+    send_message(
+        ticktimer,
+        Message::new_scalar(TICKTIMER_NOTIFY_CONDITION, page, 1, 0, 0),
+    )
+    .ok();
+}
+
+/// Hand the job at `page` to an idle worker, starting one if there is none.
+fn dispatch(job: usize, page: usize) -> core::result::Result<(), Error> {
+    while let Some(slot) = IDLE_WORKERS
+        .iter()
+        .map(|slot| slot.swap(0, Ordering::SeqCst))
+        .find(|&slot| slot != 0)
+    {
+        let worker = slot - 1;
+        // A worker that has gone away is forgotten, and the next one tried
+        if send_message(worker, Message::new_scalar(job, page, worker, 0, 0)).is_ok() {
+            return Ok(());
+        }
+    }
+    let worker = start_worker()?;
+    send_message(worker, Message::new_scalar(job, page, worker, 0, 0)).map(|_| ())
+}
+
+fn start_worker() -> core::result::Result<CID, Error> {
+    let sid = create_server()?;
+    let cid = match connect(sid) {
+        Ok(cid) => cid,
+        Err(e) => {
+            destroy_server(sid).ok();
+            return Err(e);
+        }
+    };
+    let (s0, s1, s2, s3) = sid.to_u32();
+    if let Err(e) = crate::create_thread_4(
+        run_worker,
+        s0 as usize,
+        s1 as usize,
+        s2 as usize,
+        s3 as usize,
+    ) {
+        destroy_server(sid).ok();
+        return Err(e);
+    }
+    Ok(cid)
+}
+
+fn run_worker(s0: usize, s1: usize, s2: usize, s3: usize) {
+    let sid = SID::from_u32(s0 as u32, s1 as u32, s2 as u32, s3 as u32);
+    while let Ok(envelope) = receive_message(sid) {
+        let (job, page, cid) = match envelope.body.scalar_message() {
+            Some(scalar) => (scalar.id, scalar.arg1, scalar.arg2),
+            None => continue,
+        };
+        match job {
+            WORK_SEND => run_call(page),
+            WORK_WAKEUP => run_wakeup(page),
+            _ => (),
+        }
+        // Wait for the next job, unless enough workers are waiting already
+        if !IDLE_WORKERS.iter().any(|slot| {
+            slot.compare_exchange(0, cid + 1, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+        }) {
+            break;
+        }
+    }
+    destroy_server(sid).ok();
+}
+
+/// What a timed receive shares with the worker keeping time for it. It sits
+/// at the start of a page of its own. Whichever of the two loses the race to
+/// move it on from `JOB_PENDING` frees it, except that a receive woken by its
+/// own wakeup frees it on the worker's behalf.
+struct Wakeup {
+    state: AtomicUsize,
+    server: SID,
+    serial: usize,
+    /// The ticktimer time at which the receive gives up
+    at: u64,
+}
+
+fn run_wakeup(page: usize) {
+    let wakeup = unsafe { &*(page as *const Wakeup) };
+    let (server, serial) = (wakeup.server, wakeup.serial);
+    if let Ok(ticktimer) = ticktimer() {
+        while wakeup.state.load(Ordering::SeqCst) == JOB_PENDING {
+            let now = elapsed_ms(ticktimer).unwrap_or(wakeup.at);
+            if now >= wakeup.at {
+                break;
+            }
+            wait_on(ticktimer, page, wakeup.at - now);
+        }
+    }
+    if wakeup
+        .state
+        .compare_exchange(JOB_PENDING, JOB_TIMED_OUT, Ordering::SeqCst, Ordering::SeqCst)
+        .is_ok()
+    {
+        // From here on the page belongs to the receive
+        if let Ok(cid) = connect(server) {
+            send_message(cid, Message::new_scalar(TIMEOUT_WAKEUP_ID, serial, 0, 0, 0)).ok();
+        }
+    } else {
+        free_page(page);
+    }
+}
+
+fn free_page(page: usize) {
+    unmap_memory(unsafe { MemoryRange::new(page, CALL_PAGE_LEN).unwrap() }).ok();
+}
+
+/// Suspend the current thread until a message is received, or until
+/// `timeout_ms` milliseconds have passed. A timeout of 0 waits forever.
+///
+/// # Errors
+///
+/// * **ServerNotFound**: The server does not exist, or belongs to another process
+/// * **Timeout**: No message arrived in time
+/// * **ThreadNotAvailable**: There was no thread left to keep time on
+pub fn receive_message_timeout(
+    server: SID,
+    timeout_ms: usize,
+) -> core::result::Result<MessageEnvelope, Error> {
+    if timeout_ms == 0 {
+        return receive_message(server);
+    }
+    let ticktimer = ticktimer()?;
+    let at = elapsed_ms(ticktimer)? + timeout_ms as u64;
+    let serial = NEXT_SERIAL.fetch_add(1, Ordering::SeqCst);
+    let page = map_page(CALL_PAGE_LEN)?;
+    let addr = page.as_mut_ptr() as usize;
+    unsafe {
+        core::ptr::write(
+            addr as *mut Wakeup,
+            Wakeup {
+                state: AtomicUsize::new(JOB_PENDING),
+                server,
+                serial,
+                at,
+            },
+        )
+    };
+    if let Err(e) = dispatch(WORK_WAKEUP, addr) {
+        unmap_memory(page).ok();
+        return Err(e);
+    }
+
+    let wakeup = unsafe { &*(addr as *const Wakeup) };
+    loop {
+        let result = receive_message(server);
+        if let Ok(envelope) = &result {
+            if let Some(scalar) = envelope.body.scalar_message() {
+                if scalar.id == TIMEOUT_WAKEUP_ID {
+                    if scalar.arg1 == serial {
+                        free_page(addr);
+                        return Err(Error::Timeout);
+                    }
+                    // Left behind by an earlier receive
+                    continue;
+                }
+            }
+        }
+        if wakeup
+            .state
+            .compare_exchange(JOB_PENDING, JOB_FINISHED, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            notify(ticktimer, addr);
+        } else {
+            // The wakeup is on its way, and will be left for the next receive
+            free_page(addr);
+        }
+        return result;
+    }
+}
+
+/// What a timed call shares between the caller and the worker making it. It
+/// sits at the start of a page of its own, which is freed by whichever of the
+/// two is last to be done with it.
+struct Call {
+    state: AtomicUsize,
+    connection: CID,
+    message: Option<Message>,
+    result: Option<core::result::Result<Result, Error>>,
+    /// The worker's copy of the memory being lent, if any
+    copy: Option<MemoryRange>,
+}
+
+fn run_call(page: usize) {
+    let call = unsafe { &mut *(page as *mut Call) };
+    let message = call.message.take().expect("timed call had no message");
+    let result = send_message(call.connection, message);
+    if call.state.load(Ordering::SeqCst) == JOB_PENDING {
+        call.result = Some(result);
+    }
+    if call
+        .state
+        .compare_exchange(JOB_PENDING, JOB_FINISHED, Ordering::SeqCst, Ordering::SeqCst)
+        .is_ok()
+    {
+        if let Ok(ticktimer) = ticktimer() {
+            notify(ticktimer, page);
+        }
+    } else {
+        // Nobody is waiting for the result any more
+        free_call(page);
+    }
+}
+
+fn free_call(page: usize) {
+    let call = unsafe { &mut *(page as *mut Call) };
+    if let Some(copy) = call.copy.take() {
+        unmap_memory(copy).ok();
+    }
+    unsafe { core::ptr::drop_in_place(call) };
+    free_page(page);
+}
+
+fn map_page(len: usize) -> core::result::Result<MemoryRange, Error> {
+    #[cfg(feature = "bit-flags")]
+    let flags = crate::MemoryFlags::R | crate::MemoryFlags::W;
+    #[cfg(not(feature = "bit-flags"))]
+    let flags = 0b0000_0010 | 0b0000_0100;
+    map_memory(None, None, len, flags)
+}
+
+/// Send a message to a server, like `send_message()`, but give up if the
+/// server hasn't answered within `timeout_ms` milliseconds. A timeout of 0
+/// waits forever.
+///
+/// Memory that is lent is first copied, and the copy is what the server
+/// sees; anything it writes to a mutable lend is copied back once it
+/// answers. A server that answers too late finds nobody waiting, and its
+/// answer is thrown away.
+///
+/// # Errors
+///
+/// * **ServerNotFound**: The server does not exist so the connection is now invalid
+/// * **BadAddress**: The client tried to pass a Memory message using an address it doesn't own
+/// * **ServerQueueFull**: The queue in the server is full
+/// * **Timeout**: The server didn't answer in time
+/// * **ThreadNotAvailable**: There was no thread left to make the call on
+pub fn send_message_timeout(
+    connection: CID,
+    message: Message,
+    timeout_ms: usize,
+) -> core::result::Result<Result, Error> {
+    if timeout_ms == 0 || !message.is_blocking() {
+        return send_message(connection, message);
+    }
+    let ticktimer = ticktimer()?;
+    let at = elapsed_ms(ticktimer)? + timeout_ms as u64;
+
+    // Lent memory is swapped for a copy the worker owns
+    let mutable = matches!(message, Message::MutableBorrow(_));
+    let (message, lent) = match message {
+        Message::Borrow(msg) | Message::MutableBorrow(msg) => {
+            let copy = map_page(msg.buf.len())?;
+            unsafe {
+                core::ptr::copy_nonoverlapping(msg.buf.as_ptr(), copy.as_mut_ptr(), msg.buf.len())
+            };
+            let lend = MemoryMessage {
+                id: msg.id,
+                buf: copy,
+                offset: msg.offset,
+                valid: msg.valid,
+            };
+            if mutable {
+                (Message::MutableBorrow(lend), Some((msg.buf, copy)))
+            } else {
+                (Message::Borrow(lend), Some((msg.buf, copy)))
+            }
+        }
+        other => (other, None),
+    };
+
+    let page = match map_page(CALL_PAGE_LEN) {
+        Ok(page) => page,
+        Err(e) => {
+            if let Some((_, copy)) = lent {
+                unmap_memory(copy).ok();
+            }
+            return Err(e);
+        }
+    };
+    let addr = page.as_mut_ptr() as usize;
+    unsafe {
+        core::ptr::write(
+            addr as *mut Call,
+            Call {
+                state: AtomicUsize::new(JOB_PENDING),
+                connection,
+                message: Some(message),
+                result: None,
+                copy: lent.map(|(_, copy)| copy),
+            },
+        )
+    };
+    if let Err(e) = dispatch(WORK_SEND, addr) {
+        free_call(addr);
+        return Err(e);
+    }
+
+    let call = unsafe { &mut *(addr as *mut Call) };
+    while call.state.load(Ordering::SeqCst) != JOB_FINISHED {
+        // If the clock can't be read, give up rather than wait forever
+        let now = elapsed_ms(ticktimer).unwrap_or(at);
+        if now >= at {
+            // The worker may have finished while we were looking at the clock
+            if call
+                .state
+                .compare_exchange(JOB_PENDING, JOB_TIMED_OUT, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+            {
+                return Err(Error::Timeout);
+            }
+            break;
+        }
+        wait_on(ticktimer, addr, at - now);
+    }
+
+    let result = call.result.take().unwrap_or(Err(Error::InternalError));
+    if let (Some((buf, copy)), true) = (lent, mutable) {
+        if result.is_ok() {
+            unsafe { core::ptr::copy_nonoverlapping(copy.as_ptr(), buf.as_mut_ptr(), buf.len()) };
+        }
+    }
+    free_call(addr);
+    result
+}
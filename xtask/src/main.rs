@@ -47,6 +47,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         "shellchat",
         "ime-frontend",
         "ime-plugin-shell",
+        "ime-plugin-dict",
         "graphics-server",
         "ticktimer-server",
         "log-server",
@@ -66,6 +67,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         "dns",
         "pddb",
         "modals",
+        "watchdog",
+        "app-loader",
     ];
     let app_pkgs = [
         // "standard" demo apps
@@ -386,6 +389,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             generate_app_menus(&apps);
             run(false, &pkgs, None, false)?
         }
+        Some("hosted-pair") => {
+            let mut args = env::args();
+            args.nth(1);
+            let mut pkgs = hw_pkgs.to_vec();
+            let apps: Vec<String> = args.collect();
+            for app in &apps {
+                pkgs.push(app);
+            }
+            generate_app_menus(&apps);
+            run(false, &pkgs, None, true)?;
+            // each instance is told which it is, which puts it on the virtual segment at 127.0.100.<instance>
+            let mut instances = vec![];
+            for instance in 1..=2 {
+                println!("Starting instance {} at 127.0.100.{}...", instance, instance);
+                instances.push(kernel_command(false, &pkgs)?.env("XOUS_NET_INSTANCE", instance.to_string()).spawn()?);
+            }
+            for mut instance in instances {
+                instance.wait()?;
+            }
+        }
         Some("hosted-ci") => {
             let mut pkgs = hw_pkgs.to_vec();
             let mut apps: Vec<String> = args.collect();
@@ -397,6 +420,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             generate_app_menus(&apps);
             run(false, &pkgs, None, true)?
         }
+        Some("hosted-golden") => {
+            let mut pkgs = hw_pkgs.to_vec();
+            pkgs.push("ui-golden");
+            generate_app_menus(&Vec::<String>::new());
+            // on the virtual clock, so animations and timeouts land the same way on every run
+            run(false, &pkgs, Some(&["--features", "graphics-server/headless", "--features", "ui-golden/virtual-clock"]), false)?;
+            // the harness shuts the system down when it's done, leaving an `.actual.pbm` for every failure
+            let mut failed = Vec::new();
+            for entry in std::fs::read_dir(project_root().join("tools").join("golden-images"))? {
+                let name = entry?.file_name().to_string_lossy().to_string();
+                if let Some(scenario) = name.strip_suffix(".actual.pbm") {
+                    failed.push(scenario.to_string());
+                }
+            }
+            if !failed.is_empty() {
+                return Err(format!("golden images differ: {}", failed.join(", ")).into());
+            }
+        }
         Some("debug") => {
             let mut args = env::args();
             args.nth(1);
@@ -576,6 +617,10 @@ Hardware images:
 
 Hosted emulation:
  run [app1] [..]         runs a release build using a hosted environment plus specified apps
+ hosted-pair [app1] [..] runs two hosted instances side by side, at 127.0.100.1 and 127.0.100.2 on a
+                         virtual network segment, so they can reach each other
+ hosted-golden           checks the UI against the reference images in tools/golden-images, without a window.
+                         Set XOUS_GOLDEN_UPDATE=1 to replace the references instead.
 
 Renode emulation:
  renode-image            builds a functional image for renode
@@ -965,11 +1010,23 @@ fn run(
     features: Option<&[&str]>,
     dry_run: bool,
 ) -> Result<(), DynError> {
-    let stream = if debug { "debug" } else { "release" };
-
     build(init, debug, None, None, None, features)?;
 
     // Build and run the kernel
+    if !dry_run {
+        println!("Building and running kernel...");
+        let status = kernel_command(debug, init)?.status()?;
+        if !status.success() {
+            return Err("cargo build failed".into());
+        }
+    }
+
+    Ok(())
+}
+
+/// The command that builds and runs the hosted kernel, which starts the `init` programs built by `run()`
+fn kernel_command(debug: bool, init: &[&str]) -> Result<Command, DynError> {
+    let stream = if debug { "debug" } else { "release" };
     let mut args = vec!["run"];
     if !debug {
         args.push("--release");
@@ -995,18 +1052,9 @@ fn run(
     let mut dir = project_root();
     dir.push("kernel");
 
-    if !dry_run {
-        println!("Building and running kernel...");
-        let status = Command::new(cargo())
-            .current_dir(dir)
-            .args(&args)
-            .status()?;
-        if !status.success() {
-            return Err("cargo build failed".into());
-        }
-    }
-
-    Ok(())
+    let mut command = Command::new(cargo());
+    command.current_dir(dir).args(&args);
+    Ok(command)
 }
 
 fn build_kernel(debug: bool) -> Result<PathBuf, DynError> {
@@ -1521,6 +1569,9 @@ use std::string::String;
 struct AppManifest {
     context_name: String,
     menu_name: HashMap<String, HashMap<String, String>>,
+    /// launcher icon: a raw, 32x32 pixel, 1 bit per pixel bitmap, relative to the repo root
+    #[serde(default)]
+    icon: Option<String>,
 }
 #[derive(Deserialize, Serialize, Debug)]
 struct Locales {
@@ -1604,7 +1655,7 @@ fn generate_app_menus(apps: &Vec<String>) {
         writeln!(menu, "#![allow(unused_variables)]").unwrap();
     }
     writeln!(menu, r####"use crate::StatusOpcode;
-use gam::{{MenuItem, MenuPayload}};
+use gam::LauncherEntry;
 use locales::t;
 use num_traits::*;
 use std::{{error::Error, fmt}};
@@ -1664,12 +1715,12 @@ pub(crate) fn app_index_to_name(index: usize) -> Result<&'static str, AppDispatc
     }}
 }}
 
-pub(crate) fn app_menu_items(menu_items: &mut Vec::<MenuItem>, status_conn: u32) {{
+pub(crate) fn app_launcher_entries(entries: &mut Vec::<LauncherEntry>, status_conn: u32) {{
 "####
     )
     .unwrap();
     for (index, (_app_name, manifest)) in working_set.iter().enumerate() {
-        writeln!(menu, "    menu_items.push(MenuItem {{",).unwrap();
+        writeln!(menu, "    entries.push(LauncherEntry {{",).unwrap();
         assert!(
             manifest.menu_name.len() == 1,
             "Improper menu name record entry"
@@ -1682,7 +1733,12 @@ pub(crate) fn app_menu_items(menu_items: &mut Vec::<MenuItem>, status_conn: u32)
             )
             .unwrap();
         }
-        writeln!(menu, "        action_conn: Some(status_conn),",).unwrap();
+        match &manifest.icon {
+            // app_autogen.rs lives three levels below the repo root
+            Some(icon) => writeln!(menu, "        icon: Some(*include_bytes!(\"../../../{}\")),", icon).unwrap(),
+            None => writeln!(menu, "        icon: None,").unwrap(),
+        }
+        writeln!(menu, "        action_conn: status_conn,",).unwrap();
         writeln!(
             menu,
             "        action_opcode: StatusOpcode::SwitchToApp.to_u32().unwrap(),",
@@ -1690,11 +1746,10 @@ pub(crate) fn app_menu_items(menu_items: &mut Vec::<MenuItem>, status_conn: u32)
         .unwrap();
         writeln!(
             menu,
-            "        action_payload: MenuPayload::Scalar([{}, 0, 0, 0]),",
+            "        action_payload: [{}, 0, 0, 0],",
             index
         )
         .unwrap();
-        writeln!(menu, "        close_on_select: true,",).unwrap();
         writeln!(menu, "    }});\n",).unwrap();
     }
     writeln!(menu, "}}").unwrap();